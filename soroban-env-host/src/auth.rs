@@ -20,6 +20,7 @@ use crate::native_contract::account_contract::{
     check_account_authentication, check_account_contract_auth,
 };
 use crate::native_contract::invoker_contract_auth::invoker_contract_auth_to_authorized_invocation;
+use crate::storage::FootprintMode;
 use crate::{Host, HostError};
 
 use super::xdr;
@@ -113,6 +114,19 @@ pub struct RecordedAuthPayload {
     pub invocation: xdr::SorobanAuthorizedInvocation,
 }
 
+// Exemptions from the `disable_non_root_auth` setting of the recording
+// authorization mode. An authorization requirement that is not tied to the
+// root contract invocation is handled as if the setting was flipped when
+// either the address being authorized is in `addresses`, or the requirement
+// comes from an invocation of a contract in `contracts`. This allows e.g. to
+// require the authorizations within a specific contract to be tied to the
+// root invocation while allowing non-root authorization everywhere else.
+#[derive(Clone, Debug, Default)]
+pub struct NonRootAuthExemptions {
+    pub addresses: Vec<ScAddress>,
+    pub contracts: Vec<ScAddress>,
+}
+
 // Snapshot of `AuthorizationManager` to use when performing the callstack
 // rollbacks.
 pub struct AuthorizationManagerSnapshot {
@@ -153,9 +167,34 @@ struct RecordingAuthInfo {
     // Whether to allow root authorized invocation to not match the root
     // contract invocation.
     disable_non_root_auth: bool,
+    // Addresses and contracts for which `disable_non_root_auth` is flipped.
+    non_root_auth_exemptions: NonRootAuthExemptions,
 }
 
 impl RecordingAuthInfo {
+    // Returns whether authorization of `address` that is not tied to the root
+    // invocation is disallowed in the current call stack, taking the
+    // exemptions into account.
+    fn non_root_auth_disabled(
+        &self,
+        host: &Host,
+        address: AddressObject,
+        call_stack: &[AuthStackFrame],
+    ) -> Result<bool, HostError> {
+        let exemptions = &self.non_root_auth_exemptions;
+        let mut exempt = host.visit_obj(address, |a: &ScAddress| {
+            Ok(exemptions.addresses.contains(a))
+        })?;
+        if !exempt {
+            if let Some(AuthStackFrame::Contract(invocation)) = call_stack.last() {
+                exempt = host.visit_obj(invocation.contract_address, |a: &ScAddress| {
+                    Ok(exemptions.contracts.contains(a))
+                })?;
+            }
+        }
+        Ok(self.disable_non_root_auth != exempt)
+    }
+
     fn try_borrow_tracker_by_address_handle(
         &self,
         host: &Host,
@@ -258,7 +297,8 @@ pub(crate) struct ContractInvocation {
 pub(crate) struct ContractFunction {
     pub(crate) contract_address: AddressObject,
     pub(crate) function_name: Symbol,
-    pub(crate) args: Vec<Val>,
+    // Shared by the functions required by `require_auth_many`.
+    pub(crate) args: Rc<Vec<Val>>,
 }
 
 #[derive(Clone)]
@@ -339,7 +379,7 @@ impl AuthStackFrame {
     fn to_authorized_function(
         &self,
         host: &Host,
-        args: Vec<Val>,
+        args: Rc<Vec<Val>>,
     ) -> Result<AuthorizedFunction, HostError> {
         match self {
             AuthStackFrame::Contract(contract_frame) => {
@@ -364,7 +404,10 @@ impl AuthorizedFunction {
                 AuthorizedFunction::ContractFn(ContractFunction {
                     contract_address: host.add_host_object(xdr_contract_fn.contract_address)?,
                     function_name: Symbol::try_from_val(host, &xdr_contract_fn.function_name)?,
-                    args: host.scvals_to_rawvals(xdr_contract_fn.args.as_slice())?,
+                    args: Rc::metered_new(
+                        host.scvals_to_rawvals(xdr_contract_fn.args.as_slice())?,
+                        host,
+                    )?,
                 })
             }
             SorobanAuthorizedFunction::CreateContractHostFn(xdr_args) => {
@@ -601,10 +644,21 @@ impl AuthorizationManager {
     // retrieved using `get_recorded_auth_payloads`.
     // metering: free
     pub(crate) fn new_recording(disable_non_root_auth: bool) -> Self {
+        Self::new_recording_with_exemptions(disable_non_root_auth, Default::default())
+    }
+
+    // Creates a new recording `AuthorizationManager` that flips
+    // `disable_non_root_auth` for the provided exemptions.
+    // metering: free
+    pub(crate) fn new_recording_with_exemptions(
+        disable_non_root_auth: bool,
+        non_root_auth_exemptions: NonRootAuthExemptions,
+    ) -> Self {
         Self {
             mode: AuthorizationMode::Recording(RecordingAuthInfo {
                 tracker_by_address_handle: Default::default(),
                 disable_non_root_auth,
+                non_root_auth_exemptions,
             }),
             call_stack: RefCell::new(vec![]),
             account_trackers: RefCell::new(vec![]),
@@ -623,6 +677,16 @@ impl AuthorizationManager {
         host: &Host,
         address: AddressObject,
         args: Vec<Val>,
+    ) -> Result<(), HostError> {
+        self.require_auth_with_shared_args(host, address, Rc::metered_new(args, host)?)
+    }
+
+    // metering: covered
+    fn require_auth_with_shared_args(
+        &self,
+        host: &Host,
+        address: AddressObject,
+        args: Rc<Vec<Val>>,
     ) -> Result<(), HostError> {
         let _span = tracy_span!("require auth");
         let authorized_function = self
@@ -641,6 +705,31 @@ impl AuthorizationManager {
         self.require_auth_internal(host, address, authorized_function)
     }
 
+    // Requires authorization of the current invocation with `args` from every
+    // address in `addresses`, in order. The error of the first address that
+    // hasn't authorized the invocation is reported together with its index.
+    // metering: covered by components, the arguments are shared by all the
+    // addresses rather than cloned.
+    pub(crate) fn require_auth_many(
+        &self,
+        host: &Host,
+        addresses: &[AddressObject],
+        args: Vec<Val>,
+    ) -> Result<(), HostError> {
+        let args = Rc::metered_new(args, host)?;
+        for (i, address) in addresses.iter().enumerate() {
+            self.require_auth_with_shared_args(host, *address, Rc::clone(&args))
+                .map_err(|e| {
+                    host.error(
+                        e.error,
+                        "authorization failed for address in batch",
+                        &[Val::from_u32(i as u32).into(), address.to_val()],
+                    )
+                })?;
+        }
+        Ok(())
+    }
+
     // metering: covered
     pub(crate) fn add_invoker_contract_auth(
         &self,
@@ -856,8 +945,9 @@ impl AuthorizationManager {
                 // tracker.
                 // Alert the user in `disable_non_root_auth` mode if we're not
                 // in the root stack frame.
-                if recording_info.disable_non_root_auth
-                    && self.try_borrow_call_stack(host)?.len() != 1
+                let call_stack = self.try_borrow_call_stack(host)?;
+                if call_stack.len() != 1
+                    && recording_info.non_root_auth_disabled(host, address, &call_stack)?
                 {
                     return Err(host.err(
                         ScErrorType::Auth,
@@ -868,6 +958,7 @@ impl AuthorizationManager {
                         &[address.into()],
                     ));
                 }
+                drop(call_stack);
                 // If a tracker for the new tree doesn't exist yet, create
                 // it and initialize with the current invocation.
                 self.try_borrow_account_trackers_mut(host)?
@@ -1159,7 +1250,10 @@ impl AuthorizationManager {
                 AuthorizationManager::new_enforcing_without_authorizations()
             }
             AuthorizationMode::Recording(rec_info) => {
-                AuthorizationManager::new_recording(rec_info.disable_non_root_auth)
+                AuthorizationManager::new_recording_with_exemptions(
+                    rec_info.disable_non_root_auth,
+                    rec_info.non_root_auth_exemptions.clone(),
+                )
             }
         }
     }
@@ -1593,7 +1687,7 @@ impl AccountAuthorizationTracker {
         }
         self.need_nonce = false;
         if let Some((nonce, expiration_ledger)) = &self.nonce {
-            let ledger_seq = host.with_ledger_info(|li| Ok(li.sequence_number))?;
+            let ledger_seq = host.with_ledger_info_provider(|li| Ok(li.sequence_number()))?;
             if ledger_seq > *expiration_ledger {
                 return Err(host.err(
                     ScErrorType::Auth,
@@ -1644,7 +1738,9 @@ impl AccountAuthorizationTracker {
         })?;
         let payload_preimage =
             HashIdPreimage::SorobanAuthorization(HashIdPreimageSorobanAuthorization {
-                network_id: Hash(host.with_ledger_info(|li| li.network_id.metered_clone(host))?),
+                network_id: Hash(
+                    host.with_ledger_info_provider(|li| li.network_id().metered_clone(host))?,
+                ),
                 nonce,
                 signature_expiration_ledger: expiration_ledger,
                 invocation: self.root_invocation_to_xdr(host)?,
@@ -1853,6 +1949,63 @@ impl Host {
                 .get_recorded_auth_payloads(self)
         }
     }
+
+    /// Suggests a nonce for a new authorization of `address`, so that the
+    /// authorization doesn't fail because its nonce has already been consumed.
+    /// The suggestion is one past the largest consumed nonce of `address` in
+    /// the storage map, skipping the nonces that are consumed in the storage
+    /// map or, in recording mode, in the snapshot source.
+    ///
+    /// This isn't metered and doesn't access the nonce entries, so they are
+    /// neither recorded in nor checked against the footprint. The nonces
+    /// consumed in the snapshot source are only found if they're in the
+    /// storage map or right after the largest nonce found there.
+    pub fn suggest_nonce(&self, address: ScAddress) -> Result<i64, HostError> {
+        self.as_budget().with_free_budget(|| {
+            let (max_nonce, snapshot) = self.with_mut_storage(|storage| {
+                let mut max_nonce = None;
+                for (key, entry) in storage.map.iter(self.as_budget())? {
+                    let xdr::LedgerKey::ContractData(data_key) = key.as_ref() else {
+                        continue;
+                    };
+                    let ScVal::LedgerKeyNonce(ScNonceKey { nonce }) = data_key.key else {
+                        continue;
+                    };
+                    if data_key.contract == address && entry.is_some() {
+                        max_nonce = max_nonce.max(Some(nonce));
+                    }
+                }
+                let snapshot = match &storage.mode {
+                    FootprintMode::Recording(snapshot) => Some(Rc::clone(snapshot)),
+                    FootprintMode::Enforcing => None,
+                };
+                Ok((max_nonce, snapshot))
+            })?;
+            let mut nonce = max_nonce.map_or(0, |n| n.wrapping_add(1));
+            loop {
+                let key = self.storage_key_for_address(
+                    address.clone(),
+                    ScVal::LedgerKeyNonce(ScNonceKey { nonce }),
+                    xdr::ContractDataDurability::Temporary,
+                )?;
+                let consumed = self.with_mut_storage(|storage| {
+                    Ok(storage
+                        .map
+                        .get::<Rc<xdr::LedgerKey>>(&key, self.as_budget())?
+                        .map(|entry| entry.is_some()))
+                })?;
+                let consumed = match (consumed, &snapshot) {
+                    (Some(consumed), _) => consumed,
+                    (None, Some(snapshot)) => snapshot.has(&key)?,
+                    (None, None) => false,
+                };
+                if !consumed {
+                    return Ok(nonce);
+                }
+                nonce = nonce.wrapping_add(1);
+            }
+        })
+    }
 }
 
 #[cfg(any(test, feature = "testutils"))]
@@ -2,6 +2,10 @@ use std::{
     cell::{RefCell, RefMut},
     fmt::{Debug, Display},
     rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
 };
 
 use crate::{
@@ -13,8 +17,14 @@ use crate::{
     Error, Host, HostError, DEFAULT_HOST_DEPTH_LIMIT,
 };
 
+#[cfg(feature = "vm")]
 use wasmi::{errors, FuelCosts, ResourceLimiter};
 
+mod host_fn_cost;
+pub use host_fn_cost::HostFnCostBounds;
+mod snapshot;
+pub use snapshot::{BudgetDimensionSnapshot, BudgetSnapshot, FuelConfigSnapshot};
+
 // These are some sane values, however the embedder should typically customize
 // these to match the network config.
 const DEFAULT_CPU_INSN_LIMIT: u64 = 100_000_000;
@@ -280,7 +290,7 @@ impl BudgetDimension {
 /// doesn't derive all the traits we want. These fields (coarsely) define the
 /// relative costs of different wasm instruction types and are for wasmi internal
 /// fuel metering use only. Units are in "fuels".
-#[derive(Clone)]
+#[derive(Clone, PartialEq, Eq)]
 pub(crate) struct FuelConfig {
     /// The base fuel costs for all instructions.
     pub base: u64,
@@ -325,6 +335,7 @@ impl FuelConfig {
     }
 }
 
+#[cfg(feature = "vm")]
 pub(crate) struct WasmiLimits {
     pub table_elements: u32,
     pub instances: usize,
@@ -332,6 +343,7 @@ pub(crate) struct WasmiLimits {
     pub memories: usize,
 }
 
+#[cfg(feature = "vm")]
 pub(crate) const WASMI_LIMITS_CONFIG: WasmiLimits = WasmiLimits {
     table_elements: 1000,
     instances: 1,
@@ -366,6 +378,9 @@ pub(crate) struct BudgetImpl {
     enabled: bool,
     fuel_config: FuelConfig,
     depth_limit: u32,
+    /// Flag set by the embedder, possibly from another thread, to abort the
+    /// execution at the next charge.
+    cancellation_flag: Option<Arc<AtomicBool>>,
 }
 
 impl BudgetImpl {
@@ -383,6 +398,7 @@ impl BudgetImpl {
             enabled: true,
             fuel_config: Default::default(),
             depth_limit: DEFAULT_HOST_DEPTH_LIMIT,
+            cancellation_flag: None,
         };
 
         b.init_tracker();
@@ -444,6 +460,7 @@ impl BudgetImpl {
         if !self.enabled {
             return Ok(());
         }
+        self.check_not_cancelled()?;
 
         // update tracker for reporting
         self.tracker.count = self.tracker.count.saturating_add(1);
@@ -461,7 +478,33 @@ impl BudgetImpl {
         self.mem_bytes.charge(ty, iterations, input)
     }
 
+    /// Applies the charges accumulated by a [`BudgetChargeBatch`] as if each
+    /// of them was made by an individual call to [`BudgetImpl::charge`].
+    fn charge_batch(&mut self, pending: &[PendingCharge]) -> Result<(), HostError> {
+        for p in pending {
+            self.charge(p.ty, p.iterations, p.input)?;
+            if self.enabled {
+                // `charge` has counted a single meter call.
+                self.tracker.count = self.tracker.count.saturating_add(p.calls - 1);
+            }
+        }
+        Ok(())
+    }
+
+    fn check_not_cancelled(&self) -> Result<(), HostError> {
+        match &self.cancellation_flag {
+            Some(flag) if flag.load(Ordering::Relaxed) => {
+                Err((ScErrorType::Budget, ScErrorCode::ExceededLimit).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    #[cfg(feature = "vm")]
     fn get_wasmi_fuel_remaining(&self) -> Result<u64, HostError> {
+        // The VM is only refueled at host function calls, so this is also
+        // where a cancellation interrupts the execution of Wasm code.
+        self.check_not_cancelled()?;
         let cpu_remaining = self.cpu_insns.get_remaining();
         let cpu_per_fuel = self
             .cpu_insns
@@ -493,6 +536,7 @@ impl Default for BudgetImpl {
             enabled: true,
             fuel_config: Default::default(),
             depth_limit: DEFAULT_HOST_DEPTH_LIMIT,
+            cancellation_flag: None,
         };
 
         for ct in ContractCostType::variants() {
@@ -871,6 +915,71 @@ impl DepthLimiter for BudgetImpl {
 #[derive(Clone)]
 pub struct Budget(pub(crate) Rc<RefCell<BudgetImpl>>);
 
+#[derive(Clone, Copy)]
+struct PendingCharge {
+    ty: ContractCostType,
+    input: Option<u64>,
+    iterations: u64,
+    // Number of individual charges merged into this one.
+    calls: u32,
+}
+
+/// Accumulates charges locally and applies them to the [`Budget`] at once
+/// when flushed, so that tight loops (e.g. per-element metering in bulk
+/// operations) borrow the budget once instead of once per element.
+///
+/// Charges with the same cost type and input are merged into a single bulk
+/// charge, which evaluates the cost model exactly as the individual charges
+/// would, so the totals and the tracker are identical to charging one by one.
+/// The only difference is that exceeding the limit is detected at the flush
+/// rather than at the charge itself, which is fine as the work between the two
+/// is bounded by a single host function call.
+pub(crate) struct BudgetChargeBatch<'a> {
+    budget: &'a Budget,
+    pending: Vec<PendingCharge>,
+}
+
+impl BudgetChargeBatch<'_> {
+    pub(crate) fn charge(&mut self, ty: ContractCostType, input: Option<u64>) {
+        self.bulk_charge(ty, 1, input)
+    }
+
+    pub(crate) fn bulk_charge(
+        &mut self,
+        ty: ContractCostType,
+        iterations: u64,
+        input: Option<u64>,
+    ) {
+        // Batches usually see just a few distinct charges, so a linear scan
+        // is cheaper than any keyed lookup.
+        if let Some(p) = self
+            .pending
+            .iter_mut()
+            .find(|p| p.ty == ty && p.input == input && p.calls < u32::MAX)
+        {
+            p.iterations = p.iterations.saturating_add(iterations);
+            p.calls += 1;
+        } else {
+            self.pending.push(PendingCharge {
+                ty,
+                input,
+                iterations,
+                calls: 1,
+            });
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), HostError> {
+        let res = self
+            .budget
+            .0
+            .try_borrow_mut_or_err()?
+            .charge_batch(&self.pending);
+        self.pending.clear();
+        res
+    }
+}
+
 #[allow(clippy::derivable_impls)]
 impl Default for Budget {
     fn default() -> Self {
@@ -982,6 +1091,36 @@ impl Budget {
         self.0.try_borrow_mut_or_err()?.charge(ty, 1, input)
     }
 
+    /// Runs `f` with a [`BudgetChargeBatch`] that is flushed to the budget
+    /// when `f` returns. The batch is flushed on error too, so that the charges
+    /// made before the error are accounted for. Exceeding the limit takes
+    /// precedence over an error of `f`, as it would have been detected first
+    /// had the charges been made individually.
+    pub(crate) fn with_charge_batch<T, F>(&self, f: F) -> Result<T, HostError>
+    where
+        F: FnOnce(&mut BudgetChargeBatch) -> Result<T, HostError>,
+    {
+        let mut batch = BudgetChargeBatch {
+            budget: self,
+            pending: Vec::new(),
+        };
+        let res = f(&mut batch);
+        batch.flush()?;
+        res
+    }
+
+    /// Attaches a cancellation flag to the budget. Once the flag is set, every
+    /// charge (and thus almost every host operation) fails with a
+    /// `(Budget, ExceededLimit)` error, which aborts the execution as if the
+    /// budget had been exhausted. The flag can be set from another thread, so
+    /// that long-running simulations can be aborted.
+    pub fn set_cancellation_flag(&self, flag: Arc<AtomicBool>) -> Result<(), HostError> {
+        self.mut_budget(|mut b| {
+            b.cancellation_flag = Some(flag);
+            Ok(())
+        })
+    }
+
     pub fn with_free_budget<F, T>(&self, f: F) -> Result<T, HostError>
     where
         F: FnOnce() -> Result<T, HostError>,
@@ -1124,11 +1263,18 @@ impl Budget {
         Ok(())
     }
 
+    #[cfg(feature = "vm")]
     pub(crate) fn get_wasmi_fuel_remaining(&self) -> Result<u64, HostError> {
         self.0.try_borrow_mut_or_err()?.get_wasmi_fuel_remaining()
     }
 
+    #[cfg(feature = "vm")]
+    pub(crate) fn fuel_config(&self) -> Result<FuelConfig, HostError> {
+        Ok(self.0.try_borrow_or_err()?.fuel_config.clone())
+    }
+
     // generate a wasmi fuel cost schedule based on our calibration
+    #[cfg(feature = "vm")]
     pub(crate) fn wasmi_fuel_costs(&self) -> Result<FuelCosts, HostError> {
         let config = &self.0.try_borrow_or_err()?.fuel_config;
         let mut costs = FuelCosts::default();
@@ -1141,6 +1287,7 @@ impl Budget {
     }
 }
 
+#[cfg(feature = "vm")]
 impl ResourceLimiter for Host {
     fn memory_growing(
         &mut self,
@@ -0,0 +1,345 @@
+//! Static cost bounds of host functions, evaluated with the live cost
+//! parameters of a [`Budget`]. These allow tooling to estimate the cost of a
+//! host function call from the sizes of its inputs without executing it.
+//!
+//! The bounds are modeled by hand after the charges the host functions make,
+//! and only cover a subset of the environment interface: the hash and
+//! signature functions, the basic vector and map accessors and the 256-bit
+//! integer arithmetic. Every other host function is reported as unmodeled.
+
+use super::{Budget, BudgetImpl, HostCostModel};
+use crate::{
+    host::{declared_size::DeclaredSizeForMetering, error::TryBorrowOrErr},
+    host_object::HostObject,
+    xdr::{ContractCostType, ScErrorCode, ScErrorType},
+    HostError, Val,
+};
+use ContractCostType::*;
+
+/// Lower and upper bounds of the cpu and memory charged for a host function
+/// call made from a contract.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct HostFnCostBounds {
+    pub min_cpu_insns: u64,
+    pub max_cpu_insns: u64,
+    pub min_mem_bytes: u64,
+    pub max_mem_bytes: u64,
+}
+
+// Number of iterations of a charge, in terms of the input sizes.
+#[derive(Clone, Copy)]
+enum Count {
+    Fixed(u64),
+    // The input size at the given index.
+    Size(usize),
+    // Steps of a binary search over a collection with the size at the given
+    // index, same as `charge_binsearch` of the metered collections.
+    BinSearch(usize),
+}
+
+// Input of the cost model of a charge, in terms of the input sizes.
+#[derive(Clone, Copy)]
+enum Input {
+    Const,
+    Fixed(u64),
+    // The input size at the given index times the factor.
+    Size(usize, u64),
+    // One more than the input size at the given index, times the factor, e.g.
+    // for a collection that grows by one element.
+    SizePlusOne(usize, u64),
+    // Either `Size` or `SizePlusOne`, depending on the inputs.
+    SizeOrPlusOne(usize, u64),
+}
+
+#[derive(Clone, Copy)]
+struct Charge {
+    ty: ContractCostType,
+    min: Count,
+    max: Count,
+    input: Input,
+}
+
+const fn charge_range(ty: ContractCostType, min: Count, max: Count, input: Input) -> Charge {
+    Charge {
+        ty,
+        min,
+        max,
+        input,
+    }
+}
+
+const fn charge(ty: ContractCostType, count: Count, input: Input) -> Charge {
+    charge_range(ty, count, count, input)
+}
+
+const fn once(ty: ContractCostType, input: Input) -> Charge {
+    charge(ty, Count::Fixed(1), input)
+}
+
+// A charge that is made at most once, depending on the inputs.
+const fn maybe_once(ty: ContractCostType, input: Input) -> Charge {
+    charge_range(ty, Count::Fixed(0), Count::Fixed(1), input)
+}
+
+// Declared sizes of the values the host functions allocate and copy, see
+// `DeclaredSizeForMetering`.
+const VAL_SIZE: u64 = <Val as DeclaredSizeForMetering>::DECLARED_SIZE;
+const MAP_ENTRY_SIZE: u64 = <(Val, Val) as DeclaredSizeForMetering>::DECLARED_SIZE;
+const VEC_SIZE: u64 = <Vec<Val> as DeclaredSizeForMetering>::DECLARED_SIZE;
+const HOST_OBJECT_SIZE: u64 = <HostObject as DeclaredSizeForMetering>::DECLARED_SIZE;
+
+// Visiting the object passed to the function.
+const VISIT: Charge = once(VisitObject, Input::Const);
+// Checking a `Val` argument, which visits it if it's an object.
+const CHECK_VAL: Charge = maybe_once(VisitObject, Input::Const);
+// Adding the object returned by the function.
+const NEW_OBJECT: Charge = once(HostMemAlloc, Input::Fixed(HOST_OBJECT_SIZE));
+
+// Converting a 256-bit integer argument reads each of the 4 pieces of an
+// object, and the result is an object unless it's small.
+const fn int256_charges(cost: Charge, num_args: u64) -> [Charge; 3] {
+    [
+        cost,
+        charge_range(
+            VisitObject,
+            Count::Fixed(0),
+            Count::Fixed(4 * num_args),
+            Input::Const,
+        ),
+        maybe_once(HostMemAlloc, Input::Fixed(HOST_OBJECT_SIZE)),
+    ]
+}
+const INT256_ADD_SUB: &[Charge] = &int256_charges(once(Int256AddSub, Input::Const), 2);
+const INT256_MUL: &[Charge] = &int256_charges(once(Int256Mul, Input::Const), 2);
+const INT256_DIV: &[Charge] = &int256_charges(once(Int256Div, Input::Const), 2);
+const INT256_POW: &[Charge] = &int256_charges(once(Int256Pow, Input::Const), 1);
+const INT256_SHIFT: &[Charge] = &int256_charges(once(Int256Shift, Input::Const), 1);
+
+// The charges made by host functions, by name, along with the number of input
+// sizes they expect. The dispatch of the call from the VM is added to all of
+// them. Every charge of a successful call is modeled, assuming that map keys
+// are compared without visiting objects.
+//
+// This is a partial, hand-written copy of the charging code: it has to be
+// updated along with the host functions it lists. The
+// `host_fn_cost_bounds_cover_charges` test goes over every function of the
+// environment interface, checking each entry here against the actual charges
+// and every other function against being reported as unmodeled.
+const HOST_FN_CHARGES: &[(&str, usize, &[Charge])] = &[
+    (
+        "compute_hash_sha256",
+        1,
+        &[
+            VISIT,
+            once(ComputeSha256Hash, Input::Size(0, 1)),
+            NEW_OBJECT,
+        ],
+    ),
+    (
+        "compute_hash_keccak256",
+        1,
+        &[
+            VISIT,
+            once(ComputeKeccak256Hash, Input::Size(0, 1)),
+            NEW_OBJECT,
+        ],
+    ),
+    (
+        "verify_sig_ed25519",
+        1,
+        &[
+            // The key, the signature and the payload.
+            charge(VisitObject, Count::Fixed(3), Input::Const),
+            once(ComputeEd25519PubKey, Input::Const),
+            once(HostMemCpy, Input::Fixed(64)),
+            once(VerifyEd25519Sig, Input::Size(0, 1)),
+        ],
+    ),
+    ("vec_len", 0, &[VISIT]),
+    ("vec_get", 0, &[VISIT, once(VecEntry, Input::Const)]),
+    (
+        "vec_put",
+        1,
+        &[
+            CHECK_VAL,
+            VISIT,
+            once(HostMemCpy, Input::Fixed(VEC_SIZE)),
+            once(HostMemAlloc, Input::Size(0, VAL_SIZE)),
+            once(HostMemCpy, Input::Size(0, VAL_SIZE)),
+            once(VecEntry, Input::Const),
+            NEW_OBJECT,
+        ],
+    ),
+    (
+        "vec_push_back",
+        1,
+        &[
+            CHECK_VAL,
+            VISIT,
+            once(HostMemCpy, Input::Fixed(VEC_SIZE)),
+            once(HostMemAlloc, Input::SizePlusOne(0, VAL_SIZE)),
+            once(HostMemCpy, Input::SizePlusOne(0, VAL_SIZE)),
+            NEW_OBJECT,
+        ],
+    ),
+    ("map_len", 0, &[VISIT]),
+    (
+        "map_get",
+        1,
+        &[
+            CHECK_VAL,
+            VISIT,
+            charge(MapEntry, Count::BinSearch(0), Input::Const),
+            once(MapEntry, Input::Const),
+        ],
+    ),
+    (
+        "map_has",
+        1,
+        &[
+            CHECK_VAL,
+            VISIT,
+            charge(MapEntry, Count::BinSearch(0), Input::Const),
+        ],
+    ),
+    (
+        "map_put",
+        1,
+        &[
+            charge_range(VisitObject, Count::Fixed(0), Count::Fixed(2), Input::Const),
+            VISIT,
+            // Looking up the key of a put into a full map.
+            charge_range(MapEntry, Count::Fixed(0), Count::BinSearch(0), Input::Const),
+            once(MapEntry, Input::Const),
+            charge(MapEntry, Count::BinSearch(0), Input::Const),
+            once(HostMemCpy, Input::Fixed(VEC_SIZE)),
+            once(HostMemAlloc, Input::SizeOrPlusOne(0, MAP_ENTRY_SIZE)),
+            once(HostMemCpy, Input::SizeOrPlusOne(0, MAP_ENTRY_SIZE)),
+            // Checking the order of the entries of the new map, which has one
+            // more entry for a new key.
+            charge(MapEntry, Count::Size(0), Input::Const),
+            maybe_once(MapEntry, Input::Const),
+            NEW_OBJECT,
+        ],
+    ),
+    ("u256_add", 0, INT256_ADD_SUB),
+    ("u256_sub", 0, INT256_ADD_SUB),
+    ("u256_mul", 0, INT256_MUL),
+    ("u256_div", 0, INT256_DIV),
+    ("u256_pow", 0, INT256_POW),
+    ("u256_shl", 0, INT256_SHIFT),
+    ("u256_shr", 0, INT256_SHIFT),
+    (
+        "u256_log2",
+        0,
+        &[
+            once(Int256Shift, Input::Const),
+            charge_range(VisitObject, Count::Fixed(0), Count::Fixed(4), Input::Const),
+        ],
+    ),
+    ("i256_add", 0, INT256_ADD_SUB),
+    ("i256_sub", 0, INT256_ADD_SUB),
+    ("i256_mul", 0, INT256_MUL),
+    ("i256_div", 0, INT256_DIV),
+    ("i256_pow", 0, INT256_POW),
+    ("i256_shl", 0, INT256_SHIFT),
+    ("i256_shr", 0, INT256_SHIFT),
+];
+
+impl Count {
+    fn eval(self, sizes: &[u64]) -> u64 {
+        match self {
+            Count::Fixed(n) => n,
+            Count::Size(i) => sizes[i],
+            Count::BinSearch(i) => 1 + (64 - sizes[i].leading_zeros()) as u64,
+        }
+    }
+}
+
+impl Input {
+    // Returns the smallest and the largest input.
+    fn eval(self, sizes: &[u64]) -> (Option<u64>, Option<u64>) {
+        let size = |i: usize, plus: u64, factor: u64| {
+            Some(sizes[i].saturating_add(plus).saturating_mul(factor))
+        };
+        match self {
+            Input::Const => (None, None),
+            Input::Fixed(n) => (Some(n), Some(n)),
+            Input::Size(i, factor) => (size(i, 0, factor), size(i, 0, factor)),
+            Input::SizePlusOne(i, factor) => (size(i, 1, factor), size(i, 1, factor)),
+            Input::SizeOrPlusOne(i, factor) => (size(i, 0, factor), size(i, 1, factor)),
+        }
+    }
+}
+
+impl BudgetImpl {
+    fn charge_bounds(
+        &self,
+        bounds: &mut HostFnCostBounds,
+        c: &Charge,
+        sizes: &[u64],
+    ) -> Result<(), HostError> {
+        let (min_input, max_input) = c.input.eval(sizes);
+        let cpu = self.cpu_insns.get_cost_model(c.ty);
+        let mem = self.mem_bytes.get_cost_model(c.ty);
+        let (min, max) = (c.min.eval(sizes), c.max.eval(sizes));
+        let min_cpu = cpu.evaluate(min_input)?.saturating_mul(min);
+        let max_cpu = cpu.evaluate(max_input)?.saturating_mul(max);
+        let min_mem = mem.evaluate(min_input)?.saturating_mul(min);
+        let max_mem = mem.evaluate(max_input)?.saturating_mul(max);
+        bounds.min_cpu_insns = bounds.min_cpu_insns.saturating_add(min_cpu);
+        bounds.max_cpu_insns = bounds.max_cpu_insns.saturating_add(max_cpu);
+        bounds.min_mem_bytes = bounds.min_mem_bytes.saturating_add(min_mem);
+        bounds.max_mem_bytes = bounds.max_mem_bytes.saturating_add(max_mem);
+        Ok(())
+    }
+}
+
+impl Budget {
+    /// Returns the bounds of the cpu and memory the host charges for calling
+    /// the host function `fn_name` (as named in the environment interface)
+    /// from a contract, for the given input sizes, using the current cost
+    /// parameters of this budget. No host state is touched.
+    ///
+    /// The meaning and number of `input_sizes` depends on the function (e.g.
+    /// the length of the hashed buffer for `compute_hash_sha256`, or the
+    /// number of entries of the map for `map_get`), see
+    /// [`Budget::host_fns_with_cost_bounds`]. Only a subset of the host
+    /// functions is modeled; this fails with `(Value, InvalidInput)` for the
+    /// rest and with `(Value, UnexpectedSize)` for the wrong number of input
+    /// sizes.
+    pub fn host_fn_cost_bounds(
+        &self,
+        fn_name: &str,
+        input_sizes: &[u64],
+    ) -> Result<HostFnCostBounds, HostError> {
+        let Some((_, num_sizes, charges)) =
+            HOST_FN_CHARGES.iter().find(|(name, _, _)| *name == fn_name)
+        else {
+            return Err((ScErrorType::Value, ScErrorCode::InvalidInput).into());
+        };
+        if input_sizes.len() != *num_sizes {
+            return Err((ScErrorType::Value, ScErrorCode::UnexpectedSize).into());
+        }
+        let b = self.0.try_borrow_or_err()?;
+        let mut bounds = HostFnCostBounds::default();
+        b.charge_bounds(
+            &mut bounds,
+            &once(DispatchHostFunction, Input::Const),
+            input_sizes,
+        )?;
+        for c in charges.iter() {
+            b.charge_bounds(&mut bounds, c, input_sizes)?;
+        }
+        Ok(bounds)
+    }
+
+    /// Returns the names of the host functions supported by
+    /// [`Budget::host_fn_cost_bounds`], along with the number of input sizes
+    /// each of them expects. This is a subset of the environment interface.
+    pub fn host_fns_with_cost_bounds() -> impl Iterator<Item = (&'static str, usize)> {
+        HOST_FN_CHARGES
+            .iter()
+            .map(|(name, num_sizes, _)| (*name, *num_sizes))
+    }
+}
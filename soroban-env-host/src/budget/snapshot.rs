@@ -0,0 +1,300 @@
+//! Export and import of the state of a [`Budget`], so that the budget of a
+//! multi-stage pipeline (e.g. preflight followed by enforcement) or of a
+//! checkpointed execution can be carried across process boundaries.
+
+use super::{Budget, BudgetDimension, BudgetImpl, FuelConfig, MeteredCostComponent, ScaledU64};
+use crate::{
+    host::error::TryBorrowOrErr,
+    xdr::{ContractCostType, ScErrorCode, ScErrorType},
+    HostError,
+};
+use std::{cell::RefCell, rc::Rc};
+
+/// The state of one dimension (cpu or memory) of a [`Budget`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BudgetDimensionSnapshot {
+    pub limit: u64,
+    pub total_count: u64,
+    /// The constant and linear term of the cost model of every cost type,
+    /// indexed by the cost type. The linear terms are scaled by
+    /// [`COST_MODEL_LIN_TERM_SCALE_BITS`](super::COST_MODEL_LIN_TERM_SCALE_BITS).
+    pub cost_models: Vec<(u64, u64)>,
+    /// The amount charged for every cost type, indexed by the cost type.
+    pub counts: Vec<u64>,
+}
+
+/// The fuel costs of Wasm instructions of a [`Budget`], in fuels.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FuelConfigSnapshot {
+    pub base: u64,
+    pub entity: u64,
+    pub load: u64,
+    pub store: u64,
+    pub call: u64,
+}
+
+/// The consumption, limits and cost models of a [`Budget`], see
+/// [`Budget::snapshot`] and [`Budget::from_snapshot`].
+///
+/// The snapshot doesn't include the cancellation flag set with
+/// [`Budget::set_cancellation_flag`], which has to be set again on the
+/// restored budget.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BudgetSnapshot {
+    pub cpu_insns: BudgetDimensionSnapshot,
+    pub mem_bytes: BudgetDimensionSnapshot,
+    /// The `(iterations, total_input)` charged for every cost type, indexed
+    /// by the cost type, as reported by [`Budget::get_tracker`].
+    pub tracker: Vec<(u64, Option<u64>)>,
+    /// The number of times the budget has been charged.
+    pub tracker_count: u32,
+    /// Whether charges are accounted for, which they aren't within
+    /// [`Budget::with_free_budget`].
+    pub enabled: bool,
+    pub fuel_config: FuelConfigSnapshot,
+    /// The remaining depth of nested values and calls.
+    pub depth_limit: u32,
+}
+
+fn err_invalid_snapshot() -> HostError {
+    (ScErrorType::Context, ScErrorCode::InvalidInput).into()
+}
+
+impl BudgetDimension {
+    fn snapshot(&self) -> BudgetDimensionSnapshot {
+        BudgetDimensionSnapshot {
+            limit: self.limit,
+            total_count: self.total_count,
+            cost_models: self
+                .cost_models
+                .iter()
+                .map(|m| (m.const_term, m.lin_term.0))
+                .collect(),
+            counts: self.counts.clone(),
+        }
+    }
+
+    fn from_snapshot(snapshot: &BudgetDimensionSnapshot) -> Result<Self, HostError> {
+        let num_types = ContractCostType::variants().len();
+        if snapshot.cost_models.len() != num_types || snapshot.counts.len() != num_types {
+            return Err(err_invalid_snapshot());
+        }
+        let total = snapshot
+            .counts
+            .iter()
+            .fold(0u64, |acc, c| acc.saturating_add(*c));
+        if total != snapshot.total_count {
+            return Err(err_invalid_snapshot());
+        }
+        Ok(Self {
+            cost_models: snapshot
+                .cost_models
+                .iter()
+                .map(|(const_term, lin_term)| MeteredCostComponent {
+                    const_term: *const_term,
+                    lin_term: ScaledU64(*lin_term),
+                })
+                .collect(),
+            limit: snapshot.limit,
+            counts: snapshot.counts.clone(),
+            total_count: snapshot.total_count,
+        })
+    }
+}
+
+impl FuelConfig {
+    fn snapshot(&self) -> FuelConfigSnapshot {
+        FuelConfigSnapshot {
+            base: self.base,
+            entity: self.entity,
+            load: self.load,
+            store: self.store,
+            call: self.call,
+        }
+    }
+
+    fn from_snapshot(snapshot: &FuelConfigSnapshot) -> Self {
+        Self {
+            base: snapshot.base,
+            entity: snapshot.entity,
+            load: snapshot.load,
+            store: snapshot.store,
+            call: snapshot.call,
+        }
+    }
+}
+
+impl BudgetSnapshot {
+    /// Serializes the snapshot into a deterministic byte encoding, which can
+    /// be decoded with [`BudgetSnapshot::from_bytes`].
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = vec![];
+        for dim in [&self.cpu_insns, &self.mem_bytes] {
+            buf.extend_from_slice(&dim.limit.to_le_bytes());
+            buf.extend_from_slice(&dim.total_count.to_le_bytes());
+            buf.extend_from_slice(&(dim.cost_models.len() as u32).to_le_bytes());
+            for ((const_term, lin_term), count) in dim.cost_models.iter().zip(dim.counts.iter()) {
+                buf.extend_from_slice(&const_term.to_le_bytes());
+                buf.extend_from_slice(&lin_term.to_le_bytes());
+                buf.extend_from_slice(&count.to_le_bytes());
+            }
+        }
+        buf.extend_from_slice(&self.tracker_count.to_le_bytes());
+        buf.extend_from_slice(&(self.tracker.len() as u32).to_le_bytes());
+        for (iterations, input) in self.tracker.iter() {
+            buf.extend_from_slice(&iterations.to_le_bytes());
+            match input {
+                None => buf.push(0),
+                Some(input) => {
+                    buf.push(1);
+                    buf.extend_from_slice(&input.to_le_bytes());
+                }
+            }
+        }
+        buf.push(self.enabled as u8);
+        let fc = &self.fuel_config;
+        for fuel in [fc.base, fc.entity, fc.load, fc.store, fc.call] {
+            buf.extend_from_slice(&fuel.to_le_bytes());
+        }
+        buf.extend_from_slice(&self.depth_limit.to_le_bytes());
+        buf
+    }
+
+    /// Decodes a snapshot encoded with [`BudgetSnapshot::to_bytes`]. Fails
+    /// with `(Context, InvalidInput)` on malformed input.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, HostError> {
+        let mut reader = SnapshotReader(bytes);
+        let mut dims = [
+            BudgetDimensionSnapshot::default(),
+            BudgetDimensionSnapshot::default(),
+        ];
+        for dim in dims.iter_mut() {
+            dim.limit = reader.u64()?;
+            dim.total_count = reader.u64()?;
+            let len = reader.cost_types_len()?;
+            for _ in 0..len {
+                dim.cost_models.push((reader.u64()?, reader.u64()?));
+                dim.counts.push(reader.u64()?);
+            }
+        }
+        let tracker_count = reader.u32()?;
+        let len = reader.cost_types_len()?;
+        let mut tracker = vec![];
+        for _ in 0..len {
+            let iterations = reader.u64()?;
+            let input = match reader.u8()? {
+                0 => None,
+                1 => Some(reader.u64()?),
+                _ => return Err(err_invalid_snapshot()),
+            };
+            tracker.push((iterations, input));
+        }
+        let enabled = match reader.u8()? {
+            0 => false,
+            1 => true,
+            _ => return Err(err_invalid_snapshot()),
+        };
+        let fuel_config = FuelConfigSnapshot {
+            base: reader.u64()?,
+            entity: reader.u64()?,
+            load: reader.u64()?,
+            store: reader.u64()?,
+            call: reader.u64()?,
+        };
+        let depth_limit = reader.u32()?;
+        if !reader.0.is_empty() {
+            return Err(err_invalid_snapshot());
+        }
+        let [cpu_insns, mem_bytes] = dims;
+        Ok(Self {
+            cpu_insns,
+            mem_bytes,
+            tracker,
+            tracker_count,
+            enabled,
+            fuel_config,
+            depth_limit,
+        })
+    }
+}
+
+struct SnapshotReader<'a>(&'a [u8]);
+
+impl SnapshotReader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], HostError> {
+        if self.0.len() < N {
+            return Err(err_invalid_snapshot());
+        }
+        let (head, tail) = self.0.split_at(N);
+        self.0 = tail;
+        head.try_into().map_err(|_| err_invalid_snapshot())
+    }
+
+    fn u8(&mut self) -> Result<u8, HostError> {
+        Ok(self.take::<1>()?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, HostError> {
+        Ok(u32::from_le_bytes(self.take()?))
+    }
+
+    fn u64(&mut self) -> Result<u64, HostError> {
+        Ok(u64::from_le_bytes(self.take()?))
+    }
+
+    // Reads the length of a vector, which is only accepted if it matches the
+    // number of cost types.
+    fn cost_types_len(&mut self) -> Result<usize, HostError> {
+        let len = self.u32()? as usize;
+        if len != ContractCostType::variants().len() {
+            return Err(err_invalid_snapshot());
+        }
+        Ok(len)
+    }
+}
+
+impl Budget {
+    /// Returns the current consumption, limits and cost models of the budget.
+    pub fn snapshot(&self) -> Result<BudgetSnapshot, HostError> {
+        let b = self.0.try_borrow_or_err()?;
+        Ok(BudgetSnapshot {
+            cpu_insns: b.cpu_insns.snapshot(),
+            mem_bytes: b.mem_bytes.snapshot(),
+            tracker: b.tracker.cost_tracker.to_vec(),
+            tracker_count: b.tracker.count,
+            enabled: b.enabled,
+            fuel_config: b.fuel_config.snapshot(),
+            depth_limit: b.depth_limit,
+        })
+    }
+
+    /// Creates a budget in the state captured by [`Budget::snapshot`], so
+    /// that further charges are accounted for exactly as they would have
+    /// been by the original budget. Fails with `(Context, InvalidInput)` if
+    /// the snapshot doesn't cover every cost type or is inconsistent.
+    pub fn from_snapshot(snapshot: &BudgetSnapshot) -> Result<Self, HostError> {
+        let mut b = BudgetImpl::default();
+        b.cpu_insns = BudgetDimension::from_snapshot(&snapshot.cpu_insns)?;
+        b.mem_bytes = BudgetDimension::from_snapshot(&snapshot.mem_bytes)?;
+        if snapshot.tracker.len() != b.tracker.cost_tracker.len() {
+            return Err(err_invalid_snapshot());
+        }
+        for (tracker, restored) in b
+            .tracker
+            .cost_tracker
+            .iter_mut()
+            .zip(snapshot.tracker.iter())
+        {
+            // Cost types with and without inputs can't be swapped.
+            if tracker.1.is_some() != restored.1.is_some() {
+                return Err(err_invalid_snapshot());
+            }
+            *tracker = *restored;
+        }
+        b.tracker.count = snapshot.tracker_count;
+        b.enabled = snapshot.enabled;
+        b.fuel_config = FuelConfig::from_snapshot(&snapshot.fuel_config);
+        b.depth_limit = snapshot.depth_limit;
+        Ok(Self(Rc::new(RefCell::new(b))))
+    }
+}
@@ -2,7 +2,7 @@
 /// environments using a clean host instance.
 /// Also contains helpers for processing the ledger changes caused by these
 /// host functions.
-use std::{cmp::max, rc::Rc};
+use std::{cell::RefCell, cmp::max, collections::BTreeMap, rc::Rc};
 
 use soroban_env_common::{
     xdr::{
@@ -25,7 +25,8 @@ use crate::{
         metered_xdr::{metered_from_xdr_with_budget, metered_write_xdr},
     },
     storage::{AccessType, Footprint, FootprintMap, SnapshotSource, Storage, StorageMap},
-    DiagnosticLevel, Host, HostError, LedgerInfo, MeteredOrdMap,
+    DiagnosticLevel, FeeRefund, Host, HostError, LedgerInfo, MeteredOrdMap, ObjectSizeLimits,
+    XdrReadLimits,
 };
 
 pub type ExpirationEntryMap = MeteredOrdMap<Rc<LedgerKey>, Rc<ExpirationEntry>, Budget>;
@@ -48,6 +49,11 @@ pub struct InvokeHostFunctionResult {
     ///
     /// Empty when invocation fails.
     pub encoded_contract_events: Vec<Vec<u8>>,
+    /// Fee refund designated by a contract during the invocation that has to
+    /// be paid to the source account.
+    ///
+    /// Always `None` when invocation fails.
+    pub fee_refund: Option<FeeRefund>,
 }
 
 /// Represents a change of the ledger entry from 'old' value to the 'new' one.
@@ -221,6 +227,13 @@ pub fn extract_rent_changes(ledger_changes: &Vec<LedgerEntryChange>) -> Vec<Ledg
 ///
 /// When diagnostics are enabled, we try to populate `diagnostic_events`
 /// even if the `InvokeHostFunctionResult` fails for any reason.
+///
+/// `object_size_limits` are the maximum sizes of the host objects from the
+/// network configuration, see [`Host::set_object_size_limits`].
+///
+/// `xdr_read_limits` are the limits from the network configuration applied
+/// when decoding every XDR input, including the resources and the ledger
+/// entries, see [`Host::set_xdr_read_limits`].
 #[allow(clippy::too_many_arguments)]
 pub fn invoke_host_function<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
     budget: &Budget,
@@ -230,6 +243,8 @@ pub fn invoke_host_function<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
     encoded_source_account: T,
     encoded_auth_entries: I,
     ledger_info: LedgerInfo,
+    object_size_limits: ObjectSizeLimits,
+    xdr_read_limits: XdrReadLimits,
     encoded_ledger_entries: I,
     encoded_expiration_entries: I,
     base_prng_seed: T,
@@ -238,13 +253,14 @@ pub fn invoke_host_function<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
     let _span0 = tracy_span!("invoke_host_function");
 
     let resources: SorobanResources =
-        metered_from_xdr_with_budget(encoded_resources.as_ref(), &budget)?;
+        metered_from_xdr_with_budget(encoded_resources.as_ref(), &budget, xdr_read_limits)?;
     let footprint = build_storage_footprint_from_xdr(&budget, resources.footprint)?;
     let storage_and_expiration_maps = build_storage_map_from_xdr_ledger_entries(
         &budget,
         &footprint,
         encoded_ledger_entries,
         encoded_expiration_entries,
+        xdr_read_limits,
     )?;
 
     let storage_map = storage_and_expiration_maps.0;
@@ -252,6 +268,8 @@ pub fn invoke_host_function<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
 
     let storage = Storage::with_enforcing_footprint_and_map(footprint, storage_map);
     let host = Host::with_storage_and_budget(storage, budget.clone());
+    host.set_object_size_limits(object_size_limits)?;
+    host.set_xdr_read_limits(xdr_read_limits)?;
     let auth_entries = host.build_auth_entries_from_xdr(encoded_auth_entries)?;
     let host_function: HostFunction = host.metered_from_xdr(encoded_host_fn.as_ref())?;
     let source_account: AccountId = host.metered_from_xdr(encoded_source_account.as_ref())?;
@@ -274,6 +292,7 @@ pub fn invoke_host_function<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
         let _span1 = tracy_span!("Host::invoke_function");
         host.invoke_function(host_function)
     };
+    let fee_refund = host.get_fee_refund()?;
     let (storage, events) = host.try_finish()?;
     if enable_diagnostics {
         extract_diagnostic_events(&events, diagnostic_events);
@@ -299,12 +318,14 @@ pub fn invoke_host_function<T: AsRef<[u8]>, I: ExactSizeIterator<Item = T>>(
             encoded_invoke_result,
             ledger_changes,
             encoded_contract_events,
+            fee_refund,
         })
     } else {
         Ok(InvokeHostFunctionResult {
             encoded_invoke_result,
             ledger_changes: vec![],
             encoded_contract_events: vec![],
+            fee_refund: None,
         })
     }
 }
@@ -413,6 +434,7 @@ fn build_storage_map_from_xdr_ledger_entries<T: AsRef<[u8]>, I: ExactSizeIterato
     footprint: &Footprint,
     encoded_ledger_entries: I,
     encoded_expiration_entries: I,
+    xdr_read_limits: XdrReadLimits,
 ) -> Result<(StorageMap, ExpirationEntryMap), HostError> {
     let mut storage_map = StorageMap::new();
     let mut expiration_map = ExpirationEntryMap::new();
@@ -427,14 +449,22 @@ fn build_storage_map_from_xdr_ledger_entries<T: AsRef<[u8]>, I: ExactSizeIterato
         let mut expiration_ledger: Option<u32> = None;
 
         let le = Rc::metered_new(
-            metered_from_xdr_with_budget::<LedgerEntry>(entry_buf.as_ref(), budget)?,
+            metered_from_xdr_with_budget::<LedgerEntry>(
+                entry_buf.as_ref(),
+                budget,
+                xdr_read_limits,
+            )?,
             budget,
         )?;
         let key = Rc::metered_new(ledger_entry_to_ledger_key(&le, budget)?, budget)?;
 
         if !expiration_buf.as_ref().is_empty() {
             let ee = Rc::metered_new(
-                metered_from_xdr_with_budget::<ExpirationEntry>(expiration_buf.as_ref(), budget)?,
+                metered_from_xdr_with_budget::<ExpirationEntry>(
+                    expiration_buf.as_ref(),
+                    budget,
+                    xdr_read_limits,
+                )?,
                 budget,
             )?;
 
@@ -497,3 +527,121 @@ impl<'a> SnapshotSource for StorageMapSnapshotSource<'a> {
         }
     }
 }
+
+/// Result of [`record_footprint_with_loader`].
+pub struct RecordedFootprint<T> {
+    /// Result of the last run of the invocation.
+    pub result: Result<T, HostError>,
+    /// Footprint recorded by the last run of the invocation.
+    pub footprint: LedgerFootprint,
+    /// Number of times the invocation has been run.
+    pub attempts: u32,
+}
+
+type LoadedEntries = BTreeMap<Rc<LedgerKey>, Option<(Rc<LedgerEntry>, Option<u32>)>>;
+
+// Snapshot source that only serves the entries loaded so far and fails on
+// any other key, remembering the key so that it can be loaded before the
+// next attempt.
+struct LoadedEntriesSnapshotSource {
+    entries: LoadedEntries,
+    misses: RefCell<Vec<Rc<LedgerKey>>>,
+}
+
+impl LoadedEntriesSnapshotSource {
+    fn lookup(
+        &self,
+        key: &Rc<LedgerKey>,
+    ) -> Result<&Option<(Rc<LedgerEntry>, Option<u32>)>, HostError> {
+        match self.entries.get(key) {
+            Some(value) => Ok(value),
+            None => {
+                self.misses.borrow_mut().push(Rc::clone(key));
+                Err(
+                    Error::from_type_and_code(ScErrorType::Storage, ScErrorCode::MissingValue)
+                        .into(),
+                )
+            }
+        }
+    }
+}
+
+impl SnapshotSource for LoadedEntriesSnapshotSource {
+    fn get(&self, key: &Rc<LedgerKey>) -> Result<(Rc<LedgerEntry>, Option<u32>), HostError> {
+        match self.lookup(key)? {
+            Some((entry, expiration)) => Ok((Rc::clone(entry), *expiration)),
+            None => Err(
+                Error::from_type_and_code(ScErrorType::Storage, ScErrorCode::MissingValue).into(),
+            ),
+        }
+    }
+
+    fn has(&self, key: &Rc<LedgerKey>) -> Result<bool, HostError> {
+        Ok(self.lookup(key)?.is_some())
+    }
+}
+
+/// Records the footprint of an invocation whose ledger entries are loaded on
+/// demand, as done by preflight implementations.
+///
+/// `invoke` is run on a fresh [Host] in the recording mode, which it should
+/// configure (ledger info, authorization etc.) before invoking the function.
+/// Whenever the invocation accesses a ledger key that hasn't been loaded yet,
+/// the key is passed to `loader` (which returns the entry and its expiration
+/// ledger, or `None` if the entry doesn't exist) and the invocation is run
+/// again, until a run doesn't access any new keys. Fails with `(Context,
+/// ExceededLimit)` if that doesn't happen within `max_attempts` runs.
+///
+/// Errors of the final run of the invocation are returned in
+/// [`RecordedFootprint::result`], while loader errors are returned directly.
+pub fn record_footprint_with_loader<T, F, L>(
+    max_attempts: u32,
+    mut loader: L,
+    mut invoke: F,
+) -> Result<RecordedFootprint<T>, HostError>
+where
+    F: FnMut(&Host) -> Result<T, HostError>,
+    L: FnMut(&LedgerKey) -> Result<Option<(LedgerEntry, Option<u32>)>, HostError>,
+{
+    let mut entries = LoadedEntries::new();
+    for attempt in 1..=max_attempts {
+        let source = Rc::new(LoadedEntriesSnapshotSource {
+            entries: entries.clone(),
+            misses: RefCell::new(vec![]),
+        });
+        let host = Host::with_storage_and_budget(
+            Storage::with_recording_footprint(source.clone()),
+            Budget::default(),
+        );
+        let result = invoke(&host);
+        let budget = host.budget_cloned();
+        let (storage, _) = host.try_finish()?;
+
+        let misses = source.misses.take();
+        if misses.is_empty() {
+            let mut read_only = vec![];
+            let mut read_write = vec![];
+            for (key, access_type) in storage.footprint.0.iter(&budget)? {
+                match access_type {
+                    AccessType::ReadOnly => read_only.push(key.as_ref().clone()),
+                    AccessType::ReadWrite => read_write.push(key.as_ref().clone()),
+                }
+            }
+            return Ok(RecordedFootprint {
+                result,
+                footprint: LedgerFootprint {
+                    read_only: read_only.try_into()?,
+                    read_write: read_write.try_into()?,
+                },
+                attempts: attempt,
+            });
+        }
+        for key in misses {
+            if !entries.contains_key(&key) {
+                let value = loader(&key)?.map(|(entry, expiration)| (Rc::new(entry), expiration));
+                entries.insert(key, value);
+            }
+        }
+    }
+    Err(Error::from_type_and_code(ScErrorType::Context, ScErrorCode::ExceededLimit).into())
+}
@@ -1,11 +1,11 @@
 use std::rc::Rc;
 
 use soroban_env_common::{
-    xdr::{Hash, ScBytes, ScString, ScVal, StringM},
+    xdr::{Hash, ScBytes, ScString, ScSymbol, ScVal, ScVec, StringM},
     Error, Symbol, SymbolSmall,
 };
 
-use crate::{budget::AsBudget, host::Frame, Host, HostError, Val};
+use crate::{budget::AsBudget, host::Frame, Host, HostError, ScValLimits, Val};
 
 use super::{
     internal::{InternalDiagnosticArg, InternalDiagnosticEvent},
@@ -38,6 +38,77 @@ impl Host {
         ))
     }
 
+    /// Enables tracing of the host functions called by Wasm contracts. Every
+    /// call is recorded as a diagnostic event with its arguments and result
+    /// rendered as [`ScVal`]s, with any value exceeding `limits` elided.
+    /// Like all the diagnostic events, these are only recorded in debug mode.
+    pub fn enable_host_fn_tracing(&self, limits: ScValLimits) -> Result<(), HostError> {
+        *self.try_borrow_host_fn_trace_limits_mut()? = Some(limits);
+        Ok(())
+    }
+
+    pub fn disable_host_fn_tracing(&self) -> Result<(), HostError> {
+        *self.try_borrow_host_fn_trace_limits_mut()? = None;
+        Ok(())
+    }
+
+    // Returns the limits to render the host function call with if it should be
+    // traced.
+    pub(crate) fn host_fn_trace_limits(&self) -> Result<Option<ScValLimits>, HostError> {
+        if !self.is_debug()? {
+            return Ok(None);
+        }
+        Ok(*self.try_borrow_host_fn_trace_limits()?)
+    }
+
+    // Renders `val` for a host function trace, replacing it with a
+    // placeholder string if it exceeds `limits`. The limits are checked
+    // before converting `val`, so rendering is bounded by the limits.
+    pub(crate) fn host_fn_trace_scval(
+        &self,
+        val: Val,
+        limits: &ScValLimits,
+    ) -> Result<ScVal, HostError> {
+        self.as_budget().with_free_budget(|| {
+            if self.val_within_limits(val, limits)? {
+                self.from_host_val(val)
+            } else {
+                Ok(ScVal::String(ScString::from(StringM::try_from(
+                    "<elided>".as_bytes().to_vec(),
+                )?)))
+            }
+        })
+    }
+
+    // Emits an event with topic = ["host_fn", function_name] and
+    // data = [[arg1, arg2, ...], result], where result is either the returned
+    // value or the error.
+    pub(crate) fn host_fn_trace_diagnostics(
+        &self,
+        fn_name: &str,
+        args: Vec<ScVal>,
+        res: Result<ScVal, Error>,
+    ) -> Result<(), HostError> {
+        let calling_contract = self.get_current_contract_id_unmetered()?;
+        self.as_budget().with_free_budget(|| {
+            let topics = vec![
+                InternalDiagnosticArg::HostVal(SymbolSmall::try_from_str("host_fn")?.into()),
+                InternalDiagnosticArg::XdrVal(ScVal::Symbol(ScSymbol(
+                    fn_name.as_bytes().to_vec().try_into()?,
+                ))),
+            ];
+            let res = match res {
+                Ok(v) => InternalDiagnosticArg::XdrVal(v),
+                Err(e) => InternalDiagnosticArg::HostVal(e.to_val()),
+            };
+            let args = vec![
+                InternalDiagnosticArg::XdrVal(ScVal::Vec(Some(ScVec(args.try_into()?)))),
+                res,
+            ];
+            self.record_diagnostic_event(calling_contract, topics, args)
+        })
+    }
+
     pub(crate) fn record_diagnostic_event(
         &self,
         contract_id: Option<Hash>,
@@ -151,6 +222,58 @@ impl Host {
         })
     }
 
+    // Emits an event with topic = ["reentry", function_name] and
+    // data = [contract_id_1, ..., contract_id_n, contract_id_1], i.e. the cycle
+    // of contract frames that the rejected re-entrant call would have closed.
+    pub(crate) fn reentry_diagnostics(
+        &self,
+        cycle: &[&Hash],
+        func: &Symbol,
+    ) -> Result<(), HostError> {
+        if !self.is_debug()? {
+            return Ok(());
+        }
+
+        let calling_contract = self.get_current_contract_id_unmetered()?;
+
+        self.as_budget().with_free_budget(|| {
+            let topics = vec![
+                InternalDiagnosticArg::HostVal(SymbolSmall::try_from_str("reentry")?.into()),
+                InternalDiagnosticArg::HostVal(func.into()),
+            ];
+            let args = cycle
+                .iter()
+                .map(|id| -> Result<_, HostError> {
+                    Ok(InternalDiagnosticArg::XdrVal(ScVal::Bytes(
+                        ScBytes::try_from(id.as_slice().to_vec())?,
+                    )))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            self.record_diagnostic_event(calling_contract, topics, args)
+        })
+    }
+
+    // Emits an event with topic = ["prng_draw", draw_kind] and
+    // data = [arg1, ..., output], for auditing the PRNG draws of contracts.
+    pub(crate) fn prng_draw_diagnostics<F>(&self, kind: &str, args: F) -> Result<(), HostError>
+    where
+        F: FnOnce() -> Vec<InternalDiagnosticArg>,
+    {
+        if !self.is_debug()? {
+            return Ok(());
+        }
+
+        let calling_contract = self.get_current_contract_id_unmetered()?;
+
+        self.as_budget().with_free_budget(|| {
+            let topics = vec![
+                InternalDiagnosticArg::HostVal(SymbolSmall::try_from_str("prng_draw")?.into()),
+                InternalDiagnosticArg::HostVal(SymbolSmall::try_from_str(kind)?.into()),
+            ];
+            self.record_diagnostic_event(calling_contract, topics, args())
+        })
+    }
+
     // Emits an event with topic = ["fn_return", function_name] and
     // data = [return_val]
     pub fn fn_return_diagnostics(
@@ -0,0 +1,89 @@
+//! Filtering of the contract events at emission, see
+//! [`Host::set_event_filter`].
+
+use soroban_env_common::{
+    xdr::{Hash, ScVal},
+    VecObject,
+};
+
+use crate::{budget::AsBudget, host_object::HostVec, Host, HostError};
+
+/// Selects the contract and system events that the host records, see
+/// [`Host::set_event_filter`].
+///
+/// An event is recorded if it's emitted by one of `contract_ids` and its
+/// topics start with one of `topic_prefixes`. An empty list matches every
+/// event.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EventFilter {
+    pub contract_ids: Vec<Hash>,
+    pub topic_prefixes: Vec<Vec<ScVal>>,
+}
+
+impl Host {
+    /// Drops the contract and system events that don't match `filter` as soon
+    /// as they're emitted, instead of recording them, which saves the memory
+    /// and the externalization cost of the events an embedder isn't
+    /// interested in (e.g. a simulation service). Diagnostic events are not
+    /// filtered.
+    ///
+    /// Filtering is not metered, but dropping events changes the cost of
+    /// externalizing them, so it must not be used when the budget
+    /// consumption has to match the one of other hosts.
+    pub fn set_event_filter(&self, filter: Option<EventFilter>) -> Result<(), HostError> {
+        *self.try_borrow_event_filter_mut()? = filter;
+        Ok(())
+    }
+
+    /// Returns the number of events dropped by the [`EventFilter`] so far,
+    /// including the events of calls that have failed.
+    pub fn filtered_event_count(&self) -> Result<u64, HostError> {
+        Ok(*self.try_borrow_filtered_event_count()?)
+    }
+
+    // Returns whether an event with `topics` emitted by the current contract
+    // passes the event filter, counting it if it doesn't.
+    pub(crate) fn event_passes_filter(&self, topics: VecObject) -> Result<bool, HostError> {
+        let filter = match self.try_borrow_event_filter()?.as_ref() {
+            Some(filter) => filter.clone(),
+            None => return Ok(true),
+        };
+        let passes = self
+            .as_budget()
+            .with_free_budget(|| self.event_matches(&filter, topics))?;
+        if !passes {
+            let mut count = self.try_borrow_filtered_event_count_mut()?;
+            *count = count.saturating_add(1);
+        }
+        Ok(passes)
+    }
+
+    fn event_matches(&self, filter: &EventFilter, topics: VecObject) -> Result<bool, HostError> {
+        if !filter.contract_ids.is_empty() {
+            match self.get_current_contract_id_opt_internal()? {
+                Some(id) if filter.contract_ids.contains(&id) => (),
+                _ => return Ok(false),
+            }
+        }
+        if filter.topic_prefixes.is_empty() {
+            return Ok(true);
+        }
+        let prefix_len = filter
+            .topic_prefixes
+            .iter()
+            .map(Vec::len)
+            .max()
+            .unwrap_or(0);
+        let topics = self.visit_obj(topics, |hv: &HostVec| {
+            Ok(hv.iter().take(prefix_len).copied().collect::<Vec<_>>())
+        })?;
+        let topics = topics
+            .into_iter()
+            .map(|t| self.from_host_val(t))
+            .collect::<Result<Vec<ScVal>, HostError>>()?;
+        Ok(filter
+            .topic_prefixes
+            .iter()
+            .any(|prefix| topics.starts_with(prefix)))
+    }
+}
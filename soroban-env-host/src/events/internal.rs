@@ -125,6 +125,9 @@ pub(crate) enum EventError {
 pub(crate) struct InternalEventsBuffer {
     //the bool keeps track of if the call this event was emitted in failed
     pub(crate) vec: Vec<(InternalEvent, EventError)>,
+    // The cpu instructions consumed when each event of `vec` was recorded,
+    // see `Host::get_events_cpu_insns`.
+    pub(crate) cpu_insns: Vec<u64>,
 }
 
 impl InternalEventsBuffer {
@@ -139,6 +142,10 @@ impl InternalEventsBuffer {
             Vec::<(InternalEvent, EventError)>::charge_bulk_init_cpy(1, budget)?;
         }
         self.vec.push((e, EventError::FromSuccessfulCall));
+        // The cpu instructions counter is only kept for post-hoc analysis of
+        // the events and isn't metered, so that it doesn't change the cost of
+        // recording events.
+        self.cpu_insns.push(budget.get_cpu_insns_consumed()?);
         Ok(())
     }
 
@@ -153,12 +160,36 @@ impl InternalEventsBuffer {
         Ok(())
     }
 
+    /// Returns the contract events (including system events) that have been
+    /// recorded so far and have not been rolled back.
+    pub(crate) fn successful_contract_events(
+        &self,
+        budget: &Budget,
+    ) -> Result<Vec<InternalContractEvent>, HostError> {
+        let mut res = vec![];
+        for e in self.vec.iter() {
+            if let (InternalEvent::Contract(c), EventError::FromSuccessfulCall) = e {
+                // Metering: same as `record`.
+                Vec::<InternalContractEvent>::charge_bulk_init_cpy(1, budget)?;
+                res.push(c.clone());
+            }
+        }
+        Ok(res)
+    }
+
     /// Converts the internal events into their external representation. This should only be called
     /// either when the host is finished (via `try_finish`), or when an error occurs.
     pub fn externalize(&self, host: &Host) -> Result<Events, HostError> {
+        self.externalize_from(host, 0)
+    }
+
+    /// Converts the internal events recorded after the first `start` ones
+    /// into their external representation.
+    pub(crate) fn externalize_from(&self, host: &Host, start: usize) -> Result<Events, HostError> {
         let vec: Result<Vec<HostEvent>, HostError> = self
             .vec
             .iter()
+            .skip(start)
             .map(|e| match &e.0 {
                 InternalEvent::Contract(c) => {
                     // Metering: we use the cost of instantiating a size=1 `Vec` as an estimate
@@ -0,0 +1,106 @@
+//! Limits on the number of contract events that each contract may emit in an
+//! invocation, see [`Host::set_contract_event_limits`].
+
+use soroban_env_common::{
+    xdr::{Hash, ScErrorCode, ScErrorType},
+    EnvBase, Symbol, TryFromVal, U32Val,
+};
+
+use crate::{host::metered_map::MeteredOrdMap, Host, HostError};
+
+const EVENTS_TRUNCATED_TOPIC: &str = "events_truncated";
+
+/// What happens to the contract events a contract emits past its limit, see
+/// [`ContractEventLimits`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum EventLimitPolicy {
+    /// Emitting the event fails with an `(Events, ExceededLimit)` error.
+    #[default]
+    Error,
+    /// The event is dropped. The first dropped event of a contract is replaced
+    /// with a system event with the topic `"events_truncated"` and the limit
+    /// as data, so that the consumers of the events can tell they're
+    /// incomplete.
+    Truncate,
+}
+
+/// Maximum number of contract events each contract may emit in a single
+/// invocation, see [`Host::set_contract_event_limits`].
+///
+/// The limit applies to every contract separately, so that a noisy contract
+/// deep in the call tree can't crowd out the events of the contracts calling
+/// it. System events and diagnostic events don't count against it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ContractEventLimits {
+    pub max_events_per_contract: u32,
+    pub policy: EventLimitPolicy,
+}
+
+impl Default for ContractEventLimits {
+    fn default() -> Self {
+        Self {
+            max_events_per_contract: u32::MAX,
+            policy: EventLimitPolicy::default(),
+        }
+    }
+}
+
+/// The number of contract events emitted by each contract during the current
+/// invocation, including the dropped ones.
+pub(crate) type ContractEventCounts = MeteredOrdMap<Hash, u32, Host>;
+
+impl Host {
+    /// Sets the maximum number of contract events each contract may emit in
+    /// a single invocation, and what happens to the events past it. The
+    /// events of calls that are rolled back don't count against the limit.
+    pub fn set_contract_event_limits(&self, limits: ContractEventLimits) -> Result<(), HostError> {
+        *self.try_borrow_contract_event_limits_mut()? = limits;
+        Ok(())
+    }
+
+    /// Returns the limits on the contract events of each contract.
+    pub fn contract_event_limits(&self) -> Result<ContractEventLimits, HostError> {
+        Ok(*self.try_borrow_contract_event_limits()?)
+    }
+
+    // Counts a contract event emitted by the current contract against its
+    // limit. Returns whether the event should be recorded.
+    pub(crate) fn count_contract_event(&self) -> Result<bool, HostError> {
+        let limits = self.contract_event_limits()?;
+        if limits.max_events_per_contract == u32::MAX {
+            return Ok(true);
+        }
+        let Some(id) = self.get_current_contract_id_opt_internal()? else {
+            return Ok(true);
+        };
+        let count = self
+            .try_borrow_contract_event_counts()?
+            .get(&id, self)?
+            .copied()
+            .unwrap_or(0);
+        let max = limits.max_events_per_contract;
+        if count >= max && limits.policy == EventLimitPolicy::Error {
+            return Err(self.err(
+                ScErrorType::Events,
+                ScErrorCode::ExceededLimit,
+                "contract has exceeded its limit of events per invocation",
+                &[U32Val::from(max).to_val()],
+            ));
+        }
+        if count <= max {
+            let counts = self
+                .try_borrow_contract_event_counts()?
+                .insert(id, count + 1, self)?;
+            *self.try_borrow_contract_event_counts_mut()? = counts;
+        }
+        if count == max {
+            self.system_event(
+                self.vec_new_from_slice(&[
+                    Symbol::try_from_val(self, &EVENTS_TRUNCATED_TOPIC)?.into()
+                ])?,
+                U32Val::from(max).to_val(),
+            )?;
+        }
+        Ok(count < max)
+    }
+}
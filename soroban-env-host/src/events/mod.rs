@@ -1,22 +1,29 @@
 pub(crate) mod diagnostic;
+mod filter;
 mod internal;
+mod limits;
 pub(crate) mod system_events;
 
-pub(crate) use internal::{EventError, InternalEventsBuffer};
 #[cfg(test)]
-pub(crate) use internal::{InternalDiagnosticArg, InternalDiagnosticEvent};
+pub(crate) use internal::InternalDiagnosticEvent;
+pub(crate) use internal::{EventError, InternalDiagnosticArg, InternalEventsBuffer};
 // expose them as pub use for benches
+pub use filter::EventFilter;
 pub use internal::{InternalContractEvent, InternalEvent};
+pub(crate) use limits::ContractEventCounts;
+pub use limits::{ContractEventLimits, EventLimitPolicy};
 use soroban_env_common::{
     num::{i256_from_pieces, u256_from_pieces},
     xdr::{
-        ContractEventBody, ContractEventType, ContractExecutable, PublicKey::PublicKeyTypeEd25519,
-        ScAddress, ScContractInstance, ScVal,
+        ContractEventBody, ContractEventType, ContractExecutable, Hash,
+        PublicKey::PublicKeyTypeEd25519, ScAddress, ScContractInstance, ScError, ScSpecEntry,
+        ScSpecUdtUnionCaseV0, ScVal,
     },
     Error, Val, VecObject,
 };
+use std::{collections::HashMap, rc::Rc};
 
-use crate::{budget::AsBudget, Host, HostError};
+use crate::{budget::AsBudget, host::metered_xdr::metered_write_xdr, Host, HostError};
 
 /// The external representation of a host event.
 #[derive(Clone, Debug)]
@@ -35,7 +42,109 @@ fn display_address(addr: &ScAddress, f: &mut std::fmt::Formatter<'_>) -> std::fm
     }
 }
 
-fn display_scval(scv: &ScVal, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+/// Contract spec entries registered with
+/// [`Host::register_contract_type_specs`], keyed by the contract id.
+pub(crate) type ContractTypeSpecs = HashMap<Hash, Rc<[ScSpecEntry]>>;
+
+fn is_symbol(val: &ScVal, name: &[u8]) -> bool {
+    matches!(val, ScVal::Symbol(sym) if sym.0.as_slice() == name)
+}
+
+// Displays `scv` as a value of one of the user-defined types in `specs`, if
+// its shape matches one of them: structs are maps keyed by the field names,
+// unions are vectors starting with the case name and errors are contract
+// errors with one of the error enum values. Returns `None` if nothing
+// matches.
+fn display_udt(
+    scv: &ScVal,
+    specs: &[ScSpecEntry],
+    f: &mut std::fmt::Formatter<'_>,
+) -> Option<std::fmt::Result> {
+    match scv {
+        ScVal::Map(Some(pairs)) => {
+            let name = specs.iter().find_map(|spec| match spec {
+                ScSpecEntry::UdtStructV0(st)
+                    if st.fields.len() == pairs.len()
+                        && pairs.iter().all(|e| {
+                            st.fields
+                                .iter()
+                                .any(|field| is_symbol(&e.key, field.name.as_slice()))
+                        }) =>
+                {
+                    Some(&st.name)
+                }
+                _ => None,
+            })?;
+            let mut write_struct = || {
+                write!(f, "{} {{ ", name)?;
+                for (i, e) in pairs.iter().enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    display_scval(&e.key, specs, f)?;
+                    write!(f, ": ")?;
+                    display_scval(&e.val, specs, f)?;
+                }
+                write!(f, " }}")
+            };
+            Some(write_struct())
+        }
+        ScVal::Vec(Some(vec)) => {
+            let first = vec.first()?;
+            let (union_name, case_name) = specs.iter().find_map(|spec| {
+                let ScSpecEntry::UdtUnionV0(union) = spec else {
+                    return None;
+                };
+                union.cases.iter().find_map(|case| match case {
+                    ScSpecUdtUnionCaseV0::VoidV0(case)
+                        if vec.len() == 1 && is_symbol(first, case.name.as_slice()) =>
+                    {
+                        Some((&union.name, &case.name))
+                    }
+                    ScSpecUdtUnionCaseV0::TupleV0(case)
+                        if vec.len() == case.type_.len() + 1
+                            && is_symbol(first, case.name.as_slice()) =>
+                    {
+                        Some((&union.name, &case.name))
+                    }
+                    _ => None,
+                })
+            })?;
+            let mut write_union = || {
+                write!(f, "{}::{}", union_name, case_name)?;
+                if vec.len() == 1 {
+                    return Ok(());
+                }
+                write!(f, "(")?;
+                for (i, e) in vec.iter().skip(1).enumerate() {
+                    if i != 0 {
+                        write!(f, ", ")?;
+                    }
+                    display_scval(e, specs, f)?;
+                }
+                write!(f, ")")
+            };
+            Some(write_union())
+        }
+        ScVal::Error(ScError::Contract(code)) => specs.iter().find_map(|spec| {
+            let ScSpecEntry::UdtErrorEnumV0(error_enum) = spec else {
+                return None;
+            };
+            let case = error_enum.cases.iter().find(|case| case.value == *code)?;
+            Some(write!(f, "{}::{}", error_enum.name, case.name))
+        }),
+        _ => None,
+    }
+}
+
+fn display_scval(
+    scv: &ScVal,
+    specs: &[ScSpecEntry],
+    f: &mut std::fmt::Formatter<'_>,
+) -> std::fmt::Result {
+    if let Some(res) = display_udt(scv, specs, f) {
+        return res;
+    }
     match scv {
         ScVal::Bool(v) => write!(f, "{}", v),
         ScVal::Void => write!(f, "Void"),
@@ -68,7 +177,7 @@ fn display_scval(scv: &ScVal, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Resu
                 if i != 0 {
                     write!(f, ", ")?;
                 }
-                display_scval(e, f)?;
+                display_scval(e, specs, f)?;
             }
             write!(f, "]")
         }
@@ -79,9 +188,9 @@ fn display_scval(scv: &ScVal, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Resu
                 if i != 0 {
                     write!(f, ", ")?;
                 }
-                display_scval(&e.key, f)?;
+                display_scval(&e.key, specs, f)?;
                 write!(f, ": ")?;
-                display_scval(&e.val, f)?;
+                display_scval(&e.val, specs, f)?;
             }
             write!(f, "}}")
         }
@@ -103,37 +212,124 @@ fn display_scval(scv: &ScVal, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Resu
     }
 }
 
-impl core::fmt::Display for HostEvent {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        if self.failed_call {
-            write!(f, "[Failed {} Event (not emitted)] ", self.event.type_)?;
-        } else {
-            write!(f, "[{} Event] ", self.event.type_)?;
-        }
-        match &self.event.contract_id {
-            None => (),
-            Some(hash) => write!(f, "contract:{}, ", *hash)?,
-        }
-        match &self.event.body {
-            ContractEventBody::V0(ceb) => {
-                write!(f, "topics:[")?;
-                for (i, topic) in ceb.topics.iter().enumerate() {
-                    if i != 0 {
-                        write!(f, ", ")?;
-                    }
-                    display_scval(topic, f)?;
+fn display_event(
+    event: &HostEvent,
+    specs: &[ScSpecEntry],
+    f: &mut std::fmt::Formatter<'_>,
+) -> std::fmt::Result {
+    if event.failed_call {
+        write!(f, "[Failed {} Event (not emitted)] ", event.event.type_)?;
+    } else {
+        write!(f, "[{} Event] ", event.event.type_)?;
+    }
+    match &event.event.contract_id {
+        None => (),
+        Some(hash) => write!(f, "contract:{}, ", *hash)?,
+    }
+    match &event.event.body {
+        ContractEventBody::V0(ceb) => {
+            write!(f, "topics:[")?;
+            for (i, topic) in ceb.topics.iter().enumerate() {
+                if i != 0 {
+                    write!(f, ", ")?;
                 }
-                write!(f, "], data:")?;
-                display_scval(&ceb.data, f)
+                display_scval(topic, specs, f)?;
             }
+            write!(f, "], data:")?;
+            display_scval(&ceb.data, specs, f)
         }
     }
 }
 
+impl core::fmt::Display for HostEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        display_event(self, &[], f)
+    }
+}
+
+/// Displays a [`HostEvent`] with the values of user-defined types rendered
+/// according to their contract spec definitions, see
+/// [`HostEvent::display_with_type_specs`].
+pub struct HostEventDisplay<'a> {
+    event: &'a HostEvent,
+    specs: &'a [ScSpecEntry],
+}
+
+impl core::fmt::Display for HostEventDisplay<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        display_event(self.event, self.specs, f)
+    }
+}
+
+impl HostEvent {
+    /// Returns a [`Display`](core::fmt::Display) implementation that renders
+    /// the maps and vectors matching the structs and unions defined in
+    /// `specs` with their type, field and case names (and similarly for the
+    /// contract errors defined in `specs`).
+    pub fn display_with_type_specs<'a>(&'a self, specs: &'a [ScSpecEntry]) -> HostEventDisplay<'a> {
+        HostEventDisplay { event: self, specs }
+    }
+
+    // Returns the display of the event using the specs registered for the
+    // contract that has emitted it.
+    pub(crate) fn display_with_registered_specs<'a>(
+        &'a self,
+        type_specs: &'a ContractTypeSpecs,
+    ) -> HostEventDisplay<'a> {
+        let specs = self
+            .event
+            .contract_id
+            .as_ref()
+            .and_then(|id| type_specs.get(id))
+            .map(|specs| specs.as_ref())
+            .unwrap_or(&[]);
+        self.display_with_type_specs(specs)
+    }
+}
+
 /// The external representation of events in the chronological order.
 #[derive(Clone, Debug, Default)]
 pub struct Events(pub Vec<HostEvent>);
 
+/// A [`HostEvent`] with its XDR body unpacked, as yielded by iterating over
+/// [`Events`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DecodedEvent {
+    pub contract_id: Option<Hash>,
+    pub event_type: ContractEventType,
+    pub topics: Vec<ScVal>,
+    pub data: ScVal,
+    pub in_successful_call: bool,
+}
+
+impl From<HostEvent> for DecodedEvent {
+    fn from(he: HostEvent) -> Self {
+        let ContractEventBody::V0(body) = he.event.body;
+        DecodedEvent {
+            contract_id: he.event.contract_id,
+            event_type: he.event.type_,
+            topics: body.topics.into_vec(),
+            data: body.data,
+            in_successful_call: !he.failed_call,
+        }
+    }
+}
+
+/// Iterator over [`Events`] that decodes every event only when it's reached.
+pub type DecodedEvents =
+    std::iter::Map<std::vec::IntoIter<HostEvent>, fn(HostEvent) -> DecodedEvent>;
+
+impl IntoIterator for Events {
+    type Item = DecodedEvent;
+    type IntoIter = DecodedEvents;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0
+            .into_iter()
+            .map(DecodedEvent::from as fn(HostEvent) -> DecodedEvent)
+    }
+}
+
 impl Host {
     pub(crate) fn with_events_mut<F, U>(&self, f: F) -> Result<U, HostError>
     where
@@ -146,6 +342,74 @@ impl Host {
         self.try_borrow_events()?.externalize(self)
     }
 
+    /// Returns the cpu instructions consumed by the budget when each of the
+    /// events returned by [`Host::get_events`] was emitted, in the same
+    /// order. This allows aligning the events with the budget consumption of
+    /// the calls emitting them.
+    pub fn get_events_cpu_insns(&self) -> Result<Vec<u64>, HostError> {
+        Ok(self.try_borrow_events()?.cpu_insns.clone())
+    }
+
+    /// Returns a copy of the events recorded so far, skipping the first
+    /// `start` ones. Unlike [`Host::try_finish`], this can be called while
+    /// the host is still in use, e.g. by embedders that stream the events
+    /// of long-running invocations: passing the number of events already
+    /// received as `start` only externalizes (and charges for) the new ones.
+    ///
+    /// Events of calls that are still running are not marked as failed, even
+    /// though they will be if the calls fail later on.
+    pub fn snapshot_events(&self, start: usize) -> Result<Events, HostError> {
+        self.try_borrow_events()?.externalize_from(self, start)
+    }
+
+    /// Returns the total size in bytes of the XDR of the contract and system
+    /// events emitted so far, i.e. the size they will count for against the
+    /// network's contract events size limit when externalized. Events of
+    /// rolled back calls are excluded; events of calls that are still running
+    /// are included, even though they will be excluded if the calls fail.
+    ///
+    /// This serializes all the counted events, so its cost is proportional to
+    /// their size.
+    pub fn contract_events_size(&self) -> Result<u64, HostError> {
+        // The events are cloned out of the buffer so that converting them (and
+        // recording diagnostics on errors) doesn't run into the borrow.
+        let events = self
+            .try_borrow_events()?
+            .successful_contract_events(self.as_budget())?;
+        let mut size: u64 = 0;
+        let mut buf = vec![];
+        for e in events.iter() {
+            buf.clear();
+            metered_write_xdr(self.as_budget(), &e.to_xdr(self)?, &mut buf)?;
+            size = size.saturating_add(buf.len() as u64);
+        }
+        Ok(size)
+    }
+
+    /// Registers the spec entries of the contract `contract_id`, so that the
+    /// values of the user-defined types in the events emitted by the
+    /// contract are rendered with their type, field and case names by
+    /// [`Host::render_event`] and in the event logs of [`HostError`]s.
+    ///
+    /// Entries other than structs, unions and error enums are ignored.
+    /// Registering the entries of a contract again replaces them.
+    pub fn register_contract_type_specs(
+        &self,
+        contract_id: Hash,
+        specs: Vec<ScSpecEntry>,
+    ) -> Result<(), HostError> {
+        self.try_borrow_contract_type_specs_mut()?
+            .insert(contract_id, specs.into());
+        Ok(())
+    }
+
+    /// Renders `event` for diagnostic output, using the spec entries
+    /// registered for the contract that has emitted it.
+    pub fn render_event(&self, event: &HostEvent) -> Result<String, HostError> {
+        let type_specs = self.try_borrow_contract_type_specs()?;
+        Ok(event.display_with_registered_specs(&type_specs).to_string())
+    }
+
     // Records a contract event.
     pub(crate) fn record_contract_event(
         &self,
@@ -153,12 +417,25 @@ impl Host {
         topics: VecObject,
         data: Val,
     ) -> Result<(), HostError> {
+        if type_ == ContractEventType::Contract && !self.count_contract_event()? {
+            return Ok(());
+        }
+        if !self.event_passes_filter(topics)? {
+            return Ok(());
+        }
         let ce = InternalContractEvent {
             type_,
             contract_id: self.bytesobj_from_internal_contract_id()?,
             topics,
             data,
         };
+        #[cfg(any(test, feature = "testutils"))]
+        {
+            self.record_object_leak_roots(&[topics.to_val(), data])?;
+            if let Some(id) = ce.contract_id {
+                self.record_object_leak_roots(&[id.to_val()])?;
+            }
+        }
         self.with_events_mut(|events| {
             Ok(events.record(InternalEvent::Contract(ce), self.as_budget()))
         })?
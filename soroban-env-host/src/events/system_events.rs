@@ -1,7 +1,12 @@
-use crate::{native_contract::common_types::ContractExecutable, Host, HostError};
+use crate::{
+    host::metered_clone::MeteredClone, native_contract::common_types::ContractExecutable, Host,
+    HostError,
+};
 use soroban_env_common::{xdr, EnvBase, Symbol, TryFromVal, TryIntoVal, Val, VecObject};
 
 const CONTRACT_EXECUTABLE_UPDATE_TOPIC: &str = "executable_update";
+const UPGRADE_TIMELOCK_SET_TOPIC: &str = "upgrade_timelock_set";
+const UPGRADE_ANNOUNCED_TOPIC: &str = "upgrade_announced";
 
 impl Host {
     pub fn system_event(&self, topics: VecObject, data: Val) -> Result<(), HostError> {
@@ -28,4 +33,35 @@ impl Host {
         )?;
         Ok(())
     }
+
+    // Emits a system event for setting the upgrade timelock of the contract.
+    // The only event topic is "upgrade_timelock_set" and the data is the
+    // timelock delay in ledgers.
+    pub(crate) fn emit_upgrade_timelock_set_event(&self, delay: u32) -> Result<(), HostError> {
+        self.system_event(
+            self.vec_new_from_slice(&[
+                Symbol::try_from_val(self, &UPGRADE_TIMELOCK_SET_TOPIC)?.into()
+            ])?,
+            delay.into(),
+        )
+    }
+
+    // Emits a system event for announcing an update of the contract Wasm
+    // under the upgrade timelock. The topics are "upgrade_announced" and the
+    // announced executable (encoded as `ContractExecutable` above), and the
+    // data is the first ledger at which the update can be activated.
+    pub(crate) fn emit_upgrade_announced_event(
+        &self,
+        wasm_hash: &xdr::Hash,
+        activation_ledger: u32,
+    ) -> Result<(), HostError> {
+        let executable = xdr::ContractExecutable::Wasm(wasm_hash.metered_clone(self)?);
+        self.system_event(
+            self.vec_new_from_slice(&[
+                Symbol::try_from_val(self, &UPGRADE_ANNOUNCED_TOPIC)?.into(),
+                ContractExecutable::from_xdr(self, &executable)?.try_into_val(self)?,
+            ])?,
+            activation_ledger.into(),
+        )
+    }
 }
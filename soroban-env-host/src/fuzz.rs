@@ -0,0 +1,157 @@
+//! Helpers for fuzzing the conversion of [`ScVal`]s to host values and back,
+//! so that downstream fuzzers can share a single harness.
+//!
+//! This module is only available with the `"fuzz"` feature.
+
+use core::cmp::Ordering;
+
+use arbitrary::{Arbitrary, Unstructured};
+
+use crate::{
+    budget::Budget,
+    xdr::{
+        Duration, Int128Parts, Int256Parts, ScAddress, ScBytes, ScError, ScMap, ScMapEntry,
+        ScString, ScSymbol, ScVal, ScVec, TimePoint, UInt128Parts, UInt256Parts,
+    },
+    Compare, Host, HostError,
+};
+
+const SYMBOL_CHARS: &[u8] = b"_0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+const MAX_SYMBOL_LEN: usize = 32;
+
+// Indices of the kinds of values `ScValGenerator` produces. Kinds up to
+// `LAST_SCALAR_KIND` are scalars, the ones after it are containers.
+const LAST_SCALAR_KIND: u32 = 16;
+const LAST_KIND: u32 = 18;
+
+/// Generator of arbitrary [`ScVal`]s that are valid inputs for the host, with
+/// configurable bounds on their shape.
+#[derive(Clone, Debug)]
+pub struct ScValGenerator {
+    /// Maximum nesting depth of vectors and maps. Values at this depth are
+    /// always scalars.
+    pub max_depth: u32,
+    /// Maximum number of elements of a single vector or map.
+    pub max_container_len: usize,
+    /// Maximum length of a single bytes, string or symbol payload.
+    pub max_payload_len: usize,
+}
+
+impl Default for ScValGenerator {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_container_len: 8,
+            max_payload_len: 64,
+        }
+    }
+}
+
+impl ScValGenerator {
+    /// Generates an [`ScVal`] that the host accepts, i.e. one that contains no
+    /// ledger keys or contract instances and whose maps have sorted, unique
+    /// keys.
+    pub fn generate(&self, u: &mut Unstructured<'_>) -> arbitrary::Result<ScVal> {
+        let budget = Budget::default();
+        budget
+            .reset_unlimited()
+            .map_err(|_| arbitrary::Error::IncorrectFormat)?;
+        self.generate_at_depth(u, &budget, 0)
+    }
+
+    fn generate_at_depth(
+        &self,
+        u: &mut Unstructured<'_>,
+        budget: &Budget,
+        depth: u32,
+    ) -> arbitrary::Result<ScVal> {
+        let last_kind = if depth < self.max_depth {
+            LAST_KIND
+        } else {
+            LAST_SCALAR_KIND
+        };
+        let v = match u.int_in_range(0..=last_kind)? {
+            0 => ScVal::Bool(u.arbitrary()?),
+            1 => ScVal::Void,
+            2 => ScVal::Error(ScError::arbitrary(u)?),
+            3 => ScVal::U32(u.arbitrary()?),
+            4 => ScVal::I32(u.arbitrary()?),
+            5 => ScVal::U64(u.arbitrary()?),
+            6 => ScVal::I64(u.arbitrary()?),
+            7 => ScVal::Timepoint(TimePoint(u.arbitrary()?)),
+            8 => ScVal::Duration(Duration(u.arbitrary()?)),
+            9 => ScVal::U128(UInt128Parts::arbitrary(u)?),
+            10 => ScVal::I128(Int128Parts::arbitrary(u)?),
+            11 => ScVal::U256(UInt256Parts::arbitrary(u)?),
+            12 => ScVal::I256(Int256Parts::arbitrary(u)?),
+            13 => ScVal::Bytes(ScBytes(to_xdr(self.payload(u)?)?)),
+            14 => ScVal::String(ScString(to_xdr(self.payload(u)?)?)),
+            15 => ScVal::Symbol(ScSymbol(to_xdr(self.symbol(u)?)?)),
+            16 => ScVal::Address(ScAddress::arbitrary(u)?),
+            17 => {
+                let len = u.int_in_range(0..=self.max_container_len)?;
+                let mut vec = Vec::with_capacity(len);
+                for _ in 0..len {
+                    vec.push(self.generate_at_depth(u, budget, depth + 1)?);
+                }
+                ScVal::Vec(Some(ScVec(to_xdr(vec)?)))
+            }
+            _ => {
+                let len = u.int_in_range(0..=self.max_container_len)?;
+                let mut entries = Vec::with_capacity(len);
+                for _ in 0..len {
+                    entries.push(ScMapEntry {
+                        key: self.generate_at_depth(u, budget, depth + 1)?,
+                        val: self.generate_at_depth(u, budget, depth + 1)?,
+                    });
+                }
+                // Maps are only accepted with keys in the host's sort order.
+                let mut err = None;
+                entries.sort_by(|a, b| {
+                    budget.compare(&a.key, &b.key).unwrap_or_else(|e| {
+                        err.get_or_insert(e);
+                        Ordering::Equal
+                    })
+                });
+                if err.is_some() {
+                    return Err(arbitrary::Error::IncorrectFormat);
+                }
+                entries
+                    .dedup_by(|a, b| matches!(budget.compare(&a.key, &b.key), Ok(Ordering::Equal)));
+                ScVal::Map(Some(ScMap(to_xdr(entries)?)))
+            }
+        };
+        Ok(v)
+    }
+
+    fn payload(&self, u: &mut Unstructured<'_>) -> arbitrary::Result<Vec<u8>> {
+        let len = u.int_in_range(0..=self.max_payload_len)?;
+        Ok(u.bytes(len)?.to_vec())
+    }
+
+    fn symbol(&self, u: &mut Unstructured<'_>) -> arbitrary::Result<Vec<u8>> {
+        let len = u.int_in_range(0..=self.max_payload_len.min(MAX_SYMBOL_LEN))?;
+        let mut symbol = Vec::with_capacity(len);
+        for _ in 0..len {
+            symbol.push(*u.choose(SYMBOL_CHARS)?);
+        }
+        Ok(symbol)
+    }
+}
+
+fn to_xdr<T, U: TryFrom<Vec<T>>>(v: Vec<T>) -> arbitrary::Result<U> {
+    U::try_from(v).map_err(|_| arbitrary::Error::IncorrectFormat)
+}
+
+/// Converts `v` to a host value and back with metering enabled, and panics if
+/// the result differs from `v`. Errors raised by the conversions, such as
+/// running out of budget, are returned to the caller.
+pub fn assert_scval_round_trip(host: &Host, v: &ScVal) -> Result<(), HostError> {
+    let val = host.to_host_val(v)?;
+    let round_tripped = host.from_host_val(val)?;
+    assert_eq!(
+        &round_tripped, v,
+        "ScVal changed after a round trip through the host"
+    );
+    Ok(())
+}
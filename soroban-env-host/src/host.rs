@@ -2,63 +2,297 @@
 #![allow(dead_code)]
 
 use core::{cell::RefCell, cmp::Ordering, fmt::Debug};
-use std::rc::Rc;
+use std::{collections::BTreeMap, rc::Rc};
 
 use crate::{
-    auth::AuthorizationManager,
-    budget::{AsBudget, Budget},
-    events::{diagnostic::DiagnosticLevel, Events, InternalEventsBuffer},
-    host_object::{HostMap, HostObject, HostObjectType, HostVec},
+    auth::{AuthorizationManager, NonRootAuthExemptions},
+    budget::{AsBudget, Budget, BudgetChargeBatch},
+    events::{
+        diagnostic::DiagnosticLevel, ContractEventCounts, ContractEventLimits, ContractTypeSpecs,
+        EventFilter, Events, InternalDiagnosticArg, InternalEventsBuffer,
+    },
+    host_object::{HostMap, HostObject, HostObjectType, HostVec, ObjectSizeLimits},
     impl_bignum_host_fns_rhs_u32, impl_wrapping_obj_from_num, impl_wrapping_obj_to_num,
     num::*,
-    storage::Storage,
+    storage::{AccessType, CodeStore, Storage, StorageAccessStats},
     xdr::{
         int128_helpers, AccountId, Asset, ContractCostType, ContractEventType, ContractExecutable,
-        CreateContractArgs, Duration, Hash, LedgerEntryData, PublicKey, ScAddress, ScBytes,
-        ScErrorType, ScString, ScSymbol, ScVal, TimePoint,
+        CreateContractArgs, Duration, Hash, LedgerEntry, LedgerEntryData, LedgerKey, PublicKey,
+        ScAddress, ScBytes, ScErrorType, ScString, ScSymbol, ScVal, TimePoint,
     },
     AddressObject, Bool, BytesObject, ConversionError, Error, I128Object, I256Object, MapObject,
     StorageType, StringObject, SymbolObject, SymbolSmall, SymbolStr, TryFromVal, U128Object,
     U256Object, U32Val, U64Val, VecObject, VmCaller, VmCallerEnv, Void, I256, U256,
 };
 
+use crate::vm::ModuleCache;
 use crate::Vm;
 use crate::{EnvBase, Object, Symbol, Val};
 
+#[cfg(any(test, feature = "testutils"))]
+use crate::host_object::HandlePermutation;
+
+pub(crate) mod acl;
+mod amount;
+pub use amount::{AmountRounding, CLASSIC_ASSET_DECIMALS};
+mod builder;
+pub use builder::HostBuilder;
+mod canonical;
+pub use canonical::CANONICAL_ENCODING_PREFIX;
 mod comparison;
+#[cfg(any(test, feature = "testutils"))]
+pub use comparison::ObjectComparator;
+mod compression;
 mod conversion;
+pub use conversion::{ScValLimitExceeded, ScValLimits};
 pub(crate) mod crypto;
 mod data_helper;
-mod declared_size;
+pub(crate) mod data_locks;
+pub(crate) mod declared_size;
+#[cfg(any(test, feature = "differential"))]
+mod differential;
+#[cfg(any(test, feature = "differential"))]
+pub use differential::{
+    run_differential, DifferentialReport, FuelConsumption, InvocationOutcome, VmConfig,
+};
 pub(crate) mod error;
+mod error_context;
+pub use error_context::ErrorContext;
 pub(crate) mod frame;
 pub(crate) mod ledger_info_helper;
+pub use ledger_info_helper::LedgerInfoProvider;
 mod lifecycle;
 mod mem_helper;
+mod memo;
 pub(crate) mod metered_clone;
 pub(crate) mod metered_map;
 pub(crate) mod metered_vector;
 pub(crate) mod metered_xdr;
+pub use metered_xdr::XdrReadLimits;
+mod metrics;
+pub use metrics::{MetricsSink, StorageOp};
 mod num;
+#[cfg(any(test, feature = "testutils"))]
+mod object_leaks;
+mod oracle;
+#[cfg(any(test, feature = "testutils"))]
+pub use object_leaks::{LeakedObject, ObjectAllocationSite};
 mod prng;
-pub use prng::{Seed, SEED_BYTES};
+mod render;
+pub use prng::{PrngDraw, PrngMode, PrngTranscript, Seed, SEED_BYTES};
 mod validity;
-pub use error::HostError;
+pub use error::{ErrorCause, HostError};
 use soroban_env_common::xdr::{ContractIdPreimage, ContractIdPreimageFromAddress, ScErrorCode};
 
 use self::{
     frame::{Context, ContractReentryMode},
+    ledger_info_helper::LedgerSource,
+    memo::Memo,
     prng::Prng,
 };
 use self::{
-    metered_clone::{MeteredClone, MeteredContainer},
+    metered_clone::{MeteredAlloc, MeteredClone, MeteredContainer},
     metered_xdr::metered_write_xdr,
 };
 use crate::impl_bignum_host_fns;
 use crate::Compare;
-#[cfg(any(test, feature = "testutils"))]
-pub use frame::ContractFunctionSet;
 pub(crate) use frame::Frame;
+#[cfg(any(test, feature = "testutils"))]
+pub use frame::{ContractFunctionSet, HostFnInterposer, INTERPOSABLE_HOST_FNS};
+pub use frame::{HostFunctionOp, HostFunctionOpResult};
+
+/// Minimum ledger protocol version at which the `is_source_account` and
+/// `get_source_account` host functions are available.
+pub(crate) const SOURCE_ACCOUNT_FNS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `set_fee_refund` host function
+/// is available.
+pub(crate) const FEE_REFUND_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which failed `try_call` sub-calls
+/// invoke the `__on_error` handler of the calling contract. The handler call
+/// changes the cost of failed sub-calls, so it is only enabled from the
+/// protocol following the current one.
+pub(crate) const ON_ERROR_HANDLER_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which authenticating a classic account
+/// requires at least one valid signature, even when its medium threshold is 0.
+pub(crate) const ACCOUNT_MIN_ONE_SIGNATURE_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which equal symbol objects share one
+/// handle, see [`Host::intern_symbol`].
+pub(crate) const SYMBOL_INTERNING_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the ristretto255 host functions
+/// are available.
+pub(crate) const RISTRETTO255_FNS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `verify_sig_ecdsa_secp256r1`
+/// host function is available.
+pub(crate) const SECP256R1_VERIFY_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `set_upgrade_timelock` host
+/// function is available and `update_current_contract_wasm` honors the
+/// upgrade timelock of the contract.
+pub(crate) const UPGRADE_TIMELOCK_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `put_contract_data_locked`
+/// host function is available.
+pub(crate) const DATA_LOCKS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the built-in token contract can
+/// be paused. Checking the pause switch reads the contract instance on every
+/// transfer and burn, which changes their cost.
+pub(crate) const SAC_PAUSE_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the admin of the built-in token
+/// contract can be transferred in two steps with `propose_admin` and
+/// `accept_admin`. Before it, `set_admin` is the only way to change the admin
+/// and doesn't touch the pending transfer.
+pub(crate) const SAC_ADMIN_TRANSFER_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the built-in token contract
+/// supports periodic allowances. `transfer_from` and `burn_from` fall back to
+/// reading the periodic allowance when the regular one doesn't cover the
+/// amount, which changes their footprint.
+pub(crate) const SAC_PERIODIC_ALLOWANCE_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which invoking a function that a Wasm
+/// contract doesn't export fails before instantiating the contract, which
+/// changes the cost of such invocations.
+pub(crate) const EARLY_MISSING_EXPORT_CHECK_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which contracts can be created with the
+/// Wasm hash reserved for the built-in smart wallet, and at which contracts
+/// with that hash are dispatched to it. Before it the hash is an ordinary Wasm
+/// hash.
+pub(crate) const SMART_WALLET_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which contracts can be created with the
+/// Wasm hash reserved for the built-in non-fungible token, and at which
+/// contracts with that hash are dispatched to it. Before it the hash is an
+/// ordinary Wasm hash.
+pub(crate) const NFT_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `claim_idempotency_key` host
+/// function is available.
+pub(crate) const IDEMPOTENCY_KEYS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the
+/// `map_new_from_linear_memory_with_val_keys` host function is available.
+pub(crate) const MAP_FROM_VAL_KEYS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `bytes_append_from_linear_memory`
+/// host function is available.
+pub(crate) const BYTES_APPEND_FROM_LINEAR_MEMORY_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `i128_scale` host function is
+/// available.
+pub(crate) const I128_SCALE_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `verify_multisig_ed25519` host
+/// function is available.
+pub(crate) const MULTISIG_ED25519_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `clone_contract` host function
+/// is available.
+pub(crate) const CLONE_CONTRACT_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `get_own_custom_section` host
+/// function is available.
+pub(crate) const OWN_CUSTOM_SECTION_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `get_wasm_hash_of_contract` host
+/// function is available.
+pub(crate) const WASM_HASH_OF_CONTRACT_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `vec_dedup`, `vec_reverse` and
+/// `vec_contains_sorted` host functions are available.
+pub(crate) const VEC_DEDUP_REVERSE_CONTAINS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `map_range` and
+/// `map_nth_key_range` host functions are available.
+pub(crate) const MAP_RANGE_FNS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `get_written_entries_hash` host
+/// function is available.
+pub(crate) const WRITTEN_ENTRIES_HASH_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `get_contract_events_size` host
+/// function is available.
+pub(crate) const CONTRACT_EVENTS_SIZE_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `u256_sqrt` and `u256_log2` host
+/// functions are available.
+pub(crate) const U256_SQRT_LOG2_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the range-checked conversions
+/// between `i128` and the 256-bit integers are available.
+pub(crate) const I128_256_CONVERSIONS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `map_copy_range_to_linear_memory`
+/// host function is available.
+pub(crate) const MAP_COPY_RANGE_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `instance_storage_size_bytes` and
+/// `instance_storage_entry_count` host functions are available.
+pub(crate) const INSTANCE_STORAGE_STATS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `address_cmp` and
+/// `address_to_hash_id` host functions are available.
+pub(crate) const ADDRESS_CMP_AND_HASH_ID_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `get_classic_offer` and
+/// `get_classic_liquidity_pool` host functions are available.
+pub(crate) const CLASSIC_OFFER_AND_POOL_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `ledger_entry_exists` host
+/// function is available.
+pub(crate) const LEDGER_ENTRY_EXISTS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `memo_put` and `memo_get` host
+/// functions are available.
+pub(crate) const MEMO_FNS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `require_auth_many` host
+/// function is available.
+pub(crate) const REQUIRE_AUTH_MANY_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `rate_limit_check_and_consume`
+/// host function is available.
+pub(crate) const RATE_LIMIT_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `val_to_canonical_bytes` host
+/// function is available.
+pub(crate) const CANONICAL_VAL_BYTES_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `can_put_contract_data` host
+/// function is available.
+pub(crate) const CAN_PUT_CONTRACT_DATA_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `string_new_from_vals` host
+/// function is available.
+pub(crate) const STRING_FROM_VALS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `require_nonnegative_i128` and
+/// `require_positive_i128` host functions are available.
+pub(crate) const REQUIRE_I128_AMOUNT_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `verify_oracle_attestation` host
+/// function is available.
+pub(crate) const ORACLE_ATTESTATION_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `error_to_val_with_context` host
+/// function is available.
+pub(crate) const ERROR_CONTEXT_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `acl_add`, `acl_remove` and
+/// `acl_contains` host functions are available.
+pub(crate) const ACL_FNS_MIN_PROTOCOL: u32 = 21;
+
+/// Minimum ledger protocol version at which the `bytes_decompress_zstd` host
+/// function is available.
+pub(crate) const ZSTD_DECOMPRESSION_MIN_PROTOCOL: u32 = 21;
 
 /// Defines the maximum depth for recursive calls in the host, i.e. `Val` conversion, comparison,
 /// and deep clone, to prevent stack overflow.
@@ -74,6 +308,10 @@ pub(crate) use frame::Frame;
 /// `DEFAULT_HOST_DEPTH_LIMIT` here is set to a smaller value.
 pub const DEFAULT_HOST_DEPTH_LIMIT: u32 = 100;
 
+/// The maximum size of a Wasm custom section that a contract may read via
+/// `get_own_custom_section`.
+pub(crate) const MAX_OWN_CUSTOM_SECTION_SIZE: usize = 64 * 1024;
+
 /// Temporary helper for denoting a slice of guest memory, as formed by
 /// various bytes operations.
 pub(crate) struct VmSlice {
@@ -94,12 +332,115 @@ pub struct LedgerInfo {
     pub max_entry_expiration: u32,
 }
 
+/// Fee refund designated by the top-level contract of an invocation via the
+/// `set_fee_refund` host function, to be paid to the source account of the
+/// transaction by the embedder when the invocation succeeds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeRefund {
+    /// Contract that has designated the refund.
+    pub contract_id: Hash,
+    /// Refund amount in stroops.
+    pub amount: i64,
+}
+
+/// Copies of vectors or maps made by the host functions that "modify" them,
+/// which create a modified copy since host objects are immutable, see
+/// [`ExecutionStats::vec_copies`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ContainerCopyStats {
+    /// Number of containers created by copying an existing one with a change
+    /// applied, e.g. by `vec_push_back` or `map_put`.
+    pub copies: u64,
+    /// Total number of elements (or entries) of the created copies.
+    pub copied_elements: u64,
+    /// Number of the copies that are identical to the container they've been
+    /// copied from, and could have shared it instead. Only the copies that are
+    /// unchanged by construction are counted, e.g. when putting the value
+    /// that's already there, or slicing the whole vector.
+    pub unchanged_copies: u64,
+}
+
+impl ContainerCopyStats {
+    fn record(&mut self, len: usize, unchanged: bool) {
+        self.copies = self.copies.saturating_add(1);
+        self.copied_elements = self.copied_elements.saturating_add(len as u64);
+        if unchanged {
+            self.unchanged_copies = self.unchanged_copies.saturating_add(1);
+        }
+    }
+}
+
+/// High-water marks and totals observed while executing contracts on a
+/// [`Host`], see [`Host::execution_stats`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExecutionStats {
+    /// Maximum depth of the context (frame) stack.
+    pub max_frame_depth: u32,
+    /// Number of host objects created. Host objects are only dropped along
+    /// with the host, so this is also the growth of the object table.
+    pub host_objects_created: u32,
+    /// Maximum CPU instructions charged by a single host function call made
+    /// from a Wasm contract, including any nested contract invocations it
+    /// performs.
+    pub max_host_fn_cpu_insns: u64,
+    /// Maximum memory bytes charged by a single host function call made from
+    /// a Wasm contract, including any nested contract invocations it performs.
+    pub max_host_fn_mem_bytes: u64,
+    /// Total CPU instructions charged for parsing and validating the Wasm
+    /// modules of contracts.
+    pub vm_parse_cpu_insns: u64,
+    /// Total memory bytes charged for parsing and validating the Wasm modules
+    /// of contracts.
+    pub vm_parse_mem_bytes: u64,
+    /// Total CPU instructions charged for instantiating parsed Wasm modules,
+    /// i.e. linking the host functions and setting up the module instances.
+    pub vm_instantiation_cpu_insns: u64,
+    /// Total memory bytes charged for instantiating parsed Wasm modules.
+    pub vm_instantiation_mem_bytes: u64,
+    /// The number of Wasm modules taken from the module cache instead of
+    /// being parsed, see [`Host::cache_module`].
+    pub module_cache_hits: u64,
+    /// The ledger entry accesses performed by each contract while it was at
+    /// the top of the call stack, i.e. excluding the accesses of the
+    /// contracts it has called. Only tracked after
+    /// [`Host::enable_storage_access_stats`].
+    pub contract_storage_access: BTreeMap<Hash, StorageAccessStats>,
+    /// The copies of vectors made by the vector host functions. Only tracked
+    /// after [`Host::enable_container_copy_stats`].
+    pub vec_copies: ContainerCopyStats,
+    /// The copies of maps made by the map host functions. Only tracked after
+    /// [`Host::enable_container_copy_stats`].
+    pub map_copies: ContainerCopyStats,
+}
+
+/// The side effects of a finished [`Host`], see [`Host::try_finish_owned`].
+/// Unlike the outputs of [`Host::try_finish`], this owns all of its data and
+/// is `Send + 'static`, so it can be moved out of the thread that has run the
+/// host.
+#[derive(Debug, Clone, Default)]
+pub struct FinishedHostState {
+    /// Every ledger key accessed by the host, with its access type.
+    pub footprint: Vec<(LedgerKey, AccessType)>,
+    /// The final state of every ledger entry in the footprint along with its
+    /// expiration ledger, or `None` if the entry doesn't exist.
+    pub ledger_entries: Vec<(LedgerKey, Option<(LedgerEntry, Option<u32>)>)>,
+    pub events: Events,
+    pub fee_refund: Option<FeeRefund>,
+    /// See [`Host::freed_contract_data_bytes`].
+    pub freed_contract_data_bytes: u64,
+    pub execution_stats: ExecutionStats,
+    pub cpu_insns_consumed: u64,
+    pub mem_bytes_consumed: u64,
+}
+
 #[derive(Clone, Default)]
 struct HostImpl {
     source_account: RefCell<Option<AccountId>>,
-    ledger: RefCell<Option<LedgerInfo>>,
+    ledger: RefCell<Option<LedgerSource>>,
     objects: RefCell<Vec<HostObject>>,
     storage: RefCell<Storage>,
+    code_store: RefCell<Option<Rc<dyn CodeStore>>>,
+    metrics_sink: RefCell<Option<Rc<dyn MetricsSink>>>,
     context: RefCell<Vec<Context>>,
     // Note: budget is refcounted and is _not_ deep-cloned when you call HostImpl::deep_clone,
     // mainly because it's not really possible to achieve (the same budget is connected to many
@@ -108,15 +449,69 @@ struct HostImpl {
     // FIXME: deep_clone is gone, maybe Budget should not be separately refcounted?
     budget: Budget,
     events: RefCell<InternalEventsBuffer>,
+    contract_event_limits: RefCell<ContractEventLimits>,
+    // The number of contract events emitted by each contract during the
+    // current invocation.
+    contract_event_counts: RefCell<ContractEventCounts>,
+    event_filter: RefCell<Option<EventFilter>>,
+    filtered_event_count: RefCell<u64>,
     authorization_manager: RefCell<AuthorizationManager>,
     diagnostic_level: RefCell<DiagnosticLevel>,
+    host_fn_trace_limits: RefCell<Option<ScValLimits>>,
+    xdr_read_limits: RefCell<XdrReadLimits>,
+    object_size_limits: RefCell<ObjectSizeLimits>,
+    contract_type_specs: RefCell<ContractTypeSpecs>,
     base_prng: RefCell<Option<Prng>>,
+    prng_mode: RefCell<PrngMode>,
+    fee_refund: RefCell<Option<FeeRefund>>,
+    execution_stats: RefCell<ExecutionStats>,
+    // The storage access totals that have already been attributed to the
+    // contracts in `execution_stats`.
+    attributed_storage_access: RefCell<StorageAccessStats>,
+    // Whether the copies of vectors and maps are counted in
+    // `execution_stats`.
+    container_copy_stats_enabled: RefCell<bool>,
+    // Values cached by contracts with `memo_put` during the current
+    // invocation, keyed by the contract id and the cache key.
+    memo: RefCell<Memo>,
+    // The objects of the symbols created so far, so that creating an equal
+    // symbol again returns the existing object.
+    symbol_objects: RefCell<SymbolObjectMap>,
+    // The parsed Wasm modules, reused across the invocations of the host.
+    module_cache: RefCell<ModuleCache>,
     // Note: we're not going to charge metering for testutils because it's out of the scope
     // of what users will be charged for in production -- it's scaffolding for testing a contract,
     // but shouldn't be charged to the contract itself (and will never be compiled-in to
     // production hosts)
     #[cfg(any(test, feature = "testutils"))]
     contracts: RefCell<std::collections::HashMap<Hash, Rc<dyn ContractFunctionSet>>>,
+    // Replacements for host functions called by specific test contracts, keyed
+    // by the contract id and host function name.
+    #[cfg(any(test, feature = "testutils"))]
+    host_fn_interposers:
+        RefCell<std::collections::HashMap<(Hash, &'static str), Rc<dyn HostFnInterposer>>>,
+    // (caller, callee) contract id pairs for which calls are allowed even
+    // when the callee is already on the call stack.
+    #[cfg(any(test, feature = "testutils"))]
+    reentry_allow_list: RefCell<std::collections::HashSet<(Hash, Hash)>>,
+    // Permutation of object handles, for catching code that relies on handles
+    // being assigned sequentially.
+    #[cfg(any(test, feature = "testutils"))]
+    object_handle_permutation: RefCell<Option<HandlePermutation>>,
+    // Replacement for the host's ordering of objects.
+    #[cfg(any(test, feature = "testutils"))]
+    object_comparator: RefCell<Option<Rc<dyn ObjectComparator>>>,
+    // Outputs returned by the next PRNG u64 draws instead of drawing them.
+    #[cfg(any(test, feature = "testutils"))]
+    programmed_prng_outputs: RefCell<std::collections::VecDeque<u64>>,
+    // Opaque state attached by the embedder for test contracts, see
+    // `Host::with_embedder_context`.
+    #[cfg(any(test, feature = "testutils"))]
+    embedder_context: RefCell<Option<Box<dyn std::any::Any>>>,
+    // Allocation sites and escaped values of the host objects, see
+    // `Host::enable_object_leak_detection`.
+    #[cfg(any(test, feature = "testutils"))]
+    object_leak_tracker: RefCell<Option<object_leaks::ObjectLeakTracker>>,
     // Store a copy of the `AuthorizationManager` for the last host function
     // invocation. In order to emulate the production behavior in tests, we reset
     // authorization manager after every invocation (as it's not meant to be
@@ -125,7 +520,15 @@ struct HostImpl {
     // has happened or has been recorded.
     #[cfg(any(test, feature = "testutils"))]
     previous_authorization_manager: RefCell<Option<AuthorizationManager>>,
+    // Configuration of the Wasm VMs, varied by differential testing.
+    #[cfg(any(test, feature = "differential"))]
+    vm_config: RefCell<VmConfig>,
 }
+
+/// The interning table of symbol objects, see [`Host::intern_symbol`], keyed
+/// by the symbol bytes.
+pub(crate) type SymbolObjectMap = BTreeMap<ScSymbol, SymbolObject>;
+
 // Host is a newtype on Rc<HostImpl> so we can impl Env for it below.
 #[derive(Clone)]
 pub struct Host(Rc<HostImpl>);
@@ -168,7 +571,7 @@ impl_checked_borrow_helpers!(
 );
 impl_checked_borrow_helpers!(
     ledger,
-    Option<LedgerInfo>,
+    Option<LedgerSource>,
     try_borrow_ledger,
     try_borrow_ledger_mut
 );
@@ -179,6 +582,18 @@ impl_checked_borrow_helpers!(
     try_borrow_objects_mut
 );
 impl_checked_borrow_helpers!(storage, Storage, try_borrow_storage, try_borrow_storage_mut);
+impl_checked_borrow_helpers!(
+    code_store,
+    Option<Rc<dyn CodeStore>>,
+    try_borrow_code_store,
+    try_borrow_code_store_mut
+);
+impl_checked_borrow_helpers!(
+    metrics_sink,
+    Option<Rc<dyn MetricsSink>>,
+    try_borrow_metrics_sink,
+    try_borrow_metrics_sink_mut
+);
 impl_checked_borrow_helpers!(
     context,
     Vec<Context>,
@@ -197,22 +612,177 @@ impl_checked_borrow_helpers!(
     try_borrow_authorization_manager,
     try_borrow_authorization_manager_mut
 );
+impl_checked_borrow_helpers!(
+    fee_refund,
+    Option<FeeRefund>,
+    try_borrow_fee_refund,
+    try_borrow_fee_refund_mut
+);
+impl_checked_borrow_helpers!(
+    xdr_read_limits,
+    XdrReadLimits,
+    try_borrow_xdr_read_limits,
+    try_borrow_xdr_read_limits_mut
+);
+impl_checked_borrow_helpers!(
+    object_size_limits,
+    ObjectSizeLimits,
+    try_borrow_object_size_limits,
+    try_borrow_object_size_limits_mut
+);
+impl_checked_borrow_helpers!(
+    contract_type_specs,
+    ContractTypeSpecs,
+    try_borrow_contract_type_specs,
+    try_borrow_contract_type_specs_mut
+);
+impl_checked_borrow_helpers!(
+    execution_stats,
+    ExecutionStats,
+    try_borrow_execution_stats,
+    try_borrow_execution_stats_mut
+);
+impl_checked_borrow_helpers!(
+    attributed_storage_access,
+    StorageAccessStats,
+    try_borrow_attributed_storage_access,
+    try_borrow_attributed_storage_access_mut
+);
+impl_checked_borrow_helpers!(
+    container_copy_stats_enabled,
+    bool,
+    try_borrow_container_copy_stats_enabled,
+    try_borrow_container_copy_stats_enabled_mut
+);
+impl_checked_borrow_helpers!(memo, Memo, try_borrow_memo, try_borrow_memo_mut);
+impl_checked_borrow_helpers!(
+    module_cache,
+    ModuleCache,
+    try_borrow_module_cache,
+    try_borrow_module_cache_mut
+);
+impl_checked_borrow_helpers!(
+    contract_event_limits,
+    ContractEventLimits,
+    try_borrow_contract_event_limits,
+    try_borrow_contract_event_limits_mut
+);
+impl_checked_borrow_helpers!(
+    contract_event_counts,
+    ContractEventCounts,
+    try_borrow_contract_event_counts,
+    try_borrow_contract_event_counts_mut
+);
+impl_checked_borrow_helpers!(
+    event_filter,
+    Option<EventFilter>,
+    try_borrow_event_filter,
+    try_borrow_event_filter_mut
+);
+impl_checked_borrow_helpers!(
+    filtered_event_count,
+    u64,
+    try_borrow_filtered_event_count,
+    try_borrow_filtered_event_count_mut
+);
+impl_checked_borrow_helpers!(
+    symbol_objects,
+    SymbolObjectMap,
+    try_borrow_symbol_objects,
+    try_borrow_symbol_objects_mut
+);
 impl_checked_borrow_helpers!(
     diagnostic_level,
     DiagnosticLevel,
     try_borrow_diagnostic_level,
     try_borrow_diagnostic_level_mut
 );
+impl_checked_borrow_helpers!(
+    host_fn_trace_limits,
+    Option<ScValLimits>,
+    try_borrow_host_fn_trace_limits,
+    try_borrow_host_fn_trace_limits_mut
+);
 impl_checked_borrow_helpers!(
     base_prng,
     Option<Prng>,
     try_borrow_base_prng,
     try_borrow_base_prng_mut
 );
+impl_checked_borrow_helpers!(
+    prng_mode,
+    PrngMode,
+    try_borrow_prng_mode,
+    try_borrow_prng_mode_mut
+);
 
 #[cfg(any(test, feature = "testutils"))]
 impl_checked_borrow_helpers!(contracts, std::collections::HashMap<Hash, Rc<dyn ContractFunctionSet>>, try_borrow_contracts, try_borrow_contracts_mut);
 
+#[cfg(any(test, feature = "testutils"))]
+impl_checked_borrow_helpers!(
+    host_fn_interposers,
+    std::collections::HashMap<(Hash, &'static str), Rc<dyn HostFnInterposer>>,
+    try_borrow_host_fn_interposers,
+    try_borrow_host_fn_interposers_mut
+);
+
+#[cfg(any(test, feature = "testutils"))]
+impl_checked_borrow_helpers!(
+    reentry_allow_list,
+    std::collections::HashSet<(Hash, Hash)>,
+    try_borrow_reentry_allow_list,
+    try_borrow_reentry_allow_list_mut
+);
+
+#[cfg(any(test, feature = "testutils"))]
+impl_checked_borrow_helpers!(
+    object_handle_permutation,
+    Option<HandlePermutation>,
+    try_borrow_object_handle_permutation,
+    try_borrow_object_handle_permutation_mut
+);
+
+#[cfg(any(test, feature = "differential"))]
+impl_checked_borrow_helpers!(
+    vm_config,
+    VmConfig,
+    try_borrow_vm_config,
+    try_borrow_vm_config_mut
+);
+
+#[cfg(any(test, feature = "testutils"))]
+impl_checked_borrow_helpers!(
+    object_leak_tracker,
+    Option<object_leaks::ObjectLeakTracker>,
+    try_borrow_object_leak_tracker,
+    try_borrow_object_leak_tracker_mut
+);
+
+#[cfg(any(test, feature = "testutils"))]
+impl_checked_borrow_helpers!(
+    object_comparator,
+    Option<Rc<dyn ObjectComparator>>,
+    try_borrow_object_comparator,
+    try_borrow_object_comparator_mut
+);
+
+#[cfg(any(test, feature = "testutils"))]
+impl_checked_borrow_helpers!(
+    programmed_prng_outputs,
+    std::collections::VecDeque<u64>,
+    try_borrow_programmed_prng_outputs,
+    try_borrow_programmed_prng_outputs_mut
+);
+
+#[cfg(any(test, feature = "testutils"))]
+impl_checked_borrow_helpers!(
+    embedder_context,
+    Option<Box<dyn std::any::Any>>,
+    try_borrow_embedder_context,
+    try_borrow_embedder_context_mut
+);
+
 #[cfg(any(test, feature = "testutils"))]
 impl_checked_borrow_helpers!(
     previous_authorization_manager,
@@ -245,18 +815,52 @@ impl Host {
             ledger: RefCell::new(None),
             objects: Default::default(),
             storage: RefCell::new(storage),
+            code_store: RefCell::new(None),
+            metrics_sink: RefCell::new(None),
             context: Default::default(),
             budget,
             events: Default::default(),
+            contract_event_limits: Default::default(),
+            contract_event_counts: RefCell::new(ContractEventCounts::new()),
+            event_filter: RefCell::new(None),
+            filtered_event_count: RefCell::new(0),
             authorization_manager: RefCell::new(
                 AuthorizationManager::new_enforcing_without_authorizations(),
             ),
             diagnostic_level: Default::default(),
+            host_fn_trace_limits: RefCell::new(None),
+            xdr_read_limits: Default::default(),
+            object_size_limits: Default::default(),
+            contract_type_specs: Default::default(),
             base_prng: RefCell::new(None),
+            prng_mode: Default::default(),
+            fee_refund: RefCell::new(None),
+            execution_stats: Default::default(),
+            attributed_storage_access: Default::default(),
+            container_copy_stats_enabled: RefCell::new(false),
+            memo: RefCell::new(Memo::default()),
+            symbol_objects: RefCell::new(SymbolObjectMap::new()),
+            module_cache: Default::default(),
             #[cfg(any(test, feature = "testutils"))]
             contracts: Default::default(),
             #[cfg(any(test, feature = "testutils"))]
+            host_fn_interposers: Default::default(),
+            #[cfg(any(test, feature = "testutils"))]
+            reentry_allow_list: Default::default(),
+            #[cfg(any(test, feature = "testutils"))]
+            object_handle_permutation: RefCell::new(None),
+            #[cfg(any(test, feature = "testutils"))]
+            object_comparator: RefCell::new(None),
+            #[cfg(any(test, feature = "testutils"))]
+            programmed_prng_outputs: Default::default(),
+            #[cfg(any(test, feature = "testutils"))]
+            embedder_context: RefCell::new(None),
+            #[cfg(any(test, feature = "testutils"))]
+            object_leak_tracker: RefCell::new(None),
+            #[cfg(any(test, feature = "testutils"))]
             previous_authorization_manager: RefCell::new(None),
+            #[cfg(any(test, feature = "differential"))]
+            vm_config: Default::default(),
         }))
     }
 
@@ -292,6 +896,20 @@ impl Host {
         Ok(())
     }
 
+    /// Switches to the recording authorization mode, like
+    /// [`Host::switch_to_recording_auth`], with `disable_non_root_auth`
+    /// flipped for the given addresses and for the authorizations required
+    /// within invocations of the given contracts.
+    pub fn switch_to_recording_auth_with_exemptions(
+        &self,
+        disable_non_root_auth: bool,
+        exemptions: NonRootAuthExemptions,
+    ) -> Result<(), HostError> {
+        *self.try_borrow_authorization_manager_mut()? =
+            AuthorizationManager::new_recording_with_exemptions(disable_non_root_auth, exemptions);
+        Ok(())
+    }
+
     pub fn set_authorization_entries(
         &self,
         auth_entries: Vec<soroban_env_common::xdr::SorobanAuthorizationEntry>,
@@ -301,51 +919,410 @@ impl Host {
         Ok(())
     }
 
-    pub fn set_base_prng_seed(&self, seed: prng::Seed) -> Result<(), HostError> {
-        *self.try_borrow_base_prng_mut()? = Some(Prng::new_from_seed(seed));
+    /// Sets a [`CodeStore`] that will be consulted before the [`Storage`]
+    /// when loading contract code for execution.
+    pub fn set_code_store(&self, code_store: Rc<dyn CodeStore>) -> Result<(), HostError> {
+        *self.try_borrow_code_store_mut()? = Some(code_store);
         Ok(())
     }
 
-    pub fn set_ledger_info(&self, info: LedgerInfo) -> Result<(), HostError> {
-        *self.try_borrow_ledger_mut()? = Some(info);
+    /// Sets the limits applied when decoding XDR inputs, such as serialized
+    /// values passed to `deserialize_from_bytes`. Embedders are expected to
+    /// derive them from the network configuration.
+    pub fn set_xdr_read_limits(&self, limits: XdrReadLimits) -> Result<(), HostError> {
+        *self.try_borrow_xdr_read_limits_mut()? = limits;
         Ok(())
     }
 
-    pub fn with_ledger_info<F, T>(&self, f: F) -> Result<T, HostError>
+    /// Returns the limits applied when decoding XDR inputs.
+    pub fn xdr_read_limits(&self) -> Result<XdrReadLimits, HostError> {
+        Ok(*self.try_borrow_xdr_read_limits()?)
+    }
+
+    /// Sets the maximum sizes of the vector, map, bytes and string objects,
+    /// which protect the host from pathological memory use that the memory
+    /// budget alone doesn't model well. Embedders are expected to derive them
+    /// from the network configuration, which
+    /// [`invoke_host_function`](crate::e2e_invoke::invoke_host_function) takes
+    /// as an input.
+    pub fn set_object_size_limits(&self, limits: ObjectSizeLimits) -> Result<(), HostError> {
+        *self.try_borrow_object_size_limits_mut()? = limits;
+        Ok(())
+    }
+
+    /// Returns the maximum sizes of the host objects.
+    pub fn object_size_limits(&self) -> Result<ObjectSizeLimits, HostError> {
+        Ok(*self.try_borrow_object_size_limits()?)
+    }
+
+    /// Returns the fee refund designated by a contract during the invocation,
+    /// if any. The refund should only be honored if the invocation succeeds.
+    pub fn get_fee_refund(&self) -> Result<Option<FeeRefund>, HostError> {
+        Ok(self.try_borrow_fee_refund()?.clone())
+    }
+
+    /// Returns the total XDR size in bytes of the contract data entries that
+    /// existed before the invocation and have been deleted by it, see
+    /// [`Storage::freed_contract_data_bytes`]. This allows embedders to
+    /// consistently compute rent refunds for state deletion. Not metered, this
+    /// is meant to be called once the invocation is over.
+    pub fn freed_contract_data_bytes(&self) -> Result<u64, HostError> {
+        self.try_borrow_storage()?
+            .freed_contract_data_bytes(self.as_budget())
+    }
+
+    /// Returns the high-water marks and totals observed since this host was created or
+    /// since the last call to [`Host::reset_execution_stats`].
+    pub fn execution_stats(&self) -> Result<ExecutionStats, HostError> {
+        Ok(self.try_borrow_execution_stats()?.clone())
+    }
+
+    pub fn reset_execution_stats(&self) -> Result<(), HostError> {
+        *self.try_borrow_execution_stats_mut()? = ExecutionStats::default();
+        Ok(())
+    }
+
+    /// Starts tracking the ledger entry accesses of every contract, which are
+    /// reported in [`ExecutionStats::contract_storage_access`], e.g. for
+    /// attributing the resource fees of an invocation to the contracts that
+    /// have caused them. This isn't metered, but serializes every accessed
+    /// entry to measure its size.
+    pub fn enable_storage_access_stats(&self) -> Result<(), HostError> {
+        let mut storage = self.try_borrow_storage_mut()?;
+        storage.enable_access_stats();
+        *self.try_borrow_attributed_storage_access_mut()? = StorageAccessStats::default();
+        Ok(())
+    }
+
+    // Attributes the storage accesses performed since the previous call to
+    // the contract at the top of the call stack. This is called whenever the
+    // top of the stack changes. Accesses made outside of contracts (e.g. by
+    // the host function frame) aren't attributed.
+    pub(crate) fn attribute_storage_access(&self) -> Result<(), HostError> {
+        let Some(total) = self.try_borrow_storage()?.access_stats else {
+            return Ok(());
+        };
+        let delta = {
+            let mut attributed = self.try_borrow_attributed_storage_access_mut()?;
+            let delta = total.saturating_sub(&attributed);
+            *attributed = total;
+            delta
+        };
+        if delta.is_empty() {
+            return Ok(());
+        }
+        // No need for metered clones here, as the stats aren't metered.
+        let contract_id = match self.try_borrow_context()?.last().map(|ctx| &ctx.frame) {
+            Some(Frame::ContractVM { vm, .. }) => vm.contract_id.clone(),
+            Some(Frame::Token(id, ..)) => id.clone(),
+            #[cfg(any(test, feature = "testutils"))]
+            Some(Frame::TestContract(tc)) => tc.id.clone(),
+            Some(Frame::HostFunction(_)) | None => return Ok(()),
+        };
+        let mut stats = self.try_borrow_execution_stats_mut()?;
+        let contract_stats = stats
+            .contract_storage_access
+            .entry(contract_id)
+            .or_default();
+        *contract_stats = contract_stats.saturating_add(&delta);
+        Ok(())
+    }
+
+    pub(crate) fn record_frame_depth(&self, depth: usize) -> Result<(), HostError> {
+        let mut stats = self.try_borrow_execution_stats_mut()?;
+        stats.max_frame_depth = stats.max_frame_depth.max(depth as u32);
+        Ok(())
+    }
+
+    pub(crate) fn record_host_object_created(&self) -> Result<(), HostError> {
+        let mut stats = self.try_borrow_execution_stats_mut()?;
+        stats.host_objects_created = stats.host_objects_created.saturating_add(1);
+        Ok(())
+    }
+
+    /// Starts counting the copies of vectors and maps made by the host
+    /// functions, which are reported in [`ExecutionStats::vec_copies`] and
+    /// [`ExecutionStats::map_copies`]. This isn't metered.
+    pub fn enable_container_copy_stats(&self) -> Result<(), HostError> {
+        *self.try_borrow_container_copy_stats_enabled_mut()? = true;
+        Ok(())
+    }
+
+    // Records that a host function has copied a vector with a change applied
+    // into a vector of `len` elements. `unchanged` tells whether the copy is
+    // known to be identical to the original.
+    pub(crate) fn record_vec_copy(&self, len: usize, unchanged: bool) -> Result<(), HostError> {
+        if *self.try_borrow_container_copy_stats_enabled()? {
+            let mut stats = self.try_borrow_execution_stats_mut()?;
+            stats.vec_copies.record(len, unchanged);
+        }
+        Ok(())
+    }
+
+    // Same as `record_vec_copy`, for maps.
+    pub(crate) fn record_map_copy(&self, len: usize, unchanged: bool) -> Result<(), HostError> {
+        if *self.try_borrow_container_copy_stats_enabled()? {
+            let mut stats = self.try_borrow_execution_stats_mut()?;
+            stats.map_copies.record(len, unchanged);
+        }
+        Ok(())
+    }
+
+    /// Returns the budget consumed so far, to be passed to
+    /// [`Host::record_host_fn_cost`] once the host function call is done.
+    #[cfg(feature = "vm")]
+    pub(crate) fn host_fn_cost_start(&self) -> Result<(u64, u64), HostError> {
+        Ok((
+            self.as_budget().get_cpu_insns_consumed()?,
+            self.as_budget().get_mem_bytes_consumed()?,
+        ))
+    }
+
+    // Returns the cpu and memory consumed since `start`, as returned by
+    // `host_fn_cost_start`.
+    #[cfg(feature = "vm")]
+    fn cost_since(&self, start: (u64, u64)) -> Result<(u64, u64), HostError> {
+        let (cpu_start, mem_start) = start;
+        let cpu = self
+            .as_budget()
+            .get_cpu_insns_consumed()?
+            .saturating_sub(cpu_start);
+        let mem = self
+            .as_budget()
+            .get_mem_bytes_consumed()?
+            .saturating_sub(mem_start);
+        Ok((cpu, mem))
+    }
+
+    #[cfg(feature = "vm")]
+    pub(crate) fn record_host_fn_cost(&self, start: (u64, u64)) -> Result<(), HostError> {
+        let (cpu, mem) = self.cost_since(start)?;
+        let mut stats = self.try_borrow_execution_stats_mut()?;
+        stats.max_host_fn_cpu_insns = stats.max_host_fn_cpu_insns.max(cpu);
+        stats.max_host_fn_mem_bytes = stats.max_host_fn_mem_bytes.max(mem);
+        Ok(())
+    }
+
+    /// Returns the budget consumed so far, to be passed to
+    /// [`Host::record_vm_parse_cost`] or
+    /// [`Host::record_vm_instantiation_cost`].
+    #[cfg(feature = "vm")]
+    pub(crate) fn vm_cost_start(&self) -> Result<(u64, u64), HostError> {
+        self.host_fn_cost_start()
+    }
+
+    #[cfg(feature = "vm")]
+    pub(crate) fn record_vm_parse_cost(&self, start: (u64, u64)) -> Result<(), HostError> {
+        let (cpu, mem) = self.cost_since(start)?;
+        let mut stats = self.try_borrow_execution_stats_mut()?;
+        stats.vm_parse_cpu_insns = stats.vm_parse_cpu_insns.saturating_add(cpu);
+        stats.vm_parse_mem_bytes = stats.vm_parse_mem_bytes.saturating_add(mem);
+        Ok(())
+    }
+
+    #[cfg(feature = "vm")]
+    pub(crate) fn record_vm_instantiation_cost(&self, start: (u64, u64)) -> Result<(), HostError> {
+        let (cpu, mem) = self.cost_since(start)?;
+        let mut stats = self.try_borrow_execution_stats_mut()?;
+        stats.vm_instantiation_cpu_insns = stats.vm_instantiation_cpu_insns.saturating_add(cpu);
+        stats.vm_instantiation_mem_bytes = stats.vm_instantiation_mem_bytes.saturating_add(mem);
+        Ok(())
+    }
+
+    #[cfg(feature = "vm")]
+    pub(crate) fn record_module_cache_hit(&self) -> Result<(), HostError> {
+        let mut stats = self.try_borrow_execution_stats_mut()?;
+        stats.module_cache_hits = stats.module_cache_hits.saturating_add(1);
+        Ok(())
+    }
+
+    /// Makes the next PRNG `u64` range draws of contracts return `outputs`
+    /// in order, instead of drawing them, so that probabilistic contracts can
+    /// be tested deterministically. The draws return to the PRNG once the
+    /// outputs run out. A draw fails if its programmed output is outside of
+    /// the requested range.
+    #[cfg(any(test, feature = "testutils"))]
+    pub fn program_prng_outputs(&self, outputs: Vec<u64>) -> Result<(), HostError> {
+        *self.try_borrow_programmed_prng_outputs_mut()? = outputs.into();
+        Ok(())
+    }
+
+    /// Attaches an opaque `context` to the host, replacing the previous one,
+    /// or removes it if `context` is `None`. The context can be accessed with
+    /// [`Host::with_embedder_context`], e.g. by the [`ContractFunctionSet`]
+    /// implementations of test doubles such as mock oracles or clocks.
+    #[cfg(any(test, feature = "testutils"))]
+    pub fn set_embedder_context(
+        &self,
+        context: Option<Box<dyn std::any::Any>>,
+    ) -> Result<(), HostError> {
+        *self.try_borrow_embedder_context_mut()? = context;
+        Ok(())
+    }
+
+    /// Runs `f` with the context attached by [`Host::set_embedder_context`].
+    /// Fails with `(Context, MissingValue)` if there is no context or if it
+    /// is not a `T`. The context is borrowed while `f` runs, so accessing it
+    /// again from within `f`, e.g. from a nested contract call, fails.
+    #[cfg(any(test, feature = "testutils"))]
+    pub fn with_embedder_context<T, F, R>(&self, f: F) -> Result<R, HostError>
     where
-        F: FnOnce(&LedgerInfo) -> Result<T, HostError>,
+        T: std::any::Any,
+        F: FnOnce(&mut T) -> Result<R, HostError>,
     {
-        match self.try_borrow_ledger()?.as_ref() {
+        let mut context = self.try_borrow_embedder_context_mut()?;
+        match context.as_mut().and_then(|c| c.downcast_mut::<T>()) {
+            Some(c) => f(c),
             None => Err(self.err(
                 ScErrorType::Context,
-                ScErrorCode::InternalError,
-                "missing ledger info",
+                ScErrorCode::MissingValue,
+                "embedder context is missing or has an unexpected type",
                 &[],
             )),
-            Some(li) => f(li),
         }
     }
 
+    // Returns the arguments of the current invocation that addresses
+    // authorize with `require_auth`.
+    fn current_frame_args_for_auth(&self) -> Result<Vec<Val>, HostError> {
+        self.with_current_frame(|f| {
+            let args = match f {
+                Frame::ContractVM { args, .. } => args,
+                Frame::HostFunction(_) => {
+                    return Err(self.err(
+                        ScErrorType::Context,
+                        ScErrorCode::InternalError,
+                        "require_auth is not suppported for host fns",
+                        &[],
+                    ))
+                }
+                Frame::Token(_, _, args, _) => args,
+                #[cfg(any(test, feature = "testutils"))]
+                Frame::TestContract(c) => &c.args,
+            };
+            args.metered_clone(self)
+        })
+    }
+
+    pub fn set_base_prng_seed(&self, seed: prng::Seed) -> Result<(), HostError> {
+        *self.try_borrow_base_prng_mut()? = Some(Prng::new_from_seed(seed));
+        Ok(())
+    }
+
+    /// Sets the ledger information as a fixed [`LedgerInfo`], replacing any
+    /// [`LedgerInfoProvider`].
+    pub fn set_ledger_info(&self, info: LedgerInfo) -> Result<(), HostError> {
+        *self.try_borrow_ledger_mut()? = Some(LedgerSource::Info(info));
+        Ok(())
+    }
+
+    /// Makes the host read the ledger information from `provider`, replacing
+    /// any [`LedgerInfo`] set before. Outside of invocations the provider is
+    /// queried whenever the information is needed, while every field is read
+    /// once at the start of an invocation and kept until its end.
+    pub fn set_ledger_info_provider(
+        &self,
+        provider: Rc<dyn LedgerInfoProvider>,
+    ) -> Result<(), HostError> {
+        *self.try_borrow_ledger_mut()? = Some(LedgerSource::Provider {
+            provider,
+            snapshot: None,
+        });
+        if !self.try_borrow_context()?.is_empty() {
+            self.snapshot_ledger_info()?;
+        }
+        Ok(())
+    }
+
+    fn err_missing_ledger_info(&self) -> HostError {
+        self.err(
+            ScErrorType::Context,
+            ScErrorCode::InternalError,
+            "missing ledger info",
+            &[],
+        )
+    }
+
+    /// Calls `f` with the source of the ledger information, which only reads
+    /// the fields `f` asks for. During an invocation, that's the information
+    /// read from the [`LedgerInfoProvider`] at its start.
+    pub fn with_ledger_info_provider<F, T>(&self, f: F) -> Result<T, HostError>
+    where
+        F: FnOnce(&dyn LedgerInfoProvider) -> Result<T, HostError>,
+    {
+        // The provider is cloned out of the host, so that it may call back
+        // into the host.
+        let provider = match self.try_borrow_ledger()?.as_ref() {
+            None => return Err(self.err_missing_ledger_info()),
+            Some(LedgerSource::Info(li))
+            | Some(LedgerSource::Provider {
+                snapshot: Some(li), ..
+            }) => return f(li),
+            Some(LedgerSource::Provider { provider, .. }) => Rc::clone(provider),
+        };
+        f(provider.as_ref())
+    }
+
+    /// Calls `f` with the ledger information. Outside of invocations, every
+    /// field is read from the [`LedgerInfoProvider`] first, if any.
+    pub fn with_ledger_info<F, T>(&self, f: F) -> Result<T, HostError>
+    where
+        F: FnOnce(&LedgerInfo) -> Result<T, HostError>,
+    {
+        let provider = match self.try_borrow_ledger()?.as_ref() {
+            None => return Err(self.err_missing_ledger_info()),
+            Some(LedgerSource::Info(li))
+            | Some(LedgerSource::Provider {
+                snapshot: Some(li), ..
+            }) => return f(li),
+            Some(LedgerSource::Provider { provider, .. }) => Rc::clone(provider),
+        };
+        f(&LedgerInfo::from_provider(provider.as_ref()))
+    }
+
+    /// Mutates the ledger information set with [`Host::set_ledger_info`].
+    /// Fails with `(Context, InvalidAction)` if it's served by a
+    /// [`LedgerInfoProvider`] instead.
     pub fn with_mut_ledger_info<F>(&self, mut f: F) -> Result<(), HostError>
     where
         F: FnMut(&mut LedgerInfo),
     {
         match self.try_borrow_ledger_mut()?.as_mut() {
-            None => Err(self.err(
-                ScErrorType::Context,
-                ScErrorCode::InternalError,
-                "missing ledger info",
-                &[],
-            )),
-            Some(li) => {
+            None => Err(self.err_missing_ledger_info()),
+            Some(LedgerSource::Info(li)) => {
                 f(li);
                 Ok(())
             }
+            Some(LedgerSource::Provider { .. }) => Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidAction,
+                "ledger info is served by a provider and can't be mutated",
+                &[],
+            )),
         }
     }
 
     pub fn get_ledger_protocol_version(&self) -> Result<u32, HostError> {
-        self.with_ledger_info(|li| Ok(li.protocol_version))
+        self.with_ledger_info_provider(|li| Ok(li.protocol_version()))
+    }
+
+    /// Returns an error if the current ledger protocol version is lower than
+    /// `min_protocol`. Used to gate host functionality that only becomes
+    /// available starting from a given protocol.
+    pub(crate) fn check_protocol_version_lower_bound(
+        &self,
+        min_protocol: u32,
+    ) -> Result<(), HostError> {
+        let ledger_protocol = self.get_ledger_protocol_version()?;
+        if ledger_protocol < min_protocol {
+            return Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidAction,
+                "functionality is not supported by the current ledger protocol",
+                &[ledger_protocol.into(), min_protocol.into()],
+            ));
+        }
+        Ok(())
     }
 
     /// Helper for mutating the [`Budget`] held in this [`Host`], either to
@@ -374,6 +1351,8 @@ impl Host {
     /// underlying [`HostImpl`], returning its finalized components containing
     /// processing side effects  to the caller as a tuple wrapped in `Ok(...)`.
     pub fn try_finish(self) -> Result<(Storage, Events), HostError> {
+        #[cfg(any(test, feature = "testutils"))]
+        self.object_leak_diagnostics()?;
         let events = self.try_borrow_events()?.externalize(&self)?;
         Rc::try_unwrap(self.0)
             .map(|host_impl| {
@@ -385,6 +1364,48 @@ impl Host {
             })
     }
 
+    /// Like [`Host::try_finish`], but returns the side effects as a
+    /// [`FinishedHostState`] that can be sent to other threads, along with the
+    /// execution stats and the consumed budget.
+    pub fn try_finish_owned(self) -> Result<FinishedHostState, HostError> {
+        let fee_refund = self.try_borrow_fee_refund()?.clone();
+        let freed_contract_data_bytes = self.freed_contract_data_bytes()?;
+        let execution_stats = self.execution_stats()?;
+        let cpu_insns_consumed = self.budget_ref().get_cpu_insns_consumed()?;
+        let mem_bytes_consumed = self.budget_ref().get_mem_bytes_consumed()?;
+        let (storage, events) = self.try_finish()?;
+        // The host is gone, so there's nothing left to meter these clones
+        // against.
+        let footprint = storage
+            .footprint
+            .0
+            .map
+            .iter()
+            .map(|(k, access)| (k.as_ref().clone(), *access))
+            .collect();
+        let ledger_entries = storage
+            .map
+            .map
+            .iter()
+            .map(|(k, entry)| {
+                let entry = entry
+                    .as_ref()
+                    .map(|(e, expiration)| (e.as_ref().clone(), *expiration));
+                (k.as_ref().clone(), entry)
+            })
+            .collect();
+        Ok(FinishedHostState {
+            footprint,
+            ledger_entries,
+            events,
+            fee_refund,
+            freed_contract_data_bytes,
+            execution_stats,
+            cpu_insns_consumed,
+            mem_bytes_consumed,
+        })
+    }
+
     // Testing interface to create values directly for later use via Env functions.
     // It needs to be a `pub` method because benches are considered a separate crate.
     #[cfg(any(test, feature = "testutils"))]
@@ -392,25 +1413,37 @@ impl Host {
         self.to_host_val(v).map(Into::into)
     }
 
-    fn symbol_matches(&self, s: &[u8], sym: Symbol) -> Result<bool, HostError> {
+    // The comparison is charged to `batch`, as this is called once per
+    // element when scanning slices of symbols.
+    fn symbol_matches(
+        &self,
+        batch: &mut BudgetChargeBatch,
+        s: &[u8],
+        sym: Symbol,
+    ) -> Result<bool, HostError> {
+        let mut matches = |slice: &[u8]| -> Result<bool, HostError> {
+            batch.charge(
+                ContractCostType::HostMemCmp,
+                Some(slice.len().min(s.len()) as u64),
+            );
+            Ok(slice == s)
+        };
         if let Ok(ss) = SymbolSmall::try_from(sym) {
             let sstr: SymbolStr = ss.into();
-            let slice: &[u8] = sstr.as_ref();
-            self.as_budget()
-                .compare(&slice, &s)
-                .map(|c| c == Ordering::Equal)
+            matches(sstr.as_ref())
         } else {
             let sobj: SymbolObject = sym.try_into()?;
-            self.visit_obj(sobj, |scsym: &ScSymbol| {
-                self.as_budget()
-                    .compare(&scsym.as_slice(), &s)
-                    .map(|c| c == Ordering::Equal)
-            })
+            self.visit_obj(sobj, |scsym: &ScSymbol| matches(scsym.as_slice()))
         }
     }
 
-    fn check_symbol_matches(&self, s: &[u8], sym: Symbol) -> Result<(), HostError> {
-        if self.symbol_matches(s, sym)? {
+    fn check_symbol_matches(
+        &self,
+        batch: &mut BudgetChargeBatch,
+        s: &[u8],
+        sym: Symbol,
+    ) -> Result<(), HostError> {
+        if self.symbol_matches(batch, s, sym)? {
             Ok(())
         } else {
             Err(self.err(
@@ -544,10 +1577,12 @@ impl EnvBase for Host {
     }
 
     fn bytes_new_from_slice(&self, mem: &[u8]) -> Result<BytesObject, HostError> {
+        self.check_object_len::<ScBytes>(mem.len())?;
         self.add_host_object(self.scbytes_from_slice(mem)?)
     }
 
     fn string_new_from_slice(&self, s: &str) -> Result<StringObject, HostError> {
+        self.check_object_len::<ScString>(s.len())?;
         self.add_host_object(ScString(
             self.metered_slice_to_vec(s.as_bytes())?.try_into()?,
         ))
@@ -558,7 +1593,7 @@ impl EnvBase for Host {
         for ch in s.chars() {
             SymbolSmall::validate_char(ch)?;
         }
-        self.add_host_object(ScSymbol(
+        self.intern_symbol(ScSymbol(
             self.metered_slice_to_vec(s.as_bytes())?.try_into()?,
         ))
     }
@@ -572,6 +1607,7 @@ impl EnvBase for Host {
                 &[],
             ));
         }
+        self.check_object_len::<HostMap>(keys.len())?;
         Vec::<(Val, Val)>::charge_bulk_init_cpy(keys.len() as u64, self)?;
         let map_vec = keys
             .iter()
@@ -610,10 +1646,13 @@ impl EnvBase for Host {
                 ));
             }
 
-            for (ik, mk) in keys.iter().zip(hm.keys(self)?) {
-                let sym: Symbol = mk.try_into()?;
-                self.check_symbol_matches(ik.as_bytes(), sym)?;
-            }
+            self.as_budget().with_charge_batch(|batch| {
+                for (ik, mk) in keys.iter().zip(hm.keys(self)?) {
+                    let sym: Symbol = mk.try_into()?;
+                    self.check_symbol_matches(batch, ik.as_bytes(), sym)?;
+                }
+                Ok(())
+            })?;
 
             metered_clone::charge_shallow_copy::<Val>(keys.len() as u64, self)?;
             for (iv, mv) in vals.iter_mut().zip(hm.values(self)?) {
@@ -625,6 +1664,7 @@ impl EnvBase for Host {
     }
 
     fn vec_new_from_slice(&self, vals: &[Val]) -> Result<VecObject, Self::Error> {
+        self.check_object_len::<HostVec>(vals.len())?;
         let vec = HostVec::from_exact_iter(vals.iter().cloned(), self.budget_ref())?;
         for v in vec.iter() {
             self.check_val_integrity(*v)?;
@@ -651,11 +1691,13 @@ impl EnvBase for Host {
 
     fn symbol_index_in_strs(&self, sym: Symbol, slices: &[&str]) -> Result<U32Val, Self::Error> {
         let mut found = None;
-        self.scan_slice_of_slices(slices, |i, slice| {
-            if self.symbol_matches(slice.as_bytes(), sym)? && found.is_none() {
-                found = Some(i)
-            }
-            Ok(())
+        self.as_budget().with_charge_batch(|batch| {
+            self.scan_slice_of_slices(slices, |i, slice| {
+                if self.symbol_matches(batch, slice.as_bytes(), sym)? && found.is_none() {
+                    found = Some(i)
+                }
+                Ok(())
+            })
         })?;
         match found {
             None => Err(self.err(
@@ -766,6 +1808,8 @@ impl VmCallerEnv for Host {
                 atype.cmp(&btype)
             }
         };
+        #[cfg(any(test, feature = "testutils"))]
+        let res = self.maybe_override_obj_cmp(a, b, res)?;
         // Finally, translate Ordering::Foo to a number to return to caller.
         Ok(match res {
             Ordering::Less => -1,
@@ -785,16 +1829,71 @@ impl VmCallerEnv for Host {
         Ok(Val::VOID)
     }
 
+    // Notes on metering: covered by the components.
+    fn get_contract_events_size(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+    ) -> Result<U32Val, HostError> {
+        self.check_protocol_version_lower_bound(CONTRACT_EVENTS_SIZE_MIN_PROTOCOL)?;
+        // Sizes beyond `u32::MAX` are far above any network limit.
+        let size = u32::try_from(self.contract_events_size()?).unwrap_or(u32::MAX);
+        Ok(size.into())
+    }
+
+    // Notes on metering: the lookup, the insertion and the undo log entry are
+    // metered. The memo doesn't touch the storage.
+    fn memo_put(&self, _vmcaller: &mut VmCaller<Host>, k: Val, v: Val) -> Result<Void, HostError> {
+        self.check_protocol_version_lower_bound(MEMO_FNS_MIN_PROTOCOL)?;
+        self.check_val_integrity(k)?;
+        self.check_val_integrity(v)?;
+        let key = (self.get_current_contract_id_internal()?, k);
+        self.try_borrow_memo_mut()?.put(key, v, self)?;
+        Ok(Val::VOID)
+    }
+
+    // Notes on metering: covered by the lookup.
+    fn memo_get(&self, _vmcaller: &mut VmCaller<Host>, k: Val) -> Result<Val, HostError> {
+        self.check_protocol_version_lower_bound(MEMO_FNS_MIN_PROTOCOL)?;
+        self.check_val_integrity(k)?;
+        let key = (self.get_current_contract_id_internal()?, k);
+        match self.try_borrow_memo()?.get(&key, self)? {
+            Some(v) => Ok(v),
+            None => Ok(Val::VOID.into()),
+        }
+    }
+
+    // Notes on metering: covered by the components.
+    fn error_to_val_with_context(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        error: Error,
+    ) -> Result<MapObject, HostError> {
+        self.check_protocol_version_lower_bound(ERROR_CONTEXT_MIN_PROTOCOL)?;
+        self.error_to_val_with_context_internal(error)
+    }
+
     fn get_ledger_version(&self, _vmcaller: &mut VmCaller<Host>) -> Result<U32Val, Self::Error> {
+        #[cfg(any(test, feature = "testutils"))]
+        if let Some(res) = self.maybe_call_host_fn_interposer("get_ledger_version", &[])? {
+            return Ok(res);
+        }
         Ok(self.get_ledger_protocol_version()?.into())
     }
 
     fn get_ledger_sequence(&self, _vmcaller: &mut VmCaller<Host>) -> Result<U32Val, Self::Error> {
-        self.with_ledger_info(|li| Ok(li.sequence_number.into()))
+        #[cfg(any(test, feature = "testutils"))]
+        if let Some(res) = self.maybe_call_host_fn_interposer("get_ledger_sequence", &[])? {
+            return Ok(res);
+        }
+        self.with_ledger_info_provider(|li| Ok(li.sequence_number().into()))
     }
 
     fn get_ledger_timestamp(&self, _vmcaller: &mut VmCaller<Host>) -> Result<U64Val, Self::Error> {
-        self.with_ledger_info(|li| Ok(U64Val::try_from_val(self, &li.timestamp)?))
+        #[cfg(any(test, feature = "testutils"))]
+        if let Some(res) = self.maybe_call_host_fn_interposer("get_ledger_timestamp", &[])? {
+            return Ok(res);
+        }
+        self.with_ledger_info_provider(|li| Ok(U64Val::try_from_val(self, &li.timestamp())?))
     }
 
     fn fail_with_error(
@@ -822,10 +1921,13 @@ impl VmCallerEnv for Host {
         &self,
         _vmcaller: &mut VmCaller<Host>,
     ) -> Result<BytesObject, Self::Error> {
-        self.with_ledger_info(|li| {
-            // FIXME: cache this and a few other such IDs: https://github.com/stellar/rs-soroban-env/issues/681
-            self.add_host_object(self.scbytes_from_slice(li.network_id.as_slice())?)
-        })
+        #[cfg(any(test, feature = "testutils"))]
+        if let Some(res) = self.maybe_call_host_fn_interposer("get_ledger_network_id", &[])? {
+            return Ok(res);
+        }
+        let network_id = self.with_ledger_info_provider(|li| Ok(li.network_id()))?;
+        // FIXME: cache this and a few other such IDs: https://github.com/stellar/rs-soroban-env/issues/681
+        self.add_host_object(self.scbytes_from_slice(network_id.as_slice())?)
     }
 
     // Notes on metering: covered by the components.
@@ -843,9 +1945,90 @@ impl VmCallerEnv for Host {
         &self,
         _vmcaller: &mut VmCaller<Host>,
     ) -> Result<U32Val, Self::Error> {
+        #[cfg(any(test, feature = "testutils"))]
+        if let Some(res) = self.maybe_call_host_fn_interposer("get_max_expiration_ledger", &[])? {
+            return Ok(res);
+        }
         Ok(self.max_expiration_ledger()?.into())
     }
 
+    fn set_fee_refund(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        amount: i64,
+    ) -> Result<Void, Self::Error> {
+        self.check_protocol_version_lower_bound(FEE_REFUND_MIN_PROTOCOL)?;
+        if amount < 0 {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "fee refund amount must be non-negative",
+                &[],
+            ));
+        }
+        let contract_id = self.get_current_contract_id_internal()?;
+        // Only the top-level contract of the invocation can designate the
+        // refund, so that the contracts it calls can't override it.
+        // Notes on metering: not charged, the context stack is bounded by the
+        // depth limit.
+        let contract_frames = self
+            .try_borrow_context()?
+            .iter()
+            .filter(|ctx| !matches!(ctx.frame, Frame::HostFunction(_)))
+            .count();
+        if contract_frames > 1 {
+            return Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidAction,
+                "only the top-level contract can designate a fee refund",
+                &[],
+            ));
+        }
+        *self.try_borrow_fee_refund_mut()? = Some(FeeRefund {
+            contract_id,
+            amount,
+        });
+        Ok(Val::VOID)
+    }
+
+    // Notes on metering: section lookup and copy are covered by components.
+    fn get_own_custom_section(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        name: StringObject,
+    ) -> Result<BytesObject, HostError> {
+        self.check_protocol_version_lower_bound(OWN_CUSTOM_SECTION_MIN_PROTOCOL)?;
+        let vm = self.with_current_frame(|frame| match frame {
+            Frame::ContractVM { vm, .. } => Ok(Rc::clone(vm)),
+            _ => Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidAction,
+                "custom sections are only accessible from Wasm contracts",
+                &[],
+            )),
+        })?;
+        let section = self.visit_obj(name, |s: &ScString| {
+            vm.metered_custom_section(self, s.as_slice())
+        })?;
+        let Some(section) = section else {
+            return Err(self.err(
+                ScErrorType::WasmVm,
+                ScErrorCode::MissingValue,
+                "custom section not found in contract Wasm",
+                &[name.to_val()],
+            ));
+        };
+        if section.len() > MAX_OWN_CUSTOM_SECTION_SIZE {
+            return Err(self.err(
+                ScErrorType::WasmVm,
+                ScErrorCode::ExceededLimit,
+                "custom section exceeds the maximum readable size",
+                &[name.to_val(), U32Val::from(section.len() as u32).to_val()],
+            ));
+        }
+        self.add_host_object(self.scbytes_from_slice(section)?)
+    }
+
     // endregion "context" module functions
 
     // region: "int" module functions
@@ -1083,6 +2266,84 @@ impl VmCallerEnv for Host {
     impl_bignum_host_fns_rhs_u32!(u256_shl, checked_shl, U256, U256Val, Int256Shift);
     impl_bignum_host_fns_rhs_u32!(u256_shr, checked_shr, U256, U256Val, Int256Shift);
 
+    fn u256_sqrt(&self, _vmcaller: &mut VmCaller<Host>, x: U256Val) -> Result<U256Val, HostError> {
+        self.check_protocol_version_lower_bound(U256_SQRT_LOG2_MIN_PROTOCOL)?;
+        let n = U256::try_from_val(self, &x.to_val())?;
+        if n < U256::new(2) {
+            return Ok(x);
+        }
+        // Newton's method starting from a power of two not below the root, so
+        // the iterates decrease until they reach the floor of the root. This
+        // takes a handful of iterations, each of them charged as a division.
+        let bits = 256 - n.leading_zeros();
+        let mut r = U256::ONE << ((bits + 1) / 2);
+        loop {
+            self.charge_budget(ContractCostType::Int256Div, None)?;
+            let next = (r + n / r) >> 1;
+            if next >= r {
+                break;
+            }
+            r = next;
+        }
+        Ok(U256Val::try_from_val(self, &r)?)
+    }
+
+    fn u256_log2(&self, _vmcaller: &mut VmCaller<Host>, x: U256Val) -> Result<U32Val, HostError> {
+        self.check_protocol_version_lower_bound(U256_SQRT_LOG2_MIN_PROTOCOL)?;
+        self.charge_budget(ContractCostType::Int256Shift, None)?;
+        let n = U256::try_from_val(self, &x.to_val())?;
+        if n == U256::ZERO {
+            return Err(self.err(
+                ScErrorType::Object,
+                ScErrorCode::ArithDomain,
+                "log2 of zero is undefined",
+                &[x.to_val()],
+            ));
+        }
+        Ok((255 - n.leading_zeros()).into())
+    }
+
+    fn u256_to_i128_checked(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        x: U256Val,
+    ) -> Result<Val, HostError> {
+        self.check_protocol_version_lower_bound(I128_256_CONVERSIONS_MIN_PROTOCOL)?;
+        let n = U256::try_from_val(self, &x.to_val())?;
+        if n > U256::new(i128::MAX as u128) {
+            return Ok(self.err_i128_range_val(x.to_val()));
+        }
+        Ok(Val::try_from_val(self, &n.as_i128())?)
+    }
+
+    fn i256_to_i128_checked(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        x: I256Val,
+    ) -> Result<Val, HostError> {
+        self.check_protocol_version_lower_bound(I128_256_CONVERSIONS_MIN_PROTOCOL)?;
+        let n = I256::try_from_val(self, &x.to_val())?;
+        if n < I256::new(i128::MIN) || n > I256::new(i128::MAX) {
+            return Ok(self.err_i128_range_val(x.to_val()));
+        }
+        Ok(Val::try_from_val(self, &n.as_i128())?)
+    }
+
+    fn i128_to_u256(&self, _vmcaller: &mut VmCaller<Host>, x: Val) -> Result<Val, HostError> {
+        self.check_protocol_version_lower_bound(I128_256_CONVERSIONS_MIN_PROTOCOL)?;
+        let n = i128::try_from_val(self, &x)?;
+        if n < 0 {
+            return Ok(self.err_i128_range_val(x));
+        }
+        Ok(Val::try_from_val(self, &U256::new(n as u128))?)
+    }
+
+    fn i128_to_i256(&self, _vmcaller: &mut VmCaller<Host>, x: Val) -> Result<I256Val, HostError> {
+        self.check_protocol_version_lower_bound(I128_256_CONVERSIONS_MIN_PROTOCOL)?;
+        let n = i128::try_from_val(self, &x)?;
+        Ok(I256Val::try_from_val(self, &I256::new(n))?)
+    }
+
     impl_bignum_host_fns!(i256_add, checked_add, I256, I256Val, Int256AddSub);
     impl_bignum_host_fns!(i256_sub, checked_sub, I256, I256Val, Int256AddSub);
     impl_bignum_host_fns!(i256_mul, checked_mul, I256, I256Val, Int256Mul);
@@ -1091,6 +2352,45 @@ impl VmCallerEnv for Host {
     impl_bignum_host_fns_rhs_u32!(i256_shl, checked_shl, I256, I256Val, Int256Shift);
     impl_bignum_host_fns_rhs_u32!(i256_shr, checked_shr, I256, I256Val, Int256Shift);
 
+    fn i128_scale(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        amount: Val,
+        from_decimals: U32Val,
+        to_decimals: U32Val,
+        rounding: U32Val,
+    ) -> Result<Val, HostError> {
+        self.check_protocol_version_lower_bound(I128_SCALE_MIN_PROTOCOL)?;
+        let amount = i128::try_from_val(self, &amount)?;
+        let rounding = self.amount_rounding_from_u32(rounding.into())?;
+        let res = self.scale_amount(amount, from_decimals.into(), to_decimals.into(), rounding)?;
+        Ok(Val::try_from_val(self, &res)?)
+    }
+
+    // Notes on metering: covered by the conversion, the check is free.
+    fn require_nonnegative_i128(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        amount: Val,
+    ) -> Result<Void, HostError> {
+        self.check_protocol_version_lower_bound(REQUIRE_I128_AMOUNT_MIN_PROTOCOL)?;
+        let amount = i128::try_from_val(self, &amount)?;
+        self.require_nonnegative_amount(amount)?;
+        Ok(Val::VOID)
+    }
+
+    // Notes on metering: covered by the conversion, the check is free.
+    fn require_positive_i128(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        amount: Val,
+    ) -> Result<Void, HostError> {
+        self.check_protocol_version_lower_bound(REQUIRE_I128_AMOUNT_MIN_PROTOCOL)?;
+        let amount = i128::try_from_val(self, &amount)?;
+        self.require_positive_amount(amount)?;
+        Ok(Val::VOID)
+    }
+
     // endregion "int" module functions
     // region: "map" module functions
 
@@ -1107,7 +2407,22 @@ impl VmCallerEnv for Host {
     ) -> Result<MapObject, HostError> {
         self.check_val_integrity(k)?;
         self.check_val_integrity(v)?;
-        let mnew = self.visit_obj(m, |hm: &HostMap| hm.insert(k, v, self))?;
+        let mnew = self.visit_obj(m, |hm: &HostMap| {
+            // Only a new key grows the map, the key is looked up only when the
+            // map is full.
+            let full = HostMap::size_limit(&self.object_size_limits()?)
+                .map_or(false, |(_, limit)| hm.len() >= limit as usize);
+            if full && !hm.contains_key(&k, self)? {
+                self.check_object_len::<HostMap>(hm.len() + 1)?;
+            }
+            let (mnew, replaced) = hm.insert_with_replaced(k, v, self)?;
+            let unchanged = replaced.map_or(false, |i| {
+                let (k0, v0) = &hm.map[i];
+                k0.get_payload() == k.get_payload() && v0.get_payload() == v.get_payload()
+            });
+            self.record_map_copy(mnew.len(), unchanged)?;
+            Ok(mnew)
+        })?;
         self.add_host_object(mnew)
     }
 
@@ -1137,7 +2452,14 @@ impl VmCallerEnv for Host {
         k: Val,
     ) -> Result<MapObject, HostError> {
         self.check_val_integrity(k)?;
-        match self.visit_obj(m, |hm: &HostMap| hm.remove(&k, self))? {
+        let res = self.visit_obj(m, |hm: &HostMap| {
+            let res = hm.remove(&k, self)?;
+            if let Some((mnew, _)) = &res {
+                self.record_map_copy(mnew.len(), false)?;
+            }
+            Ok(res)
+        })?;
+        match res {
             Some((mnew, _)) => Ok(self.add_host_object(mnew)?),
             None => Err(self.err(
                 ScErrorType::Object,
@@ -1206,39 +2528,129 @@ impl VmCallerEnv for Host {
         let vec = self.visit_obj(m, |hm: &HostMap| {
             HostVec::from_exact_iter(hm.values(self)?.cloned(), self.budget_ref())
         })?;
-        self.add_host_object(vec)
+        self.add_host_object(vec)
+    }
+
+    fn map_range(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        m: MapObject,
+        start_key: Val,
+        end_key: Val,
+    ) -> Result<MapObject, HostError> {
+        self.check_protocol_version_lower_bound(MAP_RANGE_FNS_MIN_PROTOCOL)?;
+        self.check_val_integrity(start_key)?;
+        self.check_val_integrity(end_key)?;
+        let mnew = self.visit_obj(m, |hm: &HostMap| {
+            let range = hm.key_range(&start_key, &end_key, self)?;
+            hm.slice(range, self)
+        })?;
+        self.add_host_object(mnew)
+    }
+
+    fn map_nth_key_range(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        m: MapObject,
+        start_idx: U32Val,
+        count: U32Val,
+    ) -> Result<VecObject, HostError> {
+        self.check_protocol_version_lower_bound(MAP_RANGE_FNS_MIN_PROTOCOL)?;
+        let start = u32::from(start_idx) as usize;
+        let count = u32::from(count) as usize;
+        let vec = self.visit_obj(m, |hm: &HostMap| {
+            // The range is cut at the end of the map in order to allow
+            // paginating without knowing the map size.
+            let end = start.saturating_add(count).min(hm.len()).max(start);
+            HostVec::from_exact_iter(
+                hm.keys_in_range(start..end, self)?.cloned(),
+                self.budget_ref(),
+            )
+        })?;
+        self.add_host_object(vec)
+    }
+
+    fn map_new_from_linear_memory(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        keys_pos: U32Val,
+        vals_pos: U32Val,
+        len: U32Val,
+    ) -> Result<MapObject, HostError> {
+        // Step 1: extract all key symbols.
+        let VmSlice {
+            vm,
+            pos: keys_pos,
+            len,
+        } = self.decode_vmslice(keys_pos, len)?;
+        self.check_object_len::<HostMap>(len as usize)?;
+        Vec::<Symbol>::charge_bulk_init_cpy(len as u64, self)?;
+        let mut key_syms: Vec<Symbol> = Vec::with_capacity(len as usize);
+        self.as_budget().with_charge_batch(|batch| {
+            self.metered_vm_scan_slices_in_linear_memory(
+                vmcaller,
+                &vm,
+                keys_pos,
+                len as usize,
+                |n, slice| {
+                    batch.charge(ContractCostType::VmMemRead, Some(slice.len() as u64));
+                    let scsym = ScSymbol(slice.try_into()?);
+                    let sym = Symbol::try_from(self.to_host_val(&ScVal::Symbol(scsym))?)?;
+                    key_syms.push(sym);
+                    Ok(())
+                },
+            )
+        })?;
+
+        // Step 2: extract all val Vals.
+        let vals_pos: u32 = vals_pos.into();
+        Vec::<Val>::charge_bulk_init_cpy(len as u64, self)?;
+        let mut vals: Vec<Val> = vec![Val::VOID.into(); len as usize];
+        self.metered_vm_read_vals_from_linear_memory::<8, Val>(
+            vmcaller,
+            &vm,
+            vals_pos,
+            vals.as_mut_slice(),
+            |buf| self.relative_to_absolute(Val::from_payload(u64::from_le_bytes(*buf))),
+        )?;
+        for v in vals.iter() {
+            self.check_val_integrity(*v)?;
+        }
+
+        // Step 3: turn pairs into a map.
+        let pair_iter = key_syms
+            .iter()
+            .map(|s| s.to_val())
+            .zip(vals.iter().cloned());
+        let map = HostMap::from_exact_iter(pair_iter, self)?;
+        self.add_host_object(map)
     }
 
-    fn map_new_from_linear_memory(
+    fn map_new_from_linear_memory_with_val_keys(
         &self,
         vmcaller: &mut VmCaller<Host>,
         keys_pos: U32Val,
         vals_pos: U32Val,
         len: U32Val,
     ) -> Result<MapObject, HostError> {
-        // Step 1: extract all key symbols.
+        self.check_protocol_version_lower_bound(MAP_FROM_VAL_KEYS_MIN_PROTOCOL)?;
         let VmSlice {
             vm,
             pos: keys_pos,
             len,
         } = self.decode_vmslice(keys_pos, len)?;
-        Vec::<Symbol>::charge_bulk_init_cpy(len as u64, self)?;
-        let mut key_syms: Vec<Symbol> = Vec::with_capacity(len as usize);
-        self.metered_vm_scan_slices_in_linear_memory(
+        self.check_object_len::<HostMap>(len as usize)?;
+
+        // Step 1: extract all key and val Vals.
+        Vec::<Val>::charge_bulk_init_cpy(len as u64, self)?;
+        let mut keys: Vec<Val> = vec![Val::VOID.into(); len as usize];
+        self.metered_vm_read_vals_from_linear_memory::<8, Val>(
             vmcaller,
             &vm,
             keys_pos,
-            len as usize,
-            |n, slice| {
-                self.charge_budget(ContractCostType::VmMemRead, Some(slice.len() as u64))?;
-                let scsym = ScSymbol(slice.try_into()?);
-                let sym = Symbol::try_from(self.to_host_val(&ScVal::Symbol(scsym))?)?;
-                key_syms.push(sym);
-                Ok(())
-            },
+            keys.as_mut_slice(),
+            |buf| self.relative_to_absolute(Val::from_payload(u64::from_le_bytes(*buf))),
         )?;
-
-        // Step 2: extract all val Vals.
         let vals_pos: u32 = vals_pos.into();
         Vec::<Val>::charge_bulk_init_cpy(len as u64, self)?;
         let mut vals: Vec<Val> = vec![Val::VOID.into(); len as usize];
@@ -1249,15 +2661,13 @@ impl VmCallerEnv for Host {
             vals.as_mut_slice(),
             |buf| self.relative_to_absolute(Val::from_payload(u64::from_le_bytes(*buf))),
         )?;
-        for v in vals.iter() {
+        for v in keys.iter().chain(vals.iter()) {
             self.check_val_integrity(*v)?;
         }
 
-        // Step 3: turn pairs into a map.
-        let pair_iter = key_syms
-            .iter()
-            .map(|s| s.to_val())
-            .zip(vals.iter().cloned());
+        // Step 2: turn pairs into a map. This also checks that the keys are
+        // sorted and unique.
+        let pair_iter = keys.iter().cloned().zip(vals.iter().cloned());
         let map = HostMap::from_exact_iter(pair_iter, self)?;
         self.add_host_object(map)
     }
@@ -1277,30 +2687,32 @@ impl VmCallerEnv for Host {
         } = self.decode_vmslice(keys_pos, len)?;
         self.visit_obj(map, |mapobj: &HostMap| {
             // Step 1: check all key symbols.
-            self.metered_vm_scan_slices_in_linear_memory(
-                vmcaller,
-                &vm,
-                keys_pos,
-                len as usize,
-                |n, slice| {
-                    let sym = Symbol::try_from(
-                        mapobj
-                            .map
-                            .get(n)
-                            .ok_or_else(|| {
-                                self.err(
-                                    ScErrorType::Object,
-                                    ScErrorCode::IndexBounds,
-                                    "vector out of bounds while unpacking map to linear memory",
-                                    &[],
-                                )
-                            })?
-                            .0,
-                    )?;
-                    self.check_symbol_matches(slice, sym)?;
-                    Ok(())
-                },
-            )?;
+            self.as_budget().with_charge_batch(|batch| {
+                self.metered_vm_scan_slices_in_linear_memory(
+                    vmcaller,
+                    &vm,
+                    keys_pos,
+                    len as usize,
+                    |n, slice| {
+                        let sym = Symbol::try_from(
+                            mapobj
+                                .map
+                                .get(n)
+                                .ok_or_else(|| {
+                                    self.err(
+                                        ScErrorType::Object,
+                                        ScErrorCode::IndexBounds,
+                                        "vector out of bounds while unpacking map to linear memory",
+                                        &[],
+                                    )
+                                })?
+                                .0,
+                        )?;
+                        self.check_symbol_matches(batch, slice, sym)?;
+                        Ok(())
+                    },
+                )
+            })?;
 
             // Step 2: write all vals.
             self.metered_vm_write_vals_to_linear_memory(
@@ -1320,6 +2732,51 @@ impl VmCallerEnv for Host {
         Ok(Val::VOID)
     }
 
+    fn map_copy_range_to_linear_memory(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        map: MapObject,
+        start: U32Val,
+        keys_pos: U32Val,
+        vals_pos: U32Val,
+        len: U32Val,
+    ) -> Result<U32Val, HostError> {
+        self.check_protocol_version_lower_bound(MAP_COPY_RANGE_MIN_PROTOCOL)?;
+        let VmSlice { vm, pos, len } = self.decode_vmslice(keys_pos, len)?;
+        let start: u32 = start.into();
+        self.visit_obj(map, |mapobj: &HostMap| {
+            let entries = mapobj.map.as_slice();
+            let start = start as usize;
+            if start > entries.len() {
+                return Err(self.err(
+                    ScErrorType::Object,
+                    ScErrorCode::IndexBounds,
+                    "map range start out of bounds while copying map to linear memory",
+                    &[],
+                ));
+            }
+            let end = entries.len().min(start.saturating_add(len as usize));
+            let window = &entries[start..end];
+            self.metered_vm_write_vals_to_linear_memory(vmcaller, &vm, pos, window, |pair| {
+                Ok(u64::to_le_bytes(
+                    self.absolute_to_relative(pair.0)?.get_payload(),
+                ))
+            })?;
+            self.metered_vm_write_vals_to_linear_memory(
+                vmcaller,
+                &vm,
+                vals_pos.into(),
+                window,
+                |pair| {
+                    Ok(u64::to_le_bytes(
+                        self.absolute_to_relative(pair.1)?.get_payload(),
+                    ))
+                },
+            )?;
+            self.usize_to_u32val(window.len())
+        })
+    }
+
     // endregion "map" module functions
     // region: "vec" module functions
 
@@ -1338,7 +2795,10 @@ impl VmCallerEnv for Host {
         self.check_val_integrity(x)?;
         let vnew = self.visit_obj(v, |hv: &HostVec| {
             self.validate_index_lt_bound(i, hv.len())?;
-            hv.set(i as usize, x, self.as_budget())
+            let unchanged = hv.as_slice()[i as usize].get_payload() == x.get_payload();
+            let vnew = hv.set(i as usize, x, self.as_budget())?;
+            self.record_vec_copy(vnew.len(), unchanged)?;
+            Ok(vnew)
         })?;
         self.add_host_object(vnew)
     }
@@ -1365,7 +2825,9 @@ impl VmCallerEnv for Host {
         let i: u32 = i.into();
         let vnew = self.visit_obj(v, |hv: &HostVec| {
             self.validate_index_lt_bound(i, hv.len())?;
-            hv.remove(i as usize, self.as_budget())
+            let vnew = hv.remove(i as usize, self.as_budget())?;
+            self.record_vec_copy(vnew.len(), false)?;
+            Ok(vnew)
         })?;
         self.add_host_object(vnew)
     }
@@ -1382,7 +2844,12 @@ impl VmCallerEnv for Host {
         x: Val,
     ) -> Result<VecObject, HostError> {
         self.check_val_integrity(x)?;
-        let vnew = self.visit_obj(v, |hv: &HostVec| hv.push_front(x, self.as_budget()))?;
+        let vnew = self.visit_obj(v, |hv: &HostVec| {
+            self.check_object_len::<HostVec>(hv.len() + 1)?;
+            let vnew = hv.push_front(x, self.as_budget())?;
+            self.record_vec_copy(vnew.len(), false)?;
+            Ok(vnew)
+        })?;
         self.add_host_object(vnew)
     }
 
@@ -1391,7 +2858,11 @@ impl VmCallerEnv for Host {
         _vmcaller: &mut VmCaller<Host>,
         v: VecObject,
     ) -> Result<VecObject, HostError> {
-        let vnew = self.visit_obj(v, |hv: &HostVec| hv.pop_front(self.as_budget()))?;
+        let vnew = self.visit_obj(v, |hv: &HostVec| {
+            let vnew = hv.pop_front(self.as_budget())?;
+            self.record_vec_copy(vnew.len(), false)?;
+            Ok(vnew)
+        })?;
         self.add_host_object(vnew)
     }
 
@@ -1402,7 +2873,12 @@ impl VmCallerEnv for Host {
         x: Val,
     ) -> Result<VecObject, HostError> {
         self.check_val_integrity(x)?;
-        let vnew = self.visit_obj(v, |hv: &HostVec| hv.push_back(x, self.as_budget()))?;
+        let vnew = self.visit_obj(v, |hv: &HostVec| {
+            self.check_object_len::<HostVec>(hv.len() + 1)?;
+            let vnew = hv.push_back(x, self.as_budget())?;
+            self.record_vec_copy(vnew.len(), false)?;
+            Ok(vnew)
+        })?;
         self.add_host_object(vnew)
     }
 
@@ -1411,7 +2887,11 @@ impl VmCallerEnv for Host {
         _vmcaller: &mut VmCaller<Host>,
         v: VecObject,
     ) -> Result<VecObject, HostError> {
-        let vnew = self.visit_obj(v, |hv: &HostVec| hv.pop_back(self.as_budget()))?;
+        let vnew = self.visit_obj(v, |hv: &HostVec| {
+            let vnew = hv.pop_back(self.as_budget())?;
+            self.record_vec_copy(vnew.len(), false)?;
+            Ok(vnew)
+        })?;
         self.add_host_object(vnew)
     }
 
@@ -1438,7 +2918,10 @@ impl VmCallerEnv for Host {
         self.check_val_integrity(x)?;
         let vnew = self.visit_obj(v, |hv: &HostVec| {
             self.validate_index_le_bound(i, hv.len())?;
-            hv.insert(i as usize, x, self.as_budget())
+            self.check_object_len::<HostVec>(hv.len() + 1)?;
+            let vnew = hv.insert(i as usize, x, self.as_budget())?;
+            self.record_vec_copy(vnew.len(), false)?;
+            Ok(vnew)
         })?;
         self.add_host_object(vnew)
     }
@@ -1454,7 +2937,10 @@ impl VmCallerEnv for Host {
                 if hv1.len() > u32::MAX as usize - hv2.len() {
                     Err(self.err_arith_overflow())
                 } else {
-                    hv1.append(hv2, self.as_budget())
+                    self.check_object_len::<HostVec>(hv1.len() + hv2.len())?;
+                    let vnew = hv1.append(hv2, self.as_budget())?;
+                    self.record_vec_copy(vnew.len(), hv2.len() == 0)?;
+                    Ok(vnew)
                 }
             })
         })?;
@@ -1472,7 +2958,9 @@ impl VmCallerEnv for Host {
         let end: u32 = end.into();
         let vnew = self.visit_obj(v, |hv: &HostVec| {
             let range = self.valid_range_from_start_end_bound(start, end, hv.len())?;
-            hv.slice(range, self.as_budget())
+            let vnew = hv.slice(range, self.as_budget())?;
+            self.record_vec_copy(vnew.len(), vnew.len() == hv.len())?;
+            Ok(vnew)
         })?;
         self.add_host_object(vnew)
     }
@@ -1524,6 +3012,48 @@ impl VmCallerEnv for Host {
         })
     }
 
+    fn vec_dedup(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        v: VecObject,
+    ) -> Result<VecObject, HostError> {
+        self.check_protocol_version_lower_bound(VEC_DEDUP_REVERSE_CONTAINS_MIN_PROTOCOL)?;
+        let vnew = self.visit_obj(v, |hv: &HostVec| {
+            let vnew = hv.dedup_by(|a, b| Ok(self.compare(a, b)?.is_eq()), self.as_budget())?;
+            self.record_vec_copy(vnew.len(), vnew.len() == hv.len())?;
+            Ok(vnew)
+        })?;
+        self.add_host_object(vnew)
+    }
+
+    fn vec_reverse(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        v: VecObject,
+    ) -> Result<VecObject, HostError> {
+        self.check_protocol_version_lower_bound(VEC_DEDUP_REVERSE_CONTAINS_MIN_PROTOCOL)?;
+        let vnew = self.visit_obj(v, |hv: &HostVec| {
+            let vnew = hv.reverse(self.as_budget())?;
+            self.record_vec_copy(vnew.len(), vnew.len() <= 1)?;
+            Ok(vnew)
+        })?;
+        self.add_host_object(vnew)
+    }
+
+    fn vec_contains_sorted(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        v: VecObject,
+        x: Val,
+    ) -> Result<Bool, Self::Error> {
+        self.check_protocol_version_lower_bound(VEC_DEDUP_REVERSE_CONTAINS_MIN_PROTOCOL)?;
+        self.check_val_integrity(x)?;
+        self.visit_obj(v, |hv: &HostVec| {
+            let res = hv.binary_search_by(|probe| self.compare(probe, &x), self.as_budget())?;
+            Ok(res.is_ok().into())
+        })
+    }
+
     fn vec_new_from_linear_memory(
         &self,
         vmcaller: &mut VmCaller<Host>,
@@ -1531,6 +3061,7 @@ impl VmCallerEnv for Host {
         len: U32Val,
     ) -> Result<VecObject, HostError> {
         let VmSlice { vm, pos, len } = self.decode_vmslice(vals_pos, len)?;
+        self.check_object_len::<HostVec>(len as usize)?;
         Vec::<Val>::charge_bulk_init_cpy(len as u64, self)?;
         let mut vals: Vec<Val> = vec![Val::VOID.to_val(); len as usize];
         self.metered_vm_read_vals_from_linear_memory::<8, Val>(
@@ -1581,8 +3112,12 @@ impl VmCallerEnv for Host {
         v: Val,
         t: StorageType,
     ) -> Result<Void, HostError> {
+        self.report_storage_op(StorageOp::Put)?;
         self.check_val_integrity(k)?;
         self.check_val_integrity(v)?;
+        self.check_contract_data_not_locked(k, &t)?;
+        #[cfg(any(test, feature = "testutils"))]
+        self.record_object_leak_roots(&[k, v])?;
         match t {
             StorageType::Temporary | StorageType::Persistent => {
                 self.put_contract_data_into_ledger(k, v, t)?
@@ -1603,13 +3138,14 @@ impl VmCallerEnv for Host {
         k: Val,
         t: StorageType,
     ) -> Result<Bool, HostError> {
+        self.report_storage_op(StorageOp::Has)?;
         self.check_val_integrity(k)?;
         let res = match t {
             StorageType::Temporary | StorageType::Persistent => {
                 let key = self.storage_key_from_rawval(k, t.try_into()?)?;
                 self.try_borrow_storage_mut()?
                     .has(&key, self.as_budget())
-                    .map_err(|e| self.decorate_contract_data_storage_error(e, k))?
+                    .map_err(|e| self.decorate_contract_data_storage_error(e, k, &key))?
             }
             StorageType::Instance => {
                 self.with_instance_storage(|s| Ok(s.map.get(&k, self)?.is_some()))?
@@ -1626,6 +3162,7 @@ impl VmCallerEnv for Host {
         k: Val,
         t: StorageType,
     ) -> Result<Val, HostError> {
+        self.report_storage_op(StorageOp::Get)?;
         self.check_val_integrity(k)?;
         match t {
             StorageType::Temporary | StorageType::Persistent => {
@@ -1633,7 +3170,7 @@ impl VmCallerEnv for Host {
                 let entry = self
                     .try_borrow_storage_mut()?
                     .get(&key, self.as_budget())
-                    .map_err(|e| self.decorate_contract_data_storage_error(e, k))?;
+                    .map_err(|e| self.decorate_contract_data_storage_error(e, k, &key))?;
                 match &entry.data {
                     LedgerEntryData::ContractData(e) => Ok(self.to_host_val(&e.val)?),
                     _ => Err(self.err(
@@ -1667,13 +3204,16 @@ impl VmCallerEnv for Host {
         k: Val,
         t: StorageType,
     ) -> Result<Void, HostError> {
+        self.report_storage_op(StorageOp::Del)?;
         self.check_val_integrity(k)?;
+        self.check_contract_data_not_locked(k, &t)?;
+        self.remove_contract_data_lock(k, &t)?;
         match t {
             StorageType::Temporary | StorageType::Persistent => {
                 let key = self.contract_data_key_from_rawval(k, t.try_into()?)?;
                 self.try_borrow_storage_mut()?
                     .del(&key, self.as_budget())
-                    .map_err(|e| self.decorate_contract_data_storage_error(e, k))?;
+                    .map_err(|e| self.decorate_contract_data_storage_error(e, k, &key))?;
             }
             StorageType::Instance => {
                 self.with_mut_instance_storage(|s| {
@@ -1697,6 +3237,7 @@ impl VmCallerEnv for Host {
         low_expiration_watermark: U32Val,
         high_expiration_watermark: U32Val,
     ) -> Result<Void, HostError> {
+        self.report_storage_op(StorageOp::Bump)?;
         self.check_val_integrity(k)?;
         if matches!(t, StorageType::Instance) {
             return Err(self.err(
@@ -1710,11 +3251,11 @@ impl VmCallerEnv for Host {
         self.try_borrow_storage_mut()?
             .bump(
                 self,
-                key,
+                Rc::clone(&key),
                 low_expiration_watermark.into(),
                 high_expiration_watermark.into(),
             )
-            .map_err(|e| self.decorate_contract_data_storage_error(e, k))?;
+            .map_err(|e| self.decorate_contract_data_storage_error(e, k, &key))?;
         Ok(Val::VOID)
     }
 
@@ -1788,6 +3329,122 @@ impl VmCallerEnv for Host {
         self.create_contract_internal(None, args)
     }
 
+    // Notes on metering: covered by the components.
+    fn clone_contract(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        source_contract: AddressObject,
+        salt: BytesObject,
+    ) -> Result<AddressObject, HostError> {
+        self.check_protocol_version_lower_bound(CLONE_CONTRACT_MIN_PROTOCOL)?;
+        let executable =
+            ContractExecutable::Wasm(self.retrieve_contract_wasm_hash(source_contract)?);
+        let deployer = self.add_host_object(ScAddress::Contract(
+            self.get_current_contract_id_internal()?,
+        ))?;
+        let contract_id_preimage = ContractIdPreimage::Address(ContractIdPreimageFromAddress {
+            address: self.visit_obj(deployer, |addr: &ScAddress| addr.metered_clone(self))?,
+            salt: self.u256_from_bytesobj_input("contract_id_salt", salt)?,
+        });
+        let args = CreateContractArgs {
+            contract_id_preimage,
+            executable,
+        };
+        self.create_contract_internal(Some(deployer), args)
+    }
+
+    // Notes on metering: covered by the components.
+    fn get_wasm_hash_of_contract(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        contract: AddressObject,
+    ) -> Result<BytesObject, HostError> {
+        self.check_protocol_version_lower_bound(WASM_HASH_OF_CONTRACT_MIN_PROTOCOL)?;
+        let wasm_hash = self.retrieve_contract_wasm_hash(contract)?;
+        self.add_host_object(self.scbytes_from_hash(&wasm_hash)?)
+    }
+
+    // Notes on metering: covered by the components.
+    fn get_written_entries_hash(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+    ) -> Result<BytesObject, HostError> {
+        self.check_protocol_version_lower_bound(WRITTEN_ENTRIES_HASH_MIN_PROTOCOL)?;
+        let hash = self.written_entries_hash()?;
+        self.add_host_object(self.scbytes_from_hash(&hash)?)
+    }
+
+    // Notes on metering: covered by the components.
+    fn instance_storage_size_bytes(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+    ) -> Result<U32Val, HostError> {
+        self.check_protocol_version_lower_bound(INSTANCE_STORAGE_STATS_MIN_PROTOCOL)?;
+        let storage = self.with_instance_storage(|s| self.host_map_to_scmap(&s.map))?;
+        let mut buf = vec![];
+        metered_write_xdr(self.budget_ref(), &storage, &mut buf)?;
+        self.usize_to_u32val(buf.len())
+    }
+
+    // Notes on metering: `len` is free
+    fn instance_storage_entry_count(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+    ) -> Result<U32Val, HostError> {
+        self.check_protocol_version_lower_bound(INSTANCE_STORAGE_STATS_MIN_PROTOCOL)?;
+        self.with_instance_storage(|s| self.usize_to_u32val(s.map.len()))
+    }
+
+    // Notes on metering: covered by the components.
+    fn get_classic_offer(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        seller: AddressObject,
+        offer_id: i64,
+    ) -> Result<Val, HostError> {
+        self.check_protocol_version_lower_bound(CLASSIC_OFFER_AND_POOL_MIN_PROTOCOL)?;
+        let ScAddress::Account(seller_id) = self.scaddress_from_address(seller)? else {
+            return Err(self.err(
+                ScErrorType::Object,
+                ScErrorCode::InvalidInput,
+                "offer seller must be an account address",
+                &[seller.to_val()],
+            ));
+        };
+        Ok(match self.classic_offer_to_map(seller_id, offer_id)? {
+            Some(map) => map.to_val(),
+            None => Val::VOID.to_val(),
+        })
+    }
+
+    // Notes on metering: covered by the components.
+    fn get_classic_liquidity_pool(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        pool_id: BytesObject,
+    ) -> Result<Val, HostError> {
+        self.check_protocol_version_lower_bound(CLASSIC_OFFER_AND_POOL_MIN_PROTOCOL)?;
+        let pool_id = self.hash_from_bytesobj_input("pool_id", pool_id)?;
+        Ok(match self.classic_liquidity_pool_to_map(pool_id)? {
+            Some(map) => map.to_val(),
+            None => Val::VOID.to_val(),
+        })
+    }
+
+    // Notes on metering: decoding the key is metered by its size, the lookup
+    // is covered by the storage.
+    fn ledger_entry_exists(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        key_xdr: BytesObject,
+    ) -> Result<Bool, HostError> {
+        self.check_protocol_version_lower_bound(LEDGER_ENTRY_EXISTS_MIN_PROTOCOL)?;
+        let key: LedgerKey = self.metered_from_xdr_obj(key_xdr)?;
+        let key = Rc::metered_new(key, self)?;
+        let exists = self.try_borrow_storage_mut()?.has(&key, self.as_budget())?;
+        Ok(exists.into())
+    }
+
     // Notes on metering: covered by the components.
     fn get_contract_id(
         &self,
@@ -1810,40 +3467,164 @@ impl VmCallerEnv for Host {
         self.add_host_object(ScAddress::Contract(hash_id))
     }
 
-    fn upload_wasm(
+    fn upload_wasm(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        wasm: BytesObject,
+    ) -> Result<BytesObject, HostError> {
+        let wasm_vec =
+            self.visit_obj(wasm, |bytes: &ScBytes| bytes.as_vec().metered_clone(self))?;
+        self.upload_contract_wasm(wasm_vec)
+    }
+
+    fn update_current_contract_wasm(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        hash: BytesObject,
+    ) -> Result<Void, HostError> {
+        let wasm_hash = self.hash_from_bytesobj_input("wasm_hash", hash)?;
+        if !self.wasm_exists(&wasm_hash)? {
+            return Err(self.err(
+                ScErrorType::Storage,
+                ScErrorCode::MissingValue,
+                "Wasm does not exist",
+                &[hash.to_val()],
+            ));
+        }
+        // Under an upgrade timelock the update is only announced here and
+        // activated by a later call.
+        let timelocks_enabled =
+            self.get_ledger_protocol_version()? >= UPGRADE_TIMELOCK_MIN_PROTOCOL;
+        if timelocks_enabled && !self.check_upgrade_timelock(&wasm_hash)? {
+            return Ok(Val::VOID);
+        }
+        let curr_contract_id = self.get_current_contract_id_internal()?;
+        let key = self.contract_instance_ledger_key(&curr_contract_id)?;
+        let mut instance = self.retrieve_contract_instance_from_storage(&key)?;
+        let new_executable = ContractExecutable::Wasm(wasm_hash);
+        self.emit_update_contract_event(&instance.executable, &new_executable)?;
+        if timelocks_enabled {
+            // The instance of the frame is written back at the end of the
+            // frame when the timelock or the instance storage is modified, so
+            // it must not revert the update.
+            let executable = new_executable.metered_clone(self)?;
+            self.with_current_frame_instance_mut(|instance| {
+                instance.executable = executable;
+                Ok(())
+            })?;
+        }
+        instance.executable = new_executable;
+        self.store_contract_instance(instance, curr_contract_id, &key)?;
+        Ok(Val::VOID)
+    }
+
+    // Notes on metering: covered by the components.
+    fn set_upgrade_timelock(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        delay_ledgers: U32Val,
+    ) -> Result<Void, HostError> {
+        self.check_protocol_version_lower_bound(UPGRADE_TIMELOCK_MIN_PROTOCOL)?;
+        self.set_upgrade_timelock_internal(delay_ledgers.into())?;
+        Ok(Val::VOID)
+    }
+
+    fn claim_idempotency_key(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        key: BytesObject,
+    ) -> Result<Void, HostError> {
+        self.check_protocol_version_lower_bound(IDEMPOTENCY_KEYS_MIN_PROTOCOL)?;
+        self.claim_idempotency_key_internal(key)?;
+        Ok(Val::VOID)
+    }
+
+    // Notes on metering: covered by the components.
+    fn rate_limit_check_and_consume(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        key: Val,
+        capacity: U32Val,
+        refill_per_ledger: U32Val,
+        cost: U32Val,
+    ) -> Result<Bool, HostError> {
+        self.check_protocol_version_lower_bound(RATE_LIMIT_MIN_PROTOCOL)?;
+        let consumed = self.rate_limit_check_and_consume_internal(
+            key,
+            capacity.into(),
+            refill_per_ledger.into(),
+            cost.into(),
+        )?;
+        Ok(consumed.into())
+    }
+
+    // Notes on metering: covered by the components.
+    fn can_put_contract_data(
         &self,
         _vmcaller: &mut VmCaller<Host>,
-        wasm: BytesObject,
-    ) -> Result<BytesObject, HostError> {
-        let wasm_vec =
-            self.visit_obj(wasm, |bytes: &ScBytes| bytes.as_vec().metered_clone(self))?;
-        self.upload_contract_wasm(wasm_vec)
+        k: Val,
+        v: Val,
+        t: StorageType,
+    ) -> Result<Bool, HostError> {
+        self.check_protocol_version_lower_bound(CAN_PUT_CONTRACT_DATA_MIN_PROTOCOL)?;
+        self.check_val_integrity(k)?;
+        self.check_val_integrity(v)?;
+        Ok(self.can_put_contract_data_internal(k, v, t)?.into())
     }
 
-    fn update_current_contract_wasm(
+    // Notes on metering: covered by components
+    fn put_contract_data_locked(
         &self,
-        _vmcaller: &mut VmCaller<Host>,
-        hash: BytesObject,
+        vmcaller: &mut VmCaller<Host>,
+        k: Val,
+        v: Val,
+        t: StorageType,
+        unlock_ledger: U32Val,
     ) -> Result<Void, HostError> {
-        let wasm_hash = self.hash_from_bytesobj_input("wasm_hash", hash)?;
-        if !self.wasm_exists(&wasm_hash)? {
-            return Err(self.err(
-                ScErrorType::Storage,
-                ScErrorCode::MissingValue,
-                "Wasm does not exist",
-                &[hash.to_val()],
-            ));
-        }
-        let curr_contract_id = self.get_current_contract_id_internal()?;
-        let key = self.contract_instance_ledger_key(&curr_contract_id)?;
-        let mut instance = self.retrieve_contract_instance_from_storage(&key)?;
-        let new_executable = ContractExecutable::Wasm(wasm_hash);
-        self.emit_update_contract_event(&instance.executable, &new_executable)?;
-        instance.executable = new_executable;
-        self.store_contract_instance(instance, curr_contract_id, &key)?;
+        self.check_protocol_version_lower_bound(DATA_LOCKS_MIN_PROTOCOL)?;
+        let unlock_ledger = u32::from(unlock_ledger);
+        self.check_unlock_ledger(unlock_ledger)?;
+        self.put_contract_data(vmcaller, k, v, t.clone())?;
+        self.put_contract_data_locked_internal(k, &t, unlock_ledger)?;
         Ok(Val::VOID)
     }
 
+    fn acl_add(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        k: Val,
+        t: StorageType,
+        address: AddressObject,
+    ) -> Result<Bool, HostError> {
+        self.check_protocol_version_lower_bound(ACL_FNS_MIN_PROTOCOL)?;
+        self.check_val_integrity(k)?;
+        Ok(self.acl_add_internal(vmcaller, k, t, address)?.into())
+    }
+
+    fn acl_remove(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        k: Val,
+        t: StorageType,
+        address: AddressObject,
+    ) -> Result<Bool, HostError> {
+        self.check_protocol_version_lower_bound(ACL_FNS_MIN_PROTOCOL)?;
+        self.check_val_integrity(k)?;
+        Ok(self.acl_remove_internal(vmcaller, k, t, address)?.into())
+    }
+
+    fn acl_contains(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        k: Val,
+        t: StorageType,
+        address: AddressObject,
+    ) -> Result<Bool, HostError> {
+        self.check_protocol_version_lower_bound(ACL_FNS_MIN_PROTOCOL)?;
+        self.check_val_integrity(k)?;
+        Ok(self.acl_contains_internal(vmcaller, k, t, address)?.into())
+    }
+
     // endregion "ledger" module functions
     // region: "call" module functions
 
@@ -1884,19 +3665,23 @@ impl VmCallerEnv for Host {
         args: VecObject,
     ) -> Result<Val, HostError> {
         let argvec = self.call_args_from_obj(args)?;
+        let callee_id = self.contract_id_from_address(contract_address)?;
         // this is the "loosened" path of calling a contract.
         // TODO: A `reentry` flag will be passed from `try_call` into here.
         // For now, we are passing in `ContractReentryMode::Prohibited` to disable
         // reentry.
         let res = self.call_n_internal(
-            &self.contract_id_from_address(contract_address)?,
+            &callee_id,
             func,
             argvec.as_slice(),
             ContractReentryMode::Prohibited,
             false,
         );
         match res {
-            Ok(rv) => Ok(rv),
+            Ok(rv) => {
+                self.set_failed_call(None)?;
+                Ok(rv)
+            }
             Err(e) => {
                 self.error(
                     e.error,
@@ -1908,8 +3693,8 @@ impl VmCallerEnv for Host {
                 // abort execution.
                 if e.is_recoverable() {
                     // Pass contract errors through.
-                    if e.error.is_type(ScErrorType::Contract) {
-                        Ok(e.error.to_val())
+                    let error = if e.error.is_type(ScErrorType::Contract) {
+                        e.error
                     } else {
                         // Narrow all the remaining host errors down to a single
                         // error type. We don't want to expose the granular host
@@ -1920,12 +3705,11 @@ impl VmCallerEnv for Host {
                         // hashed into blockchain.
                         // The granular error codes are still observable with
                         // diagnostic events.
-                        Ok(Error::from_type_and_code(
-                            ScErrorType::Context,
-                            ScErrorCode::InvalidAction,
-                        )
-                        .to_val())
-                    }
+                        Error::from_type_and_code(ScErrorType::Context, ScErrorCode::InvalidAction)
+                    };
+                    self.set_failed_call(Some((error, callee_id, func)))?;
+                    self.call_on_error_handler(error, contract_address, func)?;
+                    Ok(error.to_val())
                 } else {
                     Err(e)
                 }
@@ -2032,7 +3816,8 @@ impl VmCallerEnv for Host {
         lm_pos: U32Val,
         len: U32Val,
     ) -> Result<SymbolObject, HostError> {
-        self.memobj_new_from_linear_memory::<ScSymbol>(vmcaller, lm_pos, len)
+        let bytes = self.metered_vec_from_linear_memory(vmcaller, lm_pos, len)?;
+        self.intern_symbol(ScSymbol(bytes.try_into()?))
     }
 
     fn symbol_index_in_linear_memory(
@@ -2044,20 +3829,22 @@ impl VmCallerEnv for Host {
     ) -> Result<U32Val, HostError> {
         let VmSlice { vm, pos, len } = self.decode_vmslice(lm_pos, len)?;
         let mut found = None;
-        self.metered_vm_scan_slices_in_linear_memory(
-            vmcaller,
-            &vm,
-            pos,
-            len as usize,
-            |i, slice| {
-                if self.symbol_matches(slice, sym)? {
-                    if found.is_none() {
-                        found = Some(self.usize_to_u32(i)?)
+        self.as_budget().with_charge_batch(|batch| {
+            self.metered_vm_scan_slices_in_linear_memory(
+                vmcaller,
+                &vm,
+                pos,
+                len as usize,
+                |i, slice| {
+                    if self.symbol_matches(batch, slice, sym)? {
+                        if found.is_none() {
+                            found = Some(self.usize_to_u32(i)?)
+                        }
                     }
-                }
-                Ok(())
-            },
-        )?;
+                    Ok(())
+                },
+            )
+        })?;
         match found {
             None => Err(self.err(
                 ScErrorType::Value,
@@ -2074,6 +3861,51 @@ impl VmCallerEnv for Host {
         self.add_host_object(self.scbytes_from_vec(Vec::<u8>::new())?)
     }
 
+    // Notes on metering: the combined buffer is charged upfront, as in
+    // `bytes_append`, and the slices are scanned twice: once for their total
+    // length and once to copy them.
+    fn bytes_append_from_linear_memory(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        b: BytesObject,
+        slices_pos: U32Val,
+        len: U32Val,
+    ) -> Result<BytesObject, HostError> {
+        self.check_protocol_version_lower_bound(BYTES_APPEND_FROM_LINEAR_MEMORY_MIN_PROTOCOL)?;
+        let VmSlice { vm, pos, len } = self.decode_vmslice(slices_pos, len)?;
+        let mut appended_len = 0;
+        self.metered_vm_scan_slices_in_linear_memory(
+            vmcaller,
+            &vm,
+            pos,
+            len as usize,
+            |_, slice| {
+                appended_len = self.validate_usize_sum_fits_in_u32(appended_len, slice.len())?;
+                Ok(())
+            },
+        )?;
+        let mut vnew = self.visit_obj(b, |sb: &ScBytes| {
+            let new_len = self.validate_usize_sum_fits_in_u32(sb.len(), appended_len)?;
+            self.check_object_len::<ScBytes>(new_len)?;
+            Vec::<u8>::charge_bulk_init_cpy(new_len as u64, self)?;
+            let mut vnew: Vec<u8> = Vec::with_capacity(new_len);
+            vnew.extend_from_slice(sb.as_slice());
+            Ok(vnew)
+        })?;
+        self.charge_budget(ContractCostType::VmMemRead, Some(appended_len as u64))?;
+        self.metered_vm_scan_slices_in_linear_memory(
+            vmcaller,
+            &vm,
+            pos,
+            len as usize,
+            |_, slice| {
+                vnew.extend_from_slice(slice);
+                Ok(())
+            },
+        )?;
+        self.add_host_object(self.scbytes_from_vec(vnew)?)
+    }
+
     // Notes on metering: `get_mut` is free
     fn bytes_put(
         &self,
@@ -2175,6 +4007,87 @@ impl VmCallerEnv for Host {
         self.usize_to_u32val(len)
     }
 
+    // Notes on metering: the parts are visited without being copied. The
+    // string is charged upfront as a bulk copy of its maximum length, where
+    // integers take the length of the widest value of their type.
+    fn string_new_from_vals(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        fmt_parts: VecObject,
+    ) -> Result<StringObject, HostError> {
+        self.check_protocol_version_lower_bound(STRING_FROM_VALS_MIN_PROTOCOL)?;
+        let parts = self.visit_obj(fmt_parts, |hv: &HostVec| hv.to_vec(self.budget_ref()))?;
+        let mut max_len: u64 = 0;
+        for part in parts.iter() {
+            let part_len = if let Ok(obj) = StringObject::try_from(*part) {
+                self.visit_obj(obj, |s: &ScString| Ok(s.len()))?
+            } else if let Ok(obj) = SymbolObject::try_from(*part) {
+                self.visit_obj(obj, |s: &ScSymbol| Ok(s.len()))?
+            } else if let Ok(sym) = SymbolSmall::try_from(*part) {
+                SymbolStr::from(sym).len()
+            } else if U32Val::try_from(*part).is_ok() {
+                10
+            } else if I32Val::try_from(*part).is_ok() {
+                11
+            } else if U64Val::try_from(*part).is_ok() || I64Val::try_from(*part).is_ok() {
+                20
+            } else if U128Val::try_from(*part).is_ok() {
+                39
+            } else if I128Val::try_from(*part).is_ok() {
+                40
+            } else if U256Val::try_from(*part).is_ok() || I256Val::try_from(*part).is_ok() {
+                78
+            } else {
+                return Err(self.err(
+                    ScErrorType::Object,
+                    ScErrorCode::UnexpectedType,
+                    "string parts must be strings, symbols or integers",
+                    &[*part],
+                ));
+            };
+            max_len = max_len.saturating_add(part_len as u64);
+        }
+        Vec::<u8>::charge_bulk_init_cpy(max_len, self)?;
+        let mut buf: Vec<u8> = vec![];
+        for part in parts.iter() {
+            if let Ok(obj) = StringObject::try_from(*part) {
+                self.visit_obj(obj, |s: &ScString| {
+                    buf.extend_from_slice(s.as_slice());
+                    Ok(())
+                })?;
+            } else if let Ok(obj) = SymbolObject::try_from(*part) {
+                self.visit_obj(obj, |s: &ScSymbol| {
+                    buf.extend_from_slice(s.as_slice());
+                    Ok(())
+                })?;
+            } else if let Ok(sym) = SymbolSmall::try_from(*part) {
+                let sstr: SymbolStr = sym.into();
+                let bytes: &[u8] = sstr.as_ref();
+                buf.extend_from_slice(bytes);
+            } else {
+                let digits = if let Ok(u) = U32Val::try_from(*part) {
+                    u32::from(u).to_string()
+                } else if let Ok(i) = I32Val::try_from(*part) {
+                    i32::from(i).to_string()
+                } else if U64Val::try_from(*part).is_ok() {
+                    u64::try_from_val(self, part)?.to_string()
+                } else if I64Val::try_from(*part).is_ok() {
+                    i64::try_from_val(self, part)?.to_string()
+                } else if U128Val::try_from(*part).is_ok() {
+                    u128::try_from_val(self, part)?.to_string()
+                } else if I128Val::try_from(*part).is_ok() {
+                    i128::try_from_val(self, part)?.to_string()
+                } else if U256Val::try_from(*part).is_ok() {
+                    U256::try_from_val(self, part)?.to_string()
+                } else {
+                    I256::try_from_val(self, part)?.to_string()
+                };
+                buf.extend_from_slice(digits.as_bytes());
+            }
+        }
+        self.add_host_object(ScString(buf.try_into()?))
+    }
+
     // Notes on metering: `push` is free
     fn bytes_push(
         &self,
@@ -2187,6 +4100,7 @@ impl VmCallerEnv for Host {
             // we allocate the new vector to be able to hold `len + 1` bytes, so that the push
             // will not trigger a reallocation, causing data to be cloned twice.
             let len = self.validate_usize_sum_fits_in_u32(hv.len(), 1)?;
+            self.check_object_len::<ScBytes>(len)?;
             Vec::<u8>::charge_bulk_init_cpy(len as u64, self)?;
             let mut vnew: Vec<u8> = Vec::with_capacity(len);
             vnew.extend_from_slice(hv.as_slice());
@@ -2273,6 +4187,7 @@ impl VmCallerEnv for Host {
             // we allocate the new vector to be able to hold `len + 1` bytes, so that the insert
             // will not trigger a reallocation, causing data to be cloned twice.
             let len = self.validate_usize_sum_fits_in_u32(hv.len(), 1)?;
+            self.check_object_len::<ScBytes>(len)?;
             Vec::<u8>::charge_bulk_init_cpy(len as u64, self)?;
             let mut vnew: Vec<u8> = Vec::with_capacity(len);
             vnew.extend_from_slice(hv.as_slice());
@@ -2293,6 +4208,7 @@ impl VmCallerEnv for Host {
                 // we allocate large enough memory to hold the new combined vector, so that
                 // allocation only happens once, and charge for it upfront.
                 let len = self.validate_usize_sum_fits_in_u32(sb1.len(), sb2.len())?;
+                self.check_object_len::<ScBytes>(len)?;
                 Vec::<u8>::charge_bulk_init_cpy(len as u64, self)?;
                 let mut vnew: Vec<u8> = Vec::with_capacity(len);
                 vnew.extend_from_slice(sb1.as_slice());
@@ -2303,6 +4219,29 @@ impl VmCallerEnv for Host {
         self.add_host_object(ScBytes(vnew.try_into()?))
     }
 
+    // Notes on metering: covered by components.
+    fn val_to_canonical_bytes(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        v: Val,
+    ) -> Result<BytesObject, HostError> {
+        self.check_protocol_version_lower_bound(CANONICAL_VAL_BYTES_MIN_PROTOCOL)?;
+        let encoding = self.canonical_encoding(v)?;
+        self.add_host_object(self.scbytes_from_vec(encoding)?)
+    }
+
+    // Notes on metering: covered by components.
+    fn bytes_decompress_zstd(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        b: BytesObject,
+        max_out_len: U32Val,
+    ) -> Result<BytesObject, HostError> {
+        self.check_protocol_version_lower_bound(ZSTD_DECOMPRESSION_MIN_PROTOCOL)?;
+        let decompressed = self.zstd_decompress_bytesobj_input(b, max_out_len.into())?;
+        self.add_host_object(self.scbytes_from_vec(decompressed)?)
+    }
+
     fn bytes_slice(
         &self,
         _vmcaller: &mut VmCaller<Host>,
@@ -2375,6 +4314,93 @@ impl VmCallerEnv for Host {
         self.recover_key_ecdsa_secp256k1_internal(&hash, &sig, rid)
     }
 
+    fn verify_multisig_ed25519(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        keys: VecObject,
+        weights: VecObject,
+        threshold: U32Val,
+        payload: BytesObject,
+        sigs: VecObject,
+    ) -> Result<Bool, HostError> {
+        self.check_protocol_version_lower_bound(MULTISIG_ED25519_MIN_PROTOCOL)?;
+        let keys = self.call_args_from_obj(keys)?;
+        let weights = self.call_args_from_obj(weights)?;
+        let sigs = self.call_args_from_obj(sigs)?;
+        let res = self.verify_multisig_ed25519_internal(
+            keys.as_slice(),
+            weights.as_slice(),
+            threshold.into(),
+            payload,
+            sigs.as_slice(),
+        )?;
+        Ok(res.into())
+    }
+
+    // Notes on metering: covered by components.
+    fn verify_oracle_attestation(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        payload: BytesObject,
+        signature: BytesObject,
+        public_key: BytesObject,
+        max_age: U64Val,
+    ) -> Result<MapObject, HostError> {
+        self.check_protocol_version_lower_bound(ORACLE_ATTESTATION_MIN_PROTOCOL)?;
+        let max_age = u64::try_from_val(self, &max_age.to_val())?;
+        self.verify_oracle_attestation_internal(payload, signature, public_key, max_age)
+    }
+
+    // Notes on metering: covered by components.
+    fn ristretto255_point_add(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        a: BytesObject,
+        b: BytesObject,
+    ) -> Result<BytesObject, HostError> {
+        self.check_protocol_version_lower_bound(RISTRETTO255_FNS_MIN_PROTOCOL)?;
+        self.ristretto255_point_add_internal(a, b)
+    }
+
+    // Notes on metering: covered by components.
+    fn ristretto255_scalar_mul(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        scalar: BytesObject,
+        point: BytesObject,
+    ) -> Result<BytesObject, HostError> {
+        self.check_protocol_version_lower_bound(RISTRETTO255_FNS_MIN_PROTOCOL)?;
+        self.ristretto255_scalar_mul_internal(scalar, point)
+    }
+
+    // Notes on metering: covered by components.
+    fn ristretto255_basepoint_mul(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        scalar: BytesObject,
+    ) -> Result<BytesObject, HostError> {
+        self.check_protocol_version_lower_bound(RISTRETTO255_FNS_MIN_PROTOCOL)?;
+        self.ristretto255_basepoint_mul_internal(scalar)
+    }
+
+    // Notes on metering: covered by components.
+    fn verify_sig_ecdsa_secp256r1(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        public_key: BytesObject,
+        msg_digest: BytesObject,
+        signature: BytesObject,
+    ) -> Result<Void, HostError> {
+        self.check_protocol_version_lower_bound(SECP256R1_VERIFY_MIN_PROTOCOL)?;
+        let digest = self.hash_from_bytesobj_input("msg_digest", msg_digest)?;
+        let sig =
+            self.fixed_length_bytes_from_bytesobj_input::<[u8; 64], 64>("signature", signature)?;
+        let res = self.visit_obj(public_key, |pk: &ScBytes| {
+            self.verify_sig_ecdsa_secp256r1_internal(pk.as_slice(), &digest.0, &sig)
+        });
+        Ok(res?.into())
+    }
+
     // endregion "crypto" module functions
     // region: "test" module functions
 
@@ -2403,30 +4429,42 @@ impl VmCallerEnv for Host {
         _vmcaller: &mut VmCaller<Self::VmUserState>,
         address: AddressObject,
     ) -> Result<Void, Self::Error> {
-        let args = self.with_current_frame(|f| {
-            let args = match f {
-                Frame::ContractVM { args, .. } => args,
-                Frame::HostFunction(_) => {
-                    return Err(self.err(
-                        ScErrorType::Context,
-                        ScErrorCode::InternalError,
-                        "require_auth is not suppported for host fns",
-                        &[],
-                    ))
-                }
-                Frame::Token(_, _, args, _) => args,
-                #[cfg(any(test, feature = "testutils"))]
-                Frame::TestContract(c) => &c.args,
-            };
-            args.metered_clone(self)
-        })?;
-
+        let args = self.current_frame_args_for_auth()?;
         Ok(self
             .try_borrow_authorization_manager()?
             .require_auth(self, address, args)?
             .into())
     }
 
+    // Notes on metering: covered by the components. The arguments of the
+    // invocation are cloned from the frame once and shared by all the
+    // addresses.
+    fn require_auth_many(
+        &self,
+        _vmcaller: &mut VmCaller<Self::VmUserState>,
+        addresses: VecObject,
+    ) -> Result<Void, Self::Error> {
+        self.check_protocol_version_lower_bound(REQUIRE_AUTH_MANY_MIN_PROTOCOL)?;
+        let vals = self.visit_obj(addresses, |a: &HostVec| a.to_vec(self.budget_ref()))?;
+        Vec::<AddressObject>::charge_bulk_init_cpy(vals.len() as u64, self)?;
+        let mut addresses = Vec::with_capacity(vals.len());
+        for v in vals {
+            addresses.push(AddressObject::try_from(v).map_err(|_| {
+                self.err(
+                    ScErrorType::Object,
+                    ScErrorCode::UnexpectedType,
+                    "require_auth_many expects a vector of addresses",
+                    &[v],
+                )
+            })?);
+        }
+        let args = self.current_frame_args_for_auth()?;
+        Ok(self
+            .try_borrow_authorization_manager()?
+            .require_auth_many(self, &addresses, args)?
+            .into())
+    }
+
     fn authorize_as_curr_contract(
         &self,
         _vmcaller: &mut VmCaller<Self::VmUserState>,
@@ -2484,6 +4522,62 @@ impl VmCallerEnv for Host {
         }
     }
 
+    fn is_source_account(
+        &self,
+        _vmcaller: &mut VmCaller<Self::VmUserState>,
+        address: AddressObject,
+    ) -> Result<Bool, Self::Error> {
+        self.check_protocol_version_lower_bound(SOURCE_ACCOUNT_FNS_MIN_PROTOCOL)?;
+        let source_account = self.try_borrow_source_account()?.metered_clone(self)?;
+        let Some(source_account) = source_account else {
+            return Ok(false.into());
+        };
+        self.visit_obj(address, |addr: &ScAddress| match addr {
+            ScAddress::Account(acc) => Ok((*acc == source_account).into()),
+            ScAddress::Contract(_) => Ok(false.into()),
+        })
+    }
+
+    fn get_source_account(
+        &self,
+        _vmcaller: &mut VmCaller<Self::VmUserState>,
+    ) -> Result<Val, Self::Error> {
+        self.check_protocol_version_lower_bound(SOURCE_ACCOUNT_FNS_MIN_PROTOCOL)?;
+        match self.source_account_address()? {
+            Some(addr) => Ok(addr.into()),
+            None => Ok(().into()),
+        }
+    }
+
+    // Notes on metering: covered by `Compare<ScAddress>`.
+    fn address_cmp(
+        &self,
+        _vmcaller: &mut VmCaller<Self::VmUserState>,
+        a: AddressObject,
+        b: AddressObject,
+    ) -> Result<i64, Self::Error> {
+        self.check_protocol_version_lower_bound(ADDRESS_CMP_AND_HASH_ID_MIN_PROTOCOL)?;
+        let ord = self.visit_obj(a, |a: &ScAddress| {
+            self.visit_obj(b, |b: &ScAddress| self.compare(a, b))
+        })?;
+        Ok(match ord {
+            Ordering::Less => -1,
+            Ordering::Equal => 0,
+            Ordering::Greater => 1,
+        })
+    }
+
+    // Notes on metering: covered by `metered_hash_xdr`.
+    fn address_to_hash_id(
+        &self,
+        _vmcaller: &mut VmCaller<Self::VmUserState>,
+        address: AddressObject,
+    ) -> Result<BytesObject, Self::Error> {
+        self.check_protocol_version_lower_bound(ADDRESS_CMP_AND_HASH_ID_MIN_PROTOCOL)?;
+        let hash = self.visit_obj(address, |addr: &ScAddress| self.metered_hash_xdr(addr))?;
+        self.add_host_object(self.scbytes_from_slice(&hash)?)
+    }
+
     // endregion "address" module functions
     // region: "prng" module functions
 
@@ -2496,10 +4590,7 @@ impl VmCallerEnv for Host {
             let slice: &[u8] = bytes.as_ref();
             self.charge_budget(ContractCostType::HostMemCpy, Some(prng::SEED_BYTES))?;
             if let Ok(seed32) = slice.try_into() {
-                self.with_current_prng(|prng| {
-                    *prng = Prng::new_from_seed(seed32);
-                    Ok(())
-                })?;
+                self.prng_reseed_internal(seed32)?;
                 Ok(Val::VOID)
             } else if let Ok(len) = u32::try_from(slice.len()) {
                 Err(self.err(
@@ -2524,9 +4615,14 @@ impl VmCallerEnv for Host {
         _vmcaller: &mut VmCaller<Self::VmUserState>,
         length: U32Val,
     ) -> Result<BytesObject, Self::Error> {
-        self.add_host_object(
-            self.with_current_prng(|prng| prng.bytes_new(length.into(), self.as_budget()))?,
-        )
+        let bytes = self.add_host_object(self.prng_bytes_new_internal(length.into())?)?;
+        self.prng_draw_diagnostics("bytes_new", || {
+            vec![
+                InternalDiagnosticArg::HostVal(length.to_val()),
+                InternalDiagnosticArg::HostVal(bytes.to_val()),
+            ]
+        })?;
+        Ok(bytes)
     }
 
     fn prng_u64_in_inclusive_range(
@@ -2535,7 +4631,30 @@ impl VmCallerEnv for Host {
         lo: u64,
         hi: u64,
     ) -> Result<u64, Self::Error> {
-        self.with_current_prng(|prng| prng.u64_in_inclusive_range(lo..=hi, self.as_budget()))
+        #[cfg(any(test, feature = "testutils"))]
+        let programmed = self.try_borrow_programmed_prng_outputs_mut()?.pop_front();
+        #[cfg(not(any(test, feature = "testutils")))]
+        let programmed: Option<u64> = None;
+        let res = match programmed {
+            Some(res) if !(lo..=hi).contains(&res) => {
+                return Err(self.err(
+                    ScErrorType::Value,
+                    ScErrorCode::InvalidInput,
+                    "programmed PRNG output is out of the requested range",
+                    &[],
+                ))
+            }
+            Some(res) => res,
+            None => self.prng_u64_in_inclusive_range_internal(lo, hi)?,
+        };
+        self.prng_draw_diagnostics("u64_range", || {
+            vec![
+                InternalDiagnosticArg::XdrVal(ScVal::U64(lo)),
+                InternalDiagnosticArg::XdrVal(ScVal::U64(hi)),
+                InternalDiagnosticArg::XdrVal(ScVal::U64(res)),
+            ]
+        })?;
+        Ok(res)
     }
 
     fn prng_vec_shuffle(
@@ -2543,10 +4662,15 @@ impl VmCallerEnv for Host {
         _vmcaller: &mut VmCaller<Self::VmUserState>,
         vec: VecObject,
     ) -> Result<VecObject, Self::Error> {
-        let vnew = self.visit_obj(vec, |v: &HostVec| {
-            self.with_current_prng(|prng| prng.vec_shuffle(v, self.as_budget()))
+        let vnew = self.visit_obj(vec, |v: &HostVec| self.prng_vec_shuffle_internal(v))?;
+        let shuffled = self.add_host_object(vnew)?;
+        self.prng_draw_diagnostics("shuffle", || {
+            vec![
+                InternalDiagnosticArg::HostVal(vec.to_val()),
+                InternalDiagnosticArg::HostVal(shuffled.to_val()),
+            ]
         })?;
-        self.add_host_object(vnew)
+        Ok(shuffled)
     }
     // endregion "prng" module functions
 }
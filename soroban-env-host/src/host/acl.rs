@@ -0,0 +1,319 @@
+//! Access control lists stored in contract data, see the `acl_add`,
+//! `acl_remove` and `acl_contains` host functions.
+//!
+//! The members of a list are kept sorted in pages of at most
+//! [`ACL_PAGE_CAPACITY`] addresses. Every page is a contract data entry of its
+//! own, under the key `(ACL_TAG, k, page_id)` for the list `k`, and the index
+//! of the list, under the key `(ACL_TAG, k)`, holds the first member and the
+//! id of every page in order. A membership check reads the index and a single
+//! page, and an addition or a removal rewrites at most the index and two
+//! pages, so the cost of an operation grows with the number of pages rather
+//! than with the number of members.
+//!
+//! Every entry of a list that is accessed is bumped, unless the list is in the
+//! instance storage, which is bumped along with the instance.
+
+use std::cmp::min;
+
+use crate::{
+    xdr::{ScErrorCode, ScErrorType},
+    AddressObject, EnvBase, Host, HostError, StorageType, Symbol, TryFromVal, U32Val, Val,
+    VecObject, VmCaller, VmCallerEnv,
+};
+
+/// Symbol that tags the contract data keys of the entries of access control
+/// lists.
+pub(crate) const ACL_TAG: &str = "Acl";
+
+/// Maximum number of members of a page of an access control list. A page that
+/// goes over it is split in two halves.
+pub(crate) const ACL_PAGE_CAPACITY: u32 = 64;
+
+/// Number of ledgers the entries of an access control list are bumped to when
+/// accessed (30 days), capped by the maximum expiration of the network, and
+/// the number of remaining ledgers (29 days) under which they get bumped.
+const ACL_BUMP_AMOUNT: u32 = 30 * 17280;
+const ACL_LIFETIME_THRESHOLD: u32 = ACL_BUMP_AMOUNT - 17280;
+
+// The index of an access control list: the first member of every page in
+// order, the ids of these pages, and the next unused page id.
+struct AclIndex {
+    firsts: VecObject,
+    page_ids: VecObject,
+    next_page_id: u32,
+}
+
+impl Host {
+    fn acl_malformed(&self, k: Val) -> HostError {
+        self.err(
+            ScErrorType::Storage,
+            ScErrorCode::UnexpectedType,
+            "malformed access control list",
+            &[k],
+        )
+    }
+
+    fn acl_index_key(&self, k: Val) -> Result<Val, HostError> {
+        let tag = Symbol::try_from_val(self, &ACL_TAG)?;
+        Ok(self.vec_new_from_slice(&[tag.to_val(), k])?.into())
+    }
+
+    fn acl_page_key(&self, k: Val, page_id: u32) -> Result<Val, HostError> {
+        let tag = Symbol::try_from_val(self, &ACL_TAG)?;
+        Ok(self
+            .vec_new_from_slice(&[tag.to_val(), k, U32Val::from(page_id).to_val()])?
+            .into())
+    }
+
+    fn acl_bump(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        key: Val,
+        t: &StorageType,
+    ) -> Result<(), HostError> {
+        if matches!(t, StorageType::Instance) {
+            return Ok(());
+        }
+        let ledger_seq = self.with_ledger_info_provider(|li| Ok(li.sequence_number()))?;
+        let high = min(
+            ACL_BUMP_AMOUNT,
+            self.max_expiration_ledger()?.saturating_sub(ledger_seq),
+        );
+        let low = min(ACL_LIFETIME_THRESHOLD, high);
+        self.bump_contract_data(vmcaller, key, t.clone(), low.into(), high.into())?;
+        Ok(())
+    }
+
+    fn acl_put(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        key: Val,
+        v: Val,
+        t: &StorageType,
+    ) -> Result<(), HostError> {
+        self.put_contract_data(vmcaller, key, v, t.clone())?;
+        self.acl_bump(vmcaller, key, t)
+    }
+
+    fn acl_read_index(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        k: Val,
+        t: &StorageType,
+    ) -> Result<Option<AclIndex>, HostError> {
+        let key = self.acl_index_key(k)?;
+        if !bool::from(self.has_contract_data(vmcaller, key, t.clone())?) {
+            return Ok(None);
+        }
+        self.acl_bump(vmcaller, key, t)?;
+        let index = self.get_contract_data(vmcaller, key, t.clone())?;
+        let index = VecObject::try_from(index).map_err(|_| self.acl_malformed(k))?;
+        let field = |vmcaller: &mut VmCaller<Host>, i: u32| self.vec_get(vmcaller, index, i.into());
+        Ok(Some(AclIndex {
+            firsts: VecObject::try_from(field(vmcaller, 0)?).map_err(|_| self.acl_malformed(k))?,
+            page_ids: VecObject::try_from(field(vmcaller, 1)?)
+                .map_err(|_| self.acl_malformed(k))?,
+            next_page_id: U32Val::try_from(field(vmcaller, 2)?)
+                .map_err(|_| self.acl_malformed(k))?
+                .into(),
+        }))
+    }
+
+    fn acl_write_index(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        k: Val,
+        t: &StorageType,
+        index: &AclIndex,
+    ) -> Result<(), HostError> {
+        let key = self.acl_index_key(k)?;
+        let v = self.vec_new_from_slice(&[
+            index.firsts.to_val(),
+            index.page_ids.to_val(),
+            U32Val::from(index.next_page_id).to_val(),
+        ])?;
+        self.acl_put(vmcaller, key, v.to_val(), t)
+    }
+
+    fn acl_new_page_id(&self, k: Val, index: &mut AclIndex) -> Result<u32, HostError> {
+        let page_id = index.next_page_id;
+        index.next_page_id = page_id.checked_add(1).ok_or_else(|| {
+            self.err(
+                ScErrorType::Storage,
+                ScErrorCode::ExceededLimit,
+                "too many access control list pages",
+                &[k],
+            )
+        })?;
+        Ok(page_id)
+    }
+
+    // Returns the position in the index of the only page that may hold
+    // `address`, which is the last page whose first member is not after it,
+    // if any.
+    fn acl_find_page(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        index: &AclIndex,
+        address: AddressObject,
+    ) -> Result<Option<u32>, HostError> {
+        let res = self.vec_binary_search(vmcaller, index.firsts, address.to_val())?;
+        let (found, pos) = (res >> u32::BITS == 1, res as u32);
+        Ok(if found { Some(pos) } else { pos.checked_sub(1) })
+    }
+
+    // Reads the page at position `pos` of the index, returning its key along
+    // with the page.
+    fn acl_read_page(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        k: Val,
+        t: &StorageType,
+        index: &AclIndex,
+        pos: u32,
+    ) -> Result<(Val, VecObject), HostError> {
+        let page_id = self.vec_get(vmcaller, index.page_ids, pos.into())?;
+        let page_id = U32Val::try_from(page_id).map_err(|_| self.acl_malformed(k))?;
+        let key = self.acl_page_key(k, page_id.into())?;
+        let page = self.get_contract_data(vmcaller, key, t.clone())?;
+        self.acl_bump(vmcaller, key, t)?;
+        let page = VecObject::try_from(page).map_err(|_| self.acl_malformed(k))?;
+        Ok((key, page))
+    }
+
+    // Returns the position of `address` in `page` and whether it's a member.
+    fn acl_search_page(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        page: VecObject,
+        address: AddressObject,
+    ) -> Result<(u32, bool), HostError> {
+        let res = self.vec_binary_search(vmcaller, page, address.to_val())?;
+        Ok((res as u32, res >> u32::BITS == 1))
+    }
+
+    // Notes on metering: covered by components. Splitting a page rewrites it
+    // along with the new page, which happens at most once every
+    // `ACL_PAGE_CAPACITY / 2` additions to the page.
+    pub(crate) fn acl_add_internal(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        k: Val,
+        t: StorageType,
+        address: AddressObject,
+    ) -> Result<bool, HostError> {
+        let mut index = match self.acl_read_index(vmcaller, k, &t)? {
+            Some(index) => index,
+            None => AclIndex {
+                firsts: self.vec_new(vmcaller)?,
+                page_ids: self.vec_new(vmcaller)?,
+                next_page_id: 0,
+            },
+        };
+        if u32::from(self.vec_len(vmcaller, index.firsts)?) == 0 {
+            // The first member gets a page of its own.
+            let page_id = self.acl_new_page_id(k, &mut index)?;
+            let page = self.vec_new_from_slice(&[address.to_val()])?;
+            let key = self.acl_page_key(k, page_id)?;
+            self.acl_put(vmcaller, key, page.to_val(), &t)?;
+            index.firsts = self.vec_push_back(vmcaller, index.firsts, address.to_val())?;
+            index.page_ids =
+                self.vec_push_back(vmcaller, index.page_ids, U32Val::from(page_id).to_val())?;
+            self.acl_write_index(vmcaller, k, &t, &index)?;
+            return Ok(true);
+        }
+        // An address before the first member goes into the first page.
+        let pos = self.acl_find_page(vmcaller, &index, address)?.unwrap_or(0);
+        let (key, page) = self.acl_read_page(vmcaller, k, &t, &index, pos)?;
+        let (i, found) = self.acl_search_page(vmcaller, page, address)?;
+        if found {
+            return Ok(false);
+        }
+        let page = self.vec_insert(vmcaller, page, i.into(), address.to_val())?;
+        if i == 0 {
+            index.firsts = self.vec_put(vmcaller, index.firsts, pos.into(), address.to_val())?;
+        }
+        let len = u32::from(self.vec_len(vmcaller, page)?);
+        if len > ACL_PAGE_CAPACITY {
+            let half = len / 2;
+            let left = self.vec_slice(vmcaller, page, 0_u32.into(), half.into())?;
+            let right = self.vec_slice(vmcaller, page, half.into(), len.into())?;
+            let right_id = self.acl_new_page_id(k, &mut index)?;
+            let right_key = self.acl_page_key(k, right_id)?;
+            self.acl_put(vmcaller, key, left.to_val(), &t)?;
+            self.acl_put(vmcaller, right_key, right.to_val(), &t)?;
+            let right_first = self.vec_front(vmcaller, right)?;
+            index.firsts =
+                self.vec_insert(vmcaller, index.firsts, (pos + 1).into(), right_first)?;
+            index.page_ids = self.vec_insert(
+                vmcaller,
+                index.page_ids,
+                (pos + 1).into(),
+                U32Val::from(right_id).to_val(),
+            )?;
+        } else {
+            self.acl_put(vmcaller, key, page.to_val(), &t)?;
+        }
+        self.acl_write_index(vmcaller, k, &t, &index)?;
+        Ok(true)
+    }
+
+    // Notes on metering: covered by components.
+    pub(crate) fn acl_remove_internal(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        k: Val,
+        t: StorageType,
+        address: AddressObject,
+    ) -> Result<bool, HostError> {
+        let Some(mut index) = self.acl_read_index(vmcaller, k, &t)? else {
+            return Ok(false);
+        };
+        let Some(pos) = self.acl_find_page(vmcaller, &index, address)? else {
+            return Ok(false);
+        };
+        let (key, page) = self.acl_read_page(vmcaller, k, &t, &index, pos)?;
+        let (i, found) = self.acl_search_page(vmcaller, page, address)?;
+        if !found {
+            return Ok(false);
+        }
+        let page = self.vec_del(vmcaller, page, i.into())?;
+        if u32::from(self.vec_len(vmcaller, page)?) == 0 {
+            // Empty pages are deleted, and so is the index of an empty list.
+            self.del_contract_data(vmcaller, key, t.clone())?;
+            index.firsts = self.vec_del(vmcaller, index.firsts, pos.into())?;
+            index.page_ids = self.vec_del(vmcaller, index.page_ids, pos.into())?;
+            if u32::from(self.vec_len(vmcaller, index.firsts)?) == 0 {
+                let index_key = self.acl_index_key(k)?;
+                self.del_contract_data(vmcaller, index_key, t)?;
+                return Ok(true);
+            }
+        } else {
+            self.acl_put(vmcaller, key, page.to_val(), &t)?;
+            if i == 0 {
+                let first = self.vec_front(vmcaller, page)?;
+                index.firsts = self.vec_put(vmcaller, index.firsts, pos.into(), first)?;
+            }
+        }
+        self.acl_write_index(vmcaller, k, &t, &index)?;
+        Ok(true)
+    }
+
+    // Notes on metering: covered by components.
+    pub(crate) fn acl_contains_internal(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        k: Val,
+        t: StorageType,
+        address: AddressObject,
+    ) -> Result<bool, HostError> {
+        let Some(index) = self.acl_read_index(vmcaller, k, &t)? else {
+            return Ok(false);
+        };
+        let Some(pos) = self.acl_find_page(vmcaller, &index, address)? else {
+            return Ok(false);
+        };
+        let (_, page) = self.acl_read_page(vmcaller, k, &t, &index, pos)?;
+        Ok(self.acl_search_page(vmcaller, page, address)?.1)
+    }
+}
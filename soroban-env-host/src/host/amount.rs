@@ -0,0 +1,115 @@
+use crate::{
+    err,
+    native_contract::contract_error::ContractError,
+    xdr::{ContractCostType, ScErrorCode, ScErrorType},
+    Host, HostError,
+};
+
+/// Number of decimal places used by the amounts of Stellar classic assets,
+/// and thus by the built-in token contracts wrapping them.
+pub const CLASSIC_ASSET_DECIMALS: u32 = 7;
+
+/// Rounding applied by [`Host::scale_amount`] when the number of decimals
+/// decreases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmountRounding {
+    /// Rounds towards zero, i.e. truncates.
+    TowardZero = 0,
+    /// Rounds away from zero.
+    AwayFromZero = 1,
+    /// Rounds to the nearest value, with halves rounded away from zero.
+    HalfAwayFromZero = 2,
+}
+
+impl Host {
+    pub(crate) fn amount_rounding_from_u32(
+        &self,
+        rounding: u32,
+    ) -> Result<AmountRounding, HostError> {
+        match rounding {
+            0 => Ok(AmountRounding::TowardZero),
+            1 => Ok(AmountRounding::AwayFromZero),
+            2 => Ok(AmountRounding::HalfAwayFromZero),
+            _ => Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "invalid rounding mode",
+                &[rounding.into()],
+            )),
+        }
+    }
+
+    /// Rescales `amount` from `from_decimals` to `to_decimals` decimal places,
+    /// e.g. from a token with 18 decimals to one with
+    /// [`CLASSIC_ASSET_DECIMALS`]. When the number of decimals decreases, the
+    /// result is rounded according to `rounding`. Fails with a
+    /// `(Value, ArithDomain)` error if the result doesn't fit into an `i128`.
+    pub fn scale_amount(
+        &self,
+        amount: i128,
+        from_decimals: u32,
+        to_decimals: u32,
+        rounding: AmountRounding,
+    ) -> Result<i128, HostError> {
+        if to_decimals >= from_decimals {
+            self.charge_budget(ContractCostType::Int256Mul, None)?;
+            return 10_i128
+                .checked_pow(to_decimals - from_decimals)
+                .and_then(|factor| amount.checked_mul(factor))
+                .ok_or_else(|| self.err_arith_overflow());
+        }
+        self.charge_budget(ContractCostType::Int256Div, None)?;
+        // Powers of 10 that fit into a `u128` also fit into an `i128`. Larger
+        // factors exceed any amount, so the whole amount is the remainder.
+        let factor = 10_u128.checked_pow(from_decimals - to_decimals);
+        let (quotient, remainder) = match factor {
+            Some(factor) => (amount / factor as i128, amount % factor as i128),
+            None => (0, amount),
+        };
+        let round_away = match (rounding, factor) {
+            (AmountRounding::TowardZero, _) => false,
+            (AmountRounding::AwayFromZero, _) => remainder != 0,
+            // Can't overflow, as the remainder is less than the factor, which
+            // is at most 10^38.
+            (AmountRounding::HalfAwayFromZero, Some(factor)) => {
+                remainder.unsigned_abs() * 2 >= factor
+            }
+            (AmountRounding::HalfAwayFromZero, None) => false,
+        };
+        if round_away {
+            // Can't overflow, as `quotient` is at most `i128::MAX / 10`.
+            Ok(quotient + amount.signum())
+        } else {
+            Ok(quotient)
+        }
+    }
+
+    /// Fails with the token contract's `NegativeAmountError` if `amount` is
+    /// negative, which is how the built-in token contracts reject negative
+    /// amounts.
+    pub(crate) fn require_nonnegative_amount(&self, amount: i128) -> Result<(), HostError> {
+        if amount < 0 {
+            return Err(err!(
+                self,
+                ContractError::NegativeAmountError,
+                "negative amount is not allowed",
+                amount
+            ));
+        }
+        Ok(())
+    }
+
+    /// Like [`Host::require_nonnegative_amount`], but also fails with
+    /// `NegativeAmountError` if `amount` is zero.
+    pub(crate) fn require_positive_amount(&self, amount: i128) -> Result<(), HostError> {
+        if amount <= 0 {
+            return Err(err!(
+                self,
+                ContractError::NegativeAmountError,
+                "non-positive amount is not allowed",
+                amount
+            ));
+        }
+        Ok(())
+    }
+}
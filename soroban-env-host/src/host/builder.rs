@@ -0,0 +1,228 @@
+use std::rc::Rc;
+
+use crate::{
+    auth::NonRootAuthExemptions,
+    budget::Budget,
+    events::{diagnostic::DiagnosticLevel, ContractEventLimits, EventFilter},
+    storage::{CodeStore, FootprintMode, Storage},
+    xdr::{AccountId, ScErrorCode, ScErrorType, SorobanAuthorizationEntry},
+    Host, HostError, LedgerInfo, LedgerInfoProvider, MetricsSink, ObjectSizeLimits, PrngMode, Seed,
+    XdrReadLimits,
+};
+
+enum AuthMode {
+    Enforcing(Vec<SorobanAuthorizationEntry>),
+    Recording {
+        disable_non_root_auth: bool,
+        exemptions: NonRootAuthExemptions,
+    },
+}
+
+/// Assembles a [`Host`] from its configuration, validating that the parts are
+/// consistent with each other before constructing it.
+///
+/// All the parts except for the [`Storage`] are optional: the budget defaults
+/// to [`Budget::default`] and the authorization to the enforcing mode without
+/// any authorization entries.
+#[derive(Default)]
+pub struct HostBuilder {
+    storage: Option<Storage>,
+    budget: Option<Budget>,
+    ledger_info: Option<LedgerInfo>,
+    ledger_info_provider: Option<Rc<dyn LedgerInfoProvider>>,
+    source_account: Option<AccountId>,
+    auth_mode: Option<AuthMode>,
+    diagnostic_level: DiagnosticLevel,
+    base_prng_seed: Option<Seed>,
+    prng_mode: Option<PrngMode>,
+    code_store: Option<Rc<dyn CodeStore>>,
+    metrics_sink: Option<Rc<dyn MetricsSink>>,
+    xdr_read_limits: Option<XdrReadLimits>,
+    object_size_limits: Option<ObjectSizeLimits>,
+    contract_event_limits: Option<ContractEventLimits>,
+    event_filter: Option<EventFilter>,
+}
+
+impl HostBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn storage(mut self, storage: Storage) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    pub fn budget(mut self, budget: Budget) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn ledger_info(mut self, ledger_info: LedgerInfo) -> Self {
+        self.ledger_info = Some(ledger_info);
+        self
+    }
+
+    /// Serves the ledger information from `provider`, see
+    /// [`Host::set_ledger_info_provider`].
+    pub fn ledger_info_provider(mut self, provider: Rc<dyn LedgerInfoProvider>) -> Self {
+        self.ledger_info_provider = Some(provider);
+        self
+    }
+
+    pub fn source_account(mut self, source_account: AccountId) -> Self {
+        self.source_account = Some(source_account);
+        self
+    }
+
+    /// Enforces the authorizations against `auth_entries`, see
+    /// [`Host::set_authorization_entries`].
+    pub fn enforcing_auth(mut self, auth_entries: Vec<SorobanAuthorizationEntry>) -> Self {
+        self.auth_mode = Some(AuthMode::Enforcing(auth_entries));
+        self
+    }
+
+    /// Records the authorizations instead of enforcing them, see
+    /// [`Host::switch_to_recording_auth_with_exemptions`].
+    pub fn recording_auth(
+        mut self,
+        disable_non_root_auth: bool,
+        exemptions: NonRootAuthExemptions,
+    ) -> Self {
+        self.auth_mode = Some(AuthMode::Recording {
+            disable_non_root_auth,
+            exemptions,
+        });
+        self
+    }
+
+    pub fn diagnostic_level(mut self, diagnostic_level: DiagnosticLevel) -> Self {
+        self.diagnostic_level = diagnostic_level;
+        self
+    }
+
+    pub fn base_prng_seed(mut self, seed: Seed) -> Self {
+        self.base_prng_seed = Some(seed);
+        self
+    }
+
+    pub fn prng_mode(mut self, mode: PrngMode) -> Self {
+        self.prng_mode = Some(mode);
+        self
+    }
+
+    pub fn code_store(mut self, code_store: Rc<dyn CodeStore>) -> Self {
+        self.code_store = Some(code_store);
+        self
+    }
+
+    pub fn metrics_sink(mut self, sink: Rc<dyn MetricsSink>) -> Self {
+        self.metrics_sink = Some(sink);
+        self
+    }
+
+    pub fn xdr_read_limits(mut self, limits: XdrReadLimits) -> Self {
+        self.xdr_read_limits = Some(limits);
+        self
+    }
+
+    pub fn object_size_limits(mut self, limits: ObjectSizeLimits) -> Self {
+        self.object_size_limits = Some(limits);
+        self
+    }
+
+    pub fn contract_event_limits(mut self, limits: ContractEventLimits) -> Self {
+        self.contract_event_limits = Some(limits);
+        self
+    }
+
+    pub fn event_filter(mut self, filter: EventFilter) -> Self {
+        self.event_filter = Some(filter);
+        self
+    }
+
+    /// Validates the configuration and constructs the [`Host`].
+    ///
+    /// Fails with `(Context, MissingValue)` without a [`Storage`], and with
+    /// `(Context, InvalidInput)` for the recording authorization mode over a
+    /// [`Storage`] in [`FootprintMode::Enforcing`], or for the enforcing
+    /// authorization mode with neither authorization entries nor a source
+    /// account, since nothing could be authorized then. Setting both a
+    /// [`LedgerInfo`] and a [`LedgerInfoProvider`] fails with
+    /// `(Context, InvalidInput)` as well.
+    pub fn build(self) -> Result<Host, HostError> {
+        let Some(storage) = self.storage else {
+            return Err((ScErrorType::Context, ScErrorCode::MissingValue).into());
+        };
+        if self.ledger_info.is_some() && self.ledger_info_provider.is_some() {
+            return Err((ScErrorType::Context, ScErrorCode::InvalidInput).into());
+        }
+        match &self.auth_mode {
+            Some(AuthMode::Recording { .. }) => {
+                if matches!(storage.mode, FootprintMode::Enforcing) {
+                    return Err((ScErrorType::Context, ScErrorCode::InvalidInput).into());
+                }
+            }
+            Some(AuthMode::Enforcing(entries)) => {
+                if entries.is_empty() && self.source_account.is_none() {
+                    return Err((ScErrorType::Context, ScErrorCode::InvalidInput).into());
+                }
+            }
+            None => (),
+        }
+
+        let host = Host::with_storage_and_budget(storage, self.budget.unwrap_or_default());
+        if let Some(source_account) = self.source_account {
+            host.set_source_account(source_account)?;
+        }
+        if let Some(ledger_info) = self.ledger_info {
+            host.set_ledger_info(ledger_info)?;
+        }
+        if let Some(provider) = self.ledger_info_provider {
+            host.set_ledger_info_provider(provider)?;
+        }
+        match self.auth_mode {
+            Some(AuthMode::Enforcing(entries)) => host.set_authorization_entries(entries)?,
+            Some(AuthMode::Recording {
+                disable_non_root_auth,
+                exemptions,
+            }) => {
+                host.switch_to_recording_auth_with_exemptions(disable_non_root_auth, exemptions)?
+            }
+            None => (),
+        }
+        host.set_diagnostic_level(self.diagnostic_level)?;
+        if let Some(seed) = self.base_prng_seed {
+            host.set_base_prng_seed(seed)?;
+        }
+        if let Some(mode) = self.prng_mode {
+            host.set_prng_mode(mode)?;
+        }
+        if let Some(code_store) = self.code_store {
+            host.set_code_store(code_store)?;
+        }
+        if let Some(sink) = self.metrics_sink {
+            host.set_metrics_sink(sink)?;
+        }
+        if let Some(limits) = self.xdr_read_limits {
+            host.set_xdr_read_limits(limits)?;
+        }
+        if let Some(limits) = self.object_size_limits {
+            host.set_object_size_limits(limits)?;
+        }
+        if let Some(limits) = self.contract_event_limits {
+            host.set_contract_event_limits(limits)?;
+        }
+        if let Some(filter) = self.event_filter {
+            host.set_event_filter(Some(filter))?;
+        }
+        Ok(host)
+    }
+}
+
+impl Host {
+    /// Returns a [`HostBuilder`] to assemble a new [`Host`] with.
+    pub fn builder() -> HostBuilder {
+        HostBuilder::new()
+    }
+}
@@ -0,0 +1,228 @@
+//! A canonical, JSON-like encoding of values for signing flows that verify
+//! signatures off-chain, where producing XDR is inconvenient.
+//!
+//! The encoding is the prefix [`CANONICAL_ENCODING_PREFIX`] followed by a
+//! compact JSON document without any whitespace. Every value is encoded as an
+//! object with a single member named after its type:
+//!
+//! - `null`, `true` and `false` encode `Void` and `Bool`.
+//! - `{"u32":N}` and `{"i32":N}` encode 32-bit integers as JSON numbers.
+//! - `{"u64":"N"}`, `{"i64":"N"}`, `{"timepoint":"N"}`, `{"duration":"N"}`,
+//!   `{"u128":"N"}`, `{"i128":"N"}`, `{"u256":"N"}` and `{"i256":"N"}`
+//!   encode wider integers as decimal strings.
+//! - `{"error":[TYPE,CODE]}` encodes an error by its numeric type and code.
+//! - `{"bytes":"HEX"}` encodes bytes as lowercase hex.
+//! - `{"string":"S"}` and `{"symbol":"S"}` encode UTF-8 strings and symbols.
+//! - `{"address":"STRKEY"}` encodes account and contract addresses.
+//! - `{"vec":[V,...]}` encodes vectors.
+//! - `{"map":[[K,V],...]}` encodes maps as key-value pairs, in the order of
+//!   the keys in the host.
+//!
+//! In JSON strings only `"`, `\` and the control characters are escaped, the
+//! latter as `\u00XX`.
+
+use crate::{
+    budget::AsBudget,
+    host::metered_xdr::metered_write_xdr,
+    num::{i256_from_pieces, u256_from_pieces},
+    xdr::{
+        int128_helpers, ContractCostType, DepthLimiter, PublicKey, ScAddress, ScErrorCode,
+        ScErrorType, ScVal,
+    },
+    Error, Host, HostError, Val,
+};
+
+/// The prefix of every canonical encoding, which identifies the version of
+/// the encoding.
+pub const CANONICAL_ENCODING_PREFIX: &[u8] = b"scval1:";
+
+// An upper bound of the ratio between the length of the canonical encoding of
+// a value and the length of its XDR, reached by strings of control characters
+// that are escaped from 1 byte to 6.
+const MAX_CANONICAL_EXPANSION: u64 = 6;
+
+impl Host {
+    /// Returns the canonical encoding of `v`, see the [module
+    /// documentation](self). Fails with `(Value, InvalidInput)` for strings
+    /// that aren't valid UTF-8.
+    // Notes on metering: the conversion to `ScVal` is metered and depth
+    // limited. The encoding isn't metered as it's written, so it's charged
+    // upfront as `ValSer` of its maximum length, which is bounded by the
+    // length of the XDR of the converted value, itself metered.
+    pub(crate) fn canonical_encoding(&self, v: Val) -> Result<Vec<u8>, HostError> {
+        let scv = self.from_host_val(v)?;
+        let mut xdr = vec![];
+        metered_write_xdr(self.as_budget(), &scv, &mut xdr)?;
+        let max_len = (CANONICAL_ENCODING_PREFIX.len() as u64)
+            .saturating_add((xdr.len() as u64).saturating_mul(MAX_CANONICAL_EXPANSION));
+        self.charge_budget(ContractCostType::ValSer, Some(max_len))?;
+        let mut buf = CANONICAL_ENCODING_PREFIX.to_vec();
+        self.write_canonical(&scv, &mut buf)?;
+        Ok(buf)
+    }
+
+    fn write_canonical(&self, scv: &ScVal, buf: &mut Vec<u8>) -> Result<(), HostError> {
+        match scv {
+            ScVal::Void => buf.extend_from_slice(b"null"),
+            ScVal::Bool(b) => buf.extend_from_slice(if *b { b"true" } else { b"false" }),
+            ScVal::Error(e) => {
+                let e = Error::from(e.clone());
+                write_tagged(buf, "error", |buf| {
+                    let s = format!("[{},{}]", e.get_type() as i32, e.get_code());
+                    buf.extend_from_slice(s.as_bytes());
+                })
+            }
+            ScVal::U32(u) => write_tagged(buf, "u32", |buf| write_display(buf, u)),
+            ScVal::I32(i) => write_tagged(buf, "i32", |buf| write_display(buf, i)),
+            ScVal::U64(u) => write_tagged(buf, "u64", |buf| write_decimal(buf, u)),
+            ScVal::I64(i) => write_tagged(buf, "i64", |buf| write_decimal(buf, i)),
+            ScVal::Timepoint(t) => write_tagged(buf, "timepoint", |buf| write_decimal(buf, &t.0)),
+            ScVal::Duration(d) => write_tagged(buf, "duration", |buf| write_decimal(buf, &d.0)),
+            ScVal::U128(u) => write_tagged(buf, "u128", |buf| {
+                write_decimal(buf, &int128_helpers::u128_from_pieces(u.hi, u.lo))
+            }),
+            ScVal::I128(i) => write_tagged(buf, "i128", |buf| {
+                write_decimal(buf, &int128_helpers::i128_from_pieces(i.hi, i.lo))
+            }),
+            ScVal::U256(u) => write_tagged(buf, "u256", |buf| {
+                write_decimal(buf, &u256_from_pieces(u.hi_hi, u.hi_lo, u.lo_hi, u.lo_lo))
+            }),
+            ScVal::I256(i) => write_tagged(buf, "i256", |buf| {
+                write_decimal(buf, &i256_from_pieces(i.hi_hi, i.hi_lo, i.lo_hi, i.lo_lo))
+            }),
+            ScVal::Bytes(b) => write_tagged(buf, "bytes", |buf| {
+                buf.push(b'"');
+                for byte in b.iter() {
+                    buf.extend_from_slice(format!("{:02x}", byte).as_bytes());
+                }
+                buf.push(b'"');
+            }),
+            ScVal::String(s) => {
+                let s = std::str::from_utf8(s.as_slice()).map_err(|_| {
+                    self.err(
+                        ScErrorType::Value,
+                        ScErrorCode::InvalidInput,
+                        "canonical encoding requires UTF-8 strings",
+                        &[],
+                    )
+                })?;
+                write_tagged(buf, "string", |buf| write_json_string(buf, s))
+            }
+            ScVal::Symbol(s) => {
+                // Symbols only consist of `[a-zA-Z0-9_]`, so they are valid
+                // UTF-8 and never need escaping.
+                let s = std::str::from_utf8(s.as_slice()).map_err(|_| {
+                    self.err(
+                        ScErrorType::Value,
+                        ScErrorCode::InternalError,
+                        "symbol is not valid UTF-8",
+                        &[],
+                    )
+                })?;
+                write_tagged(buf, "symbol", |buf| write_json_string(buf, s))
+            }
+            ScVal::Address(address) => {
+                let strkey = match address {
+                    ScAddress::Account(account_id) => {
+                        let PublicKey::PublicKeyTypeEd25519(key) = &account_id.0;
+                        stellar_strkey::ed25519::PublicKey(key.0).to_string()
+                    }
+                    ScAddress::Contract(id) => stellar_strkey::Contract(id.0).to_string(),
+                };
+                write_tagged(buf, "address", |buf| write_json_string(buf, &strkey))
+            }
+            ScVal::Vec(Some(v)) => {
+                self.write_canonical_container(buf, "vec", v.iter(), |host, buf, elt| {
+                    host.write_canonical(elt, buf)
+                })?
+            }
+            ScVal::Map(Some(m)) => {
+                self.write_canonical_container(buf, "map", m.iter(), |host, buf, entry| {
+                    buf.push(b'[');
+                    host.write_canonical(&entry.key, buf)?;
+                    buf.push(b',');
+                    host.write_canonical(&entry.val, buf)?;
+                    buf.push(b']');
+                    Ok(())
+                })?
+            }
+            ScVal::Vec(None)
+            | ScVal::Map(None)
+            | ScVal::LedgerKeyContractInstance
+            | ScVal::LedgerKeyNonce(_)
+            | ScVal::ContractInstance(_) => {
+                return Err(self.err(
+                    ScErrorType::Value,
+                    ScErrorCode::InvalidInput,
+                    "value has no canonical encoding",
+                    &[],
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    // Writes the elements of a vector or map as `{"tag":[E,...]}`, within the
+    // depth limit of the budget.
+    fn write_canonical_container<'a, T: 'a>(
+        &self,
+        buf: &mut Vec<u8>,
+        tag: &str,
+        elts: impl Iterator<Item = &'a T>,
+        write_elt: impl Fn(&Host, &mut Vec<u8>, &T) -> Result<(), HostError>,
+    ) -> Result<(), HostError> {
+        self.budget_cloned().with_limited_depth(|_| {
+            write_tag_start(buf, tag);
+            buf.push(b'[');
+            for (i, elt) in elts.enumerate() {
+                if i > 0 {
+                    buf.push(b',');
+                }
+                write_elt(self, buf, elt)?;
+            }
+            buf.push(b']');
+            buf.push(b'}');
+            Ok(())
+        })
+    }
+}
+
+fn write_tag_start(buf: &mut Vec<u8>, tag: &str) {
+    buf.extend_from_slice(b"{\"");
+    buf.extend_from_slice(tag.as_bytes());
+    buf.extend_from_slice(b"\":");
+}
+
+fn write_tagged(buf: &mut Vec<u8>, tag: &str, write_val: impl FnOnce(&mut Vec<u8>)) {
+    write_tag_start(buf, tag);
+    write_val(buf);
+    buf.push(b'}');
+}
+
+fn write_display(buf: &mut Vec<u8>, v: &impl std::fmt::Display) {
+    buf.extend_from_slice(v.to_string().as_bytes());
+}
+
+fn write_decimal(buf: &mut Vec<u8>, v: &impl std::fmt::Display) {
+    buf.push(b'"');
+    write_display(buf, v);
+    buf.push(b'"');
+}
+
+fn write_json_string(buf: &mut Vec<u8>, s: &str) {
+    buf.push(b'"');
+    for c in s.chars() {
+        match c {
+            '"' => buf.extend_from_slice(b"\\\""),
+            '\\' => buf.extend_from_slice(b"\\\\"),
+            c if (c as u32) < 0x20 => {
+                buf.extend_from_slice(format!("\\u{:04x}", c as u32).as_bytes())
+            }
+            c => {
+                let mut utf8 = [0; 4];
+                buf.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+            }
+        }
+    }
+    buf.push(b'"');
+}
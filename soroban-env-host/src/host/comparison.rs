@@ -1,5 +1,7 @@
 use core::cmp::{min, Ordering};
 
+#[cfg(any(test, feature = "testutils"))]
+use soroban_env_common::Val;
 use soroban_env_common::{
     xdr::{
         AccountEntry, AccountId, ClaimableBalanceEntry, ConfigSettingEntry, ContractCostType,
@@ -12,6 +14,8 @@ use soroban_env_common::{
     },
     Compare, SymbolStr, I256, U256,
 };
+#[cfg(any(test, feature = "testutils"))]
+use std::rc::Rc;
 
 use crate::{
     budget::{AsBudget, Budget},
@@ -21,6 +25,56 @@ use crate::{
 
 use super::declared_size::DeclaredSizeForMetering;
 
+/// Replacement for the ordering of host objects, for testing that the code
+/// relying on the ordering (such as the construction of maps) detects and
+/// rejects inconsistent comparisons. Receives the compared values, at least
+/// one of which is an object, along with the ordering computed by the host,
+/// and returns the ordering to use instead.
+#[cfg(any(test, feature = "testutils"))]
+pub trait ObjectComparator {
+    fn compare(&self, host: &Host, a: Val, b: Val, ord: Ordering) -> Result<Ordering, HostError>;
+}
+
+#[cfg(any(test, feature = "testutils"))]
+impl<F> ObjectComparator for F
+where
+    F: Fn(&Host, Val, Val, Ordering) -> Result<Ordering, HostError>,
+{
+    fn compare(&self, host: &Host, a: Val, b: Val, ord: Ordering) -> Result<Ordering, HostError> {
+        self(host, a, b, ord)
+    }
+}
+
+#[cfg(any(test, feature = "testutils"))]
+impl Host {
+    /// Makes every object comparison of the host, including the comparisons
+    /// of map keys and of elements nested in containers, go through
+    /// `comparator`, or restores the host's own ordering if it's `None`.
+    pub fn set_object_comparator(
+        &self,
+        comparator: Option<Rc<dyn ObjectComparator>>,
+    ) -> Result<(), HostError> {
+        *self.try_borrow_object_comparator_mut()? = comparator;
+        Ok(())
+    }
+
+    // Returns the ordering of `a` and `b` decided by the object comparator,
+    // if there is one, or `ord` otherwise.
+    pub(crate) fn maybe_override_obj_cmp(
+        &self,
+        a: Val,
+        b: Val,
+        ord: Ordering,
+    ) -> Result<Ordering, HostError> {
+        // The comparator is cloned out so it can compare objects itself.
+        let comparator = self.try_borrow_object_comparator()?.clone();
+        match comparator {
+            Some(comparator) => comparator.compare(self, a, b, ord),
+            None => Ok(ord),
+        }
+    }
+}
+
 // We can't use core::mem::discriminant here because it returns an opaque type
 // that only supports Eq, not Ord, to reduce the possibility of an API breakage
 // based on reordering enums: https://github.com/rust-lang/rust/issues/51561
@@ -0,0 +1,104 @@
+use crate::{
+    xdr::{ContractCostType, ScBytes, ScErrorCode, ScErrorType},
+    BytesObject, Host, HostError,
+};
+use ruzstd::decoding::StreamingDecoder;
+use std::io::Read;
+
+const ZSTD_MAGIC_NUMBER: u32 = 0xFD2F_B528;
+
+// Size of the window declared by the header of the zstd frame at the start of
+// `data`, which is the amount of memory the decoder reserves upfront (RFC 8878,
+// section 3.1.1.1). Returns `None` if `data` doesn't start with a frame header.
+fn zstd_window_size(data: &[u8]) -> Option<u64> {
+    let magic = u32::from_le_bytes(data.get(0..4)?.try_into().ok()?);
+    if magic != ZSTD_MAGIC_NUMBER {
+        return None;
+    }
+    let descriptor = *data.get(4)?;
+    let single_segment = descriptor & 0x20 != 0;
+    if !single_segment {
+        let window_descriptor = *data.get(5)?;
+        let window_log = 10 + u32::from(window_descriptor >> 3);
+        let window_base = 1u64 << window_log;
+        let window_add = (window_base / 8) * u64::from(window_descriptor & 0x7);
+        return Some(window_base + window_add);
+    }
+    // Single-segment frames have no window descriptor and use the content
+    // size, which follows the dictionary id, as their window size.
+    let dict_id_len = [0, 1, 2, 4][usize::from(descriptor & 0x3)];
+    let content_size_pos = 5 + dict_id_len;
+    match descriptor >> 6 {
+        0 => data.get(content_size_pos).map(|b| u64::from(*b)),
+        1 => {
+            let bytes = data.get(content_size_pos..content_size_pos + 2)?;
+            Some(u64::from(u16::from_le_bytes(bytes.try_into().ok()?)) + 256)
+        }
+        2 => {
+            let bytes = data.get(content_size_pos..content_size_pos + 4)?;
+            Some(u64::from(u32::from_le_bytes(bytes.try_into().ok()?)))
+        }
+        _ => {
+            let bytes = data.get(content_size_pos..content_size_pos + 8)?;
+            Some(u64::from_le_bytes(bytes.try_into().ok()?))
+        }
+    }
+}
+
+impl Host {
+    fn err_invalid_zstd_data(&self) -> HostError {
+        self.err(
+            ScErrorType::Value,
+            ScErrorCode::InvalidInput,
+            "invalid zstd data",
+            &[],
+        )
+    }
+
+    pub(crate) fn zstd_decompress_bytes(
+        &self,
+        bytes: &[u8],
+        max_out_len: u32,
+    ) -> Result<Vec<u8>, HostError> {
+        // Charge for the largest output upfront, so that the decompressed size
+        // is never allocated or processed without being paid for. There is no
+        // cost type for zstd decoding yet, so producing the output is charged
+        // as deserializing a value of the same size, which also accounts for
+        // its allocation, and the input is charged as read once.
+        self.charge_budget(ContractCostType::ValDeser, Some(max_out_len as u64))?;
+        self.charge_budget(ContractCostType::HostMemCpy, Some(bytes.len() as u64))?;
+        // The decoder reserves the window declared by the frame header before
+        // decoding anything, so it is charged before the decoder is created.
+        let window_size = zstd_window_size(bytes).ok_or_else(|| self.err_invalid_zstd_data())?;
+        self.charge_budget(ContractCostType::HostMemAlloc, Some(window_size))?;
+
+        let mut decoder = StreamingDecoder::new(bytes).map_err(|_| self.err_invalid_zstd_data())?;
+        let mut out = Vec::with_capacity(max_out_len as usize);
+        (&mut decoder)
+            .take(max_out_len as u64)
+            .read_to_end(&mut out)
+            .map_err(|_| self.err_invalid_zstd_data())?;
+        // The output is complete only if there is nothing left to decode.
+        let mut extra = [0u8; 1];
+        match decoder.read(&mut extra) {
+            Ok(0) => Ok(out),
+            Ok(_) => Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::ExceededLimit,
+                "decompressed data exceeds the maximum length",
+                &[],
+            )),
+            Err(_) => Err(self.err_invalid_zstd_data()),
+        }
+    }
+
+    pub(crate) fn zstd_decompress_bytesobj_input(
+        &self,
+        b: BytesObject,
+        max_out_len: u32,
+    ) -> Result<Vec<u8>, HostError> {
+        self.visit_obj(b, |bytes: &ScBytes| {
+            self.zstd_decompress_bytes(bytes.as_slice(), max_out_len)
+        })
+    }
+}
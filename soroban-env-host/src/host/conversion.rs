@@ -13,15 +13,267 @@ use soroban_env_common::num::{
 };
 use soroban_env_common::xdr::{
     self, int128_helpers, AccountId, ContractDataDurability, DepthLimiter, Int128Parts,
-    Int256Parts, ScAddress, ScBytes, ScErrorCode, ScErrorType, ScMap, ScMapEntry, UInt128Parts,
-    UInt256Parts, VecM,
+    Int256Parts, ScAddress, ScBytes, ScErrorCode, ScErrorType, ScMap, ScMapEntry, ScString,
+    UInt128Parts, UInt256Parts, VecM,
 };
 use soroban_env_common::{
-    AddressObject, BytesObject, Convert, Object, ScValObjRef, ScValObject, TryFromVal, TryIntoVal,
-    U32Val, VecObject,
+    AddressObject, BytesObject, Convert, Object, ScValObjRef, ScValObject, SymbolSmall, TryFromVal,
+    TryIntoVal, U32Val, VecObject,
 };
 
+/// Limits on the shape of an [`ScVal`] tree, used for validating untrusted
+/// values (e.g. coming from RPC inputs) when converting them between [`ScVal`]
+/// and [`Val`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScValLimits {
+    /// Maximum nesting depth of the value, scalar values have depth 1.
+    pub max_depth: u32,
+    /// Maximum total number of values in the tree, including the map keys.
+    pub max_values: u32,
+    /// Maximum total length of all the bytes, string and symbol payloads.
+    pub max_payload_bytes: u32,
+}
+
+/// Identifies the [`ScValLimits`] limit that has been exceeded by a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScValLimitExceeded {
+    Depth,
+    Values,
+    PayloadBytes,
+}
+
+impl ScValLimits {
+    /// Checks that `v` doesn't exceed any of the limits.
+    ///
+    /// The traversal stops as soon as any limit is exceeded, so its cost is
+    /// bounded by the limits and not by the size of `v`.
+    pub fn check(&self, v: &ScVal) -> Result<(), ScValLimitExceeded> {
+        self.check_counting_values(v).1
+    }
+
+    // Same as `check`, also returning the number of values visited.
+    fn check_counting_values(&self, v: &ScVal) -> (u32, Result<(), ScValLimitExceeded>) {
+        let mut values = 0_u32;
+        let mut payload_bytes = 0_u32;
+        let res = self.check_at_depth(v, 1, &mut values, &mut payload_bytes);
+        (values, res)
+    }
+
+    fn check_at_depth(
+        &self,
+        v: &ScVal,
+        depth: u32,
+        values: &mut u32,
+        payload_bytes: &mut u32,
+    ) -> Result<(), ScValLimitExceeded> {
+        let payload_len = match v {
+            ScVal::Bytes(b) => b.len(),
+            ScVal::String(s) => s.len(),
+            ScVal::Symbol(s) => s.len(),
+            _ => 0,
+        };
+        self.visit(depth, payload_len, values, payload_bytes)?;
+        match v {
+            ScVal::Vec(Some(vec)) => {
+                for e in vec.iter() {
+                    self.check_at_depth(e, depth + 1, values, payload_bytes)?;
+                }
+            }
+            ScVal::Map(Some(map)) => {
+                for entry in map.iter() {
+                    self.check_at_depth(&entry.key, depth + 1, values, payload_bytes)?;
+                    self.check_at_depth(&entry.val, depth + 1, values, payload_bytes)?;
+                }
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    // Accounts for a value at `depth` with a payload of `payload_len` bytes,
+    // failing if that exceeds any of the limits.
+    fn visit(
+        &self,
+        depth: u32,
+        payload_len: usize,
+        values: &mut u32,
+        payload_bytes: &mut u32,
+    ) -> Result<(), ScValLimitExceeded> {
+        if depth > self.max_depth {
+            return Err(ScValLimitExceeded::Depth);
+        }
+        *values = values.saturating_add(1);
+        if *values > self.max_values {
+            return Err(ScValLimitExceeded::Values);
+        }
+        *payload_bytes =
+            payload_bytes.saturating_add(u32::try_from(payload_len).unwrap_or(u32::MAX));
+        if *payload_bytes > self.max_payload_bytes {
+            return Err(ScValLimitExceeded::PayloadBytes);
+        }
+        Ok(())
+    }
+}
+
 impl Host {
+    // Same as `ScValLimits::check_counting_values` for the `ScVal` that `val`
+    // converts to, without converting it. Like the conversion, this visits
+    // the host objects with limited depth.
+    fn check_val_counting_values(
+        &self,
+        val: Val,
+        limits: &ScValLimits,
+    ) -> Result<(u32, Result<(), ScValLimitExceeded>), HostError> {
+        let mut values = 0_u32;
+        let mut payload_bytes = 0_u32;
+        let res = self.check_val_at_depth(val, limits, 1, &mut values, &mut payload_bytes)?;
+        Ok((values, res))
+    }
+
+    fn check_val_at_depth(
+        &self,
+        val: Val,
+        limits: &ScValLimits,
+        depth: u32,
+        values: &mut u32,
+        payload_bytes: &mut u32,
+    ) -> Result<Result<(), ScValLimitExceeded>, HostError> {
+        let Ok(obj) = Object::try_from(val) else {
+            let payload_len = match SymbolSmall::try_from(val) {
+                Ok(sym) => sym.to_str().len(),
+                Err(_) => 0,
+            };
+            return Ok(limits.visit(depth, payload_len, values, payload_bytes));
+        };
+        self.budget_cloned().with_limited_depth(|_| {
+            self.visit_obj_untyped(obj, |ho| {
+                let payload_len = match ho {
+                    HostObject::Bytes(b) => b.len(),
+                    HostObject::String(s) => s.len(),
+                    HostObject::Symbol(s) => s.len(),
+                    _ => 0,
+                };
+                if let Err(e) = limits.visit(depth, payload_len, values, payload_bytes) {
+                    return Ok(Err(e));
+                }
+                let mut check_child =
+                    |e: Val| self.check_val_at_depth(e, limits, depth + 1, values, payload_bytes);
+                match ho {
+                    HostObject::Vec(vv) => {
+                        for e in vv.iter() {
+                            let res = check_child(*e)?;
+                            if res.is_err() {
+                                return Ok(res);
+                            }
+                        }
+                    }
+                    HostObject::Map(mm) => {
+                        for (k, v) in mm.iter(self)? {
+                            let mut res = check_child(*k)?;
+                            if res.is_ok() {
+                                res = check_child(*v)?;
+                            }
+                            if res.is_err() {
+                                return Ok(res);
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+                Ok(Ok(()))
+            })
+        })
+    }
+
+    /// Returns whether the [`ScVal`] that `val` converts to is within
+    /// `limits`, without converting it. The traversal stops as soon as any
+    /// limit is exceeded, so its cost is bounded by the limits and not by the
+    /// size of `val`.
+    pub(crate) fn val_within_limits(
+        &self,
+        val: Val,
+        limits: &ScValLimits,
+    ) -> Result<bool, HostError> {
+        Ok(self.check_val_counting_values(val, limits)?.1.is_ok())
+    }
+
+    // Notes on metering: every value visited by the check is charged as a
+    // `VisitObject`. The check stops at the first exceeded limit, so it visits
+    // at most `max_values + 1` values before being charged.
+    fn check_scval_limits(&self, v: &ScVal, limits: &ScValLimits) -> Result<(), HostError> {
+        let (values, res) = limits.check_counting_values(v);
+        self.charge_limits_check(values, res)
+    }
+
+    // Same as `check_scval_limits` for the `ScVal` that `val` converts to.
+    // The visited host objects are also charged when they are visited.
+    fn check_val_limits(&self, val: Val, limits: &ScValLimits) -> Result<(), HostError> {
+        let (values, res) = self.check_val_counting_values(val, limits)?;
+        self.charge_limits_check(values, res)
+    }
+
+    fn charge_limits_check(
+        &self,
+        values: u32,
+        res: Result<(), ScValLimitExceeded>,
+    ) -> Result<(), HostError> {
+        self.as_budget()
+            .bulk_charge(ContractCostType::VisitObject, values as u64, None)?;
+        let msg = match res {
+            Ok(()) => return Ok(()),
+            Err(ScValLimitExceeded::Depth) => "value exceeds the depth limit",
+            Err(ScValLimitExceeded::Values) => "value exceeds the number of values limit",
+            Err(ScValLimitExceeded::PayloadBytes) => "value exceeds the payload size limit",
+        };
+        Err(self.err(ScErrorType::Value, ScErrorCode::ExceededLimit, msg, &[]))
+    }
+
+    /// Converts an untrusted [`ScVal`] to a host [`Val`], failing with a
+    /// `(Value, ExceededLimit)` error if the value exceeds any of the
+    /// provided `limits`. The limits are checked before any host objects are
+    /// created. Use [`ScValLimits::check`] to find out which limit has been
+    /// exceeded.
+    pub fn scval_to_val_with_limits(
+        &self,
+        v: &ScVal,
+        limits: &ScValLimits,
+    ) -> Result<Val, HostError> {
+        self.check_scval_limits(v, limits)?;
+        self.to_host_val(v)
+    }
+
+    /// Converts a host [`Val`] to an [`ScVal`], failing with a
+    /// `(Value, ExceededLimit)` error if the resulting value exceeds any of
+    /// the provided `limits`. The limits are checked before the value is
+    /// converted.
+    pub fn val_to_scval_with_limits(
+        &self,
+        val: Val,
+        limits: &ScValLimits,
+    ) -> Result<ScVal, HostError> {
+        self.check_val_limits(val, limits)?;
+        self.from_host_val(val)
+    }
+
+    /// Converts a trusted [`ScVal`] to a host [`Val`], creating the host
+    /// objects backing it. Use [`Host::scval_to_val_with_limits`] for
+    /// untrusted values.
+    ///
+    /// The conversion is metered, so large values may fail with a
+    /// `(Budget, ExceededLimit)` error. Containers may be nested at most
+    /// [`DEFAULT_HOST_DEPTH_LIMIT`](crate::DEFAULT_HOST_DEPTH_LIMIT) levels
+    /// deep, and deeper values fail with a `(Value, InternalError)` error.
+    pub fn scval_to_val(&self, v: &ScVal) -> Result<Val, HostError> {
+        self.to_host_val(v)
+    }
+
+    /// Converts a host [`Val`] to an [`ScVal`]. The conversion is metered
+    /// and depth-limited like [`Host::scval_to_val`], except that deeper
+    /// values fail with a `(Value, InvalidInput)` error.
+    pub fn val_to_scval(&self, val: Val) -> Result<ScVal, HostError> {
+        self.from_host_val(val)
+    }
+
     // Notes on metering: free
     pub(crate) fn usize_to_u32(&self, u: usize) -> Result<u32, HostError> {
         match u32::try_from(u) {
@@ -475,6 +727,7 @@ impl Host {
             // below. There is no otherwise ubiquitous metering for ScVal->Val conversion,
             // since most of them happens in the "common" crate with no access to the host.
             ScVal::Vec(Some(v)) => {
+                self.check_object_len::<HostVec>(v.len())?;
                 Vec::<Val>::charge_bulk_init_cpy(v.len() as u64, self)?;
                 let mut vv = Vec::with_capacity(v.len());
                 for e in v.iter() {
@@ -483,6 +736,7 @@ impl Host {
                 Ok(self.add_host_object(HostVec::from_vec(vv)?)?.into())
             }
             ScVal::Map(Some(m)) => {
+                self.check_object_len::<HostMap>(m.len())?;
                 Vec::<(Val, Val)>::charge_bulk_init_cpy(m.len() as u64, self)?;
                 let mut mm = Vec::with_capacity(m.len());
                 for pair in m.iter() {
@@ -538,9 +792,15 @@ impl Host {
                     .add_host_object(i256_from_pieces(i.hi_hi, i.hi_lo, i.lo_hi, i.lo_lo))?
                     .into())
             }
-            ScVal::Bytes(b) => Ok(self.add_host_object(b.metered_clone(self)?)?.into()),
-            ScVal::String(s) => Ok(self.add_host_object(s.metered_clone(self)?)?.into()),
-            ScVal::Symbol(s) => Ok(self.add_host_object(s.metered_clone(self)?)?.into()),
+            ScVal::Bytes(b) => {
+                self.check_object_len::<ScBytes>(b.len())?;
+                Ok(self.add_host_object(b.metered_clone(self)?)?.into())
+            }
+            ScVal::String(s) => {
+                self.check_object_len::<ScString>(s.len())?;
+                Ok(self.add_host_object(s.metered_clone(self)?)?.into())
+            }
+            ScVal::Symbol(s) => Ok(self.intern_symbol(s.metered_clone(self)?)?.into()),
             ScVal::Address(addr) => Ok(self.add_host_object(addr.metered_clone(self)?)?.into()),
             ScVal::Bool(_)
             | ScVal::Void
@@ -2,7 +2,11 @@ use crate::{
     budget::AsBudget,
     err,
     xdr::{ContractCostType, Hash, ScBytes, ScErrorCode, ScErrorType},
-    BytesObject, Host, HostError, U32Val, Val,
+    BytesObject, Host, HostError, TryFromVal, U32Val, Val,
+};
+use curve25519_dalek::{
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    Scalar,
 };
 use rand::RngCore;
 use rand_chacha::ChaCha20Rng;
@@ -81,6 +85,77 @@ impl Host {
         })
     }
 
+    // Verifies the signatures of `payload` by a weighted set of ed25519 keys
+    // and returns whether the total weight of the keys that have signed
+    // reaches `threshold`. `sigs` has an entry for every key, either a
+    // signature or `Void` for the keys that haven't signed. Fails if any of
+    // the provided signatures is invalid, or if a key is duplicated.
+    pub(crate) fn verify_multisig_ed25519_internal(
+        &self,
+        keys: &[Val],
+        weights: &[Val],
+        threshold: u32,
+        payload: BytesObject,
+        sigs: &[Val],
+    ) -> Result<bool, HostError> {
+        if keys.len() != weights.len() || keys.len() != sigs.len() {
+            return Err(self.err(
+                ScErrorType::Crypto,
+                ScErrorCode::InvalidInput,
+                "keys, weights and signatures must have the same length",
+                &[
+                    U32Val::from(keys.len() as u32).into(),
+                    U32Val::from(weights.len() as u32).into(),
+                    U32Val::from(sigs.len() as u32).into(),
+                ],
+            ));
+        }
+        if threshold == 0 {
+            return Err(self.err(
+                ScErrorType::Crypto,
+                ScErrorCode::InvalidInput,
+                "multisig threshold must be positive",
+                &[],
+            ));
+        }
+        let mut signers = Vec::with_capacity(keys.len());
+        let mut verifying_keys: Vec<ed25519_dalek::VerifyingKey> = Vec::with_capacity(keys.len());
+        for ((key, weight), sig) in keys.iter().zip(weights).zip(sigs) {
+            let verifying_key =
+                self.ed25519_pub_key_from_bytesobj_input(BytesObject::try_from_val(self, key)?)?;
+            self.charge_budget(
+                ContractCostType::HostMemCmp,
+                Some((verifying_keys.len() * ed25519_dalek::PUBLIC_KEY_LENGTH) as u64),
+            )?;
+            if verifying_keys.contains(&verifying_key) {
+                return Err(self.err(
+                    ScErrorType::Crypto,
+                    ScErrorCode::InvalidInput,
+                    "duplicate multisig key",
+                    &[*key],
+                ));
+            }
+            verifying_keys.push(verifying_key);
+            let weight = u32::try_from_val(self, weight)?;
+            if sig.is_void() {
+                continue;
+            }
+            let sig = self.ed25519_signature_from_bytesobj_input(
+                "sig",
+                BytesObject::try_from_val(self, sig)?,
+            )?;
+            signers.push((verifying_key, weight, sig));
+        }
+        self.visit_obj(payload, |payload: &ScBytes| {
+            let mut total_weight: u64 = 0;
+            for (verifying_key, weight, sig) in signers.iter() {
+                self.verify_sig_ed25519_internal(payload.as_slice(), verifying_key, sig)?;
+                total_weight += *weight as u64;
+            }
+            Ok(total_weight >= threshold as u64)
+        })
+    }
+
     // ECDSA secp256k1 functions
 
     pub(crate) fn secp256k1_pub_key_from_bytes(
@@ -193,6 +268,150 @@ impl Host {
         self.add_host_object(rk)
     }
 
+    /// Verifies an ECDSA-secp256k1 `sig` over the 32-byte prehashed `digest`
+    /// against `pub_key`.
+    pub(crate) fn verify_sig_ecdsa_secp256k1_internal(
+        &self,
+        pub_key: &k256::PublicKey,
+        digest: &[u8],
+        sig: &k256::ecdsa::Signature,
+    ) -> Result<(), HostError> {
+        use k256::ecdsa::signature::hazmat::PrehashVerifier;
+        let _span = tracy_span!("secp256k1 verify");
+        // There is no dedicated cost type for secp256k1 verification; key
+        // recovery is a close upper bound for it.
+        self.charge_budget(ContractCostType::RecoverEcdsaSecp256k1Key, None)?;
+        k256::ecdsa::VerifyingKey::from(pub_key)
+            .verify_prehash(digest, sig)
+            .map_err(|_| {
+                self.err(
+                    ScErrorType::Crypto,
+                    ScErrorCode::InvalidInput,
+                    "failed ECDSA-secp256k1 verification",
+                    &[],
+                )
+            })
+    }
+
+    // ECDSA secp256r1 functions
+
+    /// Verifies an ECDSA-secp256r1 `sig` (64-byte `r || s`) over the 32-byte
+    /// prehashed `digest` against a SEC-1 encoded `pub_key`. High-s signatures
+    /// are rejected to prevent signature malleability.
+    pub(crate) fn verify_sig_ecdsa_secp256r1_internal(
+        &self,
+        pub_key: &[u8],
+        digest: &[u8],
+        sig: &[u8],
+    ) -> Result<(), HostError> {
+        use p256::ecdsa::{signature::hazmat::PrehashVerifier, Signature, VerifyingKey};
+        let _span = tracy_span!("secp256r1 verify");
+        // There is no dedicated cost type for secp256r1 yet; key recovery on
+        // secp256k1 is a close upper bound for a single verification.
+        self.charge_budget(ContractCostType::RecoverEcdsaSecp256k1Key, None)?;
+        let invalid_input =
+            |msg: &'static str| self.err(ScErrorType::Crypto, ScErrorCode::InvalidInput, msg, &[]);
+        if digest.len() != 32 {
+            return Err(invalid_input("ECDSA-secp256r1 digest must be 32 bytes"));
+        }
+        let vk = VerifyingKey::from_sec1_bytes(pub_key)
+            .map_err(|_| invalid_input("invalid ECDSA-secp256r1 public key"))?;
+        let sig = Signature::from_slice(sig)
+            .map_err(|_| invalid_input("invalid ECDSA-secp256r1 signature"))?;
+        if sig.normalize_s().is_some() {
+            return Err(invalid_input(
+                "ECDSA-secp256r1 signature 's' part is not normalized",
+            ));
+        }
+        vk.verify_prehash(digest, &sig)
+            .map_err(|_| invalid_input("failed ECDSA-secp256r1 verification"))
+    }
+
+    // Ristretto255 functions
+
+    pub(crate) fn ristretto255_point_from_bytesobj_input(
+        &self,
+        name: &'static str,
+        point: BytesObject,
+    ) -> Result<RistrettoPoint, HostError> {
+        let bytes = self.fixed_length_bytes_from_bytesobj_input::<[u8; 32], 32>(name, point)?;
+        // There is no dedicated cost type for ristretto255; decompressing a
+        // point is the bulk of decoding an ed25519 public key.
+        self.charge_budget(ContractCostType::ComputeEd25519PubKey, None)?;
+        CompressedRistretto(bytes).decompress().ok_or_else(|| {
+            err!(
+                self,
+                (ScErrorType::Crypto, ScErrorCode::InvalidInput),
+                "invalid ristretto255 point",
+                name
+            )
+        })
+    }
+
+    pub(crate) fn ristretto255_scalar_from_bytesobj_input(
+        &self,
+        scalar: BytesObject,
+    ) -> Result<Scalar, HostError> {
+        let bytes =
+            self.fixed_length_bytes_from_bytesobj_input::<[u8; 32], 32>("scalar", scalar)?;
+        Option::<Scalar>::from(Scalar::from_canonical_bytes(bytes)).ok_or_else(|| {
+            self.err(
+                ScErrorType::Crypto,
+                ScErrorCode::InvalidInput,
+                "ristretto255 scalar is not reduced modulo the group order",
+                &[],
+            )
+        })
+    }
+
+    fn ristretto255_point_to_bytesobj(
+        &self,
+        point: &RistrettoPoint,
+    ) -> Result<BytesObject, HostError> {
+        self.add_host_object(self.scbytes_from_slice(point.compress().as_bytes())?)
+    }
+
+    pub(crate) fn ristretto255_point_add_internal(
+        &self,
+        a: BytesObject,
+        b: BytesObject,
+    ) -> Result<BytesObject, HostError> {
+        let _span = tracy_span!("ristretto255 add");
+        let a = self.ristretto255_point_from_bytesobj_input("a", a)?;
+        let b = self.ristretto255_point_from_bytesobj_input("b", b)?;
+        // Compressing the sum costs about as much as a decompression, which
+        // dwarfs the addition itself.
+        self.charge_budget(ContractCostType::ComputeEd25519PubKey, None)?;
+        self.ristretto255_point_to_bytesobj(&(a + b))
+    }
+
+    pub(crate) fn ristretto255_scalar_mul_internal(
+        &self,
+        scalar: BytesObject,
+        point: BytesObject,
+    ) -> Result<BytesObject, HostError> {
+        let _span = tracy_span!("ristretto255 scalar mul");
+        let scalar = self.ristretto255_scalar_from_bytesobj_input(scalar)?;
+        let point = self.ristretto255_point_from_bytesobj_input("point", point)?;
+        // Verifying an ed25519 signature of an empty payload is dominated by
+        // a double-scalar multiplication, which is a close upper bound for a
+        // single scalar multiplication and the compression of the product.
+        self.charge_budget(ContractCostType::VerifyEd25519Sig, Some(0))?;
+        self.ristretto255_point_to_bytesobj(&(point * scalar))
+    }
+
+    pub(crate) fn ristretto255_basepoint_mul_internal(
+        &self,
+        scalar: BytesObject,
+    ) -> Result<BytesObject, HostError> {
+        let _span = tracy_span!("ristretto255 basepoint mul");
+        let scalar = self.ristretto255_scalar_from_bytesobj_input(scalar)?;
+        // Same as for `ristretto255_scalar_mul_internal`, even though the
+        // precomputed basepoint table makes this cheaper.
+        self.charge_budget(ContractCostType::VerifyEd25519Sig, Some(0))?;
+        self.ristretto255_point_to_bytesobj(&RistrettoPoint::mul_base(&scalar))
+    }
+
     // SHA256 functions
 
     pub(crate) fn sha256_hash_from_bytesobj_input(
@@ -2,23 +2,37 @@ use core::cmp::min;
 use std::rc::Rc;
 
 use soroban_env_common::xdr::{
-    BytesM, ContractCodeEntry, ContractDataDurability, ContractExecutable, ContractIdPreimage,
-    ExtensionPoint, HashIdPreimageContractId, ScAddress, ScContractInstance, ScErrorCode,
-    ScErrorType,
+    Asset, BytesM, ContractCodeEntry, ContractDataDurability, ContractExecutable,
+    ContractIdPreimage, ExtensionPoint, HashIdPreimageContractId, ScAddress, ScContractInstance,
+    ScErrorCode, ScErrorType,
+};
+use soroban_env_common::{
+    AddressObject, BytesObject, Env, EnvBase, I32Val, MapObject, StorageType, Symbol, TryFromVal,
+    TryIntoVal, U32Val, Val,
 };
-use soroban_env_common::{AddressObject, Env, StorageType, U32Val, Val};
 
 use crate::budget::AsBudget;
-use crate::storage::{InstanceStorageMap, Storage};
+use crate::native_contract::token::read_asset_balance;
+use crate::storage::{AccessType, FootprintMode, InstanceStorageMap, Storage};
 use crate::xdr::{
     AccountEntry, AccountId, ContractDataEntry, Hash, HashIdPreimage, LedgerEntry, LedgerEntryData,
     LedgerEntryExt, LedgerKey, LedgerKeyAccount, LedgerKeyContractCode, LedgerKeyContractData,
-    LedgerKeyTrustLine, PublicKey, ScVal, Signer, SignerKey, ThresholdIndexes, TrustLineAsset,
-    Uint256,
+    LedgerKeyLiquidityPool, LedgerKeyOffer, LedgerKeyTrustLine, LiquidityPoolEntryBody, PoolId,
+    PublicKey, ScMapEntry, ScVal, Signer, SignerKey, ThresholdIndexes, TrustLineAsset, Uint256,
+    WriteXdr,
 };
 use crate::{err, Host, HostError};
 
-use super::metered_clone::{MeteredAlloc, MeteredClone};
+use super::metered_clone::{MeteredAlloc, MeteredClone, MeteredContainer};
+use super::metered_xdr::metered_write_xdr;
+
+/// Symbol that tags the temporary contract data keys used for claimed
+/// idempotency keys.
+pub(crate) const IDEMPOTENCY_KEY_TAG: &str = "IdempotencyKey";
+
+/// Symbol that tags the temporary contract data keys used for the token
+/// buckets of rate limiters.
+pub(crate) const RATE_LIMIT_TAG: &str = "RateLimit";
 
 impl Host {
     pub fn with_mut_storage<F, U>(&self, f: F) -> Result<U, HostError>
@@ -114,6 +128,25 @@ impl Host {
         }
     }
 
+    // Notes on metering: covered by components.
+    pub(crate) fn retrieve_contract_wasm_hash(
+        &self,
+        contract: AddressObject,
+    ) -> Result<Hash, HostError> {
+        let contract_id = self.contract_id_from_address(contract)?;
+        let key = self.contract_instance_ledger_key(&contract_id)?;
+        let instance = self.retrieve_contract_instance_from_storage(&key)?;
+        match instance.executable {
+            ContractExecutable::Wasm(wasm_hash) => Ok(wasm_hash),
+            ContractExecutable::Token => Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "contract does not have Wasm executable",
+                &[contract.to_val()],
+            )),
+        }
+    }
+
     pub(crate) fn contract_code_ledger_key(
         &self,
         wasm_hash: &Hash,
@@ -143,6 +176,39 @@ impl Host {
         }
     }
 
+    /// Tries to retrieve the Wasm code from the embedder-provided
+    /// [`CodeStore`](crate::storage::CodeStore), if any. Returns `None` when
+    /// there is no code store or it doesn't have the code, in which case the
+    /// caller should fall back to [`Host::retrieve_wasm_from_storage`].
+    ///
+    /// Code coming from the store is verified to match `wasm_hash` and the
+    /// access to the code entry is still subject to the footprint.
+    pub(crate) fn retrieve_wasm_from_code_store(
+        &self,
+        wasm_hash: &Hash,
+    ) -> Result<Option<Rc<[u8]>>, HostError> {
+        let Some(code_store) = self.try_borrow_code_store()?.clone() else {
+            return Ok(None);
+        };
+        let Some(code) = code_store.get_code(wasm_hash)? else {
+            return Ok(None);
+        };
+        let actual_hash = crate::host::crypto::sha256_hash_from_bytes(code.as_ref(), self)?;
+        if actual_hash.as_slice() != wasm_hash.0.as_slice() {
+            return Err(err!(
+                self,
+                (ScErrorType::Storage, ScErrorCode::InternalError),
+                "code store returned Wasm that doesn't match the requested hash",
+                *wasm_hash
+            ));
+        }
+        let key = self.contract_code_ledger_key(wasm_hash)?;
+        self.try_borrow_storage_mut()?
+            .prepare_read_only_access_without_load(&key, self.as_budget())
+            .map_err(|e| self.decorate_contract_code_storage_error(e, wasm_hash))?;
+        Ok(Some(code))
+    }
+
     pub(crate) fn wasm_exists(&self, wasm_hash: &Hash) -> Result<bool, HostError> {
         let key = self.contract_code_ledger_key(wasm_hash)?;
         self.try_borrow_storage_mut()?
@@ -208,26 +274,63 @@ impl Host {
         Ok(())
     }
 
-    pub(crate) fn bump_contract_instance_and_code_from_contract_id(
+    // Bumps the instance of `contract_id`, whose ledger key is `key`.
+    fn bump_contract_instance(
         &self,
         contract_id: &Hash,
+        key: Rc<LedgerKey>,
         low_expiration_watermark: u32,
         high_expiration_watermark: u32,
     ) -> Result<(), HostError> {
-        let key = self.contract_instance_ledger_key(&contract_id)?;
         self.try_borrow_storage_mut()?
             .bump(
                 self,
-                key.metered_clone(self)?,
+                key,
                 low_expiration_watermark,
                 high_expiration_watermark,
             )
-            .map_err(|e| self.decorate_contract_instance_storage_error(e, &contract_id))?;
+            .map_err(|e| self.decorate_contract_instance_storage_error(e, &contract_id))
+    }
+
+    /// Bumps the instance of `contract_id` without its code, e.g. for the
+    /// built-in contracts whose executable isn't a code entry.
+    pub(crate) fn bump_contract_instance_from_contract_id(
+        &self,
+        contract_id: &Hash,
+        low_expiration_watermark: u32,
+        high_expiration_watermark: u32,
+    ) -> Result<(), HostError> {
+        let key = self.contract_instance_ledger_key(&contract_id)?;
+        self.bump_contract_instance(
+            contract_id,
+            key,
+            low_expiration_watermark,
+            high_expiration_watermark,
+        )
+    }
+
+    pub(crate) fn bump_contract_instance_and_code_from_contract_id(
+        &self,
+        contract_id: &Hash,
+        low_expiration_watermark: u32,
+        high_expiration_watermark: u32,
+    ) -> Result<(), HostError> {
+        let key = self.contract_instance_ledger_key(&contract_id)?;
+        self.bump_contract_instance(
+            contract_id,
+            key.metered_clone(self)?,
+            low_expiration_watermark,
+            high_expiration_watermark,
+        )?;
         match self
             .retrieve_contract_instance_from_storage(&key)?
             .executable
         {
-            ContractExecutable::Wasm(wasm_hash) => {
+            // Built-in contracts reserve their executable hash and have no
+            // code entry to bump.
+            ContractExecutable::Wasm(wasm_hash)
+                if self.builtin_wasm_contract(&wasm_hash)?.is_none() =>
+            {
                 let key = self.contract_code_ledger_key(&wasm_hash)?;
                 self.try_borrow_storage_mut()?
                     .bump(
@@ -238,11 +341,37 @@ impl Host {
                     )
                     .map_err(|e| self.decorate_contract_code_storage_error(e, &wasm_hash))?;
             }
-            ContractExecutable::Token => {}
+            ContractExecutable::Wasm(_) | ContractExecutable::Token => {}
         }
         Ok(())
     }
 
+    /// Returns the hash committing to the ledger entries written so far, see
+    /// [`Storage::written_entries_hash`].
+    ///
+    /// Instance storage changes of the contracts that are still running are
+    /// only written to the storage when their frames finish, so they are not
+    /// covered until then.
+    pub fn written_entries_hash(&self) -> Result<Hash, HostError> {
+        let hash = self
+            .try_borrow_storage()?
+            .written_entries_hash(self.budget_ref())?;
+        Ok(Hash(hash))
+    }
+
+    /// Returns the ledger key accesses that have been rejected by the
+    /// enforcing footprint so far, in the order they were attempted. Running
+    /// the same invocation in the recording mode produces a footprint that
+    /// covers all of them.
+    pub fn footprint_violations(&self) -> Result<Vec<(LedgerKey, AccessType)>, HostError> {
+        Ok(self
+            .try_borrow_storage()?
+            .footprint_violations
+            .iter()
+            .map(|(key, ty)| (key.as_ref().clone(), *ty))
+            .collect())
+    }
+
     // metering: covered by components
     pub fn get_full_contract_id_preimage(
         &self,
@@ -316,6 +445,134 @@ impl Host {
         )
     }
 
+    // Notes on metering: the read is covered by the storage.
+    fn read_classic_entry(
+        &self,
+        key: &Rc<LedgerKey>,
+    ) -> Result<Option<Rc<LedgerEntry>>, HostError> {
+        self.with_mut_storage(|storage| storage.try_get(key, self.as_budget()))
+    }
+
+    /// Returns the balances of `address` in the Stellar Asset Contracts of
+    /// `assets`, in order, with the same results as calling their `balance`
+    /// functions, but without invoking the contracts. This is meant for
+    /// embedders that query many balances of one address. The balance entries
+    /// have to be in the footprint, and fail the same way as in the contracts
+    /// when missing, e.g. for missing trustlines.
+    pub fn sac_balances(
+        &self,
+        address: &ScAddress,
+        assets: &[Asset],
+    ) -> Result<Vec<i128>, HostError> {
+        Vec::<i128>::charge_bulk_init_cpy(assets.len() as u64, self)?;
+        let mut balances = Vec::with_capacity(assets.len());
+        for asset in assets {
+            let contract_id = self.get_asset_contract_id_hash(asset.metered_clone(self)?)?;
+            balances.push(read_asset_balance(self, &contract_id, asset, address)?);
+        }
+        Ok(balances)
+    }
+
+    // Classic assets are represented by the address of their asset contract,
+    // which is how contracts interact with them.
+    fn classic_asset_to_address(&self, asset: &Asset) -> Result<Val, HostError> {
+        let contract_id = self.get_asset_contract_id_hash(asset.metered_clone(self)?)?;
+        Ok(self
+            .add_host_object(ScAddress::Contract(contract_id))?
+            .to_val())
+    }
+
+    /// Returns the DEX offer `offer_id` of the account `seller` as a map, or
+    /// `None` if the offer doesn't exist. The offer has to be in the
+    /// footprint.
+    pub(crate) fn classic_offer_to_map(
+        &self,
+        seller: AccountId,
+        offer_id: i64,
+    ) -> Result<Option<MapObject>, HostError> {
+        let key = Rc::metered_new(
+            LedgerKey::Offer(LedgerKeyOffer {
+                seller_id: seller,
+                offer_id,
+            }),
+            self,
+        )?;
+        let Some(entry) = self.read_classic_entry(&key)? else {
+            return Ok(None);
+        };
+        let LedgerEntryData::Offer(offer) = &entry.data else {
+            return Err(self.err(
+                ScErrorType::Storage,
+                ScErrorCode::InternalError,
+                "ledger entry is not an offer",
+                &[],
+            ));
+        };
+        let vals = [
+            offer.amount.try_into_val(self)?,
+            self.classic_asset_to_address(&offer.buying)?,
+            U32Val::from(offer.flags).to_val(),
+            offer.offer_id.try_into_val(self)?,
+            I32Val::from(offer.price.d).to_val(),
+            I32Val::from(offer.price.n).to_val(),
+            self.add_host_object(ScAddress::Account(offer.seller_id.metered_clone(self)?))?
+                .to_val(),
+            self.classic_asset_to_address(&offer.selling)?,
+        ];
+        Ok(Some(self.map_new_from_slices(
+            &[
+                "amount", "buying", "flags", "offer_id", "price_d", "price_n", "seller", "selling",
+            ],
+            &vals,
+        )?))
+    }
+
+    /// Returns the parameters and reserves of the classic liquidity pool
+    /// `pool_id` as a map, or `None` if the pool doesn't exist. The pool has
+    /// to be in the footprint.
+    pub(crate) fn classic_liquidity_pool_to_map(
+        &self,
+        pool_id: Hash,
+    ) -> Result<Option<MapObject>, HostError> {
+        let key = Rc::metered_new(
+            LedgerKey::LiquidityPool(LedgerKeyLiquidityPool {
+                liquidity_pool_id: PoolId(pool_id),
+            }),
+            self,
+        )?;
+        let Some(entry) = self.read_classic_entry(&key)? else {
+            return Ok(None);
+        };
+        let LedgerEntryData::LiquidityPool(pool) = &entry.data else {
+            return Err(self.err(
+                ScErrorType::Storage,
+                ScErrorCode::InternalError,
+                "ledger entry is not a liquidity pool",
+                &[],
+            ));
+        };
+        let LiquidityPoolEntryBody::LiquidityPoolConstantProduct(cp) = &pool.body;
+        let vals = [
+            self.classic_asset_to_address(&cp.params.asset_a)?,
+            self.classic_asset_to_address(&cp.params.asset_b)?,
+            I32Val::from(cp.params.fee).to_val(),
+            cp.reserve_a.try_into_val(self)?,
+            cp.reserve_b.try_into_val(self)?,
+            cp.total_pool_shares.try_into_val(self)?,
+        ];
+        Ok(Some(self.map_new_from_slices(
+            &[
+                "asset_a",
+                "asset_b",
+                "fee_bps",
+                "reserve_a",
+                "reserve_b",
+                "total_pool_shares",
+            ],
+            &vals,
+        )?))
+    }
+
     pub(crate) fn get_signer_weight_from_account(
         &self,
         target_signer: Uint256,
@@ -442,12 +699,12 @@ impl Host {
         if self
             .try_borrow_storage_mut()?
             .has(&key, self.as_budget())
-            .map_err(|e| self.decorate_contract_data_storage_error(e, k))?
+            .map_err(|e| self.decorate_contract_data_storage_error(e, k, &key))?
         {
             let (current, expiration_ledger) = self
                 .try_borrow_storage_mut()?
                 .get_with_expiration(&key, self.as_budget())
-                .map_err(|e| self.decorate_contract_data_storage_error(e, k))?;
+                .map_err(|e| self.decorate_contract_data_storage_error(e, k, &key))?;
             let mut current = (*current).metered_clone(self)?;
             match current.data {
                 LedgerEntryData::ContractData(ref mut entry) => {
@@ -469,7 +726,7 @@ impl Host {
                     expiration_ledger,
                     self.as_budget(),
                 )
-                .map_err(|e| self.decorate_contract_data_storage_error(e, k))?;
+                .map_err(|e| self.decorate_contract_data_storage_error(e, k, &key))?;
         } else {
             let data = ContractDataEntry {
                 contract: ScAddress::Contract(self.get_current_contract_id_internal()?),
@@ -485,15 +742,256 @@ impl Host {
                     Some(self.get_min_expiration_ledger(durability)?),
                     self.as_budget(),
                 )
-                .map_err(|e| self.decorate_contract_data_storage_error(e, k))?;
+                .map_err(|e| self.decorate_contract_data_storage_error(e, k, &key))?;
         }
 
         Ok(())
     }
+
+    /// Checks whether writing `v` under `k` to the storage `t` of the current
+    /// contract would succeed, without performing the write. Returns `false`
+    /// if `k` can't be used as a key of the storage `t` (e.g. for the reserved
+    /// ledger key values), if the written entry isn't in the read-write
+    /// footprint, or if the written value can't be serialized within the XDR
+    /// read limits of the host. For the instance storage the written entry is
+    /// the instance entry of the contract and only the size of the new map
+    /// entry is checked.
+    ///
+    /// Budget errors are still returned, as they abort the execution anyway.
+    pub(crate) fn can_put_contract_data_internal(
+        &self,
+        k: Val,
+        v: Val,
+        t: StorageType,
+    ) -> Result<bool, HostError> {
+        match self.check_contract_data_write(k, v, t) {
+            Ok(()) => Ok(true),
+            Err(e) if e.error.is_type(ScErrorType::Budget) => Err(e),
+            Err(_) => Ok(false),
+        }
+    }
+
+    fn check_contract_data_write(&self, k: Val, v: Val, t: StorageType) -> Result<(), HostError> {
+        self.check_contract_data_not_locked(k, &t)?;
+        let (key, size) = match t {
+            StorageType::Temporary | StorageType::Persistent => {
+                let durability: ContractDataDurability = t.try_into()?;
+                let key = self.contract_data_key_from_rawval(k, durability)?;
+                let entry = ContractDataEntry {
+                    contract: ScAddress::Contract(self.get_current_contract_id_internal()?),
+                    key: self.from_host_val(k)?,
+                    val: self.from_host_val(v)?,
+                    durability,
+                    ext: ExtensionPoint::V0,
+                };
+                (key, self.metered_xdr_len(&entry)?)
+            }
+            StorageType::Instance => {
+                let key =
+                    self.contract_instance_ledger_key(&self.get_current_contract_id_internal()?)?;
+                let entry = ScMapEntry {
+                    key: self.from_host_val(k)?,
+                    val: self.from_host_val(v)?,
+                };
+                (key, self.metered_xdr_len(&entry)?)
+            }
+        };
+        {
+            let mut storage = self.try_borrow_storage_mut()?;
+            if let FootprintMode::Enforcing = storage.mode {
+                storage
+                    .footprint
+                    .enforce_access(&key, AccessType::ReadWrite, self.as_budget())?;
+            }
+        }
+        let limit = self.xdr_read_limits()?.len;
+        if size > limit as usize {
+            return Err(self.err(
+                ScErrorType::Storage,
+                ScErrorCode::ExceededLimit,
+                "contract data entry exceeds the xdr length limit",
+                &[k, limit.into()],
+            ));
+        }
+        Ok(())
+    }
+
+    fn metered_xdr_len(&self, v: &impl WriteXdr) -> Result<usize, HostError> {
+        let mut buf = vec![];
+        metered_write_xdr(self.as_budget(), v, &mut buf)?;
+        Ok(buf.len())
+    }
+
+    /// Records `key` as claimed by the current contract by writing a
+    /// temporary contract data entry under `(IDEMPOTENCY_KEY_TAG, key)`.
+    /// Fails if the entry already exists and hasn't expired yet.
+    pub(crate) fn claim_idempotency_key_internal(&self, key: BytesObject) -> Result<(), HostError> {
+        let tag = Symbol::try_from_val(self, &IDEMPOTENCY_KEY_TAG)?;
+        let k: Val = self
+            .vec_new_from_slice(&[tag.to_val(), key.to_val()])?
+            .into();
+        let durability = ContractDataDurability::Temporary;
+        let ledger_key = self.contract_data_key_from_rawval(k, durability)?;
+        if self
+            .try_borrow_storage_mut()?
+            .has(&ledger_key, self.as_budget())
+            .map_err(|e| self.decorate_contract_data_storage_error(e, k, &ledger_key))?
+        {
+            let (_, expiration_ledger) = self
+                .try_borrow_storage_mut()?
+                .get_with_expiration(&ledger_key, self.as_budget())
+                .map_err(|e| self.decorate_contract_data_storage_error(e, k, &ledger_key))?;
+            let ledger_seq = self.with_ledger_info_provider(|li| Ok(li.sequence_number()))?;
+            // An expired claim is equivalent to a missing one, so it can be
+            // claimed again.
+            if expiration_ledger.map_or(true, |e| e >= ledger_seq) {
+                return Err(self.err(
+                    ScErrorType::Storage,
+                    ScErrorCode::ExistingValue,
+                    "idempotency key has already been claimed",
+                    &[key.to_val()],
+                ));
+            }
+        }
+        let data = ContractDataEntry {
+            contract: ScAddress::Contract(self.get_current_contract_id_internal()?),
+            key: self.from_host_val(k)?,
+            val: ScVal::Void,
+            durability,
+            ext: ExtensionPoint::V0,
+        };
+        self.try_borrow_storage_mut()?
+            .put(
+                &ledger_key,
+                &Host::new_contract_data(self, data)?,
+                Some(self.get_min_expiration_ledger(durability)?),
+                self.as_budget(),
+            )
+            .map_err(|e| self.decorate_contract_data_storage_error(e, k, &ledger_key))
+    }
+
+    /// Refills the token bucket of the rate limiter `key` of the current
+    /// contract by `refill_per_ledger` tokens per ledger since its last use,
+    /// up to `capacity`, and consumes `cost` tokens from it if it holds
+    /// enough. Returns whether the tokens have been consumed.
+    ///
+    /// The bucket is stored as `[tokens, last_ledger]` in a temporary contract
+    /// data entry under `(RATE_LIMIT_TAG, key)`. A missing or expired bucket
+    /// is full, so the entry is kept alive only until the bucket would have
+    /// been refilled.
+    pub(crate) fn rate_limit_check_and_consume_internal(
+        &self,
+        key: Val,
+        capacity: u32,
+        refill_per_ledger: u32,
+        cost: u32,
+    ) -> Result<bool, HostError> {
+        self.check_val_integrity(key)?;
+        if capacity == 0 || cost > capacity {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "rate limit cost must not exceed a non-zero capacity",
+                &[U32Val::from(capacity).to_val(), U32Val::from(cost).to_val()],
+            ));
+        }
+        let tag = Symbol::try_from_val(self, &RATE_LIMIT_TAG)?;
+        let k: Val = self.vec_new_from_slice(&[tag.to_val(), key])?.into();
+        let durability = ContractDataDurability::Temporary;
+        let ledger_key = self.contract_data_key_from_rawval(k, durability)?;
+        let ledger_seq = self.with_ledger_info_provider(|li| Ok(li.sequence_number()))?;
+
+        let mut tokens = capacity;
+        let mut expiration = None;
+        if self
+            .try_borrow_storage_mut()?
+            .has(&ledger_key, self.as_budget())
+            .map_err(|e| self.decorate_contract_data_storage_error(e, k, &ledger_key))?
+        {
+            let (entry, expiration_ledger) = self
+                .try_borrow_storage_mut()?
+                .get_with_expiration(&ledger_key, self.as_budget())
+                .map_err(|e| self.decorate_contract_data_storage_error(e, k, &ledger_key))?;
+            if expiration_ledger.map_or(true, |e| e >= ledger_seq) {
+                let (stored_tokens, last_ledger) = self.rate_limit_bucket_from_entry(&entry, k)?;
+                let refill = u64::from(refill_per_ledger)
+                    .saturating_mul(u64::from(ledger_seq.saturating_sub(last_ledger)));
+                tokens = min(
+                    u64::from(stored_tokens).saturating_add(refill),
+                    u64::from(capacity),
+                ) as u32;
+                expiration = expiration_ledger;
+            }
+        }
+        let consumed = tokens >= cost;
+        if consumed {
+            tokens -= cost;
+        }
+
+        // Keep the bucket until it would have been refilled, as a missing
+        // bucket is full.
+        let min_expiration = self.get_min_expiration_ledger(durability)?;
+        let max_expiration = self.max_expiration_ledger()?;
+        let missing_tokens = capacity - tokens;
+        let refill_expiration = if missing_tokens == 0 {
+            min_expiration
+        } else if refill_per_ledger == 0 {
+            max_expiration
+        } else {
+            let ledgers_to_refill = (missing_tokens - 1) / refill_per_ledger + 1;
+            min(ledger_seq.saturating_add(ledgers_to_refill), max_expiration)
+        };
+        let expiration = refill_expiration
+            .max(min_expiration)
+            .max(expiration.unwrap_or(0));
+        let data = ContractDataEntry {
+            contract: ScAddress::Contract(self.get_current_contract_id_internal()?),
+            key: self.from_host_val(k)?,
+            val: ScVal::Vec(Some(
+                vec![ScVal::U32(tokens), ScVal::U32(ledger_seq)].try_into()?,
+            )),
+            durability,
+            ext: ExtensionPoint::V0,
+        };
+        self.try_borrow_storage_mut()?
+            .put(
+                &ledger_key,
+                &Host::new_contract_data(self, data)?,
+                Some(expiration),
+                self.as_budget(),
+            )
+            .map_err(|e| self.decorate_contract_data_storage_error(e, k, &ledger_key))?;
+        Ok(consumed)
+    }
+
+    fn rate_limit_bucket_from_entry(
+        &self,
+        entry: &LedgerEntry,
+        k: Val,
+    ) -> Result<(u32, u32), HostError> {
+        if let LedgerEntryData::ContractData(ContractDataEntry {
+            val: ScVal::Vec(Some(bucket)),
+            ..
+        }) = &entry.data
+        {
+            if let [ScVal::U32(tokens), ScVal::U32(last_ledger)] = bucket.as_slice() {
+                return Ok((*tokens, *last_ledger));
+            }
+        }
+        Err(self.err(
+            ScErrorType::Storage,
+            ScErrorCode::InvalidInput,
+            "malformed rate limit entry",
+            &[k],
+        ))
+    }
 }
 
 #[cfg(any(test, feature = "testutils"))]
-use crate::storage::{AccessType, Footprint};
+use crate::{
+    storage::{AccessType, Footprint},
+    xdr::ScNonceKey,
+};
 
 #[cfg(any(test, feature = "testutils"))]
 impl Host {
@@ -541,4 +1039,97 @@ impl Host {
         }
         Ok(())
     }
+
+    fn nonce_ledger_key(&self, address: ScAddress, nonce: i64) -> Result<Rc<LedgerKey>, HostError> {
+        self.storage_key_for_address(
+            address,
+            ScVal::LedgerKeyNonce(ScNonceKey { nonce }),
+            ContractDataDurability::Temporary,
+        )
+    }
+
+    // Creates the ledger entry of the consumed authorization `nonce` of
+    // `address` that expires at `expiration_ledger`, as if `address` has
+    // authorized something with this nonce, and makes it accessible in
+    // enforcing storage mode.
+    // "testutils" are not covered by budget metering.
+    pub fn create_nonce_entry(
+        &self,
+        address: ScAddress,
+        nonce: i64,
+        expiration_ledger: u32,
+    ) -> Result<(), HostError> {
+        let key = self
+            .as_budget()
+            .with_free_budget(|| self.nonce_ledger_key(address.clone(), nonce))?;
+        let entry = LedgerEntry {
+            last_modified_ledger_seq: 0,
+            data: LedgerEntryData::ContractData(ContractDataEntry {
+                contract: address,
+                key: ScVal::LedgerKeyNonce(ScNonceKey { nonce }),
+                val: ScVal::Void,
+                durability: ContractDataDurability::Temporary,
+                ext: ExtensionPoint::V0,
+            }),
+            ext: LedgerEntryExt::V0,
+        };
+        self.setup_storage_entry(
+            key,
+            Some((Rc::new(entry), Some(expiration_ledger))),
+            AccessType::ReadWrite,
+        )
+    }
+
+    // Returns the expiration ledger of the consumed authorization `nonce` of
+    // `address`, or `None` if there is no entry for the nonce in storage.
+    // This doesn't access the entry, so it's neither recorded in nor checked
+    // against the footprint.
+    // "testutils" are not covered by budget metering.
+    pub fn get_nonce_expiration(
+        &self,
+        address: ScAddress,
+        nonce: i64,
+    ) -> Result<Option<u32>, HostError> {
+        self.as_budget().with_free_budget(|| {
+            let key = self.nonce_ledger_key(address, nonce)?;
+            self.with_mut_storage(|storage| {
+                let entry = storage.map.get::<Rc<LedgerKey>>(&key, self.as_budget())?;
+                Ok(match entry {
+                    Some(Some((_, expiration_ledger))) => *expiration_ledger,
+                    _ => None,
+                })
+            })
+        })
+    }
+
+    // Removes the entries of consumed authorization nonces that expire before
+    // `ledger` from storage, in the same way as the expired temporary entries
+    // are removed from the ledger. This allows the removed nonces to be used
+    // again. Returns the number of removed entries.
+    // "testutils" are not covered by budget metering.
+    pub fn expire_nonces_below(&self, ledger: u32) -> Result<u32, HostError> {
+        self.as_budget().with_free_budget(|| {
+            self.with_mut_storage(|storage| {
+                let mut expired = vec![];
+                for (key, entry) in storage.map.iter(self.as_budget())? {
+                    let LedgerKey::ContractData(data_key) = key.as_ref() else {
+                        continue;
+                    };
+                    if !matches!(data_key.key, ScVal::LedgerKeyNonce(_)) {
+                        continue;
+                    }
+                    let Some((_, Some(expiration_ledger))) = entry else {
+                        continue;
+                    };
+                    if *expiration_ledger < ledger {
+                        expired.push(Rc::clone(key));
+                    }
+                }
+                for key in expired.iter() {
+                    storage.map = storage.map.insert(Rc::clone(key), None, self.as_budget())?;
+                }
+                Ok(expired.len() as u32)
+            })
+        })
+    }
 }
@@ -0,0 +1,231 @@
+use crate::{
+    budget::AsBudget,
+    host::{
+        frame::Frame,
+        metered_clone::{charge_heap_alloc, charge_shallow_copy},
+    },
+    xdr::{
+        ContractCostType, ScErrorCode, ScErrorType, ScMap, ScMapEntry, ScNonceKey, ScVal, ScVec,
+    },
+    Host, HostError, StorageType, Val,
+};
+
+/// Key of the host entry of a contract instance holding its data locks, see
+/// [`Host::put_contract_data_locked_internal`]. The value is a map from
+/// `[storage type, key]` vectors to the ledger at which the corresponding
+/// entry gets unlocked.
+pub(crate) const DATA_LOCKS_KEY: ScVal = ScVal::LedgerKeyNonce(ScNonceKey { nonce: 0 });
+
+/// Maximum number of unexpired data locks a contract can hold, so that the
+/// locks kept in its instance stay small.
+pub(crate) const MAX_DATA_LOCKS_PER_CONTRACT: usize = 32;
+
+impl Host {
+    fn data_lock_key(&self, k: Val, t: &StorageType) -> Result<ScVal, HostError> {
+        let t: u32 = match t {
+            StorageType::Temporary => 0,
+            StorageType::Persistent => 1,
+            StorageType::Instance => 2,
+        };
+        Ok(ScVal::Vec(Some(ScVec(
+            vec![ScVal::U32(t), self.from_host_val(k)?].try_into()?,
+        ))))
+    }
+
+    // Returns the locks recorded in the host entry `locks`, ordered by key.
+    fn data_lock_entries<'a>(
+        &self,
+        locks: Option<&'a ScVal>,
+    ) -> Result<&'a [ScMapEntry], HostError> {
+        match locks {
+            None => Ok(&[]),
+            Some(ScVal::Map(Some(m))) => Ok(m.as_slice()),
+            Some(_) => Err(self.err(
+                ScErrorType::Storage,
+                ScErrorCode::InternalError,
+                "malformed data locks",
+                &[],
+            )),
+        }
+    }
+
+    // Takes the locks out of the host entry `locks`, to be modified.
+    fn take_data_lock_entries(
+        &self,
+        locks: &mut Option<ScVal>,
+    ) -> Result<Vec<ScMapEntry>, HostError> {
+        self.data_lock_entries(locks.as_ref())?;
+        Ok(match locks.take() {
+            Some(ScVal::Map(Some(m))) => m.0.into(),
+            _ => vec![],
+        })
+    }
+
+    // Finds the lock of `lock_key` in `entries`, with the same charges as a
+    // lookup in a `MeteredOrdMap`.
+    fn find_data_lock(
+        &self,
+        entries: &[ScMapEntry],
+        lock_key: &ScVal,
+    ) -> Result<Result<usize, usize>, HostError> {
+        if entries.is_empty() {
+            return Ok(Err(0));
+        }
+        let mag = 64 - (entries.len() as u64).leading_zeros();
+        self.as_budget()
+            .bulk_charge(ContractCostType::MapEntry, 1 + mag as u64, None)?;
+        Ok(entries.binary_search_by(|e| e.key.cmp(lock_key)))
+    }
+
+    // There are no locks outside of contract frames, where contract data
+    // can't be accessed in the first place.
+    fn in_contract_frame(&self) -> Result<bool, HostError> {
+        self.with_current_frame_opt(|frame| {
+            Ok(!matches!(frame, None | Some(Frame::HostFunction(_))))
+        })
+    }
+
+    /// Fails with `(Storage, InvalidAction)` if the entry `k` of the storage
+    /// type `t` of the current contract is locked at the current ledger.
+    // Notes on metering: covered by components. Nothing is charged unless the
+    // contract has locks.
+    pub(crate) fn check_contract_data_not_locked(
+        &self,
+        k: Val,
+        t: &StorageType,
+    ) -> Result<(), HostError> {
+        if !self.in_contract_frame()? {
+            return Ok(());
+        }
+        let unlock_ledger = self.visit_host_instance_entry(&DATA_LOCKS_KEY, |locks| {
+            if locks.is_none() {
+                return Ok(None);
+            }
+            let entries = self.data_lock_entries(locks)?;
+            let lock_key = self.data_lock_key(k, t)?;
+            Ok(match self.find_data_lock(entries, &lock_key)? {
+                Ok(i) => match entries[i].val {
+                    ScVal::U32(unlock_ledger) => Some(unlock_ledger),
+                    _ => None,
+                },
+                Err(_) => None,
+            })
+        })?;
+        let Some(unlock_ledger) = unlock_ledger else {
+            return Ok(());
+        };
+        let ledger_seq = self.with_ledger_info_provider(|li| Ok(li.sequence_number()))?;
+        if ledger_seq < unlock_ledger {
+            return Err(self.err(
+                ScErrorType::Storage,
+                ScErrorCode::InvalidAction,
+                "contract data entry is locked",
+                &[k, unlock_ledger.into()],
+            ));
+        }
+        Ok(())
+    }
+
+    /// Fails with `(Value, InvalidInput)` unless `unlock_ledger` is after the
+    /// current ledger and no later than the maximum expiration ledger, so
+    /// that entries can't be locked forever.
+    pub(crate) fn check_unlock_ledger(&self, unlock_ledger: u32) -> Result<(), HostError> {
+        let ledger_seq = self.with_ledger_info_provider(|li| Ok(li.sequence_number()))?;
+        if unlock_ledger <= ledger_seq || unlock_ledger > self.max_expiration_ledger()? {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "unlock ledger is out of range",
+                &[unlock_ledger.into()],
+            ));
+        }
+        Ok(())
+    }
+
+    /// Locks the entry `k` of the storage type `t` of the current contract,
+    /// which has just been written, so that it can't be modified or deleted
+    /// before `unlock_ledger`. The locks are kept in the contract instance,
+    /// outside of its instance storage. Expired locks are dropped first, and
+    /// this fails with `(Storage, ExceededLimit)` if the contract would hold
+    /// more than [`MAX_DATA_LOCKS_PER_CONTRACT`] locks.
+    // Notes on metering: covered by components. Dropping the expired locks
+    // visits every lock.
+    pub(crate) fn put_contract_data_locked_internal(
+        &self,
+        k: Val,
+        t: &StorageType,
+        unlock_ledger: u32,
+    ) -> Result<(), HostError> {
+        let ledger_seq = self.with_ledger_info_provider(|li| Ok(li.sequence_number()))?;
+        let lock_key = self.data_lock_key(k, t)?;
+        self.with_host_instance_entry_mut(&DATA_LOCKS_KEY, |locks| {
+            let mut entries = self.take_data_lock_entries(locks)?;
+            self.as_budget()
+                .bulk_charge(ContractCostType::MapEntry, entries.len() as u64, None)?;
+            entries.retain(|e| !matches!(e.val, ScVal::U32(l) if l <= ledger_seq));
+            match self.find_data_lock(&entries, &lock_key)? {
+                Ok(i) => entries[i].val = ScVal::U32(unlock_ledger),
+                Err(i) => {
+                    if entries.len() >= MAX_DATA_LOCKS_PER_CONTRACT {
+                        return Err(self.err(
+                            ScErrorType::Storage,
+                            ScErrorCode::ExceededLimit,
+                            "too many data locks",
+                            &[k],
+                        ));
+                    }
+                    // Inserting shifts the locks after `i`.
+                    charge_heap_alloc::<ScMapEntry>(1, self)?;
+                    charge_shallow_copy::<ScMapEntry>((entries.len() - i) as u64, self)?;
+                    entries.insert(
+                        i,
+                        ScMapEntry {
+                            key: lock_key,
+                            val: ScVal::U32(unlock_ledger),
+                        },
+                    );
+                }
+            }
+            *locks = Some(ScVal::Map(Some(ScMap(entries.try_into()?))));
+            Ok(())
+        })
+    }
+
+    /// Drops the lock of the entry `k` of the storage type `t` of the current
+    /// contract, if any, once the entry has been deleted. The entry can only
+    /// be deleted once its lock has expired.
+    // Notes on metering: covered by components. Nothing is charged unless the
+    // contract has locks.
+    pub(crate) fn remove_contract_data_lock(
+        &self,
+        k: Val,
+        t: &StorageType,
+    ) -> Result<(), HostError> {
+        if !self.in_contract_frame()? {
+            return Ok(());
+        }
+        let pos = self.visit_host_instance_entry(&DATA_LOCKS_KEY, |locks| {
+            if locks.is_none() {
+                return Ok(None);
+            }
+            let entries = self.data_lock_entries(locks)?;
+            let lock_key = self.data_lock_key(k, t)?;
+            Ok(self.find_data_lock(entries, &lock_key)?.ok())
+        })?;
+        let Some(i) = pos else {
+            return Ok(());
+        };
+        self.with_host_instance_entry_mut(&DATA_LOCKS_KEY, |locks| {
+            let mut entries = self.take_data_lock_entries(locks)?;
+            // Removing shifts the locks after `i`.
+            charge_shallow_copy::<ScMapEntry>((entries.len() - i - 1) as u64, self)?;
+            entries.remove(i);
+            *locks = if entries.is_empty() {
+                None
+            } else {
+                Some(ScVal::Map(Some(ScMap(entries.try_into()?))))
+            };
+            Ok(())
+        })
+    }
+}
@@ -27,6 +27,7 @@ use crate::{
     VecObject, Void, I256, U256,
 };
 use std::rc::Rc;
+#[cfg(feature = "vm")]
 use wasmi::Value;
 
 // Declared size (bytes) of a single element. This value determines the metering input for clone
@@ -95,6 +96,7 @@ impl_declared_size_type!(StringObject, 8);
 impl_declared_size_type!(Symbol, 8);
 impl_declared_size_type!(SymbolSmall, 8);
 impl_declared_size_type!(SymbolObject, 8);
+#[cfg(feature = "vm")]
 impl_declared_size_type!(Value, 16);
 // other common types
 impl_declared_size_type!(SymbolStr, SCSYMBOL_LIMIT);
@@ -287,6 +289,7 @@ mod test {
         expect!["8"].assert_eq(size_of::<Symbol>().to_string().as_str());
         expect!["8"].assert_eq(size_of::<SymbolSmall>().to_string().as_str());
         expect!["8"].assert_eq(size_of::<SymbolObject>().to_string().as_str());
+        #[cfg(feature = "vm")]
         expect!["16"].assert_eq(size_of::<Value>().to_string().as_str());
         // other common types
         expect!["32"].assert_eq(size_of::<SymbolStr>().to_string().as_str());
@@ -460,6 +463,7 @@ mod test {
         assert_mem_size_le_declared_size!(Symbol);
         assert_mem_size_le_declared_size!(SymbolSmall);
         assert_mem_size_le_declared_size!(SymbolObject);
+        #[cfg(feature = "vm")]
         assert_mem_size_le_declared_size!(Value);
         // other common types
         assert_mem_size_le_declared_size!(SymbolStr);
@@ -0,0 +1,148 @@
+//! Differential testing of the Wasm VM configuration: runs the same host
+//! function on two hosts that only differ in the [`VmConfig`] of their VMs
+//! and compares the results, the budget consumption and the events. This is
+//! meant for validating VM changes for consensus safety, e.g. in the CI of
+//! downstream validators, which can fail on an inconsistent
+//! [`DifferentialReport`] and log its [`mismatches`](DifferentialReport::mismatches).
+
+use crate::{
+    events::HostEvent,
+    xdr::{HostFunction, ScVal},
+    Error, Host, HostError,
+};
+
+/// How the VM consumes fuel for the instructions of bulk operations (such as
+/// `memory.copy`), see `wasmi::FuelConsumptionMode`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FuelConsumption {
+    /// Fuel is consumed before executing the operation, which is the mode
+    /// used in production.
+    #[default]
+    Eager,
+    /// Fuel is consumed after executing the operation, only for the part of
+    /// the operation that succeeded.
+    Lazy,
+}
+
+/// The interpreter configuration knobs of the Wasm VMs of a [`Host`], which
+/// are varied between the runs of [`run_differential`]. The default is the
+/// configuration used in production.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct VmConfig {
+    pub fuel_consumption: FuelConsumption,
+}
+
+/// The observable outcome of running a host function.
+#[derive(Clone, Debug)]
+pub struct InvocationOutcome {
+    pub result: Result<ScVal, Error>,
+    pub cpu_insns: u64,
+    pub mem_bytes: u64,
+    pub events: Vec<HostEvent>,
+}
+
+/// The outcomes of running a host function under the baseline and the
+/// candidate [`VmConfig`].
+#[derive(Clone, Debug)]
+pub struct DifferentialReport {
+    pub baseline: InvocationOutcome,
+    pub candidate: InvocationOutcome,
+}
+
+impl DifferentialReport {
+    pub fn results_match(&self) -> bool {
+        self.baseline.result == self.candidate.result
+    }
+
+    pub fn budgets_match(&self) -> bool {
+        self.baseline.cpu_insns == self.candidate.cpu_insns
+            && self.baseline.mem_bytes == self.candidate.mem_bytes
+    }
+
+    /// Compares the events and whether their calls have failed.
+    pub fn events_match(&self) -> bool {
+        self.baseline.events.len() == self.candidate.events.len()
+            && self
+                .baseline
+                .events
+                .iter()
+                .zip(self.candidate.events.iter())
+                .all(|(a, b)| a.event == b.event && a.failed_call == b.failed_call)
+    }
+
+    /// Returns whether the candidate configuration is indistinguishable from
+    /// the baseline one for this invocation.
+    pub fn is_consistent(&self) -> bool {
+        self.results_match() && self.budgets_match() && self.events_match()
+    }
+
+    /// Describes every difference between the outcomes, one line each.
+    pub fn mismatches(&self) -> Vec<String> {
+        let (a, b) = (&self.baseline, &self.candidate);
+        let mut mismatches = vec![];
+        if !self.results_match() {
+            mismatches.push(format!("result: {:?} != {:?}", a.result, b.result));
+        }
+        if a.cpu_insns != b.cpu_insns {
+            mismatches.push(format!("cpu_insns: {} != {}", a.cpu_insns, b.cpu_insns));
+        }
+        if a.mem_bytes != b.mem_bytes {
+            mismatches.push(format!("mem_bytes: {} != {}", a.mem_bytes, b.mem_bytes));
+        }
+        if !self.events_match() {
+            mismatches.push(format!(
+                "events: {} events != {} events",
+                a.events.len(),
+                b.events.len()
+            ));
+        }
+        mismatches
+    }
+}
+
+impl Host {
+    /// Sets the configuration of the Wasm VMs created from now on.
+    pub fn set_vm_config(&self, config: VmConfig) -> Result<(), HostError> {
+        *self.try_borrow_vm_config_mut()? = config;
+        Ok(())
+    }
+
+    pub(crate) fn get_vm_config(&self) -> Result<VmConfig, HostError> {
+        Ok(*self.try_borrow_vm_config()?)
+    }
+
+    fn run_with_vm_config(
+        &self,
+        config: VmConfig,
+        host_fn: HostFunction,
+    ) -> Result<InvocationOutcome, HostError> {
+        self.set_vm_config(config)?;
+        let result = self.invoke_function(host_fn).map_err(|e| e.error);
+        let budget = self.budget_cloned();
+        Ok(InvocationOutcome {
+            result,
+            cpu_insns: budget.get_cpu_insns_consumed()?,
+            mem_bytes: budget.get_mem_bytes_consumed()?,
+            events: self.get_events()?.0,
+        })
+    }
+}
+
+/// Runs `host_fn` on two hosts created by `make_host`, the first one using
+/// the `baseline` configuration and the second one using `candidate`.
+/// `make_host` has to set up identical hosts, e.g. with the same storage and
+/// budget, for the report to only reflect the VM differences.
+pub fn run_differential<F>(
+    make_host: F,
+    host_fn: HostFunction,
+    baseline: VmConfig,
+    candidate: VmConfig,
+) -> Result<DifferentialReport, HostError>
+where
+    F: Fn() -> Result<Host, HostError>,
+{
+    Ok(DifferentialReport {
+        baseline: make_host()?.run_with_vm_config(baseline, host_fn.clone())?,
+        candidate: make_host()?.run_with_vm_config(candidate, host_fn)?,
+    })
+}
@@ -1,8 +1,11 @@
 use crate::{
     budget::AsBudget,
-    events::Events,
-    xdr::{self, Hash, LedgerKey, ScAddress, ScError, ScErrorCode, ScErrorType},
-    ConversionError, EnvBase, Error, Host, TryFromVal, U32Val, Val,
+    events::{ContractTypeSpecs, Events},
+    xdr::{
+        self, ContractDataDurability, Hash, LedgerKey, LedgerKeyContractData, ScAddress, ScError,
+        ScErrorCode, ScErrorType,
+    },
+    ConversionError, EnvBase, Error, Host, Symbol, TryFromVal, U32Val, Val,
 };
 use backtrace::{Backtrace, BacktraceFrame};
 use core::fmt::Debug;
@@ -17,7 +20,47 @@ use super::metered_clone::MeteredClone;
 #[derive(Clone)]
 pub(crate) struct DebugInfo {
     pub(crate) events: Events,
+    pub(crate) type_specs: ContractTypeSpecs,
     pub(crate) backtrace: Backtrace,
+    pub(crate) causes: Vec<ErrorCause>,
+}
+
+/// A single link in the chain of causes of a [HostError]. The first link is
+/// recorded where the error is raised and another one is appended every time
+/// the error propagates out of a contract or host function frame, so the
+/// chain reads from the innermost cause outwards.
+///
+/// Causes are only recorded when running in [DiagnosticLevel::Debug](crate::DiagnosticLevel::Debug).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorCause {
+    pub error: Error,
+    pub msg: String,
+    /// Contract that was executing, if any.
+    pub contract_id: Option<Hash>,
+    /// Function of the frame that was executing (a contract function or a
+    /// host function type), if any.
+    pub fn_name: Option<String>,
+    /// Arguments of the error, rendered for diagnostics.
+    pub args: Vec<String>,
+}
+
+impl std::fmt::Display for ErrorCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.error)?;
+        match (&self.contract_id, &self.fn_name) {
+            (Some(id), Some(fn_name)) => write!(f, " in {:?}::{}", id, fn_name)?,
+            (Some(id), None) => write!(f, " in {:?}", id)?,
+            (None, Some(fn_name)) => write!(f, " in {}", fn_name)?,
+            (None, None) => (),
+        }
+        if !self.msg.is_empty() {
+            write!(f, ": {}", self.msg)?;
+        }
+        if !self.args.is_empty() {
+            write!(f, " [{}]", self.args.join(", "))?;
+        }
+        Ok(())
+    }
 }
 
 #[derive(Clone)]
@@ -67,6 +110,13 @@ impl Debug for HostError {
 
         writeln!(f, "HostError: {:?}", self.error)?;
         if let Some(info) = &self.info {
+            if !info.causes.is_empty() {
+                writeln!(f)?;
+                writeln!(f, "Error chain (innermost first):")?;
+                for (i, cause) in info.causes.iter().enumerate() {
+                    writeln!(f, "   {}: {}", i, cause)?;
+                }
+            }
             let mut bt = info.backtrace.clone();
             bt.resolve();
             let frames: Vec<BacktraceFrame> = bt
@@ -86,7 +136,12 @@ impl Debug for HostError {
                     writeln!(f, "Event log (newest first):")?;
                     wrote_heading = true;
                 }
-                writeln!(f, "   {}: {}", i, e)?;
+                writeln!(
+                    f,
+                    "   {}: {}",
+                    i,
+                    e.display_with_registered_specs(&info.type_specs)
+                )?;
             }
             if info.events.0.len() > MAX_EVENTS {
                 writeln!(f, "   {}: ... elided ...", MAX_EVENTS)?;
@@ -115,6 +170,15 @@ impl HostError {
         }
     }
 
+    /// Returns the chain of causes of this error, innermost first. Empty
+    /// unless the error was raised with diagnostics enabled.
+    pub fn causes(&self) -> &[ErrorCause] {
+        match &self.info {
+            Some(info) => info.causes.as_slice(),
+            None => &[],
+        }
+    }
+
     /// Identifies whether the error can be meaningfully recovered from.
     ///
     /// We consider errors that occur due to broken execution preconditions (
@@ -242,7 +306,20 @@ impl Host {
                     return e;
                 }
             }
-            let info = self.maybe_get_debug_info();
+            let mut info = self.maybe_get_debug_info();
+            if let Some(info) = info.as_mut() {
+                let (contract_id, fn_name) = self.current_frame_identity();
+                info.causes.push(ErrorCause {
+                    error,
+                    msg: msg.to_string(),
+                    contract_id,
+                    fn_name,
+                    args: args
+                        .iter()
+                        .map(|a| self.render_val_for_diagnostics(*a))
+                        .collect(),
+                });
+            }
             return HostError { error, info };
         }
         error.into()
@@ -258,13 +335,60 @@ impl Host {
                     Ok(events) => events,
                     Err(e) => return None,
                 };
+                // Cloning the specs only clones the `Rc`s of the entries.
+                let type_specs = match self.0.contract_type_specs.try_borrow() {
+                    Ok(type_specs) => type_specs.clone(),
+                    Err(_) => Default::default(),
+                };
                 let backtrace = Backtrace::new_unresolved();
-                return Some(Box::new(DebugInfo { backtrace, events }));
+                return Some(Box::new(DebugInfo {
+                    backtrace,
+                    events,
+                    type_specs,
+                    causes: Vec::new(),
+                }));
             }
         }
         None
     }
 
+    /// Appends a cause to the chain of `err` when it propagates out of a frame
+    /// with the provided identity. Errors that were raised without debug info
+    /// get it attached here, so that the chain starts at the innermost frame
+    /// that observed them.
+    pub(crate) fn chain_error_cause(
+        &self,
+        mut err: HostError,
+        contract_id: Option<Hash>,
+        fn_name: Option<String>,
+    ) -> HostError {
+        if err.info.is_none() {
+            err.info = self.maybe_get_debug_info();
+        }
+        if let Some(info) = err.info.as_mut() {
+            info.causes.push(ErrorCause {
+                error: err.error,
+                msg: "propagated out of frame".to_string(),
+                contract_id,
+                fn_name,
+                args: vec![],
+            });
+        }
+        err
+    }
+
+    /// Same as [Host::error] for the [Error] of `err`, but keeps the chain of
+    /// causes of `err` in front of the newly recorded cause.
+    pub(crate) fn escalate_error(&self, err: HostError, msg: &str) -> HostError {
+        let mut escalation = self.error(err.error, msg, &[]);
+        if let (Some(info), Some(prev)) = (escalation.info.as_mut(), err.info) {
+            let mut causes = prev.causes;
+            causes.append(&mut info.causes);
+            info.causes = causes;
+        }
+        escalation
+    }
+
     // Some common error patterns here.
 
     pub(crate) fn err_arith_overflow(&self) -> HostError {
@@ -276,6 +400,20 @@ impl Host {
         )
     }
 
+    // Out of range conversions between i128 and the 256-bit integers are
+    // returned to the guest as error values rather than failing the call.
+    pub(crate) fn err_i128_range_val(&self, x: Val) -> Val {
+        self.err(
+            ScErrorType::Value,
+            ScErrorCode::ArithDomain,
+            "value out of range of the conversion",
+            &[x],
+        )
+        .error
+        .to_val()
+    }
+
+    #[cfg(feature = "vm")]
     pub(crate) fn err_oob_linear_memory(&self) -> HostError {
         self.err(
             ScErrorType::WasmVm,
@@ -379,20 +517,52 @@ impl Host {
         err
     }
 
+    // Renders the contract and durability of a contract data key as debug
+    // args of a footprint error. These are only produced on the unrecoverable
+    // error path, so they are not metered.
+    fn contract_data_key_debug_args(&self, lk: &LedgerKey) -> Vec<Val> {
+        let LedgerKey::ContractData(LedgerKeyContractData {
+            contract,
+            durability,
+            ..
+        }) = lk
+        else {
+            return vec![];
+        };
+        let durability = match durability {
+            ContractDataDurability::Persistent => "persistent",
+            ContractDataDurability::Temporary => "temporary",
+        };
+        [
+            self.add_host_object(contract.clone())
+                .ok()
+                .map(|a| a.to_val()),
+            Symbol::try_from_val(self, &durability)
+                .ok()
+                .map(|s| s.to_val()),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
     pub(crate) fn decorate_contract_data_storage_error(
         &self,
         err: HostError,
         key: Val,
+        lk: &LedgerKey,
     ) -> HostError {
         if !err.error.is_type(ScErrorType::Storage) {
             return err;
         }
         if err.error.is_code(ScErrorCode::ExceededLimit) {
+            let mut args = vec![key];
+            args.extend(self.contract_data_key_debug_args(lk));
             return self.err(
                 ScErrorType::Storage,
                 ScErrorCode::ExceededLimit,
                 "trying to access contract storage key outside of the footprint",
-                &[key],
+                &args,
             );
         }
         if err.error.is_code(ScErrorCode::MissingValue) {
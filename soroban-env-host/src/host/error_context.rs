@@ -0,0 +1,162 @@
+//! Packaging of recoverable errors together with the contract function they
+//! originate from, so that contracts can propagate the errors of their callees
+//! to their own callers in a standard shape. The packaged error is a map with
+//! the following fields:
+//!
+//!   - `contract`: the address of the originating contract, or `Void` if the
+//!     error didn't originate from a contract
+//!   - `error`: the [`Error`] itself
+//!   - `function`: the symbol of the originating function, or `Void`
+
+use crate::{
+    host::{
+        frame::{Context, Frame},
+        metered_clone::MeteredClone,
+    },
+    xdr::{Hash, ScAddress, ScErrorCode, ScErrorType, ScSymbol, ScVal},
+    AddressObject, EnvBase, Error, Host, HostError, MapObject, Symbol, TryFromVal, Val,
+};
+
+const CONTRACT_FIELD: &str = "contract";
+const ERROR_FIELD: &str = "error";
+const FUNCTION_FIELD: &str = "function";
+
+/// An error together with where it has originated, as packaged by the
+/// `error_to_val_with_context` host function.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ErrorContext {
+    pub error: Error,
+    pub contract: Option<Hash>,
+    pub function: Option<ScSymbol>,
+}
+
+impl Host {
+    // Notes on metering: covered by the components.
+    pub(crate) fn error_to_val_with_context_internal(
+        &self,
+        error: Error,
+    ) -> Result<MapObject, HostError> {
+        if !HostError::from(error).is_recoverable() {
+            return Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidInput,
+                "only recoverable errors can be propagated",
+                &[error.to_val()],
+            ));
+        }
+        // The error of a failed `try_call` of the current frame originates from
+        // the callee.
+        let failed_call = match self.try_borrow_context()?.last() {
+            Some(Context {
+                failed_call: Some((e, id, func)),
+                ..
+            }) if *e == error => Some((id.metered_clone(self)?, *func)),
+            _ => None,
+        };
+        if let Some((id, func)) = failed_call {
+            return self.error_context_map(error, Some(id), func.to_val());
+        }
+        let (contract, function) = self.with_current_frame_opt(|frame| {
+            Ok(match frame {
+                Some(Frame::ContractVM { vm, fn_name, .. }) => {
+                    (Some(vm.contract_id.metered_clone(self)?), Some(*fn_name))
+                }
+                Some(Frame::Token(id, fn_name, ..)) => {
+                    (Some(id.metered_clone(self)?), Some(*fn_name))
+                }
+                #[cfg(any(test, feature = "testutils"))]
+                Some(Frame::TestContract(tc)) => (Some(tc.id.metered_clone(self)?), Some(tc.func)),
+                Some(Frame::HostFunction(_)) | None => (None, None),
+            })
+        })?;
+        let function = function.map_or(Val::VOID.to_val(), |f| f.to_val());
+        self.error_context_map(error, contract, function)
+    }
+
+    // Records the outcome of a `try_call` made by the current frame, see
+    // `Context::failed_call`.
+    pub(crate) fn set_failed_call(
+        &self,
+        failed_call: Option<(Error, Hash, Symbol)>,
+    ) -> Result<(), HostError> {
+        if let Some(ctx) = self.try_borrow_context_mut()?.last_mut() {
+            ctx.failed_call = failed_call;
+        }
+        Ok(())
+    }
+
+    fn error_context_map(
+        &self,
+        error: Error,
+        contract: Option<Hash>,
+        function: Val,
+    ) -> Result<MapObject, HostError> {
+        let contract = match contract {
+            Some(id) => self.add_host_object(ScAddress::Contract(id))?.to_val(),
+            None => Val::VOID.to_val(),
+        };
+        self.map_new_from_slices(
+            &[CONTRACT_FIELD, ERROR_FIELD, FUNCTION_FIELD],
+            &[contract, error.to_val(), function],
+        )
+    }
+
+    /// Packages `ctx` into a value of the same shape as the one produced by
+    /// the `error_to_val_with_context` host function.
+    pub fn error_context_to_val(&self, ctx: &ErrorContext) -> Result<Val, HostError> {
+        let function = match &ctx.function {
+            Some(f) => Symbol::try_from_val(self, &f.as_slice())?.to_val(),
+            None => Val::VOID.to_val(),
+        };
+        let map = self.error_context_map(ctx.error, ctx.contract.clone(), function)?;
+        Ok(map.to_val())
+    }
+
+    /// Unpacks an error packaged by the `error_to_val_with_context` host
+    /// function, e.g. from the return value of a contract.
+    pub fn error_context_from_val(&self, val: Val) -> Result<ErrorContext, HostError> {
+        let map = MapObject::try_from(val).map_err(|_| {
+            self.err(
+                ScErrorType::Value,
+                ScErrorCode::UnexpectedType,
+                "error context is not a map",
+                &[val],
+            )
+        })?;
+        let mut fields = [Val::VOID.to_val(); 3];
+        self.map_unpack_to_slice(
+            map,
+            &[CONTRACT_FIELD, ERROR_FIELD, FUNCTION_FIELD],
+            &mut fields,
+        )?;
+        let [contract, error, function] = fields;
+        let malformed = |field: Val| {
+            self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "malformed error context",
+                &[field],
+            )
+        };
+        let error = Error::try_from(error).map_err(|_| malformed(error))?;
+        let contract = if contract.is_void() {
+            None
+        } else {
+            let addr = AddressObject::try_from(contract).map_err(|_| malformed(contract))?;
+            match self.scaddress_from_address(addr)? {
+                ScAddress::Contract(id) => Some(id),
+                ScAddress::Account(_) => return Err(malformed(contract)),
+            }
+        };
+        let function = match self.from_host_val(function)? {
+            ScVal::Void => None,
+            ScVal::Symbol(s) => Some(s),
+            _ => return Err(malformed(function)),
+        };
+        Ok(ErrorContext {
+            error,
+            contract,
+            function,
+        })
+    }
+}
@@ -6,9 +6,14 @@ use soroban_env_common::{
 use crate::{
     auth::AuthorizationManagerSnapshot,
     budget::AsBudget,
+    events::ContractEventCounts,
+    native_contract::BuiltinWasmContract,
     storage::{InstanceStorageMap, StorageMap},
-    xdr::{ContractExecutable, Hash, HostFunction, HostFunctionType, ScVal},
-    Error, Host, HostError, Object, Symbol, SymbolStr, TryFromVal, TryIntoVal, Val,
+    xdr::{
+        ContractExecutable, Hash, HostFunction, HostFunctionType, ScMap, ScMapEntry, ScVal,
+        SorobanAuthorizationEntry,
+    },
+    Compare, Error, Host, HostError, Object, Symbol, SymbolStr, TryFromVal, TryIntoVal, Val,
     DEFAULT_HOST_DEPTH_LIMIT,
 };
 
@@ -16,15 +21,36 @@ use crate::{
 use crate::host::testutils;
 #[cfg(any(test, feature = "testutils"))]
 use core::cell::RefCell;
-use std::rc::Rc;
+use core::cmp::Ordering;
+use std::{ops::Range, rc::Rc};
 
-use crate::Vm;
+use crate::{vm::ParsedModule, Vm};
 
 use super::{
     metered_clone::{MeteredClone, MeteredContainer, MeteredIterator},
     prng::Prng,
+    FeeRefund,
 };
 
+/// An operation of a batch run by [`Host::invoke_functions`].
+#[derive(Clone, Debug)]
+pub struct HostFunctionOp {
+    pub host_function: HostFunction,
+    /// The authorization entries enforced for this operation only, or `None`
+    /// to keep using the current authorization mode of the [`Host`], e.g. to
+    /// record the authorizations.
+    pub auth_entries: Option<Vec<SorobanAuthorizationEntry>>,
+}
+
+/// The outcome of an operation of a batch run by [`Host::invoke_functions`].
+#[derive(Debug)]
+pub struct HostFunctionOpResult {
+    pub result: Result<ScVal, HostError>,
+    /// The indices of the events emitted by the operation in
+    /// [`Host::get_events`].
+    pub events: Range<usize>,
+}
+
 /// Determines the re-entry mode for calling a contract.
 pub(crate) enum ContractReentryMode {
     /// Re-entry is completely prohibited.
@@ -41,6 +67,11 @@ pub(crate) enum ContractReentryMode {
 /// contracts.
 const RESERVED_CONTRACT_FN_PREFIX: &str = "__";
 
+/// The reserved function a contract can export to be notified about the
+/// recoverable failures of its `try_call` sub-calls, see
+/// [`Host::call_on_error_handler`].
+pub(crate) const ON_ERROR_FN_NAME: &str = "__on_error";
+
 /// Saves host state (storage and objects) for rolling back a (sub-)transaction
 /// on error. A helper type used by [`FrameGuard`].
 // Notes on metering: `RollbackPoint` are metered under Frame operations
@@ -49,6 +80,10 @@ pub(super) struct RollbackPoint {
     storage: StorageMap,
     events: usize,
     auth: AuthorizationManagerSnapshot,
+    fee_refund: Option<FeeRefund>,
+    memo: usize,
+    // Only snapshotted when the contract event limits are enabled.
+    contract_event_counts: Option<ContractEventCounts>,
 }
 
 #[cfg(any(test, feature = "testutils"))]
@@ -56,6 +91,35 @@ pub trait ContractFunctionSet {
     fn call(&self, func: &Symbol, host: &Host, args: &[Val]) -> Option<Val>;
 }
 
+/// Names of the host functions that can be interposed for test contracts via
+/// [`Host::register_test_contract_host_fn_interposer`].
+#[cfg(any(test, feature = "testutils"))]
+pub const INTERPOSABLE_HOST_FNS: &[&str] = &[
+    "get_ledger_version",
+    "get_ledger_sequence",
+    "get_ledger_timestamp",
+    "get_ledger_network_id",
+    "get_max_expiration_ledger",
+];
+
+/// Replacement for a host function that is called when a specific test
+/// contract invokes that host function. Receives the arguments of the host
+/// function and returns its result.
+#[cfg(any(test, feature = "testutils"))]
+pub trait HostFnInterposer {
+    fn call(&self, host: &Host, args: &[Val]) -> Result<Val, HostError>;
+}
+
+#[cfg(any(test, feature = "testutils"))]
+impl<F> HostFnInterposer for F
+where
+    F: Fn(&Host, &[Val]) -> Result<Val, HostError>,
+{
+    fn call(&self, host: &Host, args: &[Val]) -> Result<Val, HostError> {
+        self(host, args)
+    }
+}
+
 #[cfg(any(test, feature = "testutils"))]
 #[derive(Debug, Clone)]
 pub(crate) struct TestContractFrame {
@@ -86,6 +150,9 @@ pub(crate) struct Context {
     pub(crate) frame: Frame,
     prng: Option<Prng>,
     pub(crate) storage: Option<InstanceStorageMap>,
+    /// The error of the last `try_call` made by the frame, along with the
+    /// callee and the function, if that call has failed.
+    pub(crate) failed_call: Option<(Error, Hash, Symbol)>,
 }
 
 /// Holds contextual information about a single invocation, either
@@ -124,18 +191,40 @@ impl Host {
         let auth_manager = self.try_borrow_authorization_manager()?;
         let auth_snapshot = auth_manager.snapshot(self)?;
         auth_manager.push_frame(self, &frame)?;
+        self.attribute_storage_access()?;
 
         let ctx = Context {
             frame,
             prng: None,
             storage: None,
+            failed_call: None,
         };
         Vec::<Context>::charge_bulk_init_cpy(1, self.as_budget())?;
-        self.try_borrow_context_mut()?.push(ctx);
+        let depth = {
+            let mut context = self.try_borrow_context_mut()?;
+            context.push(ctx);
+            context.len()
+        };
+        if depth == 1 {
+            self.snapshot_ledger_info()?;
+        }
+        self.record_frame_depth(depth)?;
+        // Notes on metering: the event counts are not charged for, they are
+        // only kept when the embedder enables the limits and hold one entry
+        // per emitting contract.
+        let contract_event_counts =
+            if self.contract_event_limits()?.max_events_per_contract == u32::MAX {
+                None
+            } else {
+                Some(self.try_borrow_contract_event_counts()?.clone())
+            };
         Ok(RollbackPoint {
             storage: self.try_borrow_storage()?.map.metered_clone(self)?,
             events: self.try_borrow_events()?.vec.len(),
             auth: auth_snapshot,
+            fee_refund: self.try_borrow_fee_refund()?.clone(),
+            memo: self.try_borrow_memo()?.undo_len(),
+            contract_event_counts,
         })
     }
 
@@ -150,6 +239,7 @@ impl Host {
         if orp.is_none() {
             self.persist_instance_storage()?;
         }
+        self.attribute_storage_access()?;
         self.try_borrow_context_mut()?
             .pop()
             .expect("unmatched host frame push/pop");
@@ -167,6 +257,18 @@ impl Host {
             self.try_borrow_events_mut()?.rollback(rp.events)?;
             self.try_borrow_authorization_manager()?
                 .rollback(self, rp.auth)?;
+            *self.try_borrow_fee_refund_mut()? = rp.fee_refund;
+            self.try_borrow_memo_mut()?.rollback(rp.memo);
+            if let Some(counts) = rp.contract_event_counts {
+                *self.try_borrow_contract_event_counts_mut()? = counts;
+            }
+        }
+        // The memo, the event counts and the ledger information snapshot only
+        // live until the end of the invocation.
+        if self.try_borrow_context()?.is_empty() {
+            self.try_borrow_memo_mut()?.clear();
+            *self.try_borrow_contract_event_counts_mut()? = ContractEventCounts::new();
+            self.drop_ledger_info_snapshot()?;
         }
         // Empty call stack in tests means that some contract function call
         // has been finished and hence the authorization manager can be reset.
@@ -348,6 +450,10 @@ impl Host {
         }
         let rp = self.push_frame(frame)?;
         let res = f();
+        #[cfg(any(test, feature = "testutils"))]
+        if let Ok(v) = res {
+            self.record_object_leak_roots(&[v])?;
+        }
         let res = if let Ok(v) = res {
             if let Ok(err) = Error::try_from(v) {
                 Err(self.error(err, "escalating Ok(Error) frame-exit to Err(Error)", &[]))
@@ -357,6 +463,13 @@ impl Host {
         } else {
             res
         };
+        let res = match res {
+            Err(e) if self.is_debug()? => {
+                let (contract_id, fn_name) = self.current_frame_identity();
+                Err(self.chain_error_cause(e, contract_id, fn_name))
+            }
+            res => res,
+        };
         if res.is_err() {
             // Pop and rollback on error.
             self.pop_frame(Some(rp))?;
@@ -370,6 +483,38 @@ impl Host {
         res
     }
 
+    /// Returns the contract ID and function name of the frame at the top of the
+    /// context stack for error reporting, or `None`s if there is no frame.
+    ///
+    /// This is only called on the error path and never fails: errors raised
+    /// while rendering the function name are suppressed by holding the events
+    /// buffer, which keeps them from recording debug info (and hence causes)
+    /// of their own.
+    pub(crate) fn current_frame_identity(&self) -> (Option<Hash>, Option<String>) {
+        let Ok(_events_guard) = self.0.events.try_borrow_mut() else {
+            return (None, None);
+        };
+        let Ok(context) = self.0.context.try_borrow() else {
+            return (None, None);
+        };
+        let Some(ctx) = context.last() else {
+            return (None, None);
+        };
+        // No need for metered clones here as we are on the error path.
+        let (id, func) = match &ctx.frame {
+            Frame::ContractVM { vm, fn_name, .. } => (vm.contract_id.clone(), *fn_name),
+            Frame::HostFunction(hf) => return (None, Some(format!("{:?}", hf))),
+            Frame::Token(id, fn_name, ..) => (id.clone(), *fn_name),
+            #[cfg(any(test, feature = "testutils"))]
+            Frame::TestContract(tc) => (tc.id.clone(), tc.func),
+        };
+        let fn_name = self
+            .as_budget()
+            .with_free_budget(|| Ok(SymbolStr::try_from_val(self, &func)?.to_string()))
+            .ok();
+        (Some(id), fn_name)
+    }
+
     /// Returns [`Hash`] contract ID from the VM frame at the top of the context
     /// stack, or a [`HostError`] if the context stack is empty or has a non-VM
     /// frame at its top.
@@ -399,6 +544,44 @@ impl Host {
         }
     }
 
+    /// Calls the [`HostFnInterposer`] registered for the host function
+    /// `fn_name` and the test contract that is currently running, if any.
+    /// Returns `None` if the host function has not been interposed and has to
+    /// be executed normally.
+    #[cfg(any(test, feature = "testutils"))]
+    pub(crate) fn maybe_call_host_fn_interposer<T: TryFrom<Val>>(
+        &self,
+        fn_name: &'static str,
+        args: &[Val],
+    ) -> Result<Option<T>, HostError> {
+        if self.try_borrow_host_fn_interposers()?.is_empty() {
+            return Ok(None);
+        }
+        let contract_id = self.with_current_frame_opt(|frame| match frame {
+            Some(Frame::TestContract(tc)) => Ok(Some(tc.id.clone())),
+            _ => Ok(None),
+        })?;
+        let Some(contract_id) = contract_id else {
+            return Ok(None);
+        };
+        let interposer = self
+            .try_borrow_host_fn_interposers()?
+            .get(&(contract_id, fn_name))
+            .cloned();
+        let Some(interposer) = interposer else {
+            return Ok(None);
+        };
+        let res = interposer.call(self, args)?;
+        T::try_from(res).map(Some).map_err(|_| {
+            self.err(
+                ScErrorType::Value,
+                ScErrorCode::UnexpectedType,
+                "unexpected result type of interposed host function",
+                &[res],
+            )
+        })
+    }
+
     /// Pushes a test contract [`Frame`], runs a closure, and then pops the
     /// frame, rolling back if the closure returned an error. Returns the result
     /// that the closure returned (or any error caused during the frame
@@ -419,6 +602,25 @@ impl Host {
         )
     }
 
+    /// Pushes a [`Frame::Token`] for the native contract `id` as if its
+    /// function `func` was invoked with `args`, runs `f` in it and pops the
+    /// frame. Used for unit-testing native contract functions.
+    #[cfg(any(test, feature = "testutils"))]
+    pub(crate) fn with_native_contract_frame<F>(
+        &self,
+        id: Hash,
+        func: Symbol,
+        args: Vec<Val>,
+        f: F,
+    ) -> Result<Val, HostError>
+    where
+        F: FnOnce() -> Result<Val, HostError>,
+    {
+        let instance_key = self.contract_instance_ledger_key(&id)?;
+        let instance = self.retrieve_contract_instance_from_storage(&instance_key)?;
+        self.with_frame(Frame::Token(id, func, args, instance), f)
+    }
+
     #[cfg(any(test, feature = "testutils"))]
     fn create_test_contract_frame(
         &self,
@@ -446,9 +648,44 @@ impl Host {
         Vec::<Val>::charge_bulk_init_cpy(args.len() as u64, self.as_budget())?;
         let args_vec = args.to_vec();
         match &instance.executable {
+            ContractExecutable::Wasm(wasm_hash)
+                if self.builtin_wasm_contract(wasm_hash)?
+                    == Some(BuiltinWasmContract::SmartWallet) =>
+            {
+                self.with_frame(
+                    Frame::Token(id.metered_clone(self)?, *func, args_vec, instance),
+                    || {
+                        use crate::native_contract::{smart_wallet::SmartWallet, NativeContract};
+                        SmartWallet.call(func, self, args)
+                    },
+                )
+            }
+            ContractExecutable::Wasm(wasm_hash)
+                if self.builtin_wasm_contract(wasm_hash)? == Some(BuiltinWasmContract::Nft) =>
+            {
+                self.with_frame(
+                    Frame::Token(id.metered_clone(self)?, *func, args_vec, instance),
+                    || {
+                        use crate::native_contract::{nft::Nft, NativeContract};
+                        Nft.call(func, self, args)
+                    },
+                )
+            }
             ContractExecutable::Wasm(wasm_hash) => {
-                let code_entry = self.retrieve_wasm_from_storage(&wasm_hash)?;
-                let vm = Vm::new(self, id.metered_clone(self)?, code_entry.as_slice())?;
+                let parsed = if let Some(code) = self.retrieve_wasm_from_code_store(&wasm_hash)? {
+                    ParsedModule::new_or_cached(self, wasm_hash, code.as_ref())?
+                } else {
+                    let code_entry = self.retrieve_wasm_from_storage(&wasm_hash)?;
+                    ParsedModule::new_or_cached(self, wasm_hash, code_entry.as_slice())?
+                };
+                // Calls of missing functions fail before paying for the
+                // instantiation.
+                if self.get_ledger_protocol_version()?
+                    >= super::EARLY_MISSING_EXPORT_CHECK_MIN_PROTOCOL
+                {
+                    parsed.check_function_export(self, func)?;
+                }
+                let vm = Vm::instantiate(self, id.metered_clone(self)?, parsed)?;
                 let relative_objects = Vec::new();
                 self.with_frame(
                     Frame::ContractVM {
@@ -464,13 +701,81 @@ impl Host {
             ContractExecutable::Token => self.with_frame(
                 Frame::Token(id.metered_clone(self)?, *func, args_vec, instance),
                 || {
-                    use crate::native_contract::{NativeContract, Token};
+                    use crate::native_contract::{token, NativeContract, Token};
+                    token::migrate_storage(self, func)?;
                     Token.call(func, self, args)
                 },
             ),
         }
     }
 
+    /// Invokes the [`ON_ERROR_FN_NAME`] handler of the Wasm contract at the
+    /// top of the context stack after its `try_call` of `func` on `callee`
+    /// failed with the recoverable `error`. The handler is called with the
+    /// error value returned by `try_call`, `callee`, `func` and the context of
+    /// the error, as packaged by `error_to_val_with_context`, and may only
+    /// re-enter its own contract. Nothing happens when the contract doesn't
+    /// export a handler, when the current function is the handler itself (so
+    /// that failures within handlers don't recurse) or before
+    /// [`ON_ERROR_HANDLER_MIN_PROTOCOL`](super::ON_ERROR_HANDLER_MIN_PROTOCOL).
+    ///
+    /// The handler can't change the outcome of `try_call`: its recoverable
+    /// failures roll back its changes and are only reported in diagnostics,
+    /// while non-recoverable failures (e.g. exceeding the budget) are
+    /// propagated.
+    // Notes on metering: the export lookup is a free check on the already
+    // parsed module, the handler call is covered by its components.
+    pub(crate) fn call_on_error_handler(
+        &self,
+        error: Error,
+        callee: AddressObject,
+        func: Symbol,
+    ) -> Result<(), HostError> {
+        if self.get_ledger_protocol_version()? < super::ON_ERROR_HANDLER_MIN_PROTOCOL {
+            return Ok(());
+        }
+        let handler_fn = Symbol::try_from_val(self, &ON_ERROR_FN_NAME)?;
+        let caller_id = self.with_current_frame(|frame| {
+            let Frame::ContractVM { vm, fn_name, .. } = frame else {
+                return Ok(None);
+            };
+            if !vm.has_function_export(ON_ERROR_FN_NAME)
+                || self.compare(&fn_name.to_val(), &handler_fn.to_val())? == Ordering::Equal
+            {
+                return Ok(None);
+            }
+            Ok(Some(vm.contract_id.metered_clone(self)?))
+        })?;
+        let Some(caller_id) = caller_id else {
+            return Ok(());
+        };
+        let context = self.error_to_val_with_context_internal(error)?;
+        let res = self.call_n_internal(
+            &caller_id,
+            handler_fn,
+            &[
+                error.to_val(),
+                callee.to_val(),
+                func.to_val(),
+                context.to_val(),
+            ],
+            ContractReentryMode::SelfAllowed,
+            true,
+        );
+        match res {
+            Ok(_) => Ok(()),
+            Err(e) if e.is_recoverable() => {
+                self.error(
+                    e.error,
+                    "contract __on_error handler failed",
+                    &[func.to_val()],
+                );
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
     // Notes on metering: this is covered by the called components.
     pub(crate) fn call_n_internal(
         &self,
@@ -495,9 +800,23 @@ impl Host {
                 &[func.to_val()],
             ));
         }
+        #[allow(unused_mut)]
+        let mut reentry_mode = reentry_mode;
+        #[cfg(any(test, feature = "testutils"))]
+        if let Some(caller_id) = self.get_current_contract_id_unmetered()? {
+            if self
+                .try_borrow_reentry_allow_list()?
+                .contains(&(caller_id, id.clone()))
+            {
+                reentry_mode = ContractReentryMode::Allowed;
+            }
+        }
         if !matches!(reentry_mode, ContractReentryMode::Allowed) {
             let mut is_last_non_host_frame = true;
-            for ctx in self.try_borrow_context()?.iter().rev() {
+            let context = self.try_borrow_context()?;
+            // Contract frames visited so far, from the top of the stack down.
+            let mut visited: Vec<&Hash> = vec![];
+            for ctx in context.iter().rev() {
                 let exist_id = match &ctx.frame {
                     Frame::ContractVM { vm, .. } => &vm.contract_id,
                     Frame::Token(id, ..) => id,
@@ -512,6 +831,11 @@ impl Host {
                         is_last_non_host_frame = false;
                         continue;
                     }
+                    let cycle: Vec<&Hash> = std::iter::once(exist_id)
+                        .chain(visited.into_iter().rev())
+                        .chain(std::iter::once(id))
+                        .collect();
+                    self.reentry_diagnostics(&cycle, &func)?;
                     return Err(self.err(
                         ScErrorType::Context,
                         ScErrorCode::InvalidAction,
@@ -519,6 +843,9 @@ impl Host {
                         &[],
                     ));
                 }
+                if self.is_debug()? {
+                    visited.push(exist_id);
+                }
                 is_last_non_host_frame = false;
             }
         }
@@ -590,12 +917,14 @@ impl Host {
                             // `Error`. Since we're trying to emulate
                             // what-the-VM-would-do here, and the VM traps with
                             // an unreachable error on contract panic, we
-                            // generate same error (by converting a wasm
+                            // generate same error (that of a wasm
                             // trap-unreachable code). It's a little weird
                             // because we're not actually running a VM, but we
                             // prioritize emulation fidelity over honesty here.
-                            let mut error: Error =
-                                Error::from(wasmi::core::TrapCode::UnreachableCodeReached);
+                            let mut error: Error = Error::from_type_and_code(
+                                ScErrorType::WasmVm,
+                                ScErrorCode::InvalidAction,
+                            );
 
                             let mut recovered_error_from_panic_refcell = false;
                             if let Ok(panic) = panic.try_borrow() {
@@ -694,8 +1023,59 @@ impl Host {
 
     // Notes on metering: covered by the called components.
     pub fn invoke_function(&self, hf: HostFunction) -> Result<ScVal, HostError> {
-        let rv = self.invoke_function_raw(hf)?;
-        self.from_host_val(rv)
+        let timer = self.start_metrics_timer()?;
+        let res = self
+            .invoke_function_raw(hf)
+            .and_then(|rv| self.from_host_val(rv));
+        self.report_invocation(timer, res.is_ok())?;
+        res
+    }
+
+    /// Runs a batch of host function operations sharing the storage, like the
+    /// operations of a single transaction, and returns their results.
+    ///
+    /// The batch is atomic: the first failing operation rolls back the
+    /// storage changes and events of all the operations and ends the batch,
+    /// so its result is the last one returned. Operations are otherwise
+    /// isolated by their own authorization entries and event ranges: the
+    /// operations without entries run in the authorization mode the [`Host`]
+    /// had at the start of the batch, which is also restored at its end.
+    // Notes on metering: the snapshots of the storage and authorization
+    // state taken for rolling back the batch are not charged, as they are
+    // bookkeeping of the embedder rather than work done on behalf of the
+    // contracts.
+    pub fn invoke_functions(
+        &self,
+        ops: Vec<HostFunctionOp>,
+    ) -> Result<Vec<HostFunctionOpResult>, HostError> {
+        let storage = self.try_borrow_storage()?.map.clone();
+        let auth_manager = self.try_borrow_authorization_manager()?.clone();
+        let events_start = self.try_borrow_events()?.vec.len();
+        let fee_refund = self.try_borrow_fee_refund()?.clone();
+        let mut results: Vec<HostFunctionOpResult> = vec![];
+        for op in ops {
+            let has_auth_entries = op.auth_entries.is_some();
+            if let Some(auth_entries) = op.auth_entries {
+                self.set_authorization_entries(auth_entries)?;
+            }
+            let op_events_start = self.try_borrow_events()?.vec.len();
+            let result = self.invoke_function(op.host_function);
+            if has_auth_entries {
+                *self.try_borrow_authorization_manager_mut()? = auth_manager.clone();
+            }
+            let failed = result.is_err();
+            results.push(HostFunctionOpResult {
+                result,
+                events: op_events_start..self.try_borrow_events()?.vec.len(),
+            });
+            if failed {
+                self.try_borrow_storage_mut()?.map = storage;
+                self.try_borrow_events_mut()?.rollback(events_start)?;
+                *self.try_borrow_fee_refund_mut()? = fee_refund;
+                break;
+            }
+        }
+        Ok(results)
     }
 
     pub(crate) fn maybe_init_instance_storage(&self, ctx: &mut Context) -> Result<(), HostError> {
@@ -723,7 +1103,10 @@ impl Host {
             storage_map.as_ref().map_or_else(
                 || Ok(vec![]),
                 |m| {
-                    m.iter()
+                    // The host entries are kept out of the storage visible to
+                    // the contract.
+                    m[..m.len() - host_instance_entries_len(m)]
+                        .iter()
                         .map(|i| Ok((self.to_host_val(&i.key)?, self.to_host_val(&i.val)?)))
                         .metered_collect::<Result<Vec<(Val, Val)>, HostError>>(self)?
                 },
@@ -733,6 +1116,107 @@ impl Host {
         Ok(())
     }
 
+    // Calls `f` with the contract instance of the current frame, which
+    // `persist_instance_storage` writes back at the end of the frame.
+    pub(crate) fn with_current_frame_instance_mut<F, U>(&self, f: F) -> Result<U, HostError>
+    where
+        F: FnOnce(&mut ScContractInstance) -> Result<U, HostError>,
+    {
+        self.with_current_context_mut(|ctx| match &mut ctx.frame {
+            Frame::ContractVM { instance, .. } => f(instance),
+            Frame::HostFunction(_) => Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InternalError,
+                "unexpected contract instance access for host fn",
+                &[],
+            )),
+            Frame::Token(_, _, _, instance) => f(instance),
+            #[cfg(any(test, feature = "testutils"))]
+            Frame::TestContract(t) => f(&mut t.instance),
+        })
+    }
+
+    // Removes the host entry `key` from the instance of the current frame and
+    // returns its value.
+    fn take_host_instance_entry(&self, key: &ScVal) -> Result<Option<ScVal>, HostError> {
+        self.with_current_frame_instance_mut(|instance| {
+            let Some(storage) = &mut instance.storage else {
+                return Ok(None);
+            };
+            let start = storage.len() - host_instance_entries_len(storage);
+            let Some(i) = storage[start..].iter().position(|e| e.key == *key) else {
+                return Ok(None);
+            };
+            let mut entries: Vec<ScMapEntry> = std::mem::take(&mut storage.0).into();
+            let entry = entries.remove(start + i);
+            storage.0 = entries.try_into()?;
+            Ok(Some(entry.val))
+        })
+    }
+
+    // Sets the host entry `key` of the instance of the current frame, which
+    // must not be present, to `val` if it's not `None`.
+    fn restore_host_instance_entry(
+        &self,
+        key: &ScVal,
+        val: Option<ScVal>,
+    ) -> Result<(), HostError> {
+        let Some(val) = val else {
+            return Ok(());
+        };
+        self.with_current_frame_instance_mut(|instance| {
+            let mut entries: Vec<ScMapEntry> = match instance.storage.take() {
+                Some(m) => m.0.into(),
+                None => vec![],
+            };
+            let start = entries.len() - host_instance_entries_len(&entries);
+            let pos = start + entries[start..].partition_point(|e| e.key < *key);
+            entries.insert(
+                pos,
+                ScMapEntry {
+                    key: key.clone(),
+                    val,
+                },
+            );
+            instance.storage = Some(ScMap(entries.try_into()?));
+            Ok(())
+        })
+    }
+
+    // Calls `f` with the value of the host entry `key` of the instance of the
+    // current frame, if any.
+    // Notes on metering: there are only a few host entries, so moving them
+    // around is not charged for.
+    pub(crate) fn visit_host_instance_entry<F, U>(&self, key: &ScVal, f: F) -> Result<U, HostError>
+    where
+        F: FnOnce(Option<&ScVal>) -> Result<U, HostError>,
+    {
+        let val = self.take_host_instance_entry(key)?;
+        let res = f(val.as_ref());
+        self.restore_host_instance_entry(key, val)?;
+        res
+    }
+
+    // Calls `f` with the value of the host entry `key` of the instance of the
+    // current frame, which `f` may replace, remove (by setting it to `None`)
+    // or create. The instance is persisted at the end of the frame.
+    // Notes on metering: same as `visit_host_instance_entry`.
+    pub(crate) fn with_host_instance_entry_mut<F, U>(
+        &self,
+        key: &ScVal,
+        f: F,
+    ) -> Result<U, HostError>
+    where
+        F: FnOnce(&mut Option<ScVal>) -> Result<U, HostError>,
+    {
+        let mut val = self.take_host_instance_entry(key)?;
+        let res = f(&mut val);
+        self.restore_host_instance_entry(key, val)?;
+        // Makes sure that the instance is persisted at the end of the frame.
+        self.with_mut_instance_storage(|_| Ok(()))?;
+        res
+    }
+
     // Make the in-memory instance storage persist into the `Storage` by writing
     // its updated contents into corresponding `ContractData` ledger entry.
     fn persist_instance_storage(&self) -> Result<(), HostError> {
@@ -760,9 +1244,27 @@ impl Host {
                     #[cfg(any(test, feature = "testutils"))]
                     Frame::TestContract(t) => t.instance.executable.clone(),
                 };
+                let mut map = self.host_map_to_scmap(&storage.map)?;
+                let instance_storage = match &ctx.frame {
+                    Frame::ContractVM { instance, .. } => &instance.storage,
+                    Frame::HostFunction(_) => &None,
+                    Frame::Token(_, _, _, instance) => &instance.storage,
+                    #[cfg(any(test, feature = "testutils"))]
+                    Frame::TestContract(t) => &t.instance.storage,
+                };
+                let host_entries = instance_storage
+                    .as_ref()
+                    .map_or(&[][..], |m| &m[m.len() - host_instance_entries_len(m)..]);
+                if !host_entries.is_empty() {
+                    let mut entries: Vec<ScMapEntry> = map.0.into();
+                    for entry in host_entries {
+                        entries.push(entry.metered_clone(self)?);
+                    }
+                    map = ScMap(entries.try_into()?);
+                }
                 Ok(Some(ScContractInstance {
                     executable,
-                    storage: Some(self.host_map_to_scmap(&storage.map)?),
+                    storage: Some(map),
                 }))
             } else {
                 Ok(None)
@@ -785,3 +1287,27 @@ impl Host {
         Ok(())
     }
 }
+
+/// Returns whether `entry` of the storage of a contract instance is kept by
+/// the host, i.e. the upgrade timelock and the data locks of the contract.
+///
+/// The host entries are keyed by the values that can't be converted to a
+/// `Val`, so that contracts can't access them as instance storage. These
+/// sort after all the other keys, so the host entries are always at the end
+/// of the storage.
+pub(crate) fn is_host_instance_entry(entry: &ScMapEntry) -> bool {
+    matches!(
+        entry.key,
+        ScVal::LedgerKeyContractInstance | ScVal::LedgerKeyNonce(_)
+    )
+}
+
+// Returns the number of host entries at the end of the storage of a contract
+// instance.
+fn host_instance_entries_len(entries: &[ScMapEntry]) -> usize {
+    entries
+        .iter()
+        .rev()
+        .take_while(|e| is_host_instance_entry(e))
+        .count()
+}
@@ -1,31 +1,146 @@
+use std::rc::Rc;
+
 use soroban_env_common::xdr::{ContractDataDurability, LedgerKey};
 
 use crate::{Host, HostError, LedgerInfo};
 
+/// Source of the information about the ledger the host runs in, see
+/// [`Host::set_ledger_info_provider`].
+///
+/// Embedders can implement this to serve the fields lazily, e.g. from their
+/// own ledger state, instead of copying them into a [`LedgerInfo`] up front.
+/// Fields that are added to the network configuration later on are added as
+/// methods with a default implementation, so that existing providers keep
+/// working.
+pub trait LedgerInfoProvider {
+    fn protocol_version(&self) -> u32;
+    fn sequence_number(&self) -> u32;
+    fn timestamp(&self) -> u64;
+    fn network_id(&self) -> [u8; 32];
+    fn base_reserve(&self) -> u32;
+    fn min_temp_entry_expiration(&self) -> u32;
+    fn min_persistent_entry_expiration(&self) -> u32;
+    fn max_entry_expiration(&self) -> u32;
+}
+
+impl LedgerInfoProvider for LedgerInfo {
+    fn protocol_version(&self) -> u32 {
+        self.protocol_version
+    }
+
+    fn sequence_number(&self) -> u32 {
+        self.sequence_number
+    }
+
+    fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
+    fn network_id(&self) -> [u8; 32] {
+        self.network_id
+    }
+
+    fn base_reserve(&self) -> u32 {
+        self.base_reserve
+    }
+
+    fn min_temp_entry_expiration(&self) -> u32 {
+        self.min_temp_entry_expiration
+    }
+
+    fn min_persistent_entry_expiration(&self) -> u32 {
+        self.min_persistent_entry_expiration
+    }
+
+    fn max_entry_expiration(&self) -> u32 {
+        self.max_entry_expiration
+    }
+}
+
+impl LedgerInfo {
+    /// Reads every field of the ledger information from `provider`.
+    pub fn from_provider(provider: &dyn LedgerInfoProvider) -> Self {
+        Self {
+            protocol_version: provider.protocol_version(),
+            sequence_number: provider.sequence_number(),
+            timestamp: provider.timestamp(),
+            network_id: provider.network_id(),
+            base_reserve: provider.base_reserve(),
+            min_temp_entry_expiration: provider.min_temp_entry_expiration(),
+            min_persistent_entry_expiration: provider.min_persistent_entry_expiration(),
+            max_entry_expiration: provider.max_entry_expiration(),
+        }
+    }
+}
+
+/// Where the host gets the ledger information from: either a [`LedgerInfo`]
+/// set with [`Host::set_ledger_info`] or a [`LedgerInfoProvider`].
+#[derive(Clone)]
+pub(crate) enum LedgerSource {
+    Info(LedgerInfo),
+    Provider {
+        provider: Rc<dyn LedgerInfoProvider>,
+        // The fields read from the provider at the start of the current
+        // invocation, which are served until its end instead of querying the
+        // provider again. `None` outside of invocations.
+        snapshot: Option<LedgerInfo>,
+    },
+}
+
 impl Host {
+    /// Reads the ledger information from the provider, if any, at the start
+    /// of an invocation, so that it can't change during the invocation.
+    // Notes on metering: not charged, the ledger information has a small
+    // fixed size.
+    pub(crate) fn snapshot_ledger_info(&self) -> Result<(), HostError> {
+        // The provider is cloned out of the host, so that it may call back
+        // into the host.
+        let provider = match self.try_borrow_ledger()?.as_ref() {
+            Some(LedgerSource::Provider { provider, .. }) => Rc::clone(provider),
+            None | Some(LedgerSource::Info(_)) => return Ok(()),
+        };
+        let info = LedgerInfo::from_provider(provider.as_ref());
+        if let Some(LedgerSource::Provider { snapshot, .. }) =
+            self.try_borrow_ledger_mut()?.as_mut()
+        {
+            *snapshot = Some(info);
+        }
+        Ok(())
+    }
+
+    /// Drops the ledger information read by [`Host::snapshot_ledger_info`]
+    /// at the end of an invocation.
+    pub(crate) fn drop_ledger_info_snapshot(&self) -> Result<(), HostError> {
+        if let Some(LedgerSource::Provider { snapshot, .. }) =
+            self.try_borrow_ledger_mut()?.as_mut()
+        {
+            *snapshot = None;
+        }
+        Ok(())
+    }
+
     pub(crate) fn get_min_expiration_ledger(
         &self,
         storage_type: ContractDataDurability,
     ) -> Result<u32, HostError> {
-        let ledger_seq = self.with_ledger_info(|li| Ok(li.sequence_number))?;
-        let min_expiration = match storage_type {
-            ContractDataDurability::Temporary => {
-                self.with_ledger_info(|li| Ok(li.min_temp_entry_expiration))?
-            }
-            ContractDataDurability::Persistent => {
-                self.with_ledger_info(|li: &LedgerInfo| Ok(li.min_persistent_entry_expiration))?
-            }
-        };
-        Ok(ledger_seq.saturating_add(min_expiration.saturating_sub(1)))
+        self.with_ledger_info_provider(|li| {
+            let min_expiration = match storage_type {
+                ContractDataDurability::Temporary => li.min_temp_entry_expiration(),
+                ContractDataDurability::Persistent => li.min_persistent_entry_expiration(),
+            };
+            Ok(li
+                .sequence_number()
+                .saturating_add(min_expiration.saturating_sub(1)))
+        })
     }
 
     pub(crate) fn max_expiration_ledger(&self) -> Result<u32, HostError> {
-        self.with_ledger_info(|li| {
+        self.with_ledger_info_provider(|li| {
             Ok(li
-                .sequence_number
+                .sequence_number()
                 // Entry can live for at most max_entry_expiration ledgers from
                 // now, counting the current one.
-                .saturating_add(li.max_entry_expiration.saturating_sub(1)))
+                .saturating_add(li.max_entry_expiration().saturating_sub(1)))
         })
     }
 }
@@ -8,7 +8,7 @@ use crate::{
     xdr::{
         Asset, ContractCodeEntry, ContractDataDurability, ContractExecutable, ContractIdPreimage,
         ContractIdPreimageFromAddress, ExtensionPoint, Hash, LedgerKey, LedgerKeyContractCode,
-        ScAddress, ScContractInstance, ScErrorCode, ScErrorType,
+        ScAddress, ScContractInstance, ScErrorCode, ScErrorType, ScVal, ScVec,
     },
     AddressObject, BytesObject, Host, HostError, Symbol, TryFromVal, Vm,
 };
@@ -40,7 +40,9 @@ impl Host {
         // possible to accidentally create a contract that never may be invoked
         // (just by providing a bad hash).
         if let ContractExecutable::Wasm(wasm_hash) = &contract_executable {
-            if !self.wasm_exists(wasm_hash)? {
+            // The built-in contracts have no Wasm behind their reserved hashes.
+            let is_builtin = self.builtin_wasm_contract(wasm_hash)?.is_some();
+            if !is_builtin && !self.wasm_exists(wasm_hash)? {
                 return Err(err!(
                     self,
                     (ScErrorType::Storage, ScErrorCode::MissingValue),
@@ -215,11 +217,149 @@ impl Host {
         }
         Ok(hash_obj)
     }
+
+    // Reads the upgrade timelock of the current contract from its instance.
+    // Notes on metering: the entry is only inspected in place.
+    fn read_upgrade_timelock(&self) -> Result<Option<UpgradeTimelock>, HostError> {
+        self.visit_host_instance_entry(&UPGRADE_TIMELOCK_KEY, |val| {
+            let Some(val) = val else {
+                return Ok(None);
+            };
+            let fields = match val {
+                ScVal::Vec(Some(fields)) => fields.as_slice(),
+                _ => &[],
+            };
+            match fields {
+                [ScVal::U32(delay)] => Ok(Some(UpgradeTimelock {
+                    delay: *delay,
+                    pending: None,
+                })),
+                [ScVal::U32(delay), ScVal::Bytes(hash), ScVal::U32(announced)] => {
+                    Ok(Some(UpgradeTimelock {
+                        delay: *delay,
+                        pending: Some((
+                            self.fixed_length_bytes_from_slice::<Hash, 32>(
+                                "wasm_hash",
+                                hash.as_slice(),
+                            )?,
+                            *announced,
+                        )),
+                    }))
+                }
+                _ => Err(self.err(
+                    ScErrorType::Storage,
+                    ScErrorCode::InternalError,
+                    "malformed upgrade timelock",
+                    &[],
+                )),
+            }
+        })
+    }
+
+    // Writes the upgrade timelock into the instance of the current contract,
+    // which is persisted at the end of the frame along with the instance
+    // storage.
+    fn write_upgrade_timelock(&self, timelock: &UpgradeTimelock) -> Result<(), HostError> {
+        let mut fields = vec![ScVal::U32(timelock.delay)];
+        if let Some((hash, announced)) = &timelock.pending {
+            fields.push(ScVal::Bytes(self.scbytes_from_hash(hash)?));
+            fields.push(ScVal::U32(*announced));
+        }
+        let val = ScVal::Vec(Some(ScVec(fields.try_into()?)));
+        self.with_host_instance_entry_mut(&UPGRADE_TIMELOCK_KEY, |entry| {
+            *entry = Some(val);
+            Ok(())
+        })
+    }
+
+    /// Sets the minimum number of ledgers between announcing an update of the
+    /// Wasm of the current contract and activating it with
+    /// `update_current_contract_wasm`. The delay can only be increased, so
+    /// that it can't be bypassed by the contract being updated.
+    // Notes on metering: covered by components.
+    pub(crate) fn set_upgrade_timelock_internal(&self, delay: u32) -> Result<(), HostError> {
+        let timelock = match self.read_upgrade_timelock()? {
+            Some(timelock) if timelock.delay > delay => {
+                return Err(self.err(
+                    ScErrorType::Context,
+                    ScErrorCode::InvalidAction,
+                    "upgrade timelock can't be decreased",
+                    &[timelock.delay.into(), delay.into()],
+                ));
+            }
+            Some(timelock) => UpgradeTimelock { delay, ..timelock },
+            None => UpgradeTimelock {
+                delay,
+                pending: None,
+            },
+        };
+        self.write_upgrade_timelock(&timelock)?;
+        self.emit_upgrade_timelock_set_event(delay)
+    }
+
+    /// Checks the upgrade timelock of the current contract before updating
+    /// its Wasm to `wasm_hash`. Returns `true` if the update can be activated
+    /// now, which is the case when there is no timelock or when the update
+    /// has been announced at least the timelock delay ago. Otherwise, if the
+    /// update hasn't been announced yet, announces it (replacing any other
+    /// pending update) and returns `false`, and if it has been announced
+    /// too recently, fails with `(Context, InvalidAction)`.
+    // Notes on metering: covered by components.
+    pub(crate) fn check_upgrade_timelock(&self, wasm_hash: &Hash) -> Result<bool, HostError> {
+        let Some(timelock) = self.read_upgrade_timelock()? else {
+            return Ok(true);
+        };
+        if timelock.delay == 0 {
+            return Ok(true);
+        }
+        let ledger_seq = self.with_ledger_info_provider(|li| Ok(li.sequence_number()))?;
+        match &timelock.pending {
+            Some((pending_hash, announced)) if pending_hash == wasm_hash => {
+                let activation_ledger = announced.saturating_add(timelock.delay);
+                if ledger_seq < activation_ledger {
+                    return Err(self.err(
+                        ScErrorType::Context,
+                        ScErrorCode::InvalidAction,
+                        "upgrade timelock has not elapsed yet",
+                        &[activation_ledger.into(), ledger_seq.into()],
+                    ));
+                }
+                self.write_upgrade_timelock(&UpgradeTimelock {
+                    delay: timelock.delay,
+                    pending: None,
+                })?;
+                Ok(true)
+            }
+            _ => {
+                self.write_upgrade_timelock(&UpgradeTimelock {
+                    delay: timelock.delay,
+                    pending: Some((wasm_hash.metered_clone(self)?, ledger_seq)),
+                })?;
+                self.emit_upgrade_announced_event(
+                    wasm_hash,
+                    ledger_seq.saturating_add(timelock.delay),
+                )?;
+                Ok(false)
+            }
+        }
+    }
+}
+
+/// Key of the host entry of a contract instance holding its upgrade timelock,
+/// see [`Host::set_upgrade_timelock_internal`]. The value is a vector holding
+/// the delay in ledgers, followed by the hash of the pending Wasm and the
+/// ledger of its announcement if an update is pending.
+const UPGRADE_TIMELOCK_KEY: ScVal = ScVal::LedgerKeyContractInstance;
+
+struct UpgradeTimelock {
+    delay: u32,
+    // The announced Wasm hash and the ledger of the announcement.
+    pending: Option<(Hash, u32)>,
 }
 
 use super::crypto;
 #[cfg(any(test, feature = "testutils"))]
-use super::ContractFunctionSet;
+use super::{ContractFunctionSet, HostFnInterposer, INTERPOSABLE_HOST_FNS};
 
 // "testutils" is not covered by budget metering.
 #[cfg(any(test, feature = "testutils"))]
@@ -252,4 +392,49 @@ impl Host {
         contracts.insert(contract_id, contract_fns);
         Ok(())
     }
+
+    /// Registers `interposer` to be called instead of the host function
+    /// `fn_name` whenever that host function is called by the test contract
+    /// registered at `contract_address`. Other contracts keep observing the
+    /// regular behavior of the host function.
+    ///
+    /// Only the host functions listed in [`INTERPOSABLE_HOST_FNS`] can be
+    /// interposed.
+    pub fn register_test_contract_host_fn_interposer(
+        &self,
+        contract_address: AddressObject,
+        fn_name: &str,
+        interposer: Rc<dyn HostFnInterposer>,
+    ) -> Result<(), HostError> {
+        let Some(fn_name) = INTERPOSABLE_HOST_FNS.iter().find(|f| **f == fn_name) else {
+            return Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidInput,
+                "host function can't be interposed",
+                &[],
+            ));
+        };
+        let contract_id = self.contract_id_from_address(contract_address)?;
+        self.try_borrow_host_fn_interposers_mut()?
+            .insert((contract_id, *fn_name), interposer);
+        Ok(())
+    }
+
+    /// Allows the contract at `caller` to call the contract at `callee` even
+    /// when `callee` is already on the call stack. Any other re-entrant calls
+    /// are still rejected.
+    ///
+    /// This is meant for experimenting with reentrancy-safe contract designs
+    /// in tests, as re-entry is never allowed in production.
+    pub fn allow_contract_reentry(
+        &self,
+        caller: AddressObject,
+        callee: AddressObject,
+    ) -> Result<(), HostError> {
+        let caller_id = self.contract_id_from_address(caller)?;
+        let callee_id = self.contract_id_from_address(callee)?;
+        self.try_borrow_reentry_allow_list_mut()?
+            .insert((caller_id, callee_id));
+        Ok(())
+    }
 }
@@ -31,6 +31,7 @@ impl Host {
         })
     }
 
+    #[cfg(feature = "vm")]
     pub(crate) fn metered_vm_write_bytes_to_linear_memory(
         &self,
         vmcaller: &mut VmCaller<Host>,
@@ -46,6 +47,7 @@ impl Host {
         )
     }
 
+    #[cfg(feature = "vm")]
     pub(crate) fn metered_vm_read_bytes_from_linear_memory(
         &self,
         vmcaller: &mut VmCaller<Host>,
@@ -61,6 +63,7 @@ impl Host {
         )
     }
 
+    #[cfg(feature = "vm")]
     pub(crate) fn metered_vm_write_vals_to_linear_memory<const VAL_SZ: usize, VAL>(
         &self,
         vmcaller: &mut VmCaller<Host>,
@@ -102,6 +105,7 @@ impl Host {
         Ok(())
     }
 
+    #[cfg(feature = "vm")]
     pub(crate) fn metered_vm_read_vals_from_linear_memory<const VAL_SZ: usize, VAL>(
         &self,
         vmcaller: &mut VmCaller<Host>,
@@ -156,6 +160,7 @@ impl Host {
     // this assumption is incorrect, this will not break the safety of this
     // function, only make it read junk memory in the guest and therefore likely
     // cause the callback to return an error.
+    #[cfg(feature = "vm")]
     pub(crate) fn metered_vm_scan_slices_in_linear_memory(
         &self,
         vmcaller: &mut VmCaller<Host>,
@@ -304,6 +309,7 @@ impl Host {
             .checked_add(len)
             .ok_or_else(|| self.err_arith_overflow())? as usize;
         if obj_new.len() < obj_end {
+            self.check_object_len::<HOT>(obj_end)?;
             self.charge_budget(
                 ContractCostType::HostMemAlloc,
                 Some((obj_end - obj_new.len()) as u64),
@@ -355,11 +361,23 @@ impl Host {
         lm_pos: U32Val,
         len: U32Val,
     ) -> Result<HOT::Wrapper, HostError> {
+        self.check_object_len::<HOT>(u32::from(len) as usize)?;
+        let vnew = self.metered_vec_from_linear_memory(vmcaller, lm_pos, len)?;
+        self.add_host_object::<HOT>(vnew.try_into()?)
+    }
+
+    /// Copies `len` bytes at `lm_pos` in the linear memory into a new vector.
+    pub(crate) fn metered_vec_from_linear_memory(
+        &self,
+        vmcaller: &mut VmCaller<Host>,
+        lm_pos: U32Val,
+        len: U32Val,
+    ) -> Result<Vec<u8>, HostError> {
         let VmSlice { vm, pos, len } = self.decode_vmslice(lm_pos, len)?;
         self.charge_budget(ContractCostType::HostMemAlloc, Some(len as u64))?;
         let mut vnew: Vec<u8> = vec![0; len as usize];
         self.metered_vm_read_bytes_from_linear_memory(vmcaller, &vm, pos, &mut vnew)?;
-        self.add_host_object::<HOT>(vnew.try_into()?)
+        Ok(vnew)
     }
 
     // Test function for calibration purpose. The caller needs to ensure `src` and `dest` has
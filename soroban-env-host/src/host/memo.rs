@@ -0,0 +1,109 @@
+use std::cmp::Ordering;
+
+use crate::{
+    budget::AsBudget,
+    host::metered_clone,
+    xdr::{ContractCostType, Hash},
+    Compare, Host, HostError, Val,
+};
+
+type MemoKey = (Hash, Val);
+
+/// The invocation-scoped cache of the `memo_put` and `memo_get` host
+/// functions, keyed by the contract id and the cache key.
+///
+/// Entries are updated in place. The previous value of every written key is
+/// kept in an undo log, so that the writes of a failed frame can be undone by
+/// truncating the log to its length at the start of the frame.
+#[derive(Default)]
+pub(crate) struct Memo {
+    // Sorted by key.
+    entries: Vec<(MemoKey, Val)>,
+    // The index of each written entry and its value before the write (`None`
+    // for new entries), oldest first.
+    undo_log: Vec<(usize, Option<Val>)>,
+}
+
+impl Memo {
+    // Same charges as a lookup in a `MeteredOrdMap`.
+    fn find(&self, key: &MemoKey, host: &Host) -> Result<Result<usize, usize>, HostError> {
+        let mag = 64 - (self.entries.len() as u64).leading_zeros();
+        host.as_budget()
+            .bulk_charge(ContractCostType::MapEntry, 1 + mag as u64, None)?;
+        let mut err: Option<HostError> = None;
+        let res = self.entries.binary_search_by(|probe| {
+            if err.is_some() {
+                return Ordering::Equal;
+            }
+            match host.compare(&probe.0, key) {
+                Ok(ord) => ord,
+                Err(he) => {
+                    err = Some(he);
+                    Ordering::Equal
+                }
+            }
+        });
+        match err {
+            Some(he) => Err(he),
+            None => Ok(res),
+        }
+    }
+
+    pub(crate) fn get(&self, key: &MemoKey, host: &Host) -> Result<Option<Val>, HostError> {
+        Ok(match self.find(key, host)? {
+            Ok(i) => Some(self.entries[i].1),
+            Err(_) => None,
+        })
+    }
+
+    pub(crate) fn put(&mut self, key: MemoKey, val: Val, host: &Host) -> Result<(), HostError> {
+        metered_clone::charge_heap_alloc::<(usize, Option<Val>)>(1, host)?;
+        match self.find(&key, host)? {
+            Ok(i) => {
+                let prev = std::mem::replace(&mut self.entries[i].1, val);
+                self.undo_log.push((i, Some(prev)));
+            }
+            Err(i) => {
+                // Inserting shifts the entries after `i`.
+                metered_clone::charge_heap_alloc::<(MemoKey, Val)>(1, host)?;
+                metered_clone::charge_shallow_copy::<(MemoKey, Val)>(
+                    (self.entries.len() - i) as u64,
+                    host,
+                )?;
+                self.entries.insert(i, (key, val));
+                self.undo_log.push((i, None));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the length of the undo log, to be passed to
+    /// [`Memo::rollback`].
+    pub(crate) fn undo_len(&self) -> usize {
+        self.undo_log.len()
+    }
+
+    /// Undoes the writes made since the undo log had length `undo_len`.
+    // Notes on metering: not metered, this does less work than the writes it
+    // undoes, which have been charged.
+    pub(crate) fn rollback(&mut self, undo_len: usize) {
+        // Undoing in reverse order restores the entries exactly as they were
+        // after each write, so the recorded indices stay valid.
+        while self.undo_log.len() > undo_len {
+            let Some((i, prev)) = self.undo_log.pop() else {
+                break;
+            };
+            match prev {
+                Some(prev) => self.entries[i].1 = prev,
+                None => {
+                    self.entries.remove(i);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+        self.undo_log.clear();
+    }
+}
@@ -6,7 +6,7 @@ use crate::{
     xdr::ContractCostType,
     Compare, Error, Host, HostError,
 };
-use std::{borrow::Borrow, cmp::Ordering, marker::PhantomData};
+use std::{borrow::Borrow, cmp::Ordering, marker::PhantomData, ops::Range};
 
 const MAP_OOB: Error = Error::from_type_and_code(ScErrorType::Object, ScErrorCode::IndexBounds);
 
@@ -162,6 +162,17 @@ where
     }
 
     pub fn insert(&self, key: K, value: V, ctx: &Ctx) -> Result<Self, HostError> {
+        Ok(self.insert_with_replaced(key, value, ctx)?.0)
+    }
+
+    /// Same as [`MeteredOrdMap::insert`], also returning the position of the
+    /// entry that has been replaced, if any.
+    pub(crate) fn insert_with_replaced(
+        &self,
+        key: K,
+        value: V,
+        ctx: &Ctx,
+    ) -> Result<(Self, Option<usize>), HostError> {
         self.charge_access(1, ctx)?;
         match self.find(&key, ctx)? {
             Ok(replace_pos) => {
@@ -174,7 +185,7 @@ where
                 let init = self.map.iter().take(replace_pos).cloned();
                 let fini = self.map.iter().skip(replace_pos + 1).cloned();
                 let iter = init.chain([(key, value)]).chain(fini);
-                Self::from_exact_iter(iter, ctx)
+                Ok((Self::from_exact_iter(iter, ctx)?, Some(replace_pos)))
             }
             Err(insert_pos) => {
                 // [0,1,2] insert_pos == 1
@@ -186,7 +197,7 @@ where
                     let init = self.map.iter().take(insert_pos).cloned();
                     let fini = self.map.iter().skip(insert_pos).cloned();
                     let iter = init.chain([(key, value)]).chain(fini);
-                    Self::from_exact_iter(iter, ctx)
+                    Ok((Self::from_exact_iter(iter, ctx)?, None))
                 }
             }
         }
@@ -260,6 +271,44 @@ where
         Ok(self.find(key, ctx)?.is_ok())
     }
 
+    /// Returns the range of positions of the entries with keys in
+    /// `start..end`, i.e. `start` inclusive and `end` exclusive.
+    pub fn key_range<Q>(&self, start: &Q, end: &Q, ctx: &Ctx) -> Result<Range<usize>, HostError>
+    where
+        K: Borrow<Q>,
+        Ctx: Compare<Q, Error = HostError>,
+    {
+        // Reversed bounds between the same two entries have the same
+        // positions, so the bounds themselves are compared.
+        if <Ctx as Compare<Q>>::compare(ctx, start, end)? == Ordering::Greater {
+            return Err((ScErrorType::Object, ScErrorCode::InvalidInput).into());
+        }
+        let (Ok(lo) | Err(lo)) = self.find(start, ctx)?;
+        let (Ok(hi) | Err(hi)) = self.find(end, ctx)?;
+        Ok(lo..hi)
+    }
+
+    /// Returns a new map with the entries at the `range` of positions.
+    pub fn slice(&self, range: Range<usize>, ctx: &Ctx) -> Result<Self, HostError> {
+        match self.map.get(range) {
+            Some(slice) => Self::from_exact_iter(slice.iter().cloned(), ctx),
+            None => Err(MAP_OOB.into()),
+        }
+    }
+
+    pub fn keys_in_range(
+        &self,
+        range: Range<usize>,
+        ctx: &Ctx,
+    ) -> Result<impl Iterator<Item = &K>, HostError> {
+        let slice = self
+            .map
+            .get(range)
+            .ok_or_else(|| HostError::from(MAP_OOB))?;
+        self.charge_access(slice.len(), ctx)?;
+        Ok(slice.iter().map(|(k, _)| k))
+    }
+
     pub fn keys(&self, ctx: &Ctx) -> Result<impl Iterator<Item = &K>, HostError> {
         self.charge_scan(ctx)?;
         Ok(self.map.iter().map(|(k, _)| k))
@@ -244,6 +244,33 @@ where
         }
     }
 
+    pub fn reverse(&self, budget: &Budget) -> Result<Self, HostError> {
+        let iter = self.vec.iter().rev().cloned();
+        Self::from_exact_iter(iter, budget)
+    }
+
+    // Removes the consecutive elements for which `same` returns true, keeping
+    // the first element of every run, like `Vec::dedup_by` does.
+    pub fn dedup_by<F>(&self, same: F, budget: &Budget) -> Result<Self, HostError>
+    where
+        F: Fn(&A, &A) -> Result<bool, HostError>,
+    {
+        self.charge_scan(budget)?;
+        let mut vec: Vec<A> = Vec::new();
+        for val in self.vec.iter() {
+            if let Some(last) = vec.last() {
+                if same(last, val)? {
+                    continue;
+                }
+            }
+            vec.push(val.clone());
+        }
+        // Like in `from_exact_iter`, the copy is charged after it's made in
+        // order to charge it all at once.
+        vec.charge_deep_clone(budget)?;
+        Self::from_vec(vec)
+    }
+
     pub fn first_index_of<F>(&self, f: F, budget: &Budget) -> Result<Option<usize>, HostError>
     where
         F: Fn(&A) -> Result<Ordering, HostError>,
@@ -4,13 +4,35 @@ use crate::{
     xdr::{ReadXdr, ScBytes, WriteXdr},
     BytesObject, Host, HostError,
 };
-use std::io::Write;
+use std::io::{Cursor, Write};
 
 use sha2::{Digest, Sha256};
 use soroban_env_common::xdr::{
-    DepthLimitedWrite, ScErrorCode, ScErrorType, DEFAULT_XDR_RW_DEPTH_LIMIT,
+    DepthLimitedRead, DepthLimitedWrite, ScErrorCode, ScErrorType, DEFAULT_XDR_RW_DEPTH_LIMIT,
 };
 
+/// Limits applied by [`Host::metered_from_xdr`] and
+/// [`metered_from_xdr_with_budget`] when decoding XDR.
+///
+/// Exceeding the depth limit fails with a `(Context, ExceededLimit)` error,
+/// exceeding the length limit with a `(Value, ExceededLimit)` error.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct XdrReadLimits {
+    /// Maximum nesting depth of the decoded value.
+    pub depth: u32,
+    /// Maximum length in bytes of the encoded input.
+    pub len: u32,
+}
+
+impl Default for XdrReadLimits {
+    fn default() -> Self {
+        Self {
+            depth: DEFAULT_XDR_RW_DEPTH_LIMIT,
+            len: u32::MAX,
+        }
+    }
+}
+
 struct MeteredWrite<'a, W: Write> {
     budget: &'a Budget,
     w: &'a mut W,
@@ -43,8 +65,18 @@ impl Host {
 
     pub fn metered_from_xdr<T: ReadXdr>(&self, bytes: &[u8]) -> Result<T, HostError> {
         let _span = tracy_span!("read xdr");
+        let limits = self.xdr_read_limits()?;
+        if bytes.len() > limits.len as usize {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::ExceededLimit,
+                "xdr input exceeds the length limit",
+                &[limits.len.into()],
+            ));
+        }
         self.charge_budget(ContractCostType::ValDeser, Some(bytes.len() as u64))?;
-        self.map_err(T::from_xdr(bytes))
+        let mut r = DepthLimitedRead::new(Cursor::new(bytes), limits.depth);
+        self.map_err(T::read_xdr_to_end(&mut r))
     }
 
     pub(crate) fn metered_from_xdr_obj<T: ReadXdr>(
@@ -70,14 +102,20 @@ pub fn metered_write_xdr(
         .map_err(|_| (ScErrorType::Budget, ScErrorCode::ExceededLimit).into())
 }
 
-// Host-less metered XDR decoding.
+// Host-less metered XDR decoding, with the same limits and errors as
+// `metered_from_xdr`.
 // Prefer using `metered_from_xdr` when host is available for better error
 // reporting.
 pub fn metered_from_xdr_with_budget<T: ReadXdr>(
     bytes: &[u8],
     budget: &Budget,
+    limits: XdrReadLimits,
 ) -> Result<T, HostError> {
     let _span = tracy_span!("read xdr with budget");
+    if bytes.len() > limits.len as usize {
+        return Err((ScErrorType::Value, ScErrorCode::ExceededLimit).into());
+    }
     budget.charge(ContractCostType::ValDeser, Some(bytes.len() as u64))?;
-    T::from_xdr(bytes).map_err(|e| e.into())
+    let mut r = DepthLimitedRead::new(Cursor::new(bytes), limits.depth);
+    T::read_xdr_to_end(&mut r).map_err(|e| e.into())
 }
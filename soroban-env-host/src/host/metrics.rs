@@ -0,0 +1,131 @@
+//! Reporting of the host internals to the telemetry of embedders, see
+//! [`MetricsSink`].
+
+use std::{
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use crate::{budget::AsBudget, Host, HostError};
+
+/// A contract data operation reported to [`MetricsSink::storage_op`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StorageOp {
+    Get,
+    Has,
+    Put,
+    Del,
+    Bump,
+}
+
+/// An embedder-provided receiver of counters and timers of the host
+/// internals, e.g. for exporting them as Prometheus metrics, see
+/// [`Host::set_metrics_sink`].
+///
+/// Every method defaults to a no-op, so sinks only need to implement the
+/// ones they're interested in. Reporting is not metered and doesn't affect
+/// the execution in any way; the wall-clock durations are only measured when
+/// a sink is set.
+pub trait MetricsSink {
+    /// Called when a top-level invocation of [`Host::invoke_function`]
+    /// finishes, successfully or not.
+    fn invocation(&self, _elapsed: Duration, _succeeded: bool) {}
+
+    /// Called after every top-level invocation with the total budget
+    /// consumed by the host so far.
+    fn budget_consumption(&self, _cpu_insns: u64, _mem_bytes: u64) {}
+
+    /// Called when a host function called by a Wasm contract returns, with
+    /// the budget it has consumed (including any nested contract calls).
+    fn host_fn_call(&self, _name: &'static str, _elapsed: Duration, _cpu_insns: u64) {}
+
+    /// Called when a Wasm module has been parsed and validated.
+    fn vm_parse(&self, _elapsed: Duration, _cpu_insns: u64) {}
+
+    /// Called when a parsed Wasm module has been instantiated.
+    fn vm_instantiation(&self, _elapsed: Duration, _cpu_insns: u64) {}
+
+    /// Called for every contract data operation performed by a contract.
+    fn storage_op(&self, _op: StorageOp) {}
+}
+
+/// The start of a measurement reported to the [`MetricsSink`], if any.
+pub(crate) struct MetricsTimer(Option<Instant>);
+
+impl MetricsTimer {
+    fn elapsed(&self) -> Duration {
+        self.0.map(|start| start.elapsed()).unwrap_or_default()
+    }
+}
+
+impl Host {
+    /// Sets the [`MetricsSink`] that receives the metrics of this host from
+    /// now on.
+    pub fn set_metrics_sink(&self, sink: Rc<dyn MetricsSink>) -> Result<(), HostError> {
+        *self.try_borrow_metrics_sink_mut()? = Some(sink);
+        Ok(())
+    }
+
+    // The sink is cloned out of the host, so that it may call back into the
+    // host.
+    fn with_metrics_sink(&self, f: impl FnOnce(&dyn MetricsSink)) -> Result<(), HostError> {
+        let sink = self.try_borrow_metrics_sink()?.clone();
+        if let Some(sink) = sink {
+            f(sink.as_ref());
+        }
+        Ok(())
+    }
+
+    pub(crate) fn start_metrics_timer(&self) -> Result<MetricsTimer, HostError> {
+        let enabled = self.try_borrow_metrics_sink()?.is_some();
+        Ok(MetricsTimer(enabled.then(Instant::now)))
+    }
+
+    pub(crate) fn report_invocation(
+        &self,
+        timer: MetricsTimer,
+        succeeded: bool,
+    ) -> Result<(), HostError> {
+        let cpu = self.as_budget().get_cpu_insns_consumed()?;
+        let mem = self.as_budget().get_mem_bytes_consumed()?;
+        self.with_metrics_sink(|sink| {
+            sink.invocation(timer.elapsed(), succeeded);
+            sink.budget_consumption(cpu, mem);
+        })
+    }
+
+    #[cfg(feature = "vm")]
+    pub(crate) fn report_host_fn_call(
+        &self,
+        name: &'static str,
+        cost_start: (u64, u64),
+        timer: MetricsTimer,
+    ) -> Result<(), HostError> {
+        let (cpu, _) = self.cost_since(cost_start)?;
+        self.with_metrics_sink(|sink| sink.host_fn_call(name, timer.elapsed(), cpu))
+    }
+
+    #[cfg(feature = "vm")]
+    pub(crate) fn report_vm_parse(
+        &self,
+        cost_start: (u64, u64),
+        timer: MetricsTimer,
+    ) -> Result<(), HostError> {
+        let (cpu, _) = self.cost_since(cost_start)?;
+        self.with_metrics_sink(|sink| sink.vm_parse(timer.elapsed(), cpu))
+    }
+
+    #[cfg(feature = "vm")]
+    pub(crate) fn report_vm_instantiation(
+        &self,
+        cost_start: (u64, u64),
+        timer: MetricsTimer,
+    ) -> Result<(), HostError> {
+        let (cpu, _) = self.cost_since(cost_start)?;
+        self.with_metrics_sink(|sink| sink.vm_instantiation(timer.elapsed(), cpu))
+    }
+
+    pub(crate) fn report_storage_op(&self, op: StorageOp) -> Result<(), HostError> {
+        self.with_metrics_sink(|sink| sink.storage_op(op))
+    }
+}
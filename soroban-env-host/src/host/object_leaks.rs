@@ -0,0 +1,159 @@
+use std::collections::HashSet;
+
+use crate::{
+    events::InternalDiagnosticArg,
+    host_object::HostObject,
+    xdr::{Hash, ScString, ScSymbol, ScVal},
+    Host, HostError, Object, U32Val, Val,
+};
+
+/// Where a host object has been allocated, as recorded by
+/// [`Host::enable_object_leak_detection`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ObjectAllocationSite {
+    /// The host function called by a Wasm contract that allocated the
+    /// object, or `None` if the object was allocated outside of any such
+    /// call, e.g. by a native contract or a test.
+    pub host_fn: Option<&'static str>,
+    /// The contract executing when the object was allocated.
+    pub contract_id: Option<Hash>,
+    /// The function of the frame executing when the object was allocated.
+    pub function: Option<String>,
+}
+
+/// A host object that hasn't been referenced by any return value, storage
+/// write or event, see [`Host::leaked_objects`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeakedObject {
+    pub handle: u32,
+    pub site: ObjectAllocationSite,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct ObjectLeakTracker {
+    // The allocation sites of the objects, by object index.
+    sites: Vec<(usize, ObjectAllocationSite)>,
+    // Values that escaped the host as return values, storage writes or
+    // events. The objects reachable from these are not leaked.
+    roots: Vec<Val>,
+    current_host_fn: Option<&'static str>,
+}
+
+// Note: none of this is metered, as it's only a testing aid.
+impl Host {
+    /// Starts recording the allocation site of every host object, which
+    /// allows finding the objects that are allocated but never used with
+    /// [`Host::leaked_objects`]. These are also reported as diagnostic events
+    /// with the `leaked_object` topic by [`Host::try_finish`].
+    pub fn enable_object_leak_detection(&self) -> Result<(), HostError> {
+        *self.try_borrow_object_leak_tracker_mut()? = Some(ObjectLeakTracker::default());
+        Ok(())
+    }
+
+    /// Returns the objects allocated since
+    /// [`Host::enable_object_leak_detection`] that are not reachable from any
+    /// contract call return value, stored value or event. Objects that have
+    /// only been used as inputs to produce other objects (e.g. the bytes
+    /// converted into a string) are reported too, as their allocation could
+    /// likely have been avoided.
+    pub fn leaked_objects(&self) -> Result<Vec<LeakedObject>, HostError> {
+        let Some(tracker) = self.try_borrow_object_leak_tracker()?.clone() else {
+            return Ok(vec![]);
+        };
+        let mut referenced = HashSet::new();
+        let mut pending = tracker.roots;
+        while let Some(val) = pending.pop() {
+            let Ok(obj) = Object::try_from(val) else {
+                continue;
+            };
+            let index = self.obj_handle_to_index(obj.get_handle())?;
+            if !referenced.insert(index) {
+                continue;
+            }
+            match self.try_borrow_objects()?.get(index) {
+                Some(HostObject::Vec(v)) => pending.extend(v.iter().copied()),
+                Some(HostObject::Map(m)) => {
+                    for (k, v) in m.map.iter() {
+                        pending.push(*k);
+                        pending.push(*v);
+                    }
+                }
+                _ => (),
+            }
+        }
+        let mut leaked = vec![];
+        for (index, site) in tracker.sites {
+            if !referenced.contains(&index) {
+                leaked.push(LeakedObject {
+                    handle: self.index_to_obj_handle(index, false)?,
+                    site,
+                });
+            }
+        }
+        Ok(leaked)
+    }
+
+    pub(crate) fn record_object_allocation(&self, index: usize) -> Result<(), HostError> {
+        if self.try_borrow_object_leak_tracker()?.is_none() {
+            return Ok(());
+        }
+        let (contract_id, function) = self.current_frame_identity();
+        if let Some(tracker) = self.try_borrow_object_leak_tracker_mut()?.as_mut() {
+            let site = ObjectAllocationSite {
+                host_fn: tracker.current_host_fn,
+                contract_id,
+                function,
+            };
+            tracker.sites.push((index, site));
+        }
+        Ok(())
+    }
+
+    // Marks the objects reachable from `vals` as referenced.
+    pub(crate) fn record_object_leak_roots(&self, vals: &[Val]) -> Result<(), HostError> {
+        if let Some(tracker) = self.try_borrow_object_leak_tracker_mut()?.as_mut() {
+            tracker.roots.extend_from_slice(vals);
+        }
+        Ok(())
+    }
+
+    // Sets the host function called by a Wasm contract that is executing,
+    // returning the previous one.
+    pub(crate) fn set_object_leak_host_fn(
+        &self,
+        host_fn: Option<&'static str>,
+    ) -> Result<Option<&'static str>, HostError> {
+        Ok(match self.try_borrow_object_leak_tracker_mut()?.as_mut() {
+            Some(tracker) => std::mem::replace(&mut tracker.current_host_fn, host_fn),
+            None => None,
+        })
+    }
+
+    // Emits an event with topic = ["leaked_object"] and
+    // data = [handle, host_fn, function] for every leaked object.
+    pub(crate) fn object_leak_diagnostics(&self) -> Result<(), HostError> {
+        if !self.is_debug()? || self.try_borrow_object_leak_tracker()?.is_none() {
+            return Ok(());
+        }
+        let string = |s: Option<&str>| -> Result<InternalDiagnosticArg, HostError> {
+            Ok(InternalDiagnosticArg::XdrVal(match s {
+                Some(s) => ScVal::String(ScString(s.as_bytes().to_vec().try_into()?)),
+                None => ScVal::Void,
+            }))
+        };
+        for leak in self.leaked_objects()? {
+            self.as_budget().with_free_budget(|| {
+                let topics = vec![InternalDiagnosticArg::XdrVal(ScVal::Symbol(ScSymbol(
+                    "leaked_object".try_into()?,
+                )))];
+                let args = vec![
+                    InternalDiagnosticArg::HostVal(U32Val::from(leak.handle).to_val()),
+                    string(leak.site.host_fn)?,
+                    string(leak.site.function.as_deref())?,
+                ];
+                self.record_diagnostic_event(leak.site.contract_id, topics, args)
+            })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use crate::{
+    budget::AsBudget,
+    host::crypto::sha256_hash_from_bytes,
+    xdr::{ScBytes, ScErrorCode, ScErrorType, ScVal},
+    BytesObject, Host, HostError, MapObject, TryFromVal, U64Val,
+};
+
+/// The payload field of an oracle attestation holding the time (in seconds,
+/// like the ledger timestamp) at which the attested values were observed.
+pub(crate) const ATTESTATION_TIMESTAMP_FIELD: &str = "timestamp";
+
+impl Host {
+    // Verifies that `signature` is a valid signature of `payload` by
+    // `public_key`, which is either a 32-byte ed25519 key, or a SEC-1 encoded
+    // ECDSA-secp256k1 key signing the SHA-256 digest of the payload.
+    fn verify_attestation_signature(
+        &self,
+        payload: BytesObject,
+        signature: BytesObject,
+        public_key: BytesObject,
+    ) -> Result<(), HostError> {
+        let key_len = self.visit_obj(public_key, |key: &ScBytes| Ok(key.len()))?;
+        if key_len == ed25519_dalek::PUBLIC_KEY_LENGTH {
+            let verifying_key = self.ed25519_pub_key_from_bytesobj_input(public_key)?;
+            let sig = self.ed25519_signature_from_bytesobj_input("signature", signature)?;
+            self.visit_obj(payload, |payload: &ScBytes| {
+                self.verify_sig_ed25519_internal(payload.as_slice(), &verifying_key, &sig)
+            })
+        } else {
+            let pub_key = self.secp256k1_pub_key_from_bytesobj_input(public_key)?;
+            let sig = self.secp256k1_signature_from_bytesobj_input(signature)?;
+            let digest = self.visit_obj(payload, |payload: &ScBytes| {
+                sha256_hash_from_bytes(payload.as_slice(), self.as_budget())
+            })?;
+            self.verify_sig_ecdsa_secp256k1_internal(&pub_key, &digest, &sig)
+        }
+    }
+
+    // Verifies a signed oracle attestation and returns its payload, an XDR
+    // `ScVal::Map` that must have a u64 `timestamp` field no later than the
+    // ledger timestamp and at most `max_age` seconds older than it.
+    pub(crate) fn verify_oracle_attestation_internal(
+        &self,
+        payload: BytesObject,
+        signature: BytesObject,
+        public_key: BytesObject,
+        max_age: u64,
+    ) -> Result<MapObject, HostError> {
+        // The signature is verified first, so that no unauthenticated data is
+        // decoded.
+        self.verify_attestation_signature(payload, signature, public_key)?;
+        let payload: ScVal = self.metered_from_xdr_obj(payload)?;
+        let ScVal::Map(Some(fields)) = &payload else {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "oracle attestation payload must be a map",
+                &[],
+            ));
+        };
+        let timestamp = fields
+            .iter()
+            .find_map(|entry| match (&entry.key, &entry.val) {
+                (ScVal::Symbol(key), ScVal::U64(timestamp))
+                    if key.0.as_slice() == ATTESTATION_TIMESTAMP_FIELD.as_bytes() =>
+                {
+                    Some(*timestamp)
+                }
+                _ => None,
+            });
+        let Some(timestamp) = timestamp else {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "oracle attestation payload must have a u64 timestamp field",
+                &[],
+            ));
+        };
+        let now = self.with_ledger_info_provider(|li| Ok(li.timestamp()))?;
+        if timestamp > now {
+            return Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidInput,
+                "oracle attestation is from the future",
+                &[
+                    U64Val::try_from_val(self, &timestamp)?.to_val(),
+                    U64Val::try_from_val(self, &now)?.to_val(),
+                ],
+            ));
+        }
+        if now - timestamp > max_age {
+            return Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidInput,
+                "oracle attestation is stale",
+                &[
+                    U64Val::try_from_val(self, &timestamp)?.to_val(),
+                    U64Val::try_from_val(self, &now)?.to_val(),
+                ],
+            ));
+        }
+        Ok(MapObject::try_from_val(self, &self.to_host_val(&payload)?)?)
+    }
+}
@@ -3,15 +3,15 @@ use super::{
     metered_clone::MeteredContainer,
 };
 use crate::{
-    budget::Budget,
+    budget::{AsBudget, Budget},
     host::metered_clone::MeteredClone,
     host_object::HostVec,
-    xdr::{ContractCostType, ScBytes},
-    HostError,
+    xdr::{ContractCostType, ScBytes, ScErrorCode, ScErrorType},
+    Host, HostError,
 };
 use rand::{distributions::Uniform, prelude::Distribution, seq::SliceRandom};
 use rand_chacha::{rand_core::SeedableRng, ChaCha20Rng};
-use std::ops::RangeInclusive;
+use std::{collections::VecDeque, ops::RangeInclusive};
 
 /// PRNG subsystem in the host, which provides best-effort pseudo-randomness to
 /// guest contracts using a combination of features that guests cannot easily
@@ -111,22 +111,33 @@ impl Prng {
         v: &HostVec,
         budget: &Budget,
     ) -> Result<HostVec, HostError> {
-        // A Fisher-Yates shuffle essentially does one call to u64_in_range for
-        // each element of the input vector, followed by an optional swap. Since
-        // u64_in_range is itself a rejection sampling operation (to avoid bias)
-        // we can't be 100% sure how many draws it'll make, but the expected
-        // number of draws is 1. To give ourselves a little more safety we'll
-        // double that number. We also give the implementation freedom to draw a
-        // 64-bit (8-byte) value per index, meaning we charge for generating 2 *
-        // 8 * len bytes.
-        let mut v2 = v.metered_clone(budget)?;
-        // We charge for both the PRNG draws and the swaps here (as "memcpys").
-        self.charge_prng_bytes(budget, 16u64.saturating_mul(v.len() as u64))?;
-        budget.charge(ContractCostType::HostMemCpy, Some(v.len() as u64))?;
+        let mut v2 = charge_vec_shuffle(v, budget)?;
         v2.as_mut_slice().shuffle(&mut self.0);
         Ok(v2)
     }
 
+    // Same as `vec_shuffle`, and also returns the permutation that has been
+    // applied to `v`, see `permute_vec`. The shuffle only depends on the
+    // length of the vector, so it's the same as that of `vec_shuffle` for the
+    // same PRNG state, and so are the charges: the permutation is computed
+    // for free, so that recording a transcript doesn't change the cost.
+    pub(crate) fn vec_shuffle_with_permutation(
+        &mut self,
+        v: &HostVec,
+        budget: &Budget,
+    ) -> Result<(HostVec, Vec<u32>), HostError> {
+        let mut v2 = charge_vec_shuffle(v, budget)?;
+        let permutation = budget.with_free_budget(|| {
+            let mut permutation: Vec<u32> = (0..v.len() as u32).collect();
+            permutation.shuffle(&mut self.0);
+            Ok(permutation)
+        })?;
+        if !permute_vec(v, &permutation, &mut v2) {
+            return Err((ScErrorType::Context, ScErrorCode::InternalError).into());
+        }
+        Ok((v2, permutation))
+    }
+
     pub(crate) fn bytes_new(&mut self, size: u32, budget: &Budget) -> Result<ScBytes, HostError> {
         Vec::<u8>::charge_bulk_init_cpy(size as u64, budget)?;
         let mut vec = vec![0u8; size as usize];
@@ -141,3 +152,234 @@ impl Prng {
         Ok(Self(ChaCha20Rng::from_seed(new_seed)))
     }
 }
+
+// Charges for shuffling `v` and returns the copy of `v` to be shuffled.
+fn charge_vec_shuffle(v: &HostVec, budget: &Budget) -> Result<HostVec, HostError> {
+    // A Fisher-Yates shuffle essentially does one call to u64_in_range for
+    // each element of the input vector, followed by an optional swap. Since
+    // u64_in_range is itself a rejection sampling operation (to avoid bias)
+    // we can't be 100% sure how many draws it'll make, but the expected
+    // number of draws is 1. To give ourselves a little more safety we'll
+    // double that number. We also give the implementation freedom to draw a
+    // 64-bit (8-byte) value per index, meaning we charge for generating 2 *
+    // 8 * len bytes.
+    let v2 = v.metered_clone(budget)?;
+    // We charge for both the PRNG draws and the swaps here (as "memcpys").
+    budget.charge(
+        ContractCostType::ChaCha20DrawBytes,
+        Some(16u64.saturating_mul(v.len() as u64)),
+    )?;
+    budget.charge(ContractCostType::HostMemCpy, Some(v.len() as u64))?;
+    Ok(v2)
+}
+
+// Sets the element `i` of `out`, a copy of `v` charged for with
+// `charge_vec_shuffle`, to the element `permutation[i]` of `v`. Returns false
+// if `permutation` isn't a permutation of the indices of `v`.
+// Notes on metering: not metered, this does the same work as the shuffle it
+// stands for.
+fn permute_vec(v: &HostVec, permutation: &[u32], out: &mut HostVec) -> bool {
+    if permutation.len() != v.len() || out.len() != v.len() {
+        return false;
+    }
+    let mut seen = vec![false; v.len()];
+    for (dst, i) in out.as_mut_slice().iter_mut().zip(permutation) {
+        match seen.get_mut(*i as usize) {
+            Some(seen) if !*seen => *seen = true,
+            _ => return false,
+        }
+        *dst = v.as_slice()[*i as usize];
+    }
+    true
+}
+
+/// The output of a PRNG draw made by a contract, see [`PrngTranscript`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PrngDraw {
+    /// The bytes returned by `prng_bytes_new`.
+    Bytes(Vec<u8>),
+    /// The number returned by `prng_u64_in_inclusive_range`.
+    U64(u64),
+    /// The permutation applied by `prng_vec_shuffle`: element `i` of the
+    /// shuffled vector is element `permutation[i]` of the input vector.
+    Shuffle(Vec<u32>),
+}
+
+/// The outputs of the PRNG draws made by contracts, in the order they have
+/// been made, as recorded in [`PrngMode::Recording`] and replayed in
+/// [`PrngMode::Replay`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrngTranscript {
+    pub draws: VecDeque<PrngDraw>,
+}
+
+/// Where the outputs of the `prng_*` host functions come from, see
+/// [`Host::set_prng_mode`].
+///
+/// The modes other than [`PrngMode::Seeded`] are meant for replaying and
+/// verifying invocations in contexts where the base PRNG seed of the original
+/// execution isn't available.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum PrngMode {
+    /// The outputs are drawn from the PRNG seeded with
+    /// [`Host::set_base_prng_seed`].
+    #[default]
+    Seeded,
+    /// Same as [`PrngMode::Seeded`], and additionally the outputs are
+    /// appended to the transcript, see [`Host::prng_transcript`].
+    Recording(PrngTranscript),
+    /// All the `prng_*` host functions fail with `(Context, InvalidAction)`.
+    Unavailable,
+    /// The outputs are taken in order from the transcript instead of being
+    /// drawn, and reseeding has no effect. A draw fails with
+    /// `(Context, MissingValue)` when the transcript is exhausted, and with
+    /// `(Context, InvalidInput)` when the next output in the transcript
+    /// doesn't fit the draw.
+    Replay(PrngTranscript),
+}
+
+impl Host {
+    /// Sets where the outputs of the `prng_*` host functions come from from
+    /// now on.
+    pub fn set_prng_mode(&self, mode: PrngMode) -> Result<(), HostError> {
+        *self.try_borrow_prng_mode_mut()? = mode;
+        Ok(())
+    }
+
+    /// Returns the transcript of the PRNG draws recorded so far in
+    /// [`PrngMode::Recording`], or the rest of the transcript to replay in
+    /// [`PrngMode::Replay`].
+    pub fn prng_transcript(&self) -> Result<Option<PrngTranscript>, HostError> {
+        Ok(match &*self.try_borrow_prng_mode()? {
+            PrngMode::Recording(transcript) | PrngMode::Replay(transcript) => {
+                Some(transcript.clone())
+            }
+            PrngMode::Seeded | PrngMode::Unavailable => None,
+        })
+    }
+
+    // Fails in `PrngMode::Unavailable`, and returns whether the draws are
+    // replayed.
+    fn is_prng_replayed(&self) -> Result<bool, HostError> {
+        match &*self.try_borrow_prng_mode()? {
+            PrngMode::Seeded | PrngMode::Recording(_) => Ok(false),
+            PrngMode::Replay(_) => Ok(true),
+            PrngMode::Unavailable => Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InvalidAction,
+                "PRNG is unavailable",
+                &[],
+            )),
+        }
+    }
+
+    // Fails in `PrngMode::Unavailable`, and returns the next draw of the
+    // transcript in `PrngMode::Replay`.
+    fn next_replayed_prng_draw(&self) -> Result<Option<PrngDraw>, HostError> {
+        if !self.is_prng_replayed()? {
+            return Ok(None);
+        }
+        let draw = match &mut *self.try_borrow_prng_mode_mut()? {
+            PrngMode::Replay(transcript) => transcript.draws.pop_front(),
+            _ => None,
+        };
+        match draw {
+            Some(draw) => Ok(Some(draw)),
+            None => Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::MissingValue,
+                "PRNG transcript is exhausted",
+                &[],
+            )),
+        }
+    }
+
+    fn record_prng_draw(&self, draw: impl FnOnce() -> PrngDraw) -> Result<(), HostError> {
+        if let PrngMode::Recording(transcript) = &mut *self.try_borrow_prng_mode_mut()? {
+            transcript.draws.push_back(draw());
+        }
+        Ok(())
+    }
+
+    fn err_prng_transcript_mismatch(&self) -> HostError {
+        self.err(
+            ScErrorType::Context,
+            ScErrorCode::InvalidInput,
+            "PRNG transcript doesn't match the draw",
+            &[],
+        )
+    }
+
+    // Notes on metering: replayed draws are charged for the objects they
+    // produce, but not for the PRNG itself. Recording isn't metered.
+    pub(crate) fn prng_reseed_internal(&self, seed: Seed) -> Result<(), HostError> {
+        if self.is_prng_replayed()? {
+            return Ok(());
+        }
+        self.with_current_prng(|prng| {
+            *prng = Prng::new_from_seed(seed);
+            Ok(())
+        })
+    }
+
+    pub(crate) fn prng_bytes_new_internal(&self, length: u32) -> Result<ScBytes, HostError> {
+        match self.next_replayed_prng_draw()? {
+            Some(PrngDraw::Bytes(bytes)) if bytes.len() == length as usize => {
+                Vec::<u8>::charge_bulk_init_cpy(length as u64, self)?;
+                Ok(ScBytes::try_from(bytes)?)
+            }
+            Some(_) => Err(self.err_prng_transcript_mismatch()),
+            None => {
+                let bytes =
+                    self.with_current_prng(|prng| prng.bytes_new(length, self.as_budget()))?;
+                self.record_prng_draw(|| PrngDraw::Bytes(bytes.to_vec()))?;
+                Ok(bytes)
+            }
+        }
+    }
+
+    pub(crate) fn prng_u64_in_inclusive_range_internal(
+        &self,
+        lo: u64,
+        hi: u64,
+    ) -> Result<u64, HostError> {
+        match self.next_replayed_prng_draw()? {
+            Some(PrngDraw::U64(u)) if (lo..=hi).contains(&u) => Ok(u),
+            Some(_) => Err(self.err_prng_transcript_mismatch()),
+            None => {
+                let u = self.with_current_prng(|prng| {
+                    prng.u64_in_inclusive_range(lo..=hi, self.as_budget())
+                })?;
+                self.record_prng_draw(|| PrngDraw::U64(u))?;
+                Ok(u)
+            }
+        }
+    }
+
+    pub(crate) fn prng_vec_shuffle_internal(&self, v: &HostVec) -> Result<HostVec, HostError> {
+        // The permutation is only computed when it's recorded, as it costs an
+        // extra allocation.
+        let recording = matches!(&*self.try_borrow_prng_mode()?, PrngMode::Recording(_));
+        match self.next_replayed_prng_draw()? {
+            Some(PrngDraw::Shuffle(permutation)) => {
+                // Replayed shuffles are charged the same as the shuffles they
+                // replay.
+                let mut shuffled = charge_vec_shuffle(v, self.as_budget())?;
+                if permute_vec(v, &permutation, &mut shuffled) {
+                    Ok(shuffled)
+                } else {
+                    Err(self.err_prng_transcript_mismatch())
+                }
+            }
+            Some(_) => Err(self.err_prng_transcript_mismatch()),
+            None if recording => {
+                let (shuffled, permutation) = self.with_current_prng(|prng| {
+                    prng.vec_shuffle_with_permutation(v, self.as_budget())
+                })?;
+                self.record_prng_draw(|| PrngDraw::Shuffle(permutation))?;
+                Ok(shuffled)
+            }
+            None => self.with_current_prng(|prng| prng.vec_shuffle(v, self.as_budget())),
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Rendering of values for diagnostics, see
+//! [`Host::render_val_for_diagnostics`].
+
+use std::fmt::Write;
+
+use crate::{
+    host_object::{handle_to_index, is_relative_object_handle, HostObject},
+    Host, Object, Val,
+};
+
+// Nesting depth past which the elements of containers are elided.
+const MAX_RENDER_DEPTH: u32 = 3;
+// Number of elements of a container rendered before eliding the rest.
+const MAX_RENDERED_ELEMENTS: usize = 8;
+// Number of bytes of a bytes, string or symbol object rendered before
+// eliding the rest.
+const MAX_RENDERED_BYTES: usize = 32;
+// Length in bytes of the rendering past which the rest of the value is
+// elided.
+const MAX_RENDERED_LEN: usize = 256;
+
+const ELISION: &str = "..";
+
+impl Host {
+    /// Renders `v` in a human-readable form for diagnostics, such as the
+    /// arguments of errors. This can't fail and isn't metered, so it's safe
+    /// to use in error paths: the contents of objects are rendered up to a
+    /// fixed depth and length, the rest is elided, and objects that can't be
+    /// accessed are rendered as raw handles.
+    pub fn render_val_for_diagnostics(&self, v: Val) -> String {
+        let mut out = String::new();
+        match self.0.objects.try_borrow() {
+            Ok(objects) => self.render_val(&objects, v, 0, &mut out),
+            Err(_) => {
+                let _ = write!(out, "{:?}", v);
+            }
+        }
+        if out.len() > MAX_RENDERED_LEN {
+            let mut end = MAX_RENDERED_LEN;
+            while !out.is_char_boundary(end) {
+                end -= 1;
+            }
+            out.truncate(end);
+            out.push_str(ELISION);
+        }
+        out
+    }
+
+    // Same as `obj_handle_to_index`, but can't fail. The handle permutation
+    // is ignored if it can't be borrowed.
+    fn obj_handle_to_index_for_render(&self, handle: u32) -> usize {
+        #[cfg(any(test, feature = "testutils"))]
+        if let Ok(perm) = self.0.object_handle_permutation.try_borrow() {
+            if let Some(perm) = *perm {
+                return perm.unpermute(handle_to_index(handle) as u32) as usize;
+            }
+        }
+        handle_to_index(handle)
+    }
+
+    fn render_val(&self, objects: &[HostObject], v: Val, depth: u32, out: &mut String) {
+        if out.len() > MAX_RENDERED_LEN {
+            return;
+        }
+        let obj = match Object::try_from(v) {
+            Ok(obj) if !is_relative_object_handle(obj.get_handle()) => obj,
+            _ => {
+                let _ = write!(out, "{:?}", v);
+                return;
+            }
+        };
+        let index = self.obj_handle_to_index_for_render(obj.get_handle());
+        let Some(hobj) = objects.get(index) else {
+            let _ = write!(out, "{:?}", v);
+            return;
+        };
+        let _ = match hobj {
+            HostObject::Vec(vec) => {
+                self.render_elements('[', ']', vec.iter().copied(), depth, out, |h, v, o| {
+                    h.render_val(objects, v, depth + 1, o)
+                });
+                Ok(())
+            }
+            HostObject::Map(map) => {
+                let entries = map.map.iter().copied();
+                self.render_elements('{', '}', entries, depth, out, |h, (k, v), o| {
+                    h.render_val(objects, k, depth + 1, o);
+                    o.push_str(": ");
+                    h.render_val(objects, v, depth + 1, o);
+                });
+                Ok(())
+            }
+            HostObject::U64(u) => write!(out, "U64({})", u),
+            HostObject::I64(i) => write!(out, "I64({})", i),
+            HostObject::TimePoint(t) => write!(out, "Timepoint({})", t.0),
+            HostObject::Duration(d) => write!(out, "Duration({})", d.0),
+            HostObject::U128(u) => write!(out, "U128({})", u),
+            HostObject::I128(i) => write!(out, "I128({})", i),
+            HostObject::U256(u) => write!(out, "U256({})", u),
+            HostObject::I256(i) => write!(out, "I256({})", i),
+            HostObject::Bytes(b) => {
+                let _ = write!(out, "Bytes(");
+                for byte in b.iter().take(MAX_RENDERED_BYTES) {
+                    let _ = write!(out, "{:02x}", byte);
+                }
+                if b.len() > MAX_RENDERED_BYTES {
+                    out.push_str(ELISION);
+                }
+                write!(out, ")")
+            }
+            HostObject::String(s) => Self::render_str(s.as_slice(), "\"", "\"", out),
+            HostObject::Symbol(s) => Self::render_str(s.as_slice(), "Symbol(", ")", out),
+            HostObject::Address(a) => write!(out, "{:?}", a),
+        };
+    }
+
+    fn render_elements<T>(
+        &self,
+        open: char,
+        close: char,
+        elements: impl ExactSizeIterator<Item = T>,
+        depth: u32,
+        out: &mut String,
+        render: impl Fn(&Host, T, &mut String),
+    ) {
+        out.push(open);
+        let len = elements.len();
+        if depth >= MAX_RENDER_DEPTH && len > 0 {
+            out.push_str(ELISION);
+        } else {
+            for (i, element) in elements.take(MAX_RENDERED_ELEMENTS).enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                render(self, element, out);
+                if out.len() > MAX_RENDERED_LEN {
+                    break;
+                }
+            }
+            if len > MAX_RENDERED_ELEMENTS {
+                out.push_str(", ");
+                out.push_str(ELISION);
+            }
+        }
+        out.push(close);
+    }
+
+    fn render_str(bytes: &[u8], open: &str, close: &str, out: &mut String) -> std::fmt::Result {
+        out.push_str(open);
+        let shown = &bytes[..bytes.len().min(MAX_RENDERED_BYTES)];
+        out.push_str(&String::from_utf8_lossy(shown));
+        if bytes.len() > MAX_RENDERED_BYTES {
+            out.push_str(ELISION);
+        }
+        write!(out, "{}", close)
+    }
+}
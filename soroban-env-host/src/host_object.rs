@@ -2,13 +2,16 @@
 
 use soroban_env_common::{
     xdr::{ContractCostType, ScErrorCode, ScErrorType},
-    Compare, DurationSmall, I128Small, I256Small, I64Small, SymbolSmall, SymbolStr, Tag,
+    Compare, DurationSmall, I128Small, I256Small, I64Small, Symbol, SymbolSmall, SymbolStr, Tag,
     TimepointSmall, TryFromVal, U128Small, U256Small, U64Small,
 };
 
 use crate::{
-    budget::Budget,
-    host::metered_clone::{self, MeteredClone},
+    budget::{AsBudget, Budget},
+    host::{
+        metered_clone::{self, MeteredClone},
+        SYMBOL_INTERNING_MIN_PROTOCOL,
+    },
     HostError,
 };
 
@@ -21,6 +24,31 @@ use super::{
     U64Object, Val, VecObject,
 };
 
+/// Maximum sizes of the host objects, see [`Host::set_object_size_limits`].
+///
+/// Creating a larger object fails with an `(Object, ExceededLimit)` error,
+/// which is distinct from the `(Budget, ExceededLimit)` error of running out
+/// of memory budget.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ObjectSizeLimits {
+    /// Maximum number of elements of a vector.
+    pub max_vec_len: u32,
+    /// Maximum number of entries of a map.
+    pub max_map_len: u32,
+    /// Maximum length in bytes of a bytes or string object.
+    pub max_bytes_len: u32,
+}
+
+impl Default for ObjectSizeLimits {
+    fn default() -> Self {
+        Self {
+            max_vec_len: u32::MAX,
+            max_map_len: u32::MAX,
+            max_bytes_len: u32::MAX,
+        }
+    }
+}
+
 pub(crate) type HostMap = MeteredOrdMap<Val, Val, Host>;
 pub(crate) type HostVec = MeteredVector<Val>;
 
@@ -132,6 +160,11 @@ pub trait HostObjectType: MeteredClone {
     fn new_from_handle(handle: u32) -> Self::Wrapper;
     fn inject(self) -> HostObject;
     fn try_extract(obj: &HostObject) -> Option<&Self>;
+    /// Returns the name of objects of this type in errors and the limit of
+    /// their length, if they have one in [`ObjectSizeLimits`].
+    fn size_limit(_limits: &ObjectSizeLimits) -> Option<(&'static str, u32)> {
+        None
+    }
 }
 
 // Some host objects are "a slab of memory" which we want
@@ -143,7 +176,7 @@ pub(crate) trait MemHostObjectType:
 }
 
 macro_rules! declare_host_object_type {
-    ($TY:ty, $TAG:ident, $CASE:ident) => {
+    ($TY:ty, $TAG:ident, $CASE:ident $(, $NAME:literal, $LIMIT:ident)?) => {
         impl HostObjectType for $TY {
             type Wrapper = $TAG;
             fn new_from_handle(handle: u32) -> Self::Wrapper {
@@ -159,13 +192,19 @@ macro_rules! declare_host_object_type {
                     _ => None,
                 }
             }
+
+            $(
+                fn size_limit(limits: &ObjectSizeLimits) -> Option<(&'static str, u32)> {
+                    Some(($NAME, limits.$LIMIT))
+                }
+            )?
         }
     };
 }
 
 macro_rules! declare_mem_host_object_type {
-    ($TY:ty, $TAG:ident, $CASE:ident) => {
-        declare_host_object_type!($TY, $TAG, $CASE);
+    ($TY:ty, $TAG:ident, $CASE:ident $(, $NAME:literal, $LIMIT:ident)?) => {
+        declare_host_object_type!($TY, $TAG, $CASE $(, $NAME, $LIMIT)?);
         impl MemHostObjectType for $TY {
             fn as_byte_slice(&self) -> &[u8] {
                 self.as_slice()
@@ -175,8 +214,8 @@ macro_rules! declare_mem_host_object_type {
 }
 
 // ${type of contained data}, ${object-wrapper common type}, ${case in HostObject}
-declare_host_object_type!(HostMap, MapObject, Map);
-declare_host_object_type!(HostVec, VecObject, Vec);
+declare_host_object_type!(HostMap, MapObject, Map, "map", max_map_len);
+declare_host_object_type!(HostVec, VecObject, Vec, "vector", max_vec_len);
 declare_host_object_type!(u64, U64Object, U64);
 declare_host_object_type!(i64, I64Object, I64);
 declare_host_object_type!(xdr::TimePoint, TimepointObject, TimePoint);
@@ -185,8 +224,8 @@ declare_host_object_type!(u128, U128Object, U128);
 declare_host_object_type!(i128, I128Object, I128);
 declare_host_object_type!(U256, U256Object, U256);
 declare_host_object_type!(I256, I256Object, I256);
-declare_mem_host_object_type!(xdr::ScBytes, BytesObject, Bytes);
-declare_mem_host_object_type!(xdr::ScString, StringObject, String);
+declare_mem_host_object_type!(xdr::ScBytes, BytesObject, Bytes, "bytes", max_bytes_len);
+declare_mem_host_object_type!(xdr::ScString, StringObject, String, "string", max_bytes_len);
 declare_mem_host_object_type!(xdr::ScSymbol, SymbolObject, Symbol);
 declare_host_object_type!(xdr::ScAddress, AddressObject, Address);
 
@@ -242,12 +281,109 @@ pub fn index_to_handle(host: &Host, index: usize, relative: bool) -> Result<u32,
     Err(host.err_arith_overflow())
 }
 
+// Object table indices are shifted left by one bit to form handles, so only
+// 31 bits are available for them.
+#[cfg(any(test, feature = "testutils"))]
+const INDEX_MASK: u32 = u32::MAX >> 1;
+
+/// Deterministic bijection on object table indices, used to assign object
+/// handles in a permuted order when handle randomization is enabled (see
+/// [`Host::enable_object_handle_randomization`]). Maps `i` to
+/// `(i * mul + add) mod 2^31`, which is invertible since `mul` is odd.
+#[cfg(any(test, feature = "testutils"))]
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct HandlePermutation {
+    mul: u32,
+    mul_inv: u32,
+    add: u32,
+}
+
+#[cfg(any(test, feature = "testutils"))]
+impl HandlePermutation {
+    fn from_seed(seed: u64) -> Self {
+        let mul = (seed as u32) | 1;
+        let add = ((seed >> 32) as u32) & INDEX_MASK;
+        // Newton's iteration for the inverse modulo 2^32, each step doubles
+        // the number of correct low bits (starting with 3).
+        let mut mul_inv = mul;
+        for _ in 0..4 {
+            mul_inv = mul_inv.wrapping_mul(2_u32.wrapping_sub(mul.wrapping_mul(mul_inv)));
+        }
+        Self { mul, mul_inv, add }
+    }
+
+    fn permute(&self, index: u32) -> u32 {
+        index.wrapping_mul(self.mul).wrapping_add(self.add) & INDEX_MASK
+    }
+
+    pub(crate) fn unpermute(&self, permuted: u32) -> u32 {
+        permuted.wrapping_sub(self.add).wrapping_mul(self.mul_inv) & INDEX_MASK
+    }
+}
+
 impl Host {
+    /// Makes the host assign object handles in an order permuted by `seed`
+    /// instead of sequentially, both for the absolute handles used by the
+    /// host and the relative handles seen by Wasm contracts. This is meant
+    /// for catching code that relies on the ordering or contiguity of object
+    /// handles. Has to be enabled before any object is created.
+    #[cfg(any(test, feature = "testutils"))]
+    pub fn enable_object_handle_randomization(&self, seed: u64) -> Result<(), HostError> {
+        if !self.try_borrow_objects()?.is_empty() {
+            return Err(self.err(
+                ScErrorType::Context,
+                ScErrorCode::InternalError,
+                "handle randomization enabled after objects have been created",
+                &[],
+            ));
+        }
+        *self.try_borrow_object_handle_permutation_mut()? =
+            Some(HandlePermutation::from_seed(seed));
+        Ok(())
+    }
+
+    // Converts an object table index into a handle, applying the handle
+    // permutation if there is one.
+    pub(crate) fn index_to_obj_handle(
+        &self,
+        index: usize,
+        relative: bool,
+    ) -> Result<u32, HostError> {
+        #[cfg(any(test, feature = "testutils"))]
+        if let Some(perm) = *self.try_borrow_object_handle_permutation()? {
+            let Some(index) = u32::try_from(index).ok().filter(|i| *i <= INDEX_MASK) else {
+                return Err(self.err_arith_overflow());
+            };
+            let permuted = perm.permute(index);
+            // Integrity check: the permutation must be invertible for lookups
+            // to find the object again.
+            if perm.unpermute(permuted) != index {
+                return Err(self.err(
+                    ScErrorType::Object,
+                    ScErrorCode::InternalError,
+                    "object handle permutation is not invertible",
+                    &[],
+                ));
+            }
+            return index_to_handle(self, permuted as usize, relative);
+        }
+        index_to_handle(self, index, relative)
+    }
+
+    // Inverse of `index_to_obj_handle`.
+    pub(crate) fn obj_handle_to_index(&self, handle: u32) -> Result<usize, HostError> {
+        #[cfg(any(test, feature = "testutils"))]
+        if let Some(perm) = *self.try_borrow_object_handle_permutation()? {
+            return Ok(perm.unpermute(handle_to_index(handle) as u32) as usize);
+        }
+        Ok(handle_to_index(handle))
+    }
+
     pub(crate) fn relative_to_absolute(&self, val: Val) -> Result<Val, HostError> {
         if let Ok(obj) = Object::try_from(val) {
             let handle = obj.get_handle();
             return if is_relative_object_handle(handle) {
-                let index = handle_to_index(handle);
+                let index = self.obj_handle_to_index(handle)?;
                 let abs_opt = self.with_current_frame_relative_object_table(|table| {
                     Ok(table.get(index).map(|x| *x))
                 })?;
@@ -317,13 +453,49 @@ impl Host {
                     table.push(obj);
                     Ok(index)
                 })?;
-                let handle = index_to_handle(self, index, true)?;
+                let handle = self.index_to_obj_handle(index, true)?;
                 Ok(Object::from_handle_and_tag(handle, val.get_tag()).into())
             };
         }
         Ok(val)
     }
 
+    /// Returns the object of the symbol `sym`, reusing the object of an equal
+    /// symbol created before, if any. Symbol objects are immutable, so
+    /// sharing them is only observable through their handles being equal.
+    ///
+    /// This is only enabled from protocol [`SYMBOL_INTERNING_MIN_PROTOCOL`]
+    /// on; before that every symbol gets a new object.
+    // Notes on metering: the lookup is charged as a binary search over the
+    // interning table, comparing the symbol bytes at every step. The table
+    // entry, which holds a copy of the symbol, is charged along with the new
+    // object.
+    pub(crate) fn intern_symbol(&self, sym: xdr::ScSymbol) -> Result<SymbolObject, HostError> {
+        if self.try_borrow_ledger()?.is_none()
+            || self.get_ledger_protocol_version()? < SYMBOL_INTERNING_MIN_PROTOCOL
+        {
+            return self.add_host_object(sym);
+        }
+        let num_interned = self.try_borrow_symbol_objects()?.len() as u64;
+        let steps = 1 + (64 - num_interned.leading_zeros()) as u64;
+        self.as_budget()
+            .bulk_charge(ContractCostType::MapEntry, steps, None)?;
+        self.as_budget().bulk_charge(
+            ContractCostType::HostMemCmp,
+            steps,
+            Some(sym.len() as u64),
+        )?;
+        let interned = self.try_borrow_symbol_objects()?.get(&sym).copied();
+        if let Some(obj) = interned {
+            return Ok(obj);
+        }
+        metered_clone::charge_heap_alloc::<(xdr::ScSymbol, SymbolObject)>(1, self)?;
+        let key = sym.metered_clone(self)?;
+        let obj = self.add_host_object(sym)?;
+        self.try_borrow_symbol_objects_mut()?.insert(key, obj);
+        Ok(obj)
+    }
+
     /// Moves a value of some type implementing [`HostObjectType`] into the host's
     /// object array, returning a [`HostObj`] containing the new object's array
     /// index, tagged with the [`xdr::ScObjectType`].
@@ -333,14 +505,56 @@ impl Host {
     ) -> Result<HOT::Wrapper, HostError> {
         let _span = tracy_span!("add host object");
         let index = self.try_borrow_objects()?.len();
-        let handle = index_to_handle(self, index, false)?;
+        let handle = self.index_to_obj_handle(index, false)?;
         // charge for the new host object, which is just the amortized cost of a single
         // `HostObject` allocation
         metered_clone::charge_heap_alloc::<HostObject>(1, self)?;
-        self.try_borrow_objects_mut()?.push(HOT::inject(hot));
+        let obj = HOT::inject(hot);
+        self.check_object_size_limits(&obj)?;
+        self.try_borrow_objects_mut()?.push(obj);
+        self.record_host_object_created()?;
+        #[cfg(any(test, feature = "testutils"))]
+        self.record_object_allocation(index)?;
         Ok(HOT::new_from_handle(handle))
     }
 
+    /// Fails with `(Object, ExceededLimit)` if an object of type `HOT` of
+    /// length `len` would exceed the [`ObjectSizeLimits`]. Constructors call
+    /// this before allocating the object.
+    pub(crate) fn check_object_len<HOT: HostObjectType>(
+        &self,
+        len: usize,
+    ) -> Result<(), HostError> {
+        let Some((name, limit)) = HOT::size_limit(&self.object_size_limits()?) else {
+            return Ok(());
+        };
+        if len > limit as usize {
+            return Err(self.err(
+                ScErrorType::Object,
+                ScErrorCode::ExceededLimit,
+                "object exceeds the size limit",
+                &[
+                    Symbol::try_from_val(self, &name)?.to_val(),
+                    Val::from_u32(u32::try_from(len).unwrap_or(u32::MAX)).to_val(),
+                    Val::from_u32(limit).to_val(),
+                ],
+            ));
+        }
+        Ok(())
+    }
+
+    // Constructors check the limits before allocating, this also catches the
+    // objects derived from other objects in any other way.
+    fn check_object_size_limits(&self, obj: &HostObject) -> Result<(), HostError> {
+        match obj {
+            HostObject::Vec(v) => self.check_object_len::<HostVec>(v.len()),
+            HostObject::Map(m) => self.check_object_len::<HostMap>(m.len()),
+            HostObject::Bytes(b) => self.check_object_len::<xdr::ScBytes>(b.len()),
+            HostObject::String(s) => self.check_object_len::<xdr::ScString>(s.len()),
+            _ => Ok(()),
+        }
+    }
+
     pub(crate) fn visit_obj_untyped<F, U>(
         &self,
         obj: impl Into<Object>,
@@ -356,9 +570,10 @@ impl Host {
         // into the host object buffer, it is ubiquitous and therefore we charge
         // budget here for safety / future proofing.
         self.charge_budget(ContractCostType::VisitObject, None)?;
-        let r = self.try_borrow_objects()?;
         let obj: Object = obj.into();
         let handle: u32 = obj.get_handle();
+        let index = self.obj_handle_to_index(handle)?;
+        let r = self.try_borrow_objects()?;
         if is_relative_object_handle(handle) {
             Err(self.err(
                 ScErrorType::Context,
@@ -366,7 +581,7 @@ impl Host {
                 "looking up relative object",
                 &[Val::from_u32(handle).to_val()],
             ))
-        } else if let Some(obj) = r.get(handle_to_index(handle)) {
+        } else if let Some(obj) = r.get(index) {
             f(obj)
         } else {
             // Discard the broken object here instead of including
@@ -14,6 +14,11 @@
 //! `"testutils"` feature, which enables an interface on [Host] for registering
 //! other test contracts by ID.
 //!
+//! The VM itself is enabled by the default `"vm"` feature. Without it, Wasm
+//! contracts can neither be uploaded nor invoked, while frames, storage,
+//! authorization, the budget and native and test contracts all work as usual,
+//! which suits lightweight embedders that never execute Wasm.
+//!
 //! The [Host] type provides some facilities above and beyond just the [Env]
 //! trait, including:
 //!
@@ -45,29 +50,55 @@ macro_rules! tracy_span {
 
 pub mod budget;
 pub mod events;
-pub use events::diagnostic::DiagnosticLevel;
+pub use events::{
+    diagnostic::DiagnosticLevel, ContractEventLimits, EventFilter, EventLimitPolicy,
+};
 mod host;
 pub(crate) mod host_object;
+pub use host_object::ObjectSizeLimits;
 
 mod native_contract;
+pub use native_contract::token::{AssetOrContract, TokenClient, TokenMetadataInfo};
+pub use native_contract::smart_wallet::SMART_WALLET_EXECUTABLE_HASH;
+pub use native_contract::nft::NFT_EXECUTABLE_HASH;
 
 pub mod auth;
+#[cfg(feature = "vm")]
 pub mod vm;
+#[cfg(not(feature = "vm"))]
+mod no_vm;
+#[cfg(not(feature = "vm"))]
+pub(crate) use no_vm as vm;
 pub use vm::Vm;
-#[cfg(any(test, feature = "testutils"))]
+#[cfg(all(any(test, feature = "testutils"), feature = "vm"))]
 pub mod cost_runner;
 pub mod storage;
 #[cfg(test)]
 mod test;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
 
 #[cfg(any(test, feature = "testutils"))]
 #[doc(hidden)]
 pub use host::testutils::call_with_suppressed_panic_hook;
 #[cfg(any(test, feature = "testutils"))]
-pub use host::ContractFunctionSet;
+pub use native_contract::harness::NativeContractHarness;
+#[cfg(any(test, feature = "testutils"))]
+pub use host::{
+    ContractFunctionSet, HostFnInterposer, LeakedObject, ObjectAllocationSite, ObjectComparator,
+    INTERPOSABLE_HOST_FNS,
+};
+#[cfg(any(test, feature = "differential"))]
+pub use host::{
+    run_differential, DifferentialReport, FuelConsumption, InvocationOutcome, VmConfig,
+};
 pub use host::{
-    metered_map::MeteredOrdMap, metered_vector::MeteredVector, Host, HostError, LedgerInfo, Seed,
-    DEFAULT_HOST_DEPTH_LIMIT, SEED_BYTES,
+    metered_map::MeteredOrdMap, metered_vector::MeteredVector, AmountRounding, ContainerCopyStats,
+    ErrorCause, ErrorContext, ExecutionStats, FeeRefund, FinishedHostState, Host, HostBuilder,
+    HostError, HostFunctionOp, HostFunctionOpResult, LedgerInfo, LedgerInfoProvider, MetricsSink,
+    PrngDraw, PrngMode, PrngTranscript, ScValLimitExceeded, ScValLimits, Seed, StorageOp,
+    XdrReadLimits, CANONICAL_ENCODING_PREFIX, CLASSIC_ASSET_DECIMALS, DEFAULT_HOST_DEPTH_LIMIT,
+    SEED_BYTES,
 };
 pub use soroban_env_common::*;
 
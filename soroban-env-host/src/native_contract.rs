@@ -1,11 +1,14 @@
+pub(crate) mod access_control;
 pub(crate) mod base_types;
 pub(crate) mod common_types;
 pub(crate) mod contract_error;
 pub(crate) mod invoker_contract_auth;
+pub(crate) mod migrations;
 pub(crate) mod storage_utils;
 pub(crate) mod token;
 
-use crate::host::{Host, HostError};
+use crate::host::{Host, HostError, NFT_MIN_PROTOCOL, SMART_WALLET_MIN_PROTOCOL};
+use crate::xdr::Hash;
 use soroban_env_common::{Symbol, Val};
 
 pub trait NativeContract {
@@ -15,6 +18,39 @@ pub trait NativeContract {
 pub use token::Token;
 
 pub(crate) mod account_contract;
+pub(crate) mod nft;
+pub(crate) mod smart_wallet;
 
+/// The built-in contracts that are instantiated like Wasm contracts, with a
+/// reserved Wasm hash that has no Wasm behind it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum BuiltinWasmContract {
+    SmartWallet,
+    Nft,
+}
+
+impl Host {
+    /// Returns the built-in contract that `wasm_hash` is reserved for, if it's
+    /// enabled at the current ledger protocol.
+    pub(crate) fn builtin_wasm_contract(
+        &self,
+        wasm_hash: &Hash,
+    ) -> Result<Option<BuiltinWasmContract>, HostError> {
+        if wasm_hash.0 == smart_wallet::SMART_WALLET_EXECUTABLE_HASH
+            && self.get_ledger_protocol_version()? >= SMART_WALLET_MIN_PROTOCOL
+        {
+            return Ok(Some(BuiltinWasmContract::SmartWallet));
+        }
+        if wasm_hash.0 == nft::NFT_EXECUTABLE_HASH
+            && self.get_ledger_protocol_version()? >= NFT_MIN_PROTOCOL
+        {
+            return Ok(Some(BuiltinWasmContract::Nft));
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(any(test, feature = "testutils"))]
+pub(crate) mod harness;
 #[cfg(test)]
 pub(crate) mod testutils;
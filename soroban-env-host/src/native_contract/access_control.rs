@@ -0,0 +1,204 @@
+//! Access control building blocks for native contracts, mirroring the
+//! conventions of the SDK's access control libraries:
+//!
+//! - [`Ownable`] stores the owner (e.g. the admin) of a contract and checks
+//!   that it has authorized the current invocation.
+//! - [`OwnershipTransfer`] stores an ownership transfer proposed by the owner
+//!   until the proposed owner accepts it, so that ownership can't be lost to
+//!   a mistyped address.
+//! - [`Pausable`] stores an optional pause switch that the contract checks
+//!   before its pausable operations.
+//!
+//! Both are parameterized by their instance storage key, so that contracts
+//! can keep their existing storage layout. Contracts publish their own events
+//! for ownership and pause changes, as the topics are contract-specific.
+
+use crate::host::Host;
+use crate::native_contract::base_types::Address;
+use crate::native_contract::contract_error::ContractError;
+use crate::native_contract::storage_utils::StorageUtils;
+use crate::{err, HostError};
+use soroban_env_common::{Env, StorageType, TryFromVal, TryIntoVal, Val};
+use soroban_native_sdk_macros::contracttype;
+
+/// The owner of a contract, stored in the instance storage under `key`.
+pub(crate) struct Ownable {
+    key: Val,
+}
+
+impl Ownable {
+    pub(crate) fn new(key: Val) -> Self {
+        Self { key }
+    }
+
+    // Metering: covered by components
+    pub(crate) fn read_owner(&self, e: &Host) -> Result<Address, HostError> {
+        let owner = e.get_contract_data(self.key, StorageType::Instance)?;
+        owner.try_into_val(e)
+    }
+
+    // Metering: covered by components
+    pub(crate) fn write_owner(&self, e: &Host, owner: Address) -> Result<(), HostError> {
+        e.put_contract_data(self.key, owner.try_into_val(e)?, StorageType::Instance)?;
+        Ok(())
+    }
+
+    /// Requires the authorization of the owner for the current invocation and
+    /// returns the owner.
+    // Metering: covered by components
+    pub(crate) fn require_owner(&self, e: &Host) -> Result<Address, HostError> {
+        let owner = self.read_owner(e)?;
+        owner.require_auth()?;
+        Ok(owner)
+    }
+}
+
+/// An ownership transfer proposed by the owner, which the proposed owner has
+/// to accept before `expiration_ledger`.
+#[contracttype]
+pub struct PendingOwner {
+    pub owner: Address,
+    pub expiration_ledger: u32,
+}
+
+/// A pending ownership transfer stored in the instance storage under `key`.
+/// Like the pause switch, it's only stored while a transfer is pending.
+pub(crate) struct OwnershipTransfer {
+    key: Val,
+}
+
+impl OwnershipTransfer {
+    pub(crate) fn new(key: Val) -> Self {
+        Self { key }
+    }
+
+    // Metering: covered by components
+    pub(crate) fn read_pending(&self, e: &Host) -> Result<Option<PendingOwner>, HostError> {
+        match StorageUtils::try_get(e, self.key, StorageType::Instance)? {
+            Some(pending) => Ok(Some(pending.try_into_val(e)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Proposes `owner` as the new owner until `expiration_ledger` (inclusive),
+    /// replacing any pending proposal. The caller is responsible for checking
+    /// that this is authorized, e.g. with [`Ownable::require_owner`].
+    // Metering: covered by components
+    pub(crate) fn propose(
+        &self,
+        e: &Host,
+        owner: Address,
+        expiration_ledger: u32,
+    ) -> Result<(), HostError> {
+        let ledger_seq: u32 = e.get_ledger_sequence()?.into();
+        if expiration_ledger < ledger_seq {
+            return Err(err!(
+                e,
+                ContractError::OwnershipTransferError,
+                "expiration must be >= ledger sequence",
+                expiration_ledger,
+                ledger_seq
+            ));
+        }
+        let max_expiration = e.max_expiration_ledger()?;
+        if expiration_ledger > max_expiration {
+            return Err(err!(
+                e,
+                ContractError::OwnershipTransferError,
+                "expiration is greater than max",
+                expiration_ledger,
+                max_expiration
+            ));
+        }
+        let pending = PendingOwner {
+            owner,
+            expiration_ledger,
+        };
+        e.put_contract_data(self.key, pending.try_into_val(e)?, StorageType::Instance)?;
+        Ok(())
+    }
+
+    /// Requires the authorization of the proposed owner for the current
+    /// invocation, removes the proposal and returns the proposed owner. Fails
+    /// with [`ContractError::OwnershipTransferError`] if there is no proposal
+    /// or it has expired. The caller is responsible for writing the new owner.
+    // Metering: covered by components
+    pub(crate) fn accept(&self, e: &Host) -> Result<Address, HostError> {
+        let Some(pending) = self.read_pending(e)? else {
+            return Err(e.error(
+                ContractError::OwnershipTransferError.into(),
+                "no pending ownership transfer",
+                &[],
+            ));
+        };
+        let ledger_seq: u32 = e.get_ledger_sequence()?.into();
+        if pending.expiration_ledger < ledger_seq {
+            return Err(err!(
+                e,
+                ContractError::OwnershipTransferError,
+                "pending ownership transfer has expired",
+                pending.expiration_ledger,
+                ledger_seq
+            ));
+        }
+        pending.owner.require_auth()?;
+        e.del_contract_data(self.key, StorageType::Instance)?;
+        Ok(pending.owner)
+    }
+
+    /// Removes the pending proposal, if any.
+    // Metering: covered by components
+    pub(crate) fn clear(&self, e: &Host) -> Result<(), HostError> {
+        if e.has_contract_data(self.key, StorageType::Instance)?.into() {
+            e.del_contract_data(self.key, StorageType::Instance)?;
+        }
+        Ok(())
+    }
+}
+
+/// A pause switch stored in the instance storage under `key`. The switch is
+/// only stored while the contract is paused, so contracts that are never
+/// paused don't pay for it.
+pub(crate) struct Pausable {
+    key: Val,
+}
+
+impl Pausable {
+    pub(crate) fn new(key: Val) -> Self {
+        Self { key }
+    }
+
+    // Metering: covered by components
+    pub(crate) fn is_paused(&self, e: &Host) -> Result<bool, HostError> {
+        match StorageUtils::try_get(e, self.key, StorageType::Instance)? {
+            Some(paused) => Ok(bool::try_from_val(e, &paused)?),
+            None => Ok(false),
+        }
+    }
+
+    /// Fails with [`ContractError::ContractPausedError`] if the contract is
+    /// paused.
+    // Metering: covered by components
+    pub(crate) fn require_not_paused(&self, e: &Host) -> Result<(), HostError> {
+        if self.is_paused(e)? {
+            return Err(e.error(
+                ContractError::ContractPausedError.into(),
+                "contract is paused",
+                &[],
+            ));
+        }
+        Ok(())
+    }
+
+    /// Pauses or unpauses the contract. The caller is responsible for
+    /// checking that this is authorized, e.g. with [`Ownable::require_owner`].
+    // Metering: covered by components
+    pub(crate) fn set_paused(&self, e: &Host, paused: bool) -> Result<(), HostError> {
+        if paused {
+            e.put_contract_data(self.key, paused.into(), StorageType::Instance)?;
+        } else if self.is_paused(e)? {
+            e.del_contract_data(self.key, StorageType::Instance)?;
+        }
+        Ok(())
+    }
+}
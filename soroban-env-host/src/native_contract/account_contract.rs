@@ -3,7 +3,7 @@ use crate::auth::{AuthorizedFunction, AuthorizedInvocation};
 // it doesn't need to be directly invoked. But semantically this is analagous
 // to a generic smart wallet contract that supports authentication and blanket
 // context authorization.
-use crate::host::{frame::ContractReentryMode, Host};
+use crate::host::{frame::ContractReentryMode, Host, ACCOUNT_MIN_ONE_SIGNATURE_MIN_PROTOCOL};
 use crate::native_contract::{base_types::BytesN, contract_error::ContractError};
 use crate::{err, HostError};
 use core::cmp::Ordering;
@@ -41,7 +41,7 @@ pub struct CreateContractHostFnContext {
 
 #[derive(Clone)]
 #[contracttype]
-enum AuthorizationContext {
+pub(crate) enum AuthorizationContext {
     Contract(ContractAuthorizationContext),
     CreateContractHostFn(CreateContractHostFnContext),
 }
@@ -57,7 +57,7 @@ impl AuthorizationContext {
     fn from_authorized_fn(host: &Host, function: &AuthorizedFunction) -> Result<Self, HostError> {
         match function {
             AuthorizedFunction::ContractFn(contract_fn) => {
-                let args = HostVec::try_from_val(host, &contract_fn.args)?;
+                let args = HostVec::try_from_val(host, &*contract_fn.args)?;
                 Ok(AuthorizationContext::Contract(
                     ContractAuthorizationContext {
                         contract: contract_fn.contract_address.try_into_val(host)?,
@@ -202,14 +202,19 @@ pub(crate) fn check_account_authentication(
         weight += signer_weight as u32;
         prev_pk = Some(sig.public_key);
     }
-    let threshold = account.thresholds.0[ThresholdIndexes::Med as usize];
-    if weight < threshold as u32 {
+    let mut threshold = account.thresholds.0[ThresholdIndexes::Med as usize] as u32;
+    // Like in stellar-core, at least one valid signature is required, even
+    // when the medium threshold is 0.
+    if host.get_ledger_protocol_version()? >= ACCOUNT_MIN_ONE_SIGNATURE_MIN_PROTOCOL {
+        threshold = threshold.max(1);
+    }
+    if weight < threshold {
         Err(err!(
             host,
             ContractError::AuthenticationError,
             "signature weight is lower than threshold",
             weight,
-            threshold as u32
+            threshold
         ))
     } else {
         Ok(())
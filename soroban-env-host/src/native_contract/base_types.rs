@@ -1,11 +1,13 @@
 use crate::host::metered_clone::MeteredClone;
 use crate::host::{Host, HostError};
+use crate::native_contract::storage_utils::StorageUtils;
 
 use core::cmp::Ordering;
+use core::marker::PhantomData;
 use soroban_env_common::xdr::{AccountId, ScAddress, ScErrorCode, ScErrorType};
 use soroban_env_common::{
-    AddressObject, BytesObject, Compare, ConversionError, Env, EnvBase, MapObject, StringObject,
-    TryFromVal, Val, VecObject,
+    AddressObject, BytesObject, Compare, ConversionError, Env, EnvBase, MapObject, StorageType,
+    StringObject, Symbol, TryFromVal, Val, VecObject,
 };
 
 #[derive(Clone)]
@@ -541,3 +543,122 @@ impl Address {
         self.object
     }
 }
+
+/// Expiration bump applied to the entries of a [`StorageMap`] whenever they
+/// are written or explicitly bumped.
+#[derive(Clone, Copy)]
+pub struct BumpPolicy {
+    pub low_expiration_watermark: u32,
+    pub high_expiration_watermark: u32,
+}
+
+/// Typed wrapper over the contract data entries of the current contract that
+/// have the same storage type and whose keys share a common symbol prefix.
+///
+/// The entry for key `k` is stored under the key `Vec[prefix, k]`, which is
+/// the same encoding as the one used by a `#[contracttype]` enum variant named
+/// `prefix` that holds `k`.
+pub struct StorageMap<K, V> {
+    host: Host,
+    prefix: &'static str,
+    storage_type: StorageType,
+    bump_policy: Option<BumpPolicy>,
+    _types: PhantomData<(K, V)>,
+}
+
+impl<K, V> StorageMap<K, V>
+where
+    Val: TryFromVal<Host, K> + TryFromVal<Host, V>,
+    V: TryFromVal<Host, Val>,
+    HostError: From<<Val as TryFromVal<Host, K>>::Error>
+        + From<<Val as TryFromVal<Host, V>>::Error>
+        + From<<V as TryFromVal<Host, Val>>::Error>,
+{
+    pub fn new(
+        env: &Host,
+        prefix: &'static str,
+        storage_type: StorageType,
+        bump_policy: Option<BumpPolicy>,
+    ) -> Self {
+        Self {
+            host: env.clone(),
+            prefix,
+            storage_type,
+            bump_policy,
+            _types: PhantomData,
+        }
+    }
+
+    fn storage_key(&self, k: &K) -> Result<Val, HostError> {
+        let mut key = Vec::new(&self.host)?;
+        key.push_val(Symbol::try_from_val(&self.host, &self.prefix)?.to_val())?;
+        key.push(k)?;
+        Ok(key.into())
+    }
+
+    pub fn has(&self, k: &K) -> Result<bool, HostError> {
+        Ok(self
+            .host
+            .has_contract_data(self.storage_key(k)?, self.storage_type.clone())?
+            .into())
+    }
+
+    pub fn get(&self, k: &K) -> Result<Option<V>, HostError> {
+        match StorageUtils::try_get(&self.host, self.storage_key(k)?, self.storage_type.clone())? {
+            Some(v) => Ok(Some(V::try_from_val(&self.host, &v)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Writes the entry and bumps it according to the bump policy.
+    pub fn set(&self, k: &K, v: &V) -> Result<(), HostError> {
+        let key = self.storage_key(k)?;
+        self.host.put_contract_data(
+            key,
+            Val::try_from_val(&self.host, v)?,
+            self.storage_type.clone(),
+        )?;
+        self.bump_key(key)
+    }
+
+    pub fn remove(&self, k: &K) -> Result<(), HostError> {
+        self.host
+            .del_contract_data(self.storage_key(k)?, self.storage_type.clone())?;
+        Ok(())
+    }
+
+    /// Bumps the entry according to the bump policy. The entry must exist.
+    pub fn bump(&self, k: &K) -> Result<(), HostError> {
+        self.bump_key(self.storage_key(k)?)
+    }
+
+    /// Bumps the entry with the given watermarks instead of the bump policy,
+    /// for entries whose lifetime depends on their value. The entry must
+    /// exist.
+    pub fn bump_by(
+        &self,
+        k: &K,
+        low_expiration_watermark: u32,
+        high_expiration_watermark: u32,
+    ) -> Result<(), HostError> {
+        self.host.bump_contract_data(
+            self.storage_key(k)?,
+            self.storage_type.clone(),
+            low_expiration_watermark.into(),
+            high_expiration_watermark.into(),
+        )?;
+        Ok(())
+    }
+
+    fn bump_key(&self, key: Val) -> Result<(), HostError> {
+        if let Some(policy) = self.bump_policy {
+            self.host.bump_contract_data(
+                key,
+                self.storage_type.clone(),
+                policy.low_expiration_watermark.into(),
+                policy.high_expiration_watermark.into(),
+            )?;
+        }
+        Ok(())
+    }
+}
@@ -1,9 +1,10 @@
 use num_derive::FromPrimitive;
-use soroban_env_common::Error;
+use soroban_native_sdk_macros::contracterror;
 
 // Use the same error for all the built-in contract error.
 // In theory we could have a separate enum for each built-in contract, but it's
 // not clear how to distinguish them if multiple built-in contracts are involved.
+#[contracterror]
 #[derive(Debug, FromPrimitive, PartialEq, Eq)]
 pub enum ContractError {
     InternalError = 1,
@@ -21,10 +22,7 @@ pub enum ContractError {
     BalanceDeauthorizedError = 11,
     OverflowError = 12,
     TrustlineMissingError = 13,
-}
-
-impl From<ContractError> for Error {
-    fn from(err: ContractError) -> Self {
-        Error::from_contract_error(err as u32)
-    }
+    NonFungibleTokenError = 14,
+    ContractPausedError = 15,
+    OwnershipTransferError = 16,
 }
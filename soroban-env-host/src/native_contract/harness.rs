@@ -0,0 +1,87 @@
+use crate::{
+    xdr::{ContractEventBody, ContractEventType, ScErrorCode, ScErrorType, ScVal},
+    AddressObject, Host, HostError, Symbol, TryFromVal, Val,
+};
+
+/// Harness for unit-testing native contract functions (such as the token
+/// contract methods) by calling them directly instead of via `Host::call`.
+pub struct NativeContractHarness<'a> {
+    host: &'a Host,
+    address: AddressObject,
+}
+
+impl<'a> NativeContractHarness<'a> {
+    /// Creates a harness for the native contract at `address`.
+    pub fn new(host: &'a Host, address: AddressObject) -> Self {
+        Self { host, address }
+    }
+
+    /// Returns the address of the native contract.
+    pub fn address(&self) -> AddressObject {
+        self.address
+    }
+
+    /// Runs `f` in a frame of the native contract, as if its function `func`
+    /// was invoked with `args`. Authorization is recorded instead of being
+    /// enforced, so every `require_auth` made by `f` succeeds. The previous
+    /// authorization state is restored afterwards.
+    pub fn call<T, F>(&self, func: &str, args: &[Val], f: F) -> Result<T, HostError>
+    where
+        F: FnOnce(&Host) -> Result<T, HostError>,
+    {
+        let contract_id = self.host.contract_id_from_address(self.address)?;
+        let func = Symbol::try_from_val(self.host, &func)?;
+
+        let prev_auth_manager = self.host.snapshot_auth_manager()?;
+        self.host.switch_to_recording_auth(true)?;
+        let mut res = None;
+        let frame_res =
+            self.host
+                .with_native_contract_frame(contract_id, func, args.to_vec(), || {
+                    res = Some(f(self.host)?);
+                    Ok(Val::VOID.into())
+                });
+        self.host.set_auth_manager(prev_auth_manager)?;
+        frame_res?;
+        res.ok_or_else(|| {
+            self.host.err(
+                ScErrorType::Context,
+                ScErrorCode::InternalError,
+                "native contract frame succeeded without a result",
+                &[],
+            )
+        })
+    }
+
+    /// Returns whether the native contract has emitted a contract event with
+    /// the given `topics` and `data` in a call that didn't fail.
+    pub fn event_emitted(&self, topics: &[Val], data: Val) -> Result<bool, HostError> {
+        let contract_id = self.host.contract_id_from_address(self.address)?;
+        let topics = topics
+            .iter()
+            .map(|t| self.host.from_host_val(*t))
+            .collect::<Result<Vec<ScVal>, HostError>>()?;
+        let data = self.host.from_host_val(data)?;
+        let events = self.host.get_events()?.0;
+        Ok(events.iter().any(|e| {
+            let ContractEventBody::V0(body) = &e.event.body;
+            !e.failed_call
+                && e.event.type_ == ContractEventType::Contract
+                && e.event.contract_id.as_ref() == Some(&contract_id)
+                && body.topics.as_slice() == topics.as_slice()
+                && body.data == data
+        }))
+    }
+
+    /// Panics unless the native contract has emitted a contract event with
+    /// the given `topics` and `data` in a call that didn't fail.
+    pub fn assert_event_emitted(&self, topics: &[Val], data: Val) -> Result<(), HostError> {
+        assert!(
+            self.event_emitted(topics, data)?,
+            "event with topics {:?} and data {:?} was not emitted",
+            topics,
+            data
+        );
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
 use super::account_contract::{ContractAuthorizationContext, CreateContractHostFnContext};
 use super::common_types::ContractExecutable;
 use crate::budget::AsBudget;
-use crate::host::metered_clone::{MeteredClone, MeteredContainer};
+use crate::host::metered_clone::{MeteredAlloc, MeteredClone, MeteredContainer};
 use crate::host_object::HostVec;
 use crate::{
     auth::{AuthorizedFunction, AuthorizedInvocation, ContractFunction},
@@ -14,6 +14,7 @@ use soroban_env_common::xdr::{
 };
 use soroban_env_common::{TryFromVal, TryIntoVal, Val};
 use soroban_native_sdk_macros::contracttype;
+use std::rc::Rc;
 
 #[derive(Clone)]
 #[contracttype]
@@ -45,9 +46,12 @@ impl InvokerContractAuthEntry {
                 let function = AuthorizedFunction::ContractFn(ContractFunction {
                     contract_address: contract_invocation.context.contract.as_object(),
                     function_name: contract_invocation.context.fn_name,
-                    args: host.visit_obj(
-                        contract_invocation.context.args.as_object(),
-                        |v: &HostVec| v.to_vec(host.budget_ref()),
+                    args: Rc::metered_new(
+                        host.visit_obj(
+                            contract_invocation.context.args.as_object(),
+                            |v: &HostVec| v.to_vec(host.budget_ref()),
+                        )?,
+                        host,
                     )?,
                 });
                 let mut sub_invocations: Vec<AuthorizedInvocation> = vec![];
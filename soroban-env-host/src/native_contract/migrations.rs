@@ -0,0 +1,95 @@
+//! Versioning of the instance storage layout of native contracts.
+//!
+//! A native contract declares the [`Migrations`] of its instance storage,
+//! where the `n`-th migration upgrades the storage from schema version `n` to
+//! `n + 1`. The schema version is stored in the instance storage under
+//! [`SCHEMA_VERSION_KEY`], and storage without it is at version 0, so that
+//! contracts initialized before their layout was versioned go through all
+//! the migrations. Contracts record the latest version when they're
+//! initialized and run the pending migrations before handling every call that
+//! writes the instance storage, so that their writes only ever deal with the
+//! latest layout. Read-only calls see the storage as is, so that reading a
+//! contract never writes it, and have to handle the layouts of every version.
+
+use crate::native_contract::contract_error::ContractError;
+use crate::native_contract::storage_utils::StorageUtils;
+use crate::{err, Host, HostError};
+use soroban_env_common::{Env, StorageType, Symbol, TryFromVal, TryIntoVal, Val};
+
+/// Upgrades the instance storage of the current contract by one version.
+pub(crate) type Migration = fn(&Host) -> Result<(), HostError>;
+
+/// The instance storage key of the schema version.
+pub(crate) const SCHEMA_VERSION_KEY: &str = "SchemaVersion";
+
+/// The migrations of the instance storage of a native contract, in order.
+pub(crate) struct Migrations(&'static [Migration]);
+
+impl Migrations {
+    pub(crate) const fn new(migrations: &'static [Migration]) -> Self {
+        Self(migrations)
+    }
+
+    pub(crate) fn latest_version(&self) -> u32 {
+        self.0.len() as u32
+    }
+
+    /// Records that the instance storage of a newly initialized contract has
+    /// the latest layout.
+    pub(crate) fn init(&self, e: &Host) -> Result<(), HostError> {
+        // A missing version is version 0, so there's nothing to record (and
+        // pay for) until the first migration is added.
+        if self.0.is_empty() {
+            return Ok(());
+        }
+        write_version(e, self.latest_version())
+    }
+
+    /// Runs the migrations from the stored schema version on, if the
+    /// contract has been initialized according to `is_initialized`. Fails
+    /// if the storage is at a version later than the latest one.
+    // Metering: covered by components.
+    pub(crate) fn run(
+        &self,
+        e: &Host,
+        is_initialized: fn(&Host) -> Result<bool, HostError>,
+    ) -> Result<(), HostError> {
+        if self.0.is_empty() || !is_initialized(e)? {
+            return Ok(());
+        }
+        let version = read_version(e)?;
+        let latest = self.latest_version();
+        if version > latest {
+            return Err(err!(
+                e,
+                ContractError::InternalError,
+                "storage schema version is later than the latest one",
+                version,
+                latest
+            ));
+        }
+        if version == latest {
+            return Ok(());
+        }
+        for migration in &self.0[version as usize..] {
+            migration(e)?;
+        }
+        write_version(e, latest)
+    }
+}
+
+fn version_key(e: &Host) -> Result<Val, HostError> {
+    Ok(Symbol::try_from_val(e, &SCHEMA_VERSION_KEY)?.to_val())
+}
+
+fn read_version(e: &Host) -> Result<u32, HostError> {
+    match StorageUtils::try_get(e, version_key(e)?, StorageType::Instance)? {
+        Some(version) => Ok(version.try_into_val(e)?),
+        None => Ok(0),
+    }
+}
+
+fn write_version(e: &Host, version: u32) -> Result<(), HostError> {
+    e.put_contract_data(version_key(e)?, version.into(), StorageType::Instance)?;
+    Ok(())
+}
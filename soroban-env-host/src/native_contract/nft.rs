@@ -0,0 +1,389 @@
+// This is a built-in non-fungible token contract. Like the built-in smart
+// wallet, it is a regular contract instance whose executable is a reserved
+// Wasm hash, so it can be deployed any number of times and wrapped or
+// extended by other contracts.
+//
+// Storage layout:
+// - Instance storage holds `NftInstanceKey::Admin` (the `Address` allowed to
+//   mint), `NftInstanceKey::Name` and `NftInstanceKey::Symbol` (`String`s).
+// - Persistent storage holds one entry per token and owner, under the keys
+//   `Vec[Symbol("Owner"), token_id]` (the owner `Address`),
+//   `Vec[Symbol("Approved"), token_id]` (the `Address` approved to transfer
+//   the token, if any), `Vec[Symbol("TokenUri"), token_id]` (the metadata URI
+//   `String`) and `Vec[Symbol("Balance"), owner]` (the number of tokens owned,
+//   as `u64`). These entries are bumped whenever they are written.
+// - The instance itself is bumped by `init` and by every call that writes
+//   storage.
+//
+// Events have the event name as the first topic, followed by the addresses
+// involved, and the token id as their data:
+// - `["mint", admin, to]`
+// - `["transfer", from, to]`
+// - `["burn", from]`
+// - `["approve", owner, spender]`
+use crate::host::Host;
+use crate::native_contract::base_types::{Address, BumpPolicy, StorageMap, String};
+use crate::native_contract::contract_error::ContractError;
+use crate::{err, HostError};
+use core::cmp::Ordering;
+use soroban_env_common::{Compare, Env, StorageType, TryFromVal, TryIntoVal, Val};
+use soroban_native_sdk_macros::{contractevent, contractimpl, contracttype};
+
+/// Executable hash reserved for the built-in non-fungible token contract,
+/// i.e. `sha256("soroban-nft-v1")`. Contract instances with
+/// `ContractExecutable::Wasm` referring to this hash are dispatched to the
+/// native implementation instead of a Wasm module.
+pub const NFT_EXECUTABLE_HASH: [u8; 32] = [
+    167, 50, 20, 104, 155, 193, 206, 214, 142, 153, 226, 196, 167, 48, 232, 78, 53, 233, 105, 44,
+    235, 229, 4, 224, 168, 40, 56, 42, 68, 74, 197, 84,
+];
+
+const DAY_IN_LEDGERS: u32 = 17280;
+const TOKEN_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
+const TOKEN_LIFETIME_THRESHOLD: u32 = TOKEN_BUMP_AMOUNT - DAY_IN_LEDGERS;
+const INSTANCE_BUMP_AMOUNT: u32 = 7 * DAY_IN_LEDGERS;
+const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+#[derive(Clone)]
+#[contracttype]
+enum NftInstanceKey {
+    Admin,
+    Name,
+    Symbol,
+}
+
+#[contractevent]
+struct Mint {
+    #[topic]
+    admin: Address,
+    #[topic]
+    to: Address,
+    token_id: u64,
+}
+
+#[contractevent]
+struct Transfer {
+    #[topic]
+    from: Address,
+    #[topic]
+    to: Address,
+    token_id: u64,
+}
+
+#[contractevent]
+struct Burn {
+    #[topic]
+    from: Address,
+    token_id: u64,
+}
+
+#[contractevent]
+struct Approve {
+    #[topic]
+    owner: Address,
+    #[topic]
+    spender: Address,
+    token_id: u64,
+}
+
+pub trait NftTrait {
+    /// Initializes the contract with the address allowed to mint tokens and
+    /// the collection metadata. Requires the authorization of `admin`.
+    fn init(e: &Host, admin: Address, name: String, symbol: String) -> Result<(), HostError>;
+
+    fn name(e: &Host) -> Result<String, HostError>;
+
+    fn symbol(e: &Host) -> Result<String, HostError>;
+
+    /// Mints the new token `token_id` with the metadata URI `uri` to `to`.
+    /// Requires the authorization of the admin.
+    fn mint(e: &Host, to: Address, token_id: u64, uri: String) -> Result<(), HostError>;
+
+    /// Destroys the token `token_id` owned by `from`.
+    fn burn(e: &Host, from: Address, token_id: u64) -> Result<(), HostError>;
+
+    /// Transfers the token `token_id` owned by `from` to `to`.
+    fn transfer(e: &Host, from: Address, to: Address, token_id: u64) -> Result<(), HostError>;
+
+    /// Transfers the token `token_id` owned by `from` to `to` on behalf of
+    /// `spender`, which has to be approved for the token.
+    fn transfer_from(
+        e: &Host,
+        spender: Address,
+        from: Address,
+        to: Address,
+        token_id: u64,
+    ) -> Result<(), HostError>;
+
+    /// Approves `spender` to transfer the token `token_id` owned by `owner`,
+    /// replacing any previous approval. The approval is cleared when the
+    /// token is transferred.
+    fn approve(e: &Host, owner: Address, spender: Address, token_id: u64) -> Result<(), HostError>;
+
+    fn owner(e: &Host, token_id: u64) -> Result<Address, HostError>;
+
+    fn is_approved(e: &Host, token_id: u64, spender: Address) -> Result<bool, HostError>;
+
+    /// Returns the number of tokens owned by `owner`.
+    fn balance(e: &Host, owner: Address) -> Result<u64, HostError>;
+
+    fn token_uri(e: &Host, token_id: u64) -> Result<String, HostError>;
+}
+
+pub struct Nft;
+
+fn read_instance<T: TryFromVal<Host, Val>>(e: &Host, key: NftInstanceKey) -> Result<T, HostError>
+where
+    HostError: From<T::Error>,
+{
+    let rv = e.get_contract_data(key.try_into_val(e)?, StorageType::Instance)?;
+    Ok(T::try_from_val(e, &rv)?)
+}
+
+fn write_instance(e: &Host, key: NftInstanceKey, val: Val) -> Result<(), HostError> {
+    e.put_contract_data(key.try_into_val(e)?, val, StorageType::Instance)?;
+    Ok(())
+}
+
+// Bumps the contract instance but not its code, as the reserved executable
+// hash has no code entry.
+fn bump_instance(e: &Host) -> Result<(), HostError> {
+    e.bump_contract_instance_from_contract_id(
+        &e.get_current_contract_id_internal()?,
+        INSTANCE_LIFETIME_THRESHOLD,
+        INSTANCE_BUMP_AMOUNT,
+    )
+}
+
+fn token_map<K, V>(e: &Host, prefix: &'static str) -> StorageMap<K, V>
+where
+    Val: TryFromVal<Host, K> + TryFromVal<Host, V>,
+    V: TryFromVal<Host, Val>,
+    HostError: From<<Val as TryFromVal<Host, K>>::Error>
+        + From<<Val as TryFromVal<Host, V>>::Error>
+        + From<<V as TryFromVal<Host, Val>>::Error>,
+{
+    StorageMap::new(
+        e,
+        prefix,
+        StorageType::Persistent,
+        Some(BumpPolicy {
+            low_expiration_watermark: TOKEN_LIFETIME_THRESHOLD,
+            high_expiration_watermark: TOKEN_BUMP_AMOUNT,
+        }),
+    )
+}
+
+fn owners(e: &Host) -> StorageMap<u64, Address> {
+    token_map(e, "Owner")
+}
+
+fn approvals(e: &Host) -> StorageMap<u64, Address> {
+    token_map(e, "Approved")
+}
+
+fn uris(e: &Host) -> StorageMap<u64, String> {
+    token_map(e, "TokenUri")
+}
+
+fn balances(e: &Host) -> StorageMap<Address, u64> {
+    token_map(e, "Balance")
+}
+
+fn read_owner(e: &Host, token_id: u64) -> Result<Address, HostError> {
+    owners(e).get(&token_id)?.ok_or_else(|| {
+        err!(
+            e,
+            ContractError::NonFungibleTokenError,
+            "token does not exist",
+            token_id
+        )
+    })
+}
+
+// Checks that `from` owns `token_id`.
+fn check_owner(e: &Host, from: &Address, token_id: u64) -> Result<(), HostError> {
+    let owner = read_owner(e, token_id)?;
+    if e.compare(&owner, from)? != Ordering::Equal {
+        return Err(err!(
+            e,
+            ContractError::UnauthorizedError,
+            "address does not own the token",
+            from.clone(),
+            token_id
+        ));
+    }
+    Ok(())
+}
+
+fn read_balance(e: &Host, owner: &Address) -> Result<u64, HostError> {
+    Ok(balances(e).get(owner)?.unwrap_or(0))
+}
+
+fn add_to_balance(e: &Host, owner: &Address) -> Result<(), HostError> {
+    let balance = read_balance(e, owner)?.checked_add(1).ok_or_else(|| {
+        e.error(
+            ContractError::OverflowError.into(),
+            "token balance overflow",
+            &[],
+        )
+    })?;
+    balances(e).set(owner, &balance)
+}
+
+fn remove_from_balance(e: &Host, owner: &Address) -> Result<(), HostError> {
+    match read_balance(e, owner)? {
+        // The balance of an owner can't be 0.
+        0 => Err(e.error(
+            ContractError::InternalError.into(),
+            "token owner has no balance",
+            &[],
+        )),
+        1 => balances(e).remove(owner),
+        balance => balances(e).set(owner, &(balance - 1)),
+    }
+}
+
+// Moves `token_id` from `from`, which must be its owner, to `to` and clears
+// its approval.
+fn move_token(e: &Host, from: Address, to: Address, token_id: u64) -> Result<(), HostError> {
+    check_owner(e, &from, token_id)?;
+    approvals(e).remove(&token_id)?;
+    remove_from_balance(e, &from)?;
+    add_to_balance(e, &to)?;
+    owners(e).set(&token_id, &to)?;
+    Transfer { from, to, token_id }.publish(e)
+}
+
+#[contractimpl]
+// Metering: covered by components.
+impl NftTrait for Nft {
+    fn init(e: &Host, admin: Address, name: String, symbol: String) -> Result<(), HostError> {
+        let _span = tracy_span!("native nft init");
+        let admin_key: Val = NftInstanceKey::Admin.try_into_val(e)?;
+        if e.has_contract_data(admin_key, StorageType::Instance)?
+            .into()
+        {
+            return Err(e.error(
+                ContractError::AlreadyInitializedError.into(),
+                "nft contract has been already initialized",
+                &[],
+            ));
+        }
+        admin.require_auth()?;
+        write_instance(e, NftInstanceKey::Admin, admin.try_into_val(e)?)?;
+        write_instance(e, NftInstanceKey::Name, name.try_into_val(e)?)?;
+        write_instance(e, NftInstanceKey::Symbol, symbol.try_into_val(e)?)?;
+        bump_instance(e)
+    }
+
+    fn name(e: &Host) -> Result<String, HostError> {
+        read_instance(e, NftInstanceKey::Name)
+    }
+
+    fn symbol(e: &Host) -> Result<String, HostError> {
+        read_instance(e, NftInstanceKey::Symbol)
+    }
+
+    fn mint(e: &Host, to: Address, token_id: u64, uri: String) -> Result<(), HostError> {
+        let _span = tracy_span!("native nft mint");
+        let admin: Address = read_instance(e, NftInstanceKey::Admin)?;
+        admin.require_auth()?;
+        bump_instance(e)?;
+        if owners(e).has(&token_id)? {
+            return Err(err!(
+                e,
+                ContractError::NonFungibleTokenError,
+                "token already exists",
+                token_id
+            ));
+        }
+        add_to_balance(e, &to)?;
+        owners(e).set(&token_id, &to)?;
+        uris(e).set(&token_id, &uri)?;
+        Mint {
+            admin,
+            to,
+            token_id,
+        }
+        .publish(e)
+    }
+
+    fn burn(e: &Host, from: Address, token_id: u64) -> Result<(), HostError> {
+        let _span = tracy_span!("native nft burn");
+        from.require_auth()?;
+        bump_instance(e)?;
+        check_owner(e, &from, token_id)?;
+        approvals(e).remove(&token_id)?;
+        uris(e).remove(&token_id)?;
+        owners(e).remove(&token_id)?;
+        remove_from_balance(e, &from)?;
+        Burn { from, token_id }.publish(e)
+    }
+
+    fn transfer(e: &Host, from: Address, to: Address, token_id: u64) -> Result<(), HostError> {
+        let _span = tracy_span!("native nft transfer");
+        from.require_auth()?;
+        bump_instance(e)?;
+        move_token(e, from, to, token_id)
+    }
+
+    fn transfer_from(
+        e: &Host,
+        spender: Address,
+        from: Address,
+        to: Address,
+        token_id: u64,
+    ) -> Result<(), HostError> {
+        let _span = tracy_span!("native nft transfer_from");
+        spender.require_auth()?;
+        bump_instance(e)?;
+        if !Self::is_approved(e, token_id, spender.clone())? {
+            return Err(err!(
+                e,
+                ContractError::UnauthorizedError,
+                "spender is not approved for the token",
+                spender,
+                token_id
+            ));
+        }
+        move_token(e, from, to, token_id)
+    }
+
+    fn approve(e: &Host, owner: Address, spender: Address, token_id: u64) -> Result<(), HostError> {
+        let _span = tracy_span!("native nft approve");
+        owner.require_auth()?;
+        bump_instance(e)?;
+        check_owner(e, &owner, token_id)?;
+        approvals(e).set(&token_id, &spender)?;
+        Approve {
+            owner,
+            spender,
+            token_id,
+        }
+        .publish(e)
+    }
+
+    fn owner(e: &Host, token_id: u64) -> Result<Address, HostError> {
+        read_owner(e, token_id)
+    }
+
+    fn is_approved(e: &Host, token_id: u64, spender: Address) -> Result<bool, HostError> {
+        match approvals(e).get(&token_id)? {
+            Some(approved) => Ok(e.compare(&approved, &spender)? == Ordering::Equal),
+            None => Ok(false),
+        }
+    }
+
+    fn balance(e: &Host, owner: Address) -> Result<u64, HostError> {
+        read_balance(e, &owner)
+    }
+
+    fn token_uri(e: &Host, token_id: u64) -> Result<String, HostError> {
+        // Check that the token exists to fail with a contract error.
+        read_owner(e, token_id)?;
+        uris(e)
+            .get(&token_id)?
+            .ok_or_else(|| e.error(ContractError::InternalError.into(), "token has no uri", &[]))
+    }
+}
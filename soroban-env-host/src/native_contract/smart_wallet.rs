@@ -0,0 +1,413 @@
+// This is a built-in smart wallet contract. Unlike the built-in account
+// 'contract', this is a regular contract instance that is invoked by the auth
+// framework via `__check_auth`. It supports multiple ed25519 and secp256r1
+// signers with a signature threshold, a recovery address that may replace the
+// signers, and ed25519 session keys that are restricted to calling a set of
+// contracts until an expiration ledger.
+use crate::host::Host;
+use crate::native_contract::account_contract::AuthorizationContext;
+use crate::native_contract::base_types::{Address, BytesN, Vec as HostVec};
+use crate::native_contract::contract_error::ContractError;
+use crate::{err, HostError};
+use core::cmp::Ordering;
+use soroban_env_common::{Compare, Env, StorageType, TryFromVal, TryIntoVal, Val};
+use soroban_native_sdk_macros::{contractimpl, contracttype};
+
+/// Executable hash reserved for the built-in smart wallet, i.e.
+/// `sha256("soroban-smart-wallet-v1")`. Contract instances with
+/// `ContractExecutable::Wasm` referring to this hash are dispatched to the
+/// native implementation instead of a Wasm module.
+pub const SMART_WALLET_EXECUTABLE_HASH: [u8; 32] = [
+    158, 58, 38, 84, 241, 194, 10, 222, 238, 63, 210, 235, 173, 95, 125, 124, 64, 9, 234, 135, 142,
+    186, 111, 139, 168, 156, 108, 31, 126, 182, 194, 60,
+];
+
+const MAX_SIGNERS: u32 = 20;
+
+const DAY_IN_LEDGERS: u32 = 17280;
+const INSTANCE_BUMP_AMOUNT: u32 = 7 * DAY_IN_LEDGERS;
+const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+#[derive(Clone)]
+#[contracttype]
+pub enum SignerKey {
+    Ed25519(BytesN<32>),
+    // SEC-1 uncompressed public key.
+    Secp256r1(BytesN<65>),
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct SignerSignature {
+    pub key: SignerKey,
+    pub signature: BytesN<64>,
+}
+
+#[derive(Clone)]
+#[contracttype]
+pub struct SessionKey {
+    pub expiration_ledger: u32,
+    pub contracts: HostVec,
+}
+
+#[derive(Clone)]
+#[contracttype]
+enum DataKey {
+    Signers,
+    Threshold,
+    Recovery,
+    Session(BytesN<32>),
+}
+
+pub trait SmartWalletTrait {
+    /// Initializes the wallet with the given signers, the number of distinct
+    /// signatures required for authorization and the recovery address.
+    fn init(e: &Host, signers: HostVec, threshold: u32, recovery: Address)
+        -> Result<(), HostError>;
+
+    fn signers(e: &Host) -> Result<HostVec, HostError>;
+
+    fn threshold(e: &Host) -> Result<u32, HostError>;
+
+    fn add_signer(e: &Host, key: SignerKey) -> Result<(), HostError>;
+
+    fn remove_signer(e: &Host, key: SignerKey) -> Result<(), HostError>;
+
+    fn set_threshold(e: &Host, threshold: u32) -> Result<(), HostError>;
+
+    fn add_session_key(e: &Host, key: BytesN<32>, session: SessionKey) -> Result<(), HostError>;
+
+    fn remove_session_key(e: &Host, key: BytesN<32>) -> Result<(), HostError>;
+
+    /// Replaces the signers and threshold; authorized by the recovery address
+    /// instead of the signers.
+    fn recover(e: &Host, signers: HostVec, threshold: u32) -> Result<(), HostError>;
+
+    /// Authorizes `auth_contexts` either with a threshold of signer signatures
+    /// or with a single non-expired session key signature.
+    fn __check_auth(
+        e: &Host,
+        signature_payload: BytesN<32>,
+        signatures: HostVec,
+        auth_contexts: HostVec,
+    ) -> Result<(), HostError>;
+}
+
+pub struct SmartWallet;
+
+fn read_instance<T: TryFromVal<Host, Val>>(e: &Host, key: DataKey) -> Result<T, HostError>
+where
+    HostError: From<T::Error>,
+{
+    let rv = e.get_contract_data(key.try_into_val(e)?, StorageType::Instance)?;
+    Ok(T::try_from_val(e, &rv)?)
+}
+
+fn write_instance(e: &Host, key: DataKey, val: Val) -> Result<(), HostError> {
+    e.put_contract_data(key.try_into_val(e)?, val, StorageType::Instance)?;
+    Ok(())
+}
+
+fn has_instance(e: &Host, key: DataKey) -> Result<bool, HostError> {
+    let rv = e.has_contract_data(key.try_into_val(e)?, StorageType::Instance)?;
+    Ok(rv.try_into()?)
+}
+
+// All the wallet state lives in the instance storage, so bumping the instance
+// keeps the whole wallet alive.
+fn bump_instance(e: &Host) -> Result<(), HostError> {
+    e.bump_current_contract_instance_and_code(
+        INSTANCE_LIFETIME_THRESHOLD.into(),
+        INSTANCE_BUMP_AMOUNT.into(),
+    )?;
+    Ok(())
+}
+
+fn find_signer(e: &Host, signers: &HostVec, key: &SignerKey) -> Result<Option<u32>, HostError> {
+    for i in 0..signers.len()? {
+        let signer: SignerKey = signers.get(i)?;
+        if e.compare(&signer, key)? == Ordering::Equal {
+            return Ok(Some(i));
+        }
+    }
+    Ok(None)
+}
+
+fn write_signers(e: &Host, signers: &HostVec, threshold: u32) -> Result<(), HostError> {
+    let len = signers.len()?;
+    if len > MAX_SIGNERS {
+        return Err(err!(
+            e,
+            ContractError::OperationNotSupportedError,
+            "too many smart wallet signers",
+            len
+        ));
+    }
+    if threshold == 0 || threshold > len {
+        return Err(err!(
+            e,
+            ContractError::OperationNotSupportedError,
+            "threshold must be between 1 and the number of signers",
+            threshold,
+            len
+        ));
+    }
+    // Validate the keys and make sure they are distinct.
+    for i in 0..len {
+        let key: SignerKey = signers.get(i)?;
+        if find_signer(e, signers, &key)? != Some(i) {
+            return Err(err!(
+                e,
+                ContractError::OperationNotSupportedError,
+                "duplicate smart wallet signer",
+                i
+            ));
+        }
+    }
+    write_instance(e, DataKey::Signers, signers.clone().into())?;
+    write_instance(e, DataKey::Threshold, threshold.into())
+}
+
+fn require_self_auth(e: &Host) -> Result<(), HostError> {
+    let this: Address = e.get_current_contract_address()?.try_into_val(e)?;
+    this.require_auth()
+}
+
+fn check_session_auth(
+    e: &Host,
+    session: SessionKey,
+    auth_contexts: &HostVec,
+) -> Result<(), HostError> {
+    let ledger_seq: u32 = e.get_ledger_sequence()?.into();
+    if session.expiration_ledger < ledger_seq {
+        return Err(err!(
+            e,
+            ContractError::AuthenticationError,
+            "session key has expired",
+            session.expiration_ledger,
+            ledger_seq
+        ));
+    }
+    let this: Address = e.get_current_contract_address()?.try_into_val(e)?;
+    for i in 0..auth_contexts.len()? {
+        let context: AuthorizationContext = auth_contexts.get(i)?;
+        let contract = match context {
+            AuthorizationContext::Contract(c) => c.contract,
+            AuthorizationContext::CreateContractHostFn(_) => {
+                return Err(err!(
+                    e,
+                    ContractError::AuthenticationError,
+                    "session keys can't authorize contract creation",
+                    i
+                ));
+            }
+        };
+        // Session keys must never be able to administer the wallet itself.
+        let mut allowed = e.compare(&contract, &this)? != Ordering::Equal;
+        if allowed {
+            allowed = false;
+            for j in 0..session.contracts.len()? {
+                let c: Address = session.contracts.get(j)?;
+                if e.compare(&contract, &c)? == Ordering::Equal {
+                    allowed = true;
+                    break;
+                }
+            }
+        }
+        if !allowed {
+            return Err(err!(
+                e,
+                ContractError::AuthenticationError,
+                "contract is not allowed for the session key",
+                contract
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn verify_signature(
+    e: &Host,
+    signature_payload: &BytesN<32>,
+    sig: SignerSignature,
+) -> Result<(), HostError> {
+    match sig.key {
+        SignerKey::Ed25519(pk) => {
+            e.verify_sig_ed25519(
+                pk.into(),
+                signature_payload.clone().into(),
+                sig.signature.into(),
+            )?;
+        }
+        SignerKey::Secp256r1(pk) => {
+            e.verify_sig_ecdsa_secp256r1_internal(
+                &pk.to_array()?,
+                &signature_payload.to_array()?,
+                &sig.signature.to_array()?,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+#[contractimpl]
+// Metering: covered by components.
+impl SmartWalletTrait for SmartWallet {
+    fn init(
+        e: &Host,
+        signers: HostVec,
+        threshold: u32,
+        recovery: Address,
+    ) -> Result<(), HostError> {
+        let _span = tracy_span!("native smart wallet init");
+        bump_instance(e)?;
+        if has_instance(e, DataKey::Signers)? {
+            return Err(e.error(
+                ContractError::AlreadyInitializedError.into(),
+                "smart wallet has been already initialized",
+                &[],
+            ));
+        }
+        write_signers(e, &signers, threshold)?;
+        write_instance(e, DataKey::Recovery, recovery.try_into_val(e)?)
+    }
+
+    fn signers(e: &Host) -> Result<HostVec, HostError> {
+        bump_instance(e)?;
+        read_instance(e, DataKey::Signers)
+    }
+
+    fn threshold(e: &Host) -> Result<u32, HostError> {
+        bump_instance(e)?;
+        read_instance(e, DataKey::Threshold)
+    }
+
+    fn add_signer(e: &Host, key: SignerKey) -> Result<(), HostError> {
+        let _span = tracy_span!("native smart wallet add_signer");
+        bump_instance(e)?;
+        require_self_auth(e)?;
+        let mut signers: HostVec = read_instance(e, DataKey::Signers)?;
+        signers.push(&key)?;
+        write_signers(e, &signers, read_instance(e, DataKey::Threshold)?)
+    }
+
+    fn remove_signer(e: &Host, key: SignerKey) -> Result<(), HostError> {
+        let _span = tracy_span!("native smart wallet remove_signer");
+        bump_instance(e)?;
+        require_self_auth(e)?;
+        let signers: HostVec = read_instance(e, DataKey::Signers)?;
+        let Some(idx) = find_signer(e, &signers, &key)? else {
+            return Err(err!(
+                e,
+                ContractError::OperationNotSupportedError,
+                "signer does not belong to the smart wallet",
+                key
+            ));
+        };
+        let signers: HostVec = e
+            .vec_del(signers.as_object(), idx.into())?
+            .try_into_val(e)?;
+        write_signers(e, &signers, read_instance(e, DataKey::Threshold)?)
+    }
+
+    fn set_threshold(e: &Host, threshold: u32) -> Result<(), HostError> {
+        let _span = tracy_span!("native smart wallet set_threshold");
+        bump_instance(e)?;
+        require_self_auth(e)?;
+        write_signers(e, &read_instance(e, DataKey::Signers)?, threshold)
+    }
+
+    fn add_session_key(e: &Host, key: BytesN<32>, session: SessionKey) -> Result<(), HostError> {
+        let _span = tracy_span!("native smart wallet add_session_key");
+        bump_instance(e)?;
+        require_self_auth(e)?;
+        write_instance(e, DataKey::Session(key), session.try_into_val(e)?)
+    }
+
+    fn remove_session_key(e: &Host, key: BytesN<32>) -> Result<(), HostError> {
+        let _span = tracy_span!("native smart wallet remove_session_key");
+        bump_instance(e)?;
+        require_self_auth(e)?;
+        e.del_contract_data(
+            DataKey::Session(key).try_into_val(e)?,
+            StorageType::Instance,
+        )?;
+        Ok(())
+    }
+
+    fn recover(e: &Host, signers: HostVec, threshold: u32) -> Result<(), HostError> {
+        let _span = tracy_span!("native smart wallet recover");
+        bump_instance(e)?;
+        let recovery: Address = read_instance(e, DataKey::Recovery)?;
+        recovery.require_auth()?;
+        write_signers(e, &signers, threshold)
+    }
+
+    fn __check_auth(
+        e: &Host,
+        signature_payload: BytesN<32>,
+        signatures: HostVec,
+        auth_contexts: HostVec,
+    ) -> Result<(), HostError> {
+        let _span = tracy_span!("native smart wallet __check_auth");
+        bump_instance(e)?;
+        let len = signatures.len()?;
+        if len == 1 {
+            let sig: SignerSignature = signatures.get(0)?;
+            if let SignerKey::Ed25519(pk) = &sig.key {
+                let session_key = DataKey::Session(pk.clone());
+                if has_instance(e, session_key.clone())? {
+                    let session: SessionKey = read_instance(e, session_key)?;
+                    verify_signature(e, &signature_payload, sig)?;
+                    return check_session_auth(e, session, &auth_contexts);
+                }
+            }
+        }
+
+        let signers: HostVec = read_instance(e, DataKey::Signers)?;
+        let threshold: u32 = read_instance(e, DataKey::Threshold)?;
+        if len > signers.len()? {
+            return Err(err!(
+                e,
+                ContractError::AuthenticationError,
+                "too many smart wallet signatures",
+                len
+            ));
+        }
+        let mut prev_key: Option<SignerKey> = None;
+        for i in 0..len {
+            let sig: SignerSignature = signatures.get(i)?;
+            // Cannot take multiple signatures from the same key.
+            if let Some(prev) = prev_key {
+                if e.compare(&prev, &sig.key)? != Ordering::Less {
+                    return Err(err!(
+                        e,
+                        ContractError::AuthenticationError,
+                        "signer keys are not ordered",
+                        i
+                    ));
+                }
+            }
+            if find_signer(e, &signers, &sig.key)?.is_none() {
+                return Err(err!(
+                    e,
+                    ContractError::AuthenticationError,
+                    "signer does not belong to the smart wallet",
+                    sig.key
+                ));
+            }
+            prev_key = Some(sig.key.clone());
+            verify_signature(e, &signature_payload, sig)?;
+        }
+        if len < threshold {
+            return Err(err!(
+                e,
+                ContractError::AuthenticationError,
+                "signature count is lower than threshold",
+                len,
+                threshold
+            ));
+        }
+        Ok(())
+    }
+}
@@ -2,6 +2,7 @@ mod admin;
 mod allowance;
 mod asset_info;
 mod balance;
+mod client;
 mod contract;
 mod event;
 mod metadata;
@@ -11,5 +12,45 @@ mod storage_types;
 #[cfg(test)]
 pub(crate) mod test_token;
 
+pub(crate) use balance::read_asset_balance;
+pub use client::TokenClient;
 pub use contract::Token;
 pub use contract::TokenTrait;
+pub use metadata::{AssetOrContract, TokenMetadataInfo};
+pub(crate) use storage_types::MIGRATIONS;
+
+use crate::{Host, HostError, Symbol, SymbolStr, TryFromVal};
+
+/// The token functions that don't write the instance storage. Calls to them
+/// don't migrate it, so that reading a token never writes its instance.
+const READ_ONLY_FNS: &[&str] = &[
+    "allowance",
+    "periodic_allowance",
+    "balance",
+    "spendable_balance",
+    "authorized",
+    "paused",
+    "admin",
+    "decimals",
+    "name",
+    "symbol",
+];
+
+/// Returns whether the token function `func` doesn't write the instance
+/// storage.
+// Notes on metering: the conversion of `func` is covered by components, the
+// comparisons with the short function names are not charged.
+pub(crate) fn is_read_only_fn(e: &Host, func: &Symbol) -> Result<bool, HostError> {
+    let func = SymbolStr::try_from_val(e, func)?;
+    Ok(READ_ONLY_FNS.contains(&func.as_ref()))
+}
+
+/// Brings the instance storage of an initialized token contract up to date
+/// before a call to `func`, see [`MIGRATIONS`]. Calls to read-only functions
+/// leave the storage as is, so these have to handle every schema version.
+pub(crate) fn migrate_storage(e: &Host, func: &Symbol) -> Result<(), HostError> {
+    if MIGRATIONS.latest_version() == 0 || is_read_only_fn(e, func)? {
+        return Ok(());
+    }
+    MIGRATIONS.run(e, asset_info::has_asset_info)
+}
@@ -1,24 +1,45 @@
-use crate::host::Host;
+use crate::host::{Host, SAC_PAUSE_MIN_PROTOCOL};
+use crate::native_contract::access_control::{Ownable, OwnershipTransfer, Pausable};
 use crate::native_contract::base_types::Address;
 use crate::HostError;
-use soroban_env_common::{Env, StorageType, TryIntoVal};
+use soroban_env_common::TryIntoVal;
 
 use super::storage_types::InstanceDataKey;
 
+fn admin(e: &Host) -> Result<Ownable, HostError> {
+    Ok(Ownable::new(InstanceDataKey::Admin.try_into_val(e)?))
+}
+
+pub(crate) fn admin_transfer(e: &Host) -> Result<OwnershipTransfer, HostError> {
+    Ok(OwnershipTransfer::new(
+        InstanceDataKey::PendingAdmin.try_into_val(e)?,
+    ))
+}
+
+pub(crate) fn pause_switch(e: &Host) -> Result<Pausable, HostError> {
+    Ok(Pausable::new(InstanceDataKey::Paused.try_into_val(e)?))
+}
+
 // Metering: covered by components
 pub fn read_administrator(e: &Host) -> Result<Address, HostError> {
-    let key = InstanceDataKey::Admin;
-    let rv = e.get_contract_data(key.try_into_val(e)?, StorageType::Instance)?;
-    rv.try_into_val(e)
+    admin(e)?.read_owner(e)
 }
 
 // Metering: covered by components
 pub fn write_administrator(e: &Host, id: Address) -> Result<(), HostError> {
-    let key = InstanceDataKey::Admin;
-    e.put_contract_data(
-        key.try_into_val(e)?,
-        id.try_into_val(e)?,
-        StorageType::Instance,
-    )?;
-    Ok(())
+    admin(e)?.write_owner(e, id)
+}
+
+// Metering: covered by components
+pub(crate) fn require_administrator(e: &Host) -> Result<Address, HostError> {
+    admin(e)?.require_owner(e)
+}
+
+// Metering: covered by components. Before `SAC_PAUSE_MIN_PROTOCOL` the token
+// can't be paused, so the pause switch isn't read.
+pub(crate) fn check_not_paused(e: &Host) -> Result<(), HostError> {
+    if e.get_ledger_protocol_version()? < SAC_PAUSE_MIN_PROTOCOL {
+        return Ok(());
+    }
+    pause_switch(e)?.require_not_paused(e)
 }
@@ -1,19 +1,34 @@
-use crate::host::{metered_clone::MeteredClone, Host};
-use crate::native_contract::base_types::Address;
+use crate::host::{metered_clone::MeteredClone, Host, SAC_PERIODIC_ALLOWANCE_MIN_PROTOCOL};
+use crate::native_contract::base_types::{Address, StorageMap};
 use crate::native_contract::contract_error::ContractError;
-use crate::native_contract::storage_utils::StorageUtils;
-use crate::native_contract::token::storage_types::{AllowanceDataKey, DataKey};
+use crate::native_contract::token::storage_types::AllowanceDataKey;
+use crate::xdr::{ScErrorCode, ScErrorType};
 use crate::{err, HostError};
-use soroban_env_common::{Env, StorageType, TryIntoVal};
+use soroban_env_common::{Env, StorageType};
 
-use super::storage_types::AllowanceValue;
+use super::storage_types::{
+    AllowanceValue, PeriodicAllowanceValue, ALLOWANCE_KEY_PREFIX, PERIODIC_ALLOWANCE_KEY_PREFIX,
+};
+
+// Allowances live as long as they're valid, so they're bumped according to
+// their expiration rather than with a bump policy.
+fn allowances(e: &Host) -> StorageMap<AllowanceDataKey, AllowanceValue> {
+    StorageMap::new(e, ALLOWANCE_KEY_PREFIX, StorageType::Temporary, None)
+}
+
+fn periodic_allowances(e: &Host) -> StorageMap<AllowanceDataKey, PeriodicAllowanceValue> {
+    StorageMap::new(
+        e,
+        PERIODIC_ALLOWANCE_KEY_PREFIX,
+        StorageType::Temporary,
+        None,
+    )
+}
 
 // Metering: covered by components
 pub fn read_allowance(e: &Host, from: Address, spender: Address) -> Result<i128, HostError> {
-    let key = DataKey::Allowance(AllowanceDataKey { from, spender });
-    if let Some(allowance) = StorageUtils::try_get(e, key.try_into_val(e)?, StorageType::Temporary)?
-    {
-        let val: AllowanceValue = allowance.try_into_val(e)?;
+    let key = AllowanceDataKey { from, spender };
+    if let Some(val) = allowances(e).get(&key)? {
         if val.expiration_ledger < e.get_ledger_sequence()?.into() {
             Ok(0)
         } else {
@@ -24,47 +39,48 @@ pub fn read_allowance(e: &Host, from: Address, spender: Address) -> Result<i128,
     }
 }
 
-// Metering: covered by components
-pub fn write_allowance(
-    e: &Host,
-    from: Address,
-    spender: Address,
-    amount: i128,
-    expiration: u32,
-) -> Result<(), HostError> {
-    let key = DataKey::Allowance(AllowanceDataKey { from, spender });
-
-    // validates the expiration and then returns the ledger seq
-    // The expiration can be less than ledger seq if clearing an allowance
-    let ledger_seq = e.with_ledger_info(|li| {
+// Validates the expiration of an allowance of `amount` and returns the ledger
+// seq. The expiration can be less than ledger seq if clearing an allowance.
+fn check_allowance_expiration(e: &Host, amount: i128, expiration: u32) -> Result<u32, HostError> {
+    e.with_ledger_info_provider(|li| {
         if expiration > e.max_expiration_ledger()? {
             Err(err!(
                 e,
                 ContractError::AllowanceError,
                 "expiration is greater than max",
                 expiration,
-                li.max_entry_expiration
+                li.max_entry_expiration()
             ))
-        } else if amount > 0 && expiration < li.sequence_number {
+        } else if amount > 0 && expiration < li.sequence_number() {
             Err(err!(
                 e,
                 ContractError::AllowanceError,
                 "expiration must be >= ledger sequence",
                 expiration,
-                li.sequence_number
+                li.sequence_number()
             ))
         } else {
-            Ok(li.sequence_number)
+            Ok(li.sequence_number())
         }
-    })?;
+    })
+}
+
+// Metering: covered by components
+pub fn write_allowance(
+    e: &Host,
+    from: Address,
+    spender: Address,
+    amount: i128,
+    expiration: u32,
+) -> Result<(), HostError> {
+    let allowances = allowances(e);
+    let key = AllowanceDataKey { from, spender };
+    let ledger_seq = check_allowance_expiration(e, amount, expiration)?;
 
     // Returns the allowance to write and the previous expiration of the existing allowance.
     // If an allowance didn't exist, then the previous expiration will be None.
     let allowance_with_old_expiration_option: Option<(AllowanceValue, Option<u32>)> =
-        if let Some(allowance) =
-            StorageUtils::try_get(e, key.try_into_val(e)?, StorageType::Temporary)?
-        {
-            let mut updated_allowance: AllowanceValue = allowance.try_into_val(e)?;
+        if let Some(mut updated_allowance) = allowances.get(&key)? {
             updated_allowance.amount = amount;
 
             let old_expiration = updated_allowance.expiration_ledger;
@@ -84,22 +100,13 @@ pub fn write_allowance(
 
     match allowance_with_old_expiration_option {
         Some(allowance_with_old_expiration) => {
-            e.put_contract_data(
-                key.try_into_val(e)?,
-                allowance_with_old_expiration.0.try_into_val(e)?,
-                StorageType::Temporary,
-            )?;
+            allowances.set(&key, &allowance_with_old_expiration.0)?;
 
             if allowance_with_old_expiration.0.amount > 0
                 && allowance_with_old_expiration.1.unwrap_or(0) < expiration
             {
                 let live_for = expiration - ledger_seq + 1;
-                e.bump_contract_data(
-                    key.try_into_val(e)?,
-                    StorageType::Temporary,
-                    live_for.into(),
-                    live_for.into(),
-                )?;
+                allowances.bump_by(&key, live_for, live_for)?;
             }
         }
         None => {}
@@ -115,17 +122,148 @@ fn write_allowance_amount(
     spender: Address,
     amount: i128,
 ) -> Result<(), HostError> {
-    let key = DataKey::Allowance(AllowanceDataKey {
+    let key = AllowanceDataKey {
         from: from.metered_clone(e)?,
         spender: spender.metered_clone(e)?,
-    });
+    };
 
-    let allowance: AllowanceValue = e
-        .get_contract_data(key.try_into_val(e)?, StorageType::Temporary)?
-        .try_into_val(e)?;
+    let allowance = allowances(e).get(&key)?.ok_or_else(|| {
+        e.err(
+            ScErrorType::Storage,
+            ScErrorCode::MissingValue,
+            "allowance is missing",
+            &[],
+        )
+    })?;
     write_allowance(e, from, spender, amount, allowance.expiration_ledger)
 }
 
+// Metering: covered by components
+pub fn write_periodic_allowance(
+    e: &Host,
+    from: Address,
+    spender: Address,
+    amount_per_period: i128,
+    period_ledgers: u32,
+    expiration: u32,
+) -> Result<(), HostError> {
+    let periodic_allowances = periodic_allowances(e);
+    let key = AllowanceDataKey { from, spender };
+    if period_ledgers == 0 {
+        return Err(err!(
+            e,
+            ContractError::AllowanceError,
+            "period must be at least one ledger",
+            period_ledgers
+        ));
+    }
+    let ledger_seq = check_allowance_expiration(e, amount_per_period, expiration)?;
+
+    if amount_per_period == 0 {
+        if periodic_allowances.has(&key)? {
+            periodic_allowances.remove(&key)?;
+        }
+        return Ok(());
+    }
+
+    // Approving again starts a new window.
+    let allowance = PeriodicAllowanceValue {
+        amount_per_period,
+        period_ledgers,
+        expiration_ledger: expiration,
+        period_start: ledger_seq,
+        spent_in_period: 0,
+    };
+    periodic_allowances.set(&key, &allowance)?;
+    let live_for = expiration - ledger_seq + 1;
+    periodic_allowances.bump_by(&key, live_for, live_for)
+}
+
+// Reads the periodic allowance, with its window moved to the one containing
+// the current ledger. Returns `None` if there is no unexpired allowance.
+fn read_periodic_allowance_value(
+    e: &Host,
+    key: &AllowanceDataKey,
+) -> Result<Option<PeriodicAllowanceValue>, HostError> {
+    let Some(mut allowance) = periodic_allowances(e).get(key)? else {
+        return Ok(None);
+    };
+    let ledger_seq: u32 = e.get_ledger_sequence()?.into();
+    if allowance.expiration_ledger < ledger_seq {
+        return Ok(None);
+    }
+    let elapsed_periods =
+        ledger_seq.saturating_sub(allowance.period_start) / allowance.period_ledgers;
+    if elapsed_periods > 0 {
+        // Can't overflow, as the result is at most `ledger_seq`.
+        allowance.period_start += elapsed_periods * allowance.period_ledgers;
+        allowance.spent_in_period = 0;
+    }
+    Ok(Some(allowance))
+}
+
+fn periodic_allowance_remaining(
+    e: &Host,
+    allowance: &PeriodicAllowanceValue,
+) -> Result<i128, HostError> {
+    allowance
+        .amount_per_period
+        .checked_sub(allowance.spent_in_period)
+        .ok_or_else(|| {
+            e.error(
+                ContractError::OverflowError.into(),
+                "periodic allowance overflowed",
+                &[],
+            )
+        })
+}
+
+// Metering: covered by components
+pub fn read_periodic_allowance(
+    e: &Host,
+    from: Address,
+    spender: Address,
+) -> Result<i128, HostError> {
+    let key = AllowanceDataKey { from, spender };
+    match read_periodic_allowance_value(e, &key)? {
+        Some(allowance) => periodic_allowance_remaining(e, &allowance),
+        None => Ok(0),
+    }
+}
+
+// Spends `amount` from the current window of the periodic allowance, if it
+// has enough left. Returns whether the amount has been spent.
+fn spend_periodic_allowance(
+    e: &Host,
+    from: Address,
+    spender: Address,
+    amount: i128,
+) -> Result<bool, HostError> {
+    let key = AllowanceDataKey { from, spender };
+    let Some(mut allowance) = read_periodic_allowance_value(e, &key)? else {
+        return Ok(false);
+    };
+    if periodic_allowance_remaining(e, &allowance)? < amount {
+        return Ok(false);
+    }
+    allowance.spent_in_period = allowance
+        .spent_in_period
+        .checked_add(amount)
+        .ok_or_else(|| {
+            e.error(
+                ContractError::OverflowError.into(),
+                "periodic allowance overflowed",
+                &[],
+            )
+        })?;
+    periodic_allowances(e).set(&key, &allowance)?;
+    Ok(true)
+}
+
+// Spends from the regular allowance if it covers `amount`, and otherwise from
+// the current window of the periodic allowance. Before
+// `SAC_PERIODIC_ALLOWANCE_MIN_PROTOCOL` there are no periodic allowances, so
+// they aren't read.
 // Metering: covered by components
 pub fn spend_allowance(
     e: &Host,
@@ -135,6 +273,11 @@ pub fn spend_allowance(
 ) -> Result<(), HostError> {
     let allowance = read_allowance(e, from.metered_clone(e)?, spender.metered_clone(e)?)?;
     if allowance < amount {
+        if e.get_ledger_protocol_version()? >= SAC_PERIODIC_ALLOWANCE_MIN_PROTOCOL
+            && spend_periodic_allowance(e, from, spender, amount)?
+        {
+            return Ok(());
+        }
         return Err(err!(
             e,
             ContractError::AllowanceError,
@@ -1,24 +1,25 @@
 use std::rc::Rc;
 
 use crate::budget::AsBudget;
-use crate::host::metered_clone::MeteredClone;
+use crate::host::metered_clone::{MeteredAlloc, MeteredClone};
 use crate::host::Host;
-use crate::native_contract::base_types::{Address, BytesN};
+use crate::native_contract::base_types::{Address, BumpPolicy, BytesN, StorageMap};
 use crate::native_contract::contract_error::ContractError;
-use crate::native_contract::storage_utils::StorageUtils;
 use crate::native_contract::token::asset_info::read_asset_info;
 use crate::native_contract::token::public_types::AssetInfo;
-use crate::native_contract::token::storage_types::DataKey;
 use crate::storage::Storage;
 use crate::{err, HostError};
 use soroban_env_common::xdr::{
-    AccountEntry, AccountEntryExt, AccountEntryExtensionV1Ext, AccountFlags, AccountId,
-    LedgerEntry, LedgerEntryData, LedgerKey, ScAddress, TrustLineAsset, TrustLineEntry,
-    TrustLineEntryExt, TrustLineFlags,
+    AccountEntry, AccountEntryExt, AccountEntryExtensionV1Ext, AccountFlags, AccountId, Asset,
+    ContractDataDurability, Hash, LedgerEntry, LedgerEntryData, LedgerKey, LedgerKeyContractData,
+    ScAddress, ScSymbol, ScVal, ScVec, TrustLineAsset, TrustLineEntry, TrustLineEntryExt,
+    TrustLineFlags,
 };
-use soroban_env_common::{Env, StorageType, TryIntoVal};
+use soroban_env_common::{StorageType, TryIntoVal};
 
-use super::storage_types::{BalanceValue, BALANCE_BUMP_AMOUNT, BALANCE_LIFETIME_THRESHOLD};
+use super::storage_types::{
+    BalanceValue, BALANCE_BUMP_AMOUNT, BALANCE_KEY_PREFIX, BALANCE_LIFETIME_THRESHOLD,
+};
 
 /// This module handles all balance and authorization related logic for both
 /// Accounts and non-Accounts. For Accounts, a trustline is expected (unless this
@@ -35,17 +36,9 @@ pub fn read_balance(e: &Host, addr: Address) -> Result<i128, HostError> {
     match addr.to_sc_address()? {
         ScAddress::Account(acc_id) => Ok(get_classic_balance(e, acc_id)?.0.into()),
         ScAddress::Contract(_) => {
-            let key = DataKey::Balance(addr);
-            if let Some(raw_balance) =
-                StorageUtils::try_get(e, key.try_into_val(e)?, StorageType::Persistent)?
-            {
-                e.bump_contract_data(
-                    key.try_into_val(e)?,
-                    StorageType::Persistent,
-                    BALANCE_LIFETIME_THRESHOLD.into(),
-                    BALANCE_BUMP_AMOUNT.into(),
-                )?;
-                let balance: BalanceValue = raw_balance.try_into_val(e)?;
+            let balances = balances(e);
+            if let Some(balance) = balances.get(&addr)? {
+                balances.bump(&addr)?;
                 Ok(balance.amount)
             } else {
                 Ok(0)
@@ -54,6 +47,64 @@ pub fn read_balance(e: &Host, addr: Address) -> Result<i128, HostError> {
     }
 }
 
+// Reads the balance of `addr` in the Stellar Asset Contract `contract_id` of
+// `asset` without running the contract, with the same result as its `balance`
+// function. Unlike `read_balance`, this doesn't bump the balance entry.
+// Metering: covered by components.
+pub(crate) fn read_asset_balance(
+    e: &Host,
+    contract_id: &Hash,
+    asset: &Asset,
+    addr: &ScAddress,
+) -> Result<i128, HostError> {
+    match addr {
+        ScAddress::Account(acc_id) => {
+            let asset = match asset {
+                Asset::Native => TrustLineAsset::Native,
+                Asset::CreditAlphanum4(asset) => {
+                    e.create_asset_4(asset.asset_code.0, asset.issuer.metered_clone(e)?)
+                }
+                Asset::CreditAlphanum12(asset) => {
+                    e.create_asset_12(asset.asset_code.0, asset.issuer.metered_clone(e)?)
+                }
+            };
+            Ok(get_asset_balance(e, asset, acc_id.metered_clone(e)?)?
+                .0
+                .into())
+        }
+        ScAddress::Contract(_) => {
+            let key = ScVal::Vec(Some(ScVec(
+                vec![
+                    ScVal::Symbol(ScSymbol(BALANCE_KEY_PREFIX.try_into()?)),
+                    ScVal::Address(addr.metered_clone(e)?),
+                ]
+                .try_into()?,
+            )));
+            let lk = Rc::metered_new(
+                LedgerKey::ContractData(LedgerKeyContractData {
+                    contract: ScAddress::Contract(contract_id.metered_clone(e)?),
+                    key,
+                    durability: ContractDataDurability::Persistent,
+                }),
+                e,
+            )?;
+            let entry = e.with_mut_storage(|storage| storage.try_get(&lk, e.as_budget()))?;
+            let Some(entry) = entry else {
+                return Ok(0);
+            };
+            let LedgerEntryData::ContractData(data) = &entry.data else {
+                return Err(e.error(
+                    ContractError::InternalError.into(),
+                    "unexpected entry found",
+                    &[],
+                ));
+            };
+            let balance: BalanceValue = e.to_host_val(&data.val)?.try_into_val(e)?;
+            Ok(balance.amount)
+        }
+    }
+}
+
 // Metering: *mostly* covered by components.
 pub fn get_spendable_balance(e: &Host, addr: Address) -> Result<i128, HostError> {
     match addr.to_sc_address()? {
@@ -62,21 +113,20 @@ pub fn get_spendable_balance(e: &Host, addr: Address) -> Result<i128, HostError>
     }
 }
 
-fn write_balance(e: &Host, addr: Address, balance: BalanceValue) -> Result<(), HostError> {
-    let key = DataKey::Balance(addr);
-    e.put_contract_data(
-        key.try_into_val(e)?,
-        balance.try_into_val(e)?,
+fn balances(e: &Host) -> StorageMap<Address, BalanceValue> {
+    StorageMap::new(
+        e,
+        BALANCE_KEY_PREFIX,
         StorageType::Persistent,
-    )?;
+        Some(BumpPolicy {
+            low_expiration_watermark: BALANCE_LIFETIME_THRESHOLD,
+            high_expiration_watermark: BALANCE_BUMP_AMOUNT,
+        }),
+    )
+}
 
-    e.bump_contract_data(
-        key.try_into_val(e)?,
-        StorageType::Persistent,
-        BALANCE_LIFETIME_THRESHOLD.into(),
-        BALANCE_BUMP_AMOUNT.into(),
-    )?;
-    Ok(())
+fn write_balance(e: &Host, addr: Address, balance: BalanceValue) -> Result<(), HostError> {
+    balances(e).set(&addr, &balance)
 }
 
 // Metering: covered by components.
@@ -101,11 +151,8 @@ pub fn receive_balance(e: &Host, addr: Address, amount: i128) -> Result<(), Host
             Ok(transfer_classic_balance(e, acc_id, i64_amount)?)
         }
         ScAddress::Contract(_) => {
-            let key = DataKey::Balance(addr.metered_clone(e)?);
-            let mut balance = if let Some(raw_balance) =
-                StorageUtils::try_get(e, key.try_into_val(e)?, StorageType::Persistent)?
-            {
-                raw_balance.try_into_val(e)?
+            let mut balance = if let Some(balance) = balances(e).get(&addr)? {
+                balance
             } else {
                 // balance passed the authorization check at the top of this function, so write true.
                 BalanceValue {
@@ -149,11 +196,7 @@ pub fn spend_balance_no_authorization_check(
         ScAddress::Contract(_) => {
             // If a balance exists, calculate new amount and write the existing authorized state as is because
             // this can be used to clawback when deauthorized.
-            let key = DataKey::Balance(addr.metered_clone(e)?);
-            if let Some(raw_balance) =
-                StorageUtils::try_get(e, key.try_into_val(e)?, StorageType::Persistent)?
-            {
-                let mut balance: BalanceValue = raw_balance.try_into_val(e)?;
+            if let Some(mut balance) = balances(e).get(&addr)? {
                 if balance.amount < amount {
                     return Err(err!(
                         e,
@@ -205,11 +248,7 @@ pub fn is_authorized(e: &Host, addr: Address) -> Result<bool, HostError> {
     match addr.to_sc_address()? {
         ScAddress::Account(acc_id) => is_account_authorized(e, acc_id),
         ScAddress::Contract(_) => {
-            let key = DataKey::Balance(addr);
-            if let Some(raw_balance) =
-                StorageUtils::try_get(e, key.try_into_val(e)?, StorageType::Persistent)?
-            {
-                let balance: BalanceValue = raw_balance.try_into_val(e)?;
+            if let Some(balance) = balances(e).get(&addr)? {
                 Ok(balance.authorized)
             } else {
                 Ok(!is_asset_auth_required(e)?)
@@ -231,11 +270,7 @@ pub fn write_authorization(e: &Host, addr: Address, authorize: bool) -> Result<(
     match addr.to_sc_address()? {
         ScAddress::Account(acc_id) => set_authorization(e, acc_id, authorize),
         ScAddress::Contract(_) => {
-            let key = DataKey::Balance(addr.metered_clone(e)?);
-            if let Some(raw_balance) =
-                StorageUtils::try_get(e, key.try_into_val(e)?, StorageType::Persistent)?
-            {
-                let mut balance: BalanceValue = raw_balance.try_into_val(e)?;
+            if let Some(mut balance) = balances(e).get(&addr)? {
                 balance.authorized = authorize;
                 write_balance(e, addr, balance)
             } else {
@@ -305,11 +340,7 @@ pub fn check_clawbackable(e: &Host, addr: Address) -> Result<(), HostError> {
             }
         },
         ScAddress::Contract(_) => {
-            let key = DataKey::Balance(addr);
-            if let Some(raw_balance) =
-                StorageUtils::try_get(e, key.try_into_val(e)?, StorageType::Persistent)?
-            {
-                let balance: BalanceValue = raw_balance.try_into_val(e)?;
+            if let Some(balance) = balances(e).get(&addr)? {
                 if !balance.clawback {
                     return Err(e.error(
                         ContractError::BalanceError.into(),
@@ -375,43 +406,46 @@ pub fn transfer_classic_balance(e: &Host, to_key: AccountId, amount: i64) -> Res
 // TODO: Metering analysis
 //returns (total balance, spendable balance)
 fn get_classic_balance(e: &Host, to_key: AccountId) -> Result<(i64, i64), HostError> {
-    let get_trustline_balance_safe = |asset: TrustLineAsset,
-                                      issuer: AccountId,
-                                      to: AccountId|
-     -> Result<(i64, i64), HostError> {
-        if issuer == to {
-            return Ok((i64::MAX, i64::MAX));
-        }
-
-        get_trustline_balance(e, to, asset)
+    let asset = match read_asset_info(e)? {
+        AssetInfo::Native => TrustLineAsset::Native,
+        AssetInfo::AlphaNum4(asset) => e.create_asset_4(
+            asset.asset_code.to_array()?,
+            e.account_id_from_bytesobj(asset.issuer.into())?,
+        ),
+        AssetInfo::AlphaNum12(asset) => e.create_asset_12(
+            asset.asset_code.to_array()?,
+            e.account_id_from_bytesobj(asset.issuer.into())?,
+        ),
     };
+    get_asset_balance(e, asset, to_key)
+}
 
-    match read_asset_info(e)? {
-        AssetInfo::Native => get_account_balance(e, to_key),
-        AssetInfo::AlphaNum4(asset) => {
-            let issuer_account_id = e.account_id_from_bytesobj(asset.issuer.into())?;
-            get_trustline_balance_safe(
-                e.create_asset_4(
-                    asset.asset_code.to_array()?,
-                    issuer_account_id.metered_clone(e)?,
-                ),
-                issuer_account_id,
-                to_key,
-            )
-        }
-
-        AssetInfo::AlphaNum12(asset) => {
-            let issuer_account_id = e.account_id_from_bytesobj(asset.issuer.into())?;
-            get_trustline_balance_safe(
-                e.create_asset_12(
-                    asset.asset_code.to_array()?,
-                    issuer_account_id.metered_clone(e)?,
-                ),
-                issuer_account_id,
-                to_key,
-            )
+// Returns the (total balance, spendable balance) of `to_key` in the classic
+// `asset`: its native balance, or its trustline balance, which is unlimited
+// for the issuer of the asset.
+// Metering: covered by components.
+fn get_asset_balance(
+    e: &Host,
+    asset: TrustLineAsset,
+    to_key: AccountId,
+) -> Result<(i64, i64), HostError> {
+    let issuer = match &asset {
+        TrustLineAsset::Native => return get_account_balance(e, to_key),
+        TrustLineAsset::CreditAlphanum4(asset) => &asset.issuer,
+        TrustLineAsset::CreditAlphanum12(asset) => &asset.issuer,
+        TrustLineAsset::PoolShare(_) => {
+            return Err(e.error(
+                ContractError::InternalError.into(),
+                "unexpected pool share asset",
+                &[],
+            ))
         }
+    };
+    if issuer == &to_key {
+        return Ok((i64::MAX, i64::MAX));
     }
+
+    get_trustline_balance(e, to_key, asset)
 }
 
 // Metering: *mostly* covered by components. The arithmetics are free.
@@ -599,7 +633,7 @@ fn get_min_max_account_balance(e: &Host, ae: &AccountEntry) -> Result<(i64, i64)
         ));
     }
 
-    let base_reserve = e.with_ledger_info(|li| Ok(li.base_reserve))? as i64;
+    let base_reserve = e.with_ledger_info_provider(|li| Ok(li.base_reserve()))? as i64;
     if let AccountEntryExt::V1(ext1) = &ae.ext {
         let net_entries = if let AccountEntryExtensionV1Ext::V2(ext2) = &ext1.ext {
             2i64 + (ae.num_sub_entries as i64) + (ext2.num_sponsoring as i64)
@@ -0,0 +1,183 @@
+use crate::{
+    host::frame::ContractReentryMode, AddressObject, Host, HostError, StringObject, Symbol,
+    TryFromVal, TryIntoVal, Val,
+};
+
+/// A typed client for invoking a built-in token contract from the host side,
+/// e.g. by embedders or in host-level tests.
+///
+/// Each method invokes the corresponding token function as a top-level call,
+/// i.e. in the same way as [`Env::call`](crate::Env::call) does, so the
+/// authorization of the operations has to be provided via the host's
+/// authorization manager. The return values are converted to Rust types and
+/// conversion failures are reported as host errors.
+pub struct TokenClient<'a> {
+    host: &'a Host,
+    address: AddressObject,
+}
+
+impl<'a> TokenClient<'a> {
+    /// Creates a client for the token contract at `address`.
+    pub fn new(host: &'a Host, address: AddressObject) -> Self {
+        Self { host, address }
+    }
+
+    /// Returns the address of the token contract.
+    pub fn address(&self) -> AddressObject {
+        self.address
+    }
+
+    fn invoke<T>(&self, func: &str, args: &[Val]) -> Result<T, HostError>
+    where
+        T: TryFromVal<Host, Val>,
+        HostError: From<T::Error>,
+    {
+        let res = self.host.call_n_internal(
+            &self.host.contract_id_from_address(self.address)?,
+            Symbol::try_from_val(self.host, &func)?,
+            args,
+            ContractReentryMode::Prohibited,
+            false,
+        )?;
+        Ok(T::try_from_val(self.host, &res)?)
+    }
+
+    pub fn allowance(
+        &self,
+        from: AddressObject,
+        spender: AddressObject,
+    ) -> Result<i128, HostError> {
+        self.invoke("allowance", &[from.into(), spender.into()])
+    }
+
+    pub fn approve(
+        &self,
+        from: AddressObject,
+        spender: AddressObject,
+        amount: i128,
+        expiration_ledger: u32,
+    ) -> Result<(), HostError> {
+        self.invoke(
+            "approve",
+            &[
+                from.into(),
+                spender.into(),
+                amount.try_into_val(self.host)?,
+                expiration_ledger.into(),
+            ],
+        )
+    }
+
+    pub fn balance(&self, addr: AddressObject) -> Result<i128, HostError> {
+        self.invoke("balance", &[addr.into()])
+    }
+
+    pub fn spendable_balance(&self, addr: AddressObject) -> Result<i128, HostError> {
+        self.invoke("spendable_balance", &[addr.into()])
+    }
+
+    pub fn authorized(&self, addr: AddressObject) -> Result<bool, HostError> {
+        self.invoke("authorized", &[addr.into()])
+    }
+
+    pub fn transfer(
+        &self,
+        from: AddressObject,
+        to: AddressObject,
+        amount: i128,
+    ) -> Result<(), HostError> {
+        self.invoke(
+            "transfer",
+            &[from.into(), to.into(), amount.try_into_val(self.host)?],
+        )
+    }
+
+    pub fn transfer_from(
+        &self,
+        spender: AddressObject,
+        from: AddressObject,
+        to: AddressObject,
+        amount: i128,
+    ) -> Result<(), HostError> {
+        self.invoke(
+            "transfer_from",
+            &[
+                spender.into(),
+                from.into(),
+                to.into(),
+                amount.try_into_val(self.host)?,
+            ],
+        )
+    }
+
+    pub fn burn(&self, from: AddressObject, amount: i128) -> Result<(), HostError> {
+        self.invoke("burn", &[from.into(), amount.try_into_val(self.host)?])
+    }
+
+    pub fn burn_from(
+        &self,
+        spender: AddressObject,
+        from: AddressObject,
+        amount: i128,
+    ) -> Result<(), HostError> {
+        self.invoke(
+            "burn_from",
+            &[spender.into(), from.into(), amount.try_into_val(self.host)?],
+        )
+    }
+
+    pub fn set_authorized(&self, addr: AddressObject, authorize: bool) -> Result<(), HostError> {
+        self.invoke("set_authorized", &[addr.into(), authorize.into()])
+    }
+
+    pub fn mint(&self, to: AddressObject, amount: i128) -> Result<(), HostError> {
+        self.invoke("mint", &[to.into(), amount.try_into_val(self.host)?])
+    }
+
+    pub fn clawback(&self, from: AddressObject, amount: i128) -> Result<(), HostError> {
+        self.invoke("clawback", &[from.into(), amount.try_into_val(self.host)?])
+    }
+
+    pub fn set_admin(&self, new_admin: AddressObject) -> Result<(), HostError> {
+        self.invoke("set_admin", &[new_admin.into()])
+    }
+
+    pub fn propose_admin(
+        &self,
+        new_admin: AddressObject,
+        expiration_ledger: u32,
+    ) -> Result<(), HostError> {
+        self.invoke(
+            "propose_admin",
+            &[new_admin.into(), expiration_ledger.into()],
+        )
+    }
+
+    pub fn accept_admin(&self) -> Result<(), HostError> {
+        self.invoke("accept_admin", &[])
+    }
+
+    pub fn set_paused(&self, paused: bool) -> Result<(), HostError> {
+        self.invoke("set_paused", &[paused.into()])
+    }
+
+    pub fn paused(&self) -> Result<bool, HostError> {
+        self.invoke("paused", &[])
+    }
+
+    pub fn admin(&self) -> Result<AddressObject, HostError> {
+        self.invoke("admin", &[])
+    }
+
+    pub fn decimals(&self) -> Result<u32, HostError> {
+        self.invoke("decimals", &[])
+    }
+
+    pub fn name(&self) -> Result<StringObject, HostError> {
+        self.invoke("name", &[])
+    }
+
+    pub fn symbol(&self) -> Result<StringObject, HostError> {
+        self.invoke("symbol", &[])
+    }
+}
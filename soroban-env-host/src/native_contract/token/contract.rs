@@ -1,27 +1,36 @@
-use crate::host::{metered_clone::MeteredClone, Host};
+use crate::host::{
+    metered_clone::MeteredClone, Host, SAC_ADMIN_TRANSFER_MIN_PROTOCOL, SAC_PAUSE_MIN_PROTOCOL,
+    SAC_PERIODIC_ALLOWANCE_MIN_PROTOCOL,
+};
 use crate::native_contract::base_types::{Address, Bytes, BytesN, String};
 use crate::native_contract::contract_error::ContractError;
-use crate::native_contract::token::allowance::{read_allowance, spend_allowance, write_allowance};
+use crate::native_contract::token::allowance::{
+    read_allowance, read_periodic_allowance, spend_allowance, write_allowance,
+    write_periodic_allowance,
+};
 use crate::native_contract::token::asset_info::{has_asset_info, write_asset_info};
 use crate::native_contract::token::balance::{
     is_authorized, read_balance, receive_balance, spend_balance, write_authorization,
 };
 use crate::native_contract::token::event;
 use crate::native_contract::token::public_types::AssetInfo;
-use crate::{err, HostError};
+use crate::HostError;
 
 use soroban_env_common::xdr::Asset;
 use soroban_env_common::{ConversionError, Env, EnvBase, TryFromVal, TryIntoVal};
 use soroban_native_sdk_macros::contractimpl;
 
-use super::admin::{read_administrator, write_administrator};
+use super::admin::{
+    admin_transfer, check_not_paused, pause_switch, read_administrator, require_administrator,
+    write_administrator,
+};
 use super::asset_info::read_asset_info;
 use super::balance::{
     check_clawbackable, get_spendable_balance, spend_balance_no_authorization_check,
 };
 use super::metadata::{read_name, read_symbol, set_metadata, DECIMAL};
 use super::public_types::{AlphaNum12AssetInfo, AlphaNum4AssetInfo};
-use super::storage_types::{INSTANCE_BUMP_AMOUNT, INSTANCE_LIFETIME_THRESHOLD};
+use super::storage_types::{INSTANCE_BUMP_AMOUNT, INSTANCE_LIFETIME_THRESHOLD, MIGRATIONS};
 
 pub trait TokenTrait {
     /// init_asset can create a contract for a wrapped classic asset
@@ -44,6 +53,27 @@ pub trait TokenTrait {
         expiration_ledger: u32,
     ) -> Result<(), HostError>;
 
+    /// Approves `spender` to spend up to `amount_per_period` of `from`'s
+    /// balance in every window of `period_ledgers` ledgers until
+    /// `expiration_ledger`, starting with a window at the current ledger.
+    /// This is used by `transfer_from` and `burn_from` whenever the regular
+    /// allowance doesn't cover the amount. An `amount_per_period` of 0 removes
+    /// the periodic allowance. Only available from
+    /// `SAC_PERIODIC_ALLOWANCE_MIN_PROTOCOL`.
+    fn approve_periodic(
+        e: &Host,
+        from: Address,
+        spender: Address,
+        amount_per_period: i128,
+        period_ledgers: u32,
+        expiration_ledger: u32,
+    ) -> Result<(), HostError>;
+
+    /// Returns the amount left to spend in the current window of the periodic
+    /// allowance of `spender`. Only available from
+    /// `SAC_PERIODIC_ALLOWANCE_MIN_PROTOCOL`.
+    fn periodic_allowance(e: &Host, from: Address, spender: Address) -> Result<i128, HostError>;
+
     fn balance(e: &Host, addr: Address) -> Result<i128, HostError>;
 
     fn spendable_balance(e: &Host, addr: Address) -> Result<i128, HostError>;
@@ -70,8 +100,35 @@ pub trait TokenTrait {
 
     fn clawback(e: &Host, from: Address, amount: i128) -> Result<(), HostError>;
 
+    /// Makes `new_admin` the admin in a single step, cancelling any pending
+    /// admin transfer from `SAC_ADMIN_TRANSFER_MIN_PROTOCOL`. Requires the
+    /// authorization of the admin.
+    ///
+    /// Prefer `propose_admin` and `accept_admin`, which can't hand the token
+    /// over to an address that can't authorize; `set_admin` is kept for
+    /// compatibility with the existing admin tooling.
     fn set_admin(e: &Host, new_admin: Address) -> Result<(), HostError>;
 
+    /// Proposes `new_admin` as the admin, which takes effect once `new_admin`
+    /// calls `accept_admin` no later than `expiration_ledger`. Replaces any
+    /// pending proposal. Requires the authorization of the admin. Only
+    /// available from `SAC_ADMIN_TRANSFER_MIN_PROTOCOL`.
+    fn propose_admin(e: &Host, new_admin: Address, expiration_ledger: u32)
+        -> Result<(), HostError>;
+
+    /// Accepts the admin transfer proposed with `propose_admin`. Requires the
+    /// authorization of the proposed admin. Only available from
+    /// `SAC_ADMIN_TRANSFER_MIN_PROTOCOL`.
+    fn accept_admin(e: &Host) -> Result<(), HostError>;
+
+    /// Pauses or unpauses the token. While the token is paused, transfers
+    /// and burns fail, while the admin functions remain available. Requires
+    /// the authorization of the admin. Only available from
+    /// `SAC_PAUSE_MIN_PROTOCOL`.
+    fn set_paused(e: &Host, paused: bool) -> Result<(), HostError>;
+
+    fn paused(e: &Host) -> Result<bool, HostError>;
+
     fn admin(e: &Host) -> Result<Address, HostError>;
 
     fn decimals(e: &Host) -> Result<u32, HostError>;
@@ -84,16 +141,7 @@ pub trait TokenTrait {
 pub struct Token;
 
 fn check_nonnegative_amount(e: &Host, amount: i128) -> Result<(), HostError> {
-    if amount < 0 {
-        Err(err!(
-            e,
-            ContractError::NegativeAmountError,
-            "negative amount is not allowed",
-            amount
-        ))
-    } else {
-        Ok(())
-    }
+    e.require_nonnegative_amount(amount)
 }
 
 fn check_non_native(e: &Host) -> Result<(), HostError> {
@@ -178,6 +226,7 @@ impl TokenTrait for Token {
 
         //Write metadata only after asset_info is set
         set_metadata(e)?;
+        MIGRATIONS.init(e)?;
         Ok(())
     }
 
@@ -218,6 +267,55 @@ impl TokenTrait for Token {
         Ok(())
     }
 
+    // Metering: covered by components
+    fn approve_periodic(
+        e: &Host,
+        from: Address,
+        spender: Address,
+        amount_per_period: i128,
+        period_ledgers: u32,
+        expiration_ledger: u32,
+    ) -> Result<(), HostError> {
+        let _span = tracy_span!("native token approve_periodic");
+        e.check_protocol_version_lower_bound(SAC_PERIODIC_ALLOWANCE_MIN_PROTOCOL)?;
+        check_nonnegative_amount(e, amount_per_period)?;
+        from.require_auth()?;
+
+        e.bump_current_contract_instance_and_code(
+            INSTANCE_LIFETIME_THRESHOLD.into(),
+            INSTANCE_BUMP_AMOUNT.into(),
+        )?;
+
+        write_periodic_allowance(
+            e,
+            from.metered_clone(e)?,
+            spender.metered_clone(e)?,
+            amount_per_period,
+            period_ledgers,
+            expiration_ledger,
+        )?;
+        event::approve_periodic(
+            e,
+            from,
+            spender,
+            amount_per_period,
+            period_ledgers,
+            expiration_ledger,
+        )?;
+        Ok(())
+    }
+
+    // Metering: covered by components
+    fn periodic_allowance(e: &Host, from: Address, spender: Address) -> Result<i128, HostError> {
+        let _span = tracy_span!("native token periodic_allowance");
+        e.check_protocol_version_lower_bound(SAC_PERIODIC_ALLOWANCE_MIN_PROTOCOL)?;
+        e.bump_current_contract_instance_and_code(
+            INSTANCE_LIFETIME_THRESHOLD.into(),
+            INSTANCE_BUMP_AMOUNT.into(),
+        )?;
+        read_periodic_allowance(e, from, spender)
+    }
+
     // Metering: covered by components
     fn balance(e: &Host, addr: Address) -> Result<i128, HostError> {
         let _span = tracy_span!("native token balance");
@@ -251,6 +349,7 @@ impl TokenTrait for Token {
     fn transfer(e: &Host, from: Address, to: Address, amount: i128) -> Result<(), HostError> {
         let _span = tracy_span!("native token transfer");
         check_nonnegative_amount(e, amount)?;
+        check_not_paused(e)?;
         from.require_auth()?;
 
         e.bump_current_contract_instance_and_code(
@@ -274,6 +373,7 @@ impl TokenTrait for Token {
     ) -> Result<(), HostError> {
         let _span = tracy_span!("native token transfer_from");
         check_nonnegative_amount(e, amount)?;
+        check_not_paused(e)?;
         spender.require_auth()?;
 
         e.bump_current_contract_instance_and_code(
@@ -292,6 +392,7 @@ impl TokenTrait for Token {
     fn burn(e: &Host, from: Address, amount: i128) -> Result<(), HostError> {
         let _span = tracy_span!("native token burn");
         check_nonnegative_amount(e, amount)?;
+        check_not_paused(e)?;
         check_non_native(e)?;
         from.require_auth()?;
 
@@ -309,6 +410,7 @@ impl TokenTrait for Token {
     fn burn_from(e: &Host, spender: Address, from: Address, amount: i128) -> Result<(), HostError> {
         let _span = tracy_span!("native token burn_from");
         check_nonnegative_amount(e, amount)?;
+        check_not_paused(e)?;
         check_non_native(e)?;
         spender.require_auth()?;
 
@@ -328,8 +430,7 @@ impl TokenTrait for Token {
         let _span = tracy_span!("native token clawback");
         check_nonnegative_amount(e, amount)?;
         check_clawbackable(e, from.metered_clone(e)?)?;
-        let admin = read_administrator(e)?;
-        admin.require_auth()?;
+        let admin = require_administrator(e)?;
 
         e.bump_current_contract_instance_and_code(
             INSTANCE_LIFETIME_THRESHOLD.into(),
@@ -344,8 +445,7 @@ impl TokenTrait for Token {
     // Metering: covered by components
     fn set_authorized(e: &Host, addr: Address, authorize: bool) -> Result<(), HostError> {
         let _span = tracy_span!("native token set_authorized");
-        let admin = read_administrator(e)?;
-        admin.require_auth()?;
+        let admin = require_administrator(e)?;
 
         e.bump_current_contract_instance_and_code(
             INSTANCE_LIFETIME_THRESHOLD.into(),
@@ -361,8 +461,7 @@ impl TokenTrait for Token {
     fn mint(e: &Host, to: Address, amount: i128) -> Result<(), HostError> {
         let _span = tracy_span!("native token mint");
         check_nonnegative_amount(e, amount)?;
-        let admin = read_administrator(e)?;
-        admin.require_auth()?;
+        let admin = require_administrator(e)?;
 
         e.bump_current_contract_instance_and_code(
             INSTANCE_LIFETIME_THRESHOLD.into(),
@@ -377,19 +476,79 @@ impl TokenTrait for Token {
     // Metering: covered by components
     fn set_admin(e: &Host, new_admin: Address) -> Result<(), HostError> {
         let _span = tracy_span!("native token set_admin");
-        let admin = read_administrator(e)?;
-        admin.require_auth()?;
+        let admin = require_administrator(e)?;
+
+        e.bump_current_contract_instance_and_code(
+            INSTANCE_LIFETIME_THRESHOLD.into(),
+            INSTANCE_BUMP_AMOUNT.into(),
+        )?;
+
+        if e.get_ledger_protocol_version()? >= SAC_ADMIN_TRANSFER_MIN_PROTOCOL {
+            admin_transfer(e)?.clear(e)?;
+        }
+        write_administrator(e, new_admin.metered_clone(e)?)?;
+        event::set_admin(e, admin, new_admin)?;
+        Ok(())
+    }
+
+    // Metering: covered by components
+    fn propose_admin(
+        e: &Host,
+        new_admin: Address,
+        expiration_ledger: u32,
+    ) -> Result<(), HostError> {
+        let _span = tracy_span!("native token propose_admin");
+        e.check_protocol_version_lower_bound(SAC_ADMIN_TRANSFER_MIN_PROTOCOL)?;
+        let admin = require_administrator(e)?;
 
         e.bump_current_contract_instance_and_code(
             INSTANCE_LIFETIME_THRESHOLD.into(),
             INSTANCE_BUMP_AMOUNT.into(),
         )?;
 
+        admin_transfer(e)?.propose(e, new_admin.metered_clone(e)?, expiration_ledger)?;
+        event::propose_admin(e, admin, new_admin, expiration_ledger)?;
+        Ok(())
+    }
+
+    // Metering: covered by components
+    fn accept_admin(e: &Host) -> Result<(), HostError> {
+        let _span = tracy_span!("native token accept_admin");
+        e.check_protocol_version_lower_bound(SAC_ADMIN_TRANSFER_MIN_PROTOCOL)?;
+        let new_admin = admin_transfer(e)?.accept(e)?;
+
+        e.bump_current_contract_instance_and_code(
+            INSTANCE_LIFETIME_THRESHOLD.into(),
+            INSTANCE_BUMP_AMOUNT.into(),
+        )?;
+
+        let admin = read_administrator(e)?;
         write_administrator(e, new_admin.metered_clone(e)?)?;
         event::set_admin(e, admin, new_admin)?;
         Ok(())
     }
 
+    // Metering: covered by components
+    fn set_paused(e: &Host, paused: bool) -> Result<(), HostError> {
+        let _span = tracy_span!("native token set_paused");
+        e.check_protocol_version_lower_bound(SAC_PAUSE_MIN_PROTOCOL)?;
+        let admin = require_administrator(e)?;
+
+        e.bump_current_contract_instance_and_code(
+            INSTANCE_LIFETIME_THRESHOLD.into(),
+            INSTANCE_BUMP_AMOUNT.into(),
+        )?;
+
+        pause_switch(e)?.set_paused(e, paused)?;
+        event::set_paused(e, admin, paused)?;
+        Ok(())
+    }
+
+    fn paused(e: &Host) -> Result<bool, HostError> {
+        let _span = tracy_span!("native token paused");
+        pause_switch(e)?.is_paused(e)
+    }
+
     fn admin(e: &Host) -> Result<Address, HostError> {
         let _span = tracy_span!("native token admin");
         read_administrator(e)
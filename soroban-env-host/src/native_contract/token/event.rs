@@ -1,10 +1,121 @@
-use crate::native_contract::base_types::Vec;
+use crate::native_contract::base_types::String;
 use crate::HostError;
 use crate::{host::Host, native_contract::base_types::Address};
-use soroban_env_common::{Env, Symbol, TryFromVal, TryIntoVal};
+use soroban_native_sdk_macros::contractevent;
 
 use super::metadata::read_name;
 
+// All the token events have the event name as the first topic, followed by
+// the addresses involved and the SEP-0011 string of the asset (`native` or
+// `CODE:ISSUER`), so that the asset can be identified without a reverse
+// lookup of the contract id. The asset string is the token's name.
+
+#[contractevent]
+struct Approve {
+    #[topic]
+    from: Address,
+    #[topic]
+    to: Address,
+    #[topic]
+    asset: String,
+    amount: i128,
+    expiration_ledger: u32,
+}
+
+#[contractevent]
+struct ApprovePeriodic {
+    #[topic]
+    from: Address,
+    #[topic]
+    to: Address,
+    #[topic]
+    asset: String,
+    amount_per_period: i128,
+    period_ledgers: u32,
+    expiration_ledger: u32,
+}
+
+#[contractevent]
+struct Transfer {
+    #[topic]
+    from: Address,
+    #[topic]
+    to: Address,
+    #[topic]
+    asset: String,
+    amount: i128,
+}
+
+#[contractevent]
+struct Mint {
+    #[topic]
+    admin: Address,
+    #[topic]
+    to: Address,
+    #[topic]
+    asset: String,
+    amount: i128,
+}
+
+#[contractevent]
+struct Clawback {
+    #[topic]
+    admin: Address,
+    #[topic]
+    from: Address,
+    #[topic]
+    asset: String,
+    amount: i128,
+}
+
+#[contractevent]
+struct SetAuthorized {
+    #[topic]
+    admin: Address,
+    #[topic]
+    id: Address,
+    #[topic]
+    asset: String,
+    authorize: bool,
+}
+
+#[contractevent]
+struct SetAdmin {
+    #[topic]
+    admin: Address,
+    #[topic]
+    asset: String,
+    new_admin: Address,
+}
+
+#[contractevent]
+struct ProposeAdmin {
+    #[topic]
+    admin: Address,
+    #[topic]
+    asset: String,
+    new_admin: Address,
+    expiration_ledger: u32,
+}
+
+#[contractevent]
+struct SetPaused {
+    #[topic]
+    admin: Address,
+    #[topic]
+    asset: String,
+    paused: bool,
+}
+
+#[contractevent]
+struct Burn {
+    #[topic]
+    from: Address,
+    #[topic]
+    asset: String,
+    amount: i128,
+}
+
 pub(crate) fn approve(
     e: &Host,
     from: Address,
@@ -12,17 +123,33 @@ pub(crate) fn approve(
     amount: i128,
     expiration_ledger: u32,
 ) -> Result<(), HostError> {
-    let mut topics = Vec::new(e)?;
-    topics.push(&Symbol::try_from_val(e, &"approve")?)?;
-    topics.push(&from)?;
-    topics.push(&to)?;
-    topics.push(&read_name(e)?)?;
+    Approve {
+        from,
+        to,
+        asset: read_name(e)?,
+        amount,
+        expiration_ledger,
+    }
+    .publish(e)
+}
 
-    let mut data = Vec::new(e)?;
-    data.push(&amount)?;
-    data.push(&expiration_ledger)?;
-    e.contract_event(topics.into(), data.into())?;
-    Ok(())
+pub(crate) fn approve_periodic(
+    e: &Host,
+    from: Address,
+    to: Address,
+    amount_per_period: i128,
+    period_ledgers: u32,
+    expiration_ledger: u32,
+) -> Result<(), HostError> {
+    ApprovePeriodic {
+        from,
+        to,
+        asset: read_name(e)?,
+        amount_per_period,
+        period_ledgers,
+        expiration_ledger,
+    }
+    .publish(e)
 }
 
 pub(crate) fn transfer(
@@ -31,23 +158,23 @@ pub(crate) fn transfer(
     to: Address,
     amount: i128,
 ) -> Result<(), HostError> {
-    let mut topics = Vec::new(e)?;
-    topics.push(&Symbol::try_from_val(e, &"transfer")?)?;
-    topics.push(&from)?;
-    topics.push(&to)?;
-    topics.push(&read_name(e)?)?;
-    e.contract_event(topics.into(), amount.try_into_val(e)?)?;
-    Ok(())
+    Transfer {
+        from,
+        to,
+        asset: read_name(e)?,
+        amount,
+    }
+    .publish(e)
 }
 
 pub(crate) fn mint(e: &Host, admin: Address, to: Address, amount: i128) -> Result<(), HostError> {
-    let mut topics = Vec::new(e)?;
-    topics.push(&Symbol::try_from_val(e, &"mint")?)?;
-    topics.push(&admin)?;
-    topics.push(&to)?;
-    topics.push(&read_name(e)?)?;
-    e.contract_event(topics.into(), amount.try_into_val(e)?)?;
-    Ok(())
+    Mint {
+        admin,
+        to,
+        asset: read_name(e)?,
+        amount,
+    }
+    .publish(e)
 }
 
 pub(crate) fn clawback(
@@ -56,13 +183,13 @@ pub(crate) fn clawback(
     from: Address,
     amount: i128,
 ) -> Result<(), HostError> {
-    let mut topics = Vec::new(e)?;
-    topics.push(&Symbol::try_from_val(e, &"clawback")?)?;
-    topics.push(&admin)?;
-    topics.push(&from)?;
-    topics.push(&read_name(e)?)?;
-    e.contract_event(topics.into(), amount.try_into_val(e)?)?;
-    Ok(())
+    Clawback {
+        admin,
+        from,
+        asset: read_name(e)?,
+        amount,
+    }
+    .publish(e)
 }
 
 pub(crate) fn set_authorized(
@@ -71,29 +198,53 @@ pub(crate) fn set_authorized(
     id: Address,
     authorize: bool,
 ) -> Result<(), HostError> {
-    let mut topics = Vec::new(e)?;
-    topics.push(&Symbol::try_from_val(e, &"set_authorized")?)?;
-    topics.push(&admin)?;
-    topics.push(&id)?;
-    topics.push(&read_name(e)?)?;
-    e.contract_event(topics.into(), authorize.try_into_val(e)?)?;
-    Ok(())
+    SetAuthorized {
+        admin,
+        id,
+        asset: read_name(e)?,
+        authorize,
+    }
+    .publish(e)
 }
 
 pub(crate) fn set_admin(e: &Host, admin: Address, new_admin: Address) -> Result<(), HostError> {
-    let mut topics = Vec::new(e)?;
-    topics.push(&Symbol::try_from_val(e, &"set_admin")?)?;
-    topics.push(&admin)?;
-    topics.push(&read_name(e)?)?;
-    e.contract_event(topics.into(), new_admin.try_into_val(e)?)?;
-    Ok(())
+    SetAdmin {
+        admin,
+        asset: read_name(e)?,
+        new_admin,
+    }
+    .publish(e)
+}
+
+pub(crate) fn propose_admin(
+    e: &Host,
+    admin: Address,
+    new_admin: Address,
+    expiration_ledger: u32,
+) -> Result<(), HostError> {
+    ProposeAdmin {
+        admin,
+        asset: read_name(e)?,
+        new_admin,
+        expiration_ledger,
+    }
+    .publish(e)
+}
+
+pub(crate) fn set_paused(e: &Host, admin: Address, paused: bool) -> Result<(), HostError> {
+    SetPaused {
+        admin,
+        asset: read_name(e)?,
+        paused,
+    }
+    .publish(e)
 }
 
 pub(crate) fn burn(e: &Host, from: Address, amount: i128) -> Result<(), HostError> {
-    let mut topics = Vec::new(e)?;
-    topics.push(&Symbol::try_from_val(e, &"burn")?)?;
-    topics.push(&from)?;
-    topics.push(&read_name(e)?)?;
-    e.contract_event(topics.into(), amount.try_into_val(e)?)?;
-    Ok(())
+    Burn {
+        from,
+        asset: read_name(e)?,
+        amount,
+    }
+    .publish(e)
 }
@@ -3,7 +3,12 @@ use std::fmt::Write;
 use soroban_native_sdk_macros::contracttype;
 use stellar_strkey::ed25519;
 
-use crate::{host::Host, native_contract::base_types::BytesN, HostError};
+use crate::{
+    host::Host,
+    native_contract::base_types::BytesN,
+    xdr::{Asset, ContractCostType, ContractExecutable, Hash, ScErrorCode, ScErrorType, ScVal},
+    HostError,
+};
 use soroban_env_common::{
     ConversionError, Env, EnvBase, StorageType, SymbolSmall, TryFromVal, TryIntoVal,
 };
@@ -22,7 +27,7 @@ pub struct TokenMetadata {
     pub symbol: String,
 }
 
-pub const DECIMAL: u32 = 7;
+pub const DECIMAL: u32 = crate::CLASSIC_ASSET_DECIMALS;
 
 // This does a specific and fairly unique escaping transformation as defined
 // in TxRep / SEP-0011.
@@ -200,3 +205,109 @@ pub fn read_symbol(e: &Host) -> Result<String, HostError> {
         .try_into_val(e)?;
     Ok(metadata.symbol)
 }
+
+/// Identifies a built-in token for [`Host::token_metadata`]: either by the
+/// classic asset it wraps, or directly by its contract ID.
+#[derive(Clone, Debug)]
+pub enum AssetOrContract {
+    Asset(Asset),
+    Contract(Hash),
+}
+
+/// Metadata of a built-in token, as returned by [`Host::token_metadata`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TokenMetadataInfo {
+    pub decimals: u32,
+    pub name: std::string::String,
+    pub symbol: std::string::String,
+}
+
+impl Host {
+    /// Reads the name, symbol and decimals of a built-in token contract
+    /// directly from its instance ledger entry. Unlike calling the `name`,
+    /// `symbol` and `decimals` contract functions, this does not push any
+    /// frames, bump any entries or touch the authorization manager, so it is
+    /// suitable for embedders serving large batches of metadata lookups.
+    ///
+    /// The token instance entry still has to be accessible through the
+    /// storage (i.e. be present in the footprint in enforcing mode).
+    pub fn token_metadata(&self, token: AssetOrContract) -> Result<TokenMetadataInfo, HostError> {
+        let contract_id = match token {
+            AssetOrContract::Asset(asset) => self.get_asset_contract_id_hash(asset)?,
+            AssetOrContract::Contract(contract_id) => contract_id,
+        };
+        let key = self.contract_instance_ledger_key(&contract_id)?;
+        let instance = self
+            .retrieve_contract_instance_from_storage(&key)
+            .map_err(|e| self.decorate_contract_instance_storage_error(e, &contract_id))?;
+        if !matches!(instance.executable, ContractExecutable::Token) {
+            return Err(self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "contract is not a built-in token",
+                &[],
+            ));
+        }
+        let metadata = instance
+            .storage
+            .as_ref()
+            .and_then(|storage| {
+                storage.iter().find(|entry| {
+                    matches!(&entry.key, ScVal::Symbol(k) if k.as_slice() == METADATA_KEY.as_bytes())
+                })
+            })
+            .ok_or_else(|| {
+                self.err(
+                    ScErrorType::Storage,
+                    ScErrorCode::MissingValue,
+                    "token metadata is missing from instance storage",
+                    &[],
+                )
+            })?;
+
+        let mut decimals = None;
+        let mut name = None;
+        let mut symbol = None;
+        if let ScVal::Map(Some(fields)) = &metadata.val {
+            for field in fields.iter() {
+                match (&field.key, &field.val) {
+                    (ScVal::Symbol(k), ScVal::U32(d)) if k.as_slice() == b"decimal" => {
+                        decimals = Some(*d)
+                    }
+                    (ScVal::Symbol(k), ScVal::String(s)) if k.as_slice() == b"name" => {
+                        name = Some(self.metadata_string_from_slice(s.as_slice())?)
+                    }
+                    (ScVal::Symbol(k), ScVal::String(s)) if k.as_slice() == b"symbol" => {
+                        symbol = Some(self.metadata_string_from_slice(s.as_slice())?)
+                    }
+                    _ => (),
+                }
+            }
+        }
+        match (decimals, name, symbol) {
+            (Some(decimals), Some(name), Some(symbol)) => Ok(TokenMetadataInfo {
+                decimals,
+                name,
+                symbol,
+            }),
+            _ => Err(self.err(
+                ScErrorType::Storage,
+                ScErrorCode::InternalError,
+                "malformed token metadata in instance storage",
+                &[],
+            )),
+        }
+    }
+
+    fn metadata_string_from_slice(&self, s: &[u8]) -> Result<std::string::String, HostError> {
+        self.charge_budget(ContractCostType::HostMemCpy, Some(s.len() as u64))?;
+        std::string::String::from_utf8(s.to_vec()).map_err(|_| {
+            self.err(
+                ScErrorType::Value,
+                ScErrorCode::InvalidInput,
+                "token metadata string is not valid utf-8",
+                &[],
+            )
+        })
+    }
+}
@@ -1,4 +1,5 @@
 use crate::native_contract::base_types::Address;
+use crate::native_contract::migrations::Migrations;
 use soroban_env_common::TryIntoVal;
 use soroban_native_sdk_macros::contracttype;
 
@@ -8,6 +9,18 @@ pub(crate) const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_I
 
 pub(crate) const BALANCE_BUMP_AMOUNT: u32 = 30 * DAY_IN_LEDGERS;
 pub(crate) const BALANCE_LIFETIME_THRESHOLD: u32 = BALANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
+// Balances are stored in a `StorageMap` under this prefix, which keeps their
+// keys identical to the former `DataKey::Balance(Address)` variant.
+pub(crate) const BALANCE_KEY_PREFIX: &str = "Balance";
+// Allowances are stored in `StorageMap`s under these prefixes, which keep
+// their keys identical to the former `DataKey::Allowance(AllowanceDataKey)`
+// and `DataKey::PeriodicAllowance(AllowanceDataKey)` variants.
+pub(crate) const ALLOWANCE_KEY_PREFIX: &str = "Allowance";
+pub(crate) const PERIODIC_ALLOWANCE_KEY_PREFIX: &str = "PeriodicAllowance";
+
+/// The migrations of the token instance storage, see [`Migrations`]. The
+/// layout hasn't changed since it's been versioned, so there are none yet.
+pub(crate) const MIGRATIONS: Migrations = Migrations::new(&[]);
 
 #[contracttype]
 pub struct AllowanceDataKey {
@@ -21,6 +34,18 @@ pub struct AllowanceValue {
     pub expiration_ledger: u32,
 }
 
+/// Allowance that can be spent up to `amount_per_period` in every window of
+/// `period_ledgers` ledgers. `period_start` is the first ledger of the
+/// window that `spent_in_period` refers to.
+#[contracttype]
+pub struct PeriodicAllowanceValue {
+    pub amount_per_period: i128,
+    pub period_ledgers: u32,
+    pub expiration_ledger: u32,
+    pub period_start: u32,
+    pub spent_in_period: i128,
+}
+
 #[contracttype]
 pub struct BalanceValue {
     pub amount: i128,
@@ -28,16 +53,14 @@ pub struct BalanceValue {
     pub clawback: bool,
 }
 
-/// Keys for the persistent data associated with token users.
-#[contracttype]
-pub enum DataKey {
-    Allowance(AllowanceDataKey),
-    Balance(Address),
-}
-
 /// Keys for token instance data.
 #[contracttype]
 pub enum InstanceDataKey {
     Admin,
     AssetInfo,
+    // Only present while the token is paused, see `Pausable`.
+    Paused,
+    // Only present while an admin transfer is pending, see
+    // `OwnershipTransfer`.
+    PendingAdmin,
 }
@@ -101,6 +101,43 @@ impl<'a> TestToken<'a> {
             .try_into_val(self.host)?)
     }
 
+    pub(crate) fn approve_periodic(
+        &self,
+        from: &TestSigner,
+        spender: Address,
+        amount_per_period: i128,
+        period_ledgers: u32,
+        expiration_ledger: u32,
+    ) -> Result<(), HostError> {
+        self.call_with_single_signer(
+            from,
+            "approve_periodic",
+            host_vec![
+                self.host,
+                from.address(self.host),
+                spender,
+                amount_per_period,
+                period_ledgers,
+                expiration_ledger
+            ],
+        )
+    }
+
+    pub(crate) fn periodic_allowance(
+        &self,
+        from: Address,
+        spender: Address,
+    ) -> Result<i128, HostError> {
+        Ok(self
+            .host
+            .call(
+                self.address.clone().into(),
+                Symbol::try_from_val(self.host, &"periodic_allowance")?,
+                host_vec![self.host, from, spender].into(),
+            )?
+            .try_into_val(self.host)?)
+    }
+
     pub(crate) fn spendable_balance(&self, addr: Address) -> Result<i128, HostError> {
         Ok(self
             .host
@@ -210,6 +247,38 @@ impl<'a> TestToken<'a> {
         self.call_with_single_signer(admin, "set_admin", host_vec![self.host, new_admin])
     }
 
+    pub(crate) fn propose_admin(
+        &self,
+        admin: &TestSigner,
+        new_admin: Address,
+        expiration_ledger: u32,
+    ) -> Result<(), HostError> {
+        self.call_with_single_signer(
+            admin,
+            "propose_admin",
+            host_vec![self.host, new_admin, expiration_ledger],
+        )
+    }
+
+    pub(crate) fn accept_admin(&self, new_admin: &TestSigner) -> Result<(), HostError> {
+        self.call_with_single_signer(new_admin, "accept_admin", host_vec![self.host])
+    }
+
+    pub(crate) fn set_paused(&self, admin: &TestSigner, paused: bool) -> Result<(), HostError> {
+        self.call_with_single_signer(admin, "set_paused", host_vec![self.host, paused])
+    }
+
+    pub(crate) fn paused(&self) -> Result<bool, HostError> {
+        Ok(self
+            .host
+            .call(
+                self.address.clone().into(),
+                Symbol::try_from_val(self.host, &"paused")?,
+                host_vec![self.host].into(),
+            )?
+            .try_into_val(self.host)?)
+    }
+
     pub(crate) fn admin(&self) -> Result<Address, HostError> {
         self.host
             .call(
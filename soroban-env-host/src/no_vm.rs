@@ -0,0 +1,172 @@
+//! Stand-in for the [`vm`](crate::vm) module in builds without the `vm`
+//! feature, where no Wasm code can be executed. Wasm contracts can neither be
+//! uploaded nor invoked then, while native contracts (and test contracts)
+//! work as usual.
+//!
+//! The stand-in [`Vm`] can't be constructed, so the code paths that require
+//! an instantiated VM, such as the linear-memory host functions, fail at the
+//! lookup of the VM of the current frame.
+
+use std::{convert::Infallible, rc::Rc};
+
+use crate::{
+    xdr::{Hash, ScErrorCode, ScErrorType},
+    Host, HostError, Symbol, Val, VmCaller,
+};
+
+fn err_vm_unavailable(host: &Host) -> HostError {
+    host.err(
+        ScErrorType::WasmVm,
+        ScErrorCode::InvalidAction,
+        "the Wasm VM is not available in this build",
+        &[],
+    )
+}
+
+/// A Wasm VM, which can't exist without the `vm` feature.
+pub struct Vm {
+    pub(crate) contract_id: Hash,
+    unconstructible: Infallible,
+}
+
+pub(crate) struct ParsedModule {
+    unconstructible: Infallible,
+}
+
+/// The module cache, which stays empty without the `vm` feature.
+#[derive(Clone, Default)]
+pub(crate) struct ModuleCache;
+
+impl ParsedModule {
+    pub(crate) fn new(host: &Host, _module_wasm_code: &[u8]) -> Result<Self, HostError> {
+        Err(err_vm_unavailable(host))
+    }
+
+    pub(crate) fn new_or_cached(
+        host: &Host,
+        _wasm_hash: &Hash,
+        _module_wasm_code: &[u8],
+    ) -> Result<Rc<Self>, HostError> {
+        Err(err_vm_unavailable(host))
+    }
+
+    pub(crate) fn check_function_export(
+        &self,
+        _host: &Host,
+        _func_sym: &Symbol,
+    ) -> Result<(), HostError> {
+        match self.unconstructible {}
+    }
+}
+
+impl Vm {
+    /// Always fails with `(WasmVm, InvalidAction)`.
+    pub fn new(
+        host: &Host,
+        _contract_id: Hash,
+        _module_wasm_code: &[u8],
+    ) -> Result<Rc<Self>, HostError> {
+        Err(err_vm_unavailable(host))
+    }
+
+    pub(crate) fn instantiate(
+        _host: &Host,
+        _contract_id: Hash,
+        parsed: Rc<ParsedModule>,
+    ) -> Result<Rc<Self>, HostError> {
+        match parsed.unconstructible {}
+    }
+
+    pub(crate) fn has_function_export(&self, _name: &str) -> bool {
+        match self.unconstructible {}
+    }
+
+    pub(crate) fn invoke_function_raw(
+        self: &Rc<Self>,
+        _host: &Host,
+        _func_sym: &Symbol,
+        _args: &[Val],
+    ) -> Result<Val, HostError> {
+        match self.unconstructible {}
+    }
+
+    pub fn custom_section(&self, _name: impl AsRef<str>) -> Option<&[u8]> {
+        match self.unconstructible {}
+    }
+
+    pub(crate) fn metered_custom_section(
+        &self,
+        _host: &Host,
+        _name: &[u8],
+    ) -> Result<Option<&[u8]>, HostError> {
+        match self.unconstructible {}
+    }
+}
+
+impl Host {
+    /// Always fails with `(WasmVm, InvalidAction)`.
+    pub fn cache_module(&self, _wasm: &[u8]) -> Result<Hash, HostError> {
+        Err(err_vm_unavailable(self))
+    }
+
+    pub fn clear_module_cache(&self) -> Result<(), HostError> {
+        Ok(())
+    }
+}
+
+// The linear-memory accessors of the host, which can't be reached without a
+// VM.
+impl Host {
+    pub(crate) fn metered_vm_write_bytes_to_linear_memory(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        vm: &Rc<Vm>,
+        _mem_pos: u32,
+        _buf: &[u8],
+    ) -> Result<(), HostError> {
+        match vm.unconstructible {}
+    }
+
+    pub(crate) fn metered_vm_read_bytes_from_linear_memory(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        vm: &Rc<Vm>,
+        _mem_pos: u32,
+        _buf: &mut [u8],
+    ) -> Result<(), HostError> {
+        match vm.unconstructible {}
+    }
+
+    pub(crate) fn metered_vm_write_vals_to_linear_memory<const VAL_SZ: usize, VAL>(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        vm: &Rc<Vm>,
+        _mem_pos: u32,
+        _buf: &[VAL],
+        _to_le_bytes: impl Fn(&VAL) -> Result<[u8; VAL_SZ], HostError>,
+    ) -> Result<(), HostError> {
+        match vm.unconstructible {}
+    }
+
+    pub(crate) fn metered_vm_read_vals_from_linear_memory<const VAL_SZ: usize, VAL>(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        vm: &Rc<Vm>,
+        _mem_pos: u32,
+        _buf: &mut [VAL],
+        _from_le_bytes: impl Fn(&[u8; VAL_SZ]) -> Result<VAL, HostError>,
+    ) -> Result<(), HostError> {
+        match vm.unconstructible {}
+    }
+
+    pub(crate) fn metered_vm_scan_slices_in_linear_memory(
+        &self,
+        _vmcaller: &mut VmCaller<Host>,
+        vm: &Rc<Vm>,
+        _mem_pos: u32,
+        _num_slices: usize,
+        _callback: impl FnMut(usize, &[u8]) -> Result<(), HostError>,
+    ) -> Result<(), HostError> {
+        match vm.unconstructible {}
+    }
+}
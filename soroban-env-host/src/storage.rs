@@ -7,13 +7,20 @@
 //!   - [Env::put_contract_data](crate::Env::put_contract_data)
 //!   - [Env::del_contract_data](crate::Env::del_contract_data)
 
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::rc::Rc;
 
-use soroban_env_common::xdr::{ScErrorCode, ScErrorType};
+use sha2::{Digest, Sha256};
+use soroban_env_common::xdr::{ContractCostType, ScErrorCode, ScErrorType};
 use soroban_env_common::{Env, Val};
 
 use crate::budget::Budget;
-use crate::xdr::{LedgerEntry, LedgerKey};
+use crate::host::metered_clone::MeteredClone;
+use crate::host::metered_xdr::metered_write_xdr;
+use crate::xdr::{
+    ContractDataDurability, Hash, LedgerEntry, LedgerEntryData, LedgerKey, ScAddress, ScVal,
+};
 use crate::Host;
 use crate::{host::metered_map::MeteredOrdMap, HostError};
 
@@ -59,6 +66,195 @@ pub trait SnapshotSource {
     fn has(&self, key: &Rc<LedgerKey>) -> Result<bool, HostError>;
 }
 
+/// An embedder-provided source of contract Wasm code that the
+/// [Host](crate::Host) consults before [Storage] when loading `ContractCode`
+/// entries for execution.
+///
+/// This allows embedders to serve code from a cache shared across
+/// invocations (or even processes) instead of copying it through the
+/// [SnapshotSource] or the storage map on every invocation. Code returned by
+/// the store is verified against the requested hash before use, and access
+/// to the corresponding `ContractCode` key is still recorded or enforced by
+/// the [Footprint].
+pub trait CodeStore {
+    /// Returns the Wasm code with the given hash, or `None` if the store
+    /// doesn't hold it (in which case the host falls back to [Storage]).
+    fn get_code(&self, wasm_hash: &Hash) -> Result<Option<Rc<[u8]>>, HostError>;
+}
+
+/// Hit/miss counters of a [SnapshotCache].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SnapshotCacheMetrics {
+    /// Number of reads served from the cache.
+    pub hits: u64,
+    /// Number of reads that had to go to the [SnapshotSource].
+    pub misses: u64,
+    /// Number of entries dropped to stay within the size bound.
+    pub evictions: u64,
+}
+
+/// Counts and XDR sizes of the ledger entry accesses of a [Storage], see
+/// [Storage::enable_access_stats].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StorageAccessStats {
+    /// Number of reads, including existence checks.
+    pub reads: u32,
+    /// Total size of the entries read.
+    pub read_bytes: u64,
+    /// Number of writes, including deletions.
+    pub writes: u32,
+    /// Total size of the entries written.
+    pub write_bytes: u64,
+}
+
+impl StorageAccessStats {
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub(crate) fn saturating_add(&self, other: &Self) -> Self {
+        Self {
+            reads: self.reads.saturating_add(other.reads),
+            read_bytes: self.read_bytes.saturating_add(other.read_bytes),
+            writes: self.writes.saturating_add(other.writes),
+            write_bytes: self.write_bytes.saturating_add(other.write_bytes),
+        }
+    }
+
+    pub(crate) fn saturating_sub(&self, other: &Self) -> Self {
+        Self {
+            reads: self.reads.saturating_sub(other.reads),
+            read_bytes: self.read_bytes.saturating_sub(other.read_bytes),
+            writes: self.writes.saturating_sub(other.writes),
+            write_bytes: self.write_bytes.saturating_sub(other.write_bytes),
+        }
+    }
+}
+
+type SnapshotCacheValue = Option<(Rc<LedgerEntry>, Option<u32>)>;
+
+struct SnapshotCacheImpl {
+    max_entries: usize,
+    // Maps keys to their cached value and the tick of their latest use.
+    entries: BTreeMap<Rc<LedgerKey>, (SnapshotCacheValue, u64)>,
+    // Maps ticks of use to keys, the first key being the least recently used.
+    recency: BTreeMap<u64, Rc<LedgerKey>>,
+    tick: u64,
+    metrics: SnapshotCacheMetrics,
+}
+
+impl SnapshotCacheImpl {
+    fn touch(&mut self, key: &Rc<LedgerKey>) -> Option<SnapshotCacheValue> {
+        let (value, last_used) = self.entries.get_mut(key)?;
+        self.recency.remove(last_used);
+        self.tick += 1;
+        *last_used = self.tick;
+        self.recency.insert(self.tick, Rc::clone(key));
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: Rc<LedgerKey>, value: SnapshotCacheValue) {
+        if self.max_entries == 0 {
+            return;
+        }
+        if let Some((_, last_used)) = self.entries.remove(&key) {
+            self.recency.remove(&last_used);
+        }
+        while self.entries.len() >= self.max_entries {
+            let Some((_, lru_key)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&lru_key);
+            self.metrics.evictions += 1;
+        }
+        self.tick += 1;
+        self.recency.insert(self.tick, Rc::clone(&key));
+        self.entries.insert(key, (value, self.tick));
+    }
+}
+
+/// A least-recently-used cache of [SnapshotSource] reads, holding at most a
+/// fixed number of entries.
+///
+/// The cache is a cheaply clonable handle, so a single cache can be shared
+/// by the [Storage] of all the [Host](crate::Host)s that run against the same
+/// snapshot (e.g. the transactions of a single ledger). Only reads of the
+/// snapshot itself are cached, entries written by a host are never visible to
+/// the other hosts. Using a cache with a different snapshot than the one it
+/// has been populated from will return stale entries.
+#[derive(Clone)]
+pub struct SnapshotCache(Rc<RefCell<SnapshotCacheImpl>>);
+
+impl SnapshotCache {
+    /// Creates an empty cache holding at most `max_entries` entries.
+    pub fn new(max_entries: usize) -> Self {
+        Self(Rc::new(RefCell::new(SnapshotCacheImpl {
+            max_entries,
+            entries: BTreeMap::new(),
+            recency: BTreeMap::new(),
+            tick: 0,
+            metrics: SnapshotCacheMetrics::default(),
+        })))
+    }
+
+    /// Returns the maximum number of entries held by the cache.
+    pub fn max_entries(&self) -> usize {
+        self.0.borrow().max_entries
+    }
+
+    /// Returns the number of entries currently held by the cache.
+    pub fn len(&self) -> usize {
+        self.0.borrow().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the hit/miss counters accumulated since the cache creation or
+    /// the last call to [SnapshotCache::reset_metrics].
+    pub fn metrics(&self) -> SnapshotCacheMetrics {
+        self.0.borrow().metrics
+    }
+
+    pub fn reset_metrics(&self) {
+        self.0.borrow_mut().metrics = SnapshotCacheMetrics::default();
+    }
+
+    /// Drops all the cached entries, e.g. when moving to a new snapshot.
+    pub fn clear(&self) {
+        let mut cache = self.0.borrow_mut();
+        cache.entries.clear();
+        cache.recency.clear();
+    }
+
+    // Returns the value of `key` from the cache, or loads it from `src` and
+    // caches it.
+    fn get_or_load(
+        &self,
+        key: &Rc<LedgerKey>,
+        src: &dyn SnapshotSource,
+    ) -> Result<SnapshotCacheValue, HostError> {
+        {
+            let mut cache = self.0.borrow_mut();
+            if let Some(value) = cache.touch(key) {
+                cache.metrics.hits += 1;
+                return Ok(value);
+            }
+            cache.metrics.misses += 1;
+        }
+        // The cache isn't borrowed while calling into `src`, which might use
+        // the cache handle itself.
+        let value = if src.has(key)? {
+            Some(src.get(key)?)
+        } else {
+            None
+        };
+        self.0.borrow_mut().insert(Rc::clone(key), value.clone());
+        Ok(value)
+    }
+}
+
 /// Describes the total set of [LedgerKey]s that a given transaction
 /// will access, as well as the [AccessType] governing each key.
 ///
@@ -148,6 +344,30 @@ pub struct Storage {
     pub footprint: Footprint,
     pub mode: FootprintMode,
     pub map: StorageMap,
+    /// Cache of the [SnapshotSource] reads in [FootprintMode::Recording].
+    pub snapshot_cache: Option<SnapshotCache>,
+    /// Accesses rejected by the [Footprint] in [FootprintMode::Enforcing], in
+    /// the order they were attempted.
+    pub footprint_violations: Vec<(Rc<LedgerKey>, AccessType)>,
+    /// The totals of the accesses since [Storage::enable_access_stats], or
+    /// `None` if the accesses aren't tracked.
+    pub access_stats: Option<StorageAccessStats>,
+    /// The [LedgerKey]s that have been put or deleted, see
+    /// [Storage::written_entries_hash].
+    pub written_keys: BTreeSet<Rc<LedgerKey>>,
+    /// The storage map the [Storage] has been constructed with in
+    /// [FootprintMode::Enforcing], see [Storage::freed_contract_data_bytes].
+    pub initial_map: StorageMap,
+}
+
+/// A contract data entry of a single contract, as exported by
+/// [`Storage::contract_data_entries`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContractDataEntry {
+    pub key: ScVal,
+    pub durability: ContractDataDurability,
+    pub expiration_ledger: Option<u32>,
+    pub val: ScVal,
 }
 
 // Notes on metering: all storage operations: `put`, `get`, `del`, `has` are
@@ -160,7 +380,14 @@ impl Storage {
         Self {
             mode: FootprintMode::Enforcing,
             footprint,
+            // Not metered: this only clones the `Rc`s of the entries, before
+            // the invocation starts.
+            initial_map: map.clone(),
             map,
+            snapshot_cache: None,
+            footprint_violations: vec![],
+            access_stats: None,
+            written_keys: BTreeSet::new(),
         }
     }
 
@@ -171,6 +398,24 @@ impl Storage {
             mode: FootprintMode::Recording(src),
             footprint: Footprint::default(),
             map: Default::default(),
+            snapshot_cache: None,
+            footprint_violations: vec![],
+            access_stats: None,
+            written_keys: BTreeSet::new(),
+            initial_map: Default::default(),
+        }
+    }
+
+    /// Constructs a new [Storage] in [FootprintMode::Recording] using a
+    /// given [SnapshotSource], whose reads go through the given
+    /// [SnapshotCache].
+    pub fn with_recording_footprint_and_cache(
+        src: Rc<dyn SnapshotSource>,
+        cache: SnapshotCache,
+    ) -> Self {
+        Self {
+            snapshot_cache: Some(cache),
+            ..Self::with_recording_footprint(src)
         }
     }
 
@@ -192,10 +437,12 @@ impl Storage {
     ) -> Result<Rc<LedgerEntry>, HostError> {
         let _span = tracy_span!("storage get");
         self.prepare_read_only_access(key, budget)?;
-        match self.map.get::<Rc<LedgerKey>>(key, budget)? {
+        let entry = match self.map.get::<Rc<LedgerKey>>(key, budget)? {
             None | Some(None) => Err((ScErrorType::Storage, ScErrorCode::MissingValue).into()),
             Some(Some((val, _))) => Ok(Rc::clone(val)),
-        }
+        };
+        self.record_read(entry.as_ref().ok(), budget)?;
+        entry
     }
 
     // Like `get`, but distinguishes between missing values (return `Ok(None)`)
@@ -207,13 +454,15 @@ impl Storage {
     ) -> Result<Option<Rc<LedgerEntry>>, HostError> {
         let _span = tracy_span!("storage try_get");
         self.prepare_read_only_access(key, budget)?;
-        match self.map.get::<Rc<LedgerKey>>(key, budget)? {
+        let entry = match self.map.get::<Rc<LedgerKey>>(key, budget)? {
             // Key has to be in the storage map at this point due to
             // `prepare_read_only_access`.
-            None => Err((ScErrorType::Storage, ScErrorCode::InternalError).into()),
-            Some(None) => Ok(None),
-            Some(Some((val, _))) => Ok(Some(Rc::clone(val))),
-        }
+            None => return Err((ScErrorType::Storage, ScErrorCode::InternalError).into()),
+            Some(None) => None,
+            Some(Some((val, _))) => Some(Rc::clone(val)),
+        };
+        self.record_read(entry.as_ref(), budget)?;
+        Ok(entry)
     }
 
     /// Attempts to retrieve the [LedgerEntry] associated with a given
@@ -237,10 +486,12 @@ impl Storage {
     ) -> Result<(Rc<LedgerEntry>, Option<u32>), HostError> {
         let _span = tracy_span!("storage get");
         self.prepare_read_only_access(key, budget)?;
-        match self.map.get::<Rc<LedgerKey>>(key, budget)? {
+        let res = match self.map.get::<Rc<LedgerKey>>(key, budget)? {
             None | Some(None) => Err((ScErrorType::Storage, ScErrorCode::MissingValue).into()),
             Some(Some((val, expiration))) => Ok((Rc::clone(val), *expiration)),
-        }
+        };
+        self.record_read(res.as_ref().ok().map(|(entry, _)| entry), budget)?;
+        res
     }
 
     fn put_opt(
@@ -255,9 +506,13 @@ impl Storage {
                 self.footprint.record_access(key, ty, budget)?;
             }
             FootprintMode::Enforcing => {
-                self.footprint.enforce_access(key, ty, budget)?;
+                self.enforce_access(key, ty, budget)?;
             }
         };
+        self.record_write(val.map(|(e, _)| e), budget)?;
+        // Not metered: this makes fewer key comparisons than the metered
+        // insertion into the storage map below, and only clones an `Rc`.
+        self.written_keys.insert(Rc::clone(key));
         self.map = self.map.insert(
             Rc::clone(key),
             val.map(|(e, expiration)| (Rc::clone(e), expiration)),
@@ -312,13 +567,16 @@ impl Storage {
     pub fn has(&mut self, key: &Rc<LedgerKey>, budget: &Budget) -> Result<bool, HostError> {
         let _span = tracy_span!("storage has");
         self.prepare_read_only_access(key, budget)?;
-        Ok(self
+        let entry = self
             .map
             .get::<Rc<LedgerKey>>(key, budget)?
             // Key has to be present in storage at this point, so not having it
             // would be an internal error.
             .ok_or_else(|| HostError::from((ScErrorType::Storage, ScErrorCode::InternalError)))?
-            .is_some())
+            .as_ref()
+            .map(|(entry, _)| Rc::clone(entry));
+        self.record_read(entry.as_ref(), budget)?;
+        Ok(entry.is_some())
     }
 
     /// Bumps `key` to live for at least `bump_by_ledgers` from now (not
@@ -372,8 +630,10 @@ impl Storage {
             ));
         }
 
-        let new_expiration = host.with_ledger_info(|li| {
-            Ok(li.sequence_number.saturating_add(high_expiration_watermark))
+        let new_expiration = host.with_ledger_info_provider(|li| {
+            Ok(li
+                .sequence_number()
+                .saturating_add(high_expiration_watermark))
         })?;
 
         if new_expiration > host.max_expiration_ledger()? {
@@ -397,6 +657,198 @@ impl Storage {
         Ok(())
     }
 
+    /// Returns all the existing contract data entries of the contract
+    /// `contract_id` in the [Storage] (including its instance entry), in key
+    /// order. In [FootprintMode::Recording] only the entries that have been
+    /// loaded so far are in the [Storage].
+    ///
+    /// This doesn't access the entries, so it's neither recorded in nor
+    /// checked against the [Footprint].
+    pub fn contract_data_entries(
+        &self,
+        contract_id: &Hash,
+        budget: &Budget,
+    ) -> Result<Vec<ContractDataEntry>, HostError> {
+        let mut entries = vec![];
+        for (key, entry) in self.map.iter(budget)? {
+            let LedgerKey::ContractData(key) = key.as_ref() else {
+                continue;
+            };
+            if !matches!(&key.contract, ScAddress::Contract(id) if id == contract_id) {
+                continue;
+            }
+            let Some((entry, expiration_ledger)) = entry else {
+                continue;
+            };
+            let LedgerEntryData::ContractData(data) = &entry.data else {
+                return Err((ScErrorType::Storage, ScErrorCode::InternalError).into());
+            };
+            entries.push(ContractDataEntry {
+                key: data.key.metered_clone(budget)?,
+                durability: data.durability,
+                expiration_ledger: *expiration_ledger,
+                val: data.val.metered_clone(budget)?,
+            });
+        }
+        Ok(entries)
+    }
+
+    /// Returns a SHA-256 hash committing to the current state of all the
+    /// [LedgerKey]s that have been put or deleted in the [Storage]. Keys whose
+    /// writes have been rolled back by a failed call are still included, with
+    /// the state they have been rolled back to.
+    ///
+    /// The hash is computed over the concatenation, in [LedgerKey] order, of
+    /// the XDR of each key followed by the XDR of its optional entry and
+    /// expiration ledger (absent for deleted entries), so it doesn't depend on
+    /// the order in which the entries have been written.
+    pub fn written_entries_hash(&self, budget: &Budget) -> Result<[u8; 32], HostError> {
+        let mut buf = vec![];
+        for key in self.written_keys.iter() {
+            metered_write_xdr(budget, key.as_ref(), &mut buf)?;
+            match self.map.get::<Rc<LedgerKey>>(key, budget)? {
+                Some(Some((entry, expiration_ledger))) => {
+                    metered_write_xdr(budget, &true, &mut buf)?;
+                    metered_write_xdr(budget, entry.as_ref(), &mut buf)?;
+                    metered_write_xdr(budget, expiration_ledger, &mut buf)?;
+                }
+                None | Some(None) => metered_write_xdr(budget, &false, &mut buf)?,
+            }
+        }
+        budget.charge(ContractCostType::ComputeSha256Hash, Some(buf.len() as u64))?;
+        Ok(Sha256::digest(&buf).into())
+    }
+
+    /// Returns the total XDR size in bytes of the contract data entries that
+    /// existed when the [Storage] was constructed and that are deleted now,
+    /// as of their initial state. Entries that have been created and deleted
+    /// since don't count, and neither do deletions that have been rolled
+    /// back. The initial entries are those of the map the [Storage] has been
+    /// constructed with in [FootprintMode::Enforcing], and those of the
+    /// [SnapshotSource] in [FootprintMode::Recording].
+    // Notes on metering: not metered, this is meant to be called once the
+    // invocation is over.
+    pub fn freed_contract_data_bytes(&self, budget: &Budget) -> Result<u64, HostError> {
+        let mut freed: u64 = 0;
+        for key in self.written_keys.iter() {
+            if !matches!(key.as_ref(), LedgerKey::ContractData(_))
+                || get_unmetered(&self.map, key).is_some()
+            {
+                continue;
+            }
+            let initial = match &self.mode {
+                FootprintMode::Recording(src) => {
+                    if src.has(key)? {
+                        Some(src.get(key)?.0)
+                    } else {
+                        None
+                    }
+                }
+                FootprintMode::Enforcing => get_unmetered(&self.initial_map, key).cloned(),
+            };
+            freed = freed.saturating_add(Self::access_size(initial.as_ref(), budget)?);
+        }
+        Ok(freed)
+    }
+
+    /// Records (in [FootprintMode::Recording]) or enforces (in
+    /// [FootprintMode::Enforcing]) read-only access to the [LedgerKey] without
+    /// loading its [LedgerEntry]. Used for entries whose contents are served
+    /// from outside of the [Storage], such as code from a [CodeStore].
+    ///
+    /// Fails with a missing value error if the entry is known to be deleted.
+    pub(crate) fn prepare_read_only_access_without_load(
+        &mut self,
+        key: &Rc<LedgerKey>,
+        budget: &Budget,
+    ) -> Result<(), HostError> {
+        let ty = AccessType::ReadOnly;
+        match self.mode {
+            FootprintMode::Recording(_) => {
+                self.footprint.record_access(key, ty, budget)?;
+            }
+            FootprintMode::Enforcing => {
+                self.enforce_access(key, ty, budget)?;
+            }
+        };
+        if let Some(None) = self.map.get::<Rc<LedgerKey>>(key, budget)? {
+            return Err((ScErrorType::Storage, ScErrorCode::MissingValue).into());
+        }
+        Ok(())
+    }
+
+    /// Starts tracking the number and the size of the ledger entry accesses
+    /// in [Storage::access_stats]. This is a diagnostic aid that isn't
+    /// metered, and measuring the sizes serializes every accessed entry, so
+    /// it shouldn't be enabled when the stats aren't needed.
+    pub fn enable_access_stats(&mut self) {
+        self.access_stats = Some(StorageAccessStats::default());
+    }
+
+    // Returns the XDR size of `entry`, or 0 for missing entries.
+    fn access_size(entry: Option<&Rc<LedgerEntry>>, budget: &Budget) -> Result<u64, HostError> {
+        let Some(entry) = entry else {
+            return Ok(0);
+        };
+        budget.with_free_budget(|| {
+            let mut buf = vec![];
+            metered_write_xdr(budget, entry.as_ref(), &mut buf)?;
+            Ok(buf.len() as u64)
+        })
+    }
+
+    fn record_read(
+        &mut self,
+        entry: Option<&Rc<LedgerEntry>>,
+        budget: &Budget,
+    ) -> Result<(), HostError> {
+        if self.access_stats.is_none() {
+            return Ok(());
+        }
+        let size = Self::access_size(entry, budget)?;
+        if let Some(stats) = self.access_stats.as_mut() {
+            stats.reads = stats.reads.saturating_add(1);
+            stats.read_bytes = stats.read_bytes.saturating_add(size);
+        }
+        Ok(())
+    }
+
+    fn record_write(
+        &mut self,
+        entry: Option<&Rc<LedgerEntry>>,
+        budget: &Budget,
+    ) -> Result<(), HostError> {
+        if self.access_stats.is_none() {
+            return Ok(());
+        }
+        let size = Self::access_size(entry, budget)?;
+        if let Some(stats) = self.access_stats.as_mut() {
+            stats.writes = stats.writes.saturating_add(1);
+            stats.write_bytes = stats.write_bytes.saturating_add(size);
+        }
+        Ok(())
+    }
+
+    // Enforces the access to `key` and records it as a violation if the
+    // footprint doesn't allow it.
+    fn enforce_access(
+        &mut self,
+        key: &Rc<LedgerKey>,
+        ty: AccessType,
+        budget: &Budget,
+    ) -> Result<(), HostError> {
+        let res = self.footprint.enforce_access(key, ty, budget);
+        if let Err(e) = &res {
+            if e.error.is_type(ScErrorType::Storage) && e.error.is_code(ScErrorCode::ExceededLimit)
+            {
+                // No metering here, as violations are unrecoverable and end
+                // the execution.
+                self.footprint_violations.push((Rc::clone(key), ty));
+            }
+        }
+        res
+    }
+
     fn prepare_read_only_access(
         &mut self,
         key: &Rc<LedgerKey>,
@@ -409,7 +861,9 @@ impl Storage {
                 // In recording mode we treat the map as a cache
                 // that misses read-through to the underlying src.
                 if !self.map.contains_key::<Rc<LedgerKey>>(key, budget)? {
-                    let value = if src.has(&key)? {
+                    let value = if let Some(cache) = &self.snapshot_cache {
+                        cache.get_or_load(key, src.as_ref())?
+                    } else if src.has(&key)? {
                         Some(src.get(key)?)
                     } else {
                         None
@@ -418,9 +872,19 @@ impl Storage {
                 }
             }
             FootprintMode::Enforcing => {
-                self.footprint.enforce_access(key, ty, budget)?;
+                self.enforce_access(key, ty, budget)?;
             }
         };
         Ok(())
     }
 }
+
+// Returns the entry of `key` in `map`, if any, without metering. The keys are
+// compared with their `Ord`, which orders them the same way as the metered
+// comparison that the map is sorted with.
+fn get_unmetered<'a>(map: &'a StorageMap, key: &LedgerKey) -> Option<&'a Rc<LedgerEntry>> {
+    match map.map.binary_search_by(|(k, _)| k.as_ref().cmp(key)) {
+        Ok(i) => map.map[i].1.as_ref().map(|(entry, _)| entry),
+        Err(_) => None,
+    }
+}
@@ -1,22 +1,40 @@
+// Some of the helpers are only used by the tests of Wasm contracts.
+#[cfg_attr(not(feature = "vm"), allow(dead_code, unused_imports))]
 pub(crate) mod util;
 
+// The tests of Wasm contracts need the `vm` feature, the others also run
+// without it.
 mod address;
+#[cfg(feature = "vm")]
 mod auth;
+#[cfg(feature = "vm")]
 mod basic;
+#[cfg(feature = "vm")]
 mod budget_metering;
+#[cfg(feature = "vm")]
 mod bytes;
+#[cfg(feature = "vm")]
 mod complex;
 mod crypto;
 mod depth_limit;
+#[cfg(feature = "vm")]
 mod event;
+#[cfg(feature = "vm")]
 mod hostile;
+#[cfg(feature = "vm")]
 mod invocation;
 mod ledger;
+#[cfg(feature = "vm")]
 mod lifecycle;
+#[cfg(feature = "vm")]
 mod map;
+mod nft;
 mod num;
+#[cfg(feature = "vm")]
 mod post_mvp;
 mod prng;
+mod smart_wallet;
+#[cfg(feature = "vm")]
 mod storage;
 mod str;
 mod symbol;
@@ -24,4 +42,5 @@ mod token;
 mod tuple;
 mod vec;
 
+#[cfg(feature = "vm")]
 mod metering_benchmark;
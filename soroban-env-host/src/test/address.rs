@@ -1,6 +1,6 @@
-use crate::Host;
+use crate::{Host, HostError};
 use soroban_env_common::{
-    xdr::{AccountId, Hash, PublicKey, ScAddress, ScBytes, Uint256},
+    xdr::{AccountId, Hash, PublicKey, ScAddress, ScBytes, ScErrorCode, ScErrorType, Uint256},
     Env, TryIntoVal,
 };
 
@@ -71,3 +71,137 @@ fn test_contract_address_conversions() {
         .try_into_val(&host)
         .unwrap();
 }
+
+#[test]
+fn test_source_account_introspection() {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::SOURCE_ACCOUNT_FNS_MIN_PROTOCOL
+    })
+    .unwrap();
+    let source_account = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([7_u8; 32])));
+    let other_account = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([8_u8; 32])));
+    let source_address = host
+        .add_host_object(ScAddress::Account(source_account.clone()))
+        .unwrap();
+    let other_address = host
+        .add_host_object(ScAddress::Account(other_account))
+        .unwrap();
+    let contract_address = host
+        .add_host_object(ScAddress::Contract(Hash([7_u8; 32])))
+        .unwrap();
+
+    // Without a source account nothing matches and the getter returns `()`.
+    assert!(!bool::from(host.is_source_account(source_address).unwrap()));
+    let _: () = host
+        .get_source_account()
+        .unwrap()
+        .try_into_val(&host)
+        .unwrap();
+
+    host.set_source_account(source_account.clone()).unwrap();
+    assert!(bool::from(host.is_source_account(source_address).unwrap()));
+    assert!(!bool::from(host.is_source_account(other_address).unwrap()));
+    assert!(!bool::from(
+        host.is_source_account(contract_address).unwrap()
+    ));
+    let addr_obj = host.get_source_account().unwrap();
+    assert_eq!(
+        host.visit_obj(addr_obj.try_into().unwrap(), |addr: &ScAddress| Ok(
+            addr.clone()
+        ))
+        .unwrap(),
+        ScAddress::Account(source_account)
+    );
+}
+
+#[test]
+fn test_source_account_introspection_is_protocol_gated() {
+    let host = Host::test_host_with_recording_footprint();
+    host.set_source_account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256(
+        [7_u8; 32],
+    ))))
+    .unwrap();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::SOURCE_ACCOUNT_FNS_MIN_PROTOCOL - 1
+    })
+    .unwrap();
+    let address = host.source_account_address().unwrap().unwrap();
+    assert!(HostError::result_matches_err(
+        host.is_source_account(address),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    assert!(HostError::result_matches_err(
+        host.get_source_account(),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+}
+
+#[test]
+fn test_address_cmp_and_hash_id() -> Result<(), HostError> {
+    use sha2::{Digest, Sha256};
+    use soroban_env_common::xdr::WriteXdr;
+
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::ADDRESS_CMP_AND_HASH_ID_MIN_PROTOCOL
+    })?;
+    let account =
+        |b: u8| ScAddress::Account(AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([b; 32]))));
+    let addresses = [
+        account(1),
+        account(2),
+        ScAddress::Contract(Hash([0; 32])),
+        ScAddress::Contract(Hash([1; 32])),
+    ];
+    let objs = addresses
+        .iter()
+        .map(|a| host.add_host_object(a.clone()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for (i, a) in objs.iter().enumerate() {
+        for (j, b) in objs.iter().enumerate() {
+            let expected = match i.cmp(&j) {
+                core::cmp::Ordering::Less => -1,
+                core::cmp::Ordering::Equal => 0,
+                core::cmp::Ordering::Greater => 1,
+            };
+            assert_eq!(host.address_cmp(*a, *b)?, expected);
+        }
+    }
+    // Distinct objects holding the same address are equal.
+    let copy = host.add_host_object(addresses[2].clone())?;
+    assert_eq!(host.address_cmp(objs[2], copy)?, 0);
+
+    for (addr, obj) in addresses.iter().zip(objs.iter()) {
+        let id = host.address_to_hash_id(*obj)?;
+        let expected = Sha256::digest(addr.to_xdr().unwrap()).to_vec();
+        assert_eq!(host.visit_obj(id, |b: &ScBytes| Ok(b.to_vec()))?, expected);
+    }
+    // The id of a contract address differs from its contract id.
+    let id = host.address_to_hash_id(objs[2])?;
+    assert_ne!(
+        host.visit_obj(id, |b: &ScBytes| Ok(b.to_vec()))?,
+        vec![0; 32]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_address_cmp_and_hash_id_are_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::ADDRESS_CMP_AND_HASH_ID_MIN_PROTOCOL - 1
+    })?;
+    let obj = host.add_host_object(ScAddress::Contract(Hash([0; 32])))?;
+    let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+    assert!(HostError::result_matches_err(
+        host.address_cmp(obj, obj),
+        code
+    ));
+    assert!(HostError::result_matches_err(
+        host.address_to_hash_id(obj),
+        code
+    ));
+    Ok(())
+}
@@ -9,13 +9,13 @@ use soroban_env_common::xdr::{
 use soroban_native_sdk_macros::contracttype;
 use soroban_test_wasms::{AUTH_TEST_CONTRACT, DELEGATED_ACCOUNT_TEST_CONTRACT};
 
-use crate::auth::RecordedAuthPayload;
+use crate::auth::{NonRootAuthExemptions, RecordedAuthPayload};
 use crate::budget::AsBudget;
 use crate::native_contract::base_types::Address;
 use crate::native_contract::testutils::{
     create_account, generate_signing_key, sign_payload_for_account, signing_key_to_account_id,
 };
-use crate::{host_vec, Host, LedgerInfo};
+use crate::{host_vec, Host, HostError, LedgerInfo};
 use soroban_env_common::{AddressObject, Env, Symbol, SymbolStr, TryFromVal, TryIntoVal};
 
 use crate::native_contract::base_types::Vec as HostVec;
@@ -427,6 +427,88 @@ impl AuthTest {
     }
 }
 
+#[test]
+fn test_nonce_entry_testutils() {
+    let mut test = AuthTest::setup(1, 1);
+    let address = test.key_to_sc_address(&test.keys[0]);
+    let setup = SetupNode::new(&test.contracts[0], vec![true], vec![]);
+    test.tree_test_enforcing(
+        &setup,
+        vec![vec![SignNode::tree_fn(&test.contracts[0], vec![])]],
+        true,
+    );
+    let consumed_nonce = test.last_nonces[0][0];
+    assert_eq!(
+        test.host
+            .get_nonce_expiration(address.clone(), consumed_nonce)
+            .unwrap(),
+        Some(1000)
+    );
+
+    test.host
+        .create_nonce_entry(address.clone(), 123, 500)
+        .unwrap();
+    assert_eq!(
+        test.host
+            .get_nonce_expiration(address.clone(), 123)
+            .unwrap(),
+        Some(500)
+    );
+    assert_eq!(
+        test.host
+            .get_nonce_expiration(address.clone(), 124)
+            .unwrap(),
+        None
+    );
+
+    // Nonces are only removed once their entries expire.
+    assert_eq!(test.host.expire_nonces_below(500).unwrap(), 0);
+    assert_eq!(test.host.expire_nonces_below(501).unwrap(), 1);
+    assert_eq!(
+        test.host
+            .get_nonce_expiration(address.clone(), 123)
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        test.host
+            .get_nonce_expiration(address.clone(), consumed_nonce)
+            .unwrap(),
+        Some(1000)
+    );
+    assert_eq!(test.host.expire_nonces_below(1001).unwrap(), 1);
+    assert_eq!(
+        test.host
+            .get_nonce_expiration(address, consumed_nonce)
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn test_suggest_nonce() {
+    let test = AuthTest::setup(2, 1);
+    let address = test.key_to_sc_address(&test.keys[0]);
+    let other_address = test.key_to_sc_address(&test.keys[1]);
+    assert_eq!(test.host.suggest_nonce(address.clone()).unwrap(), 0);
+
+    test.host
+        .create_nonce_entry(address.clone(), 5, 500)
+        .unwrap();
+    test.host
+        .create_nonce_entry(address.clone(), 7, 1000)
+        .unwrap();
+    test.host
+        .create_nonce_entry(other_address.clone(), 100, 1000)
+        .unwrap();
+    assert_eq!(test.host.suggest_nonce(address.clone()).unwrap(), 8);
+    assert_eq!(test.host.suggest_nonce(other_address).unwrap(), 101);
+
+    // Nonces are free again once their entries are removed.
+    assert_eq!(test.host.expire_nonces_below(1001).unwrap(), 3);
+    assert_eq!(test.host.suggest_nonce(address).unwrap(), 0);
+}
+
 #[test]
 fn test_single_authorized_call() {
     let mut test = AuthTest::setup(1, 2);
@@ -965,6 +1047,85 @@ fn test_disable_non_root_recording_auth() {
         .is_ok());
 }
 
+#[test]
+fn test_non_root_recording_auth_exemptions() {
+    let test = AuthTest::setup(1, 3);
+    let setup = SetupNode::new(
+        &test.contracts[0],
+        vec![false],
+        vec![
+            SetupNode::new(&test.contracts[1], vec![true], vec![]),
+            SetupNode::new(&test.contracts[2], vec![true], vec![]),
+        ],
+    );
+    let addresses = test.get_addresses();
+    let tree = test.convert_setup_tree(&setup);
+    let contract_address = |i: usize| {
+        test.host
+            .scaddress_from_address(test.contracts[i].clone().into())
+            .unwrap()
+    };
+    let call = || {
+        test.host.call(
+            setup.contract_address.clone().into(),
+            Symbol::try_from_small_str("tree_fn").unwrap(),
+            host_vec![&test.host, addresses, tree].into(),
+        )
+    };
+
+    // Exempting only one of the non-root invocations isn't sufficient.
+    test.host
+        .switch_to_recording_auth_with_exemptions(
+            true,
+            NonRootAuthExemptions {
+                addresses: vec![],
+                contracts: vec![contract_address(1)],
+            },
+        )
+        .unwrap();
+    assert!(HostError::result_matches_err(
+        call(),
+        (ScErrorType::Auth, ScErrorCode::InvalidAction)
+    ));
+    test.host
+        .switch_to_recording_auth_with_exemptions(
+            true,
+            NonRootAuthExemptions {
+                addresses: vec![],
+                contracts: vec![contract_address(1), contract_address(2)],
+            },
+        )
+        .unwrap();
+    assert!(call().is_ok());
+
+    // Exempting the address allows all its non-root authorizations.
+    test.host
+        .switch_to_recording_auth_with_exemptions(
+            true,
+            NonRootAuthExemptions {
+                addresses: vec![ScAddress::Account(signing_key_to_account_id(&test.keys[0]))],
+                contracts: vec![],
+            },
+        )
+        .unwrap();
+    assert!(call().is_ok());
+
+    // When non-root auth is enabled, exemptions disable it.
+    test.host
+        .switch_to_recording_auth_with_exemptions(
+            false,
+            NonRootAuthExemptions {
+                addresses: vec![],
+                contracts: vec![contract_address(2)],
+            },
+        )
+        .unwrap();
+    assert!(HostError::result_matches_err(
+        call(),
+        (ScErrorType::Auth, ScErrorCode::InvalidAction)
+    ));
+}
+
 #[test]
 fn test_three_authorized_trees() {
     let mut test = AuthTest::setup(1, 5);
@@ -2672,3 +2833,72 @@ fn test_different_auth_trees_with_duplicate_addresses() {
         false,
     );
 }
+
+#[test]
+fn test_require_auth_many() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.enable_debug()?;
+    let invoker = host.register_test_contract_wasm(AUTH_TEST_CONTRACT);
+    let invoker_id = host.contract_id_from_address(invoker)?;
+    let callee = host.register_test_contract_wasm(AUTH_TEST_CONTRACT);
+    let callee_id = host.contract_id_from_address(callee)?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::REQUIRE_AUTH_MANY_MIN_PROTOCOL
+    })?;
+    let account = host.add_host_object(ScAddress::Account(signing_key_to_account_id(
+        &generate_signing_key(),
+    )))?;
+    let func = Symbol::try_from_small_str("batch")?;
+
+    host.with_test_contract_frame(invoker_id, func, || {
+        host.with_test_contract_frame(callee_id.clone(), func, || {
+            // The invoker contract has authorized the call.
+            let addresses = host.vec_new_from_slice(&[invoker.to_val(), invoker.to_val()])?;
+            host.require_auth_many(addresses)?;
+            host.require_auth_many(host.vec_new()?)?;
+
+            // The account hasn't, and it's reported with its index.
+            let addresses = host.vec_new_from_slice(&[invoker.to_val(), account.to_val()])?;
+            assert!(host.require_auth_many(addresses).is_err());
+            assert!(host
+                .get_events()?
+                .0
+                .iter()
+                .any(|e| format!("{}", e).contains("authorization failed for address in batch")));
+
+            // Every element has to be an address.
+            let addresses = host.vec_new_from_slice(&[invoker.to_val(), func.to_val()])?;
+            assert!(HostError::result_matches_err(
+                host.require_auth_many(addresses),
+                (ScErrorType::Object, ScErrorCode::UnexpectedType)
+            ));
+            Ok(().into())
+        })
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_require_auth_many_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let invoker = host.register_test_contract_wasm(AUTH_TEST_CONTRACT);
+    let invoker_id = host.contract_id_from_address(invoker)?;
+    let callee = host.register_test_contract_wasm(AUTH_TEST_CONTRACT);
+    let callee_id = host.contract_id_from_address(callee)?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::REQUIRE_AUTH_MANY_MIN_PROTOCOL - 1
+    })?;
+    let func = Symbol::try_from_small_str("batch")?;
+
+    host.with_test_contract_frame(invoker_id, func, || {
+        host.with_test_contract_frame(callee_id.clone(), func, || {
+            let addresses = host.vec_new_from_slice(&[invoker.to_val()])?;
+            assert!(HostError::result_matches_err(
+                host.require_auth_many(addresses),
+                (ScErrorType::Context, ScErrorCode::InvalidAction)
+            ));
+            Ok(().into())
+        })
+    })?;
+    Ok(())
+}
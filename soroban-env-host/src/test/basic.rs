@@ -81,3 +81,293 @@ fn f32_does_not_work() -> Result<(), HostError> {
     assert!(crate::vm::Vm::new(&host, hash, soroban_test_wasms::ADD_F32).is_err());
     Ok(())
 }
+
+#[test]
+fn scval_conversion_with_limits() -> Result<(), HostError> {
+    use crate::{
+        budget::AsBudget,
+        xdr::{ContractCostType, ScBytes, ScErrorCode, ScErrorType, ScVec},
+        ScValLimitExceeded, ScValLimits,
+    };
+    let host = Host::default();
+    let limits = ScValLimits {
+        max_depth: 2,
+        max_values: 4,
+        max_payload_bytes: 10,
+    };
+    let bytes = |n: usize| ScVal::Bytes(ScBytes(vec![0; n].try_into().unwrap()));
+    let vec = |v: Vec<ScVal>| ScVal::Vec(Some(ScVec(v.try_into().unwrap())));
+
+    let ok = vec(vec![ScVal::U32(1), bytes(5), bytes(5)]);
+    assert_eq!(limits.check(&ok), Ok(()));
+    let val = host.scval_to_val_with_limits(&ok, &limits)?;
+    assert_eq!(host.val_to_scval_with_limits(val, &limits)?, ok);
+    assert!(host.val_within_limits(val, &limits)?);
+
+    let too_deep = vec(vec![vec(vec![])]);
+    assert_eq!(limits.check(&too_deep), Err(ScValLimitExceeded::Depth));
+    let too_many = vec(vec![ScVal::U32(1); 4]);
+    assert_eq!(limits.check(&too_many), Err(ScValLimitExceeded::Values));
+    let too_large = vec(vec![bytes(6), bytes(5)]);
+    assert_eq!(
+        limits.check(&too_large),
+        Err(ScValLimitExceeded::PayloadBytes)
+    );
+
+    // Every value visited by the check is charged for, up to the one
+    // exceeding a limit.
+    let visits = || -> Result<u64, HostError> {
+        Ok(host
+            .as_budget()
+            .get_tracker(ContractCostType::VisitObject)?
+            .0)
+    };
+    let before = visits()?;
+    assert!(host.scval_to_val_with_limits(&too_many, &limits).is_err());
+    assert_eq!(visits()? - before, 5);
+
+    for v in [too_deep, too_many, too_large] {
+        assert!(HostError::result_matches_err(
+            host.scval_to_val_with_limits(&v, &limits),
+            (ScErrorType::Value, ScErrorCode::ExceededLimit)
+        ));
+        // Converting with the relaxed limits succeeds, but converting back
+        // with the original limits doesn't.
+        let relaxed = ScValLimits {
+            max_depth: 10,
+            max_values: 10,
+            max_payload_bytes: 100,
+        };
+        let val = host.scval_to_val_with_limits(&v, &relaxed)?;
+        // The host value is checked without being converted.
+        assert!(!host.val_within_limits(val, &limits)?);
+        assert!(HostError::result_matches_err(
+            host.val_to_scval_with_limits(val, &limits),
+            (ScErrorType::Value, ScErrorCode::ExceededLimit)
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn scval_conversion() -> Result<(), HostError> {
+    use crate::{
+        budget::AsBudget,
+        xdr::{ScErrorCode, ScErrorType, ScString, ScVec},
+    };
+    let host = Host::default();
+    let vec = |v: Vec<ScVal>| ScVal::Vec(Some(ScVec(v.try_into().unwrap())));
+
+    let v = vec(vec![
+        ScVal::U32(1),
+        ScVal::String(ScString("hello".try_into().unwrap())),
+        vec(vec![ScVal::I128(i128::MAX.into())]),
+    ]);
+    let cpu = host.as_budget().get_cpu_insns_consumed()?;
+    let val = host.scval_to_val(&v)?;
+    assert!(host.as_budget().get_cpu_insns_consumed()? > cpu);
+    assert_eq!(host.val_to_scval(val)?, v);
+    assert_eq!(host.scval_to_val(&ScVal::U32(7))?.get_tag(), Tag::U32Val);
+
+    // Values nested deeper than the host depth limit are rejected.
+    host.as_budget().reset_unlimited()?;
+    let mut deep = vec(vec![]);
+    for _ in 0..crate::DEFAULT_HOST_DEPTH_LIMIT {
+        deep = vec(vec![deep]);
+    }
+    assert!(HostError::result_matches_err(
+        host.scval_to_val(&deep),
+        (ScErrorType::Value, ScErrorCode::InternalError)
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "fuzz")]
+#[test]
+fn fuzz_scval_round_trip() -> Result<(), HostError> {
+    use crate::fuzz::{assert_scval_round_trip, ScValGenerator};
+    use rand::{rngs::StdRng, RngCore, SeedableRng};
+
+    // A fixed seed keeps the generated values, and so any failure,
+    // reproducible.
+    let mut rng = StdRng::from_seed([0xff; 32]);
+    let generator = ScValGenerator::default();
+    let mut data = vec![0_u8; 4096];
+    for _ in 0..100 {
+        rng.fill_bytes(&mut data);
+        let mut u = arbitrary::Unstructured::new(&data);
+        let Ok(v) = generator.generate(&mut u) else {
+            continue;
+        };
+        assert_scval_round_trip(&Host::default(), &v)?;
+    }
+    Ok(())
+}
+
+#[test]
+fn object_handle_randomization() -> Result<(), HostError> {
+    use crate::{xdr::ScErrorCode, xdr::ScErrorType, Env, Symbol};
+
+    let host = Host::test_host_with_recording_footprint();
+    host.enable_object_handle_randomization(0x1234_5678_9abc_def0)?;
+    let vals: Vec<u64> = (0..16).map(|i| u64::MAX - i).collect();
+    let objs: Vec<Object> = vals
+        .iter()
+        .map(|v| Ok(Object::try_from(v.try_into_val(&host)?)?))
+        .collect::<Result<_, HostError>>()?;
+    // Handles are neither sequential nor monotonic, but still resolve to
+    // their objects.
+    let handles: Vec<u32> = objs.iter().map(|o| o.get_handle()).collect();
+    assert!(handles.windows(2).any(|w| w[1] < w[0]));
+    assert!(handles.windows(2).any(|w| w[1] != w[0] + 2));
+    for (obj, v) in objs.iter().zip(vals.iter()) {
+        assert_eq!(u64::try_from_val(&host, &obj.to_val())?, *v);
+    }
+
+    // Wasm contracts see permuted relative handles and work as usual.
+    let contract = host.register_test_contract_wasm(soroban_test_wasms::COMPLEX);
+    host.call(contract, Symbol::try_from_small_str("go")?, host.vec_new()?)?;
+
+    // The mode can't be enabled once there are objects.
+    assert!(HostError::result_matches_err(
+        host.enable_object_handle_randomization(1),
+        (ScErrorType::Context, ScErrorCode::InternalError)
+    ));
+    Ok(())
+}
+
+#[test]
+fn host_builder_validates_configuration() -> Result<(), HostError> {
+    use crate::{
+        auth::NonRootAuthExemptions, storage::Storage, test::util::MockSnapshotSource,
+        xdr::ScErrorCode, xdr::ScErrorType, DiagnosticLevel, HostBuilder, LedgerInfo,
+    };
+    use std::rc::Rc;
+
+    let recording_storage =
+        || Storage::with_recording_footprint(Rc::new(MockSnapshotSource::new()));
+    let no_exemptions = || NonRootAuthExemptions {
+        addresses: vec![],
+        contracts: vec![],
+    };
+
+    let res = HostBuilder::new().build();
+    let code = (ScErrorType::Context, ScErrorCode::MissingValue);
+    assert!(HostError::result_matches_err(res, code));
+    let res = Host::builder()
+        .storage(Storage::default())
+        .recording_auth(false, no_exemptions())
+        .build();
+    let code = (ScErrorType::Context, ScErrorCode::InvalidInput);
+    assert!(HostError::result_matches_err(res, code));
+    let res = Host::builder()
+        .storage(Storage::default())
+        .enforcing_auth(vec![])
+        .build();
+    assert!(HostError::result_matches_err(res, code));
+
+    let host = Host::builder()
+        .storage(recording_storage())
+        .recording_auth(true, no_exemptions())
+        .ledger_info(LedgerInfo {
+            sequence_number: 123,
+            ..Default::default()
+        })
+        .diagnostic_level(DiagnosticLevel::Debug)
+        .base_prng_seed([0; 32])
+        .build()?;
+    assert!(host.is_debug()?);
+    assert_eq!(host.with_ledger_info(|li| Ok(li.sequence_number))?, 123);
+    assert!(host.source_account_address()?.is_none());
+    Ok(())
+}
+
+#[test]
+fn object_leak_detection() -> Result<(), HostError> {
+    use crate::{EnvBase, Symbol};
+    use soroban_synth_wasm::{Arity, ModEmitter};
+
+    // Allocates a vector it doesn't use, and returns another one.
+    let mut fe = ModEmitter::new().func(Arity(0), 0);
+    fe.vec_new();
+    fe.drop();
+    fe.vec_new();
+    let wasm = fe.finish_and_export("test").finish();
+
+    let host = Host::test_host_with_recording_footprint();
+    let contract = host.register_test_contract_wasm(&wasm);
+    let args = host.vec_new()?;
+    host.enable_object_leak_detection()?;
+    let res = host.call(contract, Symbol::try_from_small_str("test")?, args)?;
+
+    let leaked = host.leaked_objects()?;
+    let from_contract: Vec<_> = leaked.iter().filter(|l| l.site.host_fn.is_some()).collect();
+    assert_eq!(from_contract.len(), 1);
+    let leak = from_contract[0];
+    assert_eq!(leak.site.host_fn, Some("vec_new"));
+    assert_eq!(
+        leak.site.contract_id,
+        Some(host.contract_id_from_address(contract)?)
+    );
+    assert_eq!(leak.site.function.as_deref(), Some("test"));
+    // The returned vector isn't leaked.
+    let res_handle = Object::try_from(res)?.get_handle();
+    assert!(leaked.iter().all(|l| l.handle != res_handle));
+    Ok(())
+}
+
+#[test]
+fn render_val_for_diagnostics() -> Result<(), HostError> {
+    use crate::{
+        budget::AsBudget,
+        xdr::{ScErrorCode, ScErrorType},
+        EnvBase,
+    };
+
+    let host = Host::default();
+    host.enable_debug()?;
+    let bytes = host.bytes_new_from_slice(&[0xab; 40])?;
+    let string = host.string_new_from_slice("hello")?;
+    let big = u64::MAX.try_into_val(&host)?;
+    let map = host.map_new_from_slices(&["a", "b"], &[string.to_val(), big])?;
+    let inner = host.vec_new_from_slice(&[1u32.into()])?;
+    let nested = host.vec_new_from_slice(&[inner.to_val()])?;
+    let nested = host.vec_new_from_slice(&[nested.to_val()])?;
+    let v = host.vec_new_from_slice(&[bytes.to_val(), map.to_val(), nested.to_val()])?;
+
+    let cpu = host.as_budget().get_cpu_insns_consumed()?;
+    let mem = host.as_budget().get_mem_bytes_consumed()?;
+    assert_eq!(
+        host.render_val_for_diagnostics(v.to_val()),
+        format!(
+            "[Bytes({}..), {{Symbol(a): \"hello\", Symbol(b): U64({})}}, [[[..]]]]",
+            "ab".repeat(32),
+            u64::MAX
+        )
+    );
+    // Long vectors are truncated.
+    let long = host.vec_new_from_slice(&[Val::VOID.to_val(); 10])?;
+    assert_eq!(
+        host.render_val_for_diagnostics(long.to_val()),
+        format!("[{}, ..]", vec!["Void"; 8].join(", "))
+    );
+    // Dangling handles are rendered as they are.
+    let dangling = unsafe { crate::VecObject::from_handle(0xffff1) }.to_val();
+    assert_eq!(
+        host.render_val_for_diagnostics(dangling),
+        format!("{:?}", dangling)
+    );
+    // Rendering is free.
+    assert_eq!(host.as_budget().get_cpu_insns_consumed()?, cpu);
+    assert_eq!(host.as_budget().get_mem_bytes_consumed()?, mem);
+
+    // Errors record the rendered arguments.
+    let err = host.err(
+        ScErrorType::Value,
+        ScErrorCode::InvalidInput,
+        "bad value",
+        &[string.to_val()],
+    );
+    assert_eq!(err.causes()[0].args, vec!["\"hello\"".to_string()]);
+    Ok(())
+}
@@ -1,12 +1,18 @@
 use crate::{
-    budget::{AsBudget, Budget},
+    budget::{AsBudget, Budget, BudgetSnapshot},
     host::metered_clone::{MeteredClone, MeteredIterator},
     host::metered_xdr::metered_write_xdr,
+    native_contract::testutils::generate_signing_key,
     xdr::{ContractCostType, ScMap, ScMapEntry, ScVal},
-    Env, Host, HostError, Symbol, Val,
+    Env, EnvBase, Host, HostError, LedgerInfo, Symbol, Val,
 };
+use ed25519_dalek::Signer;
 use expect_test::{self, expect};
-use soroban_env_common::xdr::{ScErrorCode, ScErrorType};
+use soroban_env_common::{
+    call_macro_with_all_host_functions,
+    xdr::{ScErrorCode, ScErrorType},
+    I256Val, TryFromVal, U256Val, U32Val, I256, U256,
+};
 use soroban_test_wasms::VEC;
 
 #[test]
@@ -74,6 +80,422 @@ fn vm_hostfn_invocation() -> Result<(), HostError> {
     Ok(())
 }
 
+#[test]
+fn budget_cancellation() -> Result<(), HostError> {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    };
+
+    let host = Host::test_host_with_recording_footprint();
+    let id_obj = host.register_test_contract_wasm(VEC);
+    let flag = Arc::new(AtomicBool::new(false));
+    host.as_budget().set_cancellation_flag(flag.clone())?;
+    let sym = Symbol::try_from_small_str("vec_err").unwrap();
+    let args = host.test_vec_obj::<u32>(&[1])?;
+
+    // Without cancellation the contract fails on its own.
+    assert!(HostError::result_matches_err(
+        host.try_call(id_obj, sym, args),
+        (ScErrorType::Object, ScErrorCode::IndexBounds)
+    ));
+
+    std::thread::spawn(move || flag.store(true, Ordering::Relaxed))
+        .join()
+        .unwrap();
+    assert!(HostError::result_matches_err(
+        host.try_call(id_obj, sym, args),
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    assert!(HostError::result_matches_err(
+        host.as_budget()
+            .charge(ContractCostType::HostMemCpy, Some(1)),
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    Ok(())
+}
+
+#[test]
+fn charge_batch_matches_individual_charges() -> Result<(), HostError> {
+    let charges = [
+        (ContractCostType::HostMemCmp, Some(3)),
+        (ContractCostType::VmMemRead, Some(9)),
+        (ContractCostType::HostMemCmp, Some(3)),
+        (ContractCostType::MapEntry, None),
+        (ContractCostType::HostMemCmp, Some(17)),
+        (ContractCostType::MapEntry, None),
+    ];
+    let individual = Budget::default();
+    for (ty, input) in charges {
+        individual.charge(ty, input)?;
+    }
+    let batched = Budget::default();
+    batched.with_charge_batch(|batch| {
+        for (ty, input) in charges {
+            batch.charge(ty, input);
+        }
+        Ok(())
+    })?;
+    assert_eq!(
+        individual.get_cpu_insns_consumed()?,
+        batched.get_cpu_insns_consumed()?
+    );
+    assert_eq!(
+        individual.get_mem_bytes_consumed()?,
+        batched.get_mem_bytes_consumed()?
+    );
+    for ty in [
+        ContractCostType::HostMemCmp,
+        ContractCostType::VmMemRead,
+        ContractCostType::MapEntry,
+    ] {
+        assert_eq!(individual.get_tracker(ty)?, batched.get_tracker(ty)?);
+    }
+
+    // The batch is flushed and the limit enforced when the closure returns.
+    let budget = Budget::default();
+    budget.reset_limits(1, 1)?;
+    let res = budget.with_charge_batch(|batch| {
+        batch.bulk_charge(ContractCostType::HostMemCmp, 100, Some(100));
+        Ok(())
+    });
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+
+    // Exceeding the limit is reported over an error of the closure.
+    let budget = Budget::default();
+    budget.reset_limits(1, 1)?;
+    let res: Result<(), HostError> = budget.with_charge_batch(|batch| {
+        batch.bulk_charge(ContractCostType::HostMemCmp, 100, Some(100));
+        Err((ScErrorType::Object, ScErrorCode::InvalidInput).into())
+    });
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    Ok(())
+}
+
+#[test]
+fn budget_snapshot_round_trip() -> Result<(), HostError> {
+    let charges = [
+        (ContractCostType::HostMemCmp, Some(3)),
+        (ContractCostType::MapEntry, None),
+        (ContractCostType::VmMemRead, Some(9)),
+    ];
+    let original = Budget::default();
+    original.reset_limits(1_000_000, 100_000)?;
+    for (ty, input) in charges {
+        original.charge(ty, input)?;
+    }
+
+    let snapshot = BudgetSnapshot::from_bytes(&original.snapshot()?.to_bytes())?;
+    assert_eq!(snapshot, original.snapshot()?);
+    let restored = Budget::from_snapshot(&snapshot)?;
+
+    // Both budgets account for further charges identically.
+    for (ty, input) in charges {
+        original.charge(ty, input)?;
+        restored.charge(ty, input)?;
+    }
+    assert_eq!(original.snapshot()?, restored.snapshot()?);
+    assert_eq!(
+        original.get_cpu_insns_remaining()?,
+        restored.get_cpu_insns_remaining()?
+    );
+    assert_eq!(
+        original.get_mem_bytes_remaining()?,
+        restored.get_mem_bytes_remaining()?
+    );
+
+    // Whether the budget is enabled, the fuel costs and the depth limit are
+    // carried over too.
+    let mut custom = snapshot.clone();
+    custom.enabled = false;
+    custom.fuel_config.call = 42;
+    custom.depth_limit = 7;
+    assert_eq!(BudgetSnapshot::from_bytes(&custom.to_bytes())?, custom);
+    let restored = Budget::from_snapshot(&custom)?;
+    restored.charge(ContractCostType::MapEntry, None)?;
+    assert_eq!(restored.snapshot()?, custom);
+
+    // Malformed and inconsistent snapshots are rejected.
+    let bytes = snapshot.to_bytes();
+    assert!(HostError::result_matches_err(
+        BudgetSnapshot::from_bytes(&bytes[..bytes.len() - 1]),
+        (ScErrorType::Context, ScErrorCode::InvalidInput)
+    ));
+    let mut inconsistent = snapshot.clone();
+    inconsistent.cpu_insns.total_count += 1;
+    assert!(HostError::result_matches_err(
+        Budget::from_snapshot(&inconsistent),
+        (ScErrorType::Context, ScErrorCode::InvalidInput)
+    ));
+    Ok(())
+}
+
+#[test]
+fn host_fn_cost_bounds_match_charges() -> Result<(), HostError> {
+    let expected = Budget::default();
+    expected.charge(ContractCostType::DispatchHostFunction, None)?;
+    expected.charge(ContractCostType::VisitObject, None)?;
+    expected.charge(ContractCostType::ComputeSha256Hash, Some(1000))?;
+    expected.charge(ContractCostType::HostMemAlloc, Some(48))?;
+    let bounds = Budget::default().host_fn_cost_bounds("compute_hash_sha256", &[1000])?;
+    assert_eq!(bounds.min_cpu_insns, expected.get_cpu_insns_consumed()?);
+    assert_eq!(bounds.max_cpu_insns, expected.get_cpu_insns_consumed()?);
+    assert_eq!(bounds.min_mem_bytes, expected.get_mem_bytes_consumed()?);
+    assert_eq!(bounds.max_mem_bytes, expected.get_mem_bytes_consumed()?);
+
+    // Lookups in a map cost a binary search's worth of entry visits on top of
+    // visiting the map.
+    let budget = Budget::default();
+    let bounds = budget.host_fn_cost_bounds("map_has", &[1000])?;
+    let map_len = budget.host_fn_cost_bounds("map_len", &[])?;
+    let entry = Budget::default();
+    entry.charge(ContractCostType::MapEntry, None)?;
+    assert_eq!(
+        bounds.min_cpu_insns - map_len.min_cpu_insns,
+        11 * entry.get_cpu_insns_consumed()?
+    );
+
+    for (name, num_sizes) in Budget::host_fns_with_cost_bounds() {
+        budget.host_fn_cost_bounds(name, &vec![u64::MAX; num_sizes])?;
+    }
+    assert!(HostError::result_matches_err(
+        budget.host_fn_cost_bounds("not_a_host_fn", &[]),
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+    assert!(HostError::result_matches_err(
+        budget.host_fn_cost_bounds("compute_hash_sha256", &[]),
+        (ScErrorType::Value, ScErrorCode::UnexpectedSize)
+    ));
+    Ok(())
+}
+
+// Expands to the names of all the host functions of the environment interface.
+macro_rules! host_fn_names {
+    {
+        $(
+            $(#[$mod_attr:meta])*
+            mod $mod_id:ident $mod_str:literal
+            {
+                $(
+                    $(#[$fn_attr:meta])*
+                    { $fn_id:literal, fn $func_id:ident $args:tt -> $ret:ty }
+                )*
+            }
+        )*
+    }
+    => {
+        const HOST_FN_NAMES: &[&str] = &[$($(stringify!($func_id),)*)*];
+    };
+}
+call_macro_with_all_host_functions! { host_fn_names }
+
+// Checks that the charges of `call` are within the bounds of the host function
+// `name` for the input sizes `sizes`, on top of the dispatch of the call.
+fn check_host_fn_cost_bounds(
+    host: &Host,
+    checked: &mut Vec<&'static str>,
+    name: &'static str,
+    sizes: &[u64],
+    call: impl FnOnce() -> Result<(), HostError>,
+) -> Result<(), HostError> {
+    let budget = host.as_budget();
+    let bounds = budget.host_fn_cost_bounds(name, sizes)?;
+    let dispatch = Budget::default();
+    dispatch.charge(ContractCostType::DispatchHostFunction, None)?;
+    let cpu_before = budget.get_cpu_insns_consumed()?;
+    let mem_before = budget.get_mem_bytes_consumed()?;
+    call()?;
+    let cpu = budget.get_cpu_insns_consumed()? - cpu_before + dispatch.get_cpu_insns_consumed()?;
+    let mem = budget.get_mem_bytes_consumed()? - mem_before + dispatch.get_mem_bytes_consumed()?;
+    assert!(
+        (bounds.min_cpu_insns..=bounds.max_cpu_insns).contains(&cpu),
+        "{name}{sizes:?}: {cpu} cpu insns charged, bounds {bounds:?}"
+    );
+    assert!(
+        (bounds.min_mem_bytes..=bounds.max_mem_bytes).contains(&mem),
+        "{name}{sizes:?}: {mem} mem bytes charged, bounds {bounds:?}"
+    );
+    checked.push(name);
+    Ok(())
+}
+
+#[test]
+fn host_fn_cost_bounds_cover_charges() -> Result<(), HostError> {
+    let host = Host::test_host();
+    host.set_ledger_info(LedgerInfo {
+        protocol_version: crate::host::U256_SQRT_LOG2_MIN_PROTOCOL,
+        ..Default::default()
+    })?;
+    let mut checked = vec![];
+
+    let msg = [7_u8; 100];
+    let payload = host.bytes_new_from_slice(&msg)?;
+    check_host_fn_cost_bounds(&host, &mut checked, "compute_hash_sha256", &[100], || {
+        host.compute_hash_sha256(payload).map(|_| ())
+    })?;
+    check_host_fn_cost_bounds(
+        &host,
+        &mut checked,
+        "compute_hash_keccak256",
+        &[100],
+        || host.compute_hash_keccak256(payload).map(|_| ()),
+    )?;
+    let key = generate_signing_key();
+    let public_key = host.bytes_new_from_slice(key.verifying_key().as_bytes())?;
+    let sig = host.bytes_new_from_slice(&key.sign(&msg).to_bytes())?;
+    check_host_fn_cost_bounds(&host, &mut checked, "verify_sig_ed25519", &[100], || {
+        host.verify_sig_ed25519(public_key, payload, sig)
+            .map(|_| ())
+    })?;
+
+    // Both small values and objects are put into the collections.
+    let elts: Vec<u32> = (0..10).collect();
+    let v = host.test_vec_obj(&elts)?;
+    let i = U32Val::from(3_u32);
+    check_host_fn_cost_bounds(&host, &mut checked, "vec_len", &[], || {
+        host.vec_len(v).map(|_| ())
+    })?;
+    check_host_fn_cost_bounds(&host, &mut checked, "vec_get", &[], || {
+        host.vec_get(v, i).map(|_| ())
+    })?;
+    for x in [Val::from_u32(7).to_val(), payload.to_val()] {
+        check_host_fn_cost_bounds(&host, &mut checked, "vec_put", &[10], || {
+            host.vec_put(v, i, x).map(|_| ())
+        })?;
+        check_host_fn_cost_bounds(&host, &mut checked, "vec_push_back", &[10], || {
+            host.vec_push_back(v, x).map(|_| ())
+        })?;
+    }
+
+    let mut m = host.map_new()?;
+    for k in 0..10_u32 {
+        m = host.map_put(m, (2 * k).into(), k.into())?;
+    }
+    let (present, absent) = (Val::from_u32(4).to_val(), Val::from_u32(5).to_val());
+    check_host_fn_cost_bounds(&host, &mut checked, "map_len", &[], || {
+        host.map_len(m).map(|_| ())
+    })?;
+    check_host_fn_cost_bounds(&host, &mut checked, "map_get", &[10], || {
+        host.map_get(m, present).map(|_| ())
+    })?;
+    for k in [present, absent] {
+        check_host_fn_cost_bounds(&host, &mut checked, "map_has", &[10], || {
+            host.map_has(m, k).map(|_| ())
+        })?;
+        for x in [Val::from_u32(7).to_val(), payload.to_val()] {
+            check_host_fn_cost_bounds(&host, &mut checked, "map_put", &[10], || {
+                host.map_put(m, k, x).map(|_| ())
+            })?;
+        }
+    }
+
+    // Small inputs with a small result, and objects with an object result.
+    let u256_args = [
+        (U256Val::from_u32(6), U256Val::from_u32(2)),
+        (
+            U256Val::try_from_val(&host, &(U256::ONE << 100))?,
+            U256Val::try_from_val(&host, &(U256::ONE << 90))?,
+        ),
+    ];
+    let u256_ops: [(
+        &'static str,
+        fn(&Host, U256Val, U256Val) -> Result<U256Val, HostError>,
+    ); 4] = [
+        ("u256_add", Host::u256_add),
+        ("u256_sub", Host::u256_sub),
+        ("u256_mul", Host::u256_mul),
+        ("u256_div", Host::u256_div),
+    ];
+    let u256_rhs_u32_ops: [(
+        &'static str,
+        fn(&Host, U256Val, U32Val) -> Result<U256Val, HostError>,
+    ); 3] = [
+        ("u256_pow", Host::u256_pow),
+        ("u256_shl", Host::u256_shl),
+        ("u256_shr", Host::u256_shr),
+    ];
+    for (lhs, rhs) in u256_args {
+        for (name, op) in u256_ops {
+            check_host_fn_cost_bounds(&host, &mut checked, name, &[], || {
+                op(&host, lhs, rhs).map(|_| ())
+            })?;
+        }
+        for (name, op) in u256_rhs_u32_ops {
+            check_host_fn_cost_bounds(&host, &mut checked, name, &[], || {
+                op(&host, lhs, U32Val::from(2_u32)).map(|_| ())
+            })?;
+        }
+        check_host_fn_cost_bounds(&host, &mut checked, "u256_log2", &[], || {
+            host.u256_log2(lhs).map(|_| ())
+        })?;
+    }
+    let i256_args = [
+        (I256Val::from_i32(-6), I256Val::from_i32(2)),
+        (
+            I256Val::try_from_val(&host, &(I256::ONE << 100))?,
+            I256Val::try_from_val(&host, &(I256::ONE << 90))?,
+        ),
+    ];
+    let i256_ops: [(
+        &'static str,
+        fn(&Host, I256Val, I256Val) -> Result<I256Val, HostError>,
+    ); 4] = [
+        ("i256_add", Host::i256_add),
+        ("i256_sub", Host::i256_sub),
+        ("i256_mul", Host::i256_mul),
+        ("i256_div", Host::i256_div),
+    ];
+    let i256_rhs_u32_ops: [(
+        &'static str,
+        fn(&Host, I256Val, U32Val) -> Result<I256Val, HostError>,
+    ); 3] = [
+        ("i256_pow", Host::i256_pow),
+        ("i256_shl", Host::i256_shl),
+        ("i256_shr", Host::i256_shr),
+    ];
+    for (lhs, rhs) in i256_args {
+        for (name, op) in i256_ops {
+            check_host_fn_cost_bounds(&host, &mut checked, name, &[], || {
+                op(&host, lhs, rhs).map(|_| ())
+            })?;
+        }
+        for (name, op) in i256_rhs_u32_ops {
+            check_host_fn_cost_bounds(&host, &mut checked, name, &[], || {
+                op(&host, lhs, U32Val::from(2_u32)).map(|_| ())
+            })?;
+        }
+    }
+
+    // Every modeled function is a host function and has been checked, and
+    // every other host function is reported as unmodeled.
+    let modeled: Vec<_> = Budget::host_fns_with_cost_bounds().collect();
+    for (name, _) in modeled.iter() {
+        assert!(HOST_FN_NAMES.contains(name), "{name} is not a host fn");
+    }
+    for name in HOST_FN_NAMES {
+        if let Some((_, num_sizes)) = modeled.iter().find(|(n, _)| n == name) {
+            assert!(checked.contains(name), "{name} is not checked");
+            host.as_budget()
+                .host_fn_cost_bounds(name, &vec![0; *num_sizes])?;
+        } else {
+            assert!(
+                HostError::result_matches_err(
+                    host.as_budget().host_fn_cost_bounds(name, &[]),
+                    (ScErrorType::Value, ScErrorCode::InvalidInput)
+                ),
+                "{name} is not modeled"
+            );
+        }
+    }
+    Ok(())
+}
+
 #[test]
 fn test_vm_fuel_metering() -> Result<(), HostError> {
     use super::util::wasm_module_with_4n_insns;
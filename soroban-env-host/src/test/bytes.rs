@@ -1,10 +1,10 @@
 use crate::{
     xdr::{ScError, ScVal},
-    Env, Host, HostError, Val,
+    Env, Host, HostError, Val, XdrReadLimits,
 };
 use soroban_env_common::{
     xdr::{ScErrorCode, ScErrorType},
-    Compare, EnvBase, Error,
+    BytesObject, Compare, EnvBase, Error, U32Val,
 };
 
 use crate::Symbol;
@@ -174,3 +174,368 @@ fn linear_memory_operations() -> Result<(), HostError> {
 
     Ok(())
 }
+
+#[cfg(feature = "next")]
+fn wasm_module_appending_bytes_from_linear_memory(chunk_pos: u32) -> Vec<u8> {
+    use soroban_synth_wasm::{Arity, ModEmitter, Operand};
+    let mut fe = ModEmitter::new().func(Arity(0), 0);
+    fe.push(Operand::Const32(0));
+    fe.push(Operand::Const64(0x0807060504030201));
+    fe.i64_store(0);
+    fe.push(Operand::Const32(8));
+    fe.push(Operand::Const64(0x100f0e0d0c0b0a09));
+    fe.i64_store(0);
+    // The slices, each a 4-byte pointer followed by a 4-byte length.
+    let slices = [(0_u64, 4_u64), (4, 4), (chunk_pos as u64, 8)];
+    for (i, (ptr, len)) in slices.iter().enumerate() {
+        fe.push(Operand::Const32(16 + 8 * i as i32));
+        fe.push(Operand::Const64((ptr | (len << 32)) as i64));
+        fe.i64_store(0);
+    }
+    fe.bytes_new();
+    fe.bytes_append_from_linear_memory(
+        Operand::StackTop,
+        U32Val::from(16).to_val(),
+        U32Val::from(1).to_val(),
+    );
+    fe.bytes_append_from_linear_memory(
+        Operand::StackTop,
+        U32Val::from(24).to_val(),
+        U32Val::from(2).to_val(),
+    );
+    let (mut me, f) = fe.finish();
+    me.export_func(f, "test");
+    me.export_memory("memory");
+    me.finish()
+}
+
+#[cfg(feature = "next")]
+#[test]
+fn bytes_append_from_linear_memory() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::BYTES_APPEND_FROM_LINEAR_MEMORY_MIN_PROTOCOL
+    })?;
+    let test_fn = Symbol::try_from_small_str("test")?;
+
+    // The contents of the object are followed by all the slices.
+    let contract = host
+        .register_test_contract_wasm(wasm_module_appending_bytes_from_linear_memory(8).as_slice());
+    let obj: BytesObject = host.call(contract, test_fn, host.vec_new()?)?.try_into()?;
+    let expected: Vec<u8> = (1..=16).collect();
+    let expected = host.bytes_new_from_slice(&expected)?;
+    assert_eq!(host.obj_cmp(obj.to_val(), expected.to_val())?, 0);
+
+    // Reading past the end of linear memory fails.
+    let contract = host.register_test_contract_wasm(
+        wasm_module_appending_bytes_from_linear_memory(u32::MAX - 4).as_slice(),
+    );
+    assert!(host.call(contract, test_fn, host.vec_new()?).is_err());
+    Ok(())
+}
+
+#[test]
+fn bytes_append_from_linear_memory_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::BYTES_APPEND_FROM_LINEAR_MEMORY_MIN_PROTOCOL - 1
+    })?;
+    let obj = host.bytes_new()?;
+    assert!(HostError::result_matches_err(
+        host.bytes_append_from_linear_memory(obj, U32Val::from(0), U32Val::from(1)),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[test]
+fn bytes_zstd_decompression() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::ZSTD_DECOMPRESSION_MIN_PROTOCOL
+    })?;
+    let data: Vec<u8> = (0..1000).map(|i| (i % 7) as u8).collect();
+    let obj = host.bytes_new_from_slice(&data)?;
+
+    let compressed = host.bytes_new_from_slice(&ruzstd::encoding::compress_to_vec(
+        data.as_slice(),
+        ruzstd::encoding::CompressionLevel::Fastest,
+    ))?;
+
+    let decompressed = host.bytes_decompress_zstd(compressed, 1000_u32.into())?;
+    assert_eq!(host.obj_cmp(obj.to_val(), decompressed.to_val())?, 0);
+
+    // The output length limit is strict.
+    assert!(HostError::result_matches_err(
+        host.bytes_decompress_zstd(compressed, 999_u32.into()),
+        (ScErrorType::Value, ScErrorCode::ExceededLimit)
+    ));
+
+    // Invalid compressed data is rejected.
+    let garbage = host.bytes_new_from_slice(&[0xff; 16])?;
+    assert!(HostError::result_matches_err(
+        host.bytes_decompress_zstd(garbage, 1000_u32.into()),
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+
+    // The window declared by the frame header is charged before the decoder
+    // reserves it: this frame header declares a window of 2^41 bytes.
+    let huge_window = host.bytes_new_from_slice(&[0x28, 0xb5, 0x2f, 0xfd, 0x00, 0xf8])?;
+    assert!(HostError::result_matches_err(
+        host.bytes_decompress_zstd(huge_window, 1000_u32.into()),
+        (ScErrorType::Budget, ScErrorCode::ExceededLimit)
+    ));
+    Ok(())
+}
+
+#[test]
+fn bytes_zstd_decompression_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::ZSTD_DECOMPRESSION_MIN_PROTOCOL - 1
+    })?;
+    let compressed = host.bytes_new_from_slice(&ruzstd::encoding::compress_to_vec(
+        [7_u8; 100].as_slice(),
+        ruzstd::encoding::CompressionLevel::Fastest,
+    ))?;
+    assert!(HostError::result_matches_err(
+        host.bytes_decompress_zstd(compressed, 100_u32.into()),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[test]
+fn deserialize_from_bytes_respects_xdr_read_limits() -> Result<(), HostError> {
+    let host = Host::default();
+    assert_eq!(host.xdr_read_limits()?, XdrReadLimits::default());
+    let inner = host.vec_new()?;
+    let outer: Val = host.vec_push_back(host.vec_new()?, inner.into())?.into();
+    let bo = host.serialize_to_bytes(outer)?;
+    let len: u32 = host.bytes_len(bo)?.into();
+
+    host.set_xdr_read_limits(XdrReadLimits { depth: 2, len })?;
+    let res = host.deserialize_from_bytes(bo);
+    let code = (ScErrorType::Context, ScErrorCode::ExceededLimit);
+    assert!(HostError::result_matches_err(res, code));
+
+    host.set_xdr_read_limits(XdrReadLimits {
+        depth: 100,
+        len: len - 1,
+    })?;
+    let res = host.deserialize_from_bytes(bo);
+    let code = (ScErrorType::Value, ScErrorCode::ExceededLimit);
+    assert!(HostError::result_matches_err(res, code));
+
+    host.set_xdr_read_limits(XdrReadLimits { depth: 100, len })?;
+    let back = host.deserialize_from_bytes(bo)?;
+    assert_eq!(
+        host.compare(&back, &outer.to_val())?,
+        core::cmp::Ordering::Equal
+    );
+    Ok(())
+}
+
+#[test]
+fn metered_from_xdr_with_budget_respects_xdr_read_limits() -> Result<(), HostError> {
+    use crate::{
+        budget::AsBudget,
+        host::metered_xdr::metered_from_xdr_with_budget,
+        xdr::{ScVec, WriteXdr},
+    };
+    let host = Host::default();
+    let inner = ScVal::Vec(Some(ScVec::default()));
+    let outer = ScVal::Vec(Some(ScVec(vec![inner].try_into()?)));
+    let buf = outer.to_xdr()?;
+    let len = buf.len() as u32;
+    let budget = host.as_budget();
+
+    let res = metered_from_xdr_with_budget::<ScVal>(&buf, budget, XdrReadLimits { depth: 2, len });
+    let code = (ScErrorType::Context, ScErrorCode::ExceededLimit);
+    assert!(HostError::result_matches_err(res, code));
+
+    let limits = XdrReadLimits {
+        depth: 100,
+        len: len - 1,
+    };
+    let res = metered_from_xdr_with_budget::<ScVal>(&buf, budget, limits);
+    let code = (ScErrorType::Value, ScErrorCode::ExceededLimit);
+    assert!(HostError::result_matches_err(res, code));
+
+    let limits = XdrReadLimits { depth: 100, len };
+    assert_eq!(
+        metered_from_xdr_with_budget::<ScVal>(&buf, budget, limits)?,
+        outer
+    );
+    Ok(())
+}
+
+#[test]
+fn val_to_canonical_bytes() -> Result<(), HostError> {
+    use crate::{
+        budget::AsBudget,
+        xdr::{ContractCostType, Hash, Int128Parts, ScAddress, ScMap, ScMapEntry, ScVec},
+    };
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::CANONICAL_VAL_BYTES_MIN_PROTOCOL
+    })?;
+    let encode = |v: &ScVal| -> Result<String, HostError> {
+        let obj = host.val_to_canonical_bytes(host.to_host_val(v)?)?;
+        let ScVal::Bytes(b) = host.from_host_val(obj.into())? else {
+            panic!("unexpected value");
+        };
+        Ok(String::from_utf8(b.to_vec()).unwrap())
+    };
+
+    let map = ScVal::Map(Some(ScMap(
+        vec![
+            ScMapEntry {
+                key: ScVal::Symbol("a".try_into()?),
+                val: ScVal::Vec(Some(ScVec(
+                    vec![ScVal::Bool(true), ScVal::String("q\"x\n".try_into()?)].try_into()?,
+                ))),
+            },
+            ScMapEntry {
+                key: ScVal::Symbol("b".try_into()?),
+                val: ScVal::U64(u64::MAX),
+            },
+        ]
+        .try_into()?,
+    )));
+    assert_eq!(
+        encode(&map)?,
+        r#"scval1:{"map":[[{"symbol":"a"},{"vec":[true,{"string":"q\"x\u000a"}]}],[{"symbol":"b"},{"u64":"18446744073709551615"}]]}"#
+    );
+    assert_eq!(
+        encode(&ScVal::I128(Int128Parts {
+            hi: -1,
+            lo: u64::MAX - 4
+        }))?,
+        r#"scval1:{"i128":"-5"}"#
+    );
+    assert_eq!(encode(&ScVal::U32(7))?, r#"scval1:{"u32":7}"#);
+    assert_eq!(encode(&ScVal::Void)?, "scval1:null");
+    assert_eq!(
+        encode(&ScVal::Bytes(vec![0xab, 0x01].try_into()?))?,
+        r#"scval1:{"bytes":"ab01"}"#
+    );
+    assert_eq!(
+        encode(&ScVal::Address(ScAddress::Contract(Hash([0; 32]))))?,
+        r#"scval1:{"address":"CAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAABSC4"}"#
+    );
+
+    // Strings have to be valid UTF-8.
+    let invalid = host.to_host_val(&ScVal::String(vec![0xff].try_into()?))?;
+    assert!(HostError::result_matches_err(
+        host.val_to_canonical_bytes(invalid),
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+
+    // The encoding is charged for upfront, with an upper bound of its length
+    // that holds even when every character gets escaped.
+    let escaped = host.to_host_val(&ScVal::String(vec![b'\n'; 100].try_into()?))?;
+    let val_ser_input = || -> Result<u64, HostError> {
+        Ok(host
+            .as_budget()
+            .get_tracker(ContractCostType::ValSer)?
+            .1
+            .unwrap_or(0))
+    };
+    let charged = val_ser_input()?;
+    let obj = host.val_to_canonical_bytes(escaped)?;
+    let charged = val_ser_input()? - charged;
+    assert!(charged >= u32::from(host.bytes_len(obj)?) as u64);
+    Ok(())
+}
+
+#[test]
+fn val_to_canonical_bytes_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::CANONICAL_VAL_BYTES_MIN_PROTOCOL - 1
+    })?;
+    assert!(HostError::result_matches_err(
+        host.val_to_canonical_bytes(Val::from(7_u32)),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[test]
+fn string_new_from_vals() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::STRING_FROM_VALS_MIN_PROTOCOL
+    })?;
+    let parts = host.vec_new_from_slice(&[
+        host.string_new_from_slice("ipfs://")?.to_val(),
+        Symbol::try_from_small_str("nft").unwrap().to_val(),
+        host.string_new_from_slice("/")?.to_val(),
+        host.obj_from_u64(u64::MAX)?.to_val(),
+        host.string_new_from_slice("/")?.to_val(),
+        host.obj_from_i128_pieces(-1, u64::MAX - 4)?.to_val(),
+        Val::from(7_u32),
+    ])?;
+    let s = host.string_new_from_vals(parts)?;
+    let ScVal::String(s) = host.from_host_val(s.into())? else {
+        panic!("unexpected value");
+    };
+    assert_eq!(s.as_slice(), b"ipfs://nft/18446744073709551615/-57");
+
+    // An empty vector builds an empty string.
+    let s = host.string_new_from_vals(host.vec_new()?)?;
+    assert_eq!(host.string_len(s)?, 0_u32.into());
+
+    // Other types can't be parts.
+    let parts = host.vec_new_from_slice(&[host.bytes_new_from_slice(&[1])?.to_val()])?;
+    assert!(HostError::result_matches_err(
+        host.string_new_from_vals(parts),
+        (ScErrorType::Object, ScErrorCode::UnexpectedType)
+    ));
+    Ok(())
+}
+
+#[test]
+fn string_new_from_vals_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::STRING_FROM_VALS_MIN_PROTOCOL - 1
+    })?;
+    let parts = host.vec_new_from_slice(&[Val::from(7_u32)])?;
+    assert!(HostError::result_matches_err(
+        host.string_new_from_vals(parts),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[test]
+fn bytes_and_strings_respect_object_size_limits() -> Result<(), HostError> {
+    let host = Host::default();
+    assert_eq!(
+        host.object_size_limits()?,
+        crate::ObjectSizeLimits::default()
+    );
+    host.set_object_size_limits(crate::ObjectSizeLimits {
+        max_bytes_len: 4,
+        ..Default::default()
+    })?;
+    let code = (ScErrorType::Object, ScErrorCode::ExceededLimit);
+    let b = host.bytes_new_from_slice(&[0; 4])?;
+    assert!(HostError::result_matches_err(
+        host.bytes_new_from_slice(&[0; 5]),
+        code
+    ));
+    assert!(HostError::result_matches_err(
+        host.bytes_push(b, 0_u32.into()),
+        code
+    ));
+    assert!(HostError::result_matches_err(host.bytes_append(b, b), code));
+    host.string_new_from_slice("abcd")?;
+    assert!(HostError::result_matches_err(
+        host.string_new_from_slice("abcde"),
+        code
+    ));
+    Ok(())
+}
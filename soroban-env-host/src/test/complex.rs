@@ -59,3 +59,31 @@ fn run_complex() -> Result<(), HostError> {
     }
     Ok(())
 }
+
+#[test]
+fn run_complex_on_worker_thread() -> Result<(), HostError> {
+    use crate::FinishedHostState;
+
+    let account_id = generate_account_id();
+    let salt = generate_bytes_array();
+    let worker = std::thread::spawn(move || -> Result<FinishedHostState, HostError> {
+        let host = Host::test_host_with_recording_footprint();
+        let contract_id_obj =
+            host.register_test_contract_wasm_from_source_account(COMPLEX, account_id, salt);
+        host.call(
+            contract_id_obj,
+            Symbol::try_from_small_str("go")?,
+            host.add_host_object(HostVec::new())?,
+        )?;
+        host.try_finish_owned()
+    });
+    let state = worker.join().unwrap()?;
+
+    assert!(!state.footprint.is_empty());
+    for (k, _) in state.ledger_entries.iter() {
+        assert!(state.footprint.iter().any(|(fk, _)| fk == k));
+    }
+    assert!(state.cpu_insns_consumed > 0);
+    assert!(state.execution_stats.max_frame_depth > 0);
+    Ok(())
+}
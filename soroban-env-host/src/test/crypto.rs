@@ -1,6 +1,12 @@
-use crate::{xdr::ScVal, Env, Host, HostError};
+use crate::{
+    host_vec,
+    native_contract::testutils::{generate_signing_key, HostVec},
+    xdr::{Int128Parts, ScErrorCode, ScErrorType, ScMap, ScMapEntry, ScVal, WriteXdr},
+    Env, Host, HostError, LedgerInfo, Symbol, TryFromVal,
+};
+use ed25519_dalek::Signer;
 use hex::FromHex;
-use soroban_env_common::{EnvBase, U32Val};
+use soroban_env_common::{EnvBase, TryIntoVal, U32Val};
 
 /// crypto tests
 #[test]
@@ -105,3 +111,347 @@ fn recover_ecdsa_secp256k1_key_test() -> Result<(), HostError> {
     assert_eq!(host.obj_cmp(pk_obj.to_val(), pk_obj_2.to_val())?, 0);
     Ok(())
 }
+
+#[test]
+fn verify_multisig_ed25519_test() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::MULTISIG_ED25519_MIN_PROTOCOL
+    })?;
+    let payload: &[u8] = b"multisig payload";
+    let signing_keys = [
+        generate_signing_key(),
+        generate_signing_key(),
+        generate_signing_key(),
+    ];
+    let mut keys = HostVec::new(&host)?;
+    let mut sigs = vec![];
+    for k in signing_keys.iter() {
+        keys.push_val(
+            host.bytes_new_from_slice(k.verifying_key().as_bytes())?
+                .into(),
+        )?;
+        sigs.push(host.bytes_new_from_slice(&k.sign(payload).to_bytes())?);
+    }
+    let weights = host_vec![&host, 1_u32, 2_u32, 3_u32];
+    let payload_obj = host.bytes_new_from_slice(payload)?;
+    let verify = |threshold: u32, sigs: HostVec| {
+        host.verify_multisig_ed25519(
+            keys.clone().into(),
+            weights.clone().into(),
+            threshold.into(),
+            payload_obj,
+            sigs.into(),
+        )
+        .map(bool::from)
+    };
+
+    let first_two = host_vec![&host, sigs[0], sigs[1], ()];
+    assert!(verify(3, first_two.clone())?);
+    assert!(!verify(4, first_two)?);
+    assert!(verify(6, host_vec![&host, sigs[0], sigs[1], sigs[2]])?);
+    assert!(!verify(1, host_vec![&host, (), (), ()])?);
+
+    // A signature by the wrong key fails the verification.
+    let res = verify(1, host_vec![&host, sigs[1], (), ()]);
+    let code = (ScErrorType::Crypto, ScErrorCode::InvalidInput);
+    assert!(HostError::result_matches_err(res, code));
+    // So do mismatched lengths and a zero threshold.
+    let res = verify(1, host_vec![&host, sigs[0], ()]);
+    assert!(HostError::result_matches_err(res, code));
+    let res = verify(0, host_vec![&host, sigs[0], (), ()]);
+    assert!(HostError::result_matches_err(res, code));
+    Ok(())
+}
+
+#[test]
+fn verify_multisig_ed25519_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::MULTISIG_ED25519_MIN_PROTOCOL - 1
+    })?;
+    let key = generate_signing_key();
+    let payload: &[u8] = b"multisig payload";
+    let keys = host_vec![
+        &host,
+        host.bytes_new_from_slice(key.verifying_key().as_bytes())?
+    ];
+    let sigs = host_vec![
+        &host,
+        host.bytes_new_from_slice(&key.sign(payload).to_bytes())?
+    ];
+    let res = host.verify_multisig_ed25519(
+        keys.into(),
+        host_vec![&host, 1_u32].into(),
+        1_u32.into(),
+        host.bytes_new_from_slice(payload)?,
+        sigs.into(),
+    );
+    let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+    assert!(HostError::result_matches_err(res, code));
+    Ok(())
+}
+
+#[test]
+fn verify_oracle_attestation_test() -> Result<(), HostError> {
+    use k256::ecdsa::signature::hazmat::PrehashSigner;
+    use sha2::Digest;
+
+    let host = Host::default();
+    host.set_ledger_info(LedgerInfo {
+        protocol_version: crate::host::ORACLE_ATTESTATION_MIN_PROTOCOL,
+        timestamp: 1000,
+        ..Default::default()
+    })?;
+    let attestation = |timestamp: u64| -> Vec<u8> {
+        ScVal::Map(Some(ScMap(
+            vec![
+                ScMapEntry {
+                    key: ScVal::Symbol("price".try_into().unwrap()),
+                    val: ScVal::I128(Int128Parts { hi: 0, lo: 12345 }),
+                },
+                ScMapEntry {
+                    key: ScVal::Symbol("timestamp".try_into().unwrap()),
+                    val: ScVal::U64(timestamp),
+                },
+            ]
+            .try_into()
+            .unwrap(),
+        )))
+        .to_xdr()
+        .unwrap()
+    };
+    let ed25519_key = generate_signing_key();
+    let ed25519_pub_key = host.bytes_new_from_slice(ed25519_key.verifying_key().as_bytes())?;
+    let verify_ed25519 = |payload: &[u8], max_age: u64| {
+        let sig = ed25519_key.sign(payload).to_bytes();
+        host.verify_oracle_attestation(
+            host.bytes_new_from_slice(payload)?,
+            host.bytes_new_from_slice(&sig)?,
+            ed25519_pub_key,
+            max_age.try_into_val(&host)?,
+        )
+    };
+
+    let res = verify_ed25519(&attestation(990), 10)?;
+    let price = host.map_get(res, Symbol::try_from_small_str("price").unwrap().to_val())?;
+    assert_eq!(i128::try_from_val(&host, &price)?, 12345);
+    // Stale and future attestations are rejected.
+    let code = (ScErrorType::Context, ScErrorCode::InvalidInput);
+    assert!(HostError::result_matches_err(
+        verify_ed25519(&attestation(989), 10),
+        code
+    ));
+    assert!(HostError::result_matches_err(
+        verify_ed25519(&attestation(1001), 10),
+        code
+    ));
+    // So are payloads without a timestamp.
+    let payload = ScVal::Map(Some(ScMap(Vec::<ScMapEntry>::new().try_into()?))).to_xdr()?;
+    assert!(HostError::result_matches_err(
+        verify_ed25519(&payload, 10),
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+    // A signature of another payload fails the verification.
+    let sig = ed25519_key.sign(&attestation(995)).to_bytes();
+    assert!(HostError::result_matches_err(
+        host.verify_oracle_attestation(
+            host.bytes_new_from_slice(&attestation(990))?,
+            host.bytes_new_from_slice(&sig)?,
+            ed25519_pub_key,
+            10_u64.try_into_val(&host)?,
+        ),
+        (ScErrorType::Crypto, ScErrorCode::InvalidInput)
+    ));
+
+    // secp256k1 keys sign the SHA-256 digest of the payload.
+    let secp256k1_key = k256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+    let payload = attestation(1000);
+    let digest = sha2::Sha256::digest(&payload);
+    let sig: k256::ecdsa::Signature = secp256k1_key.sign_prehash(digest.as_slice()).unwrap();
+    let res = host.verify_oracle_attestation(
+        host.bytes_new_from_slice(&payload)?,
+        host.bytes_new_from_slice(&sig.to_bytes())?,
+        host.bytes_new_from_slice(
+            secp256k1_key
+                .verifying_key()
+                .to_encoded_point(false)
+                .as_bytes(),
+        )?,
+        0_u64.try_into_val(&host)?,
+    )?;
+    let timestamp = host.map_get(res, Symbol::try_from_val(&host, &"timestamp")?.to_val())?;
+    assert_eq!(u64::try_from_val(&host, &timestamp)?, 1000);
+    Ok(())
+}
+
+#[test]
+fn verify_oracle_attestation_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::default();
+    host.set_ledger_info(LedgerInfo {
+        protocol_version: crate::host::ORACLE_ATTESTATION_MIN_PROTOCOL - 1,
+        timestamp: 1000,
+        ..Default::default()
+    })?;
+    let payload = ScVal::Map(Some(ScMap(
+        vec![ScMapEntry {
+            key: ScVal::Symbol("timestamp".try_into()?),
+            val: ScVal::U64(1000),
+        }]
+        .try_into()?,
+    )))
+    .to_xdr()?;
+    let key = generate_signing_key();
+    let res = host.verify_oracle_attestation(
+        host.bytes_new_from_slice(&payload)?,
+        host.bytes_new_from_slice(&key.sign(&payload).to_bytes())?,
+        host.bytes_new_from_slice(key.verifying_key().as_bytes())?,
+        0_u64.try_into_val(&host)?,
+    );
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[test]
+fn ristretto255_test() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::RISTRETTO255_FNS_MIN_PROTOCOL
+    })?;
+    let scalar = |n: u8| {
+        let mut bytes = [0u8; 32];
+        bytes[0] = n;
+        host.test_bin_obj(&bytes)
+    };
+    let to_scval = |obj| host.from_host_val(obj).unwrap();
+
+    let basepoint = host.ristretto255_basepoint_mul(scalar(1)?)?;
+    let expected =
+        Vec::from_hex("e2f2ae0a6abc4e71a884a961c500515f58e30b6aa582dd8db6a65945e08d2d76").unwrap();
+    assert_eq!(
+        to_scval(basepoint.to_val()),
+        ScVal::Bytes(expected.try_into().unwrap())
+    );
+
+    // 2B = B + B, whichever way it's computed.
+    let doubled = host.ristretto255_basepoint_mul(scalar(2)?)?;
+    let sum = host.ristretto255_point_add(basepoint, basepoint)?;
+    let product = host.ristretto255_scalar_mul(scalar(2)?, basepoint)?;
+    assert_eq!(to_scval(doubled.to_val()), to_scval(sum.to_val()));
+    assert_eq!(to_scval(doubled.to_val()), to_scval(product.to_val()));
+
+    // Unreduced scalars and invalid point encodings are rejected.
+    let invalid = host.test_bin_obj(&[0xff; 32])?;
+    let res = host.ristretto255_basepoint_mul(invalid);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Crypto, ScErrorCode::InvalidInput)
+    ));
+    let res = host.ristretto255_point_add(basepoint, invalid);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Crypto, ScErrorCode::InvalidInput)
+    ));
+    let res = host.ristretto255_basepoint_mul(host.test_bin_obj(&[1; 31])?);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Object, ScErrorCode::UnexpectedSize)
+    ));
+    Ok(())
+}
+
+#[test]
+fn ristretto255_fns_are_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::RISTRETTO255_FNS_MIN_PROTOCOL - 1
+    })?;
+    let mut bytes = [0u8; 32];
+    bytes[0] = 1;
+    let scalar = host.test_bin_obj(&bytes)?;
+    assert!(HostError::result_matches_err(
+        host.ristretto255_basepoint_mul(scalar),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[test]
+fn verify_sig_ecdsa_secp256r1_test() -> Result<(), HostError> {
+    use p256::ecdsa::signature::hazmat::PrehashSigner;
+    use sha2::Digest;
+
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::SECP256R1_VERIFY_MIN_PROTOCOL
+    })?;
+    let key = p256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+    let public_key =
+        host.bytes_new_from_slice(key.verifying_key().to_encoded_point(false).as_bytes())?;
+    let digest = sha2::Sha256::digest(b"webauthn client data");
+    let digest_obj = host.bytes_new_from_slice(digest.as_slice())?;
+    let sig: p256::ecdsa::Signature = key.sign_prehash(digest.as_slice()).unwrap();
+    let sig = sig.normalize_s().unwrap_or(sig);
+    let sig_obj = host.bytes_new_from_slice(&sig.to_bytes())?;
+    host.verify_sig_ecdsa_secp256r1(public_key, digest_obj, sig_obj)?;
+
+    // Compressed public keys are accepted too.
+    let compressed_key =
+        host.bytes_new_from_slice(key.verifying_key().to_encoded_point(true).as_bytes())?;
+    host.verify_sig_ecdsa_secp256r1(compressed_key, digest_obj, sig_obj)?;
+
+    // A signature over another digest fails verification.
+    let other_digest = host.bytes_new_from_slice(&[1; 32])?;
+    let res = host.verify_sig_ecdsa_secp256r1(public_key, other_digest, sig_obj);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Crypto, ScErrorCode::InvalidInput)
+    ));
+
+    // The high-s form of a valid signature is rejected.
+    let (r, s) = sig.split_scalars();
+    let high_s = p256::ecdsa::Signature::from_scalars(r, -s).unwrap();
+    let res = host.verify_sig_ecdsa_secp256r1(
+        public_key,
+        digest_obj,
+        host.bytes_new_from_slice(&high_s.to_bytes())?,
+    );
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Crypto, ScErrorCode::InvalidInput)
+    ));
+
+    // Malformed inputs are rejected.
+    let res =
+        host.verify_sig_ecdsa_secp256r1(host.bytes_new_from_slice(&[4; 65])?, digest_obj, sig_obj);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Crypto, ScErrorCode::InvalidInput)
+    ));
+    let res =
+        host.verify_sig_ecdsa_secp256r1(public_key, host.bytes_new_from_slice(&[1; 31])?, sig_obj);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Object, ScErrorCode::UnexpectedSize)
+    ));
+    Ok(())
+}
+
+#[test]
+fn verify_sig_ecdsa_secp256r1_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::SECP256R1_VERIFY_MIN_PROTOCOL - 1
+    })?;
+    let public_key = host.bytes_new_from_slice(&[4; 65])?;
+    let digest = host.bytes_new_from_slice(&[1; 32])?;
+    let sig = host.bytes_new_from_slice(&[1; 64])?;
+    assert!(HostError::result_matches_err(
+        host.verify_sig_ecdsa_secp256r1(public_key, digest, sig),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
@@ -1,14 +1,19 @@
 use crate::{
     budget::AsBudget,
     events::{
-        InternalContractEvent, InternalDiagnosticArg, InternalDiagnosticEvent, InternalEvent,
+        DecodedEvent, HostEvent, InternalContractEvent, InternalDiagnosticArg,
+        InternalDiagnosticEvent, InternalEvent,
     },
     test::util::AsScVal,
     xdr::{
         ContractCostType, ContractEvent, ContractEventBody, ContractEventType, ContractEventV0,
-        ExtensionPoint, Hash, ScAddress, ScMap, ScMapEntry, ScVal,
+        ExtensionPoint, Hash, ScAddress, ScError, ScMap, ScMapEntry, ScSpecEntry, ScSpecTypeDef,
+        ScSpecUdtErrorEnumCaseV0, ScSpecUdtErrorEnumV0, ScSpecUdtStructFieldV0, ScSpecUdtStructV0,
+        ScSpecUdtUnionCaseTupleV0, ScSpecUdtUnionCaseV0, ScSpecUdtUnionCaseVoidV0,
+        ScSpecUdtUnionV0, ScSymbol, ScVal, WriteXdr,
     },
-    ContractFunctionSet, Env, Host, HostError, Symbol, SymbolSmall, Val,
+    ContractEventLimits, ContractFunctionSet, Env, EventFilter, EventLimitPolicy, Host, HostError,
+    Symbol, SymbolSmall, Val,
 };
 use expect_test::expect;
 use std::rc::Rc;
@@ -103,6 +108,279 @@ fn test_event_rollback() -> Result<(), HostError> {
     Ok(())
 }
 
+#[test]
+fn test_events_record_cpu_insns() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.set_diagnostic_level(crate::DiagnosticLevel::Debug)?;
+    let dummy_address = ScAddress::Contract(Hash([0; 32]));
+    let id = host.add_host_object(dummy_address)?;
+    let test_contract = Rc::new(ContractWithMultipleEvents {});
+    let sym = Symbol::try_from_small_str("add").unwrap();
+    let args = host.test_vec_obj::<i32>(&[1, 2])?;
+    host.register_test_contract(id, test_contract)?;
+    host.call(id, sym, args)?;
+
+    let events = host.get_events()?.0;
+    let cpu_insns = host.get_events_cpu_insns()?;
+    assert_eq!(cpu_insns.len(), events.len());
+    let contract_events: Vec<u64> = events
+        .iter()
+        .zip(cpu_insns.iter())
+        .filter(|(e, _)| e.event.type_ != ContractEventType::Diagnostic)
+        .map(|(_, cpu)| *cpu)
+        .collect();
+    assert_eq!(contract_events.len(), 2);
+    // Recording the first contract event is charged, so the second one is
+    // tagged with a larger counter.
+    assert!(contract_events[0] > 0);
+    assert!(contract_events[0] < contract_events[1]);
+    // The counters follow the chronological order of all the events.
+    assert!(cpu_insns.windows(2).all(|w| w[0] <= w[1]));
+    assert!(*cpu_insns.last().unwrap() <= host.as_budget().get_cpu_insns_consumed()?);
+    Ok(())
+}
+
+#[test]
+fn test_snapshot_events() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let dummy_address = ScAddress::Contract(Hash([0; 32]));
+    let id = host.add_host_object(dummy_address)?;
+    let test_contract = Rc::new(ContractWithMultipleEvents {});
+    let sym = Symbol::try_from_small_str("add").unwrap();
+    let args = host.test_vec_obj::<i32>(&[1, 2])?;
+    host.register_test_contract(id, test_contract)?;
+    host.call(id, sym, args)?;
+
+    let cpu_before = host.as_budget().get_cpu_insns_consumed()?;
+    let first = host.snapshot_events(0)?.0;
+    assert_eq!(first.len(), 2);
+    // Snapshots are metered.
+    assert!(host.as_budget().get_cpu_insns_consumed()? > cpu_before);
+
+    // Only the events recorded after `start` are externalized.
+    host.call(id, sym, args)?;
+    let second = host.snapshot_events(first.len())?.0;
+    assert_eq!(second.len(), 2);
+    assert_eq!(second[0].event, first[0].event);
+    let cpu_insns = host.get_events_cpu_insns()?;
+    assert!(cpu_insns[first.len()] > cpu_insns[first.len() - 1]);
+    assert_eq!(host.get_events()?.0.len(), 4);
+
+    let cpu_before = host.as_budget().get_cpu_insns_consumed()?;
+    assert!(host.snapshot_events(4)?.0.is_empty());
+    assert_eq!(host.as_budget().get_cpu_insns_consumed()?, cpu_before);
+    Ok(())
+}
+
+#[test]
+fn test_contract_events_size() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::CONTRACT_EVENTS_SIZE_MIN_PROTOCOL
+    })?;
+    let dummy_address = ScAddress::Contract(Hash([0; 32]));
+    let id = host.add_host_object(dummy_address)?;
+    let test_contract = Rc::new(ContractWithMultipleEvents {});
+    let sym = Symbol::try_from_small_str("add").unwrap();
+    let args = host.test_vec_obj::<i32>(&[1, 2])?;
+    host.register_test_contract(id, test_contract)?;
+    assert_eq!(host.contract_events_size()?, 0);
+    host.call(id, sym, args)?;
+
+    let event_sizes: Vec<u64> = host
+        .get_events()?
+        .0
+        .iter()
+        .filter(|e| e.event.type_ != ContractEventType::Diagnostic)
+        .map(|e| e.event.to_xdr().unwrap().len() as u64)
+        .collect();
+    assert_eq!(event_sizes.len(), 2);
+    assert_eq!(
+        host.contract_events_size()?,
+        event_sizes[0] + event_sizes[1]
+    );
+    assert_eq!(
+        u32::from(host.get_contract_events_size()?) as u64,
+        event_sizes[0] + event_sizes[1]
+    );
+    // Rolled back events don't count.
+    host.try_borrow_events_mut()?.rollback(1)?;
+    assert_eq!(host.contract_events_size()?, event_sizes[0]);
+    Ok(())
+}
+
+#[test]
+fn test_contract_events_size_is_protocol_gated() -> Result<(), HostError> {
+    use crate::xdr::{ScErrorCode, ScErrorType};
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::CONTRACT_EVENTS_SIZE_MIN_PROTOCOL - 1
+    })?;
+    assert!(HostError::result_matches_err(
+        host.get_contract_events_size(),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_contract_event_limits() -> Result<(), HostError> {
+    use crate::xdr::{ScErrorCode, ScErrorType};
+    let host = Host::test_host_with_recording_footprint();
+    host.set_contract_event_limits(ContractEventLimits {
+        max_events_per_contract: 2,
+        policy: EventLimitPolicy::Truncate,
+    })?;
+    for id in [Hash([1; 32]), Hash([2; 32])] {
+        let address = host.add_host_object(ScAddress::Contract(id))?;
+        host.register_test_contract(address, Rc::new(ContractWithSingleEvent {}))?;
+    }
+    let sym = Symbol::try_from_small_str("emit").unwrap();
+    let emit = |n: u32| -> Result<Val, HostError> {
+        for i in 0..n {
+            let topics = host.test_vec_obj::<u32>(&[i])?;
+            host.contract_event(topics, Val::VOID.into())?;
+        }
+        Ok(Val::VOID.into())
+    };
+    let event_types = |host: &Host| -> Result<Vec<ContractEventType>, HostError> {
+        Ok(host.get_events()?.0.iter().map(|e| e.event.type_).collect())
+    };
+
+    // The noisy contract is truncated, with a single marker...
+    host.with_test_contract_frame(Hash([1; 32]), sym, || {
+        emit(4)?;
+        // ...without affecting the other contracts.
+        host.with_test_contract_frame(Hash([2; 32]), sym, || emit(2))
+    })?;
+    assert_eq!(
+        event_types(&host)?,
+        vec![
+            ContractEventType::Contract,
+            ContractEventType::Contract,
+            ContractEventType::System,
+            ContractEventType::Contract,
+            ContractEventType::Contract,
+        ]
+    );
+    let marker = &host.get_events()?.0[2].event;
+    assert_eq!(marker.contract_id, Some(Hash([1; 32])));
+    let ContractEventBody::V0(body) = &marker.body;
+    assert_eq!(
+        body.topics.to_vec(),
+        vec![ScVal::Symbol(ScSymbol(
+            "events_truncated".try_into().unwrap()
+        ))]
+    );
+    assert_eq!(body.data, ScVal::U32(2));
+
+    // The counts are reset for the next invocation.
+    host.with_test_contract_frame(Hash([1; 32]), sym, || emit(2))?;
+    assert_eq!(event_types(&host)?.len(), 7);
+
+    host.set_contract_event_limits(ContractEventLimits {
+        max_events_per_contract: 2,
+        policy: EventLimitPolicy::Error,
+    })?;
+    let res = host.with_test_contract_frame(Hash([1; 32]), sym, || emit(3));
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Events, ScErrorCode::ExceededLimit)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_event_filter() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    for id in [Hash([1; 32]), Hash([2; 32])] {
+        let address = host.add_host_object(ScAddress::Contract(id))?;
+        host.register_test_contract(address, Rc::new(ContractWithSingleEvent {}))?;
+    }
+    let sym = Symbol::try_from_small_str("emit").unwrap();
+    host.set_event_filter(Some(EventFilter {
+        contract_ids: vec![Hash([1; 32])],
+        topic_prefixes: vec![vec![ScVal::U32(1)], vec![ScVal::U32(2), ScVal::U32(3)]],
+    }))?;
+    let emit = |topics: &[u32]| -> Result<Val, HostError> {
+        let topics = host.test_vec_obj::<u32>(topics)?;
+        host.contract_event(topics, Val::VOID.into())?;
+        Ok(Val::VOID.into())
+    };
+    host.with_test_contract_frame(Hash([1; 32]), sym, || {
+        emit(&[1])?;
+        emit(&[1, 5])?;
+        emit(&[2, 3, 4])?;
+        emit(&[2])?;
+        emit(&[5, 1])?;
+        emit(&[])
+    })?;
+    host.with_test_contract_frame(Hash([2; 32]), sym, || emit(&[1]))?;
+
+    let topics: Vec<Vec<ScVal>> = host
+        .get_events()?
+        .0
+        .iter()
+        .map(|e| {
+            let ContractEventBody::V0(body) = &e.event.body;
+            body.topics.to_vec()
+        })
+        .collect();
+    assert_eq!(
+        topics,
+        vec![
+            vec![ScVal::U32(1)],
+            vec![ScVal::U32(1), ScVal::U32(5)],
+            vec![ScVal::U32(2), ScVal::U32(3), ScVal::U32(4)],
+        ]
+    );
+    assert_eq!(host.filtered_event_count()?, 4);
+
+    // Without a filter, every event is recorded again.
+    host.set_event_filter(None)?;
+    host.with_test_contract_frame(Hash([2; 32]), sym, || emit(&[1]))?;
+    assert_eq!(host.get_events()?.0.len(), 4);
+    assert_eq!(host.filtered_event_count()?, 4);
+    Ok(())
+}
+
+#[test]
+fn test_events_into_decoded_iter() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let dummy_address = ScAddress::Contract(Hash([0; 32]));
+    let id = host.add_host_object(dummy_address)?;
+    host.register_test_contract(id, Rc::new(ContractWithSingleEvent {}))?;
+    let sym = Symbol::try_from_small_str("add").unwrap();
+    host.call(id, sym, host.test_vec_obj::<i32>(&[1, 2])?)?;
+    host.try_borrow_events_mut()?.rollback(0)?;
+    host.call(id, sym, host.test_vec_obj::<i32>(&[1, 2])?)?;
+
+    let data = ScVal::Map(Some(ScMap(
+        vec![ScMapEntry {
+            key: ScVal::U32(1),
+            val: ScVal::U32(2),
+        }]
+        .try_into()
+        .unwrap(),
+    )));
+    let events: Vec<DecodedEvent> = host
+        .get_events()?
+        .into_iter()
+        .filter(|e| e.event_type != ContractEventType::Diagnostic)
+        .collect();
+    assert_eq!(
+        events,
+        [false, true].map(|in_successful_call| DecodedEvent {
+            contract_id: Some(Hash([0; 32])),
+            event_type: ContractEventType::Contract,
+            topics: vec![ScVal::U32(0), ScVal::U32(1)],
+            data: data.clone(),
+            in_successful_call,
+        })
+    );
+    Ok(())
+}
+
 #[test]
 fn test_internal_contract_events_metering_not_free() -> Result<(), HostError> {
     let host = Host::test_host();
@@ -163,3 +441,187 @@ fn test_internal_diagnostic_event_metering_free() -> Result<(), HostError> {
     assert_eq!(host.as_budget().get_mem_bytes_consumed()?, 0);
     Ok(())
 }
+
+#[test]
+fn host_fn_tracing() -> Result<(), HostError> {
+    use crate::{
+        xdr::{ScString, ScSymbol, ScVec},
+        ScValLimits, U32Val,
+    };
+    use soroban_synth_wasm::{Arity, ModEmitter, Operand};
+
+    let mut fe = ModEmitter::new().func(Arity(0), 0);
+    fe.vec_new();
+    fe.vec_push_back(Operand::StackTop, U32Val::from(7).to_val());
+    let (mut me, f) = fe.finish();
+    me.export_func(f, "test");
+    let wasm = me.finish();
+
+    let host = Host::test_host_with_recording_footprint();
+    host.set_diagnostic_level(crate::DiagnosticLevel::Debug)?;
+    // Only scalars and empty vectors fit into depth 1.
+    host.enable_host_fn_tracing(ScValLimits {
+        max_depth: 1,
+        max_values: 10,
+        max_payload_bytes: 100,
+    })?;
+    let contract = host.register_test_contract_wasm(wasm.as_slice());
+    host.call(
+        contract,
+        Symbol::try_from_small_str("test")?,
+        host.vec_new()?,
+    )?;
+
+    let sym = |s: &str| ScVal::Symbol(ScSymbol(s.try_into().unwrap()));
+    let vec = |v: Vec<ScVal>| ScVal::Vec(Some(ScVec(v.try_into().unwrap())));
+    let traces: Vec<_> = host
+        .get_events()?
+        .0
+        .into_iter()
+        .filter_map(|e| {
+            let ContractEventBody::V0(body) = e.event.body;
+            (body.topics.first() == Some(&sym("host_fn")))
+                .then(|| (body.topics[1].clone(), body.data))
+        })
+        .collect();
+    assert_eq!(
+        traces,
+        vec![
+            (sym("vec_new"), vec(vec![vec(vec![]), vec(vec![])])),
+            (
+                sym("vec_push_back"),
+                vec(vec![
+                    vec(vec![vec(vec![]), ScVal::U32(7)]),
+                    ScVal::String(ScString("<elided>".try_into().unwrap())),
+                ])
+            ),
+        ]
+    );
+
+    // Nothing is traced once tracing is disabled.
+    host.disable_host_fn_tracing()?;
+    host.call(
+        contract,
+        Symbol::try_from_small_str("test")?,
+        host.vec_new()?,
+    )?;
+    let num_traces = host
+        .get_events()?
+        .0
+        .iter()
+        .filter(|e| {
+            let ContractEventBody::V0(body) = &e.event.body;
+            body.topics.first() == Some(&sym("host_fn"))
+        })
+        .count();
+    assert_eq!(num_traces, 2);
+    Ok(())
+}
+
+#[test]
+fn render_event_with_contract_type_specs() -> Result<(), HostError> {
+    let host = Host::default();
+    let sym = |s: &str| ScVal::Symbol(ScSymbol(s.try_into().unwrap()));
+    let field = |name: &str| ScSpecUdtStructFieldV0 {
+        doc: Default::default(),
+        name: name.try_into().unwrap(),
+        type_: ScSpecTypeDef::U32,
+    };
+    let specs = vec![
+        ScSpecEntry::UdtStructV0(ScSpecUdtStructV0 {
+            doc: Default::default(),
+            lib: Default::default(),
+            name: "Point".try_into().unwrap(),
+            fields: vec![field("x"), field("y")].try_into().unwrap(),
+        }),
+        ScSpecEntry::UdtUnionV0(ScSpecUdtUnionV0 {
+            doc: Default::default(),
+            lib: Default::default(),
+            name: "Shape".try_into().unwrap(),
+            cases: vec![
+                ScSpecUdtUnionCaseV0::VoidV0(ScSpecUdtUnionCaseVoidV0 {
+                    doc: Default::default(),
+                    name: "Empty".try_into().unwrap(),
+                }),
+                ScSpecUdtUnionCaseV0::TupleV0(ScSpecUdtUnionCaseTupleV0 {
+                    doc: Default::default(),
+                    name: "Circle".try_into().unwrap(),
+                    type_: vec![ScSpecTypeDef::U32].try_into().unwrap(),
+                }),
+            ]
+            .try_into()
+            .unwrap(),
+        }),
+        ScSpecEntry::UdtErrorEnumV0(ScSpecUdtErrorEnumV0 {
+            doc: Default::default(),
+            lib: Default::default(),
+            name: "Error".try_into().unwrap(),
+            cases: vec![ScSpecUdtErrorEnumCaseV0 {
+                doc: Default::default(),
+                name: "OutOfBounds".try_into().unwrap(),
+                value: 3,
+            }]
+            .try_into()
+            .unwrap(),
+        }),
+    ];
+    let point = ScVal::Map(Some(ScMap(
+        vec![
+            ScMapEntry {
+                key: sym("x"),
+                val: ScVal::U32(1),
+            },
+            ScMapEntry {
+                key: sym("y"),
+                val: ScVal::U32(2),
+            },
+        ]
+        .try_into()
+        .unwrap(),
+    )));
+    let event = HostEvent {
+        event: ContractEvent {
+            ext: ExtensionPoint::V0,
+            contract_id: Some(Hash([1; 32])),
+            type_: ContractEventType::Diagnostic,
+            body: ContractEventBody::V0(ContractEventV0 {
+                topics: vec![
+                    ScVal::Vec(Some(vec![sym("Circle"), ScVal::U32(5)].try_into().unwrap())),
+                    ScVal::Vec(Some(vec![sym("Empty")].try_into().unwrap())),
+                    // Doesn't match the case arity.
+                    ScVal::Vec(Some(vec![sym("Circle")].try_into().unwrap())),
+                ]
+                .try_into()
+                .unwrap(),
+                data: ScVal::Vec(Some(
+                    vec![point, ScVal::Error(ScError::Contract(3))]
+                        .try_into()
+                        .unwrap(),
+                )),
+            }),
+        },
+        failed_call: false,
+    };
+
+    let contract_id = "01".repeat(32);
+    assert_eq!(
+        host.render_event(&event)?,
+        format!(
+            "[Diagnostic Event] contract:{contract_id}, topics:[[Circle, 5], [Empty], [Circle]], \
+             data:[{{x: 1, y: 2}}, Error(Contract, #3)]"
+        )
+    );
+    host.register_contract_type_specs(Hash([1; 32]), specs)?;
+    assert_eq!(
+        host.render_event(&event)?,
+        format!(
+            "[Diagnostic Event] contract:{contract_id}, \
+             topics:[Shape::Circle(5), Shape::Empty, [Circle]], \
+             data:[Point {{ x: 1, y: 2 }}, Error::OutOfBounds]"
+        )
+    );
+    // Registering the specs again replaces the previous ones.
+    host.register_contract_type_specs(Hash([1; 32]), vec![])?;
+    assert!(host.render_event(&event)?.contains("data:[{x: 1, y: 2}"));
+    Ok(())
+}
@@ -3,11 +3,12 @@ use std::rc::Rc;
 use expect_test::expect;
 use soroban_env_common::{
     xdr::{self, ScErrorCode},
-    Env, EnvBase, TryFromVal, Val,
+    AddressObject, Env, EnvBase, TryFromVal, Val,
 };
 
 use crate::{
-    events::HostEvent, xdr::ScErrorType, ContractFunctionSet, Error, Host, HostError, Symbol, Tag,
+    events::HostEvent, storage::CodeStore, xdr::ScErrorType, ContractFunctionSet, Error,
+    ExecutionStats, FeeRefund, Host, HostError, Symbol, Tag,
 };
 use soroban_test_wasms::{ADD_I32, ALLOC, ERR, INVOKE_CONTRACT, VEC};
 
@@ -199,6 +200,43 @@ fn invoke_cross_contract_indirect_err() -> Result<(), HostError> {
     Ok(())
 }
 
+#[test]
+fn cross_contract_error_records_cause_chain() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let id0_obj = host.register_test_contract_wasm(INVOKE_CONTRACT);
+    let id1_obj = host.register_test_contract_wasm(ADD_I32);
+    let sym = Symbol::try_from_small_str("add_with").unwrap();
+    let args = host.test_vec_obj::<i32>(&[i32::MAX, 1])?;
+    let args = host.vec_push_back(args, id1_obj.to_val())?;
+
+    // Without diagnostics no causes are recorded.
+    let err = host.call(id0_obj, sym, args).unwrap_err();
+    assert!(err.causes().is_empty());
+
+    host.enable_debug()?;
+    let err = host.call(id0_obj, sym, args).unwrap_err();
+    let trap: Error = (ScErrorType::WasmVm, ScErrorCode::InvalidAction).into();
+    let causes = err.causes();
+    // Raised in `add` and propagated out of it, then escalated by the `call`
+    // host function in `add_with` and propagated out of it.
+    assert_eq!(causes.len(), 4);
+    assert!(causes.iter().all(|c| c.error == trap));
+    assert_eq!(
+        causes[0].contract_id,
+        Some(host.contract_id_from_address(id1_obj)?)
+    );
+    assert_eq!(causes[0].fn_name.as_deref(), Some("add"));
+    for cause in &causes[2..] {
+        assert_eq!(
+            cause.contract_id,
+            Some(host.contract_id_from_address(id0_obj)?)
+        );
+        assert_eq!(cause.fn_name.as_deref(), Some("add_with"));
+    }
+    assert!(format!("{:?}", err).contains("Error chain (innermost first):"));
+    Ok(())
+}
+
 #[test]
 fn invoke_contract_with_reentry() -> Result<(), HostError> {
     let host = Host::test_host_with_recording_footprint();
@@ -281,3 +319,698 @@ fn wasm_invoke_return_err_variants() -> Result<(), HostError> {
     }
     Ok(())
 }
+
+struct TestCodeStore {
+    code: Rc<[u8]>,
+    served: std::cell::Cell<u32>,
+}
+
+impl CodeStore for TestCodeStore {
+    fn get_code(&self, _wasm_hash: &xdr::Hash) -> Result<Option<Rc<[u8]>>, HostError> {
+        self.served.set(self.served.get() + 1);
+        Ok(Some(self.code.clone()))
+    }
+}
+
+#[test]
+fn invoke_contract_with_code_from_code_store() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_id_obj = host.register_test_contract_wasm(ADD_I32);
+    let store = Rc::new(TestCodeStore {
+        code: Rc::from(ADD_I32),
+        served: Default::default(),
+    });
+    host.set_code_store(store.clone())?;
+    let res = host.call(
+        contract_id_obj,
+        Symbol::try_from_small_str("add")?,
+        host.test_vec_obj(&[1i32, 2i32])?,
+    )?;
+    assert_eq!(i32::try_from_val(&host, &res)?, 3);
+    assert_eq!(store.served.get(), 1);
+    Ok(())
+}
+
+#[test]
+fn code_store_returning_mismatched_code_is_rejected() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_id_obj = host.register_test_contract_wasm(ADD_I32);
+    host.set_code_store(Rc::new(TestCodeStore {
+        code: Rc::from(VEC),
+        served: Default::default(),
+    }))?;
+    let res = host.call(
+        contract_id_obj,
+        Symbol::try_from_small_str("add")?,
+        host.test_vec_obj(&[1i32, 2i32])?,
+    );
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Storage, ScErrorCode::InternalError)
+    ));
+    Ok(())
+}
+
+#[test]
+fn contract_can_designate_fee_refund() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(ADD_I32);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    let callee_address = host.register_test_contract_wasm(ADD_I32);
+    let callee_id = host.contract_id_from_address(callee_address)?;
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::FEE_REFUND_MIN_PROTOCOL)?;
+    let func = Symbol::try_from_small_str("refund")?;
+    assert_eq!(host.get_fee_refund()?, None);
+
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        assert!(HostError::result_matches_err(
+            host.set_fee_refund(-1),
+            (ScErrorType::Value, ScErrorCode::InvalidInput)
+        ));
+        host.set_fee_refund(100)?;
+        Ok(().into())
+    })?;
+    let expected = Some(FeeRefund {
+        contract_id: contract_id.clone(),
+        amount: 100,
+    });
+    assert_eq!(host.get_fee_refund()?, expected);
+
+    // The refund designated by a failed frame is rolled back.
+    let res = host.with_test_contract_frame(contract_id.clone(), func, || {
+        host.set_fee_refund(500)?;
+        Err(Error::from_type_and_code(ScErrorType::Context, ScErrorCode::InvalidAction).into())
+    });
+    assert!(res.is_err());
+    assert_eq!(host.get_fee_refund()?, expected);
+
+    // Contracts called by the top-level contract can't override its refund.
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        host.set_fee_refund(200)?;
+        host.with_test_contract_frame(callee_id, func, || {
+            assert!(HostError::result_matches_err(
+                host.set_fee_refund(300),
+                (ScErrorType::Context, ScErrorCode::InvalidAction)
+            ));
+            Ok(().into())
+        })
+    })?;
+    let expected = Some(FeeRefund {
+        contract_id,
+        amount: 200,
+    });
+    assert_eq!(host.get_fee_refund()?, expected);
+    Ok(())
+}
+
+#[test]
+fn fee_refund_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(ADD_I32);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::FEE_REFUND_MIN_PROTOCOL - 1)?;
+    let func = Symbol::try_from_small_str("refund")?;
+    host.with_test_contract_frame(contract_id, func, || {
+        assert!(HostError::result_matches_err(
+            host.set_fee_refund(100),
+            (ScErrorType::Context, ScErrorCode::InvalidAction)
+        ));
+        Ok(().into())
+    })?;
+    assert_eq!(host.get_fee_refund()?, None);
+    Ok(())
+}
+
+struct ReturnLedgerTimestamp;
+
+impl ContractFunctionSet for ReturnLedgerTimestamp {
+    fn call(&self, _func: &Symbol, host: &Host, _args: &[Val]) -> Option<Val> {
+        Some(host.get_ledger_timestamp().unwrap().into())
+    }
+}
+
+#[test]
+fn host_fn_interposer_applies_only_to_given_contract() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.timestamp = 1000)?;
+    let interposed = host.add_host_object(xdr::ScAddress::Contract(xdr::Hash([1; 32])))?;
+    let regular = host.add_host_object(xdr::ScAddress::Contract(xdr::Hash([2; 32])))?;
+    host.register_test_contract(interposed, Rc::new(ReturnLedgerTimestamp))?;
+    host.register_test_contract(regular, Rc::new(ReturnLedgerTimestamp))?;
+    host.register_test_contract_host_fn_interposer(
+        interposed,
+        "get_ledger_timestamp",
+        Rc::new(|host: &Host, _args: &[Val]| -> Result<Val, HostError> {
+            Ok(host.obj_from_u64(u64::MAX)?.into())
+        }),
+    )?;
+
+    let func = Symbol::try_from_small_str("timestamp")?;
+    let res = host.call(interposed, func, host.vec_new()?)?;
+    assert_eq!(u64::try_from_val(&host, &res)?, u64::MAX);
+    let res = host.call(regular, func, host.vec_new()?)?;
+    assert_eq!(u64::try_from_val(&host, &res)?, 1000);
+
+    // Only a fixed set of host functions can be interposed.
+    assert!(HostError::result_matches_err(
+        host.register_test_contract_host_fn_interposer(
+            interposed,
+            "vec_new",
+            Rc::new(|_host: &Host, _args: &[Val]| -> Result<Val, HostError> { Ok(().into()) }),
+        ),
+        (ScErrorType::Context, ScErrorCode::InvalidInput)
+    ));
+    Ok(())
+}
+
+// Calls the contract passed as the first argument with the remaining
+// arguments, or returns `1` when called without arguments.
+struct CallFirstArg;
+
+impl ContractFunctionSet for CallFirstArg {
+    fn call(&self, func: &Symbol, host: &Host, args: &[Val]) -> Option<Val> {
+        if args.is_empty() {
+            return Some(1_u32.into());
+        }
+        let contract = AddressObject::try_from(args[0]).unwrap();
+        let rest = host.vec_new_from_slice(&args[1..]).unwrap();
+        match host.call(contract, *func, rest) {
+            Ok(res) => Some(res),
+            Err(e) => Some(e.error.to_val()),
+        }
+    }
+}
+
+#[test]
+fn reentry_diagnostics_and_allow_list() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.enable_debug()?;
+    let a = host.add_host_object(xdr::ScAddress::Contract(xdr::Hash([1; 32])))?;
+    let b = host.add_host_object(xdr::ScAddress::Contract(xdr::Hash([2; 32])))?;
+    host.register_test_contract(a, Rc::new(CallFirstArg))?;
+    host.register_test_contract(b, Rc::new(CallFirstArg))?;
+
+    // a -> b -> a is rejected and the cycle is reported.
+    let func = Symbol::try_from_small_str("go")?;
+    let args = host.vec_new_from_slice(&[b.to_val(), a.to_val()])?;
+    assert!(HostError::result_matches_err(
+        host.call(a, func, args),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    let events = host.get_events()?.0;
+    let reentry_event = events
+        .iter()
+        .map(|e| format!("{}", e))
+        .find(|e| e.contains("topics:[reentry, go]"))
+        .unwrap();
+    assert_eq!(reentry_event.matches("Bytes(").count(), 3);
+
+    // Allowing b to call back into a makes the same call succeed, but only in
+    // that direction.
+    host.allow_contract_reentry(b, a)?;
+    let res = host.call(a, func, args)?;
+    assert_eq!(u32::try_from_val(&host, &res)?, 1);
+    let args = host.vec_new_from_slice(&[a.to_val(), b.to_val()])?;
+    assert!(HostError::result_matches_err(
+        host.call(b, func, args),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[test]
+fn execution_stats_track_high_water_marks() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let id0_obj = host.register_test_contract_wasm(INVOKE_CONTRACT);
+    let id1_obj = host.register_test_contract_wasm(ADD_I32);
+    host.reset_execution_stats()?;
+    assert_eq!(host.execution_stats()?, ExecutionStats::default());
+
+    let sym = Symbol::try_from_small_str("add_with").unwrap();
+    let args = host.test_vec_obj::<i32>(&[5, 6])?;
+    let args = host.vec_push_back(args, id1_obj.to_val())?;
+    host.call(id0_obj, sym, args)?;
+
+    let stats = host.execution_stats()?;
+    // Both contracts are on the stack during the nested call.
+    assert!(stats.max_frame_depth >= 2);
+    assert!(stats.host_objects_created > 0);
+    // The `call` host function made by the outer contract is metered.
+    assert!(stats.max_host_fn_cpu_insns > 0);
+    assert!(stats.max_host_fn_mem_bytes > 0);
+
+    host.reset_execution_stats()?;
+    assert_eq!(host.execution_stats()?, ExecutionStats::default());
+    // Objects are counted from the reset on.
+    host.obj_from_u64(u64::MAX)?;
+    assert_eq!(host.execution_stats()?.host_objects_created, 1);
+    Ok(())
+}
+
+#[cfg(feature = "next")]
+#[test]
+fn missing_function_fails_before_vm_instantiation() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::EARLY_MISSING_EXPORT_CHECK_MIN_PROTOCOL
+    })?;
+    let contract = host.register_test_contract_wasm(ADD_I32);
+    host.reset_execution_stats()?;
+    let args = host.test_vec_obj::<i32>(&[1, 2])?;
+
+    let res = host.call(contract, Symbol::try_from_small_str("missing")?, args);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::WasmVm, ScErrorCode::MissingValue)
+    ));
+    let stats = host.execution_stats()?;
+    assert!(stats.vm_parse_cpu_insns > 0);
+    assert_eq!(stats.vm_instantiation_cpu_insns, 0);
+    assert_eq!(stats.vm_instantiation_mem_bytes, 0);
+
+    host.call(contract, Symbol::try_from_small_str("add")?, args)?;
+    let new_stats = host.execution_stats()?;
+    assert!(new_stats.vm_parse_cpu_insns > stats.vm_parse_cpu_insns);
+    // Instantiation allocates the linear memory of the contract.
+    assert!(new_stats.vm_instantiation_mem_bytes > 0);
+    Ok(())
+}
+
+// Contracts built for the next protocol can't run at the older one.
+#[cfg(not(feature = "next"))]
+#[test]
+fn early_missing_function_failure_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::EARLY_MISSING_EXPORT_CHECK_MIN_PROTOCOL - 1
+    })?;
+    let contract = host.register_test_contract_wasm(ADD_I32);
+    host.reset_execution_stats()?;
+    let args = host.test_vec_obj::<i32>(&[1, 2])?;
+
+    // The contract is still instantiated before the call fails.
+    let res = host.call(contract, Symbol::try_from_small_str("missing")?, args);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::WasmVm, ScErrorCode::MissingValue)
+    ));
+    assert!(host.execution_stats()?.vm_instantiation_mem_bytes > 0);
+    Ok(())
+}
+
+#[test]
+fn module_cache_reuses_parsed_modules() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract = host.register_test_contract_wasm(ADD_I32);
+    host.reset_execution_stats()?;
+    let args = host.test_vec_obj::<i32>(&[1, 2])?;
+    let add = Symbol::try_from_small_str("add")?;
+
+    host.call(contract, add, args)?;
+    let stats = host.execution_stats()?;
+    assert_eq!(stats.module_cache_hits, 0);
+    host.call(contract, add, args)?;
+    let new_stats = host.execution_stats()?;
+    assert_eq!(new_stats.module_cache_hits, 1);
+    // Cached modules are charged as if they were parsed.
+    assert_eq!(new_stats.vm_parse_cpu_insns, 2 * stats.vm_parse_cpu_insns);
+
+    // Modules cached ahead of time are reused by the first call...
+    let host = Host::test_host_with_recording_footprint();
+    let contract = host.register_test_contract_wasm(ADD_I32);
+    host.cache_module(ADD_I32)?;
+    host.reset_execution_stats()?;
+    let args = host.test_vec_obj::<i32>(&[1, 2])?;
+    host.call(contract, add, args)?;
+    assert_eq!(host.execution_stats()?.module_cache_hits, 1);
+
+    // ...until the cache is cleared.
+    host.clear_module_cache()?;
+    host.call(contract, add, args)?;
+    assert_eq!(host.execution_stats()?.module_cache_hits, 1);
+    Ok(())
+}
+
+// Returns the time of the `MockClock` attached to the host and advances it.
+struct MockClockContract;
+
+struct MockClock(u64);
+
+impl ContractFunctionSet for MockClockContract {
+    fn call(&self, _func: &Symbol, host: &Host, _args: &[Val]) -> Option<Val> {
+        let time = host
+            .with_embedder_context(|clock: &mut MockClock| {
+                clock.0 += 10;
+                Ok(clock.0 - 10)
+            })
+            .ok()?;
+        Some(host.obj_from_u64(time).unwrap().into())
+    }
+}
+
+#[test]
+fn test_contracts_can_access_embedder_context() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let clock = host.add_host_object(xdr::ScAddress::Contract(xdr::Hash([1; 32])))?;
+    host.register_test_contract(clock, Rc::new(MockClockContract))?;
+    let func = Symbol::try_from_small_str("now")?;
+
+    // The contract fails when there is no context.
+    assert!(host.call(clock, func, host.vec_new()?).is_err());
+
+    host.set_embedder_context(Some(Box::new(MockClock(100))))?;
+    let res = host.call(clock, func, host.vec_new()?)?;
+    assert_eq!(u64::try_from_val(&host, &res)?, 100);
+    let res = host.call(clock, func, host.vec_new()?)?;
+    assert_eq!(u64::try_from_val(&host, &res)?, 110);
+    let time = host.with_embedder_context(|clock: &mut MockClock| Ok(clock.0))?;
+    assert_eq!(time, 120);
+
+    // Accessing the context as a different type fails.
+    assert!(HostError::result_matches_err(
+        host.with_embedder_context(|_: &mut u32| Ok(())),
+        (ScErrorType::Context, ScErrorCode::MissingValue)
+    ));
+    Ok(())
+}
+
+// Registers a contract that fails with `contract_error` and a contract
+// calling it. `test(a, b, c)` of the latter emits an event with `a` as data and
+// then returns `try_call(b, c, [])`. Its `__on_error` handler emits an event
+// with the error context as data and then makes the same `try_call`, whose
+// failure doesn't invoke the handler again.
+fn register_on_error_handler_contracts(
+    host: &Host,
+    contract_error: Error,
+) -> Result<(AddressObject, AddressObject), HostError> {
+    use soroban_synth_wasm::{Arity, ModEmitter};
+
+    let mut fe = ModEmitter::new().func(Arity(0), 0);
+    fe.fail_with_error(contract_error);
+    let callee = host.register_test_contract_wasm(&fe.finish_and_export("fail").finish());
+
+    let mut fe = ModEmitter::new().func(Arity(3), 1);
+    let (args, local) = (fe.args.clone(), fe.locals[0]);
+    fe.vec_new();
+    fe.local_set(local);
+    fe.contract_event(local, args[0]);
+    fe.drop();
+    fe.try_call(args[1], args[2], local);
+    let (me, f) = fe.finish();
+    let mut fe = me.func(Arity(4), 1);
+    let (args, local) = (fe.args.clone(), fe.locals[0]);
+    fe.vec_new();
+    fe.local_set(local);
+    fe.contract_event(local, args[3]);
+    fe.drop();
+    fe.try_call(args[1], args[2], local);
+    let (mut me, handler) = fe.finish();
+    me.export_func(f, "test");
+    me.export_func(handler, "__on_error");
+    let caller = host.register_test_contract_wasm(&me.finish());
+    Ok((caller, callee))
+}
+
+fn call_on_error_handler_contracts(
+    host: &Host,
+    contract_error: Error,
+) -> Result<Vec<xdr::ScVal>, HostError> {
+    use crate::xdr::{ContractEventBody, ContractEventType};
+
+    let (caller, callee) = register_on_error_handler_contracts(host, contract_error)?;
+    let res = host.call(
+        caller,
+        Symbol::try_from_small_str("test")?,
+        host.vec_new_from_slice(&[
+            Val::VOID.to_val(),
+            callee.to_val(),
+            Symbol::try_from_small_str("fail")?.to_val(),
+        ])?,
+    )?;
+    assert_eq!(Error::try_from(res)?, contract_error);
+
+    Ok(host
+        .get_events()?
+        .0
+        .into_iter()
+        .filter(|e| e.event.type_ == ContractEventType::Contract)
+        .map(|e| {
+            let ContractEventBody::V0(body) = e.event.body;
+            body.data
+        })
+        .collect())
+}
+
+#[cfg(feature = "next")]
+#[test]
+fn try_call_failure_invokes_on_error_handler() -> Result<(), HostError> {
+    use crate::{
+        host::ON_ERROR_HANDLER_MIN_PROTOCOL,
+        xdr::{ScMap, ScMapEntry, ScVal},
+    };
+
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.protocol_version = ON_ERROR_HANDLER_MIN_PROTOCOL)?;
+    let contract_error = Error::from_contract_error(7);
+    let event_data = call_on_error_handler_contracts(&host, contract_error)?;
+    assert_eq!(event_data.len(), 2);
+    assert_eq!(event_data[0], ScVal::Void);
+
+    // The handler receives the context of the error, which originates from
+    // the callee.
+    let ScVal::Map(Some(ScMap(context))) = &event_data[1] else {
+        panic!("unexpected handler event data");
+    };
+    let context: Vec<ScMapEntry> = context.to_vec();
+    assert_eq!(context.len(), 3);
+    assert!(matches!(
+        context[0].val,
+        ScVal::Address(xdr::ScAddress::Contract(_))
+    ));
+    assert_eq!(context[1].val, ScVal::Error(contract_error.try_into()?));
+    assert_eq!(context[2].val, ScVal::Symbol("fail".try_into()?));
+    Ok(())
+}
+
+// Contracts built for the next protocol can't run at the older one.
+#[cfg(not(feature = "next"))]
+#[test]
+fn on_error_handler_is_protocol_gated() -> Result<(), HostError> {
+    use crate::{host::ON_ERROR_HANDLER_MIN_PROTOCOL, xdr::ScVal};
+
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.protocol_version = ON_ERROR_HANDLER_MIN_PROTOCOL - 1)?;
+    let event_data = call_on_error_handler_contracts(&host, Error::from_contract_error(7))?;
+    assert_eq!(event_data, vec![ScVal::Void]);
+    Ok(())
+}
+
+#[test]
+fn differential_run_of_vm_configs() -> Result<(), HostError> {
+    use crate::{
+        run_differential,
+        test::util::{generate_account_id, generate_bytes_array},
+        xdr::{HostFunction, InvokeContractArgs, ScAddress, ScVal},
+        FuelConsumption, VmConfig,
+    };
+
+    let (account, salt) = (generate_account_id(), generate_bytes_array());
+    let make_host = || -> Result<Host, HostError> {
+        let host = Host::test_host_with_recording_footprint();
+        host.register_test_contract_wasm_from_source_account(ADD_I32, account.clone(), salt);
+        Ok(host)
+    };
+    let contract_id = {
+        let host = Host::test_host_with_recording_footprint();
+        let contract =
+            host.register_test_contract_wasm_from_source_account(ADD_I32, account.clone(), salt);
+        host.contract_id_from_address(contract)?
+    };
+    let invoke = |a: i32, b: i32| {
+        HostFunction::InvokeContract(InvokeContractArgs {
+            contract_address: ScAddress::Contract(contract_id.clone()),
+            function_name: "add".try_into().unwrap(),
+            args: vec![ScVal::I32(a), ScVal::I32(b)].try_into().unwrap(),
+        })
+    };
+    let lazy_fuel = VmConfig {
+        fuel_consumption: FuelConsumption::Lazy,
+    };
+
+    // The add contract doesn't have any bulk operations, so the fuel
+    // consumption mode doesn't make a difference.
+    let report = run_differential(make_host, invoke(4, 7), VmConfig::default(), lazy_fuel)?;
+    assert!(report.is_consistent(), "{:?}", report.mismatches());
+    assert_eq!(report.baseline.result, Ok(ScVal::I32(11)));
+
+    // Failures are compared too.
+    let report = run_differential(
+        make_host,
+        invoke(i32::MAX, 1),
+        VmConfig::default(),
+        lazy_fuel,
+    )?;
+    assert!(report.is_consistent(), "{:?}", report.mismatches());
+    assert!(report.candidate.result.is_err());
+    Ok(())
+}
+
+#[test]
+fn error_to_val_with_context_records_origin() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(ADD_I32);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::ERROR_CONTEXT_MIN_PROTOCOL)?;
+    let func = Symbol::try_from_small_str("withdraw")?;
+    let error = Error::from_contract_error(7);
+
+    let val = host.with_test_contract_frame(contract_id.clone(), func, || {
+        Ok(host.error_to_val_with_context(error)?.into())
+    })?;
+    let expected = crate::ErrorContext {
+        error,
+        contract: Some(contract_id),
+        function: Some("withdraw".try_into().unwrap()),
+    };
+    assert_eq!(host.error_context_from_val(val)?, expected);
+    // The conversion APIs produce the same shape as the host function.
+    let round_trip = host.error_context_to_val(&expected)?;
+    assert_eq!(host.error_context_from_val(round_trip)?, expected);
+
+    // Outside of a contract there is no origin.
+    let val = host.error_to_val_with_context(error)?;
+    let ctx = host.error_context_from_val(val.into())?;
+    assert_eq!(ctx.contract, None);
+    assert_eq!(ctx.function, None);
+
+    // Non-recoverable errors can't be propagated.
+    let budget_error = Error::from_type_and_code(ScErrorType::Budget, ScErrorCode::ExceededLimit);
+    assert!(HostError::result_matches_err(
+        host.error_to_val_with_context(budget_error),
+        (ScErrorType::Context, ScErrorCode::InvalidInput)
+    ));
+    // Arbitrary values are not error contexts.
+    assert!(HostError::result_matches_err(
+        host.error_context_from_val(host.map_new()?.into()),
+        (ScErrorType::Object, ScErrorCode::UnexpectedSize)
+    ));
+    Ok(())
+}
+
+#[test]
+fn error_to_val_with_context_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::ERROR_CONTEXT_MIN_PROTOCOL - 1
+    })?;
+    assert!(HostError::result_matches_err(
+        host.error_to_val_with_context(Error::from_contract_error(7)),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "next")]
+#[test]
+fn error_to_val_with_context_records_try_call_callee() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::ERROR_CONTEXT_MIN_PROTOCOL)?;
+    let callee_address = host.register_test_contract_wasm(ADD_I32);
+    let callee_id = host.contract_id_from_address(callee_address)?;
+    let caller_address = host.register_test_contract_wasm(ADD_I32);
+    let caller_id = host.contract_id_from_address(caller_address)?;
+    let caller_fn = Symbol::try_from_small_str("caller")?;
+    let add = Symbol::try_from_small_str("add")?;
+
+    host.with_test_contract_frame(caller_id.clone(), caller_fn, || {
+        // `add` traps on overflow.
+        let args = host.test_vec_obj(&[i32::MAX, 1])?;
+        let error = Error::try_from(host.try_call(callee_address, add, args)?)?;
+        let val = host.error_to_val_with_context(error)?;
+        let ctx = host.error_context_from_val(val.into())?;
+        assert_eq!(ctx.contract, Some(callee_id.clone()));
+        assert_eq!(ctx.function, Some("add".try_into().unwrap()));
+
+        // Other errors originate from the caller itself.
+        let val = host.error_to_val_with_context(Error::from_contract_error(1))?;
+        let ctx = host.error_context_from_val(val.into())?;
+        assert_eq!(ctx.contract, Some(caller_id.clone()));
+        assert_eq!(ctx.function, Some("caller".try_into().unwrap()));
+        Ok(Val::VOID.to_val())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn metrics_sink_receives_host_metrics() -> Result<(), HostError> {
+    use crate::{
+        xdr::{HostFunction, InvokeContractArgs, ScAddress, ScVal},
+        MetricsSink, StorageOp,
+    };
+    use std::{cell::RefCell, time::Duration};
+
+    #[derive(Default)]
+    struct RecordingSink {
+        invocations: RefCell<Vec<bool>>,
+        host_fns: RefCell<Vec<&'static str>>,
+        vm_parses: RefCell<u32>,
+        vm_instantiations: RefCell<u32>,
+        storage_ops: RefCell<Vec<StorageOp>>,
+        cpu_insns: RefCell<u64>,
+    }
+    impl MetricsSink for RecordingSink {
+        fn invocation(&self, _elapsed: Duration, succeeded: bool) {
+            self.invocations.borrow_mut().push(succeeded);
+        }
+        fn budget_consumption(&self, cpu_insns: u64, _mem_bytes: u64) {
+            *self.cpu_insns.borrow_mut() = cpu_insns;
+        }
+        fn host_fn_call(&self, name: &'static str, _elapsed: Duration, _cpu_insns: u64) {
+            self.host_fns.borrow_mut().push(name);
+        }
+        fn vm_parse(&self, _elapsed: Duration, _cpu_insns: u64) {
+            *self.vm_parses.borrow_mut() += 1;
+        }
+        fn vm_instantiation(&self, _elapsed: Duration, _cpu_insns: u64) {
+            *self.vm_instantiations.borrow_mut() += 1;
+        }
+        fn storage_op(&self, op: StorageOp) {
+            self.storage_ops.borrow_mut().push(op);
+        }
+    }
+
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(VEC);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    let sink = Rc::new(RecordingSink::default());
+    host.set_metrics_sink(sink.clone())?;
+
+    let res = host.invoke_function(HostFunction::InvokeContract(InvokeContractArgs {
+        contract_address: ScAddress::Contract(contract_id.clone()),
+        function_name: "vec_err".try_into().unwrap(),
+        args: vec![ScVal::U32(1)].try_into().unwrap(),
+    }));
+    assert!(res.is_err());
+    assert_eq!(*sink.invocations.borrow(), vec![false]);
+    assert_eq!(
+        *sink.cpu_insns.borrow(),
+        host.budget_cloned().get_cpu_insns_consumed()?
+    );
+    assert!(!sink.host_fns.borrow().is_empty());
+    assert!(*sink.vm_parses.borrow() > 0);
+    assert!(*sink.vm_instantiations.borrow() > 0);
+
+    let func = Symbol::try_from_small_str("metrics")?;
+    host.with_test_contract_frame(contract_id, func, || {
+        let k = Val::from(1_u32);
+        host.put_contract_data(k, k, crate::StorageType::Temporary)?;
+        host.get_contract_data(k, crate::StorageType::Temporary)?;
+        host.del_contract_data(k, crate::StorageType::Temporary)?;
+        Ok(().into())
+    })?;
+    assert_eq!(
+        *sink.storage_ops.borrow(),
+        vec![StorageOp::Put, StorageOp::Get, StorageOp::Del]
+    );
+    Ok(())
+}
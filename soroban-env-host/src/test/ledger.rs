@@ -1,9 +1,20 @@
-use soroban_env_common::{xdr::ScBytes, Env};
+use std::{cell::Cell, rc::Rc};
+
+use soroban_env_common::{
+    xdr::{
+        AccountId, AlphaNum4, Asset, AssetCode4, Hash, LedgerEntry, LedgerEntryData,
+        LedgerEntryExt, LedgerKey, LedgerKeyLiquidityPool, LedgerKeyOffer,
+        LiquidityPoolConstantProductParameters, LiquidityPoolEntry, LiquidityPoolEntryBody,
+        LiquidityPoolEntryConstantProduct, OfferEntry, OfferEntryExt, PoolId, Price, PublicKey,
+        ScAddress, ScBytes, ScErrorCode, ScErrorType, ScVal, Uint256, WriteXdr,
+    },
+    BytesObject, Env, EnvBase, MapObject, Symbol, TryFromVal, Val,
+};
 
 use crate::{
-    budget::Budget,
-    storage::{Footprint, Storage, StorageMap},
-    Host, HostError, LedgerInfo,
+    budget::{AsBudget, Budget},
+    storage::{Footprint, FootprintMode, Storage, StorageMap},
+    ContractFunctionSet, Host, HostError, LedgerInfo, LedgerInfoProvider,
 };
 
 #[test]
@@ -28,3 +39,286 @@ fn ledger_network_id() -> Result<(), HostError> {
     assert_eq!(np, vec![7; 32],);
     Ok(())
 }
+
+// Serves the ledger sequence from a cell, as if read from the embedder's
+// ledger state.
+struct SequenceProvider {
+    info: LedgerInfo,
+    sequence_number: Cell<u32>,
+}
+
+impl LedgerInfoProvider for SequenceProvider {
+    fn protocol_version(&self) -> u32 {
+        self.info.protocol_version
+    }
+    fn sequence_number(&self) -> u32 {
+        self.sequence_number.get()
+    }
+    fn timestamp(&self) -> u64 {
+        self.info.timestamp
+    }
+    fn network_id(&self) -> [u8; 32] {
+        self.info.network_id
+    }
+    fn base_reserve(&self) -> u32 {
+        self.info.base_reserve
+    }
+    fn min_temp_entry_expiration(&self) -> u32 {
+        self.info.min_temp_entry_expiration
+    }
+    fn min_persistent_entry_expiration(&self) -> u32 {
+        self.info.min_persistent_entry_expiration
+    }
+    fn max_entry_expiration(&self) -> u32 {
+        self.info.max_entry_expiration
+    }
+}
+
+struct NoopContract;
+
+impl ContractFunctionSet for NoopContract {
+    fn call(&self, _func: &Symbol, _host: &Host, _args: &[Val]) -> Option<Val> {
+        None
+    }
+}
+
+#[test]
+fn ledger_info_provider() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let info = host.with_ledger_info(|li| Ok(li.clone()))?;
+    let provider = Rc::new(SequenceProvider {
+        info: info.clone(),
+        sequence_number: Cell::new(5),
+    });
+    host.set_ledger_info_provider(provider.clone())?;
+
+    assert_eq!(u32::from(host.get_ledger_sequence()?), 5);
+    provider.sequence_number.set(6);
+    assert_eq!(u32::from(host.get_ledger_sequence()?), 6);
+    assert_eq!(
+        host.max_expiration_ledger()?,
+        6 + info.max_entry_expiration - 1
+    );
+    assert_eq!(host.with_ledger_info(|li| Ok(li.sequence_number))?, 6);
+
+    // The provider is read once per invocation.
+    let contract = host.add_host_object(ScAddress::Contract(Hash([0; 32])))?;
+    host.register_test_contract(contract, Rc::new(NoopContract))?;
+    host.with_test_contract_frame(Hash([0; 32]), Symbol::try_from_small_str("f")?, || {
+        provider.sequence_number.set(7);
+        assert_eq!(u32::from(host.get_ledger_sequence()?), 6);
+        assert_eq!(host.with_ledger_info(|li| Ok(li.sequence_number))?, 6);
+        Ok(Val::VOID.into())
+    })?;
+    assert_eq!(u32::from(host.get_ledger_sequence()?), 7);
+    provider.sequence_number.set(6);
+
+    // Only a fixed `LedgerInfo` can be mutated.
+    let res = host.with_mut_ledger_info(|li| li.sequence_number += 1);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    host.set_ledger_info(info)?;
+    host.with_mut_ledger_info(|li| li.sequence_number = 7)?;
+    assert_eq!(u32::from(host.get_ledger_sequence()?), 7);
+    Ok(())
+}
+
+#[test]
+fn classic_offer_and_liquidity_pool() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::CLASSIC_OFFER_AND_POOL_MIN_PROTOCOL
+    })?;
+    let seller = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([1; 32])));
+    let usdc = Asset::CreditAlphanum4(AlphaNum4 {
+        asset_code: AssetCode4(*b"USDC"),
+        issuer: AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([2; 32]))),
+    });
+    let put_entry = |key: LedgerKey, data: LedgerEntryData| {
+        host.with_mut_storage(|s| {
+            s.put(
+                &Rc::new(key),
+                &Rc::new(LedgerEntry {
+                    last_modified_ledger_seq: 0,
+                    data,
+                    ext: LedgerEntryExt::V0,
+                }),
+                None,
+                host.as_budget(),
+            )
+        })
+    };
+    put_entry(
+        LedgerKey::Offer(LedgerKeyOffer {
+            seller_id: seller.clone(),
+            offer_id: 7,
+        }),
+        LedgerEntryData::Offer(OfferEntry {
+            seller_id: seller.clone(),
+            offer_id: 7,
+            selling: Asset::Native,
+            buying: usdc.clone(),
+            amount: 1_000,
+            price: Price { n: 3, d: 2 },
+            flags: 0,
+            ext: OfferEntryExt::V0,
+        }),
+    )?;
+    put_entry(
+        LedgerKey::LiquidityPool(LedgerKeyLiquidityPool {
+            liquidity_pool_id: PoolId(Hash([3; 32])),
+        }),
+        LedgerEntryData::LiquidityPool(LiquidityPoolEntry {
+            liquidity_pool_id: PoolId(Hash([3; 32])),
+            body: LiquidityPoolEntryBody::LiquidityPoolConstantProduct(
+                LiquidityPoolEntryConstantProduct {
+                    params: LiquidityPoolConstantProductParameters {
+                        asset_a: Asset::Native,
+                        asset_b: usdc.clone(),
+                        fee: 30,
+                    },
+                    reserve_a: 500,
+                    reserve_b: 700,
+                    total_pool_shares: 600,
+                    pool_shares_trust_line_count: 2,
+                },
+            ),
+        }),
+    )?;
+
+    let asset_address = |asset: Asset| -> Result<ScVal, HostError> {
+        Ok(ScVal::Address(ScAddress::Contract(
+            host.get_asset_contract_id_hash(asset)?,
+        )))
+    };
+    let field = |map: Val, key: &str| -> Result<ScVal, HostError> {
+        let map: MapObject = map.try_into()?;
+        let val = host.map_get(map, Symbol::try_from_val(&host, &key)?.to_val())?;
+        host.from_host_val(val)
+    };
+
+    let seller_obj = host.add_host_object(ScAddress::Account(seller.clone()))?;
+    let offer = host.get_classic_offer(seller_obj, 7)?;
+    assert_eq!(field(offer, "amount")?, ScVal::I64(1_000));
+    assert_eq!(field(offer, "selling")?, asset_address(Asset::Native)?);
+    assert_eq!(field(offer, "buying")?, asset_address(usdc.clone())?);
+    assert_eq!(field(offer, "price_n")?, ScVal::I32(3));
+    assert_eq!(field(offer, "price_d")?, ScVal::I32(2));
+    assert_eq!(
+        field(offer, "seller")?,
+        ScVal::Address(ScAddress::Account(seller))
+    );
+    // Missing offers are reported as void.
+    assert!(host.get_classic_offer(seller_obj, 8)?.is_void());
+    // Contracts can't have offers.
+    let contract_obj = host.add_host_object(ScAddress::Contract(Hash([0; 32])))?;
+    assert!(HostError::result_matches_err(
+        host.get_classic_offer(contract_obj, 7),
+        (ScErrorType::Object, ScErrorCode::InvalidInput)
+    ));
+
+    let pool = host.get_classic_liquidity_pool(host.bytes_new_from_slice(&[3; 32])?)?;
+    assert_eq!(field(pool, "asset_a")?, asset_address(Asset::Native)?);
+    assert_eq!(field(pool, "asset_b")?, asset_address(usdc)?);
+    assert_eq!(field(pool, "fee_bps")?, ScVal::I32(30));
+    assert_eq!(field(pool, "reserve_a")?, ScVal::I64(500));
+    assert_eq!(field(pool, "reserve_b")?, ScVal::I64(700));
+    assert_eq!(field(pool, "total_pool_shares")?, ScVal::I64(600));
+    assert!(host
+        .get_classic_liquidity_pool(host.bytes_new_from_slice(&[4; 32])?)?
+        .is_void());
+    Ok(())
+}
+
+#[test]
+fn classic_offer_and_liquidity_pool_are_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::CLASSIC_OFFER_AND_POOL_MIN_PROTOCOL - 1
+    })?;
+    let seller = AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([1; 32])));
+    let seller_obj = host.add_host_object(ScAddress::Account(seller))?;
+    let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+    assert!(HostError::result_matches_err(
+        host.get_classic_offer(seller_obj, 7),
+        code
+    ));
+    assert!(HostError::result_matches_err(
+        host.get_classic_liquidity_pool(host.bytes_new_from_slice(&[3; 32])?),
+        code
+    ));
+    Ok(())
+}
+
+#[test]
+fn ledger_entry_exists() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::LEDGER_ENTRY_EXISTS_MIN_PROTOCOL
+    })?;
+    let offer_key = |offer_id: i64| {
+        LedgerKey::Offer(LedgerKeyOffer {
+            seller_id: AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([1; 32]))),
+            offer_id,
+        })
+    };
+    let key_xdr = |offer_id: i64| -> Result<BytesObject, HostError> {
+        host.bytes_new_from_slice(&offer_key(offer_id).to_xdr().unwrap())
+    };
+    host.with_mut_storage(|s| {
+        s.put(
+            &Rc::new(offer_key(1)),
+            &Rc::new(LedgerEntry {
+                last_modified_ledger_seq: 0,
+                data: LedgerEntryData::Offer(OfferEntry {
+                    seller_id: AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([1; 32]))),
+                    offer_id: 1,
+                    selling: Asset::Native,
+                    buying: Asset::Native,
+                    amount: 1,
+                    price: Price { n: 1, d: 1 },
+                    flags: 0,
+                    ext: OfferEntryExt::V0,
+                }),
+                ext: LedgerEntryExt::V0,
+            }),
+            None,
+            host.as_budget(),
+        )
+    })?;
+    assert!(bool::from(host.ledger_entry_exists(key_xdr(1)?)?));
+    assert!(!bool::from(host.ledger_entry_exists(key_xdr(2)?)?));
+
+    // Malformed keys are rejected.
+    let res = host.ledger_entry_exists(host.bytes_new_from_slice(&[1, 2, 3])?);
+    assert!(res.is_err());
+
+    // Keys outside of the footprint can't be probed.
+    host.try_borrow_storage_mut()?.mode = FootprintMode::Enforcing;
+    assert!(bool::from(host.ledger_entry_exists(key_xdr(1)?)?));
+    assert!(HostError::result_matches_err(
+        host.ledger_entry_exists(key_xdr(3)?),
+        (ScErrorType::Storage, ScErrorCode::ExceededLimit)
+    ));
+    Ok(())
+}
+
+#[test]
+fn ledger_entry_exists_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::LEDGER_ENTRY_EXISTS_MIN_PROTOCOL - 1
+    })?;
+    let key = LedgerKey::Offer(LedgerKeyOffer {
+        seller_id: AccountId(PublicKey::PublicKeyTypeEd25519(Uint256([1; 32]))),
+        offer_id: 1,
+    });
+    let key = host.bytes_new_from_slice(&key.to_xdr().unwrap())?;
+    assert!(HostError::result_matches_err(
+        host.ledger_entry_exists(key),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
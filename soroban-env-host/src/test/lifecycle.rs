@@ -1,5 +1,6 @@
 use crate::auth::RecordedAuthPayload;
 use crate::native_contract::testutils::HostVec;
+use crate::native_contract::token::test_token::TestToken;
 use crate::{
     budget::{AsBudget, Budget},
     host_vec,
@@ -7,15 +8,16 @@ use crate::{
     xdr::{
         self, ContractEvent, ContractEventBody, ContractEventType, ContractEventV0,
         ContractExecutable, CreateContractArgs, ExtensionPoint, Hash, HashIdPreimage,
-        HashIdPreimageContractId, LedgerEntryData, ScSymbol, ScVal, ScVec, Uint256,
+        HashIdPreimageContractId, LedgerEntryData, ScErrorCode, ScErrorType, ScSymbol, ScVal,
+        ScVec, Uint256,
     },
-    Env, Host, LedgerInfo, Symbol,
+    Env, Host, HostError, HostFunctionOp, LedgerInfo, Symbol,
 };
 use sha2::{Digest, Sha256};
 use soroban_env_common::xdr::{
-    ContractIdPreimage, ContractIdPreimageFromAddress, DepthLimitedWrite, HostFunction, ScAddress,
-    SorobanAuthorizationEntry, SorobanAuthorizedFunction, SorobanAuthorizedInvocation,
-    SorobanCredentials, VecM, DEFAULT_XDR_RW_DEPTH_LIMIT,
+    Asset, ContractIdPreimage, ContractIdPreimageFromAddress, DepthLimitedWrite, HostFunction,
+    InvokeContractArgs, ScAddress, SorobanAuthorizationEntry, SorobanAuthorizedFunction,
+    SorobanAuthorizedInvocation, SorobanCredentials, VecM, DEFAULT_XDR_RW_DEPTH_LIMIT,
 };
 use soroban_env_common::VecObject;
 use soroban_env_common::{xdr::ScBytes, TryIntoVal, Val};
@@ -350,6 +352,131 @@ fn test_contract_wasm_update() {
     assert_eq!(updated_res, 30);
 }
 
+#[cfg(feature = "next")]
+fn last_event_topic(host: &Host) -> ScVal {
+    let events = host.get_events().unwrap().0;
+    let ContractEventBody::V0(body) = &events.last().unwrap().event.body;
+    body.topics[0].clone()
+}
+
+#[cfg(feature = "next")]
+#[test]
+fn test_contract_wasm_update_with_timelock() {
+    use crate::{StorageType, TryFromVal};
+
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::UPGRADE_TIMELOCK_MIN_PROTOCOL
+    })
+    .unwrap();
+    let contract_addr_obj = host.register_test_contract_wasm(UPDATEABLE_CONTRACT);
+    let contract_id = host.contract_id_from_address(contract_addr_obj).unwrap();
+    let old_wasm_hash = get_contract_wasm_ref(&host, contract_id.clone());
+    let updated_wasm_hash_obj: Val = host
+        .invoke_function(HostFunction::UploadContractWasm(
+            ADD_I32.to_vec().try_into().unwrap(),
+        ))
+        .unwrap()
+        .try_into_val(&host)
+        .unwrap();
+
+    host.with_test_contract_frame(
+        contract_id.clone(),
+        Symbol::try_from_small_str("setup").unwrap(),
+        || {
+            host.set_upgrade_timelock(10_u32.into())?;
+            // The delay can't be decreased once set.
+            assert!(HostError::result_matches_err(
+                host.set_upgrade_timelock(5_u32.into()),
+                (ScErrorType::Context, ScErrorCode::InvalidAction)
+            ));
+            // The timelock is kept outside of the instance storage.
+            let key = Symbol::try_from_val(&host, &"UpgradeTimelock")?;
+            host.put_contract_data(key.into(), Val::VOID, StorageType::Instance)?;
+            host.del_contract_data(key.into(), StorageType::Instance)?;
+            Ok(Val::VOID)
+        },
+    )
+    .unwrap();
+    assert_eq!(
+        last_event_topic(&host),
+        ScVal::Symbol(ScSymbol("upgrade_timelock_set".try_into().unwrap()))
+    );
+
+    let update = || -> Result<Val, HostError> {
+        host.call(
+            contract_addr_obj,
+            Symbol::try_from_small_str("update").unwrap(),
+            host_vec![&host, &updated_wasm_hash_obj].into(),
+        )
+    };
+    // The first update only announces the new Wasm.
+    let res: i32 = update().unwrap().try_into_val(&host).unwrap();
+    assert_eq!(res, 123);
+    assert_eq!(
+        last_event_topic(&host),
+        ScVal::Symbol(ScSymbol("upgrade_announced".try_into().unwrap()))
+    );
+    assert_eq!(
+        get_contract_wasm_ref(&host, contract_id.clone()),
+        old_wasm_hash
+    );
+
+    // The update can't be activated before the delay elapses.
+    host.with_mut_ledger_info(|li| li.sequence_number += 9)
+        .unwrap();
+    assert!(HostError::result_matches_err(
+        update(),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    assert_eq!(
+        get_contract_wasm_ref(&host, contract_id.clone()),
+        old_wasm_hash
+    );
+
+    host.with_mut_ledger_info(|li| li.sequence_number += 1)
+        .unwrap();
+    let res: i32 = update().unwrap().try_into_val(&host).unwrap();
+    assert_eq!(res, 123);
+    assert_eq!(
+        last_event_topic(&host),
+        ScVal::Symbol(ScSymbol("executable_update".try_into().unwrap()))
+    );
+    let updated_res: i32 = host
+        .call(
+            contract_addr_obj,
+            Symbol::try_from_small_str("add").unwrap(),
+            host_vec![&host, 10_i32, 20_i32].into(),
+        )
+        .unwrap()
+        .try_into_val(&host)
+        .unwrap();
+    assert_eq!(updated_res, 30);
+}
+
+#[test]
+fn upgrade_timelock_is_protocol_gated() {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_addr_obj = host.register_test_contract_wasm(UPDATEABLE_CONTRACT);
+    let contract_id = host.contract_id_from_address(contract_addr_obj).unwrap();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::UPGRADE_TIMELOCK_MIN_PROTOCOL - 1
+    })
+    .unwrap();
+    let res = host.with_test_contract_frame(
+        contract_id,
+        Symbol::try_from_small_str("setup").unwrap(),
+        || {
+            host.set_upgrade_timelock(10_u32.into())?;
+            Ok(Val::VOID)
+        },
+    );
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+}
+
 #[test]
 
 fn test_create_contract_from_source_account_recording_auth() {
@@ -393,3 +520,266 @@ fn test_create_contract_from_source_account_recording_auth() {
         }]
     );
 }
+
+#[cfg(feature = "next")]
+#[test]
+fn test_clone_contract_from_contract_frame() {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::CLONE_CONTRACT_MIN_PROTOCOL)
+        .unwrap();
+    let source = host.register_test_contract_wasm(ADD_I32);
+    let source_wasm_hash =
+        get_contract_wasm_ref(&host, host.contract_id_from_address(source).unwrap());
+    let factory = host.register_test_contract_wasm(ADD_I32);
+    let factory_id = host.contract_id_from_address(factory).unwrap();
+    let salt = host.bytes_new_from_slice(&generate_bytes_array()).unwrap();
+
+    let clone: crate::AddressObject = host
+        .with_test_contract_frame(
+            factory_id.clone(),
+            Symbol::try_from_small_str("clone").unwrap(),
+            || Ok(host.clone_contract(source, salt)?.into()),
+        )
+        .unwrap()
+        .try_into()
+        .unwrap();
+    let clone_id = host.contract_id_from_address(clone).unwrap();
+    assert_eq!(clone_id, host.get_contract_id_hash(factory, salt).unwrap());
+    assert_eq!(get_contract_wasm_ref(&host, clone_id), source_wasm_hash);
+    let res: i32 = host
+        .call(
+            clone,
+            Symbol::try_from_small_str("add").unwrap(),
+            host_vec![&host, 10_i32, 20_i32].into(),
+        )
+        .unwrap()
+        .try_into_val(&host)
+        .unwrap();
+    assert_eq!(res, 30);
+
+    // Only contracts with Wasm executable can be cloned.
+    let token = TestToken::new_from_asset(&host, Asset::Native);
+    let res = host.with_test_contract_frame(
+        factory_id,
+        Symbol::try_from_small_str("clone").unwrap(),
+        || {
+            Ok(host
+                .clone_contract(
+                    token.address.clone().into(),
+                    host.bytes_new_from_slice(&generate_bytes_array())?,
+                )?
+                .into())
+        },
+    );
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+}
+
+#[test]
+fn test_clone_contract_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let source = host.register_test_contract_wasm(ADD_I32);
+    let factory = host.register_test_contract_wasm(ADD_I32);
+    let factory_id = host.contract_id_from_address(factory)?;
+    let salt = host.bytes_new_from_slice(&generate_bytes_array())?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::CLONE_CONTRACT_MIN_PROTOCOL - 1
+    })?;
+    let res =
+        host.with_test_contract_frame(factory_id, Symbol::try_from_small_str("clone")?, || {
+            Ok(host.clone_contract(source, salt)?.into())
+        });
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[cfg(feature = "next")]
+#[test]
+fn test_get_own_custom_section() -> Result<(), HostError> {
+    use soroban_env_common::meta;
+    use soroban_synth_wasm::{Arity, ModEmitter};
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::OWN_CUSTOM_SECTION_MIN_PROTOCOL
+    })?;
+    let mut fe = ModEmitter::new().func(Arity(1), 0);
+    fe.get_own_custom_section(fe.args[0]);
+    let contract = host.register_test_contract_wasm(&fe.finish_and_export("test").finish());
+    let test_fn = Symbol::try_from_small_str("test")?;
+
+    let section = host.call(
+        contract,
+        test_fn,
+        host_vec![
+            &host,
+            host.string_new_from_slice(meta::ENV_META_V0_SECTION_NAME)?
+        ]
+        .into(),
+    )?;
+    let expected = host.bytes_new_from_slice(&meta::XDR)?;
+    assert_eq!(host.obj_cmp(section, expected.to_val())?, 0);
+
+    assert!(HostError::result_matches_err(
+        host.call(
+            contract,
+            test_fn,
+            host_vec![&host, host.string_new_from_slice("missing")?].into(),
+        ),
+        (ScErrorType::WasmVm, ScErrorCode::MissingValue)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_get_own_custom_section_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::OWN_CUSTOM_SECTION_MIN_PROTOCOL - 1
+    })?;
+    let name = host.string_new_from_slice(soroban_env_common::meta::ENV_META_V0_SECTION_NAME)?;
+    assert!(HostError::result_matches_err(
+        host.get_own_custom_section(name),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_get_wasm_hash_of_contract() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract = host.register_test_contract_wasm(ADD_I32);
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::WASM_HASH_OF_CONTRACT_MIN_PROTOCOL
+    })?;
+    let wasm_hash = host.get_wasm_hash_of_contract(contract)?;
+    assert_eq!(
+        host.hash_from_bytesobj_input("wasm_hash", wasm_hash)?,
+        get_contract_wasm_ref(&host, host.contract_id_from_address(contract)?)
+    );
+
+    let token = TestToken::new_from_asset(&host, Asset::Native);
+    assert!(HostError::result_matches_err(
+        host.get_wasm_hash_of_contract(token.address.clone().into()),
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_get_wasm_hash_of_contract_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract = host.register_test_contract_wasm(ADD_I32);
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::WASM_HASH_OF_CONTRACT_MIN_PROTOCOL - 1
+    })?;
+    assert!(HostError::result_matches_err(
+        host.get_wasm_hash_of_contract(contract),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_invoke_functions_batch() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract = host.register_test_contract_wasm(ADD_I32);
+    let contract_address = ScAddress::Contract(host.contract_id_from_address(contract)?);
+    let op = |host_function: HostFunction| HostFunctionOp {
+        host_function,
+        auth_entries: None,
+    };
+    let invoke = |func: &str, args: Vec<ScVal>| {
+        op(HostFunction::InvokeContract(InvokeContractArgs {
+            contract_address: contract_address.clone(),
+            function_name: ScSymbol(func.try_into().unwrap()),
+            args: args.try_into().unwrap(),
+        }))
+    };
+    let contract_events = |range: std::ops::Range<usize>| -> Result<Vec<_>, HostError> {
+        Ok(host.get_events()?.0[range]
+            .iter()
+            .filter(|e| e.event.type_ == ContractEventType::Contract)
+            .cloned()
+            .collect())
+    };
+
+    let results = host.invoke_functions(vec![
+        op(HostFunction::UploadContractWasm(
+            CREATE_CONTRACT.try_into().unwrap(),
+        )),
+        invoke("add", vec![ScVal::I32(1), ScVal::I32(2)]),
+    ])?;
+    assert_eq!(results.len(), 2);
+    assert!(results[0].result.is_ok());
+    assert_eq!(results[1].result.as_ref().unwrap(), &ScVal::I32(3));
+    assert!(contract_events(results[0].events.clone())?.is_empty());
+    assert_eq!(contract_events(results[1].events.clone())?.len(), 1);
+
+    // A failing operation ends the batch and rolls back all of it.
+    let wasm_hash = Hash(Sha256::digest(UPDATEABLE_CONTRACT).try_into().unwrap());
+    let results = host.invoke_functions(vec![
+        invoke("add", vec![ScVal::I32(3), ScVal::I32(4)]),
+        op(HostFunction::UploadContractWasm(
+            UPDATEABLE_CONTRACT.try_into().unwrap(),
+        )),
+        invoke("missing_fn", vec![]),
+        invoke("add", vec![ScVal::I32(5), ScVal::I32(6)]),
+    ])?;
+    assert_eq!(results.len(), 3);
+    assert!(results[1].result.is_ok());
+    assert!(results[2].result.is_err());
+    let events = contract_events(results[0].events.clone())?;
+    assert_eq!(events.len(), 1);
+    assert!(events[0].failed_call);
+    let code_key = host.contract_code_ledger_key(&wasm_hash)?;
+    assert!(!host.with_mut_storage(|s| s.has(&code_key, host.as_budget()))?);
+    Ok(())
+}
+
+#[test]
+fn test_invoke_functions_batch_resets_auth_entries() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let source_account = generate_account_id();
+    host.set_source_account(source_account.clone())?;
+    host.switch_to_recording_auth(true)?;
+    let create_contract_args = CreateContractArgs {
+        contract_id_preimage: ContractIdPreimage::Address(ContractIdPreimageFromAddress {
+            address: ScAddress::Account(source_account),
+            salt: Uint256(generate_bytes_array()),
+        }),
+        executable: ContractExecutable::Wasm(Hash(Sha256::digest(ADD_I32).try_into().unwrap())),
+    };
+
+    // The empty entries of the first operation don't apply to the second one,
+    // which runs with the recording authorization of the host.
+    let results = host.invoke_functions(vec![
+        HostFunctionOp {
+            host_function: HostFunction::UploadContractWasm(ADD_I32.try_into().unwrap()),
+            auth_entries: Some(vec![]),
+        },
+        HostFunctionOp {
+            host_function: HostFunction::CreateContract(create_contract_args.clone()),
+            auth_entries: None,
+        },
+    ])?;
+    assert_eq!(results.len(), 2);
+    assert!(results[0].result.is_ok());
+    assert!(results[1].result.is_ok());
+    assert_eq!(
+        host.get_recorded_auth_payloads()?,
+        vec![RecordedAuthPayload {
+            address: None,
+            nonce: None,
+            invocation: SorobanAuthorizedInvocation {
+                function: SorobanAuthorizedFunction::CreateContractHostFn(create_contract_args),
+                sub_invocations: VecM::default()
+            }
+        }]
+    );
+    Ok(())
+}
@@ -264,6 +264,72 @@ fn map_values() -> Result<(), HostError> {
     Ok(())
 }
 
+#[test]
+fn map_range_and_nth_key_range() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::MAP_RANGE_FNS_MIN_PROTOCOL)?;
+
+    let mut map = host.map_new()?;
+    for i in [5u32, 1, 4, 2, 3] {
+        map = host.map_put(map, i.into(), (i * 10).into())?;
+    }
+
+    // Bounds are start-inclusive and end-exclusive, and need not be keys.
+    let sub = host.map_range(map, 2u32.into(), 4u32.into())?;
+    let mut expected = host.map_new()?;
+    expected = host.map_put(expected, 2u32.into(), 20u32.into())?;
+    expected = host.map_put(expected, 3u32.into(), 30u32.into())?;
+    assert_eq!(host.obj_cmp(sub.into(), expected.into())?, 0);
+    let sub = host.map_range(map, 0u32.into(), 10u32.into())?;
+    assert_eq!(host.obj_cmp(sub.into(), map.into())?, 0);
+    let sub = host.map_range(map, 3u32.into(), 3u32.into())?;
+    assert_eq!(u32::from(host.map_len(sub)?), 0);
+    let res = host.map_range(map, 4u32.into(), 2u32.into());
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Object, ScErrorCode::InvalidInput)
+    ));
+    // Reversed bounds are rejected even when no entry lies between them.
+    let res = host.map_range(map, 7u32.into(), 6u32.into());
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Object, ScErrorCode::InvalidInput)
+    ));
+
+    let keys = host.map_nth_key_range(map, 1u32.into(), 2u32.into())?;
+    let expected_keys = host.test_vec_obj::<u32>(&[2, 3])?;
+    assert_eq!(host.obj_cmp(keys.into(), expected_keys.into())?, 0);
+    // The range is cut at the end of the map.
+    let keys = host.map_nth_key_range(map, 3u32.into(), 10u32.into())?;
+    let expected_keys = host.test_vec_obj::<u32>(&[4, 5])?;
+    assert_eq!(host.obj_cmp(keys.into(), expected_keys.into())?, 0);
+    let keys = host.map_nth_key_range(map, 5u32.into(), 1u32.into())?;
+    assert_eq!(u32::from(host.vec_len(keys)?), 0);
+    let res = host.map_nth_key_range(map, 6u32.into(), 1u32.into());
+    assert!(HostError::result_matches_err(res, MAP_OOB));
+
+    Ok(())
+}
+
+#[test]
+fn map_range_and_nth_key_range_are_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::MAP_RANGE_FNS_MIN_PROTOCOL - 1
+    })?;
+    let map = host.map_put(host.map_new()?, 1u32.into(), 10u32.into())?;
+    let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+    assert!(HostError::result_matches_err(
+        host.map_range(map, 0u32.into(), 2u32.into()),
+        code
+    ));
+    assert!(HostError::result_matches_err(
+        host.map_nth_key_range(map, 0u32.into(), 1u32.into()),
+        code
+    ));
+    Ok(())
+}
+
 #[test]
 fn map_stack_no_overflow_65536_boxed_keys_and_vals() {
     let mut map: Vec<(Rc<LedgerKey>, Option<Rc<LedgerEntry>>)> = Vec::new();
@@ -338,3 +404,239 @@ fn map_build_bad_element_integrity() -> Result<(), HostError> {
 
     Ok(())
 }
+
+#[cfg(feature = "next")]
+fn wasm_module_creating_map_from_val_keys(keys: &[Val], vals: &[Val]) -> Vec<u8> {
+    use soroban_synth_wasm::{Arity, ModEmitter, Operand};
+    let mut fe = ModEmitter::new().func(Arity(0), 0);
+    let vals_pos = (keys.len() * 8) as u32;
+    for (i, v) in keys.iter().chain(vals.iter()).enumerate() {
+        fe.push(Operand::Const32((i * 8) as i32));
+        fe.push(*v);
+        fe.i64_store(0);
+    }
+    fe.map_new_from_linear_memory_with_val_keys(
+        U32Val::from(0).to_val(),
+        U32Val::from(vals_pos).to_val(),
+        U32Val::from(keys.len() as u32).to_val(),
+    );
+    let (mut me, f) = fe.finish();
+    me.export_func(f, "test");
+    me.export_memory("memory");
+    me.finish()
+}
+
+#[cfg(feature = "next")]
+#[test]
+fn map_new_from_linear_memory_with_val_keys() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::MAP_FROM_VAL_KEYS_MIN_PROTOCOL
+    })?;
+    let test_fn = Symbol::try_from_small_str("test")?;
+
+    let keys = [Val::from(1_u32), Val::from(5_u32)];
+    let vals = [Val::from(10_u32), Val::from(50_u32)];
+    let contract = host.register_test_contract_wasm(
+        wasm_module_creating_map_from_val_keys(&keys, &vals).as_slice(),
+    );
+    let map: MapObject = host.call(contract, test_fn, host.vec_new()?)?.try_into()?;
+    assert_eq!(u32::from(host.map_len(map)?), 2);
+    for (k, v) in keys.iter().zip(vals.iter()) {
+        let got = host.map_get(map, *k)?;
+        assert_eq!(
+            u32::try_from_val(&host, &got)?,
+            u32::try_from_val(&host, v)?
+        );
+    }
+
+    // Keys have to be sorted and unique.
+    for keys in [[Val::from(5_u32), Val::from(1_u32)], [Val::from(1_u32); 2]] {
+        let contract = host.register_test_contract_wasm(
+            wasm_module_creating_map_from_val_keys(&keys, &vals).as_slice(),
+        );
+        assert!(HostError::result_matches_err(
+            host.call(contract, test_fn, host.vec_new()?),
+            (ScErrorType::Object, ScErrorCode::InvalidInput)
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn map_new_from_linear_memory_with_val_keys_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::MAP_FROM_VAL_KEYS_MIN_PROTOCOL - 1
+    })?;
+    assert!(HostError::result_matches_err(
+        host.map_new_from_linear_memory_with_val_keys(
+            U32Val::from(0),
+            U32Val::from(16),
+            U32Val::from(2)
+        ),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+// Copies a window of two entries of the map argument starting at the given
+// index, and returns the copied keys, values and count as a vec.
+#[cfg(feature = "next")]
+fn wasm_module_copying_map_range() -> Vec<u8> {
+    use soroban_synth_wasm::{Arity, ModEmitter, Operand};
+    let mut fe = ModEmitter::new().func(Arity(2), 0);
+    fe.push(Operand::Const32(32));
+    fe.map_copy_range_to_linear_memory(
+        fe.args[0],
+        fe.args[1],
+        U32Val::from(0).to_val(),
+        U32Val::from(16).to_val(),
+        U32Val::from(2).to_val(),
+    );
+    fe.i64_store(0);
+    fe.vec_new_from_linear_memory(U32Val::from(0).to_val(), U32Val::from(5).to_val());
+    let (mut me, f) = fe.finish();
+    me.export_func(f, "test");
+    me.export_memory("memory");
+    me.finish()
+}
+
+#[cfg(feature = "next")]
+#[test]
+fn map_copy_range_to_linear_memory() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::MAP_COPY_RANGE_MIN_PROTOCOL)?;
+    let test_fn = Symbol::try_from_small_str("test")?;
+    let contract = host.register_test_contract_wasm(wasm_module_copying_map_range().as_slice());
+
+    let map = host.map_new()?;
+    for i in 1..=3_u32 {
+        host.map_put(map, i.into(), (i * 10).into())?;
+    }
+    let copy_range = |start: u32| -> Result<ScVal, HostError> {
+        let args = host.vec_new_from_slice(&[map.to_val(), U32Val::from(start).to_val()])?;
+        let res = host.call(contract, test_fn, args)?;
+        host.from_host_val(res)
+    };
+    let vec = |vals: &[ScVal]| ScVal::Vec(Some(ScVec(vals.to_vec().try_into().unwrap())));
+
+    assert_eq!(copy_range(0)?, vec(&[1, 2, 10, 20, 2].map(ScVal::U32)));
+    assert_eq!(copy_range(1)?, vec(&[2, 3, 20, 30, 2].map(ScVal::U32)));
+    // Only the remaining entries are copied at the end of the map, the rest
+    // of the window is left untouched.
+    assert_eq!(
+        copy_range(2)?,
+        vec(&[
+            ScVal::U32(3),
+            ScVal::Bool(false),
+            ScVal::U32(30),
+            ScVal::Bool(false),
+            ScVal::U32(1)
+        ])
+    );
+    assert_eq!(
+        copy_range(3)?,
+        vec(&[
+            ScVal::Bool(false),
+            ScVal::Bool(false),
+            ScVal::Bool(false),
+            ScVal::Bool(false),
+            ScVal::U32(0)
+        ])
+    );
+
+    // Starting past the end of the map fails.
+    assert!(HostError::result_matches_err(copy_range(4), MAP_OOB));
+    Ok(())
+}
+
+#[test]
+fn map_copy_range_to_linear_memory_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::MAP_COPY_RANGE_MIN_PROTOCOL - 1
+    })?;
+    let map = host.map_put(host.map_new()?, 1u32.into(), 10u32.into())?;
+    assert!(HostError::result_matches_err(
+        host.map_copy_range_to_linear_memory(
+            map,
+            U32Val::from(0),
+            U32Val::from(0),
+            U32Val::from(16),
+            U32Val::from(2)
+        ),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+    Ok(())
+}
+
+#[test]
+fn inconsistent_object_comparator_is_rejected() -> Result<(), HostError> {
+    use std::cmp::Ordering;
+    let host = Host::default();
+    // Large u64 keys are stored as objects.
+    let scmap = ScVal::Map(Some(ScMap(
+        vec![
+            ScMapEntry {
+                key: ScVal::U64(u64::MAX - 1),
+                val: ScVal::U32(1),
+            },
+            ScMapEntry {
+                key: ScVal::U64(u64::MAX),
+                val: ScVal::U32(2),
+            },
+        ]
+        .try_into()
+        .unwrap(),
+    )));
+    assert!(host.to_host_val(&scmap).is_ok());
+
+    let reversed = |_: &Host, _: Val, _: Val, ord: Ordering| -> Result<Ordering, HostError> {
+        Ok(ord.reverse())
+    };
+    host.set_object_comparator(Some(Rc::new(reversed)))?;
+    assert!(HostError::result_matches_err(
+        host.to_host_val(&scmap),
+        (ScErrorType::Object, ScErrorCode::InvalidInput)
+    ));
+
+    let all_equal = |_: &Host, _: Val, _: Val, _: Ordering| -> Result<Ordering, HostError> {
+        Ok(Ordering::Equal)
+    };
+    host.set_object_comparator(Some(Rc::new(all_equal)))?;
+    assert!(HostError::result_matches_err(
+        host.to_host_val(&scmap),
+        (ScErrorType::Object, ScErrorCode::InvalidInput)
+    ));
+
+    host.set_object_comparator(None)?;
+    assert!(host.to_host_val(&scmap).is_ok());
+    Ok(())
+}
+
+#[test]
+fn map_respects_object_size_limits() -> Result<(), HostError> {
+    use crate::EnvBase;
+    let host = Host::default();
+    host.set_object_size_limits(crate::ObjectSizeLimits {
+        max_map_len: 2,
+        ..Default::default()
+    })?;
+    let code = (ScErrorType::Object, ScErrorCode::ExceededLimit);
+    let vals = [Val::VOID.to_val(); 3];
+    let m = host.map_new_from_slices(&["a", "b"], &vals[..2])?;
+    assert!(HostError::result_matches_err(
+        host.map_new_from_slices(&["a", "b", "c"], &vals),
+        code
+    ));
+    let c = Symbol::try_from_val(&host, &"c")?;
+    assert!(HostError::result_matches_err(
+        host.map_put(m, c.to_val(), Val::VOID.to_val()),
+        code
+    ));
+    // Overwriting an existing key doesn't grow the map.
+    let a = Symbol::try_from_val(&host, &"a")?;
+    host.map_put(m, a.to_val(), Val::TRUE.to_val())?;
+    Ok(())
+}
@@ -0,0 +1,205 @@
+use crate::{
+    native_contract::contract_error::ContractError,
+    test::util::{generate_account_id, generate_bytes_array},
+    xdr::{ContractEventBody, ContractEventType, ScAddress, ScErrorCode, ScErrorType, ScVal},
+    AddressObject, Compare, Env, EnvBase, Host, HostError, Symbol, TryFromVal, TryIntoVal, Val,
+    NFT_EXECUTABLE_HASH,
+};
+use core::cmp::Ordering;
+
+fn call(host: &Host, nft: AddressObject, func: &str, args: &[Val]) -> Result<Val, HostError> {
+    host.call(
+        nft,
+        Symbol::try_from_val(host, &func)?,
+        host.vec_new_from_slice(args)?,
+    )
+}
+
+fn owner(host: &Host, nft: AddressObject, token_id: u64) -> Result<AddressObject, HostError> {
+    let owner = call(host, nft, "owner", &[token_id.try_into_val(host)?])?;
+    Ok(owner.try_into()?)
+}
+
+fn balance(host: &Host, nft: AddressObject, addr: AddressObject) -> Result<u64, HostError> {
+    let balance = call(host, nft, "balance", &[addr.into()])?;
+    Ok(u64::try_from_val(host, &balance)?)
+}
+
+fn last_event_name(host: &Host) -> Result<ScVal, HostError> {
+    let events = host.get_events()?.0;
+    let event = events
+        .iter()
+        .rev()
+        .find(|e| e.event.type_ == ContractEventType::Contract)
+        .unwrap();
+    let ContractEventBody::V0(body) = &event.event.body;
+    Ok(body.topics[0].clone())
+}
+
+#[test]
+fn test_nft_lifecycle() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::NFT_MIN_PROTOCOL)?;
+    host.switch_to_recording_auth(true)?;
+
+    let nft = host.create_contract(
+        host.add_host_object(ScAddress::Account(generate_account_id()))?,
+        host.bytes_new_from_slice(&NFT_EXECUTABLE_HASH)?,
+        host.bytes_new_from_slice(&generate_bytes_array())?,
+    )?;
+    let admin = host.add_host_object(ScAddress::Account(generate_account_id()))?;
+    let alice = host.add_host_object(ScAddress::Account(generate_account_id()))?;
+    let bob = host.add_host_object(ScAddress::Account(generate_account_id()))?;
+    let same = |a: AddressObject, b: AddressObject| -> Result<bool, HostError> {
+        Ok(host.compare(&a.to_val(), &b.to_val())? == Ordering::Equal)
+    };
+
+    let init_args: [Val; 3] = [
+        admin.into(),
+        host.string_new_from_slice("Collection")?.into(),
+        host.string_new_from_slice("COL")?.into(),
+    ];
+    call(&host, nft, "init", &init_args)?;
+    // Initialization requires the authorization of the admin and bumps the
+    // instance by a week.
+    let payloads = host.get_recorded_auth_payloads()?;
+    assert_eq!(payloads.len(), 1);
+    assert_eq!(
+        payloads[0].address,
+        Some(host.scaddress_from_address(admin)?)
+    );
+    let instance_key = host.contract_instance_ledger_key(&host.contract_id_from_address(nft)?)?;
+    let (_, expiration_ledger) = host.with_mut_storage(|storage| {
+        storage.get_with_expiration(&instance_key, host.budget_ref())
+    })?;
+    assert_eq!(expiration_ledger, Some(7 * 17280));
+    // Initialization is only possible once.
+    assert!(HostError::result_matches_err(
+        call(&host, nft, "init", &init_args),
+        ContractError::AlreadyInitializedError
+    ));
+
+    let uri = host.string_new_from_slice("ipfs://token/1")?;
+    call(
+        &host,
+        nft,
+        "mint",
+        &[alice.into(), 1_u64.try_into_val(&host)?, uri.into()],
+    )?;
+    assert_eq!(
+        last_event_name(&host)?,
+        ScVal::Symbol("mint".try_into().unwrap())
+    );
+    assert!(same(owner(&host, nft, 1)?, alice)?);
+    assert_eq!(balance(&host, nft, alice)?, 1);
+    let token_uri = call(&host, nft, "token_uri", &[1_u64.try_into_val(&host)?])?;
+    assert_eq!(host.compare(&token_uri, &uri.to_val())?, Ordering::Equal);
+    // Token ids are unique.
+    assert!(HostError::result_matches_err(
+        call(
+            &host,
+            nft,
+            "mint",
+            &[bob.into(), 1_u64.try_into_val(&host)?, uri.into()],
+        ),
+        ContractError::NonFungibleTokenError
+    ));
+
+    // Only the owner can transfer the token.
+    assert!(HostError::result_matches_err(
+        call(
+            &host,
+            nft,
+            "transfer",
+            &[bob.into(), alice.into(), 1_u64.try_into_val(&host)?],
+        ),
+        ContractError::UnauthorizedError
+    ));
+    call(
+        &host,
+        nft,
+        "transfer",
+        &[alice.into(), bob.into(), 1_u64.try_into_val(&host)?],
+    )?;
+    assert!(same(owner(&host, nft, 1)?, bob)?);
+    assert_eq!(balance(&host, nft, alice)?, 0);
+    assert_eq!(balance(&host, nft, bob)?, 1);
+
+    // Approved spenders can transfer the token once.
+    assert!(HostError::result_matches_err(
+        call(
+            &host,
+            nft,
+            "transfer_from",
+            &[
+                alice.into(),
+                bob.into(),
+                alice.into(),
+                1_u64.try_into_val(&host)?
+            ],
+        ),
+        ContractError::UnauthorizedError
+    ));
+    call(
+        &host,
+        nft,
+        "approve",
+        &[bob.into(), alice.into(), 1_u64.try_into_val(&host)?],
+    )?;
+    assert_eq!(
+        last_event_name(&host)?,
+        ScVal::Symbol("approve".try_into().unwrap())
+    );
+    call(
+        &host,
+        nft,
+        "transfer_from",
+        &[
+            alice.into(),
+            bob.into(),
+            alice.into(),
+            1_u64.try_into_val(&host)?,
+        ],
+    )?;
+    assert!(same(owner(&host, nft, 1)?, alice)?);
+    let approved = call(
+        &host,
+        nft,
+        "is_approved",
+        &[1_u64.try_into_val(&host)?, alice.into()],
+    )?;
+    assert!(!bool::try_from(approved)?);
+
+    call(
+        &host,
+        nft,
+        "burn",
+        &[alice.into(), 1_u64.try_into_val(&host)?],
+    )?;
+    assert_eq!(
+        last_event_name(&host)?,
+        ScVal::Symbol("burn".try_into().unwrap())
+    );
+    assert_eq!(balance(&host, nft, alice)?, 0);
+    assert!(HostError::result_matches_err(
+        owner(&host, nft, 1),
+        ContractError::NonFungibleTokenError
+    ));
+    Ok(())
+}
+
+#[test]
+fn nft_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::NFT_MIN_PROTOCOL - 1)?;
+    // Before the NFT is enabled its hash is just a missing Wasm.
+    assert!(HostError::result_matches_err(
+        host.create_contract(
+            host.add_host_object(ScAddress::Account(generate_account_id()))?,
+            host.bytes_new_from_slice(&NFT_EXECUTABLE_HASH)?,
+            host.bytes_new_from_slice(&generate_bytes_array())?,
+        ),
+        (ScErrorType::Storage, ScErrorCode::MissingValue)
+    ));
+    Ok(())
+}
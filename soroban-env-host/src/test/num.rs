@@ -4,7 +4,7 @@ use soroban_env_common::{
     Compare, Env, EnvBase, TryFromVal, TryIntoVal, I256,
 };
 
-use crate::{budget::AsBudget, Host, HostError, Val};
+use crate::{budget::AsBudget, Error, Host, HostError, Val};
 use core::fmt::Debug;
 use std::cmp::Ordering;
 
@@ -417,3 +417,233 @@ fn test_u256_bytes_roundtrip() -> Result<(), HostError> {
     assert_eq!(num, num_back);
     Ok(())
 }
+
+#[test]
+fn test_scale_amount() -> Result<(), HostError> {
+    use crate::AmountRounding::*;
+    let host = Host::default();
+
+    assert_eq!(host.scale_amount(15, 0, 7, TowardZero)?, 150_000_000);
+    assert_eq!(host.scale_amount(-15, 2, 2, AwayFromZero)?, -15);
+    let res = host.scale_amount(i128::MAX / 10, 0, 2, TowardZero);
+    let code = (ScErrorType::Value, ScErrorCode::ArithDomain);
+    assert!(HostError::result_matches_err(res, code));
+
+    for (amount, rounding, expected) in [
+        (15, TowardZero, 1),
+        (15, AwayFromZero, 2),
+        (15, HalfAwayFromZero, 2),
+        (14, HalfAwayFromZero, 1),
+        (-15, TowardZero, -1),
+        (-15, AwayFromZero, -2),
+        (-15, HalfAwayFromZero, -2),
+        (-14, HalfAwayFromZero, -1),
+        (10, AwayFromZero, 1),
+    ] {
+        assert_eq!(host.scale_amount(amount, 1, 0, rounding)?, expected);
+    }
+
+    // Divisors that do not fit in an i128 round everything to zero or one
+    // unit.
+    assert_eq!(host.scale_amount(i128::MIN, 50, 0, HalfAwayFromZero)?, 0);
+    assert_eq!(host.scale_amount(i128::MIN, 50, 0, AwayFromZero)?, -1);
+    assert_eq!(host.scale_amount(0, 50, 0, AwayFromZero)?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_i128_scale_host_fn() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::I128_SCALE_MIN_PROTOCOL)?;
+    let amount: Val = 12_345_678_i128.try_into_val(&host)?;
+    let res = host.i128_scale(amount, 7u32.into(), 2u32.into(), 0u32.into())?;
+    let res: i128 = res.try_into_val(&host)?;
+    assert_eq!(res, 123);
+
+    let res = host.i128_scale(amount, 7u32.into(), 2u32.into(), 3u32.into());
+    let code = (ScErrorType::Value, ScErrorCode::InvalidInput);
+    assert!(HostError::result_matches_err(res, code));
+    Ok(())
+}
+
+#[test]
+fn test_i128_scale_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::I128_SCALE_MIN_PROTOCOL - 1)?;
+    let amount: Val = 12_345_678_i128.try_into_val(&host)?;
+    let res = host.i128_scale(amount, 7u32.into(), 2u32.into(), 0u32.into());
+    let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+    assert!(HostError::result_matches_err(res, code));
+    Ok(())
+}
+
+#[test]
+fn test_require_i128_amount_host_fns() -> Result<(), HostError> {
+    use crate::native_contract::contract_error::ContractError;
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::REQUIRE_I128_AMOUNT_MIN_PROTOCOL
+    })?;
+    let amount = |a: i128| -> Result<Val, HostError> { Ok(a.try_into_val(&host)?) };
+    host.require_nonnegative_i128(amount(0)?)?;
+    host.require_nonnegative_i128(amount(i128::MAX)?)?;
+    host.require_positive_i128(amount(1)?)?;
+    // The errors are the ones of the built-in token contracts.
+    for res in [
+        host.require_nonnegative_i128(amount(-1)?),
+        host.require_nonnegative_i128(amount(i128::MIN)?),
+        host.require_positive_i128(amount(0)?),
+        host.require_positive_i128(amount(-1)?),
+    ] {
+        assert!(HostError::result_matches_err(
+            res,
+            ContractError::NegativeAmountError
+        ));
+    }
+    // Other values are rejected as usual.
+    assert!(HostError::result_matches_err(
+        host.require_positive_i128(1_u32.into()),
+        (ScErrorType::Value, ScErrorCode::UnexpectedType)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_require_i128_amount_host_fns_are_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::REQUIRE_I128_AMOUNT_MIN_PROTOCOL - 1
+    })?;
+    let amount: Val = 1_i128.try_into_val(&host)?;
+    let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+    assert!(HostError::result_matches_err(
+        host.require_nonnegative_i128(amount),
+        code
+    ));
+    assert!(HostError::result_matches_err(
+        host.require_positive_i128(amount),
+        code
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_u256_sqrt_and_log2() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::U256_SQRT_LOG2_MIN_PROTOCOL)?;
+    let sqrt = |x: U256| -> Result<U256, HostError> {
+        let res = host.u256_sqrt(U256Val::try_from_val(&host, &x)?)?;
+        Ok(U256::try_from_val(&host, &res.to_val())?)
+    };
+    for (x, expected) in [
+        (U256::ZERO, U256::ZERO),
+        (U256::ONE, U256::ONE),
+        (U256::new(2), U256::ONE),
+        (U256::new(15), U256::new(3)),
+        (U256::new(16), U256::new(4)),
+        (U256::new(u64::MAX as u128), U256::new(u32::MAX as u128)),
+        (U256::new(u128::MAX), U256::new(u64::MAX as u128)),
+        (U256::MAX, U256::new(u128::MAX)),
+    ] {
+        assert_eq!(sqrt(x)?, expected);
+    }
+    // The result is the floor of the root around perfect squares.
+    let r = U256::new(u128::MAX - 12345);
+    assert_eq!(sqrt(r * r)?, r);
+    assert_eq!(sqrt(r * r - U256::ONE)?, r - U256::ONE);
+    assert_eq!(sqrt(r * r + r + r)?, r);
+
+    let log2 = |x: U256| -> Result<u32, HostError> {
+        Ok(host.u256_log2(U256Val::try_from_val(&host, &x)?)?.into())
+    };
+    assert_eq!(log2(U256::ONE)?, 0);
+    assert_eq!(log2(U256::new(1023))?, 9);
+    assert_eq!(log2(U256::new(1024))?, 10);
+    assert_eq!(log2(U256::MAX)?, 255);
+    let res = host.u256_log2(U256Val::try_from_val(&host, &U256::ZERO)?);
+    let code = (ScErrorType::Object, ScErrorCode::ArithDomain);
+    assert!(HostError::result_matches_err(res, code));
+    Ok(())
+}
+
+#[test]
+fn test_u256_sqrt_and_log2_are_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::U256_SQRT_LOG2_MIN_PROTOCOL - 1
+    })?;
+    let x = U256Val::try_from_val(&host, &U256::new(16))?;
+    let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+    assert!(HostError::result_matches_err(host.u256_sqrt(x), code));
+    assert!(HostError::result_matches_err(host.u256_log2(x), code));
+    Ok(())
+}
+
+#[test]
+fn test_i128_256_conversions() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::I128_256_CONVERSIONS_MIN_PROTOCOL
+    })?;
+    let range_err = Error::from_type_and_code(ScErrorType::Value, ScErrorCode::ArithDomain);
+    let i128_val = |v: i128| Val::try_from_val(&host, &v);
+
+    for v in [0, 1, i128::MAX] {
+        let u = U256Val::try_from_val(&host, &U256::new(v as u128))?;
+        let res = host.u256_to_i128_checked(u)?;
+        assert_eq!(i128::try_from_val(&host, &res)?, v);
+        let res = host.i128_to_u256(i128_val(v)?)?;
+        assert_eq!(U256::try_from_val(&host, &res)?, U256::new(v as u128));
+    }
+    let u = U256Val::try_from_val(&host, &U256::new(i128::MAX as u128 + 1))?;
+    assert_eq!(
+        host.u256_to_i128_checked(u)?.get_payload(),
+        range_err.to_val().get_payload()
+    );
+    let res = host.i128_to_u256(i128_val(-1)?)?;
+    assert_eq!(res.get_payload(), range_err.to_val().get_payload());
+
+    for v in [i128::MIN, -1, 0, i128::MAX] {
+        let res = host.i128_to_i256(i128_val(v)?)?;
+        assert_eq!(I256::try_from_val(&host, &res.to_val())?, I256::new(v));
+        let res = host.i256_to_i128_checked(res)?;
+        assert_eq!(i128::try_from_val(&host, &res)?, v);
+    }
+    for v in [
+        I256::new(i128::MIN) - I256::ONE,
+        I256::new(i128::MAX) + I256::ONE,
+    ] {
+        let res = host.i256_to_i128_checked(I256Val::try_from_val(&host, &v)?)?;
+        assert_eq!(res.get_payload(), range_err.to_val().get_payload());
+    }
+    Ok(())
+}
+
+#[test]
+fn test_i128_256_conversions_are_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::I128_256_CONVERSIONS_MIN_PROTOCOL - 1
+    })?;
+    let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+    let i128_val = Val::try_from_val(&host, &1_i128)?;
+    let u = U256Val::try_from_val(&host, &U256::ONE)?;
+    let i = I256Val::try_from_val(&host, &I256::ONE)?;
+    assert!(HostError::result_matches_err(
+        host.u256_to_i128_checked(u),
+        code
+    ));
+    assert!(HostError::result_matches_err(
+        host.i256_to_i128_checked(i),
+        code
+    ));
+    assert!(HostError::result_matches_err(
+        host.i128_to_u256(i128_val),
+        code
+    ));
+    assert!(HostError::result_matches_err(
+        host.i128_to_i256(i128_val),
+        code
+    ));
+    Ok(())
+}
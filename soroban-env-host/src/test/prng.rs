@@ -1,7 +1,7 @@
 use crate::{
-    xdr::{Hash, ScAddress, ScVal, ScVec},
-    BytesObject, ContractFunctionSet, Env, EnvBase, Host, HostError, Symbol, SymbolSmall, U32Val,
-    U64Object, Val, VecObject,
+    xdr::{ContractEventBody, Hash, ScAddress, ScErrorCode, ScErrorType, ScSymbol, ScVal, ScVec},
+    BytesObject, ContractFunctionSet, Env, EnvBase, Host, HostError, PrngDraw, PrngMode,
+    PrngTranscript, Symbol, SymbolSmall, U32Val, U64Object, Val, VecObject,
 };
 
 /// prng tests
@@ -125,3 +125,180 @@ fn prng_test() -> Result<(), HostError> {
 
     Ok(())
 }
+
+#[test]
+fn prng_programmed_outputs_and_draw_diagnostics() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+
+    host.enable_debug()?;
+    host.set_base_prng_seed([0; 32])?;
+
+    let dummy_address = ScAddress::Contract(Hash([0; 32]));
+    let id = host.add_host_object(dummy_address)?;
+    host.register_test_contract(id, std::rc::Rc::new(PRNGUsingTest))?;
+    let args = host.test_vec_obj::<i32>(&[])?;
+
+    // Programmed outputs are returned in order, then draws go back to the PRNG.
+    host.program_prng_outputs(vec![LO, HI])?;
+    let mut draws = vec![];
+    for _ in 0..3 {
+        let u: U64Object = host.call(id, U64_RANGE.into(), args)?.try_into()?;
+        draws.push(host.obj_to_u64(u)?);
+    }
+    assert_eq!(draws[..2], [LO, HI]);
+    assert!(LO <= draws[2] && draws[2] <= HI);
+
+    // An out-of-range programmed output fails the draw.
+    host.program_prng_outputs(vec![HI + 1])?;
+    let res = host.prng_u64_in_inclusive_range(LO, HI);
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Value, ScErrorCode::InvalidInput)
+    ));
+
+    // Every draw made by the contract is recorded as a diagnostic event.
+    let bytes: BytesObject = host.call(id, BYTES_NEW.into(), args)?.try_into()?;
+    let sym = |s: &str| ScVal::Symbol(ScSymbol(s.try_into().unwrap()));
+    let draw_events: Vec<_> = host
+        .get_events()?
+        .0
+        .into_iter()
+        .filter_map(|e| {
+            let ContractEventBody::V0(body) = e.event.body;
+            (body.topics.first() == Some(&sym("prng_draw")))
+                .then(|| (body.topics[1].clone(), body.data))
+        })
+        .collect();
+    assert_eq!(draw_events.len(), 4);
+    for (i, draw) in draws.iter().enumerate() {
+        let ScVal::Vec(Some(data)) = &draw_events[i].1 else {
+            panic!("unexpected prng_draw event data");
+        };
+        assert_eq!(draw_events[i].0, sym("u64_range"));
+        assert_eq!(
+            data.0.to_vec(),
+            vec![ScVal::U64(LO), ScVal::U64(HI), ScVal::U64(*draw)]
+        );
+    }
+    let ScVal::Vec(Some(data)) = &draw_events[3].1 else {
+        panic!("unexpected prng_draw event data");
+    };
+    assert_eq!(draw_events[3].0, sym("bytes_new"));
+    assert_eq!(data.0[0], ScVal::U32(SEED_LEN));
+    assert_eq!(data.0[1], ScVal::from(host.from_host_obj(bytes)?));
+
+    Ok(())
+}
+
+// Makes one call of each of the functions of `PRNGUsingTest`.
+fn prng_draws(host: &Host) -> Result<Vec<ScVal>, HostError> {
+    let id = host.add_host_object(ScAddress::Contract(Hash([0; 32])))?;
+    host.register_test_contract(id, std::rc::Rc::new(PRNGUsingTest))?;
+    let no_args = host.test_vec_obj::<i32>(&[])?;
+    let shuffle_args = host.test_vec_obj::<ScVec>(&[host.test_scvec::<i32>(&[1, 2, 3, 4, 5])?])?;
+    let seed = host.bytes_new_from_slice(&[7; SEED_LEN as usize])?;
+    let reseed_args = host.vec_new_from_slice(&[seed.to_val()])?;
+    let mut draws = vec![];
+    for (func, args) in [
+        (BYTES_NEW, no_args),
+        (U64_RANGE, no_args),
+        (SHUFFLE, shuffle_args),
+        (RESEED, reseed_args),
+    ] {
+        let res = host.call(id, func.into(), args)?;
+        draws.push(host.from_host_val(res)?);
+    }
+    Ok(draws)
+}
+
+#[test]
+fn prng_transcript_replay() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.set_base_prng_seed([1; 32])?;
+    host.set_prng_mode(PrngMode::Recording(Default::default()))?;
+    let recorded = prng_draws(&host)?;
+    let transcript = host.prng_transcript()?.unwrap();
+    assert_eq!(transcript.draws.len(), 4);
+    assert!(matches!(transcript.draws[2], PrngDraw::Shuffle(_)));
+
+    // The draws are replayed without the seed.
+    let host = Host::test_host_with_recording_footprint();
+    host.set_prng_mode(PrngMode::Replay(transcript.clone()))?;
+    assert_eq!(prng_draws(&host)?, recorded);
+    assert_eq!(host.prng_transcript()?, Some(PrngTranscript::default()));
+    let func = Symbol::try_from_small_str("test")?;
+    host.with_test_contract_frame(Hash([0; 32]), func, || {
+        assert!(HostError::result_matches_err(
+            host.prng_u64_in_inclusive_range(LO, HI),
+            (ScErrorType::Context, ScErrorCode::MissingValue)
+        ));
+        // Draws that don't fit the transcript fail.
+        host.set_prng_mode(PrngMode::Replay(transcript.clone()))?;
+        assert!(HostError::result_matches_err(
+            host.prng_u64_in_inclusive_range(LO, HI),
+            (ScErrorType::Context, ScErrorCode::InvalidInput)
+        ));
+        Ok(Val::VOID.to_val())
+    })?;
+
+    // Nothing can be drawn when the PRNG is unavailable, even with a seed.
+    let host = Host::test_host_with_recording_footprint();
+    host.set_base_prng_seed([1; 32])?;
+    host.set_prng_mode(PrngMode::Unavailable)?;
+    let id = host.add_host_object(ScAddress::Contract(Hash([0; 32])))?;
+    host.register_test_contract(id, std::rc::Rc::new(PRNGUsingTest))?;
+    host.with_test_contract_frame(Hash([0; 32]), func, || {
+        assert!(HostError::result_matches_err(
+            host.prng_bytes_new(SEED_LEN.into()),
+            (ScErrorType::Context, ScErrorCode::InvalidAction)
+        ));
+        assert!(HostError::result_matches_err(
+            host.prng_u64_in_inclusive_range(LO, HI),
+            (ScErrorType::Context, ScErrorCode::InvalidAction)
+        ));
+        Ok(Val::VOID.to_val())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn prng_vec_shuffle_charges_are_the_same_in_every_mode() -> Result<(), HostError> {
+    use crate::budget::AsBudget;
+
+    // Returns the budget charged for shuffling a vector in `mode`, along with
+    // the recorded transcript.
+    let shuffle_cost = |mode: PrngMode| -> Result<((u64, u64), Option<PrngTranscript>), HostError> {
+        let host = Host::test_host_with_recording_footprint();
+        host.set_base_prng_seed([1; 32])?;
+        host.set_prng_mode(mode)?;
+        let id = host.add_host_object(ScAddress::Contract(Hash([0; 32])))?;
+        host.register_test_contract(id, std::rc::Rc::new(PRNGUsingTest))?;
+        let v = host.test_vec_obj::<u32>(&[1, 2, 3, 4, 5, 6, 7, 8])?;
+        let func = Symbol::try_from_small_str("test")?;
+        let mut cost = (0, 0);
+        host.with_test_contract_frame(Hash([0; 32]), func, || {
+            // The frame PRNG is set up by the first draw, which isn't done
+            // when replaying.
+            host.prng_u64_in_inclusive_range(LO, HI)?;
+            let budget = host.as_budget();
+            let (cpu, mem) = (
+                budget.get_cpu_insns_consumed()?,
+                budget.get_mem_bytes_consumed()?,
+            );
+            host.prng_vec_shuffle(v)?;
+            cost = (
+                budget.get_cpu_insns_consumed()? - cpu,
+                budget.get_mem_bytes_consumed()? - mem,
+            );
+            Ok(Val::VOID.to_val())
+        })?;
+        Ok((cost, host.prng_transcript()?))
+    };
+
+    let (plain, _) = shuffle_cost(PrngMode::Seeded)?;
+    let (recording, transcript) = shuffle_cost(PrngMode::Recording(Default::default()))?;
+    let (replay, _) = shuffle_cost(PrngMode::Replay(transcript.unwrap()))?;
+    assert_eq!(recording, plain);
+    assert_eq!(replay, plain);
+    Ok(())
+}
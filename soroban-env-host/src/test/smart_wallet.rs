@@ -0,0 +1,232 @@
+use crate::{
+    budget::AsBudget,
+    host::frame::ContractReentryMode,
+    host_vec,
+    native_contract::{
+        account_contract::{AuthorizationContext, ContractAuthorizationContext},
+        base_types::{Address, BytesN},
+        contract_error::ContractError,
+        smart_wallet::{SessionKey, SignerKey, SignerSignature},
+        testutils::{generate_signing_key, HostVec},
+    },
+    test::util::{generate_account_id, generate_bytes_array},
+    xdr::{Hash, ScAddress, ScErrorCode, ScErrorType},
+    AddressObject, Compare, Env, EnvBase, Host, HostError, Symbol, TryFromVal, TryIntoVal,
+    SMART_WALLET_EXECUTABLE_HASH,
+};
+use core::cmp::Ordering;
+use ed25519_dalek::Signer;
+use p256::ecdsa::signature::hazmat::PrehashSigner;
+
+fn check_auth(
+    host: &Host,
+    wallet: AddressObject,
+    payload: &[u8; 32],
+    signatures: HostVec,
+    contexts: HostVec,
+) -> Result<(), HostError> {
+    host.call_n_internal(
+        &host.contract_id_from_address(wallet)?,
+        Symbol::try_from_val(host, &"__check_auth")?,
+        &[
+            host.bytes_new_from_slice(payload)?.into(),
+            signatures.into(),
+            contexts.into(),
+        ],
+        ContractReentryMode::Prohibited,
+        true,
+    )?;
+    Ok(())
+}
+
+#[test]
+fn test_smart_wallet_check_auth() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::SMART_WALLET_MIN_PROTOCOL;
+    })?;
+    host.switch_to_recording_auth(true)?;
+
+    let ed_key = generate_signing_key();
+    let r1_key = p256::ecdsa::SigningKey::from_slice(&[7; 32]).unwrap();
+    let ed_signer = SignerKey::Ed25519(BytesN::from_slice(
+        &host,
+        &ed_key.verifying_key().to_bytes(),
+    )?);
+    let r1_signer = SignerKey::Secp256r1(BytesN::from_slice(
+        &host,
+        r1_key.verifying_key().to_encoded_point(false).as_bytes(),
+    )?);
+
+    let wallet = host.create_contract(
+        host.add_host_object(ScAddress::Account(generate_account_id()))?,
+        host.bytes_new_from_slice(&SMART_WALLET_EXECUTABLE_HASH)?,
+        host.bytes_new_from_slice(&generate_bytes_array())?,
+    )?;
+    let recovery = host.add_host_object(ScAddress::Account(generate_account_id()))?;
+    host.call(
+        wallet,
+        Symbol::try_from_val(&host, &"init")?,
+        host_vec![
+            &host,
+            host_vec![&host, ed_signer.clone(), r1_signer.clone()],
+            2_u32,
+            recovery
+        ]
+        .into(),
+    )?;
+    // The calls bump the wallet instance, which has no code entry to bump.
+    let instance_key =
+        host.contract_instance_ledger_key(&host.contract_id_from_address(wallet)?)?;
+    let (_, expiration) = host
+        .try_borrow_storage_mut()?
+        .get_with_expiration(&instance_key, host.as_budget())?;
+    assert_eq!(expiration, Some(7 * 17280));
+    // Initialization is only possible once.
+    assert!(HostError::result_matches_err(
+        host.call(
+            wallet,
+            Symbol::try_from_val(&host, &"init")?,
+            host_vec![&host, host_vec![&host, ed_signer.clone()], 1_u32, recovery].into(),
+        ),
+        ContractError::AlreadyInitializedError
+    ));
+
+    let payload = generate_bytes_array();
+    let ed_sig = SignerSignature {
+        key: ed_signer,
+        signature: BytesN::from_slice(&host, &ed_key.sign(&payload).to_bytes())?,
+    };
+    let r1_sig: p256::ecdsa::Signature = r1_key.sign_prehash(&payload).unwrap();
+    let r1_sig = SignerSignature {
+        key: r1_signer,
+        signature: BytesN::from_slice(&host, &r1_sig.normalize_s().unwrap_or(r1_sig).to_bytes())?,
+    };
+
+    // Signatures have to be ordered by key.
+    let (first, second) = if host.compare(&ed_sig.key, &r1_sig.key)? == Ordering::Less {
+        (ed_sig.clone(), r1_sig.clone())
+    } else {
+        (r1_sig.clone(), ed_sig.clone())
+    };
+    check_auth(
+        &host,
+        wallet,
+        &payload,
+        host_vec![&host, first.clone(), second.clone()],
+        host_vec![&host],
+    )?;
+    assert!(HostError::result_matches_err(
+        check_auth(
+            &host,
+            wallet,
+            &payload,
+            host_vec![&host, second, first],
+            host_vec![&host],
+        ),
+        ContractError::AuthenticationError
+    ));
+    // A single signature doesn't reach the threshold.
+    assert!(HostError::result_matches_err(
+        check_auth(
+            &host,
+            wallet,
+            &payload,
+            host_vec![&host, ed_sig],
+            host_vec![&host],
+        ),
+        ContractError::AuthenticationError
+    ));
+    // Signatures over a different payload are rejected.
+    assert!(check_auth(
+        &host,
+        wallet,
+        &generate_bytes_array(),
+        host_vec![&host, r1_sig],
+        host_vec![&host],
+    )
+    .is_err());
+
+    // Session keys may only authorize calls to the allowed contracts before
+    // their expiration.
+    let session_key = generate_signing_key();
+    let session_pk = BytesN::<32>::from_slice(&host, &session_key.verifying_key().to_bytes())?;
+    let allowed_contract: Address = host
+        .add_host_object(ScAddress::Contract(Hash(generate_bytes_array())))?
+        .try_into_val(&host)?;
+    host.call(
+        wallet,
+        Symbol::try_from_val(&host, &"add_session_key")?,
+        host_vec![
+            &host,
+            session_pk.clone(),
+            SessionKey {
+                expiration_ledger: 10,
+                contracts: host_vec![&host, allowed_contract.clone()],
+            }
+        ]
+        .into(),
+    )?;
+    let session_sig = SignerSignature {
+        key: SignerKey::Ed25519(session_pk),
+        signature: BytesN::from_slice(&host, &session_key.sign(&payload).to_bytes())?,
+    };
+    let context = |contract: Address| -> Result<HostVec, HostError> {
+        let mut contexts = HostVec::new(&host)?;
+        contexts.push(&AuthorizationContext::Contract(
+            ContractAuthorizationContext {
+                contract,
+                fn_name: Symbol::try_from_val(&host, &"transfer")?,
+                args: HostVec::new(&host)?,
+            },
+        ))?;
+        Ok(contexts)
+    };
+    check_auth(
+        &host,
+        wallet,
+        &payload,
+        host_vec![&host, session_sig.clone()],
+        context(allowed_contract.clone())?,
+    )?;
+    assert!(HostError::result_matches_err(
+        check_auth(
+            &host,
+            wallet,
+            &payload,
+            host_vec![&host, session_sig.clone()],
+            context(wallet.try_into_val(&host)?)?,
+        ),
+        ContractError::AuthenticationError
+    ));
+    host.with_mut_ledger_info(|li| li.sequence_number = 11)?;
+    assert!(HostError::result_matches_err(
+        check_auth(
+            &host,
+            wallet,
+            &payload,
+            host_vec![&host, session_sig],
+            context(allowed_contract)?,
+        ),
+        ContractError::AuthenticationError
+    ));
+    Ok(())
+}
+
+#[test]
+fn smart_wallet_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::SMART_WALLET_MIN_PROTOCOL - 1;
+    })?;
+    // Before the wallet is enabled its hash is just a missing Wasm.
+    assert!(HostError::result_matches_err(
+        host.create_contract(
+            host.add_host_object(ScAddress::Account(generate_account_id()))?,
+            host.bytes_new_from_slice(&SMART_WALLET_EXECUTABLE_HASH)?,
+            host.bytes_new_from_slice(&generate_bytes_array())?,
+        ),
+        (ScErrorType::Storage, ScErrorCode::MissingValue)
+    ));
+    Ok(())
+}
@@ -1,14 +1,23 @@
+use std::cell::Cell;
 use std::rc::Rc;
 
-use crate::budget::Budget;
+use crate::budget::{AsBudget, Budget};
+use crate::native_contract::base_types::{BumpPolicy, StorageMap};
 use crate::native_contract::testutils::HostVec;
-use crate::storage::{AccessType, Footprint};
+use crate::storage::{
+    AccessType, Footprint, FootprintMode, SnapshotCache, SnapshotCacheMetrics, SnapshotSource,
+    Storage,
+};
 use crate::xdr::{
-    ContractDataDurability, LedgerKey, LedgerKeyContractData, ScAddress, ScErrorCode, ScErrorType,
-    ScVal,
+    ContractDataDurability, ContractDataEntry as XdrContractDataEntry, ExtensionPoint, Hash,
+    LedgerEntry, LedgerEntryData, LedgerEntryExt, LedgerKey, LedgerKeyContractData, ScAddress,
+    ScErrorCode, ScErrorType, ScSymbol, ScVal, WriteXdr, DEFAULT_XDR_RW_DEPTH_LIMIT,
+};
+use crate::{host_vec, Host, HostError, MeteredOrdMap, XdrReadLimits};
+use soroban_env_common::{
+    AddressObject, Env, EnvBase, StorageType, Symbol, TryFromVal, TryIntoVal, U32Val, Val,
+    VecObject,
 };
-use crate::{host_vec, Host, HostError, MeteredOrdMap};
-use soroban_env_common::{AddressObject, Env, Symbol, TryFromVal, TryIntoVal};
 use soroban_test_wasms::CONTRACT_STORAGE;
 
 #[test]
@@ -347,3 +356,1267 @@ fn test_storage_mix() {
     test_storage(&host, contract_id, "temporary");
     test_storage(&host, contract_id, "instance");
 }
+
+#[test]
+fn test_claim_idempotency_key() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::IDEMPOTENCY_KEYS_MIN_PROTOCOL
+    })?;
+    let key = host.bytes_new_from_slice(&[1, 2, 3])?;
+    let other_key = host.bytes_new_from_slice(&[4, 5, 6])?;
+    let func = Symbol::try_from_small_str("claim").unwrap();
+
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        host.claim_idempotency_key(key)?;
+        // A different key can still be claimed.
+        host.claim_idempotency_key(other_key)?;
+        // Claiming the same key again fails.
+        assert!(HostError::result_matches_err(
+            host.claim_idempotency_key(key),
+            (ScErrorType::Storage, ScErrorCode::ExistingValue)
+        ));
+        Ok(().into())
+    })?;
+
+    // Bump the claim of `key` via the regular storage interface, so that it
+    // outlives the claim of `other_key`.
+    let min_temp_expiration = host.with_ledger_info(|li| Ok(li.min_temp_entry_expiration))?;
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        let tag = Symbol::try_from_val(&host, &"IdempotencyKey")?;
+        let claim_key = host.vec_new_from_slice(&[tag.to_val(), key.to_val()])?;
+        host.bump_contract_data(
+            claim_key.into(),
+            StorageType::Temporary,
+            (min_temp_expiration * 2).into(),
+            (min_temp_expiration * 2).into(),
+        )?;
+        Ok(().into())
+    })?;
+
+    // Once the claim of `other_key` expires, it can be claimed again, while
+    // the bumped claim of `key` is still active.
+    host.with_mut_ledger_info(|li| li.sequence_number += min_temp_expiration)?;
+    host.with_test_contract_frame(contract_id, func, || {
+        host.claim_idempotency_key(other_key)?;
+        assert!(HostError::result_matches_err(
+            host.claim_idempotency_key(key),
+            (ScErrorType::Storage, ScErrorCode::ExistingValue)
+        ));
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_claim_idempotency_key_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::IDEMPOTENCY_KEYS_MIN_PROTOCOL - 1
+    })?;
+    let key = host.bytes_new_from_slice(&[1, 2, 3])?;
+    let func = Symbol::try_from_small_str("claim").unwrap();
+    host.with_test_contract_frame(contract_id, func, || {
+        assert!(HostError::result_matches_err(
+            host.claim_idempotency_key(key),
+            (ScErrorType::Context, ScErrorCode::InvalidAction)
+        ));
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_rate_limit_check_and_consume() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::RATE_LIMIT_MIN_PROTOCOL)?;
+    let func = Symbol::try_from_small_str("limit").unwrap();
+    let key = host.bytes_new_from_slice(&[1, 2, 3])?.to_val();
+    let other_key = host.bytes_new_from_slice(&[4, 5, 6])?.to_val();
+    let consume = |key: Val| -> Result<bool, HostError> {
+        let res =
+            host.rate_limit_check_and_consume(key, 3_u32.into(), 1_u32.into(), 2_u32.into())?;
+        Ok(res.into())
+    };
+
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        // A new bucket is full.
+        assert!(consume(key)?);
+        // Only one token is left in the same ledger.
+        assert!(!consume(key)?);
+        // Buckets of different keys are independent.
+        assert!(consume(other_key)?);
+        // The cost can't exceed the capacity.
+        assert!(HostError::result_matches_err(
+            host.rate_limit_check_and_consume(key, 3_u32.into(), 1_u32.into(), 4_u32.into()),
+            (ScErrorType::Value, ScErrorCode::InvalidInput)
+        ));
+        Ok(().into())
+    })?;
+
+    // One token is refilled per ledger.
+    host.with_mut_ledger_info(|li| li.sequence_number += 1)?;
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        assert!(consume(key)?);
+        assert!(!consume(key)?);
+        Ok(().into())
+    })?;
+
+    // The bucket is refilled up to its capacity only.
+    host.with_mut_ledger_info(|li| li.sequence_number += 100)?;
+    host.with_test_contract_frame(contract_id, func, || {
+        assert!(consume(key)?);
+        assert!(!consume(key)?);
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_rate_limit_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::RATE_LIMIT_MIN_PROTOCOL - 1)?;
+    let func = Symbol::try_from_small_str("limit").unwrap();
+    let key = host.bytes_new_from_slice(&[1, 2, 3])?.to_val();
+    host.with_test_contract_frame(contract_id, func, || {
+        assert!(HostError::result_matches_err(
+            host.rate_limit_check_and_consume(key, 3_u32.into(), 1_u32.into(), 2_u32.into()),
+            (ScErrorType::Context, ScErrorCode::InvalidAction)
+        ));
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_put_contract_data_locked() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::DATA_LOCKS_MIN_PROTOCOL;
+        li.sequence_number = 100;
+        li.max_entry_expiration = 1000;
+    })?;
+    let func = Symbol::try_from_small_str("lock").unwrap();
+    let k = Val::from(1_u32);
+    let other_k = Val::from(2_u32);
+    let locked = (ScErrorType::Storage, ScErrorCode::InvalidAction);
+
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        // The unlock ledger has to be in the future, but not too far.
+        for unlock_ledger in [100_u32, 1100] {
+            assert!(HostError::result_matches_err(
+                host.put_contract_data_locked(k, k, StorageType::Persistent, unlock_ledger.into()),
+                (ScErrorType::Value, ScErrorCode::InvalidInput)
+            ));
+        }
+        host.put_contract_data_locked(k, k, StorageType::Persistent, 110_u32.into())?;
+        assert!(HostError::result_matches_err(
+            host.put_contract_data(k, other_k, StorageType::Persistent),
+            locked
+        ));
+        assert!(!bool::from(host.can_put_contract_data(
+            k,
+            other_k,
+            StorageType::Persistent
+        )?));
+        // Locks are specific to the key and the storage type.
+        host.put_contract_data(other_k, k, StorageType::Persistent)?;
+        host.put_contract_data(k, k, StorageType::Temporary)?;
+        // The locks are kept outside of the instance storage.
+        let locks_key = Symbol::try_from_small_str("DataLocks")?.to_val();
+        host.put_contract_data(locks_key, k, StorageType::Instance)?;
+        host.del_contract_data(locks_key, StorageType::Instance)?;
+        Ok(().into())
+    })?;
+
+    // The lock persists across invocations until the unlock ledger.
+    host.with_mut_ledger_info(|li| li.sequence_number = 109)?;
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        assert!(HostError::result_matches_err(
+            host.del_contract_data(k, StorageType::Persistent),
+            locked
+        ));
+        Ok(().into())
+    })?;
+    host.with_mut_ledger_info(|li| li.sequence_number = 110)?;
+    host.with_test_contract_frame(contract_id, func, || {
+        host.put_contract_data(k, other_k, StorageType::Persistent)?;
+        host.del_contract_data(k, StorageType::Persistent)?;
+        assert!(!bool::from(
+            host.has_contract_data(k, StorageType::Persistent)?
+        ));
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_data_locks_are_bounded_and_dropped() -> Result<(), HostError> {
+    use crate::host::data_locks::{DATA_LOCKS_KEY, MAX_DATA_LOCKS_PER_CONTRACT};
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::DATA_LOCKS_MIN_PROTOCOL;
+        li.sequence_number = 100;
+        li.max_entry_expiration = 1000;
+    })?;
+    let func = Symbol::try_from_small_str("lock").unwrap();
+    let num_locks = || {
+        host.visit_host_instance_entry(&DATA_LOCKS_KEY, |locks| {
+            Ok(match locks {
+                Some(ScVal::Map(Some(m))) => m.len(),
+                _ => 0,
+            })
+        })
+    };
+    let max = MAX_DATA_LOCKS_PER_CONTRACT as u32;
+
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        // The first lock expires before the others.
+        host.put_contract_data_locked(
+            0_u32.into(),
+            0_u32.into(),
+            StorageType::Persistent,
+            105_u32.into(),
+        )?;
+        for k in 1..max {
+            host.put_contract_data_locked(
+                k.into(),
+                k.into(),
+                StorageType::Persistent,
+                200_u32.into(),
+            )?;
+        }
+        assert_eq!(num_locks()?, MAX_DATA_LOCKS_PER_CONTRACT);
+        assert!(HostError::result_matches_err(
+            host.put_contract_data_locked(
+                max.into(),
+                max.into(),
+                StorageType::Persistent,
+                200_u32.into()
+            ),
+            (ScErrorType::Storage, ScErrorCode::ExceededLimit)
+        ));
+        Ok(().into())
+    })?;
+
+    host.with_mut_ledger_info(|li| li.sequence_number = 105)?;
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        // Deleting an unlocked entry drops its lock.
+        host.del_contract_data(0_u32.into(), StorageType::Persistent)?;
+        assert_eq!(num_locks()?, MAX_DATA_LOCKS_PER_CONTRACT - 1);
+        host.put_contract_data_locked(
+            max.into(),
+            max.into(),
+            StorageType::Persistent,
+            200_u32.into(),
+        )?;
+        assert_eq!(num_locks()?, MAX_DATA_LOCKS_PER_CONTRACT);
+        Ok(().into())
+    })?;
+
+    host.with_mut_ledger_info(|li| li.sequence_number = 200)?;
+    host.with_test_contract_frame(contract_id, func, || {
+        // Expired locks are dropped when a new lock is put.
+        host.put_contract_data_locked(
+            0_u32.into(),
+            0_u32.into(),
+            StorageType::Persistent,
+            300_u32.into(),
+        )?;
+        assert_eq!(num_locks()?, 1);
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn put_contract_data_locked_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::DATA_LOCKS_MIN_PROTOCOL - 1;
+        li.sequence_number = 100;
+        li.max_entry_expiration = 1000;
+    })?;
+    let func = Symbol::try_from_small_str("lock").unwrap();
+    let k = Val::from(1_u32);
+    host.with_test_contract_frame(contract_id, func, || {
+        assert!(HostError::result_matches_err(
+            host.put_contract_data_locked(k, k, StorageType::Persistent, 110_u32.into()),
+            (ScErrorType::Context, ScErrorCode::InvalidAction)
+        ));
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_freed_contract_data_bytes() -> Result<(), HostError> {
+    let budget = Budget::default();
+    let key = |k: i32| {
+        Rc::new(LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract(Hash([0; 32])),
+            key: ScVal::I32(k),
+            durability: ContractDataDurability::Persistent,
+        }))
+    };
+    let entry = |k: i32, v: ScVal| {
+        Rc::new(LedgerEntry {
+            last_modified_ledger_seq: 0,
+            data: LedgerEntryData::ContractData(XdrContractDataEntry {
+                contract: ScAddress::Contract(Hash([0; 32])),
+                key: ScVal::I32(k),
+                val: v,
+                durability: ContractDataDurability::Persistent,
+                ext: ExtensionPoint::V0,
+            }),
+            ext: LedgerEntryExt::V0,
+        })
+    };
+    let large = ScVal::Bytes(vec![0; 200].try_into()?);
+    let large_size = entry(1, large.clone()).to_xdr()?.len() as u64;
+
+    let mut footprint = Footprint::default();
+    let mut map = MeteredOrdMap::new();
+    for k in 1..=4 {
+        footprint.record_access(&key(k), AccessType::ReadWrite, &budget)?;
+    }
+    map = map.insert(key(1), Some((entry(1, large.clone()), Some(100))), &budget)?;
+    map = map.insert(key(2), Some((entry(2, ScVal::I32(2)), Some(100))), &budget)?;
+    map = map.insert(key(3), None, &budget)?;
+    map = map.insert(key(4), None, &budget)?;
+    let mut storage = Storage::with_enforcing_footprint_and_map(footprint, map);
+    assert_eq!(storage.freed_contract_data_bytes(&budget)?, 0);
+
+    // Deleted entries are counted with their initial size, even if they've
+    // been modified before the deletion.
+    storage.put(&key(1), &entry(1, ScVal::I32(1)), Some(100), &budget)?;
+    storage.del(&key(1), &budget)?;
+    assert_eq!(storage.freed_contract_data_bytes(&budget)?, large_size);
+    // Entries that are deleted and then created again aren't.
+    storage.del(&key(2), &budget)?;
+    storage.put(&key(2), &entry(2, ScVal::I32(3)), Some(100), &budget)?;
+    // Neither are the entries that didn't exist initially.
+    storage.put(&key(3), &entry(3, ScVal::I32(3)), Some(100), &budget)?;
+    storage.del(&key(3), &budget)?;
+    storage.del(&key(4), &budget)?;
+    assert_eq!(storage.freed_contract_data_bytes(&budget)?, large_size);
+
+    // In recording mode the initial entries come from the snapshot.
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    let func = Symbol::try_from_small_str("del").unwrap();
+    let k = Val::from(1_u32);
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        host.put_contract_data(k, Val::from(2_u32), StorageType::Persistent)?;
+        host.del_contract_data(k, StorageType::Persistent)?;
+        Ok(().into())
+    })?;
+    assert_eq!(host.freed_contract_data_bytes()?, 0);
+    Ok(())
+}
+
+#[test]
+fn test_can_put_contract_data() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::CAN_PUT_CONTRACT_DATA_MIN_PROTOCOL
+    })?;
+    let func = Symbol::try_from_small_str("put").unwrap();
+    let k = Val::from(1_u32);
+    let small = Val::from(2_u32);
+    let large = host.bytes_new_from_slice(&[0; 200])?.to_val();
+    let can_put = |v: Val, t: StorageType| -> Result<bool, HostError> {
+        Ok(host.can_put_contract_data(k, v, t)?.into())
+    };
+
+    host.with_test_contract_frame(contract_id.clone(), func, || {
+        for t in [
+            StorageType::Temporary,
+            StorageType::Persistent,
+            StorageType::Instance,
+        ] {
+            assert!(can_put(small, t)?);
+            assert!(can_put(large, t)?);
+            // Nothing is written.
+            assert!(!bool::from(host.has_contract_data(k, t)?));
+        }
+        host.set_xdr_read_limits(XdrReadLimits {
+            depth: DEFAULT_XDR_RW_DEPTH_LIMIT,
+            len: 128,
+        })?;
+        assert!(can_put(small, StorageType::Persistent)?);
+        assert!(!can_put(large, StorageType::Persistent)?);
+        assert!(!can_put(large, StorageType::Instance)?);
+        host.set_xdr_read_limits(XdrReadLimits::default())?;
+        Ok(().into())
+    })?;
+
+    // Only the keys in the read-write footprint can be written when the
+    // footprint is enforced.
+    let persistent_key = host.storage_key_for_address(
+        ScAddress::Contract(contract_id.clone()),
+        ScVal::U32(1),
+        ContractDataDurability::Persistent,
+    )?;
+    host.with_mut_storage(|s| {
+        s.mode = FootprintMode::Enforcing;
+        s.footprint
+            .record_access(&persistent_key, AccessType::ReadWrite, host.as_budget())
+    })?;
+    host.with_test_contract_frame(contract_id, func, || {
+        assert!(can_put(small, StorageType::Persistent)?);
+        assert!(!can_put(small, StorageType::Temporary)?);
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_can_put_contract_data_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::CAN_PUT_CONTRACT_DATA_MIN_PROTOCOL - 1
+    })?;
+    let func = Symbol::try_from_small_str("put").unwrap();
+    host.with_test_contract_frame(contract_id, func, || {
+        assert!(HostError::result_matches_err(
+            host.can_put_contract_data(Val::from(1_u32), Val::from(2_u32), StorageType::Persistent),
+            (ScErrorType::Context, ScErrorCode::InvalidAction)
+        ));
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_storage_map() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    let func = Symbol::try_from_small_str("map").unwrap();
+
+    host.with_test_contract_frame(contract_id, func, || {
+        let map: StorageMap<u32, i128> = StorageMap::new(
+            &host,
+            "Amount",
+            StorageType::Persistent,
+            Some(BumpPolicy {
+                low_expiration_watermark: 100,
+                high_expiration_watermark: 200,
+            }),
+        );
+        assert!(!map.has(&1)?);
+        assert_eq!(map.get(&1)?, None);
+        map.set(&1, &-5)?;
+        map.set(&2, &7)?;
+        assert_eq!(map.get(&1)?, Some(-5));
+        assert_eq!(map.get(&2)?, Some(7));
+
+        // The entries are keyed by `[prefix, key]`.
+        let tag = Symbol::try_from_val(&host, &"Amount")?;
+        let raw_key = host.vec_new_from_slice(&[tag.to_val(), 1_u32.into()])?;
+        let raw_val: i128 = host
+            .get_contract_data(raw_key.into(), StorageType::Persistent)?
+            .try_into_val(&host)?;
+        assert_eq!(raw_val, -5);
+        // Other storage types are not affected.
+        assert!(!bool::from(
+            host.has_contract_data(raw_key.into(), StorageType::Temporary)?
+        ));
+
+        map.remove(&1)?;
+        assert!(!map.has(&1)?);
+        assert!(map.has(&2)?);
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_native_storage_migrations() -> Result<(), HostError> {
+    use crate::native_contract::migrations::{Migrations, SCHEMA_VERSION_KEY};
+
+    // Every migration appends its number to the `Steps` instance entry.
+    fn log_step(e: &Host, step: u32) -> Result<(), HostError> {
+        let key = Symbol::try_from_small_str("Steps")?.to_val();
+        let mut steps: u32 = 0;
+        if bool::from(e.has_contract_data(key, StorageType::Instance)?) {
+            steps = e
+                .get_contract_data(key, StorageType::Instance)?
+                .try_into_val(e)?;
+        }
+        e.put_contract_data(key, (steps * 10 + step).into(), StorageType::Instance)?;
+        Ok(())
+    }
+    fn step1(e: &Host) -> Result<(), HostError> {
+        log_step(e, 1)
+    }
+    fn step2(e: &Host) -> Result<(), HostError> {
+        log_step(e, 2)
+    }
+    fn step3(e: &Host) -> Result<(), HostError> {
+        log_step(e, 3)
+    }
+    const MIGRATIONS: Migrations = Migrations::new(&[step1, step2, step3]);
+    const OLD_MIGRATIONS: Migrations = Migrations::new(&[step1]);
+
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    let func = Symbol::try_from_small_str("migrate").unwrap();
+    let read_u32 = |key: Val| -> Result<u32, HostError> {
+        host.get_contract_data(key, StorageType::Instance)?
+            .try_into_val(&host)
+    };
+    let version_key = Symbol::try_from_val(&host, &SCHEMA_VERSION_KEY)?.to_val();
+    let steps_key = Symbol::try_from_small_str("Steps")?.to_val();
+
+    host.with_test_contract_frame(contract_id, func, || {
+        // Nothing happens to contracts that haven't been initialized.
+        MIGRATIONS.run(&host, |_| Ok(false))?;
+        assert!(!bool::from(
+            host.has_contract_data(version_key, StorageType::Instance)?
+        ));
+
+        // A contract initialized at version 1 only runs the later migrations,
+        // and only once.
+        OLD_MIGRATIONS.init(&host)?;
+        assert_eq!(read_u32(version_key)?, 1);
+        MIGRATIONS.run(&host, |_| Ok(true))?;
+        assert_eq!(read_u32(steps_key)?, 23);
+        assert_eq!(read_u32(version_key)?, 3);
+        MIGRATIONS.run(&host, |_| Ok(true))?;
+        assert_eq!(read_u32(steps_key)?, 23);
+
+        // Storage from the future can't be handled.
+        assert!(HostError::result_matches_err(
+            OLD_MIGRATIONS.run(&host, |_| Ok(true)),
+            crate::native_contract::contract_error::ContractError::InternalError
+        ));
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_contract_data_entries_export() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_a = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_b = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let put = |contract: AddressObject, func: &str, key: &str, val: u64| {
+        host.call(
+            contract,
+            Symbol::try_from_val(&host, &func).unwrap(),
+            host_vec![&host, Symbol::try_from_val(&host, &key).unwrap(), val].into(),
+        )
+        .unwrap();
+    };
+    put(contract_a, "put_persistent", "a", 1);
+    put(contract_a, "put_temporary", "b", 2);
+    put(contract_b, "put_persistent", "c", 3);
+    let contract_a_id = host.contract_id_from_address(contract_a)?;
+    let budget = host.budget_cloned();
+    let (storage, _) = host.try_finish()?;
+
+    let entries = storage.contract_data_entries(&contract_a_id, &budget)?;
+    assert_eq!(entries.len(), 3);
+    let find = |key: ScVal| entries.iter().find(|e| e.key == key).unwrap();
+    assert!(matches!(
+        find(ScVal::LedgerKeyContractInstance).val,
+        ScVal::ContractInstance(_)
+    ));
+    let a = find(ScVal::Symbol(ScSymbol("a".try_into().unwrap())));
+    assert_eq!(a.durability, ContractDataDurability::Persistent);
+    assert_eq!(a.val, ScVal::U64(1));
+    assert!(a.expiration_ledger.is_some());
+    let b = find(ScVal::Symbol(ScSymbol("b".try_into().unwrap())));
+    assert_eq!(b.durability, ContractDataDurability::Temporary);
+    assert_eq!(b.val, ScVal::U64(2));
+    Ok(())
+}
+
+#[test]
+fn test_storage_access_stats_per_contract() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_a = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_b = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_c = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    host.enable_storage_access_stats()?;
+    let call = |contract: AddressObject, func: &str, args: VecObject| {
+        host.call(contract, Symbol::try_from_val(&host, &func).unwrap(), args)
+            .unwrap();
+    };
+    let key = Symbol::try_from_small_str("key").unwrap();
+    call(
+        contract_a,
+        "put_persistent",
+        host_vec![&host, key, 1_u64].into(),
+    );
+    call(
+        contract_b,
+        "put_persistent",
+        host_vec![&host, key, 2_u64].into(),
+    );
+    call(
+        contract_b,
+        "put_persistent",
+        host_vec![&host, key, 3_u64].into(),
+    );
+    call(contract_b, "get_persistent", host_vec![&host, key].into());
+    // Contracts that only use their instance storage aren't charged for
+    // ledger accesses they haven't made.
+    call(contract_c, "has_instance", host_vec![&host, key].into());
+
+    let stats = host.execution_stats()?.contract_storage_access;
+    let a = stats[&host.contract_id_from_address(contract_a)?];
+    let b = stats[&host.contract_id_from_address(contract_b)?];
+    assert!(!stats.contains_key(&host.contract_id_from_address(contract_c)?));
+    assert_eq!(a.writes, 1);
+    assert_eq!(b.writes, 2);
+    assert!(a.write_bytes > 0);
+    assert!(b.write_bytes > a.write_bytes);
+    assert!(b.reads > a.reads);
+    assert!(b.read_bytes > a.read_bytes);
+
+    // The stats are available once the host is finished, and cover a part
+    // of the storage totals (the rest being the instance and code reads of
+    // the host when calling the contracts).
+    let finished = host.try_finish_owned()?;
+    assert_eq!(finished.execution_stats.contract_storage_access, stats);
+    Ok(())
+}
+
+// A snapshot without entries that counts how often it has been read.
+struct CountingSnapshotSource(Cell<u32>);
+
+impl SnapshotSource for CountingSnapshotSource {
+    fn get(&self, _key: &Rc<LedgerKey>) -> Result<(Rc<LedgerEntry>, Option<u32>), HostError> {
+        Err((ScErrorType::Storage, ScErrorCode::MissingValue).into())
+    }
+
+    fn has(&self, _key: &Rc<LedgerKey>) -> Result<bool, HostError> {
+        self.0.set(self.0.get() + 1);
+        Ok(false)
+    }
+}
+
+#[test]
+fn test_snapshot_cache_shared_between_storages() -> Result<(), HostError> {
+    let budget = Budget::default();
+    budget.reset_unlimited()?;
+    let key = |k: u32| {
+        Rc::new(LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract(Hash([0; 32])),
+            key: ScVal::U32(k),
+            durability: ContractDataDurability::Persistent,
+        }))
+    };
+    let src = Rc::new(CountingSnapshotSource(Cell::new(0)));
+    let cache = SnapshotCache::new(2);
+
+    let mut storage = Storage::with_recording_footprint_and_cache(src.clone(), cache.clone());
+    assert!(!storage.has(&key(1), &budget)?);
+    assert!(!storage.has(&key(2), &budget)?);
+    // Served from the storage map, the cache is not consulted again.
+    assert!(!storage.has(&key(1), &budget)?);
+    assert_eq!(src.0.get(), 2);
+    assert_eq!(cache.len(), 2);
+
+    // A second storage reads the first two keys from the cache and evicts
+    // the least recently used one to make room for the third key.
+    let mut storage = Storage::with_recording_footprint_and_cache(src.clone(), cache.clone());
+    assert!(!storage.has(&key(1), &budget)?);
+    assert!(!storage.has(&key(2), &budget)?);
+    assert!(!storage.has(&key(3), &budget)?);
+    assert_eq!(src.0.get(), 3);
+    assert_eq!(cache.len(), 2);
+    assert_eq!(
+        cache.metrics(),
+        SnapshotCacheMetrics {
+            hits: 2,
+            misses: 3,
+            evictions: 1,
+        }
+    );
+
+    let mut storage = Storage::with_recording_footprint_and_cache(src.clone(), cache.clone());
+    assert!(!storage.has(&key(1), &budget)?);
+    assert!(!storage.has(&key(3), &budget)?);
+    assert_eq!(src.0.get(), 4);
+    assert_eq!(cache.metrics().misses, 4);
+
+    cache.reset_metrics();
+    cache.clear();
+    assert!(cache.is_empty());
+    assert_eq!(cache.metrics(), SnapshotCacheMetrics::default());
+    Ok(())
+}
+
+#[test]
+fn test_written_entries_hash() -> Result<(), HostError> {
+    let budget = Budget::default();
+    let key = |k: i32| {
+        Rc::new(LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract(Hash([0; 32])),
+            key: ScVal::I32(k),
+            durability: ContractDataDurability::Persistent,
+        }))
+    };
+    let entry = |k: i32, v: i32| {
+        Rc::new(LedgerEntry {
+            last_modified_ledger_seq: 0,
+            data: LedgerEntryData::ContractData(XdrContractDataEntry {
+                contract: ScAddress::Contract(Hash([0; 32])),
+                key: ScVal::I32(k),
+                val: ScVal::I32(v),
+                durability: ContractDataDurability::Persistent,
+                ext: ExtensionPoint::V0,
+            }),
+            ext: LedgerEntryExt::V0,
+        })
+    };
+    let new_storage =
+        || Storage::with_recording_footprint(Rc::new(CountingSnapshotSource(Cell::new(0))));
+
+    let mut s1 = new_storage();
+    s1.put(&key(1), &entry(1, 10), Some(100), &budget)?;
+    s1.put(&key(2), &entry(2, 20), Some(100), &budget)?;
+    s1.del(&key(3), &budget)?;
+    // The order of writes and read-only accesses doesn't affect the hash.
+    let mut s2 = new_storage();
+    s2.del(&key(3), &budget)?;
+    s2.has(&key(4), &budget)?;
+    s2.put(&key(2), &entry(2, 20), Some(100), &budget)?;
+    s2.put(&key(1), &entry(1, 10), Some(100), &budget)?;
+    assert_eq!(
+        s1.written_entries_hash(&budget)?,
+        s2.written_entries_hash(&budget)?
+    );
+
+    // Values and expiration ledgers do.
+    let hash = s1.written_entries_hash(&budget)?;
+    s1.put(&key(1), &entry(1, 11), Some(100), &budget)?;
+    assert_ne!(s1.written_entries_hash(&budget)?, hash);
+    s1.put(&key(1), &entry(1, 10), Some(101), &budget)?;
+    assert_ne!(s1.written_entries_hash(&budget)?, hash);
+    s1.put(&key(1), &entry(1, 10), Some(100), &budget)?;
+    assert_eq!(s1.written_entries_hash(&budget)?, hash);
+
+    // Keys with read-write access that haven't been written don't either.
+    let mut footprint = Footprint::default();
+    let mut map = MeteredOrdMap::new();
+    for k in 1..=5 {
+        footprint.record_access(&key(k), AccessType::ReadWrite, &budget)?;
+        map = map.insert(key(k), Some((entry(k, k), Some(100))), &budget)?;
+    }
+    let mut s3 = Storage::with_enforcing_footprint_and_map(footprint, map);
+    s3.put(&key(1), &entry(1, 10), Some(100), &budget)?;
+    s3.put(&key(2), &entry(2, 20), Some(100), &budget)?;
+    s3.del(&key(3), &budget)?;
+    assert_eq!(s3.written_entries_hash(&budget)?, hash);
+    Ok(())
+}
+
+#[test]
+fn test_get_written_entries_hash_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::WRITTEN_ENTRIES_HASH_MIN_PROTOCOL - 1
+    })?;
+    assert!(HostError::result_matches_err(
+        host.get_written_entries_hash(),
+        (ScErrorType::Context, ScErrorCode::InvalidAction)
+    ));
+
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::WRITTEN_ENTRIES_HASH_MIN_PROTOCOL
+    })?;
+    let hash = host.get_written_entries_hash()?;
+    assert_eq!(
+        host.hash_from_bytesobj_input("hash", hash)?,
+        host.written_entries_hash()?
+    );
+    Ok(())
+}
+
+#[test]
+fn test_instance_storage_size_and_entry_count() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::INSTANCE_STORAGE_STATS_MIN_PROTOCOL
+    })?;
+    let func = Symbol::try_from_small_str("size").unwrap();
+
+    host.with_test_contract_frame(contract_id, func, || {
+        // An empty map only encodes its length.
+        assert_eq!(u32::from(host.instance_storage_entry_count()?), 0);
+        assert_eq!(u32::from(host.instance_storage_size_bytes()?), 4);
+
+        host.put_contract_data(1_u32.into(), 2_u32.into(), StorageType::Instance)?;
+        host.put_contract_data(3_u32.into(), 4_u32.into(), StorageType::Instance)?;
+        host.put_contract_data(1_u32.into(), 5_u32.into(), StorageType::Instance)?;
+        assert_eq!(u32::from(host.instance_storage_entry_count()?), 2);
+        // Every `U32` key and value takes 8 bytes.
+        assert_eq!(u32::from(host.instance_storage_size_bytes()?), 4 + 2 * 16);
+
+        host.del_contract_data(3_u32.into(), StorageType::Instance)?;
+        assert_eq!(u32::from(host.instance_storage_entry_count()?), 1);
+        assert_eq!(u32::from(host.instance_storage_size_bytes()?), 4 + 16);
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_instance_storage_size_and_entry_count_are_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::INSTANCE_STORAGE_STATS_MIN_PROTOCOL - 1
+    })?;
+    let func = Symbol::try_from_small_str("size").unwrap();
+    host.with_test_contract_frame(contract_id, func, || {
+        let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+        assert!(HostError::result_matches_err(
+            host.instance_storage_entry_count(),
+            code
+        ));
+        assert!(HostError::result_matches_err(
+            host.instance_storage_size_bytes(),
+            code
+        ));
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_footprint_violations_are_recorded() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    // The recorded footprint only covers the contract instance and code.
+    host.try_borrow_storage_mut()?.mode = FootprintMode::Enforcing;
+    assert!(host.footprint_violations()?.is_empty());
+
+    let func = Symbol::try_from_small_str("violate").unwrap();
+    let res = host.with_test_contract_frame(contract_id.clone(), func, || {
+        host.put_contract_data(1_u32.into(), 2_u32.into(), StorageType::Temporary)?;
+        Ok(().into())
+    });
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Storage, ScErrorCode::ExceededLimit)
+    ));
+    let res = host.with_test_contract_frame(contract_id.clone(), func, || {
+        host.has_contract_data(3_u32.into(), StorageType::Persistent)?;
+        Ok(().into())
+    });
+    assert!(HostError::result_matches_err(
+        res,
+        (ScErrorType::Storage, ScErrorCode::ExceededLimit)
+    ));
+
+    let data_key = |key: u32, durability: ContractDataDurability| {
+        LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract(contract_id.clone()),
+            key: ScVal::U32(key),
+            durability,
+        })
+    };
+    assert_eq!(
+        host.footprint_violations()?,
+        vec![
+            (
+                data_key(1, ContractDataDurability::Temporary),
+                AccessType::ReadOnly
+            ),
+            (
+                data_key(3, ContractDataDurability::Persistent),
+                AccessType::ReadOnly
+            ),
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn test_record_footprint_with_loader() -> Result<(), HostError> {
+    let key = |i: u32| {
+        Rc::new(LedgerKey::ContractData(LedgerKeyContractData {
+            contract: ScAddress::Contract(Hash([0; 32])),
+            key: ScVal::U32(i),
+            durability: ContractDataDurability::Persistent,
+        }))
+    };
+    // Entry `i` holds the index of the next entry to read.
+    let entry = |i: u32, next: u32| LedgerEntry {
+        last_modified_ledger_seq: 0,
+        data: LedgerEntryData::ContractData(XdrContractDataEntry {
+            ext: ExtensionPoint::V0,
+            contract: ScAddress::Contract(Hash([0; 32])),
+            key: ScVal::U32(i),
+            durability: ContractDataDurability::Persistent,
+            val: ScVal::U32(next),
+        }),
+        ext: LedgerEntryExt::V0,
+    };
+    let loads = Cell::new(0);
+    let loader = |k: &LedgerKey| {
+        loads.set(loads.get() + 1);
+        let LedgerKey::ContractData(LedgerKeyContractData {
+            key: ScVal::U32(i), ..
+        }) = k
+        else {
+            unreachable!()
+        };
+        // Entry 2 doesn't exist.
+        Ok((*i < 2).then(|| (entry(*i, i + 1), Some(1000))))
+    };
+    // Follows the chain of entries until a missing one and writes it.
+    let invoke = |host: &Host| {
+        host.with_mut_storage(|s| {
+            let mut i = 0;
+            while let Some(le) = s.try_get(&key(i), host.as_budget())? {
+                let LedgerEntryData::ContractData(XdrContractDataEntry {
+                    val: ScVal::U32(next),
+                    ..
+                }) = &le.data
+                else {
+                    unreachable!()
+                };
+                i = *next;
+            }
+            s.put(&key(i), &Rc::new(entry(i, 0)), Some(1000), host.as_budget())?;
+            Ok(i)
+        })
+    };
+
+    let recorded = crate::e2e_invoke::record_footprint_with_loader(10, loader, invoke)?;
+    assert_eq!(recorded.result?, 2);
+    assert_eq!(recorded.attempts, 4);
+    assert_eq!(loads.get(), 3);
+    assert_eq!(
+        recorded.footprint.read_only.to_vec(),
+        vec![key(0).as_ref().clone(), key(1).as_ref().clone()]
+    );
+    assert_eq!(
+        recorded.footprint.read_write.to_vec(),
+        vec![key(2).as_ref().clone()]
+    );
+
+    // The loop gives up if the invocation keeps accessing new keys.
+    let res = crate::e2e_invoke::record_footprint_with_loader(2, loader, invoke);
+    assert!(HostError::result_matches_err(
+        res.map(|_| ()),
+        (ScErrorType::Context, ScErrorCode::ExceededLimit)
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_memo_is_scoped_to_contract_and_invocation() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_a = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_a = host.contract_id_from_address(contract_a)?;
+    let contract_b = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_b = host.contract_id_from_address(contract_b)?;
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::MEMO_FNS_MIN_PROTOCOL)?;
+    let func = Symbol::try_from_small_str("memo").unwrap();
+    let key = Symbol::try_from_small_str("key").unwrap().to_val();
+    let other_key = Symbol::try_from_small_str("other").unwrap().to_val();
+    let value = host.bytes_new_from_slice(&[1, 2, 3])?.to_val();
+    let footprint_len = host.try_borrow_storage()?.footprint.0.len();
+
+    host.with_test_contract_frame(contract_a.clone(), func, || {
+        assert!(host.memo_get(key)?.is_void());
+        host.memo_put(key, value)?;
+        assert_eq!(host.memo_get(key)?.get_payload(), value.get_payload());
+
+        // Other contracts can't see the memo of the contract.
+        host.with_test_contract_frame(contract_b.clone(), func, || {
+            assert!(host.memo_get(key)?.is_void());
+            Ok(().into())
+        })?;
+        // The memo is shared by the nested calls of the same contract.
+        host.with_test_contract_frame(contract_a.clone(), func, || {
+            assert_eq!(host.memo_get(key)?.get_payload(), value.get_payload());
+            Ok(().into())
+        })?;
+        // The entries put by a failed call are rolled back.
+        let res = host.with_test_contract_frame(contract_a.clone(), func, || {
+            host.memo_put(key, Val::from_u32(1).to_val())?;
+            host.memo_put(other_key, value)?;
+            host.memo_put(key, Val::from_u32(2).to_val())?;
+            Err((ScErrorType::Context, ScErrorCode::InvalidAction).into())
+        });
+        assert!(res.is_err());
+        assert_eq!(host.memo_get(key)?.get_payload(), value.get_payload());
+        assert!(host.memo_get(other_key)?.is_void());
+        Ok(().into())
+    })?;
+
+    // The memo is dropped at the end of the invocation and doesn't touch the
+    // ledger.
+    host.with_test_contract_frame(contract_a, func, || {
+        assert!(host.memo_get(key)?.is_void());
+        Ok(().into())
+    })?;
+    assert_eq!(host.try_borrow_storage()?.footprint.0.len(), footprint_len);
+    Ok(())
+}
+
+#[test]
+fn test_memo_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::MEMO_FNS_MIN_PROTOCOL - 1)?;
+    let func = Symbol::try_from_small_str("memo").unwrap();
+    let key = Symbol::try_from_small_str("key").unwrap().to_val();
+    host.with_test_contract_frame(contract_id, func, || {
+        let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+        assert!(HostError::result_matches_err(
+            host.memo_put(key, Val::from_u32(1).to_val()),
+            code
+        ));
+        assert!(HostError::result_matches_err(host.memo_get(key), code));
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_acl() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::ACL_FNS_MIN_PROTOCOL)?;
+    let func = Symbol::try_from_small_str("acl").unwrap();
+    let k = Symbol::try_from_small_str("allowed")?.to_val();
+    let addresses = [3_u8, 1, 2]
+        .iter()
+        .map(|i| host.add_host_object(ScAddress::Contract(Hash([*i; 32]))))
+        .collect::<Result<Vec<AddressObject>, HostError>>()?;
+
+    host.with_test_contract_frame(contract_id, func, || {
+        assert!(!bool::from(host.acl_contains(
+            k,
+            StorageType::Persistent,
+            addresses[0]
+        )?));
+        for a in addresses.iter() {
+            assert!(bool::from(host.acl_add(k, StorageType::Persistent, *a)?));
+        }
+        assert!(!bool::from(host.acl_add(
+            k,
+            StorageType::Persistent,
+            addresses[1]
+        )?));
+        // The members are kept in a single page, next to the index.
+        let tag = Symbol::try_from_small_str("Acl")?.to_val();
+        let index_key = host.vec_new_from_slice(&[tag, k])?.to_val();
+        let page_key = host
+            .vec_new_from_slice(&[tag, k, U32Val::from(0).to_val()])?
+            .to_val();
+        let has_entry = |key: Val| -> Result<bool, HostError> {
+            Ok(bool::from(
+                host.has_contract_data(key, StorageType::Persistent)?,
+            ))
+        };
+        assert!(has_entry(index_key)?);
+        let page = host.get_contract_data(page_key, StorageType::Persistent)?;
+        assert_eq!(u32::from(host.vec_len(VecObject::try_from(page)?)?), 3);
+        assert!(!bool::from(
+            host.has_contract_data(k, StorageType::Persistent)?
+        ));
+        for a in addresses.iter() {
+            assert!(bool::from(host.acl_contains(
+                k,
+                StorageType::Persistent,
+                *a
+            )?));
+        }
+        // Lists are specific to the storage type.
+        assert!(!bool::from(host.acl_contains(
+            k,
+            StorageType::Temporary,
+            addresses[0]
+        )?));
+
+        assert!(bool::from(host.acl_remove(
+            k,
+            StorageType::Persistent,
+            addresses[2]
+        )?));
+        assert!(!bool::from(host.acl_remove(
+            k,
+            StorageType::Persistent,
+            addresses[2]
+        )?));
+        assert!(!bool::from(host.acl_contains(
+            k,
+            StorageType::Persistent,
+            addresses[2]
+        )?));
+        assert!(bool::from(host.acl_contains(
+            k,
+            StorageType::Persistent,
+            addresses[0]
+        )?));
+        // No entries are left behind by the removed members.
+        assert!(bool::from(host.acl_remove(
+            k,
+            StorageType::Persistent,
+            addresses[0]
+        )?));
+        assert!(bool::from(host.acl_remove(
+            k,
+            StorageType::Persistent,
+            addresses[1]
+        )?));
+        assert!(!has_entry(index_key)?);
+        assert!(!has_entry(page_key)?);
+
+        // Plain writes to the key of the list don't affect it.
+        host.put_contract_data(k, k, StorageType::Persistent)?;
+        assert!(bool::from(host.acl_add(
+            k,
+            StorageType::Persistent,
+            addresses[0]
+        )?));
+        assert!(bool::from(host.acl_contains(
+            k,
+            StorageType::Persistent,
+            addresses[0]
+        )?));
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_acl_pages() -> Result<(), HostError> {
+    use crate::host::acl::ACL_PAGE_CAPACITY;
+    let host = Host::test_host_with_recording_footprint();
+    host.as_budget().reset_unlimited()?;
+    host.with_mut_ledger_info(|li| {
+        li.sequence_number = 100;
+        li.min_persistent_entry_expiration = 10;
+        li.max_entry_expiration = 1000;
+    })?;
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::ACL_FNS_MIN_PROTOCOL)?;
+    let func = Symbol::try_from_small_str("acl").unwrap();
+    let k = Symbol::try_from_small_str("allowed")?.to_val();
+    // Enough members for several page splits, added out of order.
+    let num_members = 4 * ACL_PAGE_CAPACITY;
+    let addresses = (0..num_members)
+        .map(|i| {
+            let id = (i * 37 % num_members).to_be_bytes();
+            let mut hash = [0_u8; 32];
+            hash[28..].copy_from_slice(&id);
+            host.add_host_object(ScAddress::Contract(Hash(hash)))
+        })
+        .collect::<Result<Vec<AddressObject>, HostError>>()?;
+    let t = StorageType::Persistent;
+    let tag = Symbol::try_from_small_str("Acl")?.to_val();
+    let index_key = host.vec_new_from_slice(&[tag, k])?.to_val();
+
+    host.with_test_contract_frame(contract_id, func, || {
+        for a in addresses.iter() {
+            assert!(bool::from(host.acl_add(k, t.clone(), *a)?));
+        }
+        for a in addresses.iter() {
+            assert!(bool::from(host.acl_contains(k, t.clone(), *a)?));
+            assert!(!bool::from(host.acl_add(k, t.clone(), *a)?));
+        }
+        let absent = host.add_host_object(ScAddress::Contract(Hash([0xff; 32])))?;
+        assert!(!bool::from(host.acl_contains(k, t.clone(), absent)?));
+
+        // The pages are at most full, and at least half full after splits.
+        let index = VecObject::try_from(host.get_contract_data(index_key, t.clone())?)?;
+        let page_ids = VecObject::try_from(host.vec_get(index, 1_u32.into())?)?;
+        let num_pages = u32::from(host.vec_len(page_ids)?);
+        assert!(num_pages > 1);
+        let mut num_paged = 0;
+        for i in 0..num_pages {
+            let page_id = host.vec_get(page_ids, i.into())?;
+            let page_key = host.vec_new_from_slice(&[tag, k, page_id])?.to_val();
+            let page = host.get_contract_data(page_key, t.clone())?;
+            let len = u32::from(host.vec_len(VecObject::try_from(page)?)?);
+            assert!((ACL_PAGE_CAPACITY / 2..=ACL_PAGE_CAPACITY).contains(&len));
+            num_paged += len;
+
+            // The entries are bumped as far as the network allows.
+            let key =
+                host.contract_data_key_from_rawval(page_key, ContractDataDurability::Persistent)?;
+            let (_, expiration) = host
+                .try_borrow_storage_mut()?
+                .get_with_expiration(&key, host.as_budget())?;
+            assert_eq!(expiration, Some(host.max_expiration_ledger()?));
+        }
+        assert_eq!(num_paged, num_members);
+
+        for a in addresses.iter() {
+            assert!(bool::from(host.acl_remove(k, t.clone(), *a)?));
+            assert!(!bool::from(host.acl_contains(k, t.clone(), *a)?));
+        }
+        assert!(!bool::from(host.has_contract_data(index_key, t.clone())?));
+        Ok(().into())
+    })?;
+    Ok(())
+}
+
+#[test]
+fn test_acl_is_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    let contract_address = host.register_test_contract_wasm(CONTRACT_STORAGE);
+    let contract_id = host.contract_id_from_address(contract_address)?;
+    host.with_mut_ledger_info(|li| li.protocol_version = crate::host::ACL_FNS_MIN_PROTOCOL - 1)?;
+    let func = Symbol::try_from_small_str("acl").unwrap();
+    let k = Symbol::try_from_small_str("allowed")?.to_val();
+    let address = host.add_host_object(ScAddress::Contract(Hash([1; 32])))?;
+
+    host.with_test_contract_frame(contract_id, func, || {
+        let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+        let t = StorageType::Persistent;
+        assert!(HostError::result_matches_err(
+            host.acl_add(k, t.clone(), address),
+            code
+        ));
+        assert!(HostError::result_matches_err(
+            host.acl_remove(k, t.clone(), address),
+            code
+        ));
+        assert!(HostError::result_matches_err(
+            host.acl_contains(k, t, address),
+            code
+        ));
+        Ok(().into())
+    })?;
+    Ok(())
+}
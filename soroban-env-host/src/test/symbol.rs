@@ -1,5 +1,9 @@
-use crate::{Host, HostError};
-use soroban_env_common::{Symbol, TryFromVal};
+use crate::{
+    budget::AsBudget,
+    xdr::{ContractCostType, ScVal},
+    Host, HostError,
+};
+use soroban_env_common::{Env, EnvBase, Symbol, TryFromVal};
 
 #[test]
 fn invalid_chars() -> Result<(), HostError> {
@@ -48,3 +52,51 @@ fn zero_len() -> Result<(), HostError> {
 
     Ok(())
 }
+
+#[test]
+fn equal_symbols_share_object() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::SYMBOL_INTERNING_MIN_PROTOCOL
+    })?;
+
+    let a = host.symbol_new_from_slice("a_long_symbol")?;
+    let b = host.symbol_new_from_slice("another_long_symbol")?;
+    let num_objects = host.try_borrow_objects()?.len();
+
+    assert_eq!(
+        host.symbol_new_from_slice("a_long_symbol")?
+            .to_val()
+            .get_payload(),
+        a.to_val().get_payload()
+    );
+    let from_scval = host.to_host_val(&ScVal::Symbol("another_long_symbol".try_into()?))?;
+    assert_eq!(from_scval.get_payload(), b.to_val().get_payload());
+    assert_ne!(a.to_val().get_payload(), b.to_val().get_payload());
+    assert_eq!(host.try_borrow_objects()?.len(), num_objects);
+
+    // Looking a symbol up compares its bytes with the interned symbols.
+    let tracker = |host: &Host| host.as_budget().get_tracker(ContractCostType::HostMemCmp);
+    let before = tracker(&host)?;
+    host.symbol_new_from_slice("a_long_symbol")?;
+    let after = tracker(&host)?;
+    assert!(after.0 >= before.0 + 3);
+    assert!(after.1.unwrap_or(0) >= before.1.unwrap_or(0) + 3 * 13);
+
+    Ok(())
+}
+
+#[test]
+fn symbols_are_not_interned_before_protocol() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::SYMBOL_INTERNING_MIN_PROTOCOL - 1
+    })?;
+
+    let a = host.symbol_new_from_slice("a_long_symbol")?;
+    let b = host.symbol_new_from_slice("a_long_symbol")?;
+    assert_ne!(a.to_val().get_payload(), b.to_val().get_payload());
+    assert_eq!(host.obj_cmp(a.to_val(), b.to_val())?, 0);
+
+    Ok(())
+}
@@ -14,10 +14,10 @@ use crate::{
             generate_signing_key, new_ledger_entry_from_data, signing_key_to_account_id,
             AccountSigner, HostVec, TestSigner,
         },
-        token::test_token::TestToken,
+        token::{test_token::TestToken, Token, TokenTrait},
     },
     test::util::generate_bytes_array,
-    Host, HostError, LedgerInfo,
+    AssetOrContract, Host, HostError, LedgerInfo, NativeContractHarness, TokenClient,
 };
 use ed25519_dalek::SigningKey;
 use soroban_env_common::{
@@ -30,7 +30,7 @@ use soroban_env_common::{
         LedgerKey, Liabilities, PublicKey, ScErrorCode, ScErrorType, TrustLineEntry,
         TrustLineEntryExt, TrustLineEntryV1, TrustLineEntryV1Ext, TrustLineFlags,
     },
-    EnvBase, Val,
+    AddressObject, Compare, EnvBase, Val,
 };
 use soroban_env_common::{Env, Symbol, TryFromVal, TryIntoVal};
 use stellar_strkey::ed25519;
@@ -316,8 +316,7 @@ impl TokenTest {
 }
 
 fn to_contract_err(e: HostError) -> ContractError {
-    assert!(e.error.is_type(ScErrorType::Contract));
-    num_traits::FromPrimitive::from_u32(e.error.get_code()).unwrap()
+    ContractError::try_from(e.error).unwrap()
 }
 
 #[test]
@@ -442,6 +441,38 @@ fn test_asset12_smart_init() {
     ]);
 }
 
+#[test]
+fn test_token_metadata_without_frames() {
+    let test = TokenTest::setup();
+    let token = test.default_token();
+    let issuer_id = signing_key_to_account_id(&test.issuer_key);
+    let asset = Asset::CreditAlphanum4(AlphaNum4 {
+        asset_code: AssetCode4(test.asset_code),
+        issuer: issuer_id,
+    });
+
+    let by_asset = test
+        .host
+        .token_metadata(AssetOrContract::Asset(asset.clone()))
+        .unwrap();
+    let contract_id = test.host.get_asset_contract_id_hash(asset).unwrap();
+    let by_contract = test
+        .host
+        .token_metadata(AssetOrContract::Contract(contract_id))
+        .unwrap();
+    assert_eq!(by_asset, by_contract);
+    assert_eq!(by_asset.decimals, token.decimals().unwrap());
+    assert_eq!(by_asset.name, token.name().unwrap().to_string());
+    assert_eq!(by_asset.symbol, token.symbol().unwrap().to_string());
+    assert!(test.host.try_borrow_context().unwrap().is_empty());
+
+    // Unknown contracts don't have any metadata.
+    assert!(test
+        .host
+        .token_metadata(AssetOrContract::Contract(Hash([7; 32])))
+        .is_err());
+}
+
 #[test]
 fn test_zero_amounts() {
     let test = TokenTest::setup();
@@ -1317,6 +1348,214 @@ fn test_set_admin() {
     token.mint(&admin, user.address(&test.host), 1).unwrap();
 }
 
+#[test]
+fn test_two_step_admin_transfer() {
+    let test = TokenTest::setup();
+    test.host
+        .with_mut_ledger_info(|li| {
+            li.protocol_version = crate::host::SAC_ADMIN_TRANSFER_MIN_PROTOCOL
+        })
+        .unwrap();
+    let admin = TestSigner::account(&test.issuer_key);
+    let token = test.default_token();
+    let new_admin = TestSigner::account(&test.user_key);
+    let user = TestSigner::account(&test.user_key_2);
+    for u in [&new_admin, &user] {
+        test.create_default_account(u);
+        test.create_default_trustline(u);
+    }
+
+    // There is nothing to accept yet.
+    assert_eq!(
+        to_contract_err(token.accept_admin(&new_admin).err().unwrap()),
+        ContractError::OwnershipTransferError
+    );
+    // Only the admin can propose a new admin.
+    assert_eq!(
+        token
+            .propose_admin(&user, new_admin.address(&test.host), 200)
+            .err()
+            .unwrap()
+            .error,
+        (ScErrorType::Auth, ScErrorCode::InvalidAction).into()
+    );
+    // The proposal can't be already expired.
+    assert_eq!(
+        to_contract_err(
+            token
+                .propose_admin(&admin, new_admin.address(&test.host), 122)
+                .err()
+                .unwrap()
+        ),
+        ContractError::OwnershipTransferError
+    );
+
+    token
+        .propose_admin(&admin, new_admin.address(&test.host), 200)
+        .unwrap();
+    // The admin doesn't change until the proposal is accepted, and only the
+    // proposed admin can accept it.
+    assert_eq!(
+        token.admin().unwrap().to_sc_address().unwrap(),
+        admin.address(&test.host).to_sc_address().unwrap()
+    );
+    assert_eq!(
+        token.accept_admin(&user).err().unwrap().error,
+        (ScErrorType::Auth, ScErrorCode::InvalidAction).into()
+    );
+    token.mint(&admin, user.address(&test.host), 1).unwrap();
+
+    token.accept_admin(&new_admin).unwrap();
+    assert_eq!(
+        token.admin().unwrap().to_sc_address().unwrap(),
+        new_admin.address(&test.host).to_sc_address().unwrap()
+    );
+    assert_eq!(
+        token
+            .mint(&admin, user.address(&test.host), 1)
+            .err()
+            .unwrap()
+            .error,
+        (ScErrorType::Auth, ScErrorCode::InvalidAction).into()
+    );
+    token.mint(&new_admin, user.address(&test.host), 1).unwrap();
+    // The proposal can only be accepted once.
+    assert_eq!(
+        to_contract_err(token.accept_admin(&new_admin).err().unwrap()),
+        ContractError::OwnershipTransferError
+    );
+
+    // Expired proposals can't be accepted.
+    token
+        .propose_admin(&new_admin, admin.address(&test.host), 150)
+        .unwrap();
+    test.host
+        .with_mut_ledger_info(|li| li.sequence_number = 151)
+        .unwrap();
+    assert_eq!(
+        to_contract_err(token.accept_admin(&admin).err().unwrap()),
+        ContractError::OwnershipTransferError
+    );
+
+    // `set_admin` cancels the pending proposal.
+    token
+        .propose_admin(&new_admin, user.address(&test.host), 200)
+        .unwrap();
+    token
+        .set_admin(&new_admin, admin.address(&test.host))
+        .unwrap();
+    assert_eq!(
+        to_contract_err(token.accept_admin(&user).err().unwrap()),
+        ContractError::OwnershipTransferError
+    );
+    assert_eq!(
+        token.admin().unwrap().to_sc_address().unwrap(),
+        admin.address(&test.host).to_sc_address().unwrap()
+    );
+}
+
+#[test]
+fn test_two_step_admin_transfer_is_protocol_gated() {
+    let test = TokenTest::setup();
+    test.host
+        .with_mut_ledger_info(|li| {
+            li.protocol_version = crate::host::SAC_ADMIN_TRANSFER_MIN_PROTOCOL - 1
+        })
+        .unwrap();
+    let admin = TestSigner::account(&test.issuer_key);
+    let token = test.default_token();
+    let new_admin = TestSigner::account(&test.user_key);
+    test.create_default_account(&new_admin);
+    assert_eq!(
+        token
+            .propose_admin(&admin, new_admin.address(&test.host), 200)
+            .err()
+            .unwrap()
+            .error,
+        (ScErrorType::Context, ScErrorCode::InvalidAction).into()
+    );
+    assert_eq!(
+        token.accept_admin(&new_admin).err().unwrap().error,
+        (ScErrorType::Context, ScErrorCode::InvalidAction).into()
+    );
+    // The single-step transfer still works.
+    token
+        .set_admin(&admin, new_admin.address(&test.host))
+        .unwrap();
+    assert_eq!(
+        token.admin().unwrap().to_sc_address().unwrap(),
+        new_admin.address(&test.host).to_sc_address().unwrap()
+    );
+}
+
+#[test]
+fn test_set_paused() {
+    let test = TokenTest::setup();
+    test.host
+        .with_mut_ledger_info(|li| li.protocol_version = crate::host::SAC_PAUSE_MIN_PROTOCOL)
+        .unwrap();
+    let admin = TestSigner::account(&test.issuer_key);
+    let token = test.default_token();
+    let user = TestSigner::account(&test.user_key);
+    let user_2 = TestSigner::account(&test.user_key_2);
+    for u in [&user, &user_2] {
+        test.create_default_account(u);
+        test.create_default_trustline(u);
+    }
+    token.mint(&admin, user.address(&test.host), 100).unwrap();
+    assert!(!token.paused().unwrap());
+
+    // Only the admin can pause the token.
+    assert_eq!(
+        token.set_paused(&user, true).err().unwrap().error,
+        (ScErrorType::Auth, ScErrorCode::InvalidAction).into()
+    );
+    token.set_paused(&admin, true).unwrap();
+    assert!(token.paused().unwrap());
+
+    // Transfers and burns fail while the token is paused...
+    assert_eq!(
+        to_contract_err(
+            token
+                .transfer(&user, user_2.address(&test.host), 1)
+                .err()
+                .unwrap()
+        ),
+        ContractError::ContractPausedError
+    );
+    assert_eq!(
+        to_contract_err(token.burn(&user, 1).err().unwrap()),
+        ContractError::ContractPausedError
+    );
+    // ...but the admin functions are still available.
+    token.mint(&admin, user_2.address(&test.host), 1).unwrap();
+    token
+        .clawback(&admin, user_2.address(&test.host), 1)
+        .unwrap();
+
+    token.set_paused(&admin, false).unwrap();
+    assert!(!token.paused().unwrap());
+    token
+        .transfer(&user, user_2.address(&test.host), 1)
+        .unwrap();
+    assert_eq!(token.balance(user_2.address(&test.host)).unwrap(), 1);
+}
+
+#[test]
+fn test_set_paused_is_protocol_gated() {
+    let test = TokenTest::setup();
+    test.host
+        .with_mut_ledger_info(|li| li.protocol_version = crate::host::SAC_PAUSE_MIN_PROTOCOL - 1)
+        .unwrap();
+    let admin = TestSigner::account(&test.issuer_key);
+    let token = test.default_token();
+    assert_eq!(
+        token.set_paused(&admin, true).err().unwrap().error,
+        (ScErrorType::Context, ScErrorCode::InvalidAction).into()
+    );
+    assert!(!token.paused().unwrap());
+}
+
 #[test]
 fn test_account_spendable_balance() {
     let test = TokenTest::setup();
@@ -2125,6 +2364,65 @@ fn test_classic_account_multisig_auth() {
         .is_type(ScErrorType::Auth));
 }
 
+#[test]
+fn test_classic_account_zero_threshold_requires_signature() {
+    let test = TokenTest::setup();
+
+    let account_id = signing_key_to_account_id(&test.user_key);
+    test.create_account(
+        &account_id,
+        vec![(&test.user_key_2, 1)],
+        100_000_000,
+        1,
+        [1, 0, 0, 0],
+        None,
+        None,
+        0,
+    );
+    let token = TestToken::new_from_asset(&test.host, Asset::Native);
+    let receiver = TestSigner::account(&test.user_key).address(&test.host);
+
+    // Before the protocol change, no signatures are needed.
+    test.host
+        .with_mut_ledger_info(|li| {
+            li.protocol_version = crate::host::ACCOUNT_MIN_ONE_SIGNATURE_MIN_PROTOCOL - 1
+        })
+        .unwrap();
+    token
+        .transfer(
+            &TestSigner::account_with_multisig(&account_id, vec![]),
+            receiver.clone(),
+            100,
+        )
+        .unwrap();
+
+    test.host
+        .with_mut_ledger_info(|li| {
+            li.protocol_version = crate::host::ACCOUNT_MIN_ONE_SIGNATURE_MIN_PROTOCOL
+        })
+        .unwrap();
+    // Failure: no signatures, even though the medium threshold is 0.
+    assert!(token
+        .transfer(
+            &TestSigner::account_with_multisig(&account_id, vec![]),
+            receiver.clone(),
+            100,
+        )
+        .err()
+        .unwrap()
+        .error
+        .is_type(ScErrorType::Auth));
+
+    // Success: any signer of the account is enough.
+    token
+        .transfer(
+            &TestSigner::account_with_multisig(&account_id, vec![&test.user_key_2]),
+            receiver,
+            100,
+        )
+        .unwrap();
+}
+
 #[test]
 fn test_negative_amounts_are_not_allowed() {
     let test = TokenTest::setup();
@@ -2736,6 +3034,7 @@ fn test_classic_transfers_not_possible_for_unauthorized_asset() {
     assert_eq!(test.get_trustline_balance(&trustline_key), 100_000_000);
 }
 
+#[cfg(feature = "vm")]
 #[allow(clippy::type_complexity)]
 fn simple_account_sign_fn<'a>(
     host: &'a Host,
@@ -2745,6 +3044,8 @@ fn simple_account_sign_fn<'a>(
     Box::new(|payload: &[u8]| -> Val { sign_payload_for_ed25519(host, kp, payload).into() })
 }
 
+// The account contract is a Wasm contract.
+#[cfg(feature = "vm")]
 #[test]
 fn test_custom_account_auth() {
     use crate::native_contract::testutils::AccountContractSigner;
@@ -2900,3 +3201,355 @@ fn test_recording_auth_for_token() {
         )]
     );
 }
+
+#[test]
+fn test_native_contract_harness() {
+    let test = TokenTest::setup();
+    let admin = contract_id_to_address(&test.host, generate_bytes_array());
+    let token = test.default_token_with_admin_id(&admin);
+    let user = contract_id_to_address(&test.host, generate_bytes_array());
+
+    let harness = NativeContractHarness::new(&test.host, token.address.clone().into());
+    let user_val: Val = user.clone().try_into_val(&test.host).unwrap();
+    harness
+        .call(
+            "mint",
+            &[user_val, 100_i128.try_into_val(&test.host).unwrap()],
+            |host| Token::mint(host, user.clone(), 100),
+        )
+        .unwrap();
+    let balance = harness
+        .call("balance", &[user_val], |host| {
+            Token::balance(host, user.clone())
+        })
+        .unwrap();
+    assert_eq!(balance, 100);
+
+    harness
+        .assert_event_emitted(
+            &[
+                Symbol::try_from_val(&test.host, &"mint").unwrap().to_val(),
+                admin.try_into_val(&test.host).unwrap(),
+                user_val,
+                token.name().unwrap().try_into_val(&test.host).unwrap(),
+            ],
+            100_i128.try_into_val(&test.host).unwrap(),
+        )
+        .unwrap();
+    assert!(!harness
+        .event_emitted(
+            &[Symbol::try_from_val(&test.host, &"mint").unwrap().to_val()],
+            100_i128.try_into_val(&test.host).unwrap(),
+        )
+        .unwrap());
+
+    // Failures of the harness are returned rather than panicking.
+    let missing = NativeContractHarness::new(
+        &test.host,
+        contract_id_to_address(&test.host, generate_bytes_array()).into(),
+    );
+    assert!(missing
+        .call("balance", &[user_val], |host| Token::balance(
+            host,
+            user.clone()
+        ))
+        .is_err());
+}
+
+#[test]
+fn test_events_include_asset_string() {
+    let mut test = TokenTest::setup();
+    test.asset_code = *b"USDC";
+    let admin = TestSigner::account(&test.issuer_key);
+    let token = test.default_token();
+    let asset_string = format!(
+        "USDC:{}",
+        ed25519::PublicKey(test.issuer_key.verifying_key().to_bytes()).to_string()
+    );
+    let asset_string = test
+        .host
+        .string_new_from_slice(asset_string.as_str())
+        .unwrap()
+        .to_val();
+
+    let user = TestSigner::account(&test.user_key);
+    let user_2 = TestSigner::account(&test.user_key_2);
+    test.create_default_account(&user);
+    test.create_default_account(&user_2);
+    test.create_default_trustline(&user);
+    test.create_default_trustline(&user_2);
+    token.mint(&admin, user.address(&test.host), 1000).unwrap();
+    token
+        .approve(&user, user_2.address(&test.host), 500, 200)
+        .unwrap();
+    token
+        .transfer_from(
+            &user_2,
+            user.address(&test.host),
+            user_2.address(&test.host),
+            100,
+        )
+        .unwrap();
+    token
+        .burn_from(&user_2, user.address(&test.host), 200)
+        .unwrap();
+
+    let harness = NativeContractHarness::new(&test.host, token.address.clone().into());
+    harness
+        .assert_event_emitted(
+            &[
+                Symbol::try_from_val(&test.host, &"transfer")
+                    .unwrap()
+                    .to_val(),
+                user.address(&test.host).try_into_val(&test.host).unwrap(),
+                user_2.address(&test.host).try_into_val(&test.host).unwrap(),
+                asset_string,
+            ],
+            100_i128.try_into_val(&test.host).unwrap(),
+        )
+        .unwrap();
+    harness
+        .assert_event_emitted(
+            &[
+                Symbol::try_from_val(&test.host, &"burn").unwrap().to_val(),
+                user.address(&test.host).try_into_val(&test.host).unwrap(),
+                asset_string,
+            ],
+            200_i128.try_into_val(&test.host).unwrap(),
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_periodic_allowance() {
+    let test = TokenTest::setup();
+    test.host
+        .with_mut_ledger_info(|li| {
+            li.protocol_version = crate::host::SAC_PERIODIC_ALLOWANCE_MIN_PROTOCOL
+        })
+        .unwrap();
+    let admin = TestSigner::account(&test.issuer_key);
+    let token = test.default_token();
+    let user = TestSigner::account(&test.user_key);
+    let user_2 = TestSigner::account(&test.user_key_2);
+    test.create_default_account(&user);
+    test.create_default_account(&user_2);
+    test.create_default_trustline(&user);
+    test.create_default_trustline(&user_2);
+    token.mint(&admin, user.address(&test.host), 1000).unwrap();
+
+    // 100 per window of 10 ledgers, starting at the current ledger 123.
+    token
+        .approve_periodic(&user, user_2.address(&test.host), 100, 10, 200)
+        .unwrap();
+    let transfer = |amount| {
+        token.transfer_from(
+            &user_2,
+            user.address(&test.host),
+            user_2.address(&test.host),
+            amount,
+        )
+    };
+    let remaining = || {
+        token
+            .periodic_allowance(user.address(&test.host), user_2.address(&test.host))
+            .unwrap()
+    };
+    assert_eq!(remaining(), 100);
+    transfer(60).unwrap();
+    assert_eq!(remaining(), 40);
+    assert_eq!(
+        to_contract_err(transfer(41).err().unwrap()),
+        ContractError::AllowanceError
+    );
+    // The regular allowance is unaffected.
+    assert_eq!(
+        token
+            .allowance(user.address(&test.host), user_2.address(&test.host))
+            .unwrap(),
+        0
+    );
+
+    // A new window starts at ledger 133.
+    test.host
+        .with_mut_ledger_info(|li| li.sequence_number = 132)
+        .unwrap();
+    assert_eq!(remaining(), 40);
+    test.host
+        .with_mut_ledger_info(|li| li.sequence_number = 133)
+        .unwrap();
+    assert_eq!(remaining(), 100);
+    transfer(100).unwrap();
+    assert_eq!(remaining(), 0);
+    assert_eq!(token.balance(user_2.address(&test.host)).unwrap(), 160);
+
+    // Skipped windows don't accumulate.
+    test.host
+        .with_mut_ledger_info(|li| li.sequence_number = 175)
+        .unwrap();
+    assert_eq!(remaining(), 100);
+
+    // The allowance can't be used after its expiration.
+    test.host
+        .with_mut_ledger_info(|li| li.sequence_number = 201)
+        .unwrap();
+    assert_eq!(remaining(), 0);
+    assert_eq!(
+        to_contract_err(transfer(1).err().unwrap()),
+        ContractError::AllowanceError
+    );
+
+    // A zero-length period is rejected.
+    assert_eq!(
+        to_contract_err(
+            token
+                .approve_periodic(&user, user_2.address(&test.host), 100, 0, 300)
+                .err()
+                .unwrap()
+        ),
+        ContractError::AllowanceError
+    );
+}
+
+#[test]
+fn test_periodic_allowance_is_protocol_gated() {
+    let test = TokenTest::setup();
+    test.host
+        .with_mut_ledger_info(|li| {
+            li.protocol_version = crate::host::SAC_PERIODIC_ALLOWANCE_MIN_PROTOCOL - 1
+        })
+        .unwrap();
+    let admin = TestSigner::account(&test.issuer_key);
+    let token = test.default_token();
+    let user = TestSigner::account(&test.user_key);
+    let user_2 = TestSigner::account(&test.user_key_2);
+    for u in [&user, &user_2] {
+        test.create_default_account(u);
+        test.create_default_trustline(u);
+    }
+    token.mint(&admin, user.address(&test.host), 1000).unwrap();
+    assert_eq!(
+        token
+            .approve_periodic(&user, user_2.address(&test.host), 100, 10, 200)
+            .err()
+            .unwrap()
+            .error,
+        (ScErrorType::Context, ScErrorCode::InvalidAction).into()
+    );
+    assert_eq!(
+        token
+            .periodic_allowance(user.address(&test.host), user_2.address(&test.host))
+            .err()
+            .unwrap()
+            .error,
+        (ScErrorType::Context, ScErrorCode::InvalidAction).into()
+    );
+    // `transfer_from` only uses the regular allowance.
+    assert_eq!(
+        to_contract_err(
+            token
+                .transfer_from(
+                    &user_2,
+                    user.address(&test.host),
+                    user_2.address(&test.host),
+                    1
+                )
+                .err()
+                .unwrap()
+        ),
+        ContractError::AllowanceError
+    );
+}
+
+#[test]
+fn test_token_client() {
+    let test = TokenTest::setup();
+    let token = test.default_token();
+    let admin = TestSigner::account(&test.issuer_key);
+    let user = TestSigner::account(&test.user_key);
+    test.create_default_account(&user);
+    test.create_default_trustline(&user);
+    let contract = contract_id_to_address(&test.host, [7; 32]);
+    test.host.switch_to_recording_auth(true).unwrap();
+
+    let client = TokenClient::new(&test.host, token.address.clone().into());
+    let user = user.address(&test.host).into();
+    let contract = contract.into();
+    assert_eq!(client.decimals().unwrap(), 7);
+    let admin: AddressObject = admin.address(&test.host).into();
+    assert_eq!(
+        test.host
+            .compare(&Val::from(client.admin().unwrap()), &Val::from(admin))
+            .unwrap(),
+        core::cmp::Ordering::Equal
+    );
+    client.mint(user, 100).unwrap();
+    client.transfer(user, contract, 40).unwrap();
+    assert_eq!(client.balance(user).unwrap(), 60);
+    assert_eq!(client.balance(contract).unwrap(), 40);
+
+    let res = client.transfer(contract, user, 41);
+    assert!(HostError::result_matches_err(
+        res,
+        ContractError::BalanceError
+    ));
+}
+
+#[test]
+fn test_sac_balances() {
+    let test = TokenTest::setup();
+    let admin = TestSigner::account(&test.issuer_key);
+    let token = test.default_token();
+    let native_token = TestToken::new_from_asset(&test.host, Asset::Native);
+    let user = TestSigner::account(&test.user_key);
+    test.create_default_account(&user);
+    test.create_default_trustline(&user);
+    token.mint(&admin, user.address(&test.host), 1000).unwrap();
+    let contract = contract_id_to_address(&test.host, [7; 32]);
+    token.mint(&admin, contract.clone(), 500).unwrap();
+
+    let issuer_id = signing_key_to_account_id(&test.issuer_key);
+    let assets = [
+        Asset::CreditAlphanum4(AlphaNum4 {
+            asset_code: AssetCode4(test.asset_code),
+            issuer: issuer_id.clone(),
+        }),
+        Asset::Native,
+    ];
+    let user_address = user.address(&test.host);
+    assert_eq!(
+        test.host
+            .sac_balances(&user_address.to_sc_address().unwrap(), &assets)
+            .unwrap(),
+        vec![1000, native_token.balance(user_address.clone()).unwrap()]
+    );
+    // Contract balances are read from the contract data, and are zero when
+    // missing.
+    assert_eq!(
+        test.host
+            .sac_balances(&contract.to_sc_address().unwrap(), &assets)
+            .unwrap(),
+        vec![500, 0]
+    );
+    // The issuer has an unlimited balance of its asset.
+    assert_eq!(
+        test.host
+            .sac_balances(&ScAddress::Account(issuer_id), &assets[..1])
+            .unwrap(),
+        vec![i64::MAX as i128]
+    );
+}
+
+#[test]
+fn test_read_only_fns_dont_migrate_storage() {
+    use crate::native_contract::token::is_read_only_fn;
+
+    let host = Host::test_host_with_recording_footprint();
+    let is_read_only =
+        |func: &str| is_read_only_fn(&host, &Symbol::try_from_val(&host, &func).unwrap()).unwrap();
+    // Both small and object symbols are recognized.
+    assert!(is_read_only("balance"));
+    assert!(is_read_only("spendable_balance"));
+    assert!(!is_read_only("transfer"));
+    assert!(!is_read_only("set_paused"));
+}
@@ -3,8 +3,9 @@ use core::cmp::Ordering;
 use soroban_env_common::{xdr::ScVal, Compare, Tag, U32Val};
 
 use crate::{
+    budget::AsBudget,
     xdr::{ScErrorCode, ScErrorType},
-    Env, Host, HostError, Object, Val,
+    ContainerCopyStats, Env, Host, HostError, Object, Val,
 };
 
 #[test]
@@ -297,6 +298,54 @@ fn vec_binary_search() -> Result<(), HostError> {
     Ok(())
 }
 
+#[test]
+fn vec_dedup_reverse_contains_sorted() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::VEC_DEDUP_REVERSE_CONTAINS_MIN_PROTOCOL
+    })?;
+    let obj0 = host.test_vec_obj::<u32>(&[1, 1, 2, 4, 4, 4, 2, 7])?;
+    let dedup = host.vec_dedup(obj0)?;
+    let exp = host.test_vec_obj::<u32>(&[1, 2, 4, 2, 7])?;
+    assert_eq!(host.obj_cmp(dedup.into(), exp.into())?, 0);
+
+    let reversed = host.vec_reverse(exp)?;
+    let exp = host.test_vec_obj::<u32>(&[7, 2, 4, 2, 1])?;
+    assert_eq!(host.obj_cmp(reversed.into(), exp.into())?, 0);
+    let empty = host.vec_new()?;
+    assert_eq!(
+        host.obj_cmp(host.vec_reverse(empty)?.into(), empty.into())?,
+        0
+    );
+    assert_eq!(
+        host.obj_cmp(host.vec_dedup(empty)?.into(), empty.into())?,
+        0
+    );
+
+    let sorted = host.test_vec_obj::<u32>(&[1, 2, 4, 5, 7, 9])?;
+    assert!(bool::from(host.vec_contains_sorted(sorted, 7u32.into())?));
+    assert!(!bool::from(host.vec_contains_sorted(sorted, 6u32.into())?));
+    assert!(!bool::from(host.vec_contains_sorted(empty, 6u32.into())?));
+    Ok(())
+}
+
+#[test]
+fn vec_dedup_reverse_contains_sorted_are_protocol_gated() -> Result<(), HostError> {
+    let host = Host::test_host_with_recording_footprint();
+    host.with_mut_ledger_info(|li| {
+        li.protocol_version = crate::host::VEC_DEDUP_REVERSE_CONTAINS_MIN_PROTOCOL - 1
+    })?;
+    let obj = host.test_vec_obj::<u32>(&[1, 1, 2])?;
+    let code = (ScErrorType::Context, ScErrorCode::InvalidAction);
+    assert!(HostError::result_matches_err(host.vec_dedup(obj), code));
+    assert!(HostError::result_matches_err(host.vec_reverse(obj), code));
+    assert!(HostError::result_matches_err(
+        host.vec_contains_sorted(obj, 2u32.into()),
+        code
+    ));
+    Ok(())
+}
+
 #[test]
 fn vec_build_bad_element_integrity() -> Result<(), HostError> {
     use crate::EnvBase;
@@ -336,3 +385,74 @@ fn vec_build_bad_element_integrity() -> Result<(), HostError> {
 
     Ok(())
 }
+
+#[test]
+fn vec_respects_object_size_limits() -> Result<(), HostError> {
+    use crate::EnvBase;
+    let host = Host::default();
+    host.set_object_size_limits(crate::ObjectSizeLimits {
+        max_vec_len: 2,
+        ..Default::default()
+    })?;
+    let code = (ScErrorType::Object, ScErrorCode::ExceededLimit);
+    let v = host.vec_new_from_slice(&[Val::VOID.to_val(); 2])?;
+    assert!(HostError::result_matches_err(
+        host.vec_new_from_slice(&[Val::VOID.to_val(); 3]),
+        code
+    ));
+    // The limit is checked before the new vector is allocated.
+    let mem = host.as_budget().get_mem_bytes_consumed()?;
+    assert!(HostError::result_matches_err(
+        host.vec_push_back(v, Val::VOID.to_val()),
+        code
+    ));
+    assert_eq!(host.as_budget().get_mem_bytes_consumed()?, mem);
+    // Operations that don't grow the vector are unaffected.
+    let v = host.vec_put(v, 0_u32.into(), Val::TRUE.to_val())?;
+    let v = host.vec_pop_back(v)?;
+    host.vec_push_front(v, Val::VOID.to_val())?;
+    Ok(())
+}
+
+#[test]
+fn vec_and_map_copy_stats() -> Result<(), HostError> {
+    let host = Host::default();
+    // Copies aren't counted until enabled.
+    let v = host.test_vec_obj(&[1u32, 2, 3])?;
+    host.vec_push_back(v, U32Val::from(4).to_val())?;
+    assert_eq!(
+        host.execution_stats()?.vec_copies,
+        ContainerCopyStats::default()
+    );
+
+    host.enable_container_copy_stats()?;
+    let v = host.vec_push_back(v, U32Val::from(4).to_val())?;
+    // Putting the value that's already there makes an unchanged copy.
+    let v = host.vec_put(v, U32Val::from(0), U32Val::from(1).to_val())?;
+    host.vec_slice(v, U32Val::from(1), U32Val::from(3))?;
+    // So does slicing the whole vector.
+    host.vec_slice(v, U32Val::from(0), U32Val::from(4))?;
+
+    let m = host.map_new()?;
+    let m = host.map_put(m, U32Val::from(1).to_val(), U32Val::from(2).to_val())?;
+    host.map_put(m, U32Val::from(1).to_val(), U32Val::from(2).to_val())?;
+
+    let stats = host.execution_stats()?;
+    assert_eq!(
+        stats.vec_copies,
+        ContainerCopyStats {
+            copies: 4,
+            copied_elements: 14,
+            unchanged_copies: 2,
+        }
+    );
+    assert_eq!(
+        stats.map_copies,
+        ContainerCopyStats {
+            copies: 2,
+            copied_elements: 2,
+            unchanged_copies: 1,
+        }
+    );
+    Ok(())
+}
@@ -11,12 +11,14 @@
 mod dispatch;
 mod fuel_refillable;
 mod func_info;
+mod module_cache;
 
 #[cfg(any(test, feature = "testutils"))]
 pub(crate) use dispatch::dummy0;
+pub(crate) use module_cache::ModuleCache;
 
 use crate::{
-    budget::AsBudget,
+    budget::{AsBudget, FuelConfig},
     err,
     host::{error::TryBorrowOrErr, metered_clone::MeteredContainer},
     xdr::ContractCostType,
@@ -36,7 +38,9 @@ use soroban_env_common::{
     ConversionError, SymbolStr, TryIntoVal, WasmiMarshal,
 };
 
-use wasmi::{Engine, FuelConsumptionMode, Instance, Linker, Memory, Module, Store, Value};
+use wasmi::{
+    Engine, ExternType, FuelConsumptionMode, Instance, Linker, Memory, Module, Store, Value,
+};
 
 #[cfg(any(test, feature = "testutils"))]
 use crate::VmCaller;
@@ -58,9 +62,9 @@ impl wasmi::core::HostError for HostError {}
 pub struct Vm {
     #[allow(dead_code)]
     pub(crate) contract_id: Hash,
-    // TODO: consider moving store and possibly module to Host so they can be
-    // recycled across calls. Or possibly beyond, to be recycled across txs.
-    module: Module,
+    // TODO: consider moving store to Host so it can be recycled across calls.
+    // The module is already shared through the `ModuleCache`.
+    parsed: Rc<ParsedModule>,
     store: RefCell<Store<Host>>,
     instance: Instance,
     memory: Option<Memory>,
@@ -74,6 +78,157 @@ pub struct VmFunction {
     pub result_count: usize,
 }
 
+/// A WASM module that has been parsed and validated but not instantiated
+/// yet, see [Vm::new].
+pub(crate) struct ParsedModule {
+    engine: Engine,
+    module: Module,
+    params: ParseParams,
+}
+
+/// The host configuration that a module is parsed and validated with. A
+/// module parsed with different parameters can't be reused from the
+/// [ModuleCache].
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct ParseParams {
+    fuel_config: FuelConfig,
+    lazy_fuel: bool,
+    ledger_protocol: u32,
+}
+
+impl ParseParams {
+    fn new(host: &Host) -> Result<Self, HostError> {
+        #[allow(unused_mut)]
+        let mut lazy_fuel = false;
+        #[cfg(any(test, feature = "differential"))]
+        {
+            lazy_fuel = host.get_vm_config()?.fuel_consumption == crate::FuelConsumption::Lazy;
+        }
+        Ok(Self {
+            fuel_config: host.as_budget().fuel_config()?,
+            lazy_fuel,
+            ledger_protocol: host.get_ledger_protocol_version()?,
+        })
+    }
+}
+
+impl ParsedModule {
+    /// Parses and validates `module_wasm_code`, which is the first half of
+    /// [Vm::new] and the part charged for by
+    /// [ContractCostType::VmInstantiation].
+    pub(crate) fn new(host: &Host, module_wasm_code: &[u8]) -> Result<Self, HostError> {
+        let _span = tracy_span!("ParsedModule::new");
+        let start = host.vm_cost_start()?;
+        let timer = host.start_metrics_timer()?;
+        let res = Self::parse(host, module_wasm_code);
+        let recorded = host
+            .record_vm_parse_cost(start)
+            .and_then(|_| host.report_vm_parse(start, timer));
+        // The error of the parsing takes precedence over that of recording it.
+        let parsed = res?;
+        recorded?;
+        Ok(parsed)
+    }
+
+    /// Returns the module with the code `module_wasm_code` from the
+    /// [ModuleCache] of the host if it's there, and parses it with
+    /// [ParsedModule::new] and adds it to the cache otherwise. `wasm_hash`
+    /// has to be the hash of the code.
+    pub(crate) fn new_or_cached(
+        host: &Host,
+        wasm_hash: &Hash,
+        module_wasm_code: &[u8],
+    ) -> Result<Rc<Self>, HostError> {
+        let params = ParseParams::new(host)?;
+        let cached = host.try_borrow_module_cache()?.get(wasm_hash, &params);
+        if let Some(parsed) = cached {
+            let _span = tracy_span!("ParsedModule::new_or_cached");
+            let start = host.vm_cost_start()?;
+            let timer = host.start_metrics_timer()?;
+            // Cached modules are charged as if they were parsed, see
+            // `ModuleCache`.
+            let res = host.charge_budget(
+                ContractCostType::VmInstantiation,
+                Some(module_wasm_code.len() as u64),
+            );
+            let recorded = host
+                .record_vm_parse_cost(start)
+                .and_then(|_| host.report_vm_parse(start, timer));
+            res?;
+            recorded?;
+            host.record_module_cache_hit()?;
+            return Ok(parsed);
+        }
+        let parsed = Rc::new(Self::new(host, module_wasm_code)?);
+        host.try_borrow_module_cache_mut()?
+            .insert(wasm_hash.clone(), Rc::clone(&parsed));
+        Ok(parsed)
+    }
+
+    fn parse(host: &Host, module_wasm_code: &[u8]) -> Result<Self, HostError> {
+        host.charge_budget(
+            ContractCostType::VmInstantiation,
+            Some(module_wasm_code.len() as u64),
+        )?;
+
+        let params = ParseParams::new(host)?;
+        let mut config = wasmi::Config::default();
+        let fuel_costs = host.as_budget().wasmi_fuel_costs()?;
+
+        // Turn off all optional wasm features.
+        config
+            .wasm_multi_value(false)
+            .wasm_mutable_global(true)
+            .wasm_saturating_float_to_int(false)
+            .wasm_sign_extension(true)
+            .floats(false)
+            .consume_fuel(true)
+            .fuel_consumption_mode(FuelConsumptionMode::Eager)
+            .set_fuel_costs(fuel_costs);
+        if params.lazy_fuel {
+            config.fuel_consumption_mode(FuelConsumptionMode::Lazy);
+        }
+
+        let engine = Engine::new(&config);
+        let module = {
+            let _span0 = tracy_span!("parse module");
+            host.map_err(Module::new(&engine, module_wasm_code))?
+        };
+
+        Vm::check_meta_section(host, &module)?;
+        Ok(Self {
+            engine,
+            module,
+            params,
+        })
+    }
+
+    /// Checks that the module exports a function named `func_sym`, failing
+    /// the same way as invoking a missing function on an instantiated [Vm].
+    pub(crate) fn check_function_export(
+        &self,
+        host: &Host,
+        func_sym: &Symbol,
+    ) -> Result<(), HostError> {
+        let func_ss: SymbolStr = func_sym.try_into_val(host)?;
+        match self.module.get_export(func_ss.as_ref()) {
+            None => Err(host.err(
+                ScErrorType::WasmVm,
+                ScErrorCode::MissingValue,
+                "invoking unknown export",
+                &[func_sym.to_val()],
+            )),
+            Some(ExternType::Func(_)) => Ok(()),
+            Some(_) => Err(host.err(
+                ScErrorType::WasmVm,
+                ScErrorCode::UnexpectedType,
+                "export is not a function",
+                &[func_sym.to_val()],
+            )),
+        }
+    }
+}
+
 impl Vm {
     fn check_contract_interface_version(
         host: &Host,
@@ -192,39 +347,42 @@ impl Vm {
         contract_id: Hash,
         module_wasm_code: &[u8],
     ) -> Result<Rc<Self>, HostError> {
-        let _span = tracy_span!("Vm::new");
-
-        host.charge_budget(
-            ContractCostType::VmInstantiation,
-            Some(module_wasm_code.len() as u64),
-        )?;
-
-        let mut config = wasmi::Config::default();
-        let fuel_costs = host.as_budget().wasmi_fuel_costs()?;
-
-        // Turn off all optional wasm features.
-        config
-            .wasm_multi_value(false)
-            .wasm_mutable_global(true)
-            .wasm_saturating_float_to_int(false)
-            .wasm_sign_extension(true)
-            .floats(false)
-            .consume_fuel(true)
-            .fuel_consumption_mode(FuelConsumptionMode::Eager)
-            .set_fuel_costs(fuel_costs);
-
-        let engine = Engine::new(&config);
-        let module = {
-            let _span0 = tracy_span!("parse module");
-            host.map_err(Module::new(&engine, module_wasm_code))?
-        };
+        let parsed = ParsedModule::new(host, module_wasm_code)?;
+        Self::instantiate(host, contract_id, Rc::new(parsed))
+    }
 
-        Self::check_meta_section(host, &module)?;
+    /// Instantiates a module parsed with [ParsedModule::new], which is the
+    /// second half of [Vm::new]. Parsing and instantiation are separate so
+    /// that invocations that fail validation against the parsed module don't
+    /// go through the instantiation at all.
+    pub(crate) fn instantiate(
+        host: &Host,
+        contract_id: Hash,
+        parsed: Rc<ParsedModule>,
+    ) -> Result<Rc<Self>, HostError> {
+        let _span = tracy_span!("Vm::instantiate");
+        let start = host.vm_cost_start()?;
+        let timer = host.start_metrics_timer()?;
+        let res = Self::instantiate_parsed(host, contract_id, parsed);
+        let recorded = host
+            .record_vm_instantiation_cost(start)
+            .and_then(|_| host.report_vm_instantiation(start, timer));
+        // The error of the instantiation takes precedence over that of
+        // recording it.
+        let vm = res?;
+        recorded?;
+        Ok(vm)
+    }
 
-        let mut store = Store::new(&engine, host.clone());
+    fn instantiate_parsed(
+        host: &Host,
+        contract_id: Hash,
+        parsed: Rc<ParsedModule>,
+    ) -> Result<Rc<Self>, HostError> {
+        let mut store = Store::new(&parsed.engine, host.clone());
         store.limiter(|host| host);
 
-        let mut linker = <Linker<Host>>::new(&engine);
+        let mut linker = <Linker<Host>>::new(&parsed.engine);
 
         {
             let _span0 = tracy_span!("define host functions");
@@ -240,7 +398,7 @@ impl Vm {
 
         let not_started_instance = {
             let _span0 = tracy_span!("instantiate module");
-            host.map_err(linker.instantiate(&mut store, &module))?
+            host.map_err(linker.instantiate(&mut store, &parsed.module))?
         };
 
         let instance = host.map_err(
@@ -261,13 +419,21 @@ impl Vm {
         // Missing metering for the Rc, but this is once per Vm so should be okay
         Ok(Rc::new(Self {
             contract_id,
-            module,
+            parsed,
             store: RefCell::new(store),
             instance,
             memory,
         }))
     }
 
+    /// Returns whether the module exports a function named `name`.
+    pub(crate) fn has_function_export(&self, name: &str) -> bool {
+        matches!(
+            self.parsed.module.get_export(name),
+            Some(ExternType::Func(_))
+        )
+    }
+
     pub(crate) fn get_memory(&self, host: &Host) -> Result<Memory, HostError> {
         match self.memory {
             Some(mem) => Ok(mem),
@@ -414,7 +580,26 @@ impl Vm {
     /// Returns the raw bytes content of a named custom section from the WASM
     /// module loaded into the [Vm], or `None` if no such custom section exists.
     pub fn custom_section(&self, name: impl AsRef<str>) -> Option<&[u8]> {
-        Self::module_custom_section(&self.module, name)
+        Self::module_custom_section(&self.parsed.module, name)
+    }
+
+    // Metered version of `custom_section`, charging for comparing the name
+    // against the name of every section visited.
+    pub(crate) fn metered_custom_section(
+        &self,
+        host: &Host,
+        name: &[u8],
+    ) -> Result<Option<&[u8]>, HostError> {
+        for s in self.parsed.module.custom_sections().iter() {
+            host.charge_budget(
+                ContractCostType::HostMemCmp,
+                Some(name.len().min(s.name.len()) as u64),
+            )?;
+            if s.name.as_bytes() == name {
+                return Ok(Some(&*s.data));
+            }
+        }
+        Ok(None)
     }
 
     /// Utility function that synthesizes a `VmCaller<Host>` configured to point
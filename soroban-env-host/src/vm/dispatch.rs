@@ -1,5 +1,8 @@
 use super::FuelRefillable;
-use crate::{xdr::ContractCostType, EnvBase, Host, HostError, VmCaller, VmCallerEnv};
+use crate::{
+    xdr::{ContractCostType, ScVal},
+    EnvBase, Host, HostError, ScValLimits, VmCaller, VmCallerEnv,
+};
 use crate::{
     AddressObject, Bool, BytesObject, DurationObject, Error, I128Object, I256Object, I256Val,
     I32Val, I64Object, MapObject, StorageType, StringObject, Symbol, SymbolObject, TimepointObject,
@@ -81,6 +84,73 @@ impl RelativeObjectConversion for StorageType {}
 impl RelativeObjectConversion for U32Val {}
 impl RelativeObjectConversion for I32Val {}
 
+// Renders host function arguments and return values for host function traces
+// (see `Host::enable_host_fn_tracing`).
+pub(crate) trait HostFnTraceArg {
+    fn trace_scval(&self, host: &Host, limits: &ScValLimits) -> Result<ScVal, HostError>;
+}
+
+macro_rules! impl_host_fn_trace_arg_via_val {
+    ($T:ty) => {
+        impl HostFnTraceArg for $T {
+            fn trace_scval(&self, host: &Host, limits: &ScValLimits) -> Result<ScVal, HostError> {
+                host.host_fn_trace_scval((*self).into(), limits)
+            }
+        }
+    };
+}
+
+impl_host_fn_trace_arg_via_val!(Val);
+impl_host_fn_trace_arg_via_val!(Symbol);
+
+impl_host_fn_trace_arg_via_val!(AddressObject);
+impl_host_fn_trace_arg_via_val!(BytesObject);
+impl_host_fn_trace_arg_via_val!(DurationObject);
+
+impl_host_fn_trace_arg_via_val!(TimepointObject);
+impl_host_fn_trace_arg_via_val!(SymbolObject);
+impl_host_fn_trace_arg_via_val!(StringObject);
+
+impl_host_fn_trace_arg_via_val!(VecObject);
+impl_host_fn_trace_arg_via_val!(MapObject);
+
+impl_host_fn_trace_arg_via_val!(I64Object);
+impl_host_fn_trace_arg_via_val!(I128Object);
+impl_host_fn_trace_arg_via_val!(I256Object);
+
+impl_host_fn_trace_arg_via_val!(U64Object);
+impl_host_fn_trace_arg_via_val!(U128Object);
+impl_host_fn_trace_arg_via_val!(U256Object);
+
+impl_host_fn_trace_arg_via_val!(U64Val);
+impl_host_fn_trace_arg_via_val!(U256Val);
+impl_host_fn_trace_arg_via_val!(I256Val);
+
+impl_host_fn_trace_arg_via_val!(Void);
+impl_host_fn_trace_arg_via_val!(Bool);
+impl_host_fn_trace_arg_via_val!(Error);
+impl_host_fn_trace_arg_via_val!(U32Val);
+impl_host_fn_trace_arg_via_val!(I32Val);
+
+// Plain integers and storage types aren't `Val`s, so they're rendered directly.
+impl HostFnTraceArg for i64 {
+    fn trace_scval(&self, _host: &Host, _limits: &ScValLimits) -> Result<ScVal, HostError> {
+        Ok(ScVal::I64(*self))
+    }
+}
+
+impl HostFnTraceArg for u64 {
+    fn trace_scval(&self, _host: &Host, _limits: &ScValLimits) -> Result<ScVal, HostError> {
+        Ok(ScVal::U64(*self))
+    }
+}
+
+impl HostFnTraceArg for StorageType {
+    fn trace_scval(&self, _host: &Host, _limits: &ScValLimits) -> Result<ScVal, HostError> {
+        Ok(ScVal::U64(self.clone() as u64))
+    }
+}
+
 ///////////////////////////////////////////////////////////////////////////////
 /// X-macro use: dispatch functions
 ///////////////////////////////////////////////////////////////////////////////
@@ -151,6 +221,8 @@ macro_rules! generate_dispatch_functions {
                     // Charge for the host function dispatching: conversion between VM fuel and
                     // host budget, marshalling values. This does not account for the actual work
                     // being done in those functions, which are metered individually by the implementation.
+                    let cost_start = host.host_fn_cost_start()?;
+                    let metrics_timer = host.start_metrics_timer()?;
                     host.charge_budget(ContractCostType::DispatchHostFunction, None)?;
                     let mut vmcaller = VmCaller(Some(caller));
                     // The odd / seemingly-redundant use of `wasmi::Value` here
@@ -162,13 +234,44 @@ macro_rules! generate_dispatch_functions {
                     // happens to be a natural switching point for that: we have
                     // conversions to and from both Val and i64 / u64 for
                     // wasmi::Value.
-                    let res: Result<_, HostError> = host.$fn_id(&mut vmcaller, $(<$type>::try_marshal_from_relative_value(Value::I64($arg), &host)?),*);
+                    $(let $arg = <$type>::try_marshal_from_relative_value(Value::I64($arg), &host)?;)*
+
+                    // If host function tracing is on, the arguments are
+                    // rendered before the call, while they're guaranteed to
+                    // be the ones the function has received.
+                    let trace_limits = host.host_fn_trace_limits()?;
+                    let trace_args: Option<Vec<ScVal>> = match &trace_limits {
+                        Some(limits) => Some(vec![$($arg.trace_scval(&host, limits)?),*]),
+                        None => None,
+                    };
+
+                    // The allocation sites of objects recorded by the leak
+                    // detector refer to the host function allocating them.
+                    #[cfg(any(test, feature = "testutils"))]
+                    let prev_host_fn = host.set_object_leak_host_fn(Some(stringify!($fn_id)))?;
+                    let res: Result<_, HostError> = host.$fn_id(&mut vmcaller, $($arg),*);
+                    #[cfg(any(test, feature = "testutils"))]
+                    host.set_object_leak_host_fn(prev_host_fn)?;
 
                     // On the off chance we got an error with no context, we can
                     // at least attach some here "at each host function call",
                     // fairly systematically. This will cause the context to
                     // propagate back through wasmi to its caller.
                     let res = host.augment_err_result(res);
+                    // The cost of the call is recorded whether it has failed
+                    // or not, without masking its error.
+                    let recorded = host.record_host_fn_cost(cost_start).and_then(|_| {
+                        host.report_host_fn_call(stringify!($fn_id), cost_start, metrics_timer)
+                    });
+                    let res = res.and_then(|ok| recorded.map(|_| ok));
+
+                    if let (Some(limits), Some(args)) = (trace_limits, trace_args) {
+                        let traced_res = match &res {
+                            Ok(ok) => Ok(ok.trace_scval(&host, &limits)?),
+                            Err(e) => Err(e.error),
+                        };
+                        host.host_fn_trace_diagnostics(stringify!($fn_id), args, traced_res)?;
+                    }
 
                     let res = match res {
                         Ok(ok) => {
@@ -181,10 +284,11 @@ macro_rules! generate_dispatch_functions {
                         },
                         Err(hosterr) => {
                             // We make a new HostError here to capture the escalation event itself.
+                            // The causes of the original error are kept.
                             let escalation: HostError =
-                                host.error(hosterr.error,
+                                host.escalate_error(hosterr,
                                            concat!("escalating error to VM trap from failed host function call: ",
-                                                   stringify!($fn_id)), &[]);
+                                                   stringify!($fn_id)));
                             let trap: Trap = escalation.into();
                             Err(trap)
                         }
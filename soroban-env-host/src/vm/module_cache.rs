@@ -0,0 +1,71 @@
+//! The cache of the parsed Wasm modules of a [Host], see [ModuleCache].
+
+use std::{collections::BTreeMap, rc::Rc};
+
+use super::{ParseParams, ParsedModule};
+use crate::{
+    budget::AsBudget,
+    host::crypto::sha256_hash_from_bytes,
+    xdr::{Hash, ScErrorCode, ScErrorType},
+    Host, HostError,
+};
+
+/// Parsed and validated Wasm modules keyed by the hash of their code, which
+/// the invocations of the contracts with that code reuse instead of parsing
+/// the code again. Modules are added as they're parsed, or ahead of time by
+/// the embedder with [Host::cache_module].
+///
+/// A cached module is charged to the budget as if it was parsed, so that the
+/// budget consumption doesn't depend on the contents of the cache, which may
+/// differ between hosts; only the time spent parsing is saved. A module is
+/// only reused while the fuel costs and the ledger protocol it was parsed
+/// with are unchanged.
+#[derive(Clone, Default)]
+pub(crate) struct ModuleCache {
+    modules: BTreeMap<Hash, Rc<ParsedModule>>,
+}
+
+impl ModuleCache {
+    pub(crate) fn get(&self, wasm_hash: &Hash, params: &ParseParams) -> Option<Rc<ParsedModule>> {
+        self.modules
+            .get(wasm_hash)
+            .filter(|parsed| parsed.params == *params)
+            .cloned()
+    }
+
+    pub(crate) fn insert(&mut self, wasm_hash: Hash, parsed: Rc<ParsedModule>) {
+        self.modules.insert(wasm_hash, parsed);
+    }
+}
+
+impl Host {
+    /// Parses and validates the Wasm module `wasm` and adds it to the module
+    /// cache of the host, so that the invocations of the contracts with this
+    /// code don't need to parse it. Returns the hash of the code. This isn't
+    /// charged to the budget, and requires the ledger info to be set, as
+    /// modules are validated against the ledger protocol.
+    pub fn cache_module(&self, wasm: &[u8]) -> Result<Hash, HostError> {
+        self.as_budget().with_free_budget(|| {
+            let hash: [u8; 32] = sha256_hash_from_bytes(wasm, self)?
+                .try_into()
+                .map_err(|_| {
+                    self.err(
+                        ScErrorType::Value,
+                        ScErrorCode::InternalError,
+                        "unexpected hash length",
+                        &[],
+                    )
+                })?;
+            let parsed = ParsedModule::new(self, wasm)?;
+            self.try_borrow_module_cache_mut()?
+                .insert(Hash(hash), Rc::new(parsed));
+            Ok(Hash(hash))
+        })
+    }
+
+    /// Removes all the modules from the module cache of the host.
+    pub fn clear_module_cache(&self) -> Result<(), HostError> {
+        *self.try_borrow_module_cache_mut()? = ModuleCache::default();
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{spanned::Spanned, DataEnum, Error, Ident};
+
+pub fn derive_error_enum(ident: &Ident, data: &DataEnum) -> TokenStream2 {
+    let errors: Vec<_> = data
+        .variants
+        .iter()
+        .filter(|v| !v.fields.is_empty())
+        .map(|v| {
+            Error::new(v.span(), "contract error variants must not have fields").to_compile_error()
+        })
+        .collect();
+    if !errors.is_empty() {
+        return quote! { #(#errors)* };
+    }
+    let cases: Vec<_> = data.variants.iter().map(|v| &v.ident).collect();
+
+    quote! {
+        impl From<#ident> for soroban_env_common::Error {
+            fn from(err: #ident) -> Self {
+                soroban_env_common::Error::from_contract_error(err as u32)
+            }
+        }
+
+        impl TryFrom<soroban_env_common::Error> for #ident {
+            type Error = soroban_env_common::Error;
+
+            fn try_from(err: soroban_env_common::Error) -> Result<Self, Self::Error> {
+                if err.is_type(soroban_env_common::xdr::ScErrorType::Contract) {
+                    let code = err.get_code();
+                    #(if code == #ident::#cases as u32 {
+                        return Ok(#ident::#cases);
+                    })*
+                }
+                Err(err)
+            }
+        }
+    }
+}
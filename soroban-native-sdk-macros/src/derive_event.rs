@@ -0,0 +1,62 @@
+use proc_macro2::{Literal, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{DataStruct, Error, Fields, Ident};
+
+// Converts the struct name to the snake case event name, e.g.
+// `ApprovePeriodic` to `approve_periodic`.
+fn event_name(ident: &Ident) -> String {
+    let mut name = String::new();
+    for (i, c) in ident.to_string().chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                name.push('_');
+            }
+            name.extend(c.to_lowercase());
+        } else {
+            name.push(c);
+        }
+    }
+    name
+}
+
+pub fn derive_event_struct(ident: &Ident, data: &DataStruct) -> TokenStream2 {
+    let Fields::Named(fields) = &data.fields else {
+        return Error::new(ident.span(), "contract events must have named fields")
+            .to_compile_error();
+    };
+    let (topics, data): (Vec<_>, Vec<_>) = fields
+        .named
+        .iter()
+        .partition(|f| f.attrs.iter().any(|a| a.path().is_ident("topic")));
+    let topics: Vec<_> = topics.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let data: Vec<_> = data.iter().map(|f| f.ident.as_ref().unwrap()).collect();
+    let name = Literal::string(&event_name(ident));
+
+    // A single data field is emitted as is, while multiple ones are emitted
+    // as a vector in the order of declaration.
+    let data = match data.as_slice() {
+        [] => quote! { soroban_env_common::Val::VOID.to_val() },
+        [field] => quote! { soroban_env_common::Val::try_from_val(env, &self.#field)? },
+        fields => quote! {{
+            let mut data = crate::native_contract::base_types::Vec::new(env)?;
+            #(data.push(&self.#fields)?;)*
+            data.into()
+        }},
+    };
+
+    quote! {
+        impl #ident {
+            /// Emits the event from the currently running contract. The topics
+            /// are the event name followed by the `#[topic]` fields.
+            pub(crate) fn publish(&self, env: &crate::Host) -> Result<(), crate::HostError> {
+                use soroban_env_common::{Env, TryFromVal};
+                let mut topics = crate::native_contract::base_types::Vec::new(env)?;
+                topics.push(&crate::Symbol::try_from_val(env, &#name)?)?;
+                #(topics.push(&self.#topics)?;)*
+                let data: soroban_env_common::Val = #data;
+                env.contract_event(topics.into(), data)?;
+                Ok(())
+            }
+        }
+    }
+}
@@ -1,8 +1,12 @@
+mod derive_error;
+mod derive_event;
 mod derive_fn;
 mod derive_type;
 
 extern crate proc_macro;
 
+use crate::derive_error::derive_error_enum;
+use crate::derive_event::derive_event_struct;
 use crate::derive_fn::derive_contract_function_set;
 use crate::derive_type::{derive_type_enum, derive_type_struct};
 use proc_macro::TokenStream;
@@ -39,6 +43,50 @@ pub fn derive_contract_type(input: TokenStream) -> TokenStream {
     quote! { #derived }.into()
 }
 
+#[proc_macro_attribute]
+pub fn contracterror(_metadata: TokenStream, input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    quote! {
+        #[derive(soroban_native_sdk_macros::ContractError)]
+        #input
+    }
+    .into()
+}
+
+#[doc(hidden)]
+#[proc_macro_derive(ContractError)]
+pub fn derive_contract_error(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let ident = &input.ident;
+    let derived = match &input.data {
+        syn::Data::Enum(e) => derive_error_enum(ident, e),
+        _ => Error::new(ident.span(), "contract errors must be enums").to_compile_error(),
+    };
+    quote! { #derived }.into()
+}
+
+#[proc_macro_attribute]
+pub fn contractevent(_metadata: TokenStream, input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    quote! {
+        #[derive(soroban_native_sdk_macros::ContractEvent)]
+        #input
+    }
+    .into()
+}
+
+#[doc(hidden)]
+#[proc_macro_derive(ContractEvent, attributes(topic))]
+pub fn derive_contract_event(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let ident = &input.ident;
+    let derived = match &input.data {
+        syn::Data::Struct(s) => derive_event_struct(ident, s),
+        _ => Error::new(ident.span(), "contract events must be structs").to_compile_error(),
+    };
+    quote! { #derived }.into()
+}
+
 fn get_methods(imp: &ItemImpl) -> impl Iterator<Item = &ImplItemFn> {
     imp.items.iter().filter_map(|i| match i {
         ImplItem::Fn(m) => Some(m),
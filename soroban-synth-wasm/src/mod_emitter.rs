@@ -177,6 +177,13 @@ impl ModEmitter {
             .export(name, wasm_encoder::ExportKind::Func, fid.0);
     }
 
+    /// Export the module's linear memory under a given name, adding it to the
+    /// `export` section of the module.
+    pub fn export_memory(&mut self, name: &str) {
+        self.exports
+            .export(name, wasm_encoder::ExportKind::Memory, 0);
+    }
+
     pub fn define_elems(&mut self, funcs: &[FuncRef]) {
         let table_index = 0;
         let offset = ConstExpr::i32_const(0);